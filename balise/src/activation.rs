@@ -0,0 +1,97 @@
+//! Listening sockets inherited from the service manager (systemd socket activation).
+//!
+//! The service manager opens the listening sockets and passes them to the process
+//! (starting at file descriptor `3`, announced by `LISTEN_PID` and `LISTEN_FDS`),
+//! so connections are queued by the kernel while the server is (re)started.
+
+use lazy_static::lazy_static;
+use std::{
+    io,
+    net::{SocketAddr, TcpListener as StdTcpListener},
+    sync::Mutex,
+};
+use tokio::net::TcpListener;
+
+lazy_static! {
+    /// The inherited sockets that were not used yet.
+    static ref INHERITED: Mutex<Vec<StdTcpListener>> = Mutex::new(inherited_listeners());
+}
+
+/// Bind a listener to `address`.
+///
+/// An inherited socket is used instead if it listens on the port of `address`
+/// (on the same or the unspecified IP address). Each inherited socket is used at most once.
+pub async fn bind(address: SocketAddr) -> io::Result<TcpListener> {
+    if let Some(listener) = take_inherited(address)? {
+        log::info!(
+            "Using the inherited socket listening on {}.",
+            listener.local_addr()?
+        );
+        return TcpListener::from_std(listener);
+    }
+    TcpListener::bind(address).await
+}
+
+fn take_inherited(address: SocketAddr) -> io::Result<Option<StdTcpListener>> {
+    let mut inherited = INHERITED.lock().unwrap();
+    let position = inherited.iter().position(|listener| {
+        listener.local_addr().map_or(false, |local| {
+            local.port() == address.port()
+                && (local.ip() == address.ip() || local.ip().is_unspecified())
+        })
+    });
+    match position {
+        Some(position) => {
+            let listener = inherited.swap_remove(position);
+            listener.set_nonblocking(true)?;
+            Ok(Some(listener))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(unix)]
+fn inherited_listeners() -> Vec<StdTcpListener> {
+    use std::{env, os::unix::io::FromRawFd, process};
+
+    /// The first file descriptor passed by the service manager.
+    const LISTEN_FDS_START: i32 = 3;
+
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    // The sockets are not meant for child processes.
+    for var in &["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        env::remove_var(var);
+    }
+
+    let (pid, fds) = match (pid, fds) {
+        (Some(pid), Some(fds)) => (pid, fds),
+        _ => return Vec::new(),
+    };
+    if pid.parse::<u32>().ok() != Some(process::id()) {
+        return Vec::new();
+    }
+    let count: i32 = match fds.parse() {
+        Ok(count) => count,
+        Err(err) => {
+            log::warn!(
+                "Ignoring the inherited sockets (LISTEN_FDS={:?}): {}",
+                fds,
+                err
+            );
+            return Vec::new();
+        }
+    };
+
+    (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(|fd| {
+            // The file descriptors were passed to this process and are not used anywhere else.
+            unsafe { StdTcpListener::from_raw_fd(fd) }
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn inherited_listeners() -> Vec<StdTcpListener> {
+    Vec::new()
+}
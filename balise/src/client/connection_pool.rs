@@ -6,6 +6,7 @@ mod stream_impl;
 #[path = "stream_impl_tcp.rs"]
 mod stream_impl;
 
+use super::{metrics, ConnectTimeouts, POOL_IDLE_TIMEOUT};
 use crate::Error;
 use lazy_static::lazy_static;
 use std::{
@@ -13,6 +14,7 @@ use std::{
     net::SocketAddr,
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::Instant,
 };
 use stream_impl::StreamImpl;
 use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
@@ -22,7 +24,8 @@ pub struct ConnectionPool {
 }
 
 struct State {
-    streams: Vec<StreamImpl>,
+    /// The idle streams and when they were put into the pool.
+    streams: Vec<(StreamImpl, Instant)>,
     current_streams: Arc<Semaphore>,
 }
 
@@ -35,10 +38,24 @@ impl ConnectionPool {
         }
     }
 
-    pub async fn stream(&self, addr: SocketAddr) -> Result<StreamGuard<'_>, Error> {
+    pub async fn stream(
+        &self,
+        addr: SocketAddr,
+        timeouts: ConnectTimeouts,
+    ) -> Result<StreamGuard<'_>, Error> {
+        let start = Instant::now();
         let mut states = self.states.lock().await;
         let (current_streams, stream) = if let Some(state) = states.get_mut(&addr) {
-            (state.current_streams.clone(), state.streams.pop())
+            // Streams idle for too long are closed (or about to be closed) by the server.
+            let len = state.streams.len();
+            state
+                .streams
+                .retain(|(_, idle_since)| idle_since.elapsed() < POOL_IDLE_TIMEOUT);
+            for _ in state.streams.len()..len {
+                metrics::record_close(addr);
+            }
+            let stream = state.streams.pop().map(|(stream, _)| stream);
+            (state.current_streams.clone(), stream)
         } else {
             let current_streams = Arc::new(Semaphore::new(Self::MAX_STREAMS));
             states.insert(
@@ -55,8 +72,13 @@ impl ConnectionPool {
 
         let stream = match stream {
             Some(stream) => stream,
-            None => stream_impl::connect(&addr).await?,
+            None => {
+                let result = stream_impl::connect(&addr, timeouts).await;
+                metrics::record_connect(addr, &result);
+                result?
+            }
         };
+        metrics::record_checkout(addr, start.elapsed());
         Ok(StreamGuard {
             stream: Some(stream),
             addr,
@@ -69,7 +91,7 @@ impl ConnectionPool {
     async fn add_stream(&self, addr: SocketAddr, stream: StreamImpl) {
         let mut states = self.states.lock().await;
         let state = states.get_mut(&addr).unwrap();
-        state.streams.push(stream);
+        state.streams.push((stream, Instant::now()));
     }
 }
 
@@ -92,6 +114,15 @@ impl<'a> StreamGuard<'a> {
     }
 }
 
+/// A stream that is not put back into the pool is closed.
+impl<'a> Drop for StreamGuard<'a> {
+    fn drop(&mut self) {
+        if self.stream.is_some() {
+            metrics::record_close(self.addr);
+        }
+    }
+}
+
 /// This is needed for accessing `StreamImpl`'s methods on `StreamGuard`.
 impl<'a> Deref for StreamGuard<'a> {
     type Target = StreamImpl;
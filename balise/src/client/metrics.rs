@@ -0,0 +1,106 @@
+//! Metrics of the connection pool and of the requests sent by all clients of the process.
+
+use crate::Error;
+use lazy_static::lazy_static;
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex, time::Duration};
+
+/// The metrics of the connections and requests to a single server.
+///
+/// All counters are totals since the process was started.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionMetrics {
+    /// The number of connections currently open (in use or idle in the pool).
+    pub open_connections: u64,
+    /// The number of times a connection was taken from the pool (or newly established).
+    pub checkouts: u64,
+    /// The total time spent waiting for a connection.
+    pub checkout_wait: Duration,
+    /// The longest time spent waiting for a connection.
+    pub max_checkout_wait: Duration,
+    /// The number of newly established connections.
+    pub connects: u64,
+    /// The number of connections established to replace a closed connection.
+    pub reconnects: u64,
+    /// The number of connections that could not be established.
+    pub connect_failures: u64,
+    /// The number of successful requests.
+    pub successes: u64,
+    /// The number of requests that failed because of a timeout.
+    pub timeouts: u64,
+    /// The number of requests whose response could not be decoded.
+    pub decode_errors: u64,
+    /// The number of requests that failed because of the connection.
+    pub io_errors: u64,
+    /// The number of requests that failed for other reasons (e.g. rejected by the server).
+    pub other_errors: u64,
+}
+
+lazy_static! {
+    static ref METRICS: Mutex<HashMap<SocketAddr, ConnectionMetrics>> = Mutex::default();
+}
+
+/// Get the metrics of all servers requests were sent to.
+#[must_use]
+pub fn connection_metrics() -> HashMap<SocketAddr, ConnectionMetrics> {
+    METRICS.lock().unwrap().clone()
+}
+
+fn update(addr: SocketAddr, f: impl FnOnce(&mut ConnectionMetrics)) {
+    f(METRICS.lock().unwrap().entry(addr).or_default());
+}
+
+/// Record that a connection was taken from the pool after waiting for `wait`.
+pub(super) fn record_checkout(addr: SocketAddr, wait: Duration) {
+    update(addr, |metrics| {
+        metrics.checkouts += 1;
+        metrics.checkout_wait += wait;
+        metrics.max_checkout_wait = metrics.max_checkout_wait.max(wait);
+    });
+}
+
+/// Record the result of establishing a new connection.
+pub(super) fn record_connect<T>(addr: SocketAddr, result: &Result<T, Error>) {
+    update(addr, |metrics| {
+        if result.is_err() {
+            metrics.connect_failures += 1;
+            return;
+        }
+        if metrics.connects > metrics.reconnects + metrics.open_connections {
+            metrics.reconnects += 1;
+        }
+        metrics.connects += 1;
+        metrics.open_connections += 1;
+    });
+}
+
+/// Record that a connection was closed.
+pub(super) fn record_close(addr: SocketAddr) {
+    update(addr, |metrics| {
+        metrics.open_connections = metrics.open_connections.saturating_sub(1);
+    });
+}
+
+/// Record the outcome of a request.
+pub(super) fn record_outcome<T>(addr: SocketAddr, result: &Result<T, Error>) {
+    match result {
+        Ok(_) => record_success(addr),
+        Err(err) => record_error(addr, err),
+    }
+}
+
+/// Record a successful request.
+pub(super) fn record_success(addr: SocketAddr) {
+    update(addr, |metrics| metrics.successes += 1);
+}
+
+/// Record a failed request.
+pub(super) fn record_error(addr: SocketAddr, err: &Error) {
+    update(addr, |metrics| match err {
+        Error::Timeout | Error::ConnectTimeout(_) | Error::HandshakeTimeout(_) => {
+            metrics.timeouts += 1;
+        }
+        Error::Encoding(_) => metrics.decode_errors += 1,
+        Error::IO(_) => metrics.io_errors += 1,
+        _ => metrics.other_errors += 1,
+    });
+}
@@ -1,16 +1,57 @@
 //! A client for communicating between RPUs.
 
 mod connection_pool;
+mod metrics;
 
-use crate::{Error, Request};
-use serde::Serialize;
+pub use metrics::{connection_metrics, ConnectionMetrics};
+
+use crate::{
+    envelope::Envelope,
+    frame::{self, ResponseFrame},
+    Error, RemoteError, Request, Streaming,
+};
+use pinxit::Signer;
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
-    convert::TryInto,
+    io,
     marker::{PhantomData, Unpin},
     net::SocketAddr,
+    sync::Arc,
     time::{Duration, Instant},
 };
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// The default maximum time to establish a TCP connection.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The default maximum time for a TLS handshake.
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The time after which an unused connection is no longer taken from the pool.
+///
+/// It is shorter than the default `idle_timeout` of the server's `Limits`,
+/// so a pooled connection is not closed by the server while it is handed out.
+pub const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The timeouts for establishing new connections.
+///
+/// Without them, dialing an unreachable server is only limited by the timeouts of the OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectTimeouts {
+    /// The maximum time to establish the TCP connection.
+    pub connect: Duration,
+    /// The maximum time for the TLS handshake (only used with TLS).
+    pub handshake: Duration,
+}
+
+impl Default for ConnectTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: DEFAULT_CONNECT_TIMEOUT,
+            handshake: DEFAULT_HANDSHAKE_TIMEOUT,
+        }
+    }
+}
 
 /// A client instance.
 ///
@@ -18,6 +59,8 @@ use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 /// for improved efficiency.
 pub struct Client<T> {
     addr: SocketAddr,
+    signer: Option<Arc<dyn Signer>>,
+    connect_timeouts: ConnectTimeouts,
     request_data: PhantomData<T>,
 }
 
@@ -36,24 +79,96 @@ impl<T> Client<T> {
     pub const fn new(addr: SocketAddr) -> Self {
         Self {
             addr,
+            signer: None,
+            connect_timeouts: ConnectTimeouts {
+                connect: DEFAULT_CONNECT_TIMEOUT,
+                handshake: DEFAULT_HANDSHAKE_TIMEOUT,
+            },
             request_data: PhantomData,
         }
     }
 
+    /// Seal all requests into an [`Envelope`](../envelope/struct.Envelope.html) signed by the `signer`.
+    ///
+    /// This is required by servers with an [`Authenticator`](../envelope/struct.Authenticator.html).
+    #[must_use]
+    pub fn with_signer(mut self, signer: Arc<dyn Signer>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Use the `connect_timeouts` when a new connection is established.
+    ///
+    /// Exceeding them fails with `Error::ConnectTimeout` or `Error::HandshakeTimeout`.
+    #[must_use]
+    pub const fn with_connect_timeouts(mut self, connect_timeouts: ConnectTimeouts) -> Self {
+        self.connect_timeouts = connect_timeouts;
+        self
+    }
+
     /// Send a request to the server specified.
+    ///
+    /// The outcome is recorded in the [`ConnectionMetrics`](struct.ConnectionMetrics.html).
     pub async fn send_request<Req>(&mut self, req: Req) -> Result<Req::Response, Error>
     where
         Req: Request<T>,
+        Req::Response: DeserializeOwned,
+        T: Serialize,
+    {
+        let result = self.try_send_request(req).await;
+        metrics::record_outcome(self.addr, &result);
+        result
+    }
+
+    async fn try_send_request<Req>(&mut self, req: Req) -> Result<Req::Response, Error>
+    where
+        Req: Request<T>,
+        Req::Response: DeserializeOwned,
         T: Serialize,
     {
         let (mut stream, addr) = self.stream().await?;
 
         log::trace!("Sending request to {}: {:?}", addr, req);
-        let res = send_request(&mut *stream, req).await?;
+        let res = send_request(&mut *stream, req, self.signer.as_deref()).await?;
 
         log::trace!("Received response from {}: {:?}", addr, res);
         stream.done().await;
-        Ok(res?)
+        res.map_err(Error::from_server)
+    }
+
+    /// Send a request with a [`Streaming`](../struct.Streaming.html) response to the server specified.
+    ///
+    /// The items of the response are read one by one from the returned `ResponseStream`.
+    /// The outcome is recorded in the [`ConnectionMetrics`](struct.ConnectionMetrics.html)
+    /// when the response ended.
+    pub async fn send_stream_request<Req, I>(
+        &mut self,
+        req: Req,
+    ) -> Result<ResponseStream<'_, I>, Error>
+    where
+        Req: Request<T, Response = Streaming<I>>,
+        I: DeserializeOwned,
+        T: Serialize,
+    {
+        let (mut stream, addr) = self.stream().await.map_err(|err| self.failed(err))?;
+
+        log::trace!("Sending stream request to {}: {:?}", addr, req);
+        let req: T = req.into();
+        write_request(&mut *stream, req, self.signer.as_deref())
+            .await
+            .map_err(|err| self.failed(err))?;
+
+        Ok(ResponseStream {
+            stream: Some(stream),
+            addr: self.addr,
+            item: PhantomData,
+        })
+    }
+
+    /// Record a failed request in the metrics.
+    fn failed(&self, err: Error) -> Error {
+        metrics::record_error(self.addr, &err);
+        err
     }
 
     /// Get a working TCP stream.
@@ -61,6 +176,7 @@ impl<T> Client<T> {
     /// A stream could be closed by the receiver while being
     /// in the pool. This is catched and a new stream will be
     /// returned in this case.
+    /// Exceeding the `ConnectTimeouts` is not retried.
     async fn stream(&self) -> Result<(connection_pool::StreamGuard<'_>, SocketAddr), Error> {
         let deadline = Instant::now() + Duration::from_secs(3);
         let delay = Duration::from_secs(1);
@@ -70,8 +186,14 @@ impl<T> Client<T> {
                 return Err(Error::Timeout);
             }
 
-            let stream = match connection_pool::POOL.stream(self.addr).await {
+            let stream = match connection_pool::POOL
+                .stream(self.addr, self.connect_timeouts)
+                .await
+            {
                 Ok(stream) => stream,
+                Err(err @ Error::ConnectTimeout(_)) | Err(err @ Error::HandshakeTimeout(_)) => {
+                    return Err(err)
+                }
                 Err(err) => {
                     log::warn!(
                         "Couldn't connect to server at {}, retrying in {:?}: {}",
@@ -116,34 +238,105 @@ impl<T> Client<T> {
     }
 }
 
+/// The items of a [`Streaming`](../struct.Streaming.html) response.
+pub struct ResponseStream<'a, I> {
+    /// The stream is `None` after the response ended.
+    stream: Option<connection_pool::StreamGuard<'a>>,
+    addr: SocketAddr,
+    item: PhantomData<fn() -> I>,
+}
+
+impl<I> ResponseStream<'_, I>
+where
+    I: DeserializeOwned,
+{
+    /// Receive the next item of the response.
+    ///
+    /// Returns `None` after the last item or after an error ended the stream.
+    /// A connection is only reused if the stream was read completely.
+    pub async fn next(&mut self) -> Option<Result<I, Error>> {
+        let stream = self.stream.as_mut()?;
+        let frame = match read_stream_frame(&mut **stream).await {
+            Ok(frame) => frame,
+            Err(err) => {
+                // The connection is in an unknown state, so it is closed.
+                self.stream = None;
+                metrics::record_error(self.addr, &err);
+                return Some(Err(err));
+            }
+        };
+
+        match frame {
+            Some(data) => {
+                let item = postcard::from_bytes(&data).map_err(Error::from);
+                if let Err(err) = &item {
+                    metrics::record_error(self.addr, err);
+                }
+                Some(item)
+            }
+            None => {
+                log::trace!("Received end of stream from {}.", self.addr);
+                metrics::record_success(self.addr);
+                if let Some(stream) = self.stream.take() {
+                    stream.done().await;
+                }
+                None
+            }
+        }
+    }
+}
+
+async fn read_stream_frame<S>(stream: &mut S) -> Result<Option<Vec<u8>>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let buf = match frame::read_frame(stream).await? {
+        Some(buf) => buf,
+        None => return Err(Error::IO(io::ErrorKind::UnexpectedEof.into())),
+    };
+
+    let frame: ResponseFrame<Option<Vec<u8>>> = postcard::from_bytes(&buf)?;
+    frame.into_result().map_err(Error::from_server)
+}
+
+/// Write the `req` as a frame (sealed into an `Envelope` if a `signer` is given).
+async fn write_request<S, T>(
+    stream: &mut S,
+    req: T,
+    signer: Option<&dyn Signer>,
+) -> Result<(), Error>
+where
+    S: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    match signer {
+        Some(signer) => frame::write_frame(stream, &Envelope::seal(&req, signer)?).await,
+        None => frame::write_frame(stream, &req).await,
+    }
+}
+
 async fn send_request<S, Req, T>(
     stream: &mut S,
     req: Req,
-) -> Result<Result<Req::Response, String>, Error>
+    signer: Option<&dyn Signer>,
+) -> Result<Result<Req::Response, RemoteError>, Error>
 where
     S: AsyncRead + AsyncWrite + Unpin,
     Req: Request<T>,
+    Req::Response: DeserializeOwned,
     T: Serialize,
 {
     let req: T = req.into();
-    // serialize request
-    let vec = vec![0; 4];
-    let mut vec = postcard::serialize_with_flavor(&req, postcard::flavors::StdVec(vec))?;
     // send request
-    let size: u32 = (vec.len() - 4)
-        .try_into()
-        .map_err(|_| Error::MessageTooLong)?;
-    vec[..4].copy_from_slice(&size.to_le_bytes());
-    stream.write_all(&vec).await?;
-    // read response length
-    let mut len_buf = [0; 4];
-    stream.read_exact(&mut len_buf).await?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-    // read message
-    let mut buf = vec![0; len];
-    stream.read_exact(&mut buf).await?;
-
-    let res = match postcard::from_bytes(&buf)? {
+    write_request(stream, req, signer).await?;
+    // read response
+    let buf = match frame::read_frame(stream).await? {
+        Some(buf) => buf,
+        None => return Err(Error::IO(io::ErrorKind::UnexpectedEof.into())),
+    };
+
+    let frame: ResponseFrame<&[u8]> = postcard::from_bytes(&buf)?;
+    let res = match frame.into_result() {
         Ok(data) => Ok(postcard::from_bytes(data)?),
         Err(err) => Err(err),
     };
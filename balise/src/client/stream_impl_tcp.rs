@@ -1,6 +1,7 @@
+use super::super::ConnectTimeouts;
 use crate::Error;
 use std::net::SocketAddr;
-use tokio::net::TcpStream;
+use tokio::{net::TcpStream, time};
 
 pub type StreamImpl = TcpStream;
 
@@ -10,7 +11,9 @@ impl<'a> super::StreamGuard<'a> {
     }
 }
 
-pub async fn connect(addr: &SocketAddr) -> Result<StreamImpl, Error> {
-    let stream = TcpStream::connect(addr).await?;
+pub async fn connect(addr: &SocketAddr, timeouts: ConnectTimeouts) -> Result<StreamImpl, Error> {
+    let stream = time::timeout(timeouts.connect, TcpStream::connect(addr))
+        .await
+        .map_err(|_| Error::ConnectTimeout(*addr))??;
     Ok(stream)
 }
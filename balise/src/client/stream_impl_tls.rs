@@ -1,8 +1,9 @@
+use super::super::ConnectTimeouts;
 use crate::Error;
 use lazy_static::lazy_static;
 use native_tls::{Certificate, TlsConnector};
 use std::{env, fs, net::SocketAddr};
-use tokio::net::TcpStream;
+use tokio::{net::TcpStream, time};
 use tokio_tls::{TlsConnector as AsyncTlsConnector, TlsStream};
 
 pub type StreamImpl = TlsStream<TcpStream>;
@@ -27,9 +28,16 @@ lazy_static! {
     };
 }
 
-pub async fn connect(addr: &SocketAddr) -> Result<StreamImpl, Error> {
+pub async fn connect(addr: &SocketAddr, timeouts: ConnectTimeouts) -> Result<StreamImpl, Error> {
     // connect with tcp stream
-    let stream = TcpStream::connect(addr).await?;
-    let stream = CONNECTOR.connect(&addr.ip().to_string(), stream).await?;
+    let stream = time::timeout(timeouts.connect, TcpStream::connect(addr))
+        .await
+        .map_err(|_| Error::ConnectTimeout(*addr))??;
+    let stream = time::timeout(
+        timeouts.handshake,
+        CONNECTOR.connect(&addr.ip().to_string(), stream),
+    )
+    .await
+    .map_err(|_| Error::HandshakeTimeout(*addr))??;
     Ok(stream)
 }
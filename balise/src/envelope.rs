@@ -0,0 +1,155 @@
+//! An authenticated envelope for requests.
+//!
+//! A [`Client`](../client/struct.Client.html) with a signer seals each request into an `Envelope`
+//! containing a timestamp and a random nonce, signed by the client's `Signer`.
+//! A [`Server`](../server/struct.Server.html) with an `Authenticator` opens the envelopes
+//! before dispatching the requests. Requests of unauthorized signers, with an invalid signature,
+//! outside the replay window or with an already used nonce never reach the handler.
+//!
+//! ```
+//! use balise::envelope::{Authenticator, Envelope};
+//! use pinxit::Identity;
+//! use std::time::Duration;
+//!
+//! let identity = Identity::generate();
+//! let authorized = identity.id().clone();
+//! let authenticator = Authenticator::new(Duration::from_secs(30), move |peer_id| {
+//!     *peer_id == authorized
+//! });
+//!
+//! let envelope = Envelope::seal(&42_u32, &identity).unwrap();
+//! let (signer, _) = authenticator.open(envelope.clone()).unwrap();
+//! assert_eq!(&signer, identity.id());
+//! // The same envelope cannot be replayed ...
+//! assert!(authenticator.open(envelope).is_err());
+//! // ... and other signers are rejected.
+//! let envelope = Envelope::seal(&42_u32, &Identity::generate()).unwrap();
+//! assert!(authenticator.open(envelope).is_err());
+//! ```
+
+use crate::Error;
+use pinxit::{PeerId, Signable, Signed, Signer};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// A serialized request with a timestamp and a nonce.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    timestamp: SystemTime,
+    nonce: u64,
+    request: Vec<u8>,
+}
+
+impl Envelope {
+    /// Serialize the `request` and seal it into an `Envelope` signed by the `signer`.
+    pub fn seal<T, S>(request: &T, signer: &S) -> Result<Signed<Self>, Error>
+    where
+        T: Serialize + ?Sized,
+        S: Signer + ?Sized,
+    {
+        let envelope = Self {
+            timestamp: SystemTime::now(),
+            nonce: rand::random(),
+            request: postcard::to_stdvec(request)?,
+        };
+        Ok(envelope.sign(signer)?)
+    }
+}
+
+impl Signable for Envelope {
+    type SignableData = Vec<u8>;
+    type Error = postcard::Error;
+    fn signable_data(&self) -> Result<Self::SignableData, Self::Error> {
+        postcard::to_stdvec(self)
+    }
+}
+
+type Authorize = dyn Fn(&PeerId) -> bool + Send + Sync;
+
+/// An `Authenticator` verifies the `Envelope`s of incoming requests.
+pub struct Authenticator {
+    replay_window: Duration,
+    authorize: Box<Authorize>,
+    seen: Mutex<Seen>,
+}
+
+/// The nonces used within the replay window (oldest first).
+#[derive(Default)]
+struct Seen {
+    nonces: HashSet<(PeerId, u64)>,
+    order: VecDeque<(Instant, (PeerId, u64))>,
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("replay_window", &self.replay_window)
+            .finish()
+    }
+}
+
+impl Authenticator {
+    /// Create a new `Authenticator`.
+    ///
+    /// Envelopes are accepted if `authorize` returns `true` for their signer
+    /// and their timestamp differs by at most `replay_window` from the local time.
+    #[must_use]
+    pub fn new<F>(replay_window: Duration, authorize: F) -> Self
+    where
+        F: Fn(&PeerId) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            replay_window,
+            authorize: Box::new(authorize),
+            seen: Mutex::default(),
+        }
+    }
+
+    /// Verify an `envelope` and return its signer and the serialized request.
+    pub fn open(&self, envelope: Signed<Envelope>) -> Result<(PeerId, Vec<u8>), Error> {
+        if !(self.authorize)(envelope.signer()) {
+            return Err(Error::Unauthorized(format!(
+                "{} is not authorized",
+                envelope.signer()
+            )));
+        }
+        let envelope = envelope.verify()?;
+
+        let age = match SystemTime::now().duration_since(envelope.timestamp) {
+            Ok(age) => age,
+            Err(err) => err.duration(),
+        };
+        if age > self.replay_window {
+            return Err(Error::Unauthorized(format!(
+                "the timestamp differs by {:?}",
+                age
+            )));
+        }
+
+        let mut seen = self.seen.lock().unwrap();
+        // An envelope is accepted for at most twice the replay window after it was first seen,
+        // so older nonces can be forgotten.
+        while let Some((inserted, _)) = seen.order.front() {
+            if inserted.elapsed() <= 2 * self.replay_window {
+                break;
+            }
+            let (_, nonce) = seen.order.pop_front().unwrap();
+            seen.nonces.remove(&nonce);
+        }
+
+        let signer = envelope.signer().clone();
+        let nonce = (signer.clone(), envelope.nonce);
+        if !seen.nonces.insert(nonce.clone()) {
+            return Err(Error::Unauthorized("the request was replayed".to_string()));
+        }
+        seen.order.push_back((Instant::now(), nonce));
+        drop(seen);
+
+        Ok((signer, envelope.into_inner().request))
+    }
+}
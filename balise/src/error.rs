@@ -1,6 +1,8 @@
 #![allow(clippy::pub_enum_variant_names)]
 
 use err_derive::Error;
+use serde::{Deserialize, Serialize};
+use std::{error::Error as StdError, fmt, net::SocketAddr};
 
 /// An error of the `pinxit` crate.
 #[derive(Debug, Error)]
@@ -10,10 +12,34 @@ pub enum Error {
     #[error(display = "Timeout: Could not send request.")]
     Timeout,
 
+    /// Timeout: The TCP connection to the server could not be established in time.
+    #[error(display = "Timeout: Could not connect to {}.", 0)]
+    ConnectTimeout(SocketAddr),
+
+    /// Timeout: The TLS handshake with the server (or a client) did not finish in time.
+    #[error(display = "Timeout: The TLS handshake with {} did not finish.", 0)]
+    HandshakeTimeout(SocketAddr),
+
     /// The message is too loong.
     #[error(display = "The message is too long.")]
     MessageTooLong,
 
+    /// The server has too many requests in flight.
+    #[error(display = "The server is busy, try again later.")]
+    ServerBusy,
+
+    /// The server is shutting down and does not accept new requests.
+    #[error(display = "The server is shutting down, try again later.")]
+    ShuttingDown,
+
+    /// The request was rejected because its envelope could not be authenticated.
+    #[error(display = "Unauthorized request: {}.", 0)]
+    Unauthorized(String),
+
+    /// The receiving side of a stream was closed.
+    #[error(display = "The stream was closed.")]
+    StreamClosed,
+
     /// An IO error.
     #[error(display = "{}", 0)]
     IO(#[error(from)] std::io::Error),
@@ -22,6 +48,10 @@ pub enum Error {
     #[error(display = "{}", 0)]
     Encoding(#[error(from)] postcard::Error),
 
+    /// A signature error.
+    #[error(display = "{}", 0)]
+    Signature(#[error(from)] pinxit::Error),
+
     /// A tls error.
     #[cfg(feature = "tls")]
     #[error(display = "{}", 0)]
@@ -29,9 +59,195 @@ pub enum Error {
 
     /// A serverside error.
     #[error(display = "Server: {}", 0)]
-    Server(#[error(from)] String),
+    Server(RemoteError),
 
     /// Any error :D.
     #[error(display = "{}", 0)]
     BoxError(#[error(from)] crate::BoxError),
 }
+
+/// The category of an [`Error`](enum.Error.html), used to decide how to handle it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The connection failed or the server was temporarily unavailable.
+    ///
+    /// Sending the same request again later may succeed.
+    Transient,
+    /// The request itself was rejected (or could not be encoded or decoded).
+    ///
+    /// Sending the same request again will fail again.
+    Rejected,
+    /// The local setup is broken (e.g. an invalid TLS configuration).
+    ///
+    /// No request can succeed until the configuration is fixed.
+    Fatal,
+}
+
+impl Error {
+    /// Convert an error received from the server into an `Error`.
+    ///
+    /// Busy and shutting down servers are mapped back to their variants.
+    #[must_use]
+    pub fn from_server(error: RemoteError) -> Self {
+        match error.code {
+            ErrorCode::ServerBusy => Self::ServerBusy,
+            ErrorCode::ShuttingDown => Self::ShuttingDown,
+            _ => Self::Server(error),
+        }
+    }
+
+    /// Get the category of the error.
+    #[must_use]
+    pub const fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Timeout
+            | Self::ConnectTimeout(_)
+            | Self::HandshakeTimeout(_)
+            | Self::ServerBusy
+            | Self::ShuttingDown
+            | Self::StreamClosed
+            | Self::IO(_)
+            | Self::Server(RemoteError {
+                retryable: true, ..
+            }) => ErrorCategory::Transient,
+            #[cfg(feature = "tls")]
+            Self::Tls(_) => ErrorCategory::Fatal,
+            Self::MessageTooLong
+            | Self::Unauthorized(_)
+            | Self::Encoding(_)
+            | Self::Signature(_)
+            | Self::Server(_)
+            | Self::BoxError(_) => ErrorCategory::Rejected,
+        }
+    }
+
+    /// Whether sending the same request again later may succeed.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self.category(), ErrorCategory::Transient)
+    }
+
+    /// Whether no request can succeed until the local setup is fixed.
+    #[must_use]
+    pub const fn is_fatal(&self) -> bool {
+        matches!(self.category(), ErrorCategory::Fatal)
+    }
+}
+
+/// The kind of a [`RemoteError`](struct.RemoteError.html).
+///
+/// New codes are only appended, so the codes of existing errors stay stable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// The handler of the request failed (e.g. the request was invalid for the application).
+    Handler,
+    /// The request could not be decoded (or the response could not be encoded).
+    BadRequest,
+    /// The request's envelope could not be authenticated.
+    Unauthorized,
+    /// The server has too many requests in flight.
+    ServerBusy,
+    /// The server is shutting down.
+    ShuttingDown,
+    /// Any other error of the server (e.g. an IO error).
+    Internal,
+}
+
+/// An error sent by a server in response to a request.
+///
+/// Unlike the error message alone, it keeps the kind of the error and whether it is retryable
+/// across the wire. Handlers can return a `RemoteError` (as a boxed error) to choose these themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteError {
+    /// The kind of the error.
+    pub code: ErrorCode,
+    /// A human readable description of the error.
+    pub message: String,
+    /// Whether sending the same request again later may succeed.
+    pub retryable: bool,
+    /// More information about the error (e.g. the errors causing it).
+    pub details: Option<String>,
+}
+
+impl RemoteError {
+    /// Create a new `RemoteError` that is not retryable.
+    #[must_use]
+    pub fn new(code: ErrorCode, message: impl fmt::Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            retryable: false,
+            details: None,
+        }
+    }
+
+    /// Set whether sending the same request again later may succeed.
+    #[must_use]
+    pub const fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Add more information about the error.
+    #[must_use]
+    pub fn with_details(mut self, details: impl fmt::Display) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    /// Convert an error message of a server sending errors as strings (earlier versions of `balise`).
+    ///
+    /// The messages of busy and shutting down servers are mapped to their codes to keep them retryable.
+    #[must_use]
+    pub fn legacy(message: String) -> Self {
+        if message == Error::ServerBusy.to_string() {
+            Self::new(ErrorCode::ServerBusy, message).with_retryable(true)
+        } else if message == Error::ShuttingDown.to_string() {
+            Self::new(ErrorCode::ShuttingDown, message).with_retryable(true)
+        } else {
+            Self::new(ErrorCode::Handler, message)
+        }
+    }
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{} ({})", self.message, details),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl StdError for RemoteError {}
+
+impl From<&Error> for RemoteError {
+    fn from(err: &Error) -> Self {
+        let code = match err {
+            Error::ServerBusy => ErrorCode::ServerBusy,
+            Error::ShuttingDown => ErrorCode::ShuttingDown,
+            Error::Unauthorized(_) | Error::Signature(_) => ErrorCode::Unauthorized,
+            Error::MessageTooLong | Error::Encoding(_) => ErrorCode::BadRequest,
+            // An error of another server is passed on as it is.
+            Error::Server(err) => return err.clone(),
+            Error::BoxError(err) => {
+                if let Some(err) = err.downcast_ref::<Self>() {
+                    return err.clone();
+                }
+                let mut remote_error = Self::new(ErrorCode::Handler, err);
+                let mut causes = Vec::new();
+                let mut source = err.source();
+                while let Some(err) = source {
+                    causes.push(err.to_string());
+                    source = err.source();
+                }
+                if !causes.is_empty() {
+                    remote_error = remote_error.with_details(causes.join(": "));
+                }
+                return remote_error;
+            }
+            _ => ErrorCode::Internal,
+        };
+        Self::new(code, err).with_retryable(err.is_retryable())
+    }
+}
@@ -0,0 +1,145 @@
+//! Length-prefixed frames of the balise protocol.
+//!
+//! Each frame consists of the payload's length (as little endian `u32`) followed by the payload.
+//! The payload of a response frame is a [`ResponseFrame`](enum.ResponseFrame.html).
+
+use crate::{Error, RemoteError};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryInto, io};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The maximum length of the payload of a frame (256 MiB).
+///
+/// Longer frames are rejected before their payload is read.
+pub const MAX_FRAME_LEN: u32 = 256 * 1024 * 1024;
+
+/// Read a single frame from the `stream`.
+///
+/// Returns `None` if the stream was closed before a new frame started.
+/// The buffer grows while reading, so a bogus length does not lead to a huge allocation.
+/// Frames longer than `MAX_FRAME_LEN` are rejected with `Error::MessageTooLong`.
+pub async fn read_frame<S>(stream: &mut S) -> Result<Option<Vec<u8>>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    match read_frame_len(stream).await? {
+        Some(len) => Ok(Some(read_frame_payload(stream, len).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Read the length of the next frame from the `stream` (see `read_frame`).
+///
+/// Returns `None` if the stream was closed before a new frame started.
+/// The payload needs to be read with `read_frame_payload` before the next frame.
+pub async fn read_frame_len<S>(stream: &mut S) -> Result<Option<u32>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut len_buf = [0; 4];
+    match stream.read_exact(&mut len_buf).await {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(Error::IO(err)),
+    };
+
+    let len = u32::from_le_bytes(len_buf);
+    if len > MAX_FRAME_LEN {
+        return Err(Error::MessageTooLong);
+    }
+    Ok(Some(len))
+}
+
+/// Read the payload of a frame with the length `len` (see `read_frame_len`) from the `stream`.
+pub async fn read_frame_payload<S>(stream: &mut S, len: u32) -> Result<Vec<u8>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    stream.take(len.into()).read_to_end(&mut buf).await?;
+    if buf.len() != len as usize {
+        return Err(Error::IO(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    Ok(buf)
+}
+
+/// Read the payload of a frame with the length `len` from the `stream` and drop it.
+///
+/// This keeps the `stream` in sync for the next frame without buffering the payload.
+pub async fn skip_frame_payload<S>(stream: &mut S, len: u32) -> Result<Vec<u8>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let skipped = tokio::io::copy(&mut stream.take(len.into()), &mut tokio::io::sink()).await?;
+    if skipped != u64::from(len) {
+        return Err(Error::IO(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(Vec::new())
+}
+
+/// Serialize a `value` and write it as a single frame to the `stream`.
+pub async fn write_frame<S, T>(stream: &mut S, value: &T) -> Result<(), Error>
+where
+    S: AsyncWrite + Unpin,
+    T: Serialize + ?Sized,
+{
+    // serialize message
+    let vec = vec![0; 4];
+    let mut vec = postcard::serialize_with_flavor(value, postcard::flavors::StdVec(vec))?;
+
+    // write message length and message
+    let size: u32 = (vec.len() - 4)
+        .try_into()
+        .ok()
+        .filter(|size| *size <= MAX_FRAME_LEN)
+        .ok_or(Error::MessageTooLong)?;
+    vec[..4].copy_from_slice(&size.to_le_bytes());
+    stream.write_all(&vec).await?;
+    Ok(())
+}
+
+/// The payload of a response frame: the encoded response (or stream item) or an error.
+///
+/// The first two variants are encoded like the `Result<T, String>` sent by earlier versions of `balise`,
+/// so the responses of older servers can still be decoded.
+///
+/// ```
+/// use balise::{frame::ResponseFrame, ErrorCode};
+///
+/// let legacy: Result<Vec<u8>, String> = Err("The server is busy, try again later.".to_string());
+/// let bytes = postcard::to_stdvec(&legacy).unwrap();
+/// let frame: ResponseFrame<Vec<u8>> = postcard::from_bytes(&bytes).unwrap();
+/// let err = frame.into_result().unwrap_err();
+/// assert_eq!(err.code, ErrorCode::ServerBusy);
+/// assert!(err.retryable);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResponseFrame<T> {
+    /// The response.
+    Ok(T),
+    /// An error message of an older server.
+    LegacyErr(String),
+    /// An error.
+    Err(RemoteError),
+}
+
+impl<T> ResponseFrame<T> {
+    /// Convert the frame into a `Result`.
+    pub fn into_result(self) -> Result<T, RemoteError> {
+        match self {
+            Self::Ok(value) => Ok(value),
+            Self::LegacyErr(message) => Err(RemoteError::legacy(message)),
+            Self::Err(err) => Err(err),
+        }
+    }
+}
+
+impl<T> From<Result<T, RemoteError>> for ResponseFrame<T> {
+    fn from(result: Result<T, RemoteError>) -> Self {
+        match result {
+            Ok(value) => Self::Ok(value),
+            Err(err) => Self::Err(err),
+        }
+    }
+}
@@ -90,20 +90,28 @@
 //! }
 //! ```
 
+#[cfg(feature = "server")]
+pub mod activation;
+
 #[cfg(feature = "client")]
 pub mod client;
 
 #[cfg(feature = "server")]
 pub mod server;
 
+pub mod envelope;
+pub mod frame;
+
 mod error;
 mod macros;
 mod stream;
+mod streaming;
 
-pub use error::Error;
+pub use error::{Error, ErrorCategory, ErrorCode, RemoteError};
 pub use stream::Stream;
+pub use streaming::{StreamSender, Streaming};
 
-use serde::{de::DeserializeOwned, Serialize};
+use serde::Serialize;
 use std::fmt::Debug;
 
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
@@ -114,7 +122,9 @@ type BoxError = Box<dyn std::error::Error + Send + Sync>;
 /// (is done automatically when using the [`define_api!`](macro.define_api.html)-macro).
 /// This allows a message `M` which implements `Request<T>` to be converted to one of the enum variants (via `Into<T>`).
 /// And the implementation can ensure that the response is of type `M::Response`.
+///
+/// The response is either a single serializable value or a [`Streaming`](struct.Streaming.html) response.
 pub trait Request<T>: Serialize + Into<T> + Debug + Clone + Send + 'static {
     /// The type of the response.
-    type Response: Serialize + DeserializeOwned + Debug + Send + 'static;
+    type Response: Debug + Send + 'static;
 }
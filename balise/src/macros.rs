@@ -62,6 +62,53 @@ macro_rules! request_and_response {
     ) => {}
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! request_names {
+    (
+        $enum_name:ident {
+            $(#[$inner:meta])*
+            $request_name:ident($($type:ty),*) => $response:ty,
+            $($tail:tt)*
+        }
+        ( $($names:ident)* )
+    ) => {
+        $crate::request_names! {
+            $enum_name {
+                $($tail)*
+            }
+            ( $($names)* $request_name )
+        }
+    };
+    (
+        $enum_name:ident {
+            $(#[$inner:meta])*
+            $request_name:ident => $response:ty,
+            $($tail:tt)*
+        }
+        ( $($names:ident)* )
+    ) => {
+        $crate::request_names! {
+            $enum_name {
+                $($tail)*
+            }
+            ( $($names)* $request_name )
+        }
+    };
+    (
+        $enum_name:ident { }
+        ( $($names:ident)* )
+    ) => {
+        impl $enum_name {
+            /// Get the names of all requests of the API (in the order of their definition).
+            #[must_use]
+            pub const fn request_names() -> &'static [&'static str] {
+                &[$(stringify!($names)),*]
+            }
+        }
+    };
+}
+
 #[macro_export]
 #[doc(hidden)]
 macro_rules! request_enum {
@@ -128,6 +175,8 @@ macro_rules! request_enum {
 
 /// Define an API.
 ///
+/// The enum gets a `request_names` function listing the names of all requests.
+///
 /// # Example
 /// ```
 /// use balise::{define_api, Request};
@@ -144,7 +193,9 @@ macro_rules! request_enum {
 ///     }
 /// }
 ///
-/// # fn main() {}
+/// # fn main() {
+/// assert_eq!(PingAPIRequest::request_names(), &["Add", "Ping"]);
+/// # }
 /// ```
 #[macro_export]
 macro_rules! define_api {
@@ -165,6 +216,12 @@ macro_rules! define_api {
             }
             ()
         }
+        $crate::request_names! {
+            $enum_name {
+                $($inner)*
+            }
+            ()
+        }
         $(#[$modmeta])*
         $vis mod $message_module_name {
             use super::*;
@@ -178,8 +235,35 @@ macro_rules! define_api {
 }
 
 /// Implement a handle function. Used in the `Handler` trait.
+///
+/// An optional identifier before the handlers (e.g. `handler!(Message, connection, { ... })`)
+/// binds the [`ConnectionInfo`](server/struct.ConnectionInfo.html) of the request.
+///
+/// The requests are recorded in the [`RequestMetrics`](server/struct.RequestMetrics.html)
+/// of their request type.
 #[macro_export]
 macro_rules! handler {
+    (
+        $T:ident, $connection:ident, {
+            $(
+                $name:ident($params:pat) => $handler:expr,
+            )*
+        }
+    ) => {
+        move |req, $connection: $crate::server::ConnectionInfo| async move {
+            let request_size = $connection.request_size;
+            match req {
+                $(
+                    $T::$name(params) => $crate::server::instrument(
+                        stringify!($T),
+                        stringify!($name),
+                        request_size,
+                        $crate::server::handle_params(params, |$params| async move { $handler }),
+                    ).await,
+                )*
+            }
+        }
+    };
     (
         $T:ident, {
             $(
@@ -187,10 +271,15 @@ macro_rules! handler {
             )*
         }
     ) => {
-        move |req| async move {
+        move |req, connection: $crate::server::ConnectionInfo| async move {
             match req {
                 $(
-                    $T::$name(params) => $crate::server::handle_params(params, |$params| async move { $handler }).await,
+                    $T::$name(params) => $crate::server::instrument(
+                        stringify!($T),
+                        stringify!($name),
+                        connection.request_size,
+                        $crate::server::handle_params(params, |$params| async move { $handler }),
+                    ).await,
                 )*
             }
         }
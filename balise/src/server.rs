@@ -1,20 +1,35 @@
 //! A server for communicating between RPUs.
 
-use crate::{Error, Request};
-use serde::de::DeserializeOwned;
+mod metrics;
+
+#[doc(hidden)]
+pub use metrics::instrument;
+pub use metrics::{request_metrics, Histogram, RequestMetrics};
+
+use crate::{
+    envelope::Authenticator,
+    frame::{self, ResponseFrame},
+    Error, RemoteError, Request, Streaming,
+};
+use pinxit::PeerId;
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
-    convert::TryInto,
+    collections::HashMap,
     fmt::Debug,
     future::Future,
-    io,
     marker::{PhantomData, Unpin},
-    net::SocketAddr,
-    sync::Arc,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 use tokio::{
-    fs,
-    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    io::{AsyncRead, AsyncWrite},
     net::TcpListener,
+    sync::{mpsc, watch, Notify, Semaphore, SemaphorePermit},
+    time,
 };
 
 type ServerResult = Result<Response, Error>;
@@ -22,7 +37,36 @@ type ServerResult = Result<Response, Error>;
 /// A transparent response to a `Request`.
 ///
 /// Use the `handle` method to create a matching response.
-pub struct Response(pub(crate) Vec<u8>);
+pub struct Response(pub(crate) ResponseData);
+
+pub(crate) enum ResponseData {
+    /// A single serialized value.
+    Single(Vec<u8>),
+    /// A stream of serialized items.
+    Stream(mpsc::Receiver<Result<Vec<u8>, RemoteError>>),
+}
+
+/// A type that can be sent as a response to a `Request`.
+pub trait IntoResponse {
+    /// Encode the response.
+    fn into_response(self) -> ServerResult;
+}
+
+impl<T> IntoResponse for T
+where
+    T: Serialize,
+{
+    fn into_response(self) -> ServerResult {
+        let data = postcard::to_stdvec(&self)?;
+        Ok(Response(ResponseData::Single(data)))
+    }
+}
+
+impl<I> IntoResponse for Streaming<I> {
+    fn into_response(self) -> ServerResult {
+        Ok(Response(ResponseData::Stream(self.into_receiver())))
+    }
+}
 
 #[cfg(feature = "tls")]
 pub use native_tls::Identity as TlsIdentity;
@@ -30,7 +74,8 @@ pub use native_tls::Identity as TlsIdentity;
 #[cfg(feature = "tls")]
 use ::{
     native_tls::{Identity, Protocol, TlsAcceptor},
-    std::path::Path,
+    std::{io, path::Path},
+    tokio::fs,
     tokio_tls::TlsAcceptor as AsyncTlsAcceptor,
 };
 
@@ -39,16 +84,262 @@ struct AsyncTlsAcceptor;
 
 #[cfg(not(feature = "tls"))]
 impl AsyncTlsAcceptor {
-    async fn accept<T>(&self, stream: T) -> Result<T, String> {
+    async fn accept<T>(&self, stream: T) -> Result<T, std::io::Error> {
         Ok(stream)
     }
 }
 
+/// Information about the origin of a request, passed to the handler.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    /// The address of the remote side of the connection.
+    pub remote_addr: SocketAddr,
+    /// The signer of the request's envelope (only if the server has an `Authenticator`).
+    ///
+    /// The TLS connection only authenticates the server, so this is the identity of the remote peer.
+    pub peer_id: Option<PeerId>,
+    /// The size of the encoded request in bytes.
+    pub request_size: usize,
+}
+
+/// Limits for the number of connections and requests a `Server` handles concurrently.
+///
+/// Requests exceeding a limit are answered with a
+/// [`ServerBusy`](../enum.Error.html#variant.ServerBusy) error.
+/// Connections exceeding the limit are not accepted until another connection is closed.
+/// The timeouts close connections that hold a slot without sending requests.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// The maximum number of open connections.
+    ///
+    /// Each connection is handled by its own task, so this bounds the tasks (and memory)
+    /// of a server under client load. Further connections wait in the listen backlog.
+    pub max_connections: usize,
+    /// The time a new connection may take to complete the TLS handshake.
+    pub handshake_timeout: Duration,
+    /// The time an open connection may wait for its next request (and again take to send it).
+    ///
+    /// Clients close pooled connections earlier (see `client::POOL_IDLE_TIMEOUT`).
+    pub idle_timeout: Duration,
+    /// The maximum number of in-flight requests over all connections.
+    pub max_in_flight: usize,
+    /// The maximum number of in-flight requests over all connections from the same host.
+    ///
+    /// A single connection handles one request at a time, so this limits
+    /// clients opening many connections in parallel.
+    pub max_in_flight_per_host: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_connections: 4096,
+            handshake_timeout: Duration::from_secs(10),
+            idle_timeout: Duration::from_secs(60),
+            max_in_flight: 1024,
+            max_in_flight_per_host: 256,
+        }
+    }
+}
+
+/// Keeps track of the open connections and in-flight requests.
+struct Limiter {
+    limits: Limits,
+    connections: AtomicUsize,
+    connection_closed: Notify,
+    in_flight: Semaphore,
+    in_flight_per_host: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl Limiter {
+    fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            connections: AtomicUsize::new(0),
+            connection_closed: Notify::new(),
+            in_flight: Semaphore::new(limits.max_in_flight),
+            in_flight_per_host: Mutex::default(),
+        }
+    }
+
+    /// Wait until a slot for another connection is free and reserve it.
+    async fn connect(self: Arc<Self>, port: u16) -> ConnectionPermit {
+        let mut waiting = false;
+        loop {
+            if self.connections.fetch_add(1, Ordering::SeqCst) < self.limits.max_connections {
+                return ConnectionPermit { limiter: self };
+            }
+            self.connections.fetch_sub(1, Ordering::SeqCst);
+            if !waiting {
+                log::warn!(
+                    "Server on Port {} reached the limit of {} connections.",
+                    port,
+                    self.limits.max_connections
+                );
+                waiting = true;
+            }
+            self.connection_closed.notified().await;
+        }
+    }
+
+    /// Try to reserve a slot for a request from `addr`.
+    ///
+    /// A connection handles one request at a time, so it holds at most one slot.
+    /// The limit per host counts the requests of all connections from the IP address of `addr`
+    /// (e.g. clients behind the same NAT share it).
+    fn try_acquire(&self, addr: &SocketAddr) -> Result<LimiterPermit, Error> {
+        let permit = self
+            .in_flight
+            .try_acquire()
+            .map_err(|_| Error::ServerBusy)?;
+
+        let host = addr.ip();
+        let mut in_flight_per_host = self.in_flight_per_host.lock().unwrap();
+        let count = in_flight_per_host.entry(host).or_default();
+        if *count >= self.limits.max_in_flight_per_host {
+            return Err(Error::ServerBusy);
+        }
+        *count += 1;
+
+        Ok(LimiterPermit {
+            limiter: self,
+            host,
+            _permit: permit,
+        })
+    }
+}
+
+/// A reserved slot for an in-flight request. The slot is released on drop.
+struct LimiterPermit<'a> {
+    limiter: &'a Limiter,
+    host: IpAddr,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl Drop for LimiterPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight_per_host = self.limiter.in_flight_per_host.lock().unwrap();
+        if let Some(count) = in_flight_per_host.get_mut(&self.host) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight_per_host.remove(&self.host);
+            }
+        }
+    }
+}
+
+/// A reserved slot for an open connection. The slot is released on drop.
+struct ConnectionPermit {
+    limiter: Arc<Limiter>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.connections.fetch_sub(1, Ordering::SeqCst);
+        self.limiter.connection_closed.notify();
+    }
+}
+
+/// Shuts down `Server`s gracefully.
+///
+/// After [`drain`](#method.drain) was called, the servers stop accepting connections,
+/// close idle connections and answer new requests with a
+/// [`ShuttingDown`](../enum.Error.html#variant.ShuttingDown) error.
+/// Requests that are already in flight are handled to the end.
+/// Clones of a `Shutdown` control the same servers.
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<ShutdownInner>,
+}
+
+struct ShutdownInner {
+    sender: watch::Sender<bool>,
+    receiver: watch::Receiver<bool>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        Self {
+            inner: Arc::new(ShutdownInner {
+                sender,
+                receiver,
+                in_flight: AtomicUsize::new(0),
+                idle: Notify::new(),
+            }),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Create a new `Shutdown` handle.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the servers are shutting down.
+    #[must_use]
+    pub fn is_shutting_down(&self) -> bool {
+        *self.inner.receiver.borrow()
+    }
+
+    /// Start shutting down and wait until all in-flight requests are handled.
+    ///
+    /// Use a timeout to limit the grace period for the in-flight requests.
+    pub async fn drain(&self) {
+        let _ = self.inner.sender.broadcast(true);
+        while self.inner.in_flight.load(Ordering::SeqCst) > 0 {
+            self.inner.idle.notified().await;
+        }
+    }
+
+    /// Wait until the servers are shutting down.
+    async fn shutting_down(&self) {
+        let mut receiver = self.inner.receiver.clone();
+        while !*receiver.borrow() {
+            if receiver.recv().await.is_none() {
+                // The sender is never dropped before the receivers.
+                return;
+            }
+        }
+    }
+
+    /// Register an in-flight request (fails if the servers are shutting down).
+    fn start_request(&self) -> Result<InFlightRequest<'_>, Error> {
+        self.inner.in_flight.fetch_add(1, Ordering::SeqCst);
+        let request = InFlightRequest { shutdown: self };
+        if self.is_shutting_down() {
+            return Err(Error::ShuttingDown);
+        }
+        Ok(request)
+    }
+}
+
+/// An in-flight request, which delays the end of `Shutdown::drain` until it is dropped.
+struct InFlightRequest<'a> {
+    shutdown: &'a Shutdown,
+}
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        let inner = &self.shutdown.inner;
+        if inner.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 && self.shutdown.is_shutting_down() {
+            inner.idle.notify();
+        }
+    }
+}
+
 /// A Server (server) instance.
 pub struct Server<T, H> {
     request_data: PhantomData<fn() -> T>,
     handler: H,
     acceptor: Arc<AsyncTlsAcceptor>,
+    limiter: Arc<Limiter>,
+    authenticator: Option<Arc<Authenticator>>,
+    shutdown: Shutdown,
 }
 
 impl<T, H> Clone for Server<T, H>
@@ -60,6 +351,9 @@ where
             request_data: PhantomData,
             handler: self.handler.clone(),
             acceptor: self.acceptor.clone(),
+            limiter: self.limiter.clone(),
+            authenticator: self.authenticator.clone(),
+            shutdown: self.shutdown.clone(),
         }
     }
 }
@@ -67,12 +361,13 @@ where
 impl<T, H, F> Server<T, H>
 where
     T: DeserializeOwned + Debug,
-    H: FnOnce(T) -> F + Clone + Sync,
+    H: FnOnce(T, ConnectionInfo) -> F + Clone + Sync,
     F: Future<Output = Result<Response, Error>> + Send,
 {
     /// Create a new server instance.
     ///
     /// The `handler` needs to provide a `handle` callback script to handle requests on the server.
+    /// It is called with the request and the `ConnectionInfo` of its origin.
     #[must_use]
     #[cfg(not(feature = "tls"))]
     pub fn new(handler: H) -> Self {
@@ -80,12 +375,16 @@ where
             request_data: PhantomData,
             handler,
             acceptor: Arc::new(AsyncTlsAcceptor),
+            limiter: Arc::new(Limiter::new(Limits::default())),
+            authenticator: None,
+            shutdown: Shutdown::default(),
         }
     }
 
     /// Create a new TLS server instance.
     ///
     /// The `handler` needs to provide a `handle` callback script to handle requests on the server.
+    /// It is called with the request and the `ConnectionInfo` of its origin.
     /// The `identity` determines the server's identity.
     #[cfg(feature = "tls")]
     pub fn new(handler: H, identity: Identity) -> Result<Self, Error> {
@@ -98,33 +397,84 @@ where
             request_data: PhantomData,
             handler,
             acceptor,
+            limiter: Arc::new(Limiter::new(Limits::default())),
+            authenticator: None,
+            shutdown: Shutdown::default(),
         })
     }
 
+    /// Set the `limits` for concurrently handled connections and requests.
+    #[must_use]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limiter = Arc::new(Limiter::new(limits));
+        self
+    }
+
+    /// Only accept requests sealed into an [`Envelope`](../envelope/struct.Envelope.html)
+    /// that is accepted by the `authenticator`.
+    ///
+    /// Other requests are rejected before they reach the handler.
+    #[must_use]
+    pub fn with_authenticator(mut self, authenticator: Authenticator) -> Self {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// Shut the server down gracefully when `shutdown` is drained.
+    #[must_use]
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
     /// The main server loop.
+    ///
+    /// Returns when the server's `Shutdown` is drained.
     pub async fn serve(self, listener: &mut TcpListener) -> Result<(), Error>
     where
         T: Send + 'static,
         H: Send + 'static,
     {
-        log::info!(
-            "Server is now listening on Port {}",
-            listener.local_addr()?.port()
-        );
+        let port = listener.local_addr()?.port();
+        log::info!("Server is now listening on Port {}", port);
         loop {
+            // Only accept a connection if it can be handled, the others wait in the backlog.
+            let permit = tokio::select! {
+                permit = self.limiter.clone().connect(port) => permit,
+                () = self.shutdown.shutting_down() => {
+                    log::info!("Server on Port {} stopped accepting connections.", port);
+                    return Ok(());
+                }
+            };
+
             // TODO: Is there a case where we should continue to listen for incoming streams?
-            let (stream, _) = listener.accept().await?;
+            let (stream, _) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                () = self.shutdown.shutting_down() => {
+                    log::info!("Server on Port {} stopped accepting connections.", port);
+                    return Ok(());
+                }
+            };
 
             let clone_self = self.clone();
 
-            // handle the client in a new thread
+            // handle the client in a new task
             tokio::spawn(async move {
+                let _permit = permit;
                 let peer_addr = stream.peer_addr().expect("Peer address");
                 log::info!("Connected: {}", peer_addr);
 
-                let result = match clone_self.acceptor.accept(stream).await {
-                    Ok(stream) => clone_self.handle_client(peer_addr, stream).await,
-                    Err(err) => Err(err.into()),
+                // A connection that never completes the handshake must not keep its slot.
+                let handshake_timeout = clone_self.limiter.limits.handshake_timeout;
+                let result = match time::timeout(
+                    handshake_timeout,
+                    clone_self.acceptor.accept(stream),
+                )
+                .await
+                {
+                    Ok(Ok(stream)) => clone_self.handle_client(peer_addr, stream).await,
+                    Ok(Err(err)) => Err(err.into()),
+                    Err(_) => Err(Error::HandshakeTimeout(peer_addr)),
                 };
                 match result {
                     Ok(()) => log::info!("Disconnected"),
@@ -138,37 +488,74 @@ where
     where
         S: AsyncRead + AsyncWrite + Unpin,
     {
+        let idle_timeout = self.limiter.limits.idle_timeout;
+        // read messages until the stream is closed (or idle, or the server is shutting down)
         loop {
-            // read message length
-            let mut len_buf = [0; 4];
-            match stream.read_exact(&mut len_buf).await {
-                Ok(_) => {}
-                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
-                Err(err) => return Err(Error::IO(err)),
+            let len = tokio::select! {
+                len = time::timeout(idle_timeout, frame::read_frame_len(&mut stream)) => match len {
+                    Ok(len) => match len? {
+                        Some(len) => len,
+                        None => break,
+                    },
+                    Err(_) => {
+                        log::debug!("Closing idle connection from {}.", addr);
+                        break;
+                    }
+                },
+                () = self.shutdown.shutting_down() => break,
             };
 
-            let len = u32::from_le_bytes(len_buf) as usize;
-
-            // read message
-            let mut buf = vec![0; len];
-            stream.read_exact(&mut buf).await?;
-
-            // handle the request
-            let res = match self.handle_request(&addr, &buf).await {
-                Ok(res) => Ok(res),
-                Err(err) => Err(err.to_string()),
+            // The slot is reserved before the payload is read,
+            // so a busy server neither buffers nor decodes it.
+            let permit = self.limiter.try_acquire(&addr);
+            let payload = if permit.is_ok() {
+                time::timeout(idle_timeout, frame::read_frame_payload(&mut stream, len)).await
+            } else {
+                time::timeout(idle_timeout, frame::skip_frame_payload(&mut stream, len)).await
+            };
+            let buf = match payload {
+                Ok(buf) => buf?,
+                Err(_) => {
+                    log::debug!(
+                        "Closing connection from {}, the request was not sent in time.",
+                        addr
+                    );
+                    break;
+                }
+            };
+            let _permit = match permit {
+                Ok(permit) => permit,
+                Err(err) => {
+                    log::warn!("Rejected request from {}: {}", addr, err);
+                    let frame = ResponseFrame::<Vec<u8>>::Err(RemoteError::from(&err));
+                    frame::write_frame(&mut stream, &frame).await?;
+                    continue;
+                }
             };
 
-            // serialize response
-            let vec = vec![0; 4];
-            let mut vec = postcard::serialize_with_flavor(&res, postcard::flavors::StdVec(vec))?;
+            let _request = match self.shutdown.start_request() {
+                Ok(request) => request,
+                Err(err) => {
+                    log::debug!("Rejected request from {}: {}", addr, err);
+                    let frame = ResponseFrame::<Vec<u8>>::Err(RemoteError::from(&err));
+                    frame::write_frame(&mut stream, &frame).await?;
+                    break;
+                }
+            };
 
-            // send response
-            let size: u32 = (vec.len() - 4)
-                .try_into()
-                .map_err(|_| Error::MessageTooLong)?;
-            vec[..4].copy_from_slice(&size.to_le_bytes());
-            stream.write_all(&vec).await?;
+            // handle the request
+            match self.handle_request(&addr, &buf).await {
+                Ok(ResponseData::Single(data)) => {
+                    frame::write_frame(&mut stream, &ResponseFrame::Ok(data)).await?;
+                }
+                Ok(ResponseData::Stream(receiver)) => {
+                    Self::send_stream(&addr, &mut stream, receiver).await?;
+                }
+                Err(err) => {
+                    let frame = ResponseFrame::<Vec<u8>>::Err(RemoteError::from(&err));
+                    frame::write_frame(&mut stream, &frame).await?;
+                }
+            }
 
             // Simulate connection drop
             // let _ = stream.shutdown(std::net::Shutdown::Both);
@@ -177,14 +564,82 @@ where
         Ok(())
     }
 
-    async fn handle_request(&self, addr: &SocketAddr, req: &[u8]) -> Result<Vec<u8>, Error> {
+    async fn handle_request(&self, addr: &SocketAddr, req: &[u8]) -> Result<ResponseData, Error> {
+        let req_size = req.len();
         // Deserialize request.
-        let req: T = postcard::from_bytes(req)?;
+        let (req, peer_id) = match self.decode_request(req) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                log::warn!("Rejected request from {}: {}", addr, err);
+                return Err(err);
+            }
+        };
         log::trace!("Received request from {}: {:?}", addr, req);
+        let connection = ConnectionInfo {
+            remote_addr: *addr,
+            peer_id,
+            request_size: req_size,
+        };
         // handle the actual request
-        let res = (self.handler.clone())(req).await.map(|response| response.0);
-        log::trace!("Send response to {}: {:?}", addr, res);
-        Ok(res?)
+        let res = (self.handler.clone())(req, connection)
+            .await
+            .map(|response| response.0);
+        match &res {
+            Ok(ResponseData::Single(data)) => log::trace!("Send response to {}: {:?}", addr, data),
+            Ok(ResponseData::Stream(_)) => log::trace!("Send stream to {}.", addr),
+            Err(err) => log::trace!("Send error to {}: {}", addr, err),
+        }
+        res
+    }
+
+    /// Deserialize a request (and open its envelope if the server has an `Authenticator`).
+    ///
+    /// Returns the request and the signer of its envelope.
+    fn decode_request(&self, req: &[u8]) -> Result<(T, Option<PeerId>), Error> {
+        match &self.authenticator {
+            Some(authenticator) => {
+                let (peer_id, req) = authenticator.open(postcard::from_bytes(req)?)?;
+                Ok((postcard::from_bytes(&req)?, Some(peer_id)))
+            }
+            None => Ok((postcard::from_bytes(req)?, None)),
+        }
+    }
+
+    /// Send each item of a stream as a separate frame.
+    ///
+    /// The frames have the type `ResponseFrame<Option<Vec<u8>>>`:
+    /// `Ok(Some(item))` for each item, `Ok(None)` for the end of the stream
+    /// and `Err(err)` if the stream was aborted.
+    /// An error before the stream started is encoded the same way.
+    async fn send_stream<S>(
+        addr: &SocketAddr,
+        stream: &mut S,
+        mut receiver: mpsc::Receiver<Result<Vec<u8>, RemoteError>>,
+    ) -> Result<(), Error>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut count = 0_usize;
+        loop {
+            // Writing blocks while the client does not read,
+            // so the producer is throttled by the bounded channel.
+            match receiver.recv().await {
+                Some(Ok(data)) => {
+                    frame::write_frame(stream, &ResponseFrame::Ok(Some(data))).await?;
+                    count += 1;
+                }
+                Some(Err(err)) => {
+                    log::warn!("Stream to {} aborted after {} items: {}", addr, count, err);
+                    frame::write_frame(stream, &ResponseFrame::<Option<Vec<u8>>>::Err(err)).await?;
+                    return Ok(());
+                }
+                None => {
+                    log::trace!("Sent {} items to {}.", count, addr);
+                    frame::write_frame(stream, &ResponseFrame::<Option<Vec<u8>>>::Ok(None)).await?;
+                    return Ok(());
+                }
+            }
+        }
     }
 }
 
@@ -211,9 +666,9 @@ pub async fn handle_params<T, R, H, F>(params: R, handler: H) -> ServerResult
 where
     R: Request<T>,
     H: FnOnce(R) -> F,
+    R::Response: IntoResponse,
     F: Future<Output = Result<R::Response, crate::BoxError>>,
 {
     let res = handler(params).await?;
-    let data = postcard::to_stdvec(&res)?;
-    Ok(Response(data))
+    res.into_response()
 }
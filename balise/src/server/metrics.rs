@@ -0,0 +1,155 @@
+//! Metrics of the requests handled by all servers of the process.
+//!
+//! The requests are recorded per API and request type by the [`handler!`](../../macro.handler.html)-macro.
+//! Requests that cannot be decoded or are rejected before reaching the handler are not recorded.
+
+use super::{Response, ResponseData};
+use crate::Error;
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The upper bounds of the size buckets (in bytes).
+const SIZE_BOUNDS: &[u64] = &[
+    64,
+    256,
+    1024,
+    4 * 1024,
+    16 * 1024,
+    64 * 1024,
+    256 * 1024,
+    1024 * 1024,
+    4 * 1024 * 1024,
+    16 * 1024 * 1024,
+    u64::MAX,
+];
+
+/// The upper bounds of the latency buckets (in µs).
+const LATENCY_BOUNDS: &[u64] = &[
+    100,
+    500,
+    1_000,
+    5_000,
+    10_000,
+    50_000,
+    100_000,
+    500_000,
+    1_000_000,
+    5_000_000,
+    u64::MAX,
+];
+
+/// A histogram with fixed buckets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// The inclusive upper bound of each bucket and the number of values in the bucket.
+    ///
+    /// The last bucket is unbounded (`u64::MAX`).
+    pub buckets: Vec<(u64, u64)>,
+    /// The number of recorded values.
+    pub count: u64,
+    /// The sum of all recorded values.
+    pub sum: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &[u64]) -> Self {
+        Self {
+            buckets: bounds.iter().map(|&bound| (bound, 0)).collect(),
+            count: 0,
+            sum: 0,
+        }
+    }
+
+    fn record(&mut self, value: u64) {
+        if let Some((_, count)) = self.buckets.iter_mut().find(|(bound, _)| value <= *bound) {
+            *count += 1;
+        }
+        self.count += 1;
+        self.sum = self.sum.saturating_add(value);
+    }
+}
+
+/// The metrics of a single request type of an API.
+///
+/// All counters are totals since the process was started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// The number of handled requests.
+    pub requests: u64,
+    /// The number of requests answered with an error.
+    pub errors: u64,
+    /// The sizes of the encoded requests (in bytes).
+    pub request_size: Histogram,
+    /// The sizes of the encoded responses (in bytes, streamed responses are not recorded).
+    pub response_size: Histogram,
+    /// The time until the response (or the first item of a stream) was ready (in µs).
+    pub latency: Histogram,
+}
+
+impl Default for RequestMetrics {
+    fn default() -> Self {
+        Self {
+            requests: 0,
+            errors: 0,
+            request_size: Histogram::new(SIZE_BOUNDS),
+            response_size: Histogram::new(SIZE_BOUNDS),
+            latency: Histogram::new(LATENCY_BOUNDS),
+        }
+    }
+}
+
+lazy_static! {
+    static ref METRICS: Mutex<HashMap<(&'static str, &'static str), RequestMetrics>> =
+        Mutex::default();
+}
+
+/// Get the metrics of all handled request types by API (the name of the message enum) and request type.
+#[must_use]
+pub fn request_metrics() -> HashMap<(&'static str, &'static str), RequestMetrics> {
+    METRICS.lock().unwrap().clone()
+}
+
+/// Record a handled request.
+fn record(
+    api: &'static str,
+    request: &'static str,
+    request_size: usize,
+    result: &Result<Response, Error>,
+    latency: Duration,
+) {
+    let mut metrics = METRICS.lock().unwrap();
+    let metrics = metrics.entry((api, request)).or_default();
+    metrics.requests += 1;
+    metrics.request_size.record(request_size as u64);
+    metrics
+        .latency
+        .record(u64::try_from(latency.as_micros()).unwrap_or(u64::MAX));
+    match result {
+        Ok(Response(ResponseData::Single(data))) => metrics.response_size.record(data.len() as u64),
+        Ok(Response(ResponseData::Stream(_))) => {}
+        Err(_) => metrics.errors += 1,
+    }
+}
+
+/// Handle a request of the type `request` of the API `api` and record its metrics.
+#[doc(hidden)]
+pub async fn instrument<F>(
+    api: &'static str,
+    request: &'static str,
+    request_size: usize,
+    response: F,
+) -> Result<Response, Error>
+where
+    F: Future<Output = Result<Response, Error>>,
+{
+    let start = Instant::now();
+    let result = response.await;
+    record(api, request, request_size, &result, start.elapsed());
+    result
+}
@@ -0,0 +1,101 @@
+//! Responses consisting of a stream of items.
+//!
+//! A request with a response of type `Streaming<I>` is answered with one frame per item,
+//! followed by a frame marking the end of the stream.
+//! Items are produced by a `StreamSender` while the stream is being sent,
+//! so the whole response never has to be held in memory.
+
+use crate::{Error, ErrorCode, RemoteError};
+use serde::Serialize;
+use std::{fmt, marker::PhantomData};
+use tokio::sync::mpsc;
+
+type Item = Result<Vec<u8>, RemoteError>;
+
+/// A response consisting of a stream of items of type `I`.
+///
+/// Use [`Streaming::channel`](#method.channel) to create a streaming response on the server
+/// and [`Client::send_stream_request`](client/struct.Client.html#method.send_stream_request)
+/// to receive it on the client.
+pub struct Streaming<I> {
+    receiver: mpsc::Receiver<Item>,
+    item: PhantomData<fn() -> I>,
+}
+
+impl<I> Streaming<I>
+where
+    I: Serialize,
+{
+    /// Create a new streaming response.
+    ///
+    /// At most `capacity` items are buffered. Sending more items waits
+    /// until the client has caught up, so slow clients throttle the producer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is `0`.
+    #[must_use]
+    pub fn channel(capacity: usize) -> (StreamSender<I>, Self) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (
+            StreamSender {
+                sender,
+                item: PhantomData,
+            },
+            Self {
+                receiver,
+                item: PhantomData,
+            },
+        )
+    }
+}
+
+impl<I> Streaming<I> {
+    #[cfg(feature = "server")]
+    pub(crate) fn into_receiver(self) -> mpsc::Receiver<Item> {
+        self.receiver
+    }
+}
+
+impl<I> fmt::Debug for Streaming<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Streaming").finish()
+    }
+}
+
+/// The sending half of a `Streaming` response.
+///
+/// The stream ends when the sender is dropped.
+pub struct StreamSender<I> {
+    sender: mpsc::Sender<Item>,
+    item: PhantomData<fn(I)>,
+}
+
+impl<I> StreamSender<I>
+where
+    I: Serialize,
+{
+    /// Send the next `item` of the stream.
+    ///
+    /// Waits while the buffer of the stream is full.
+    /// Fails with [`StreamClosed`](enum.Error.html#variant.StreamClosed)
+    /// if the client is gone, so the producer should stop.
+    pub async fn send(&mut self, item: &I) -> Result<(), Error> {
+        let data = postcard::to_stdvec(item)?;
+        self.sender
+            .send(Ok(data))
+            .await
+            .map_err(|_| Error::StreamClosed)
+    }
+
+    /// Abort the stream with an `error`.
+    ///
+    /// The client receives the error instead of the end of the stream.
+    pub async fn abort(mut self, error: impl fmt::Display) {
+        // If the client is already gone, there is nobody to tell about the error.
+        let _ = self
+            .sender
+            .send(Err(RemoteError::new(ErrorCode::Handler, error)))
+            .await;
+    }
+}
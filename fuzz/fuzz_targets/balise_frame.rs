@@ -0,0 +1,24 @@
+#![no_main]
+
+//! Feed arbitrary bytes into the balise frame parser and decode the frames as requests, envelopes and responses.
+
+use balise::{
+    envelope::Envelope,
+    frame::{self, ResponseFrame},
+};
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+use pinxit::Signed;
+use prellblock::peer::PeerMessage;
+use prellblock_client_api::ClientMessage;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let mut stream = Cursor::new(data);
+    while let Ok(Some(buf)) = block_on(frame::read_frame(&mut stream)) {
+        let _ = postcard::from_bytes::<ClientMessage>(&buf);
+        let _ = postcard::from_bytes::<PeerMessage>(&buf);
+        let _ = postcard::from_bytes::<Signed<Envelope>>(&buf);
+        let _ = postcard::from_bytes::<ResponseFrame<Vec<u8>>>(&buf);
+    }
+});
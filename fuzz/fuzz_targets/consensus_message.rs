@@ -0,0 +1,16 @@
+#![no_main]
+
+//! Feed arbitrary bytes into the `ConsensusMessage` deserializer and the stateless checks.
+
+use libfuzzer_sys::fuzz_target;
+use pinxit::Signed;
+use prellblock::consensus::ConsensusMessage;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = postcard::from_bytes::<Signed<ConsensusMessage>>(data) {
+        let _ = message.unverified_ref().verify_invariants();
+        if let Ok(message) = message.verify() {
+            let _ = message.verify_invariants();
+        }
+    }
+});
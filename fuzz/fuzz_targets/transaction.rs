@@ -0,0 +1,16 @@
+#![no_main]
+
+//! Feed arbitrary bytes into the `Transaction` deserializer and the signature verification.
+
+use libfuzzer_sys::fuzz_target;
+use pinxit::{verify_signed_batch_iter, Signed};
+use prellblock_client_api::Transaction;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(transaction) = postcard::from_bytes::<Signed<Transaction>>(data) {
+        let _ = transaction.verify();
+    }
+    if let Ok(transactions) = postcard::from_bytes::<Vec<Signed<Transaction>>>(data) {
+        let _ = verify_signed_batch_iter(transactions.iter());
+    }
+});
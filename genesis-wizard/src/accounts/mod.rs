@@ -6,6 +6,7 @@ use pinxit::{Identity, PeerId};
 use prellblock_client_api::account::{Account, AccountType, Expiry};
 use std::{
     cmp::Reverse,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
@@ -162,7 +163,7 @@ fn handle_show_accounts(accounts: &mut Vec<AccountMeta>) {
 
 fn handle_create_account<'a>(theme: &'a dyn Theme, accounts: &mut Vec<AccountMeta>) {
     let account = Account::new("New Account".to_string());
-    let identifier = Identifier::WithIdentity(Identity::generate());
+    let identifier = Identifier::WithIdentity(Arc::new(Identity::generate()));
     if let Some((account, identifier)) =
         handle_edit_account_inner(theme, account, identifier, accounts)
     {
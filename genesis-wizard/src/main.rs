@@ -14,13 +14,17 @@ use openssl::{
     x509::X509,
 };
 use pinxit::{Identity, PeerId, Signable};
-use prellblock::RpuPrivateConfig;
+use prellblock::{
+    genesis::GenesisConfig, peer::NetworkConfig, storage::StorageConfig, turi::TuriConfig,
+    RpuPrivateConfig,
+};
 use prellblock_client_api::{
     account::{Account, AccountType, Permissions},
-    consensus::GenesisTransactions,
+    consensus::{GenesisTransactions, HashAlgorithm, SignatureList},
     transaction, Transaction,
 };
-use std::{fs, path::Path, time::SystemTime};
+use std::{fs, path::Path, sync::Arc, time::SystemTime};
+use zeroize::Zeroizing;
 
 mod accounts;
 mod certificates;
@@ -28,7 +32,8 @@ mod util;
 
 #[derive(Clone)]
 enum Identifier {
-    WithIdentity(Identity),
+    // Shared, so the private key is not copied when an account is edited.
+    WithIdentity(Arc<Identity>),
     WithPeerId(PeerId),
 }
 
@@ -104,7 +109,7 @@ fn handle_generate_private_key(theme: &'_ dyn Theme) {
     let path = util::handle_set_path(theme, "CA signing private key", "config/ca/");
     let path = format!("{}/ca.key", path);
     let path = Path::new(&path);
-    fs::write(path, key.to_hex()).unwrap();
+    fs::write(path, Zeroizing::new(key.to_hex()).as_bytes()).unwrap();
     println!(
         "Saved private key to {}.",
         path.canonicalize().unwrap().display()
@@ -113,12 +118,14 @@ fn handle_generate_private_key(theme: &'_ dyn Theme) {
 
 fn handle_finish(theme: &'_ dyn Theme, accounts: Vec<AccountMeta>, ca: Option<CA>) {
     let signing_identity = loop {
-        let identity_data = Password::with_theme(theme)
-            .with_prompt(
-                "Please enter a ed25519 private key as genesis configuration signing identity:",
-            )
-            .interact()
-            .unwrap();
+        let identity_data = Zeroizing::new(
+            Password::with_theme(theme)
+                .with_prompt(
+                    "Please enter a ed25519 private key as genesis configuration signing identity:",
+                )
+                .interact()
+                .unwrap(),
+        );
         match identity_data.parse::<Identity>() {
             Ok(identity) => break identity,
             Err(err) => {
@@ -145,7 +152,7 @@ fn handle_finish(theme: &'_ dyn Theme, accounts: Vec<AccountMeta>, ca: Option<CA
         let id = match identifier {
             Identifier::WithIdentity(identity) => {
                 let peer_id = identity.id();
-                let priv_key = identity.to_hex();
+                let priv_key = Zeroizing::new(identity.to_hex());
                 if let Err(err) = fs::create_dir(account_directory.clone()) {
                     println!("Could not create directory {}: {}", account_directory, err);
                 }
@@ -154,7 +161,7 @@ fn handle_finish(theme: &'_ dyn Theme, accounts: Vec<AccountMeta>, ca: Option<CA
                     peer_id.to_hex().clone(),
                 )
                 .unwrap();
-                fs::write(private_key_path.clone(), priv_key).unwrap();
+                fs::write(private_key_path.clone(), priv_key.as_bytes()).unwrap();
                 peer_id.clone()
             }
             Identifier::WithPeerId(peer_id) => peer_id,
@@ -175,6 +182,21 @@ fn handle_finish(theme: &'_ dyn Theme, accounts: Vec<AccountMeta>, ca: Option<CA
                 tls_id: pfx_path,
                 block_path: format!("blocks/{}", name),
                 data_path: format!("data/{}", name),
+                ban_list_path: Some(format!("ban_lists/{}", name)),
+                escrow_shares: None,
+                world_state: None,
+                queue_spill: None,
+                genesis: GenesisConfig {
+                    authority: Some(signing_identity.id().clone()),
+                    founding_rpus: false,
+                },
+                observer: None,
+                turi: TuriConfig::default(),
+                load_shedding: None,
+                disk_space: None,
+                chains: Vec::new(),
+                network: NetworkConfig::default(),
+                storage: StorageConfig::default(),
             };
             let rpu_config = toml::to_string(&rpu_config).unwrap();
             fs::write(format!("{}/{}.toml", account_directory, name), rpu_config).unwrap();
@@ -219,10 +241,22 @@ fn handle_finish(theme: &'_ dyn Theme, accounts: Vec<AccountMeta>, ca: Option<CA
         }
     }
 
-    let genesis = GenesisTransactions {
+    let hash_algorithms = [HashAlgorithm::Blake2b, HashAlgorithm::Sha256];
+    let hash_algorithm = Select::with_theme(theme)
+        .with_prompt("Which hash function should the blocks use? (This cannot be changed later.)")
+        .items(&hash_algorithms)
+        .default(0)
+        .interact()
+        .unwrap();
+
+    // The RPUs only accept the genesis transactions approved by the signing identity.
+    let mut genesis = GenesisTransactions {
         transactions,
         timestamp: SystemTime::now(),
+        hash_algorithm: hash_algorithms[hash_algorithm],
+        approvals: SignatureList::default(),
     };
+    genesis.approve(&signing_identity).unwrap();
     let path = util::handle_set_path(theme, "genesis-configuration", "config/genesis");
     fs::write(
         format!("{}/genesis.yaml", path),
@@ -16,6 +16,14 @@ pub enum Error {
     /// A `Signable` failed to create a message.
     #[error(display = "Unable to create signable message: {}", 0)]
     SignableError(BoxError),
+
+    /// An identity file could not be read.
+    #[error(display = "Unable to read identity: {}", 0)]
+    Io(#[error(from)] std::io::Error),
+
+    /// An identity is not a valid hex encoded private key.
+    #[error(display = "The identity is not a valid hex encoded private key.")]
+    InvalidIdentity,
 }
 
 impl Error {
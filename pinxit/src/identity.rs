@@ -1,10 +1,14 @@
-use crate::{Error, PeerId, Signable, Signature};
+use crate::{Error, PeerId, Signable, Signature, Signer};
 use ed25519_dalek::{ExpandedSecretKey, SecretKey};
-use std::{fmt, str};
+use std::{fmt, fs, path::Path, str};
+use zeroize::Zeroizing;
 
 const SECRET_LEN: usize = ed25519_dalek::SECRET_KEY_LENGTH;
 
 /// A cryptographic identity contains a public and private key to sign messages.
+///
+/// The private key is zeroized when the `Identity` is dropped and never shown in `Debug` output.
+/// An `Identity` cannot be cloned; share it as `Arc<dyn Signer>` instead.
 pub struct Identity {
     id: PeerId,
     secret: SecretKey,
@@ -12,16 +16,10 @@ pub struct Identity {
 
 impl fmt::Debug for Identity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("Identity").field("id", &self.id).finish()
-    }
-}
-
-impl Clone for Identity {
-    fn clone(&self) -> Self {
-        Self {
-            id: self.id.clone(),
-            secret: SecretKey::from_bytes(self.secret.as_bytes()).unwrap(),
-        }
+        f.debug_struct("Identity")
+            .field("id", &self.id)
+            .field("secret", &"<redacted>")
+            .finish()
     }
 }
 
@@ -49,6 +47,14 @@ impl Identity {
         Self::from_secret_key(secret)
     }
 
+    /// Load an identity from a file containing its hex encoded private key.
+    ///
+    /// The contents of the file are zeroized after parsing.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let data = Zeroizing::new(fs::read_to_string(path)?);
+        data.trim().parse().map_err(|_| Error::InvalidIdentity)
+    }
+
     /// Get the id of the identity.
     #[must_use]
     pub const fn id(&self) -> &PeerId {
@@ -60,9 +66,19 @@ impl Identity {
     where
         S: Signable,
     {
-        let expanded = ExpandedSecretKey::from(&self.secret);
         let data = message.signable_data().map_err(Error::signable_error)?;
-        let signature = expanded.sign(data.as_ref(), &self.id.0);
-        Ok(Signature(signature))
+        Ok(self.sign_bytes(data.as_ref()))
+    }
+}
+
+impl Signer for Identity {
+    fn id(&self) -> &PeerId {
+        &self.id
+    }
+
+    fn sign_bytes(&self, data: &[u8]) -> Signature {
+        // The expanded key is zeroized on drop as well.
+        let expanded = ExpandedSecretKey::from(&self.secret);
+        Signature(expanded.sign(data, &self.id.0))
     }
 }
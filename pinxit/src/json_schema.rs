@@ -0,0 +1,51 @@
+//! JSON schemas of the hex encoded types (with the `json-schema` feature).
+
+use crate::{PeerId, Signature};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
+
+/// The schema of a hex string encoding `len` bytes.
+fn hex_string(len: usize, description: &str) -> Schema {
+    SchemaObject {
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_string()),
+            ..Metadata::default()
+        })),
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(format!("^[0-9a-f]{{{}}}$", len * 2)),
+            ..StringValidation::default()
+        })),
+        ..SchemaObject::default()
+    }
+    .into()
+}
+
+impl JsonSchema for PeerId {
+    fn schema_name() -> String {
+        "PeerId".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            ed25519_dalek::PUBLIC_KEY_LENGTH,
+            "The public key of a peer (hex encoded).",
+        )
+    }
+}
+
+impl JsonSchema for Signature {
+    fn schema_name() -> String {
+        "Signature".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            ed25519_dalek::SIGNATURE_LENGTH,
+            "An ed25519 signature (hex encoded).",
+        )
+    }
+}
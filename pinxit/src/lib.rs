@@ -37,9 +37,12 @@
 
 mod error;
 mod identity;
+#[cfg(feature = "json-schema")]
+mod json_schema;
 mod peer_id;
 mod signable;
 mod signature;
+mod signer;
 
 pub use error::Error;
 pub use identity::Identity;
@@ -48,3 +51,4 @@ pub use signable::{
     verify_signed_batch, verify_signed_batch_iter, Signable, Signed, Verified, VerifiedRef,
 };
 pub use signature::Signature;
+pub use signer::Signer;
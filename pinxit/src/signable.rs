@@ -1,6 +1,6 @@
 #![allow(clippy::use_self)]
 
-use crate::{Error, Identity, PeerId, Signature};
+use crate::{Error, PeerId, Signature, Signer};
 use serde::{Deserialize, Serialize};
 use std::{
     error::Error as StdError,
@@ -65,12 +65,15 @@ pub trait Signable: Sized {
     /// Create a signable representation from self.
     fn signable_data(&self) -> Result<Self::SignableData, Self::Error>;
 
-    /// Sign a `Signable` message with an `identity`.
-    fn sign(self, identity: &Identity) -> Result<Signed<Self>, Error> {
-        let signer = identity.id().clone();
-        let signature = identity.sign(&self)?;
+    /// Sign a `Signable` message with a `signer` (e.g. an `Identity`).
+    fn sign<S>(self, signer: &S) -> Result<Signed<Self>, Error>
+    where
+        S: Signer + ?Sized,
+    {
+        let data = self.signable_data().map_err(Error::signable_error)?;
+        let signature = signer.sign_bytes(data.as_ref());
         Ok(Signed {
-            signer,
+            signer: signer.id().clone(),
             body: self,
             signature,
         })
@@ -90,6 +93,7 @@ where
 
 /// Wraps a message with signature.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Signed<T> {
     signer: PeerId,
     body: T,
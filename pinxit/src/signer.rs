@@ -0,0 +1,28 @@
+use crate::{PeerId, Signature};
+use std::{fmt, sync::Arc};
+
+/// A `Signer` creates signatures in the name of a `PeerId`.
+///
+/// An `Identity` cannot be cloned, so there is only one copy of its private key in memory.
+/// Components that need to sign messages share an `Arc<dyn Signer>` instead.
+/// Implementations must not reveal their private key in their `Debug` output.
+pub trait Signer: fmt::Debug + Send + Sync {
+    /// Get the id of the signer.
+    fn id(&self) -> &PeerId;
+
+    /// Create a signature of the raw `data`.
+    fn sign_bytes(&self, data: &[u8]) -> Signature;
+}
+
+impl<T> Signer for Arc<T>
+where
+    T: Signer + ?Sized,
+{
+    fn id(&self) -> &PeerId {
+        T::id(self)
+    }
+
+    fn sign_bytes(&self, data: &[u8]) -> Signature {
+        T::sign_bytes(self, data)
+    }
+}
@@ -0,0 +1,10 @@
+//! Print the JSON schema of the client API.
+//!
+//! ```sh
+//! cargo run -p prellblock-client-api --features json-schema --example json_schema
+//! ```
+
+fn main() {
+    let schema = prellblock_client_api::json_schema::client_api();
+    println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+}
@@ -0,0 +1,10 @@
+//! Print the test vectors of the encoding, hashing and signing.
+//!
+//! ```sh
+//! cargo run -p prellblock-client-api --example test_vectors
+//! ```
+
+fn main() {
+    let vectors = prellblock_client_api::test_vectors::test_vectors();
+    println!("{}", serde_json::to_string_pretty(&vectors).unwrap());
+}
@@ -6,7 +6,8 @@ use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
 /// `Account` stores data needed for permission checking.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Account {
     /// The `Account`'s name.
     pub name: String,
@@ -61,6 +62,7 @@ impl Account {
 
 /// Permission fields for a account.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct Permissions {
     /// The account type.
@@ -76,6 +78,7 @@ pub struct Permissions {
 
 /// The type of an account.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[allow(clippy::module_name_repetitions)]
 #[serde(rename_all = "snake_case")]
 pub enum AccountType {
@@ -118,6 +121,7 @@ impl Default for AccountType {
 /// assert_eq!(already_expired.is_expired(), true);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum Expiry {
     /// The permission never expires.
@@ -145,6 +149,7 @@ impl Default for Expiry {
 
 /// A `ReadingPermission` can be either a white- or a blacklist.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ReadingPermission {
     /// A `Blacklist` of permissions.
@@ -156,6 +161,7 @@ pub enum ReadingPermission {
 
 /// The right to read from specific accounts.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct ReadingRight {
     /// A black- or whitelist of accounts.
     pub accounts: Vec<PeerId>,
@@ -166,6 +172,7 @@ pub struct ReadingRight {
 
 /// A filter that can select a given scope.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Permission {
     /// The scope of this filter.
     pub scope: String,
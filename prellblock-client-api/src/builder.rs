@@ -0,0 +1,401 @@
+//! Offline construction and signing of `Transaction`s.
+
+use crate::{
+    account::Permissions,
+    consensus::{BlockHash, BlockNumber},
+    encryption::{EncryptionError, KeyReference, Keyring},
+    escrow::ShareHash,
+    metadata::Metadata,
+    parameter::Parameter,
+    quota::Quota,
+    schema::Schema,
+    transaction, Transaction,
+};
+use err_derive::Error;
+use newtype_enum::Variant;
+use pinxit::{Identity, PeerId, Signable, Signed, Signer};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// The smallest step between the timestamps of two transactions of a `TransactionBuilder`.
+///
+/// (Some platforms only support a resolution of 100ns.)
+const TIMESTAMP_STEP: Duration = Duration::from_micros(1);
+
+/// An error of the `TransactionBuilder`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A transaction could not be signed.
+    #[error(display = "{}", 0)]
+    Signing(#[error(from)] pinxit::Error),
+
+    /// A value could not be encoded.
+    #[error(display = "{}", 0)]
+    Encoding(#[error(from)] postcard::Error),
+
+    /// A value could not be encrypted.
+    #[error(display = "{}", 0)]
+    Encryption(#[error(from)] EncryptionError),
+
+    /// The batch is no longer valid.
+    #[error(display = "The batch expired at {:?}.", 0)]
+    Expired(SystemTime),
+}
+
+/// A `TransactionBuilder` constructs and signs `Transaction`s without a connection to an RPU.
+///
+/// This allows gateways to pre-sign batches on an (air-gapped) signing host and submit them later.
+/// Values are encoded the same way as by the client, so pre-signed transactions are
+/// indistinguishable from transactions signed online.
+///
+/// The timestamp of each transaction doubles as its nonce:
+/// Timestamps are strictly increasing, so two transactions with the same content
+/// still have different signatures.
+///
+/// # Example
+///
+/// ```
+/// use prellblock_client_api::TransactionBuilder;
+/// use std::time::Duration;
+///
+/// # fn test() -> Result<(), Box<dyn std::error::Error>> {
+/// let identity = "03d738c972f37a6fd9b33278ac0c50236e45637bcd5aeee82d8323655257d256".parse()?;
+/// let mut builder = TransactionBuilder::new(identity).with_ttl(Duration::from_secs(3600));
+/// builder.key_value("temperature", &21.5)?;
+/// builder.key_value("temperature", &21.5)?;
+/// let batch = builder.build();
+/// assert_eq!(batch.transactions.len(), 2);
+/// // The transactions have different timestamps and therefore different signatures.
+/// assert!(batch.transactions[0] != batch.transactions[1]);
+/// # Ok(())
+/// # }
+/// # test().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TransactionBuilder {
+    signer: Arc<dyn Signer>,
+    ttl: Option<Duration>,
+    last_timestamp: Option<SystemTime>,
+    transactions: Vec<Signed<Transaction>>,
+}
+
+impl TransactionBuilder {
+    /// Create a new `TransactionBuilder` signing with `identity`.
+    #[must_use]
+    pub fn new(identity: Identity) -> Self {
+        Self::with_signer(Arc::new(identity))
+    }
+
+    /// Create a new `TransactionBuilder` signing with a shared `signer`.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn with_signer(signer: Arc<dyn Signer>) -> Self {
+        Self {
+            signer,
+            ttl: None,
+            last_timestamp: None,
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Set the time to live of the batch (counted from the first transaction).
+    ///
+    /// The TTL is checked by the client before submitting the batch.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Add a key-value transaction.
+    pub fn key_value<V>(&mut self, key: impl Into<String>, value: &V) -> Result<&mut Self, Error>
+    where
+        V: Serialize,
+    {
+        let key = key.into();
+        let value = postcard::to_stdvec(value)?;
+        self.push(|timestamp| transaction::KeyValue {
+            key,
+            value,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction setting all keys of `values` at once (all or none are written).
+    pub fn key_values<K, V>(
+        &mut self,
+        values: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<&mut Self, Error>
+    where
+        K: Into<String>,
+        V: Serialize,
+    {
+        let values = values
+            .into_iter()
+            .map(|(key, value)| Ok((key.into(), postcard::to_stdvec(&value)?)))
+            .collect::<Result<_, Error>>()?;
+        self.push(|timestamp| transaction::MultiKeyValue { values, timestamp })
+    }
+
+    /// Add a transaction setting the shared `key` to `new` if its value is `expected` when committed.
+    pub fn compare_and_swap<V>(
+        &mut self,
+        key: impl Into<String>,
+        expected: Option<&V>,
+        new: Option<&V>,
+    ) -> Result<&mut Self, Error>
+    where
+        V: Serialize,
+    {
+        let key = key.into();
+        let expected = expected.map(postcard::to_stdvec).transpose()?;
+        let new = new.map(postcard::to_stdvec).transpose()?;
+        self.push(|timestamp| transaction::CompareAndSwap {
+            key,
+            expected,
+            new,
+            timestamp,
+        })
+    }
+
+    /// Add a key-value transaction with `metadata` describing the value.
+    pub fn key_value_with_metadata<V>(
+        &mut self,
+        key: impl Into<String>,
+        value: &V,
+        metadata: Metadata,
+    ) -> Result<&mut Self, Error>
+    where
+        V: Serialize,
+    {
+        let key = key.into();
+        let value = postcard::to_stdvec(value)?;
+        self.push(|timestamp| transaction::KeyValueWithMetadata {
+            key,
+            value,
+            metadata,
+            timestamp,
+        })
+    }
+
+    /// Add a key-value transaction with the value encrypted by a key of the `keyring`.
+    pub fn encrypted_key_value<V>(
+        &mut self,
+        key: impl Into<String>,
+        value: &V,
+        keyring: &Keyring,
+    ) -> Result<&mut Self, Error>
+    where
+        V: Serialize,
+    {
+        let key = key.into();
+        let value = keyring.encrypt(&key, &postcard::to_stdvec(value)?)?;
+        self.push(|timestamp| transaction::EncryptedKeyValue {
+            key,
+            value,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction updating a `target` account's `permissions`.
+    pub fn update_account(
+        &mut self,
+        target: PeerId,
+        permissions: Permissions,
+    ) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::UpdateAccount {
+            id: target,
+            permissions,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction creating a new `account` with `permissions`.
+    pub fn create_account(
+        &mut self,
+        account: PeerId,
+        name: impl Into<String>,
+        permissions: Permissions,
+    ) -> Result<&mut Self, Error> {
+        let name = name.into();
+        self.push(|timestamp| transaction::CreateAccount {
+            id: account,
+            name,
+            permissions,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction deleting an `account`.
+    pub fn delete_account(&mut self, account: PeerId) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::DeleteAccount {
+            id: account,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction setting (or removing) the `schema` of a key `namespace`.
+    pub fn set_schema(
+        &mut self,
+        namespace: impl Into<String>,
+        schema: Option<Schema>,
+    ) -> Result<&mut Self, Error> {
+        let namespace = namespace.into();
+        self.push(|timestamp| transaction::SetSchema {
+            namespace,
+            schema,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction scheduling the migration of the `WorldState` to a format `version`.
+    pub fn schedule_migration(
+        &mut self,
+        version: u32,
+        activation_height: BlockNumber,
+    ) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::ScheduleMigration {
+            version,
+            activation_height,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction setting a governed `parameter` from `activation_height` on.
+    pub fn set_parameter(
+        &mut self,
+        parameter: Parameter,
+        value: u64,
+        activation_height: BlockNumber,
+    ) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::SetParameter {
+            parameter,
+            value,
+            activation_height,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction setting the leader `weight` of the RPU `id`.
+    pub fn set_leader_weight(&mut self, id: PeerId, weight: u32) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::SetLeaderWeight {
+            id,
+            weight,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction setting the queue `weight` of the account `id`.
+    pub fn set_queue_weight(&mut self, id: PeerId, weight: u32) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::SetQueueWeight {
+            id,
+            weight,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction setting (or removing) the `quota` of a key `namespace`.
+    pub fn set_quota(
+        &mut self,
+        namespace: impl Into<String>,
+        quota: Option<Quota>,
+    ) -> Result<&mut Self, Error> {
+        let namespace = namespace.into();
+        self.push(|timestamp| transaction::SetQuota {
+            namespace,
+            quota,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction escrowing a key by the hashes of its shares held by the RPUs.
+    pub fn escrow_key(
+        &mut self,
+        key_reference: KeyReference,
+        key_hash: BlockHash,
+        threshold: usize,
+        share_hashes: Vec<(PeerId, ShareHash)>,
+    ) -> Result<&mut Self, Error> {
+        self.push(|timestamp| transaction::EscrowKey {
+            key_reference,
+            key_hash,
+            threshold,
+            share_hashes,
+            timestamp,
+        })
+    }
+
+    /// Add a transaction authorizing an auditor to collect the shares of an escrowed key.
+    ///
+    /// The `authorization` keeps its timestamp, because the approvals of the RPUs cover it.
+    pub fn authorize_decryption(
+        &mut self,
+        authorization: transaction::AuthorizeDecryption,
+    ) -> Result<&mut Self, Error> {
+        let transaction = Transaction::from_variant(authorization);
+        self.transactions.push(transaction.sign(&self.signer)?);
+        Ok(self)
+    }
+
+    /// Finish the batch.
+    #[must_use]
+    pub fn build(self) -> SignedBatch {
+        let expires_at = match (self.ttl, self.transactions.first()) {
+            (Some(ttl), Some(first)) => Some(first.unverified_ref().timestamp() + ttl),
+            _ => None,
+        };
+        SignedBatch {
+            transactions: self.transactions,
+            expires_at,
+        }
+    }
+
+    fn push<T>(&mut self, create: impl FnOnce(SystemTime) -> T) -> Result<&mut Self, Error>
+    where
+        T: Variant<Transaction>,
+    {
+        let timestamp = self.next_timestamp();
+        let transaction = Transaction::from_variant(create(timestamp));
+        self.transactions.push(transaction.sign(&self.signer)?);
+        Ok(self)
+    }
+
+    /// Get a timestamp that is later than all previous ones.
+    fn next_timestamp(&mut self) -> SystemTime {
+        let mut timestamp = SystemTime::now();
+        if let Some(last_timestamp) = self.last_timestamp {
+            if timestamp <= last_timestamp {
+                timestamp = last_timestamp + TIMESTAMP_STEP;
+            }
+        }
+        self.last_timestamp = Some(timestamp);
+        timestamp
+    }
+}
+
+/// A batch of pre-signed `Transaction`s.
+///
+/// It can be stored (e.g. as a file) and submitted later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SignedBatch {
+    /// The signed transactions.
+    pub transactions: Vec<Signed<Transaction>>,
+    /// The point in time after which the batch must not be submitted anymore.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl SignedBatch {
+    /// Verify that the batch has not expired yet.
+    pub fn verify_not_expired(&self) -> Result<(), Error> {
+        match self.expires_at {
+            Some(expires_at) if expires_at < SystemTime::now() => Err(Error::Expired(expires_at)),
+            _ => Ok(()),
+        }
+    }
+}
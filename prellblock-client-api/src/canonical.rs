@@ -0,0 +1,46 @@
+//! Canonical serialization of structures that are hashed or signed.
+//!
+//! Hashes and signatures are calculated over the postcard encoding of a value.
+//! The iteration order of a hash map is unspecified (and differs between RPUs),
+//! so maps in hashed or signed structures must be serialized with `serialize_map`.
+
+use serde::ser::{Error, Serialize, Serializer};
+
+/// Serialize a map with its entries sorted by the encoding of their keys.
+///
+/// The result is a regular map, so it can be deserialized by any map type.
+/// Use it with `#[serde(serialize_with = "canonical::serialize_map")]`.
+///
+/// # Example
+///
+/// ```
+/// use prellblock_client_api::canonical;
+/// use std::collections::HashMap;
+///
+/// #[derive(serde::Serialize)]
+/// struct Values(#[serde(serialize_with = "canonical::serialize_map")] HashMap<String, u8>);
+///
+/// let mut values = HashMap::new();
+/// values.insert("b".to_string(), 2);
+/// values.insert("a".to_string(), 1);
+///
+/// let data = postcard::to_stdvec(&Values(values)).unwrap();
+/// assert_eq!(data, [2, 1, b'a', 1, 1, b'b', 2]);
+/// ```
+pub fn serialize_map<'a, M, K, V, S>(map: &'a M, serializer: S) -> Result<S::Ok, S::Error>
+where
+    &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: Serialize + 'a,
+    V: Serialize + 'a,
+    S: Serializer,
+{
+    let mut entries = map
+        .into_iter()
+        .map(|(key, value)| {
+            let encoded_key = postcard::to_stdvec(key).map_err(S::Error::custom)?;
+            Ok((encoded_key, key, value))
+        })
+        .collect::<Result<Vec<_>, S::Error>>()?;
+    entries.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+    serializer.collect_map(entries.into_iter().map(|(_, key, value)| (key, value)))
+}
@@ -2,14 +2,25 @@ use super::{BlockNumber, LeaderTerm, SignatureList};
 use crate::Transaction;
 use blake2::{
     digest::{generic_array::typenum::Unsigned, FixedOutput},
-    Blake2b, Digest,
+    Blake2b, Digest as _,
 };
+use hexutil::FromHexError;
 use pinxit::Signed;
-use serde::{Deserialize, Serialize};
-use std::{fmt, time::SystemTime};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use sha2::Sha256;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    time::SystemTime,
+};
 
 /// A `Block` stores transactions verified by the blockchain.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Block {
     /// The `Body` of a block. (Everything that is signed)
     pub body: Body,
@@ -34,6 +45,7 @@ impl Block {
 /// The `Body` of a `Block` stores the Block number (height in chain), the Hash of the previous `Block`
 /// and an Array of the actual `Transaction`s with their corresponding Signature in the `Block`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Body {
     /// The `LeaderTerm` of the `Block`.
     pub leader_term: LeaderTerm,
@@ -53,24 +65,163 @@ pub struct Body {
 
 impl Body {
     /// Calculate the hash of the blocks body.
+    ///
+    /// The hash function is the one of the previous block hash,
+    /// so all blocks of a chain use the hash function chosen at genesis (see `BlockHash::zero`).
     #[must_use]
     pub fn hash(&self) -> BlockHash {
         let val = postcard::to_stdvec(self).unwrap();
+        self.prev_block_hash.algorithm().hash(&val)
+    }
+}
+
+const BLAKE2B_SIZE: usize = <Blake2b as FixedOutput>::OutputSize::USIZE;
+const SHA256_SIZE: usize = <Sha256 as FixedOutput>::OutputSize::USIZE;
 
-        let result = Blake2b::digest(&val);
+/// A hash function for blocks, chosen for a chain at genesis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// Blake2b with 64 bytes of output (the default).
+    Blake2b,
+    /// SHA-256 with 32 bytes of output (required by some compliance regimes).
+    Sha256,
+}
 
-        let mut body_hash = BlockHash([0; HASH_SIZE]);
-        body_hash.0.copy_from_slice(&result);
-        body_hash
+impl HashAlgorithm {
+    /// Calculate the hash of arbitrary `data` with this hash function.
+    #[must_use]
+    pub fn hash(self, data: &[u8]) -> BlockHash {
+        match self {
+            Self::Blake2b => {
+                let mut hash = [0; BLAKE2B_SIZE];
+                hash.copy_from_slice(&Blake2b::digest(data));
+                BlockHash(Digest::Blake2b(Blake2bDigest(hash)))
+            }
+            Self::Sha256 => {
+                let mut hash = [0; SHA256_SIZE];
+                hash.copy_from_slice(&Sha256::digest(data));
+                BlockHash(Digest::Sha256(Sha256Digest(hash)))
+            }
+        }
+    }
+
+    /// Get the number of bytes of the hashes.
+    #[must_use]
+    pub const fn output_size(self) -> usize {
+        match self {
+            Self::Blake2b => BLAKE2B_SIZE,
+            Self::Sha256 => SHA256_SIZE,
+        }
     }
 }
 
-const HASH_SIZE: usize = <Blake2b as FixedOutput>::OutputSize::USIZE;
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        Self::Blake2b
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Blake2b => "blake2b",
+            Self::Sha256 => "sha256",
+        })
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blake2b" => Ok(Self::Blake2b),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(format!(
+                "Unknown hash algorithm {:?} (expected blake2b or sha256).",
+                s
+            )),
+        }
+    }
+}
 
 /// The datatype of hashes of blocks is `BlockHash`.
+///
+/// The hash function is part of the hash and is given by its length:
+/// 64 bytes for Blake2b and 32 bytes for SHA-256.
 #[derive(Copy, Clone)]
 #[allow(clippy::module_name_repetitions)]
-pub struct BlockHash([u8; HASH_SIZE]);
+pub struct BlockHash(Digest);
+
+#[derive(Copy, Clone)]
+enum Digest {
+    Blake2b(Blake2bDigest),
+    Sha256(Sha256Digest),
+}
+
+#[derive(Copy, Clone)]
+struct Blake2bDigest([u8; BLAKE2B_SIZE]);
+
+#[derive(Copy, Clone)]
+struct Sha256Digest([u8; SHA256_SIZE]);
+
+impl BlockHash {
+    /// Calculate the Blake2b hash of arbitrary `data`.
+    ///
+    /// This is used for everything that is not bound to a chain (e.g. transactions and snapshots),
+    /// blocks are hashed with the hash function of their chain (see `Body::hash`).
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        HashAlgorithm::Blake2b.hash(data)
+    }
+
+    /// Get the hash with all bytes zero for the `algorithm`.
+    ///
+    /// This is the previous block hash of a genesis block,
+    /// which selects the hash function of the chain (and of its `ChainId`).
+    #[must_use]
+    pub const fn zero(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake2b => Self(Digest::Blake2b(Blake2bDigest([0; BLAKE2B_SIZE]))),
+            HashAlgorithm::Sha256 => Self(Digest::Sha256(Sha256Digest([0; SHA256_SIZE]))),
+        }
+    }
+
+    /// Get the hash function of the hash.
+    #[must_use]
+    pub const fn algorithm(&self) -> HashAlgorithm {
+        match self.0 {
+            Digest::Blake2b(_) => HashAlgorithm::Blake2b,
+            Digest::Sha256(_) => HashAlgorithm::Sha256,
+        }
+    }
+
+    /// Get the bytes of the hash.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Digest::Blake2b(digest) => &digest.0,
+            Digest::Sha256(digest) => &digest.0,
+        }
+    }
+
+    /// Create a hash from its `bytes`, the hash function is chosen by their length.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() == BLAKE2B_SIZE {
+            let mut hash = [0; BLAKE2B_SIZE];
+            hash.copy_from_slice(bytes);
+            Some(Self(Digest::Blake2b(Blake2bDigest(hash))))
+        } else if bytes.len() == SHA256_SIZE {
+            let mut hash = [0; SHA256_SIZE];
+            hash.copy_from_slice(bytes);
+            Some(Self(Digest::Sha256(Sha256Digest(hash))))
+        } else {
+            None
+        }
+    }
+}
 
 impl fmt::Debug for BlockHash {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -78,20 +229,110 @@ impl fmt::Debug for BlockHash {
     }
 }
 
+impl fmt::Display for BlockHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Digest::Blake2b(digest) => fmt::Display::fmt(digest, f),
+            Digest::Sha256(digest) => fmt::Display::fmt(digest, f),
+        }
+    }
+}
+
+impl FromStr for BlockHash {
+    type Err = FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() == SHA256_SIZE * 2 {
+            Ok(Self(Digest::Sha256(s.parse()?)))
+        } else {
+            Ok(Self(Digest::Blake2b(s.parse()?)))
+        }
+    }
+}
+
 impl Default for BlockHash {
     fn default() -> Self {
-        Self([0; HASH_SIZE])
+        Self::zero(HashAlgorithm::default())
     }
 }
 
 impl PartialEq for BlockHash {
     fn eq(&self, other: &Self) -> bool {
-        self.0[..] == other.0[..]
+        self.algorithm() == other.algorithm() && self.as_bytes() == other.as_bytes()
     }
 }
 
 impl Eq for BlockHash {}
 
-hexutil::impl_hex!(BlockHash, HASH_SIZE, |&self| &self.0, |data| {
+impl Hash for BlockHash {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+// A Blake2b hash is encoded exactly like before there was a choice of hash functions.
+impl Serialize for BlockHash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.0 {
+            Digest::Blake2b(digest) => digest.serialize(serializer),
+            Digest::Sha256(digest) => digest.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let hex = String::deserialize(deserializer)?;
+            hex.parse().map_err(de::Error::custom)
+        } else {
+            deserializer.deserialize_bytes(BytesVisitor)
+        }
+    }
+}
+
+/// Deserializes the bytes of a `BlockHash`.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = BlockHash;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} or {} bytes", BLAKE2B_SIZE, SHA256_SIZE)
+    }
+
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        BlockHash::from_bytes(bytes).ok_or_else(|| E::invalid_length(bytes.len(), &self))
+    }
+}
+
+hexutil::impl_hex!(Blake2bDigest, BLAKE2B_SIZE, |&self| &self.0, |data| {
     Ok(Self(data))
 });
+
+hexutil::impl_hex!(Sha256Digest, SHA256_SIZE, |&self| &self.0, |data| {
+    Ok(Self(data))
+});
+
+#[cfg(feature = "json-schema")]
+impl schemars::JsonSchema for BlockHash {
+    fn schema_name() -> String {
+        "BlockHash".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::json_schema::hex_string_of_lengths(
+            &[BLAKE2B_SIZE, SHA256_SIZE],
+            "A blake2b or sha256 hash (hex encoded).",
+        )
+    }
+}
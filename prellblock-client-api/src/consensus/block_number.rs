@@ -1,13 +1,20 @@
 use serde::{Deserialize, Serialize};
 use std::{
     fmt,
+    num::ParseIntError,
     ops::{Add, AddAssign, Sub, SubAssign},
+    str::FromStr,
 };
 
 /// Number of the Block in the Blockchain.
+///
+/// The arithmetic operators panic on overflow (also in release builds),
+/// so a block number can never wrap around silently.
+/// Use the `checked_*` or `saturating_*` methods where an overflow is expected.
 #[derive(
     Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
 )]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct BlockNumber(u64);
 
 impl BlockNumber {
@@ -18,10 +25,49 @@ impl BlockNumber {
     }
 
     /// Return the stored integer as a byte array.
+    ///
+    /// The byte arrays sort in the same order as the block numbers (e.g. as database keys).
     #[must_use]
     pub fn to_be_bytes(self) -> impl AsRef<[u8]> {
         self.0.to_be_bytes()
     }
+
+    /// Read a block number from a byte array created by `to_be_bytes`.
+    ///
+    /// Returns `None` if the byte array has the wrong length.
+    #[must_use]
+    pub fn from_be_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut array = [0; 8];
+        if bytes.len() != array.len() {
+            return None;
+        }
+        array.copy_from_slice(bytes);
+        Some(Self(u64::from_be_bytes(array)))
+    }
+
+    /// Add `other` blocks, returning `None` on overflow.
+    #[must_use]
+    pub fn checked_add(self, other: u64) -> Option<Self> {
+        self.0.checked_add(other).map(Self)
+    }
+
+    /// Subtract `other` blocks, returning `None` on underflow.
+    #[must_use]
+    pub fn checked_sub(self, other: u64) -> Option<Self> {
+        self.0.checked_sub(other).map(Self)
+    }
+
+    /// Add `other` blocks, stopping at the maximum block number.
+    #[must_use]
+    pub const fn saturating_add(self, other: u64) -> Self {
+        Self(self.0.saturating_add(other))
+    }
+
+    /// Subtract `other` blocks, stopping at block #0.
+    #[must_use]
+    pub const fn saturating_sub(self, other: u64) -> Self {
+        Self(self.0.saturating_sub(other))
+    }
 }
 
 impl fmt::Display for BlockNumber {
@@ -30,29 +76,36 @@ impl fmt::Display for BlockNumber {
     }
 }
 
+impl FromStr for BlockNumber {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
 impl Add<u64> for BlockNumber {
     type Output = Self;
     fn add(self, other: u64) -> Self {
-        Self(self.0 + other)
+        self.checked_add(other).expect("block number overflow")
     }
 }
 
 impl AddAssign<u64> for BlockNumber {
     fn add_assign(&mut self, other: u64) {
-        self.0 += other;
+        *self = *self + other;
     }
 }
 
 impl Sub<u64> for BlockNumber {
     type Output = Self;
     fn sub(self, other: u64) -> Self {
-        Self(self.0 - other)
+        self.checked_sub(other).expect("block number underflow")
     }
 }
 
 impl SubAssign<u64> for BlockNumber {
     fn sub_assign(&mut self, other: u64) {
-        self.0 -= other;
+        *self = *self - other;
     }
 }
 
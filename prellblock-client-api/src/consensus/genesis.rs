@@ -0,0 +1,123 @@
+//! The genesis transactions that start a chain.
+//!
+//! The genesis transactions are distributed to the RPUs of a new chain as a file.
+//! A bootstrap authority (or each founding RPU) approves them by signing the `ChainId`,
+//! so an RPU only starts a chain that was approved (see `GenesisTransactions::verify_approvals`).
+
+use super::{proof::SignedData, Block, BlockHash, BlockNumber, Body, ChainId, HashAlgorithm};
+use super::{LeaderTerm, SignatureList, SignatureListError};
+use crate::{account::AccountType, Transaction};
+use err_derive::Error;
+use pinxit::{PeerId, Signed, Signer};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// The tags of a genesis approval in the signed data (distinct from all consensus messages).
+const GENESIS_APPROVAL_TAGS: (u8, u8) = (2, 1);
+
+/// An error while verifying the approvals of `GenesisTransactions`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum GenesisError {
+    /// An approval is not a valid signature of the `ChainId`.
+    #[error(display = "The approval of {} is invalid: {}", 0, 1)]
+    InvalidApproval(PeerId, pinxit::Error),
+
+    /// A required approval is missing.
+    #[error(display = "The genesis transactions are not approved by {}.", 0)]
+    MissingApproval(PeerId),
+}
+
+/// The first block in the chain, just a list of `Transaction`s.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GenesisTransactions {
+    /// The transactions in the genesis block.
+    pub transactions: Vec<Signed<Transaction>>,
+    /// The timestamp of genesis block creation.
+    pub timestamp: SystemTime,
+    /// The hash function of the blocks of the chain.
+    ///
+    /// It cannot be changed later, because it is part of the `ChainId`.
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    /// The signatures of the `ChainId` by the bootstrap authority or the founding RPUs.
+    ///
+    /// The approvals are not part of the genesis block.
+    #[serde(default, skip_serializing_if = "SignatureList::is_empty")]
+    pub approvals: SignatureList,
+}
+
+impl GenesisTransactions {
+    /// Create the genesis block of the chain.
+    #[must_use]
+    pub fn block(&self) -> Block {
+        Block {
+            body: Body {
+                leader_term: LeaderTerm::default(),
+                height: BlockNumber::default(),
+                prev_block_hash: BlockHash::zero(self.hash_algorithm),
+                timestamp: self.timestamp,
+                transactions: self.transactions.clone(),
+            },
+            signatures: SignatureList::default(),
+        }
+    }
+
+    /// Get the `ChainId` of the chain started by the genesis transactions.
+    #[must_use]
+    pub fn chain_id(&self) -> ChainId {
+        self.block().hash()
+    }
+
+    /// Create the data signed to approve the genesis transactions of the chain `chain_id`.
+    #[must_use]
+    pub fn approval_data(chain_id: ChainId) -> Vec<u8> {
+        postcard::to_stdvec(&(GENESIS_APPROVAL_TAGS, chain_id)).unwrap()
+    }
+
+    /// Approve the genesis transactions with the `signer` (e.g. the bootstrap authority).
+    ///
+    /// Changing the genesis transactions afterwards invalidates all approvals.
+    pub fn approve<S>(&mut self, signer: &S) -> Result<(), SignatureListError>
+    where
+        S: Signer + ?Sized,
+    {
+        let signature = signer.sign_bytes(&Self::approval_data(self.chain_id()));
+        self.approvals.insert(signer.id().clone(), signature)
+    }
+
+    /// Verify that all approvals are valid and each of the `required` peers approved.
+    pub fn verify_approvals<'a>(
+        &self,
+        required: impl IntoIterator<Item = &'a PeerId>,
+    ) -> Result<(), GenesisError> {
+        let data = Self::approval_data(self.chain_id());
+        for (peer_id, signature) in &self.approvals {
+            peer_id
+                .verify(SignedData(&data), signature)
+                .map_err(|err| GenesisError::InvalidApproval(peer_id.clone(), err))?;
+        }
+        for peer_id in required {
+            if !self.approvals.contains(peer_id) {
+                return Err(GenesisError::MissingApproval(peer_id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the founding RPUs (the RPU accounts created by the genesis transactions).
+    #[must_use]
+    pub fn founding_rpus(&self) -> Vec<PeerId> {
+        self.transactions
+            .iter()
+            .filter_map(|transaction| match transaction.unverified_ref() {
+                Transaction::CreateAccount(params) => match params.permissions.account_type {
+                    Some(AccountType::RPU { .. }) => Some(params.id.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+}
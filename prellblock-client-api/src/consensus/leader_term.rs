@@ -1,29 +1,71 @@
 use serde::{Deserialize, Serialize};
 use std::{
     fmt,
+    num::ParseIntError,
     ops::{Add, AddAssign},
+    str::FromStr,
 };
 
 /// Number indicating the current Leader.
+///
+/// The arithmetic operators panic on overflow (also in release builds),
+/// so a leader term can never wrap around silently.
+/// Use the `checked_*` or `saturating_*` methods where an overflow is expected.
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct LeaderTerm(u64);
 
+impl LeaderTerm {
+    /// Create a new leader term.
+    #[must_use]
+    pub const fn new(v: u64) -> Self {
+        Self(v)
+    }
+
+    /// Return the stored integer as a byte array.
+    ///
+    /// The byte arrays sort in the same order as the leader terms (e.g. as database keys).
+    #[must_use]
+    pub fn to_be_bytes(self) -> impl AsRef<[u8]> {
+        self.0.to_be_bytes()
+    }
+
+    /// Add `other` terms, returning `None` on overflow.
+    #[must_use]
+    pub fn checked_add(self, other: u64) -> Option<Self> {
+        self.0.checked_add(other).map(Self)
+    }
+
+    /// Add `other` terms, stopping at the maximum leader term.
+    #[must_use]
+    pub const fn saturating_add(self, other: u64) -> Self {
+        Self(self.0.saturating_add(other))
+    }
+}
+
 impl fmt::Display for LeaderTerm {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.0.fmt(f)
     }
 }
 
+impl FromStr for LeaderTerm {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
 impl Add<u64> for LeaderTerm {
     type Output = Self;
     fn add(self, other: u64) -> Self {
-        Self(self.0 + other)
+        self.checked_add(other).expect("leader term overflow")
     }
 }
 
 impl AddAssign<u64> for LeaderTerm {
     fn add_assign(&mut self, other: u64) {
-        self.0 += other
+        *self = *self + other;
     }
 }
 
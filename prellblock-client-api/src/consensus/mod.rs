@@ -1,24 +1,31 @@
 //! Consensus abstractions
 
 use pinxit::Signed;
-use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
 
 mod block;
 mod block_number;
+mod genesis;
 mod leader_term;
+mod proof;
 mod signature_list;
 
-pub use block::{Block, BlockHash, Body};
+pub use block::{Block, BlockHash, Body, HashAlgorithm};
 pub use block_number::BlockNumber;
+pub use genesis::{GenesisError, GenesisTransactions};
 pub use leader_term::LeaderTerm;
-pub use signature_list::SignatureList;
+pub(crate) use proof::SignedData;
+pub use proof::{BlockProof, BlockProofError, CertifiedHead};
+pub use signature_list::{SignatureList, SignatureListError};
 
-/// The first block in the chain, just a list of `Transaction`s.
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GenesisTransactions {
-    /// The transactions in the genesis block.
-    pub transactions: Vec<Signed<super::Transaction>>,
-    /// The timestamp of genesis block creation.
-    pub timestamp: SystemTime,
+/// The id of a chain is the hash of its genesis block.
+pub type ChainId = BlockHash;
+
+/// The hash of a signed `Transaction` identifies it (e.g. in the queue of an RPU).
+pub type TransactionHash = BlockHash;
+
+/// Calculate the `TransactionHash` of a signed `transaction`.
+#[must_use]
+pub fn transaction_hash(transaction: &Signed<super::Transaction>) -> TransactionHash {
+    let val = postcard::to_stdvec(transaction).unwrap();
+    BlockHash::of(&val)
 }
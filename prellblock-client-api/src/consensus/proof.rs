@@ -0,0 +1,153 @@
+use super::{Block, BlockHash, BlockNumber, LeaderTerm, SignatureList};
+use err_derive::Error;
+use pinxit::{PeerId, Signable};
+use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, time::SystemTime};
+
+/// The tags of the `AckAppend` response in the signed data of the consensus.
+const ACKAPPEND_TAGS: (u8, u8) = (1, 1);
+
+/// The tags of the `HeadAck` response in the signed data of the consensus.
+const HEAD_ACK_TAGS: (u8, u8) = (1, 11);
+
+/// An error while verifying a `BlockProof` (or a `CertifiedHead`).
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BlockProofError {
+    /// The proof belongs to another block.
+    #[error(display = "The proof does not belong to the block.")]
+    BlockMismatch,
+
+    /// The signed data is not the `AckAppend` message of the block (or the `HeadAck` message of the head).
+    #[error(display = "The signed data is not the AckAppend message of the block.")]
+    SignedDataMismatch,
+
+    /// A signature is invalid.
+    #[error(display = "The signature of {} is invalid: {}", 0, 1)]
+    InvalidSignature(PeerId, pinxit::Error),
+}
+
+/// The proof which RPUs accepted a `Block`.
+///
+/// Each RPU accepts a block by signing the same `AckAppend` message.
+/// The proof contains this message (exactly as it was signed) and the detached signatures,
+/// so anyone can verify the signatures without knowing the internals of the consensus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct BlockProof {
+    /// The leader term the block was committed in.
+    pub leader_term: LeaderTerm,
+    /// The number of the block.
+    pub block_number: BlockNumber,
+    /// The hash of the block.
+    pub block_hash: BlockHash,
+    /// The data signed by each RPU (the encoded `AckAppend` message).
+    pub signed_data: Vec<u8>,
+    /// The signatures of the RPUs.
+    pub signatures: SignatureList,
+}
+
+impl BlockProof {
+    /// Create the `AckAppend` message signed by the RPUs to accept the block.
+    #[must_use]
+    pub fn ackappend_data(
+        leader_term: LeaderTerm,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+    ) -> Vec<u8> {
+        postcard::to_stdvec(&(ACKAPPEND_TAGS, leader_term, block_number, block_hash)).unwrap()
+    }
+
+    /// Verify that the proof belongs to `block` and all signatures are valid.
+    ///
+    /// Returns the RPUs that vouched for the block.
+    /// Whether they are (or were) RPUs of the chain needs to be checked separately.
+    pub fn verify(&self, block: &Block) -> Result<Vec<PeerId>, BlockProofError> {
+        if self.leader_term != block.body.leader_term
+            || self.block_number != block.block_number()
+            || self.block_hash != block.hash()
+        {
+            return Err(BlockProofError::BlockMismatch);
+        }
+
+        let expected = Self::ackappend_data(self.leader_term, self.block_number, self.block_hash);
+        if self.signed_data != expected {
+            return Err(BlockProofError::SignedDataMismatch);
+        }
+
+        self.signatures
+            .into_iter()
+            .map(|(peer_id, signature)| {
+                peer_id
+                    .verify(SignedData(&self.signed_data), signature)
+                    .map(|()| peer_id.clone())
+                    .map_err(|err| BlockProofError::InvalidSignature(peer_id.clone(), err))
+            })
+            .collect()
+    }
+}
+
+/// The head of the chain certified by a supermajority of the RPUs.
+///
+/// Each RPU signs the same `HeadAck` message stating that the block is the head of its chain
+/// at `certified_at` (the time the certificate was requested).
+/// The proof contains this message (exactly as it was signed) and the detached signatures,
+/// so a single RPU can answer "what is the latest block?" in a way clients can trust.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct CertifiedHead {
+    /// The number of the head block.
+    pub block_number: BlockNumber,
+    /// The hash of the head block.
+    pub block_hash: BlockHash,
+    /// The time the RPUs confirmed the head.
+    pub certified_at: SystemTime,
+    /// The data signed by each RPU (the encoded `HeadAck` message).
+    pub signed_data: Vec<u8>,
+    /// The signatures of the RPUs.
+    pub signatures: SignatureList,
+}
+
+impl CertifiedHead {
+    /// Create the `HeadAck` message signed by the RPUs to confirm the head.
+    #[must_use]
+    pub fn head_ack_data(
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        certified_at: SystemTime,
+    ) -> Vec<u8> {
+        postcard::to_stdvec(&(HEAD_ACK_TAGS, block_number, block_hash, certified_at)).unwrap()
+    }
+
+    /// Verify that all signatures are valid.
+    ///
+    /// Returns the RPUs that confirmed the head.
+    /// Whether they are RPUs of the chain (and a supermajority of them) needs to be checked separately.
+    pub fn verify(&self) -> Result<Vec<PeerId>, BlockProofError> {
+        let expected = Self::head_ack_data(self.block_number, self.block_hash, self.certified_at);
+        if self.signed_data != expected {
+            return Err(BlockProofError::SignedDataMismatch);
+        }
+
+        self.signatures
+            .into_iter()
+            .map(|(peer_id, signature)| {
+                peer_id
+                    .verify(SignedData(&self.signed_data), signature)
+                    .map(|()| peer_id.clone())
+                    .map_err(|err| BlockProofError::InvalidSignature(peer_id.clone(), err))
+            })
+            .collect()
+    }
+}
+
+/// Raw data that was signed.
+pub(crate) struct SignedData<'a>(&'a [u8]);
+
+impl<'a> Signable for SignedData<'a> {
+    type SignableData = &'a [u8];
+    type Error = Infallible;
+    fn signable_data(&self) -> Result<Self::SignableData, Self::Error> {
+        Ok(self.0)
+    }
+}
@@ -1,13 +1,27 @@
+use crate::limits;
+use err_derive::Error;
 use pinxit::{PeerId, Signature};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, iter::FromIterator};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
 type SignatureListItem = (PeerId, Signature);
-type SignatureListItemRef<'a> = (&'a PeerId, &'a Signature);
 type SignatureListVec = Vec<SignatureListItem>;
 
+/// An error while inserting a signature into a `SignatureList`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SignatureListError {
+    /// The peer already signed.
+    #[error(display = "The peer {} signed more than once.", 0)]
+    DuplicateSigner(PeerId),
+}
+
 /// A list of `PeerId`s and `Signature`s.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+///
+/// Each peer can sign at most once. The signatures are sorted by `PeerId`,
+/// so the list is serialized the same way regardless of the order the signatures arrived in.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct SignatureList(SignatureListVec);
 
 impl SignatureList {
@@ -22,16 +36,33 @@ impl SignatureList {
         self.0.is_empty()
     }
 
-    /// Push a `SignatureListItem` to the `SignatureList`.
-    pub fn push(&mut self, item: SignatureListItem) {
-        self.0.push(item);
+    /// Check whether `peer_id` signed.
+    #[must_use]
+    pub fn contains(&self, peer_id: &PeerId) -> bool {
+        self.position(peer_id).is_ok()
     }
 
-    /// Verify that all signatures in the list are from distinct peers.
-    #[must_use]
-    pub fn is_unique(&self) -> bool {
-        let mut set = HashSet::new();
-        self.0.iter().all(|(peer_id, _)| set.insert(peer_id))
+    /// Insert the `signature` of `peer_id`.
+    ///
+    /// Fails if the peer already signed.
+    pub fn insert(
+        &mut self,
+        peer_id: PeerId,
+        signature: Signature,
+    ) -> Result<(), SignatureListError> {
+        match self.position(&peer_id) {
+            Ok(_) => Err(SignatureListError::DuplicateSigner(peer_id)),
+            Err(index) => {
+                self.0.insert(index, (peer_id, signature));
+                Ok(())
+            }
+        }
+    }
+
+    /// Find the index of `peer_id` (or where it would be inserted).
+    fn position(&self, peer_id: &PeerId) -> Result<usize, usize> {
+        self.0
+            .binary_search_by(|(other, _)| other.as_bytes().cmp(peer_id.as_bytes()))
     }
 }
 
@@ -44,22 +75,29 @@ impl<'a> IntoIterator for &'a SignatureList {
     }
 }
 
-impl FromIterator<SignatureListItem> for SignatureList {
-    fn from_iter<T>(iter: T) -> Self
-    where
-        T: IntoIterator<Item = SignatureListItem>,
-    {
-        Self(Vec::from_iter(iter))
+/// Signatures collected in a `HashMap` are unique by construction.
+impl From<HashMap<PeerId, Signature>> for SignatureList {
+    fn from(signatures: HashMap<PeerId, Signature>) -> Self {
+        let mut signatures: SignatureListVec = signatures.into_iter().collect();
+        signatures.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+        Self(signatures)
     }
 }
 
-impl<'a> FromIterator<SignatureListItemRef<'a>> for SignatureList {
-    fn from_iter<T>(iter: T) -> Self
+/// Deserializing rejects lists containing more than one signature of the same peer
+/// (or more than `MAX_PEERS` signatures).
+impl<'de> Deserialize<'de> for SignatureList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
-        T: IntoIterator<Item = SignatureListItemRef<'a>>,
+        D: Deserializer<'de>,
     {
-        iter.into_iter()
-            .map(|(peer_id, signature)| (peer_id.clone(), signature.clone()))
-            .collect()
+        let items: SignatureListVec = limits::peers(deserializer)?;
+        let mut signatures = Self(Vec::with_capacity(items.len()));
+        for (peer_id, signature) in items {
+            signatures
+                .insert(peer_id, signature)
+                .map_err(de::Error::custom)?;
+        }
+        Ok(signatures)
     }
 }
@@ -0,0 +1,209 @@
+//! Client-side encryption of values with per-namespace keys.
+//!
+//! The value of an `EncryptedKeyValue` transaction is encrypted by the client before signing.
+//! The key, the timestamp and the signature stay in the clear, so the RPUs can still
+//! order and verify the transaction without being able to read the value.
+//! Each encrypted value references the key it was encrypted with (its namespace and version),
+//! so readers holding the key can decrypt it.
+//!
+//! Values are encrypted with XChaCha20-Poly1305. The transaction key and the key reference
+//! are authenticated as well, so an encrypted value cannot be replayed under another key.
+
+use chacha20poly1305::{
+    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
+    XChaCha20Poly1305,
+};
+use err_derive::Error;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// The length of a namespace key in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// The length of the nonce of an encrypted value in bytes.
+pub const NONCE_LEN: usize = 24;
+
+/// An error while encrypting or decrypting a value.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EncryptionError {
+    /// There is no key for the namespace of a key.
+    #[error(display = "There is no encryption key for the key {:?}.", 0)]
+    NoKey(String),
+
+    /// The referenced key is unknown.
+    #[error(display = "The encryption key {} is unknown.", 0)]
+    UnknownKey(KeyReference),
+
+    /// The value could not be encrypted.
+    #[error(display = "The value could not be encrypted.")]
+    Encryption,
+
+    /// The value could not be decrypted (wrong key or manipulated data).
+    #[error(display = "The value could not be decrypted (wrong key or manipulated data).")]
+    Decryption,
+
+    /// A value could not be (de)serialized.
+    #[error(display = "{}", 0)]
+    Encoding(#[error(from)] postcard::Error),
+}
+
+/// A reference to the key a value was encrypted with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct KeyReference {
+    /// The key namespace (a key prefix) the key belongs to.
+    pub namespace: String,
+    /// The version of the key (keys are rotated by adding a newer version).
+    pub version: u32,
+}
+
+impl fmt::Display for KeyReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} (version {})", self.namespace, self.version)
+    }
+}
+
+/// An encrypted value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct EncryptedValue {
+    /// The key the value was encrypted with.
+    pub key_reference: KeyReference,
+    /// The random nonce used for the encryption.
+    pub nonce: [u8; NONCE_LEN],
+    /// The encrypted value (including the authentication tag).
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedValue {
+    /// Encode the encrypted value (as stored by the RPUs and returned by value queries).
+    pub fn encode(&self) -> Result<Vec<u8>, postcard::Error> {
+        postcard::to_stdvec(self)
+    }
+
+    /// Decode an encrypted value returned by a value query.
+    pub fn decode(data: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(data)
+    }
+}
+
+/// A set of namespace keys used to encrypt and decrypt values.
+///
+/// Values are encrypted with the newest key of the longest namespace matching their key.
+///
+/// # Example
+///
+/// ```
+/// use prellblock_client_api::encryption::{KeyReference, Keyring};
+///
+/// let mut keyring = Keyring::default();
+/// let key_reference = KeyReference {
+///     namespace: "plant1.".to_string(),
+///     version: 1,
+/// };
+/// keyring.insert(key_reference.clone(), Keyring::generate_key());
+///
+/// let encrypted = keyring.encrypt("plant1.pressure", b"4.2 bar").unwrap();
+/// assert_eq!(encrypted.key_reference, key_reference);
+/// assert_eq!(keyring.decrypt("plant1.pressure", &encrypted).unwrap(), b"4.2 bar");
+///
+/// // The encrypted value is bound to its key.
+/// assert!(keyring.decrypt("plant1.temperature", &encrypted).is_err());
+/// // There is no key for other namespaces.
+/// assert!(keyring.encrypt("plant2.pressure", b"4.2 bar").is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct Keyring {
+    keys: HashMap<KeyReference, [u8; KEY_LEN]>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Never print the keys.
+        f.debug_set().entries(self.keys.keys()).finish()
+    }
+}
+
+impl Keyring {
+    /// Generate a new random key.
+    #[must_use]
+    pub fn generate_key() -> [u8; KEY_LEN] {
+        let mut key = [0; KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        key
+    }
+
+    /// Add a `key` (replacing a key with the same reference).
+    pub fn insert(&mut self, key_reference: KeyReference, key: [u8; KEY_LEN]) {
+        self.keys.insert(key_reference, key);
+    }
+
+    /// Get the key referenced by `key_reference` (e.g. to escrow it).
+    #[must_use]
+    pub fn key(&self, key_reference: &KeyReference) -> Option<&[u8; KEY_LEN]> {
+        self.keys.get(key_reference)
+    }
+
+    /// Get the reference of the key used to encrypt values of `key`.
+    ///
+    /// This is the newest key of the longest namespace that is a prefix of `key`.
+    #[must_use]
+    pub fn key_reference(&self, key: &str) -> Option<&KeyReference> {
+        self.keys
+            .keys()
+            .filter(|key_reference| key.starts_with(key_reference.namespace.as_str()))
+            .max_by_key(|key_reference| (key_reference.namespace.len(), key_reference.version))
+    }
+
+    /// Encrypt the `value` of `key`.
+    pub fn encrypt(&self, key: &str, value: &[u8]) -> Result<EncryptedValue, EncryptionError> {
+        let key_reference = self
+            .key_reference(key)
+            .ok_or_else(|| EncryptionError::NoKey(key.to_string()))?
+            .clone();
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let aad = postcard::to_stdvec(&(key, &key_reference))?;
+        let ciphertext = self
+            .cipher(&key_reference)?
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: value,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Encryption)?;
+
+        Ok(EncryptedValue {
+            key_reference,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt the encrypted `value` of `key`.
+    pub fn decrypt(&self, key: &str, value: &EncryptedValue) -> Result<Vec<u8>, EncryptionError> {
+        let aad = postcard::to_stdvec(&(key, &value.key_reference))?;
+        self.cipher(&value.key_reference)?
+            .decrypt(
+                GenericArray::from_slice(&value.nonce),
+                Payload {
+                    msg: &value.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Decryption)
+    }
+
+    fn cipher(&self, key_reference: &KeyReference) -> Result<XChaCha20Poly1305, EncryptionError> {
+        let key = self
+            .keys
+            .get(key_reference)
+            .ok_or_else(|| EncryptionError::UnknownKey(key_reference.clone()))?;
+        Ok(XChaCha20Poly1305::new(GenericArray::from_slice(key)))
+    }
+}
@@ -0,0 +1,463 @@
+//! Escrow of namespace keys for audits of encrypted values.
+//!
+//! A namespace key (see [`encryption`](../encryption/index.html)) can be split into shares
+//! with Shamir's secret sharing over GF(2^8), one share for each RPU.
+//! Any `threshold` shares reconstruct the key, fewer shares reveal nothing about it,
+//! so no single RPU is able to decrypt the values.
+//!
+//! The hashes of the shares are recorded on-chain by an `EscrowKey` transaction.
+//! An admin authorizes an auditor with an `AuthorizeDecryption` transaction,
+//! which must carry the approvals of a supermajority of the RPUs (signed by their identities)
+//! and expires at a fixed time.
+//! Until then, each RPU releases its share to the auditor (see `GetEscrowShare`), who reconstructs the key.
+//! The share is sealed to a fresh `RecipientKey` of the auditor (X25519 and XChaCha20-Poly1305),
+//! so only the auditor who sent the request can open it.
+
+mod x25519;
+
+use crate::{
+    consensus::{BlockHash, SignatureList, SignatureListError, SignedData},
+    encryption::{KeyReference, KEY_LEN, NONCE_LEN},
+    transaction,
+};
+use blake2::{Blake2b, Digest};
+use chacha20poly1305::{
+    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
+    XChaCha20Poly1305,
+};
+use err_derive::Error;
+use pinxit::{PeerId, Signer};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, convert::TryFrom, fmt, time::SystemTime};
+
+/// The tags of a decryption approval in the signed data (distinct from all consensus messages).
+const DECRYPTION_APPROVAL_TAGS: (u8, u8) = (2, 2);
+
+/// The tags of the key derivation of a sealed share.
+const SEALED_SHARE_TAGS: (u8, u8) = (2, 3);
+
+/// The length of the public key of a `RecipientKey` in bytes.
+pub const RECIPIENT_KEY_LEN: usize = x25519::KEY_LEN;
+
+/// The length of a key share in bytes (the index of the share followed by the shared key).
+pub const SHARE_LEN: usize = KEY_LEN + 1;
+
+/// The hash of a `KeyShare` (recorded on-chain).
+pub type ShareHash = BlockHash;
+
+/// An error while splitting or combining a key.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EscrowError {
+    /// A key can be split into at most 255 shares.
+    #[error(display = "A key cannot be split into {} shares (at most 255).", 0)]
+    TooManyShares(usize),
+
+    /// The threshold is not between 1 and the number of shares.
+    #[error(
+        display = "The threshold {} is not between 1 and the number of shares ({}).",
+        0,
+        1
+    )]
+    InvalidThreshold(usize, usize),
+
+    /// No shares were given.
+    #[error(display = "There are no key shares.")]
+    NoShares,
+
+    /// A share has an invalid index or was given multiple times.
+    #[error(
+        display = "The key share {} is invalid or was given multiple times.",
+        0
+    )]
+    InvalidShare(u8),
+
+    /// The public key a share is sealed to is invalid (e.g. a point of low order).
+    #[error(display = "The recipient key is invalid.")]
+    InvalidRecipientKey,
+
+    /// The key share could not be sealed.
+    #[error(display = "The key share could not be sealed.")]
+    Sealing,
+
+    /// The sealed key share could not be opened (wrong key or manipulated data).
+    #[error(display = "The key share could not be opened (wrong key or manipulated data).")]
+    Opening,
+}
+
+/// A share of an escrowed key.
+#[derive(Clone)]
+pub struct KeyShare([u8; SHARE_LEN]);
+
+impl KeyShare {
+    /// Get the index of the share (`1` to `255`).
+    #[must_use]
+    pub const fn index(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Get the hash of the share (as recorded on-chain).
+    #[must_use]
+    pub fn hash(&self) -> ShareHash {
+        BlockHash::of(&self.0)
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.0[1..]
+    }
+}
+
+impl PartialEq for KeyShare {
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for KeyShare {}
+
+impl fmt::Debug for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Never print the share itself.
+        f.debug_tuple("KeyShare").field(&self.index()).finish()
+    }
+}
+
+hexutil::impl_hex!(KeyShare, SHARE_LEN, |&self| &self.0, |data| {
+    Ok(Self(data))
+});
+
+#[cfg(feature = "json-schema")]
+impl schemars::JsonSchema for KeyShare {
+    fn schema_name() -> String {
+        "KeyShare".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::json_schema::hex_string(
+            SHARE_LEN,
+            "A share of an escrowed key (hex encoded, the index followed by the shared key).",
+        )
+    }
+}
+
+/// A key pair of an auditor to receive key shares (see `SealedShare`).
+///
+/// Generate a new key pair for each recovery: The RPUs seal their shares to the public key in the request,
+/// so a replayed request is useless without the secret key.
+pub struct RecipientKey {
+    secret: [u8; RECIPIENT_KEY_LEN],
+    public_key: [u8; RECIPIENT_KEY_LEN],
+}
+
+impl fmt::Debug for RecipientKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Never print the secret key.
+        f.debug_struct("RecipientKey")
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+impl RecipientKey {
+    /// Generate a new random key pair.
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut secret = [0; RECIPIENT_KEY_LEN];
+        OsRng.fill_bytes(&mut secret);
+        let public_key = x25519::scalar_mult(&secret, &x25519::BASE_POINT);
+        Self { secret, public_key }
+    }
+
+    /// Get the public key (sent to the RPUs with `GetEscrowShare`).
+    #[must_use]
+    pub const fn public_key(&self) -> &[u8; RECIPIENT_KEY_LEN] {
+        &self.public_key
+    }
+
+    /// Open a share of the key `key_reference` sealed by the RPU `rpu`.
+    pub fn open(
+        &self,
+        sealed_share: &SealedShare,
+        rpu: &PeerId,
+        key_reference: &KeyReference,
+    ) -> Result<KeyShare, EscrowError> {
+        let cipher = sealing_cipher(
+            &self.secret,
+            &sealed_share.ephemeral_key,
+            &sealed_share.ephemeral_key,
+            &self.public_key,
+        )?;
+        let data = cipher
+            .decrypt(
+                GenericArray::from_slice(&sealed_share.nonce),
+                Payload {
+                    msg: &sealed_share.ciphertext,
+                    aad: &sealing_aad(rpu, key_reference),
+                },
+            )
+            .map_err(|_| EscrowError::Opening)?;
+        if data.len() != SHARE_LEN {
+            return Err(EscrowError::Opening);
+        }
+        let mut share = [0; SHARE_LEN];
+        share.copy_from_slice(&data);
+        Ok(KeyShare(share))
+    }
+}
+
+/// A key share sealed to the `RecipientKey` of an auditor.
+///
+/// The share is bound to the RPU that sealed it and to the escrowed key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SealedShare {
+    /// The public key of the ephemeral key pair of the RPU.
+    pub ephemeral_key: [u8; RECIPIENT_KEY_LEN],
+    /// The random nonce used for the encryption.
+    pub nonce: [u8; NONCE_LEN],
+    /// The encrypted share (including the authentication tag).
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedShare {
+    /// Seal the `share` of the key `key_reference` held by the RPU `rpu` to the `recipient_key`.
+    pub fn seal(
+        share: &KeyShare,
+        recipient_key: &[u8; RECIPIENT_KEY_LEN],
+        rpu: &PeerId,
+        key_reference: &KeyReference,
+    ) -> Result<Self, EscrowError> {
+        let ephemeral = RecipientKey::generate();
+        let cipher = sealing_cipher(
+            &ephemeral.secret,
+            recipient_key,
+            &ephemeral.public_key,
+            recipient_key,
+        )?;
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = cipher
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: &share.0,
+                    aad: &sealing_aad(rpu, key_reference),
+                },
+            )
+            .map_err(|_| EscrowError::Sealing)?;
+        Ok(Self {
+            ephemeral_key: ephemeral.public_key,
+            nonce,
+            ciphertext,
+        })
+    }
+}
+
+/// Derive the cipher of a sealed share from the shared secret of `secret` and `public_key`.
+fn sealing_cipher(
+    secret: &[u8; RECIPIENT_KEY_LEN],
+    public_key: &[u8; RECIPIENT_KEY_LEN],
+    ephemeral_key: &[u8; RECIPIENT_KEY_LEN],
+    recipient_key: &[u8; RECIPIENT_KEY_LEN],
+) -> Result<XChaCha20Poly1305, EscrowError> {
+    let shared_secret = x25519::scalar_mult(secret, public_key);
+    if shared_secret == [0; RECIPIENT_KEY_LEN] {
+        return Err(EscrowError::InvalidRecipientKey);
+    }
+    let data = postcard::to_stdvec(&(
+        SEALED_SHARE_TAGS,
+        shared_secret,
+        ephemeral_key,
+        recipient_key,
+    ))
+    .unwrap();
+    let key = Blake2b::digest(&data);
+    Ok(XChaCha20Poly1305::new(GenericArray::from_slice(
+        &key[..KEY_LEN],
+    )))
+}
+
+fn sealing_aad(rpu: &PeerId, key_reference: &KeyReference) -> Vec<u8> {
+    postcard::to_stdvec(&(SEALED_SHARE_TAGS, rpu, key_reference)).unwrap()
+}
+
+impl transaction::AuthorizeDecryption {
+    /// Create an authorization of an `auditor` to collect the shares of the key `key_reference`
+    /// until `expires_at`.
+    ///
+    /// It must be approved by a supermajority of the RPUs before it can be executed.
+    #[must_use]
+    pub fn new(
+        key_reference: KeyReference,
+        auditor: PeerId,
+        reason: String,
+        expires_at: SystemTime,
+    ) -> Self {
+        Self {
+            key_reference,
+            auditor,
+            reason,
+            expires_at,
+            timestamp: SystemTime::now(),
+            approvals: SignatureList::default(),
+        }
+    }
+
+    /// Create the data signed to approve the authorization.
+    ///
+    /// It covers everything but the approvals, so an approval cannot be reused for another audit.
+    #[must_use]
+    pub fn approval_data(&self) -> Vec<u8> {
+        postcard::to_stdvec(&(
+            DECRYPTION_APPROVAL_TAGS,
+            &self.key_reference,
+            &self.auditor,
+            &self.reason,
+            self.expires_at,
+            self.timestamp,
+        ))
+        .unwrap()
+    }
+
+    /// Approve the authorization with the `signer` (the identity of an RPU).
+    pub fn approve<S>(&mut self, signer: &S) -> Result<(), SignatureListError>
+    where
+        S: Signer + ?Sized,
+    {
+        let signature = signer.sign_bytes(&self.approval_data());
+        self.approvals.insert(signer.id().clone(), signature)
+    }
+
+    /// Verify that all approvals are valid signatures of the authorization.
+    ///
+    /// Returns the first invalid approval. Whether the approvers are RPUs is checked by the RPUs.
+    pub fn verify_approvals(&self) -> Result<(), (PeerId, pinxit::Error)> {
+        let data = self.approval_data();
+        for (peer_id, signature) in &self.approvals {
+            peer_id
+                .verify(SignedData(&data), signature)
+                .map_err(|err| (peer_id.clone(), err))?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the hash of an escrowed `key` (to verify a reconstructed key).
+#[must_use]
+pub fn key_hash(key: &[u8; KEY_LEN]) -> BlockHash {
+    BlockHash::of(key)
+}
+
+/// Split a `key` into `count` shares, any `threshold` of which reconstruct the key.
+///
+/// # Example
+///
+/// ```
+/// use prellblock_client_api::{encryption::Keyring, escrow};
+///
+/// let key = Keyring::generate_key();
+/// let shares = escrow::split(&key, 3, 4).unwrap();
+///
+/// assert_eq!(escrow::combine(&shares[1..]).unwrap(), key);
+/// assert_eq!(escrow::combine(&[&shares[..1], &shares[2..]].concat()).unwrap(), key);
+/// // Fewer shares do not reconstruct the key.
+/// assert_ne!(escrow::combine(&shares[..2]).unwrap(), key);
+/// ```
+pub fn split(
+    key: &[u8; KEY_LEN],
+    threshold: usize,
+    count: usize,
+) -> Result<Vec<KeyShare>, EscrowError> {
+    let max_index = u8::try_from(count).map_err(|_| EscrowError::TooManyShares(count))?;
+    if threshold == 0 || threshold > count {
+        return Err(EscrowError::InvalidThreshold(threshold, count));
+    }
+
+    // The shares are points of random polynomials of degree `threshold - 1` (one per key byte)
+    // whose constant terms are the key bytes.
+    let mut coefficients = vec![[0; KEY_LEN]; threshold - 1];
+    for coefficient in &mut coefficients {
+        OsRng.fill_bytes(coefficient);
+    }
+
+    let shares = (1..=max_index)
+        .map(|x| {
+            let mut share = [0; SHARE_LEN];
+            share[0] = x;
+            for (index, byte) in share[1..].iter_mut().enumerate() {
+                let mut y = 0;
+                for coefficient in coefficients.iter().rev() {
+                    y = mul(y ^ coefficient[index], x);
+                }
+                *byte = y ^ key[index];
+            }
+            KeyShare(share)
+        })
+        .collect();
+    Ok(shares)
+}
+
+/// Reconstruct a key from its `shares`.
+///
+/// If fewer shares than the threshold are given, the result is a wrong key.
+/// Verify the result with the `key_hash` of the escrowed key.
+pub fn combine(shares: &[KeyShare]) -> Result<[u8; KEY_LEN], EscrowError> {
+    if shares.is_empty() {
+        return Err(EscrowError::NoShares);
+    }
+    let mut indices = HashSet::new();
+    for share in shares {
+        if share.index() == 0 || !indices.insert(share.index()) {
+            return Err(EscrowError::InvalidShare(share.index()));
+        }
+    }
+
+    // Interpolate the polynomials at `0` (Lagrange).
+    let mut key = [0; KEY_LEN];
+    for share in shares {
+        let x = share.index();
+        let basis = shares
+            .iter()
+            .map(KeyShare::index)
+            .filter(|&other| other != x)
+            .fold(1, |basis, other| mul(basis, div(other, other ^ x)));
+        for (byte, y) in key.iter_mut().zip(share.data()) {
+            *byte ^= mul(*y, basis);
+        }
+    }
+    Ok(key)
+}
+
+/// Multiply in GF(2^8) (with the AES polynomial).
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// Divide in GF(2^8) (`b` must not be `0`).
+fn div(a: u8, b: u8) -> u8 {
+    // The inverse of `b` is `b^254`.
+    let mut inverse = 1;
+    let mut power = b;
+    let mut exponent = 254_u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            inverse = mul(inverse, power);
+        }
+        power = mul(power, power);
+        exponent >>= 1;
+    }
+    mul(a, inverse)
+}
@@ -0,0 +1,214 @@
+//! The X25519 function (RFC 7748) to seal key shares to an auditor.
+//!
+//! Field elements are stored in five limbs of 51 bits (radix 2^51) modulo `p = 2^255 - 19`.
+//! The Montgomery ladder runs in constant time (no branches or indices depend on the scalar).
+
+/// The length of an X25519 key in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// The u-coordinate of the base point.
+pub const BASE_POINT: [u8; KEY_LEN] = {
+    let mut point = [0; KEY_LEN];
+    point[0] = 9;
+    point
+};
+
+const MASK: u64 = (1 << 51) - 1;
+
+#[derive(Clone, Copy)]
+struct Element([u64; 5]);
+
+impl Element {
+    const ZERO: Self = Self([0; 5]);
+    const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    fn from_bytes(bytes: &[u8; KEY_LEN]) -> Self {
+        let load = |offset: usize| {
+            let mut word = [0; 8];
+            word.copy_from_slice(&bytes[offset..offset + 8]);
+            u64::from_le_bytes(word)
+        };
+        // The most significant bit is ignored.
+        Self([
+            load(0) & MASK,
+            (load(6) >> 3) & MASK,
+            (load(12) >> 6) & MASK,
+            (load(19) >> 1) & MASK,
+            (load(24) >> 12) & MASK,
+        ])
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_bytes(self) -> [u8; KEY_LEN] {
+        let mut limbs = self.carry().0;
+
+        // Subtract `p` if the value is not fully reduced (`limbs` is below `2p` after the carry).
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[4] &= MASK;
+
+        let mut bytes = [0; KEY_LEN];
+        let mut accumulator: u128 = 0;
+        let mut bits = 0;
+        let mut index = 0;
+        for limb in &limbs {
+            accumulator |= u128::from(*limb) << bits;
+            bits += 51;
+            while bits >= 8 && index < KEY_LEN {
+                bytes[index] = accumulator as u8;
+                accumulator >>= 8;
+                bits -= 8;
+                index += 1;
+            }
+        }
+        if index < KEY_LEN {
+            bytes[index] = accumulator as u8;
+        }
+        bytes
+    }
+
+    /// Propagate the carries, so each limb fits into 51 bits (plus a small excess in the first limb).
+    fn carry(self) -> Self {
+        let mut limbs = self.0;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[0] += 19 * (limbs[4] >> 51);
+        limbs[4] &= MASK;
+        limbs[1] += limbs[0] >> 51;
+        limbs[0] &= MASK;
+        Self(limbs)
+    }
+
+    fn add(self, other: Self) -> Self {
+        let mut limbs = self.0;
+        for (limb, other) in limbs.iter_mut().zip(&other.0) {
+            *limb += other;
+        }
+        Self(limbs).carry()
+    }
+
+    fn sub(self, other: Self) -> Self {
+        // Add `2p` first, so no limb underflows.
+        const TWO_P: [u64; 5] = [
+            0x000f_ffff_ffff_ffda,
+            0x000f_ffff_ffff_fffe,
+            0x000f_ffff_ffff_fffe,
+            0x000f_ffff_ffff_fffe,
+            0x000f_ffff_ffff_fffe,
+        ];
+        let other = other.carry();
+        let mut limbs = self.carry().0;
+        for ((limb, two_p), other) in limbs.iter_mut().zip(&TWO_P).zip(&other.0) {
+            *limb = *limb + two_p - other;
+        }
+        Self(limbs).carry()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn mul(self, other: Self) -> Self {
+        let a = self.0;
+        let b = other.0;
+        let m = |x: u64, y: u64| u128::from(x) * u128::from(y);
+        // Limbs above the fifth wrap around multiplied by 19 (`2^255 = 19 mod p`).
+        let b19 = [b[0], b[1] * 19, b[2] * 19, b[3] * 19, b[4] * 19];
+        let products = [
+            m(a[0], b[0]) + m(a[1], b19[4]) + m(a[2], b19[3]) + m(a[3], b19[2]) + m(a[4], b19[1]),
+            m(a[0], b[1]) + m(a[1], b[0]) + m(a[2], b19[4]) + m(a[3], b19[3]) + m(a[4], b19[2]),
+            m(a[0], b[2]) + m(a[1], b[1]) + m(a[2], b[0]) + m(a[3], b19[4]) + m(a[4], b19[3]),
+            m(a[0], b[3]) + m(a[1], b[2]) + m(a[2], b[1]) + m(a[3], b[0]) + m(a[4], b19[4]),
+            m(a[0], b[4]) + m(a[1], b[3]) + m(a[2], b[2]) + m(a[3], b[1]) + m(a[4], b[0]),
+        ];
+
+        let mut limbs = [0; 5];
+        let mut carry: u128 = 0;
+        for (limb, product) in limbs.iter_mut().zip(&products) {
+            let value = product + carry;
+            *limb = (value as u64) & MASK;
+            carry = value >> 51;
+        }
+        limbs[0] += (carry as u64) * 19;
+        Self(limbs).carry()
+    }
+
+    fn mul_small(self, factor: u64) -> Self {
+        self.mul(Self([factor, 0, 0, 0, 0]))
+    }
+
+    fn square(self) -> Self {
+        self.mul(self)
+    }
+
+    /// Invert the element (`self^(p - 2)`, zero stays zero).
+    fn invert(self) -> Self {
+        // The exponent `p - 2` is `2^255 - 21`: all bits are set except bits 2 and 4.
+        let mut result = Self::ONE;
+        for bit in (0..255).rev() {
+            result = result.square();
+            if bit != 2 && bit != 4 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+
+    /// Swap `a` and `b` if `swap` is `1` (in constant time).
+    fn conditional_swap(a: &mut Self, b: &mut Self, swap: u64) {
+        let mask = 0_u64.wrapping_sub(swap);
+        for (a, b) in a.0.iter_mut().zip(b.0.iter_mut()) {
+            let t = mask & (*a ^ *b);
+            *a ^= t;
+            *b ^= t;
+        }
+    }
+}
+
+/// Multiply the `point` (a u-coordinate) with the `scalar` (the X25519 function).
+#[allow(clippy::many_single_char_names)]
+pub fn scalar_mult(scalar: &[u8; KEY_LEN], point: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let mut scalar = *scalar;
+    scalar[0] &= 248;
+    scalar[31] &= 127;
+    scalar[31] |= 64;
+
+    let x1 = Element::from_bytes(point).carry();
+    let mut x2 = Element::ONE;
+    let mut z2 = Element::ZERO;
+    let mut x3 = x1;
+    let mut z3 = Element::ONE;
+    let mut swap = 0;
+
+    for bit in (0..255).rev() {
+        let k = u64::from((scalar[bit / 8] >> (bit % 8)) & 1);
+        swap ^= k;
+        Element::conditional_swap(&mut x2, &mut x3, swap);
+        Element::conditional_swap(&mut z2, &mut z3, swap);
+        swap = k;
+
+        let a = x2.add(z2);
+        let aa = a.square();
+        let b = x2.sub(z2);
+        let bb = b.square();
+        let e = aa.sub(bb);
+        let c = x3.add(z3);
+        let d = x3.sub(z3);
+        let da = d.mul(a);
+        let cb = c.mul(b);
+        x3 = da.add(cb).square();
+        z3 = x1.mul(da.sub(cb).square());
+        x2 = aa.mul(bb);
+        z2 = e.mul(aa.add(e.mul_small(121_665)));
+    }
+    Element::conditional_swap(&mut x2, &mut x3, swap);
+    Element::conditional_swap(&mut z2, &mut z3, swap);
+
+    x2.mul(z2.invert()).to_bytes()
+}
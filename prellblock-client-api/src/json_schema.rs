@@ -0,0 +1,152 @@
+//! A JSON schema of the client API (with the `json-schema` feature).
+//!
+//! The schema describes each `ClientMessage` request and its response
+//! in their JSON representation (e.g. as forwarded by a gateway),
+//! so external teams can generate typed clients in other languages.
+//! RPUs encode the same data model with `postcard` (see `PROTOCOL_VERSION`).
+//!
+//! The schema is kept in sync by a snapshot test (`tests/json_schema.rs`).
+
+use crate::{
+    account::Account,
+    consensus::{Block, BlockNumber, BlockProof, CertifiedHead},
+    ChainComparison, ClockSkew, CompareChains, ConnectionMetrics, ConsensusFailure, Description,
+    EscrowShare, EvictTransactions, ExecuteIdempotent, ExecuteWithDeadline, ExecutionReceipt,
+    GetAccount, GetBannedPeers, GetBlock, GetBlockProof, GetCertifiedHead, GetClockSkews,
+    GetConsensusFailures, GetCurrentBlockNumber, GetEscrowShare, GetExecutionReceipt,
+    GetLeaderTerms, GetNetworkMetrics, GetQueue, GetRequestMetrics, GetScrubStatistics, GetStatus,
+    GetTransactionTrace, GetValue, InSession, LeaderTermRecord, OpenSession, Pong,
+    QueuedTransaction, ReadValues, Receipt, ReceiptStatus, RequestMetrics, ScrubStatistics,
+    SessionChallenge, SessionToken, Status, StreamBlocks, SubscribeBlocks, Transaction,
+    TransactionTrace, UnbanPeer, WatchReceipt, PROTOCOL_VERSION,
+};
+use pinxit::{PeerId, Signed};
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation},
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// The request and response types of each `ClientMessage` (in the order of the enum).
+macro_rules! requests {
+    ($($name:ident: $request:ty => $response:ty,)*) => {
+        fn requests(gen: &mut SchemaGenerator) -> Vec<(&'static str, Schema, Schema)> {
+            vec![$((
+                stringify!($name),
+                gen.subschema_for::<$request>(),
+                gen.subschema_for::<$response>(),
+            ),)*]
+        }
+    };
+}
+
+requests! {
+    Ping: () => Pong,
+    Describe: () => Description,
+    Execute: Signed<Transaction> => (),
+    GetValue: Signed<GetValue> => ReadValues,
+    GetAccount: Signed<GetAccount> => Vec<Account>,
+    GetBlock: Signed<GetBlock> => Vec<Block>,
+    GetCurrentBlockNumber: Signed<GetCurrentBlockNumber> => BlockNumber,
+    GetBannedPeers: Signed<GetBannedPeers> => Vec<PeerId>,
+    UnbanPeer: Signed<UnbanPeer> => (),
+    GetQueue: Signed<GetQueue> => Vec<QueuedTransaction>,
+    EvictTransactions: Signed<EvictTransactions> => (),
+    GetClockSkews: Signed<GetClockSkews> => HashMap<PeerId, ClockSkew>,
+    // The blocks are streamed one by one.
+    StreamBlocks: Signed<StreamBlocks> => Vec<Block>,
+    GetConsensusFailures: Signed<GetConsensusFailures> => Vec<ConsensusFailure>,
+    GetBlockProof: Signed<GetBlockProof> => BlockProof,
+    ExecuteIdempotent: Signed<ExecuteIdempotent> => Receipt,
+    GetScrubStatistics: Signed<GetScrubStatistics> => ScrubStatistics,
+    CompareChains: Signed<CompareChains> => ChainComparison,
+    GetEscrowShare: Signed<GetEscrowShare> => EscrowShare,
+    GetSessionChallenge: () => SessionChallenge,
+    OpenSession: Signed<OpenSession> => SessionToken,
+    ExecuteIdempotentInSession: InSession<ExecuteIdempotent> => Receipt,
+    GetValueInSession: InSession<GetValue> => ReadValues,
+    GetNetworkMetrics: Signed<GetNetworkMetrics> => Vec<ConnectionMetrics>,
+    GetTransactionTrace: Signed<GetTransactionTrace> => TransactionTrace,
+    ExecuteWithDeadline: Signed<ExecuteWithDeadline> => Receipt,
+    // The status changes are streamed one by one.
+    WatchReceipt: Signed<WatchReceipt> => Vec<ReceiptStatus>,
+    GetLeaderTerms: Signed<GetLeaderTerms> => Vec<LeaderTermRecord>,
+    GetExecutionReceipt: Signed<GetExecutionReceipt> => Option<ExecutionReceipt>,
+    GetRequestMetrics: Signed<GetRequestMetrics> => Vec<RequestMetrics>,
+    GetCertifiedHead: Signed<GetCertifiedHead> => CertifiedHead,
+    // The blocks are streamed one by one.
+    SubscribeBlocks: Signed<SubscribeBlocks> => Vec<Block>,
+    GetStatus: Signed<GetStatus> => Status,
+}
+
+/// Generate the JSON schema (draft 7) of the client API.
+///
+/// The root schema matches a `ClientMessage`, the schemas of the responses
+/// are listed by request name in `responses`.
+///
+/// # Example
+///
+/// ```
+/// let schema = prellblock_client_api::json_schema::client_api();
+/// assert!(schema["responses"]["Ping"].is_object());
+/// assert!(schema["definitions"]["Transaction"].is_object());
+/// ```
+#[must_use]
+pub fn client_api() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+    let mut variants = Vec::new();
+    let mut responses = Map::new();
+    for (name, request, response) in requests(&mut gen) {
+        // Requests are externally tagged by their name.
+        variants.push(json!({
+            "type": "object",
+            "required": [name],
+            "properties": { name: request },
+            "additionalProperties": false,
+        }));
+        responses.insert(name.to_string(), json!(response));
+    }
+
+    let description = format!(
+        "The requests of the prellblock client API (protocol version {}).",
+        PROTOCOL_VERSION
+    );
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "ClientMessage",
+        "description": description,
+        "oneOf": variants,
+        "responses": responses,
+        "definitions": gen.definitions(),
+    })
+}
+
+/// The schema of a hex string encoding `len` bytes.
+pub(crate) fn hex_string(len: usize, description: &str) -> Schema {
+    hex_string_of_lengths(&[len], description)
+}
+
+/// The schema of a hex string encoding any of `lens` bytes.
+pub(crate) fn hex_string_of_lengths(lens: &[usize], description: &str) -> Schema {
+    let patterns: Vec<String> = lens
+        .iter()
+        .map(|len| format!("[0-9a-f]{{{}}}", len * 2))
+        .collect();
+    SchemaObject {
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_string()),
+            ..Metadata::default()
+        })),
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(match patterns.as_slice() {
+                [pattern] => format!("^{}$", pattern),
+                patterns => format!("^({})$", patterns.join("|")),
+            }),
+            ..StringValidation::default()
+        })),
+        ..SchemaObject::default()
+    }
+    .into()
+}
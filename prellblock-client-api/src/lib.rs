@@ -2,26 +2,81 @@
 #![allow(clippy::missing_errors_doc, clippy::similar_names)]
 
 //! Library Crate used for Communication between external Clients and internal RPUs.
+//!
+//! The `ClientMessage` API (and its dependency on `balise` and `tokio`) is part of the
+//! default `messages` feature. Without it, the crate only contains the wire types,
+//! the transaction builder and the signing, e.g. for gateways on embedded targets.
 
 pub mod account;
+mod builder;
+pub mod canonical;
 pub mod consensus;
+pub mod encryption;
+pub mod escrow;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+pub mod limits;
+pub mod metadata;
+pub mod parameter;
+pub mod quota;
+pub mod schema;
+pub mod test_vectors;
 
-use account::{Account, Permissions};
-use balise::define_api;
-use consensus::{Block, BlockNumber};
+pub use builder::{Error as BuilderError, SignedBatch, TransactionBuilder};
+
+#[cfg(feature = "messages")]
+use account::Account;
+use account::Permissions;
+#[cfg(feature = "messages")]
+use balise::{define_api, Streaming};
+use consensus::{
+    Block, BlockHash, BlockNumber, ChainId, LeaderTerm, SignatureList, TransactionHash,
+};
+#[cfg(feature = "messages")]
+use consensus::{BlockProof, CertifiedHead};
+use encryption::{EncryptedValue, KeyReference};
+use escrow::{SealedShare, ShareHash, RECIPIENT_KEY_LEN};
+use metadata::Metadata;
 use newtype_enum::newtype_enum;
+use parameter::Parameter;
 use pinxit::{PeerId, Signable, Signature, Signed};
+use quota::Quota;
+use schema::Schema;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    net::SocketAddr,
     ops::{Bound, Deref, RangeBounds},
     time::{Duration, SystemTime},
 };
 
+/// The version of the client API protocol.
+///
+/// Incremented on every incompatible change of the `ClientMessage` API.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// Play ping pong. See [`Ping`](message/struct.Ping.html).
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct Pong;
 
+/// The description of an RPU. See [`Describe`](message/struct.Describe.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Description {
+    /// The version of the client API protocol (see `PROTOCOL_VERSION`).
+    pub protocol_version: u32,
+    /// The names of the requests supported by the RPU.
+    pub requests: Vec<String>,
+    /// The maximum number of transactions the RPU puts into a single batch.
+    pub max_batch_size: usize,
+    /// The maximum size of a single message in bytes.
+    pub max_payload_size: u64,
+    /// The id of the chain (the hash of the genesis block).
+    pub chain_id: ChainId,
+}
+
 /// Filter to select a value.
 ///
 /// # Examples
@@ -57,6 +112,7 @@ pub struct Pong;
 /// assert_into(Filter::RangeFrom(String::new()), ..);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub enum Filter<T> {
     /// Select one exactly matching value.
     Exact(T),
@@ -108,6 +164,34 @@ impl<T> Filter<T> {
     }
 }
 
+impl Filter<String> {
+    /// Select all keys starting with `prefix`.
+    ///
+    /// ```
+    /// use prellblock_client_api::Filter;
+    ///
+    /// assert_eq!(
+    ///     Filter::key_prefix("line3/"),
+    ///     Filter::Range("line3/".to_string().."line30".to_string())
+    /// );
+    /// assert_eq!(Filter::key_prefix(""), Filter::RangeFrom(String::new()));
+    /// ```
+    pub fn key_prefix(prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        // The keys are ordered by their bytes, which is the order of their chars (in UTF-8).
+        // So the first key after the prefix ends with the next char of the last char that has one.
+        let mut end = prefix.clone();
+        while let Some(last) = end.pop() {
+            let next = (u32::from(last) + 1..=u32::from(char::MAX)).find_map(std::char::from_u32);
+            if let Some(next) = next {
+                end.push(next);
+                return Self::Range(prefix..end);
+            }
+        }
+        Self::RangeFrom(prefix)
+    }
+}
+
 #[allow(clippy::match_same_arms)]
 impl<T> RangeBounds<T> for Filter<T> {
     fn start_bound(&self) -> Bound<&T> {
@@ -129,6 +213,7 @@ impl<T> RangeBounds<T> for Filter<T> {
 
 /// A span or selection of a given point in time / number of values.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub enum Span {
     /// A number of values.
     Count(usize),
@@ -217,6 +302,7 @@ impl From<Duration> for Span {
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub enum Query {
     /// Get the current value.
     CurrentValue,
@@ -246,6 +332,7 @@ pub type ReadValuesOfPeer = HashMap<String, ReadValuesOfSeries>;
 /// The `Transaction`s in response to a `GetValue` request of all peers.
 pub type ReadValues = HashMap<PeerId, ReadValuesOfPeer>;
 
+#[cfg(feature = "messages")]
 define_api! {
     /// The message API module for communication between RPUs.
     mod message;
@@ -254,6 +341,9 @@ define_api! {
         /// Ping Message. See [`Pong`](../struct.Pong.html).
         Ping => Pong,
 
+        /// Describe the RPU's supported requests, limits and chain. See [`Description`](../struct.Description.html).
+        Describe => Description,
+
         /// Simple transaction Message. Will write a key:value pair.
         Execute(Signed<Transaction>) => (),
 
@@ -270,11 +360,90 @@ define_api! {
 
         /// Get the current number of blocks in the blockchain.
         GetCurrentBlockNumber(Signed<crate::GetCurrentBlockNumber>) => BlockNumber,
+
+        /// Get the peers banned by the RPU (only allowed for admins).
+        GetBannedPeers(Signed<crate::GetBannedPeers>) => Vec<PeerId>,
+
+        /// Unban a peer on the RPU (only allowed for admins).
+        UnbanPeer(Signed<crate::UnbanPeer>) => (),
+
+        /// Get the transactions queued on the RPU (only allowed for admins).
+        GetQueue(Signed<crate::GetQueue>) => Vec<QueuedTransaction>,
+
+        /// Evict queued transactions on all RPUs (only allowed for admins).
+        EvictTransactions(Signed<crate::EvictTransactions>) => (),
+
+        /// Get the estimated clock skews of the other RPUs (only allowed for admins).
+        GetClockSkews(Signed<crate::GetClockSkews>) => HashMap<PeerId, ClockSkew>,
+
+        /// Stream the `Block`s in a range of `BlockNumber`s in order.
+        StreamBlocks(Signed<crate::StreamBlocks>) => Streaming<Block>,
+
+        /// Get the latest consensus failures recorded by the RPU (only allowed for admins).
+        GetConsensusFailures(Signed<crate::GetConsensusFailures>) => Vec<ConsensusFailure>,
+
+        /// Get the proof which RPUs accepted a `Block`. See [`BlockProof`](../consensus/struct.BlockProof.html).
+        GetBlockProof(Signed<crate::GetBlockProof>) => BlockProof,
+
+        /// Execute a transaction at most once per idempotency key. See [`Receipt`](../struct.Receipt.html).
+        ExecuteIdempotent(Signed<crate::ExecuteIdempotent>) => Receipt,
+
+        /// Get the results of the block storage scrubber (only allowed for admins).
+        GetScrubStatistics(Signed<crate::GetScrubStatistics>) => ScrubStatistics,
+
+        /// Compare the chain of the RPU with the chains of all other RPUs (only allowed for admins).
+        CompareChains(Signed<crate::CompareChains>) => ChainComparison,
+
+        /// Get the RPU's share of an escrowed key (only allowed for authorized auditors).
+        GetEscrowShare(Signed<crate::GetEscrowShare>) => EscrowShare,
+
+        /// Get a challenge to open a session. See [`SessionChallenge`](../struct.SessionChallenge.html).
+        GetSessionChallenge => SessionChallenge,
+
+        /// Open a session with a signed challenge. See [`SessionToken`](../struct.SessionToken.html).
+        OpenSession(Signed<crate::OpenSession>) => SessionToken,
+
+        /// Execute a transaction at most once per idempotency key (authenticated by a session).
+        ExecuteIdempotentInSession(crate::InSession<crate::ExecuteIdempotent>) => Receipt,
+
+        /// Get the values of the given peers (authenticated by a session).
+        GetValueInSession(crate::InSession<crate::GetValue>) => ReadValues,
+
+        /// Get the metrics of the connections of the RPU to other servers (only allowed for admins).
+        GetNetworkMetrics(Signed<crate::GetNetworkMetrics>) => Vec<ConnectionMetrics>,
+
+        /// Get where a transaction is on the RPU right now (only allowed for admins).
+        GetTransactionTrace(Signed<crate::GetTransactionTrace>) => TransactionTrace,
+
+        /// Execute a transaction that must be committed before a deadline. See [`Receipt`](../struct.Receipt.html).
+        ExecuteWithDeadline(Signed<crate::ExecuteWithDeadline>) => Receipt,
+
+        /// Watch the status of a receipt of `ExecuteWithDeadline`. See [`ReceiptStatus`](../enum.ReceiptStatus.html).
+        WatchReceipt(Signed<crate::WatchReceipt>) => Streaming<ReceiptStatus>,
+
+        /// Get the history of the leader terms seen by the RPU (only allowed for admins).
+        GetLeaderTerms(Signed<crate::GetLeaderTerms>) => Vec<LeaderTermRecord>,
+
+        /// Get whether a committed transaction was applied (only allowed for its signer and admins). See [`ExecutionReceipt`](../struct.ExecutionReceipt.html).
+        GetExecutionReceipt(Signed<crate::GetExecutionReceipt>) => Option<ExecutionReceipt>,
+
+        /// Get the metrics of the requests handled by the RPU (only allowed for admins).
+        GetRequestMetrics(Signed<crate::GetRequestMetrics>) => Vec<RequestMetrics>,
+
+        /// Get the head of the chain certified by a supermajority of the RPUs. See [`CertifiedHead`](../consensus/struct.CertifiedHead.html).
+        GetCertifiedHead(Signed<crate::GetCertifiedHead>) => CertifiedHead,
+
+        /// Subscribe to the committed `Block`s, optionally resuming after a [`ResumeToken`](../struct.ResumeToken.html).
+        SubscribeBlocks(Signed<crate::SubscribeBlocks>) => Streaming<Block>,
+
+        /// Get the status of the RPU, e.g. whether it was halted (only allowed for admins). See [`Status`](../struct.Status.html).
+        GetStatus(Signed<crate::GetStatus>) => Status,
     }
 }
 
 /// Get the values of the given peers, filtered by a filter and selected by a query.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct GetValue {
     /// A Vector of `PeerId`'s to select the `Accounts` from which to read.
     pub peer_ids: Vec<PeerId>,
@@ -288,6 +457,7 @@ pub struct GetValue {
 ///
 /// Accounts that are not found will be omitted in the return value.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct GetAccount {
     /// A Vector of `PeerId`'s to select the `Accounts` from which to read.
     pub peer_ids: Vec<PeerId>,
@@ -295,15 +465,793 @@ pub struct GetAccount {
 
 /// Get a `Block` by it's `BlockNumber`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct GetBlock {
     /// The filter to select some blocks.
     pub filter: Filter<BlockNumber>,
 }
 
+/// Stream the `Block`s in a range of `BlockNumber`s in order.
+///
+/// Unlike `GetBlock`, the blocks are sent one by one, so arbitrarily large ranges can be read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct StreamBlocks {
+    /// The first block to send.
+    pub from: BlockNumber,
+    /// The block after the last block to send (`None` for all blocks up to the newest one).
+    pub to: Option<BlockNumber>,
+}
+
+/// Subscribe to the committed `Block`s (and thus their transactions).
+///
+/// The RPU replays the blocks after the `resume_token` from its block storage
+/// and then sends each block as soon as it is committed. The stream never ends.
+/// A subscriber that lost its connection subscribes again with the token
+/// of the last block it received and continues without gaps or duplicates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SubscribeBlocks {
+    /// Continue after the block of this token (`None` to start with the next committed block).
+    pub resume_token: Option<ResumeToken>,
+}
+
+/// The position of a block subscriber: the number of the last block it received.
+///
+/// ```
+/// # use prellblock_client_api::{consensus::BlockNumber, ResumeToken};
+/// let token = ResumeToken::new(BlockNumber::new(41));
+/// assert_eq!(token.next_block_number(), BlockNumber::new(42));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ResumeToken {
+    /// The number of the last delivered block.
+    pub last_block_number: BlockNumber,
+}
+
+impl ResumeToken {
+    /// Create a token to resume after the block `last_block_number`.
+    #[must_use]
+    pub const fn new(last_block_number: BlockNumber) -> Self {
+        Self { last_block_number }
+    }
+
+    /// Get the number of the first block to send when resuming.
+    #[must_use]
+    pub fn next_block_number(self) -> BlockNumber {
+        self.last_block_number + 1
+    }
+}
+
 /// Get the current number of blocks in the blockchain.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct GetCurrentBlockNumber;
 
+/// Get the head of the chain certified by a supermajority of the RPUs.
+///
+/// The RPU asks the other RPUs to confirm its head (or reuses a recent certificate),
+/// so clients can trust the latest block number without asking all RPUs.
+/// Fails if the RPUs do not agree on the head right now (e.g. while a block is committed).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetCertifiedHead;
+
+/// Get the status of the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetStatus;
+
+/// The status of an RPU. See [`GetStatus`](message/struct.GetStatus.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Status {
+    /// The number of blocks applied to the RPU's `WorldState`.
+    pub block_number: BlockNumber,
+    /// Why the RPU was halted (`None` if it is running).
+    ///
+    /// A halted RPU does not take part in the consensus and rejects new transactions.
+    pub halt_reason: Option<String>,
+}
+
+/// Get the peers banned by the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetBannedPeers;
+
+/// Unban a peer on the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct UnbanPeer {
+    /// The peer to unban.
+    pub peer_id: PeerId,
+}
+
+/// Get the transactions queued on the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetQueue;
+
+/// A transaction waiting in the queue of an RPU. See [`GetQueue`](message/struct.GetQueue.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct QueuedTransaction {
+    /// The hash identifying the transaction.
+    pub hash: TransactionHash,
+    /// The account that signed the transaction.
+    pub signer: PeerId,
+    /// The timestamp of the transaction.
+    pub timestamp: SystemTime,
+    /// How long the transaction has been waiting in the queue.
+    pub queued_for: Duration,
+}
+
+/// Evict queued transactions on all RPUs (only allowed for admins).
+///
+/// The eviction is recorded in the audit log of each RPU.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct EvictTransactions {
+    /// The hashes of the transactions to evict.
+    pub hashes: Vec<TransactionHash>,
+    /// The timestamp of the request.
+    pub timestamp: SystemTime,
+}
+
+/// Get the estimated clock skews of the other RPUs (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetClockSkews;
+
+/// The estimated skew of the clock of an RPU. See [`GetClockSkews`](message/struct.GetClockSkews.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ClockSkew {
+    /// The offset of the RPU's clock in microseconds (positive if it is ahead of the local clock).
+    pub offset_micros: i64,
+    /// The round trip time of the measurement (the error is at most half of it).
+    pub round_trip: Duration,
+    /// When the skew was measured.
+    pub measured_at: SystemTime,
+}
+
+impl ClockSkew {
+    /// Get the absolute offset of the clock.
+    #[must_use]
+    pub fn offset(&self) -> Duration {
+        let micros = self.offset_micros.checked_abs().unwrap_or(i64::max_value());
+        Duration::from_micros(u64::try_from(micros).unwrap_or_default())
+    }
+}
+
+/// Get the latest consensus failures recorded by the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetConsensusFailures;
+
+/// A failure of the consensus recorded by an RPU. See [`GetConsensusFailures`](message/struct.GetConsensusFailures.html).
+///
+/// The latest failures are persisted, so they can be inspected after the fact
+/// (e.g. to find out why the chain stopped growing).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ConsensusFailure {
+    /// When the failure was recorded.
+    pub time: SystemTime,
+    /// The kind of the failure.
+    pub kind: ConsensusFailureKind,
+    /// The leader term of the failure (the requested leader term for view changes).
+    pub leader_term: LeaderTerm,
+    /// The number of the next block at the time of the failure.
+    pub block_number: BlockNumber,
+    /// A description of the failure (e.g. the error).
+    pub reason: String,
+    /// The peers involved (e.g. the sender of a rejected message or the replaced leader).
+    pub peers: Vec<PeerId>,
+}
+
+/// The kind of a `ConsensusFailure`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum ConsensusFailureKind {
+    /// The RPU failed to lead a leader term.
+    Leader,
+    /// A consensus message of another RPU was rejected.
+    RejectedMessage,
+    /// The RPU requested a view change.
+    ViewChange,
+    /// Another RPU was started with a different genesis (see `GetChainIds`).
+    GenesisMismatch,
+}
+
+/// Why an RPU requested a view change (sent with its `ViewChange` message).
+///
+/// The reasons of all RPUs are counted per leader term (see `LeaderTermRecord`),
+/// so operators can tell whether leadership churn comes from network issues or a faulty leader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum ViewChangeReason {
+    /// Transactions were not included in a block in time.
+    Censorship,
+    /// The leader proposed an invalid block (in a `Prepare`, `Append` or `Commit` message).
+    InvalidAppend,
+    /// The leader sent a message without enough valid signatures.
+    NotEnoughSignatures,
+    /// An expected message of the leader did not arrive in time (e.g. the `NewView` message).
+    HeartbeatTimeout,
+    /// The new leader is behind the RPU.
+    OutdatedLeader,
+}
+
+/// Get the history of the leader terms seen by the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetLeaderTerms;
+
+/// A leader term seen by an RPU. See [`GetLeaderTerms`](message/struct.GetLeaderTerms.html).
+///
+/// The latest leader terms are persisted, so leadership churn can be correlated
+/// with hiccups of applications after the fact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct LeaderTermRecord {
+    /// The leader term.
+    pub leader_term: LeaderTerm,
+    /// The leader of the leader term.
+    pub leader: PeerId,
+    /// When the RPU entered the leader term.
+    pub started: SystemTime,
+    /// The number of the first block of the leader term.
+    pub start_block: BlockNumber,
+    /// The number of the first block of the next leader term (`None` for the current leader term).
+    pub end_block: Option<BlockNumber>,
+    /// Why the leader changed (e.g. the reason this RPU requested the view change).
+    pub reason: String,
+    /// The number of RPUs requesting the view change to the leader term per reason
+    /// (as far as seen by this RPU before the leader term started).
+    pub view_change_reasons: BTreeMap<ViewChangeReason, u32>,
+}
+
+/// The leader weight of RPUs whose weight was not set. See [`SetLeaderWeight`](transaction/struct.SetLeaderWeight.html).
+pub const DEFAULT_LEADER_WEIGHT: u32 = 1;
+
+/// The maximum leader weight of an RPU. See [`SetLeaderWeight`](transaction/struct.SetLeaderWeight.html).
+pub const MAX_LEADER_WEIGHT: u32 = 16;
+
+/// The queue weight of accounts whose weight was not set. See [`SetQueueWeight`](transaction/struct.SetQueueWeight.html).
+pub const DEFAULT_QUEUE_WEIGHT: u32 = 1;
+
+/// The maximum queue weight of an account. See [`SetQueueWeight`](transaction/struct.SetQueueWeight.html).
+pub const MAX_QUEUE_WEIGHT: u32 = 64;
+
+/// The maximum length of an idempotency key in bytes.
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+
+/// Execute a transaction at most once per idempotency key.
+///
+/// Gateways retrying a submission (e.g. after a timeout) send the same `idempotency_key` again.
+/// The RPU remembers the recent keys of each account and answers a retry with the original `Receipt`
+/// instead of queueing the transaction a second time.
+/// Keys are only remembered by the RPU the request was sent to, so retries need to be sent to the same RPU.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ExecuteIdempotent {
+    /// A key chosen by the client (at most `MAX_IDEMPOTENCY_KEY_LEN` bytes).
+    pub idempotency_key: String,
+    /// The transaction (signed by the same account as the request).
+    pub transaction: Signed<Transaction>,
+}
+
+/// The confirmation that an RPU accepted a transaction. See [`ExecuteIdempotent`](message/struct.ExecuteIdempotent.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Receipt {
+    /// The hash identifying the transaction.
+    pub transaction_hash: TransactionHash,
+    /// When the RPU accepted the transaction (the first time).
+    pub received_at: SystemTime,
+}
+
+/// Get whether a committed transaction was applied to the `WorldState` (only allowed for its signer and admins).
+///
+/// A committed transaction that cannot be applied (which the checks before proposing should prevent)
+/// is skipped on all RPUs alike. Returns `None` if the transaction is not committed (yet).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetExecutionReceipt {
+    /// The hash of the transaction (see `Receipt`).
+    pub transaction_hash: TransactionHash,
+}
+
+/// The outcome of a committed transaction. See [`GetExecutionReceipt`](message/struct.GetExecutionReceipt.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ExecutionReceipt {
+    /// The hash of the transaction.
+    pub transaction_hash: TransactionHash,
+    /// The number of the block containing the transaction.
+    pub block_number: BlockNumber,
+    /// The index of the transaction in the block.
+    pub index: usize,
+    /// Whether the transaction was applied.
+    pub status: ExecutionStatus,
+}
+
+/// Whether a committed transaction was applied to the `WorldState`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum ExecutionStatus {
+    /// The transaction was applied.
+    Applied,
+    /// The transaction could not be applied and was skipped.
+    Failed {
+        /// Why the transaction could not be applied.
+        reason: String,
+    },
+}
+
+impl ExecutionStatus {
+    /// Check whether the transaction was applied.
+    #[must_use]
+    pub const fn is_applied(&self) -> bool {
+        matches!(self, Self::Applied)
+    }
+}
+
+/// Get the proof which RPUs accepted a `Block`.
+///
+/// The proof can be verified without trusting the RPU. See [`BlockProof`](consensus/struct.BlockProof.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetBlockProof {
+    /// The number of the block.
+    pub block_number: BlockNumber,
+}
+
+/// Get the results of the block storage scrubber (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetScrubStatistics;
+
+/// The results of the block storage scrubber of an RPU. See [`GetScrubStatistics`](message/struct.GetScrubStatistics.html).
+///
+/// The scrubber periodically re-reads all stored blocks, verifies their hashes
+/// and replaces corrupted blocks with copies downloaded from other RPUs.
+/// The counters are totals since the RPU was started.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ScrubStatistics {
+    /// The number of finished scrubs.
+    pub scrubs: u64,
+    /// The number of verified blocks.
+    pub verified_blocks: u64,
+    /// The number of corrupted blocks found.
+    pub corrupted_blocks: u64,
+    /// The number of corrupted blocks replaced with a copy of another RPU.
+    pub repaired_blocks: u64,
+    /// The report of the latest finished scrub.
+    pub last_scrub: Option<ScrubReport>,
+}
+
+/// The report of a single scrub. See [`ScrubStatistics`](struct.ScrubStatistics.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ScrubReport {
+    /// When the scrub was started.
+    pub started_at: SystemTime,
+    /// How long the scrub took.
+    pub duration: Duration,
+    /// The number of verified blocks.
+    pub verified_blocks: u64,
+    /// The corrupted blocks that were replaced with a copy of another RPU.
+    pub repaired: Vec<BlockNumber>,
+    /// The corrupted blocks that could not be repaired.
+    pub unrepaired: Vec<BlockNumber>,
+}
+
+/// Compare the chain of the RPU with the chains of all other RPUs (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct CompareChains;
+
+/// The result of comparing the chains of all RPUs. See [`CompareChains`](message/struct.CompareChains.html).
+///
+/// The RPU asks the other RPUs for the hashes of sampled blocks.
+/// As each block contains the hash of its predecessor, the chains are equal up to a matching block,
+/// so the first mismatching block is found by narrowing down the samples.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ChainComparison {
+    /// The number of blocks of the comparing RPU.
+    pub block_number: BlockNumber,
+    /// The hash of the newest block of the comparing RPU.
+    pub last_block_hash: BlockHash,
+    /// The comparison with each other RPU.
+    pub peers: HashMap<PeerId, PeerChainComparison>,
+}
+
+/// The comparison of the chain of another RPU. See [`ChainComparison`](struct.ChainComparison.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum PeerChainComparison {
+    /// The chains were compared.
+    Compared {
+        /// The number of blocks of the RPU.
+        block_number: BlockNumber,
+        /// The first block that differs from the comparing RPU (`None` if the chains match
+        /// up to the shorter one).
+        first_mismatch: Option<BlockNumber>,
+    },
+    /// The RPU could not be asked.
+    Failed(String),
+}
+
+/// Get the RPU's share of an escrowed key. See [`escrow`](escrow/index.html).
+///
+/// Only allowed for auditors authorized by an `AuthorizeDecryption` transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetEscrowShare {
+    /// The escrowed key.
+    pub key_reference: KeyReference,
+    /// The RPU the request is sent to (other RPUs reject it).
+    pub rpu: PeerId,
+    /// The public key the share is sealed to (see `escrow::RecipientKey`).
+    pub recipient_key: [u8; RECIPIENT_KEY_LEN],
+    /// When the request was created (stale requests are rejected).
+    pub timestamp: SystemTime,
+}
+
+/// The share of an escrowed key held by an RPU. See [`GetEscrowShare`](message/struct.GetEscrowShare.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct EscrowShare {
+    /// The share of the RPU (sealed to the recipient key of the request).
+    pub sealed_share: SealedShare,
+    /// The hash of the key (to verify the reconstructed key).
+    pub key_hash: BlockHash,
+    /// The number of shares needed to reconstruct the key.
+    pub threshold: usize,
+}
+
+/// A challenge of an RPU to open a session. See [`OpenSession`](message/struct.OpenSession.html).
+///
+/// The challenge is only valid for a short time after it was issued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionChallenge {
+    /// When the RPU issued the challenge.
+    pub issued_at: SystemTime,
+    /// Random bytes chosen by the RPU.
+    pub nonce: Vec<u8>,
+    /// The RPU's authentication code of the challenge.
+    pub mac: Vec<u8>,
+}
+
+/// Open a session with a challenge of the RPU.
+///
+/// Signing the challenge proves the possession of the account's key once,
+/// later requests of the session are authenticated by the returned `SessionToken`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct OpenSession {
+    /// The challenge received from the RPU.
+    pub challenge: SessionChallenge,
+}
+
+/// A short-lived token authenticating the requests of a session.
+///
+/// A token is only accepted by the RPU that issued it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionToken {
+    /// The account that opened the session.
+    pub peer_id: PeerId,
+    /// When the session expires.
+    pub expires_at: SystemTime,
+    /// The RPU's authentication code of the token.
+    pub mac: Vec<u8>,
+}
+
+impl SessionToken {
+    /// Whether the session is expired.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        SystemTime::now() >= self.expires_at
+    }
+}
+
+/// Get the metrics of the connections of the RPU to other servers (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetNetworkMetrics;
+
+/// The metrics of the connections of an RPU to a single server (e.g. another RPU).
+/// See [`GetNetworkMetrics`](message/struct.GetNetworkMetrics.html).
+///
+/// The counters are totals since the RPU was started.
+/// They show network problems separately from their symptoms in the consensus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ConnectionMetrics {
+    /// The address of the server.
+    pub address: SocketAddr,
+    /// The number of connections currently open (in use or idle in the connection pool).
+    pub open_connections: u64,
+    /// The number of times a connection was taken from the connection pool (or newly established).
+    pub checkouts: u64,
+    /// The total time spent waiting for a connection.
+    pub checkout_wait: Duration,
+    /// The longest time spent waiting for a connection.
+    pub max_checkout_wait: Duration,
+    /// The number of newly established connections.
+    pub connects: u64,
+    /// The number of connections established to replace a closed connection.
+    pub reconnects: u64,
+    /// The number of connections that could not be established.
+    pub connect_failures: u64,
+    /// The number of successful requests.
+    pub successes: u64,
+    /// The number of requests that failed because of a timeout.
+    pub timeouts: u64,
+    /// The number of requests whose response could not be decoded.
+    pub decode_errors: u64,
+    /// The number of requests that failed because of the connection.
+    pub io_errors: u64,
+    /// The number of requests that failed for other reasons (e.g. rejected by the server).
+    pub other_errors: u64,
+}
+
+/// Get the metrics of the requests handled by the RPU (only allowed for admins).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetRequestMetrics;
+
+/// The metrics of a single request type handled by an RPU (e.g. `Execute` of the client API).
+/// See [`GetRequestMetrics`](message/struct.GetRequestMetrics.html).
+///
+/// The counters are totals since the RPU was started.
+/// Requests that cannot be decoded or are rejected before reaching their handler are not counted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct RequestMetrics {
+    /// The API of the request (`ClientMessage` or `PeerMessage`).
+    pub api: String,
+    /// The type of the request.
+    pub request: String,
+    /// The number of handled requests.
+    pub requests: u64,
+    /// The number of requests answered with an error.
+    pub errors: u64,
+    /// The sizes of the encoded requests (in bytes).
+    pub request_size: Histogram,
+    /// The sizes of the encoded responses (in bytes, streamed responses are not recorded).
+    pub response_size: Histogram,
+    /// The time until the response (or the first item of a stream) was ready (in µs).
+    pub latency: Histogram,
+}
+
+/// A histogram with fixed buckets. See [`RequestMetrics`](struct.RequestMetrics.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Histogram {
+    /// The inclusive upper bound of each bucket and the number of values in the bucket.
+    ///
+    /// The last bucket is unbounded (`u64::MAX`).
+    pub buckets: Vec<(u64, u64)>,
+    /// The number of recorded values.
+    pub count: u64,
+    /// The sum of all recorded values.
+    pub sum: u64,
+}
+
+/// Get where a transaction is on the RPU right now (only allowed for admins).
+///
+/// The transaction hash serves as trace id. It is returned to the client in the `Receipt`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct GetTransactionTrace {
+    /// The hash of the transaction.
+    pub hash: TransactionHash,
+}
+
+/// The way of a transaction through an RPU. See [`GetTransactionTrace`](message/struct.GetTransactionTrace.html).
+///
+/// Traces are only kept in memory for the most recent transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct TransactionTrace {
+    /// The RPU that recorded the trace.
+    pub rpu: PeerId,
+    /// Where the transaction is right now.
+    pub status: TransactionStatus,
+    /// The recorded events (oldest first).
+    pub events: Vec<TraceEvent>,
+}
+
+/// Where a transaction is on an RPU right now. See [`TransactionTrace`](struct.TransactionTrace.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum TransactionStatus {
+    /// The RPU has no trace of the transaction (or already forgot it).
+    Unknown,
+    /// The transaction was received, but is not queued yet.
+    Received,
+    /// The transaction is waiting in the queue.
+    Queued {
+        /// How long the transaction has been waiting in the queue.
+        queued_for: Duration,
+    },
+    /// The transaction was proposed in a block that is not committed yet.
+    Proposed {
+        /// The leader term of the proposal.
+        leader_term: LeaderTerm,
+        /// The number of the proposed block.
+        block_number: BlockNumber,
+    },
+    /// The transaction was committed.
+    Committed {
+        /// The number of the block containing the transaction.
+        block_number: BlockNumber,
+    },
+    /// The transaction was rejected as invalid by the leader.
+    Rejected {
+        /// The number of the block it was rejected in.
+        block_number: BlockNumber,
+    },
+    /// The transaction was evicted from the queue by an admin.
+    Evicted,
+    /// The transaction was removed from the queue because its deadline passed.
+    Expired,
+    /// The transaction was committed, but could not be applied to the `WorldState` (it was skipped).
+    Failed {
+        /// The number of the block containing the transaction.
+        block_number: BlockNumber,
+        /// Why the transaction could not be applied.
+        reason: String,
+    },
+}
+
+/// An event in the trace of a transaction. See [`TransactionTrace`](struct.TransactionTrace.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct TraceEvent {
+    /// When the event happened.
+    pub time: SystemTime,
+    /// What happened.
+    pub kind: TraceEventKind,
+}
+
+/// The kind of a `TraceEvent`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum TraceEventKind {
+    /// The transaction was submitted by a client to this RPU.
+    Submitted,
+    /// The transaction was received in a batch of another RPU.
+    ReceivedFrom(PeerId),
+    /// The transaction was added to the queue.
+    Queued,
+    /// The transaction was proposed in a block.
+    Proposed {
+        /// The leader term of the proposal.
+        leader_term: LeaderTerm,
+        /// The number of the proposed block.
+        block_number: BlockNumber,
+    },
+    /// The block containing the transaction was committed.
+    Committed {
+        /// The number of the block.
+        block_number: BlockNumber,
+    },
+    /// The transaction was rejected as invalid by the leader.
+    Rejected {
+        /// The number of the block it was rejected in.
+        block_number: BlockNumber,
+    },
+    /// The block containing the transaction was rolled back (the transaction is queued again).
+    RolledBack {
+        /// The number of the block.
+        block_number: BlockNumber,
+    },
+    /// The transaction was evicted from the queue by an admin.
+    Evicted,
+    /// The transaction was removed from the queue because its deadline passed.
+    Expired,
+    /// The block containing the transaction was committed, but the transaction could not be applied.
+    Failed {
+        /// The number of the block.
+        block_number: BlockNumber,
+        /// Why the transaction could not be applied.
+        reason: String,
+    },
+}
+
+/// Execute a transaction that must be committed before a deadline.
+///
+/// The transaction is submitted like with `ExecuteIdempotent`. If it is not committed
+/// by the `deadline` (e.g. because of repeated view changes), the RPU removes it from the queues
+/// of all RPUs and marks the receipt as expired instead of committing it arbitrarily late.
+/// The deadline is checked by the RPU the request was sent to, so the receipt can only be
+/// watched there (see [`WatchReceipt`](message/struct.WatchReceipt.html)).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct ExecuteWithDeadline {
+    /// A key chosen by the client (at most `MAX_IDEMPOTENCY_KEY_LEN` bytes).
+    pub idempotency_key: String,
+    /// The transaction (signed by the same account as the request).
+    pub transaction: Signed<Transaction>,
+    /// The time by which the transaction must be committed (measured by the clocks of the RPUs).
+    pub deadline: SystemTime,
+}
+
+/// Watch the status of a receipt of `ExecuteWithDeadline`.
+///
+/// The current `ReceiptStatus` is sent first, followed by every change until the status is final.
+/// Only the signer of the transaction may watch its receipt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct WatchReceipt {
+    /// The hash of the transaction (see `Receipt`).
+    pub transaction_hash: TransactionHash,
+}
+
+/// The status of a receipt of `ExecuteWithDeadline`. See [`WatchReceipt`](message/struct.WatchReceipt.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum ReceiptStatus {
+    /// The transaction is not committed yet.
+    Pending {
+        /// The time by which the transaction must be committed.
+        deadline: SystemTime,
+    },
+    /// The transaction was committed.
+    Committed {
+        /// The number of the block containing the transaction.
+        block_number: BlockNumber,
+    },
+    /// The transaction was rejected as invalid by the leader.
+    Rejected {
+        /// The number of the block it was rejected in.
+        block_number: BlockNumber,
+    },
+    /// The transaction was evicted from the queue by an admin.
+    Evicted,
+    /// The transaction was not committed by the deadline and was removed from the queues.
+    Expired {
+        /// The deadline of the transaction.
+        deadline: SystemTime,
+    },
+    /// The transaction was committed, but could not be applied to the `WorldState` (it was skipped).
+    Failed {
+        /// The number of the block containing the transaction.
+        block_number: BlockNumber,
+        /// Why the transaction could not be applied.
+        reason: String,
+    },
+}
+
+impl ReceiptStatus {
+    /// Check whether the status will not change anymore.
+    #[must_use]
+    pub const fn is_final(&self) -> bool {
+        !matches!(self, Self::Pending { .. })
+    }
+}
+
+/// A request authenticated by a `SessionToken` instead of a signature.
+///
+/// Transactions contained in the request stay individually signed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct InSession<T> {
+    /// The token of the session.
+    pub token: SessionToken,
+    /// The request.
+    pub request: T,
+}
+
 #[derive(Serialize)]
 enum ClientMessageSigningData<'a> {
     Execute(&'a Transaction),
@@ -311,6 +1259,29 @@ enum ClientMessageSigningData<'a> {
     GetAccount(&'a GetAccount),
     GetBlock(&'a GetBlock),
     GetCurrentBlockNumber(&'a GetCurrentBlockNumber),
+    GetBannedPeers(&'a GetBannedPeers),
+    UnbanPeer(&'a UnbanPeer),
+    GetQueue(&'a GetQueue),
+    EvictTransactions(&'a EvictTransactions),
+    GetClockSkews(&'a GetClockSkews),
+    StreamBlocks(&'a StreamBlocks),
+    GetConsensusFailures(&'a GetConsensusFailures),
+    GetBlockProof(&'a GetBlockProof),
+    ExecuteIdempotent(&'a ExecuteIdempotent),
+    GetScrubStatistics(&'a GetScrubStatistics),
+    CompareChains(&'a CompareChains),
+    GetEscrowShare(&'a GetEscrowShare),
+    OpenSession(&'a OpenSession),
+    GetNetworkMetrics(&'a GetNetworkMetrics),
+    GetTransactionTrace(&'a GetTransactionTrace),
+    ExecuteWithDeadline(&'a ExecuteWithDeadline),
+    WatchReceipt(&'a WatchReceipt),
+    GetLeaderTerms(&'a GetLeaderTerms),
+    GetExecutionReceipt(&'a GetExecutionReceipt),
+    GetRequestMetrics(&'a GetRequestMetrics),
+    GetCertifiedHead(&'a GetCertifiedHead),
+    SubscribeBlocks(&'a SubscribeBlocks),
+    GetStatus(&'a GetStatus),
 }
 
 macro_rules! impl_signable {
@@ -330,13 +1301,37 @@ impl_signable!(
     GetValue => GetValue,
     GetAccount => GetAccount,
     GetBlock => GetBlock,
-    GetCurrentBlockNumber => GetCurrentBlockNumber
+    GetCurrentBlockNumber => GetCurrentBlockNumber,
+    GetBannedPeers => GetBannedPeers,
+    UnbanPeer => UnbanPeer,
+    GetQueue => GetQueue,
+    EvictTransactions => EvictTransactions,
+    GetClockSkews => GetClockSkews,
+    StreamBlocks => StreamBlocks,
+    GetConsensusFailures => GetConsensusFailures,
+    GetBlockProof => GetBlockProof,
+    ExecuteIdempotent => ExecuteIdempotent,
+    GetScrubStatistics => GetScrubStatistics,
+    CompareChains => CompareChains,
+    GetEscrowShare => GetEscrowShare,
+    OpenSession => OpenSession,
+    GetNetworkMetrics => GetNetworkMetrics,
+    GetTransactionTrace => GetTransactionTrace,
+    ExecuteWithDeadline => ExecuteWithDeadline,
+    WatchReceipt => WatchReceipt,
+    GetLeaderTerms => GetLeaderTerms,
+    GetExecutionReceipt => GetExecutionReceipt,
+    GetRequestMetrics => GetRequestMetrics,
+    GetCertifiedHead => GetCertifiedHead,
+    SubscribeBlocks => SubscribeBlocks,
+    GetStatus => GetStatus
 );
 
 /// A blockchain transaction for prellblock.
 #[allow(clippy::large_enum_variant)]
 #[newtype_enum(variants = "transaction")]
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub enum Transaction {
     /// Set a `key` to a `value`.
     KeyValue {
@@ -374,6 +1369,189 @@ pub enum Transaction {
         /// The timestamp of transaction creation.
         timestamp: SystemTime,
     },
+    /// Set the `Schema` of all values in a key namespace.
+    SetSchema {
+        /// The key prefix the schema applies to.
+        namespace: String,
+        /// The schema to set (`None` removes the schema).
+        schema: Option<Schema>,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Schedule the migration of the `WorldState` to a new format `version`.
+    ///
+    /// All RPUs migrate their `WorldState` before applying the block at `activation_height`.
+    /// A later `ScheduleMigration` replaces the scheduled migration.
+    ScheduleMigration {
+        /// The format version to migrate to.
+        version: u32,
+        /// The number of the first block applied to the migrated `WorldState`.
+        activation_height: BlockNumber,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Set a governed `Parameter` of the blockchain.
+    ///
+    /// The value applies to all blocks from `activation_height` on.
+    /// A later `SetParameter` of the same parameter replaces a change that is not yet active.
+    SetParameter {
+        /// The parameter to set.
+        parameter: Parameter,
+        /// The new value.
+        value: u64,
+        /// The number of the first block the value applies to.
+        activation_height: BlockNumber,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Set a `key` to a `value` described by `metadata` (e.g. its unit).
+    KeyValueWithMetadata {
+        /// The key.
+        key: String,
+        /// The value.
+        value: Vec<u8>,
+        /// The metadata of the value.
+        metadata: Metadata,
+        /// The Timestamp.
+        timestamp: SystemTime,
+    },
+    /// Set the leader weight of an RPU.
+    ///
+    /// An RPU with weight `w` leads `w` out of `W` consecutive leader terms (`W` is the sum of all weights),
+    /// so after a view change the RPUs with higher weights are tried first.
+    SetLeaderWeight {
+        /// The RPU to set the weight of.
+        id: PeerId,
+        /// The weight (`1` to `MAX_LEADER_WEIGHT`, `DEFAULT_LEADER_WEIGHT` for RPUs without weight).
+        weight: u32,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Set a `key` to a `value` encrypted by the client. See [`encryption`](encryption/index.html).
+    EncryptedKeyValue {
+        /// The key.
+        key: String,
+        /// The encrypted value.
+        value: EncryptedValue,
+        /// The Timestamp.
+        timestamp: SystemTime,
+    },
+    /// Escrow a namespace key by recording the hashes of its shares held by the RPUs.
+    /// See [`escrow`](escrow/index.html).
+    ///
+    /// A later `EscrowKey` of the same key replaces the escrow (and its authorizations).
+    EscrowKey {
+        /// The escrowed key.
+        key_reference: KeyReference,
+        /// The hash of the key (to verify the reconstructed key).
+        key_hash: BlockHash,
+        /// The number of shares needed to reconstruct the key (at least a supermajority of the RPUs).
+        threshold: usize,
+        /// The hashes of the shares held by each RPU.
+        #[serde(deserialize_with = "limits::peers")]
+        share_hashes: Vec<(PeerId, ShareHash)>,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Authorize an `auditor` to collect the shares of an escrowed key from the RPUs.
+    AuthorizeDecryption {
+        /// The escrowed key.
+        key_reference: KeyReference,
+        /// The account allowed to collect the shares.
+        auditor: PeerId,
+        /// The reason of the audit (recorded on-chain).
+        reason: String,
+        /// When the authorization expires (the RPUs release no shares afterwards).
+        expires_at: SystemTime,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+        /// The approvals of a supermajority of the RPUs (see `AuthorizeDecryption::approve`).
+        #[serde(default)]
+        approvals: SignatureList,
+    },
+    /// Set the queue weight of a writing account.
+    ///
+    /// The leader fills blocks from the queued transactions of all accounts in a weighted round robin:
+    /// An account with weight `w` gets up to `w` transactions per round, so one account cannot dominate the blocks.
+    SetQueueWeight {
+        /// The account to set the weight of.
+        id: PeerId,
+        /// The weight (`1` to `MAX_QUEUE_WEIGHT`, `DEFAULT_QUEUE_WEIGHT` for accounts without weight).
+        weight: u32,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Set the write throughput `Quota` of a key namespace. See [`quota`](quota/index.html).
+    ///
+    /// The usage of the namespace starts over with the new quota.
+    SetQuota {
+        /// The key prefix the quota applies to.
+        namespace: String,
+        /// The quota to set (`None` removes the quota).
+        quota: Option<Quota>,
+        /// The timestamp of transaction creation.
+        timestamp: SystemTime,
+    },
+    /// Set multiple keys to values at once.
+    ///
+    /// All values are written, or none of them if any key may not be written
+    /// (e.g. a value violates the schema of its key), so readers see a consistent snapshot.
+    MultiKeyValue {
+        /// The keys and their values (each key at most once, at most `MAX_VALUES_PER_TRANSACTION`).
+        #[serde(deserialize_with = "limits::values_per_transaction")]
+        values: Vec<(String, Vec<u8>)>,
+        /// The Timestamp.
+        timestamp: SystemTime,
+    },
+    /// Set a shared `key` to `new` if its current value is `expected` (e.g. to claim a lock).
+    ///
+    /// The values are compared when the transaction is committed, the outcome is recorded
+    /// in its `ExecutionReceipt` (a mismatch fails the transaction).
+    /// Unlike other keys, these keys are shared by all accounts.
+    CompareAndSwap {
+        /// The key.
+        key: String,
+        /// The expected current value (`None` if the key must not be set).
+        expected: Option<Vec<u8>>,
+        /// The new value (`None` removes the key).
+        new: Option<Vec<u8>>,
+        /// The Timestamp.
+        timestamp: SystemTime,
+    },
+}
+
+impl Transaction {
+    /// Get the timestamp of the transaction.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn timestamp(&self) -> SystemTime {
+        match self {
+            Self::KeyValue(params) => params.timestamp,
+            Self::UpdateAccount(params) => params.timestamp,
+            Self::CreateAccount(params) => params.timestamp,
+            Self::DeleteAccount(params) => params.timestamp,
+            Self::SetSchema(params) => params.timestamp,
+            Self::ScheduleMigration(params) => params.timestamp,
+            Self::SetParameter(params) => params.timestamp,
+            Self::KeyValueWithMetadata(params) => params.timestamp,
+            Self::SetLeaderWeight(params) => params.timestamp,
+            Self::EncryptedKeyValue(params) => params.timestamp,
+            Self::EscrowKey(params) => params.timestamp,
+            Self::AuthorizeDecryption(params) => params.timestamp,
+            Self::SetQueueWeight(params) => params.timestamp,
+            Self::SetQuota(params) => params.timestamp,
+            Self::MultiKeyValue(params) => params.timestamp,
+            Self::CompareAndSwap(params) => params.timestamp,
+        }
+    }
+
+    /// Get the size of the encoded transaction in bytes (without the signature).
+    ///
+    /// RPUs reject transactions larger than the governed `Parameter::MaxTransactionSize`.
+    #[must_use]
+    pub fn payload_size(&self) -> usize {
+        postcard::to_stdvec(self).unwrap().len()
+    }
 }
 
 /// A trait signifying that a transaction can be written into the Account-tree in the `DataStorage`.
@@ -382,3 +1560,12 @@ pub trait AccountTransaction {}
 impl AccountTransaction for transaction::UpdateAccount {}
 impl AccountTransaction for transaction::CreateAccount {}
 impl AccountTransaction for transaction::DeleteAccount {}
+impl AccountTransaction for transaction::SetSchema {}
+impl AccountTransaction for transaction::ScheduleMigration {}
+impl AccountTransaction for transaction::SetParameter {}
+impl AccountTransaction for transaction::SetLeaderWeight {}
+impl AccountTransaction for transaction::EscrowKey {}
+impl AccountTransaction for transaction::AuthorizeDecryption {}
+impl AccountTransaction for transaction::SetQueueWeight {}
+impl AccountTransaction for transaction::SetQuota {}
+impl AccountTransaction for transaction::CompareAndSwap {}
@@ -0,0 +1,131 @@
+//! Limits on the size of deserialized messages.
+//!
+//! Byzantine RPUs and clients can send messages that announce huge lengths.
+//! The limits are enforced while deserializing: a sequence is rejected as soon as its announced
+//! (or actual) length exceeds the limit, before memory is allocated for its items.
+//!
+//! # Example
+//!
+//! ```
+//! use prellblock_client_api::limits;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Ballot {
+//!     #[serde(deserialize_with = "limits::peers")]
+//!     votes: Vec<u8>,
+//! }
+//!
+//! let bytes = postcard::to_stdvec(&vec![0_u8; limits::MAX_PEERS]).unwrap();
+//! assert!(postcard::from_bytes::<Ballot>(&bytes).is_ok());
+//!
+//! let bytes = postcard::to_stdvec(&vec![0_u8; limits::MAX_PEERS + 1]).unwrap();
+//! assert!(postcard::from_bytes::<Ballot>(&bytes).is_err());
+//! ```
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{fmt, marker::PhantomData};
+
+/// The maximum number of transactions in a block (and in a consensus message).
+pub const MAX_TRANSACTIONS_PER_BLOCK: usize = 4000;
+
+/// The maximum number of transactions in a batch sent from one RPU to another.
+///
+/// This is the maximum of `Parameter::MaxTransactionsPerBatch`.
+pub const MAX_TRANSACTIONS_PER_BATCH: usize = 100_000;
+
+/// The maximum number of RPUs (and thus of the signatures in a `SignatureList`).
+pub const MAX_PEERS: usize = 1024;
+
+/// The maximum number of values set by a single `MultiKeyValue` transaction.
+pub const MAX_VALUES_PER_TRANSACTION: usize = 65_536;
+
+/// The number of items allocated in advance, regardless of the announced length.
+const MAX_PREALLOCATED: usize = 256;
+
+/// Deserialize a `Vec` of at most `max_len` items.
+pub fn deserialize_bounded<'de, D, T>(deserializer: D, max_len: usize) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserializer.deserialize_seq(BoundedVisitor {
+        max_len,
+        marker: PhantomData,
+    })
+}
+
+/// Deserialize the transactions of a block (at most `MAX_TRANSACTIONS_PER_BLOCK`).
+pub fn transactions_per_block<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserialize_bounded(deserializer, MAX_TRANSACTIONS_PER_BLOCK)
+}
+
+/// Deserialize the transactions of a batch (at most `MAX_TRANSACTIONS_PER_BATCH`).
+pub fn transactions_per_batch<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserialize_bounded(deserializer, MAX_TRANSACTIONS_PER_BATCH)
+}
+
+/// Deserialize one item per RPU (at most `MAX_PEERS`).
+pub fn peers<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserialize_bounded(deserializer, MAX_PEERS)
+}
+
+/// Deserialize the values of a transaction (at most `MAX_VALUES_PER_TRANSACTION`).
+pub fn values_per_transaction<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserialize_bounded(deserializer, MAX_VALUES_PER_TRANSACTION)
+}
+
+struct BoundedVisitor<T> {
+    max_len: usize,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> Visitor<'de> for BoundedVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a sequence of at most {} items", self.max_len)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let len = seq.size_hint().unwrap_or(0);
+        if len > self.max_len {
+            return Err(de::Error::invalid_length(len, &self));
+        }
+
+        // The announced length is not trusted beyond the limit.
+        let mut items = Vec::with_capacity(len.min(MAX_PREALLOCATED));
+        while let Some(item) = seq.next_element()? {
+            if items.len() == self.max_len {
+                return Err(de::Error::invalid_length(self.max_len + 1, &self));
+            }
+            items.push(item);
+        }
+        Ok(items)
+    }
+}
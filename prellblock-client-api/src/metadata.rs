@@ -0,0 +1,236 @@
+//! Metadata describing the value of a `KeyValueWithMetadata` transaction.
+//!
+//! The metadata is validated by all RPUs and stored with the transaction in the block,
+//! so consumers of the blockchain do not have to guess the semantics of the raw value.
+
+use err_derive::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The maximum length of a unit (in bytes).
+pub const MAX_UNIT_LEN: usize = 32;
+
+/// The maximum offset (in minutes) of a time zone to UTC.
+pub const MAX_UTC_OFFSET_MINUTES: i16 = 18 * 60;
+
+/// An error while validating or parsing `Metadata`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum MetadataError {
+    /// The unit is empty, too long or contains characters other than printable ASCII.
+    #[error(
+        display = "The unit must consist of 1 to {} printable ASCII characters.",
+        MAX_UNIT_LEN
+    )]
+    InvalidUnit,
+
+    /// The offset of the time zone is out of range.
+    #[error(display = "The UTC offset of {} minutes is out of range.", 0)]
+    InvalidUtcOffset(i16),
+
+    /// The source time is more precise than its precision claims.
+    #[error(display = "The source time is more precise than {}.", 0)]
+    PrecisionExceeded(TimePrecision),
+
+    /// The time precision is unknown.
+    #[error(display = "Unknown time precision {:?}.", 0)]
+    UnknownPrecision(String),
+
+    /// The quality is unknown.
+    #[error(display = "Unknown quality {:?}.", 0)]
+    UnknownQuality(String),
+}
+
+/// The metadata of a value.
+///
+/// # Example
+///
+/// ```
+/// use prellblock_client_api::metadata::{Metadata, Quality, SourceTimestamp, TimePrecision};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let metadata = Metadata {
+///     unit: Some("km/h".to_string()),
+///     source_timestamp: Some(SourceTimestamp {
+///         time: UNIX_EPOCH + Duration::from_millis(1_590_000_000_123),
+///         utc_offset_minutes: 120,
+///         precision: TimePrecision::Milliseconds,
+///     }),
+///     quality: Some(Quality::Good),
+/// };
+/// assert!(metadata.validate().is_ok());
+///
+/// // The time has a higher precision than claimed.
+/// let mut source_timestamp = metadata.source_timestamp.unwrap();
+/// source_timestamp.precision = TimePrecision::Seconds;
+/// assert!(source_timestamp.validate().is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Metadata {
+    /// The unit of the value (e.g. `km/h`).
+    pub unit: Option<String>,
+    /// The time the value was recorded at its source.
+    pub source_timestamp: Option<SourceTimestamp>,
+    /// The quality of the value as reported by its source.
+    pub quality: Option<Quality>,
+}
+
+impl Metadata {
+    /// Verify that the metadata is well-formed.
+    pub fn validate(&self) -> Result<(), MetadataError> {
+        if let Some(unit) = &self.unit {
+            if unit.is_empty()
+                || unit.len() > MAX_UNIT_LEN
+                || !unit
+                    .bytes()
+                    .all(|byte| byte.is_ascii_graphic() || byte == b' ')
+            {
+                return Err(MetadataError::InvalidUnit);
+            }
+        }
+        if let Some(source_timestamp) = &self.source_timestamp {
+            source_timestamp.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// The time a value was recorded at its source (e.g. a sensor).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SourceTimestamp {
+    /// The point in time.
+    pub time: SystemTime,
+    /// The offset (in minutes) of the source's local time zone to UTC.
+    pub utc_offset_minutes: i16,
+    /// The precision of `time`.
+    pub precision: TimePrecision,
+}
+
+impl SourceTimestamp {
+    /// Verify that the offset is in range and the time matches the precision.
+    pub fn validate(&self) -> Result<(), MetadataError> {
+        if !(-MAX_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&self.utc_offset_minutes) {
+            return Err(MetadataError::InvalidUtcOffset(self.utc_offset_minutes));
+        }
+        let since_epoch = match self.time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration,
+            Err(err) => err.duration(),
+        };
+        if since_epoch.subsec_nanos() % self.precision.nanos() != 0 {
+            return Err(MetadataError::PrecisionExceeded(self.precision));
+        }
+        Ok(())
+    }
+}
+
+/// The precision of a `SourceTimestamp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum TimePrecision {
+    /// Whole seconds.
+    Seconds,
+    /// Milliseconds.
+    Milliseconds,
+    /// Microseconds.
+    Microseconds,
+    /// Nanoseconds.
+    Nanoseconds,
+}
+
+impl TimePrecision {
+    /// All time precisions.
+    pub const ALL: &'static [Self] = &[
+        Self::Seconds,
+        Self::Milliseconds,
+        Self::Microseconds,
+        Self::Nanoseconds,
+    ];
+
+    /// The name of the precision (as used in the command line client).
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Seconds => "s",
+            Self::Milliseconds => "ms",
+            Self::Microseconds => "us",
+            Self::Nanoseconds => "ns",
+        }
+    }
+
+    /// The number of nanoseconds in one unit of the precision.
+    const fn nanos(self) -> u32 {
+        match self {
+            Self::Seconds => 1_000_000_000,
+            Self::Milliseconds => 1_000_000,
+            Self::Microseconds => 1000,
+            Self::Nanoseconds => 1,
+        }
+    }
+}
+
+impl fmt::Display for TimePrecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TimePrecision {
+    type Err = MetadataError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|precision| precision.name() == s)
+            .ok_or_else(|| MetadataError::UnknownPrecision(s.to_string()))
+    }
+}
+
+/// The quality of a value as reported by its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum Quality {
+    /// The value is reliable.
+    Good,
+    /// The value may be inaccurate (e.g. a sensor outside its calibrated range).
+    Uncertain,
+    /// The value is not reliable (e.g. a sensor failure).
+    Bad,
+}
+
+impl Quality {
+    /// All qualities.
+    pub const ALL: &'static [Self] = &[Self::Good, Self::Uncertain, Self::Bad];
+
+    /// The name of the quality (as used in the command line client).
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Good => "good",
+            Self::Uncertain => "uncertain",
+            Self::Bad => "bad",
+        }
+    }
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Quality {
+    type Err = MetadataError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|quality| quality.name() == s)
+            .ok_or_else(|| MetadataError::UnknownQuality(s.to_string()))
+    }
+}
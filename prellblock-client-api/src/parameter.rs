@@ -0,0 +1,123 @@
+//! Parameters of the blockchain that are governed on-chain.
+//!
+//! Changing a parameter with a `SetParameter` transaction takes effect at a future block height,
+//! so all RPUs switch their behavior at the same block.
+
+use err_derive::Error;
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+/// A parameter of the blockchain that is governed on-chain.
+///
+/// All parameters are integers; durations are given in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum Parameter {
+    /// The maximum number of transactions in a block.
+    MaxTransactionsPerBlock,
+    /// The maximum time (in ms) the leader waits for a full block before proposing the pending transactions.
+    BlockGenerationTimeout,
+    /// The time (in ms) after which a queued transaction is considered censored.
+    CensorshipTimeout,
+    /// The maximum difference (in ms) between the timestamp of a block and the local time.
+    TimestampTolerance,
+    /// The maximum number of transactions an RPU aggregates into a batch.
+    MaxTransactionsPerBatch,
+    /// The maximum time (in ms) an RPU aggregates transactions before sending a batch.
+    MaxTimeBetweenBatches,
+    /// The maximum size (in bytes) of the encoded payload of a transaction (see `Transaction::payload_size`).
+    MaxTransactionSize,
+    /// The minimum time (in ms) between two blocks proposed by a leader.
+    MinBlockInterval,
+    /// The maximum random delay (in ms) added to the `MinBlockInterval` before each block.
+    BlockIntervalJitter,
+    /// The number of faulty RPUs `f` the consensus tolerates (at least `3f + 1` RPUs are needed).
+    ///
+    /// 0 tolerates as many faulty RPUs as the number of RPUs allows.
+    FaultTolerance,
+}
+
+/// The `Parameter` is unknown.
+#[derive(Debug, Error)]
+#[error(display = "Unknown parameter {:?}.", 0)]
+pub struct UnknownParameter(String);
+
+impl Parameter {
+    /// All parameters.
+    pub const ALL: &'static [Self] = &[
+        Self::MaxTransactionsPerBlock,
+        Self::BlockGenerationTimeout,
+        Self::CensorshipTimeout,
+        Self::TimestampTolerance,
+        Self::MaxTransactionsPerBatch,
+        Self::MaxTimeBetweenBatches,
+        Self::MaxTransactionSize,
+        Self::MinBlockInterval,
+        Self::BlockIntervalJitter,
+        Self::FaultTolerance,
+    ];
+
+    /// The value of the parameter until it is set.
+    #[must_use]
+    pub const fn default_value(self) -> u64 {
+        match self {
+            Self::MaxTransactionsPerBlock | Self::MaxTransactionsPerBatch => 4000,
+            Self::BlockGenerationTimeout | Self::MaxTimeBetweenBatches => 400,
+            Self::CensorshipTimeout => 10_000,
+            Self::TimestampTolerance => 30_000,
+            Self::MaxTransactionSize => 65_536,
+            Self::MinBlockInterval | Self::BlockIntervalJitter | Self::FaultTolerance => 0,
+        }
+    }
+
+    /// The values the parameter can be set to.
+    #[must_use]
+    pub const fn valid_values(self) -> RangeInclusive<u64> {
+        match self {
+            // Blocks are bounded to keep the consensus messages small.
+            Self::MaxTransactionsPerBlock => 1..=4000,
+            Self::MaxTransactionsPerBatch => 1..=100_000,
+            Self::BlockGenerationTimeout | Self::MaxTimeBetweenBatches => 1..=60_000,
+            Self::CensorshipTimeout => 1000..=3_600_000,
+            Self::TimestampTolerance => 1000..=300_000,
+            Self::MaxTransactionSize => 1024..=4_194_304,
+            Self::MinBlockInterval => 0..=60_000,
+            Self::BlockIntervalJitter => 0..=10_000,
+            Self::FaultTolerance => 0..=1000,
+        }
+    }
+
+    /// The name of the parameter (as used in the command line client).
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::MaxTransactionsPerBlock => "max_transactions_per_block",
+            Self::BlockGenerationTimeout => "block_generation_timeout",
+            Self::CensorshipTimeout => "censorship_timeout",
+            Self::TimestampTolerance => "timestamp_tolerance",
+            Self::MaxTransactionsPerBatch => "max_transactions_per_batch",
+            Self::MaxTimeBetweenBatches => "max_time_between_batches",
+            Self::MaxTransactionSize => "max_transaction_size",
+            Self::MinBlockInterval => "min_block_interval",
+            Self::BlockIntervalJitter => "block_interval_jitter",
+            Self::FaultTolerance => "fault_tolerance",
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Parameter {
+    type Err = UnknownParameter;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|parameter| parameter.name() == s)
+            .ok_or_else(|| UnknownParameter(s.to_string()))
+    }
+}
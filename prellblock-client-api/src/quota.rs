@@ -0,0 +1,77 @@
+//! Write throughput quotas of key namespaces.
+//!
+//! Admins can set a `Quota` for a key namespace (a key prefix) with a `SetQuota` transaction.
+//! All RPUs count the values written to keys in that namespace per time window
+//! and reject values exceeding the quota when validating a block,
+//! so neither a gateway nor a leader can flood the chain with writes to the namespace.
+//!
+//! The windows are aligned to the block timestamps (not the local clocks), so all RPUs agree
+//! on the usage: a window starts at a multiple of `window_ms` milliseconds since the epoch.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The maximum number of transactions and bytes a key namespace may commit per time window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Quota {
+    /// The maximum number of transactions per window.
+    pub max_transactions: u64,
+    /// The maximum size of the transactions per window (in bytes, see `Transaction::payload_size`).
+    pub max_bytes: u64,
+    /// The length of a window (in ms).
+    pub window_ms: u64,
+}
+
+impl Quota {
+    /// Get the number of the window the `timestamp` falls into.
+    #[must_use]
+    pub fn window(&self, timestamp: SystemTime) -> u64 {
+        let millis = timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        u64::try_from(millis / u128::from(self.window_ms.max(1))).unwrap_or(u64::max_value())
+    }
+}
+
+/// The usage of the `Quota` of a key namespace in the current window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct QuotaUsage {
+    /// The number of the window (see `Quota::window`).
+    pub window: u64,
+    /// The number of committed transactions in the window.
+    pub transactions: u64,
+    /// The size of the committed transactions in the window (in bytes).
+    pub bytes: u64,
+}
+
+impl QuotaUsage {
+    /// Add a transaction of `size` bytes at `timestamp` to the usage
+    /// if this does not exceed the `quota`.
+    ///
+    /// A timestamp in an earlier window (block timestamps are not strictly monotonic)
+    /// counts towards the current window. Returns whether the transaction was added.
+    pub fn try_add(&mut self, quota: &Quota, timestamp: SystemTime, size: u64) -> bool {
+        let window = quota.window(timestamp);
+        let mut usage = if window > self.window {
+            Self {
+                window,
+                ..Self::default()
+            }
+        } else {
+            *self
+        };
+        usage.transactions += 1;
+        usage.bytes = usage.bytes.saturating_add(size);
+        if usage.transactions > quota.max_transactions || usage.bytes > quota.max_bytes {
+            return false;
+        }
+        *self = usage;
+        true
+    }
+}
@@ -0,0 +1,371 @@
+//! Schemas describing the payload of `KeyValue` transactions.
+//!
+//! Admins can register a `Schema` for a key namespace (a key prefix).
+//! Values written to keys in that namespace are rejected by all RPUs if they do not match the schema.
+//!
+//! Values are postcard-encoded, so a schema describes the serde data model of the value
+//! (similar to a struct definition) rather than its bytes.
+
+use err_derive::Error;
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor},
+    ser::{SerializeTuple, Serializer},
+    Deserialize, Serialize,
+};
+use std::{cell::Cell, fmt};
+
+/// The maximum nesting depth of a `Schema`.
+pub const MAX_SCHEMA_DEPTH: usize = 16;
+
+/// An error while validating a value against a `Schema`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SchemaError {
+    /// The value could not be decoded with the schema.
+    #[error(display = "The value does not match the schema: {}", 0)]
+    Mismatch(#[error(from)] postcard::Error),
+
+    /// The value contains trailing or non-canonical bytes.
+    #[error(display = "The value contains trailing or non-canonical bytes.")]
+    NotCanonical,
+
+    /// The schema is nested too deeply.
+    #[error(
+        display = "The schema is nested deeper than {} levels.",
+        MAX_SCHEMA_DEPTH
+    )]
+    TooDeep,
+}
+
+/// A descriptor of the structure of a value.
+///
+/// # Example
+///
+/// ```
+/// use prellblock_client_api::schema::Schema;
+///
+/// // A struct `{ celsius: f32, sensor: String }`.
+/// let schema = Schema::Struct(vec![
+///     ("celsius".to_string(), Schema::F32),
+///     ("sensor".to_string(), Schema::String),
+/// ]);
+///
+/// let value = postcard::to_stdvec(&(21.5_f32, "kitchen")).unwrap();
+/// assert!(schema.validate(&value).is_ok());
+///
+/// let value = postcard::to_stdvec(&"garbage").unwrap();
+/// assert!(schema.validate(&value).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Schema {
+    /// A boolean.
+    Bool,
+    /// An unsigned 8 bit integer.
+    U8,
+    /// An unsigned 16 bit integer.
+    U16,
+    /// An unsigned 32 bit integer.
+    U32,
+    /// An unsigned 64 bit integer.
+    U64,
+    /// A signed 8 bit integer.
+    I8,
+    /// A signed 16 bit integer.
+    I16,
+    /// A signed 32 bit integer.
+    I32,
+    /// A signed 64 bit integer.
+    I64,
+    /// A 32 bit floating point number.
+    F32,
+    /// A 64 bit floating point number.
+    F64,
+    /// An UTF-8 string.
+    String,
+    /// A byte array.
+    Bytes,
+    /// An optional value.
+    Option(#[serde(deserialize_with = "deserialize_nested")] Box<Schema>),
+    /// A sequence of values of the same type (e.g. a `Vec`).
+    Seq(#[serde(deserialize_with = "deserialize_nested")] Box<Schema>),
+    /// A fixed number of values (e.g. a tuple).
+    Tuple(#[serde(deserialize_with = "deserialize_nested")] Vec<Schema>),
+    /// A struct with named fields (encoded like a tuple).
+    Struct(#[serde(deserialize_with = "deserialize_nested")] Vec<(String, Schema)>),
+}
+
+thread_local! {
+    /// The nesting depth of the `Schema` currently deserialized on this thread.
+    static DESERIALIZE_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Deserialize the inner schemas of a `Schema`.
+///
+/// Deserializing stops after `MAX_SCHEMA_DEPTH` nested levels, before the recursion exhausts the stack
+/// (the exact depth is checked by `Schema::verify_depth`).
+fn deserialize_nested<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let depth = DESERIALIZE_DEPTH.with(Cell::get);
+    if depth >= MAX_SCHEMA_DEPTH {
+        return Err(de::Error::custom(SchemaError::TooDeep));
+    }
+    DESERIALIZE_DEPTH.with(|cell| cell.set(depth + 1));
+    let result = T::deserialize(deserializer);
+    DESERIALIZE_DEPTH.with(|cell| cell.set(depth));
+    result
+}
+
+impl Schema {
+    /// Validate a postcard-encoded `value` against the schema.
+    ///
+    /// The value must be decodable with the schema and must not contain any other bytes.
+    pub fn validate(&self, value: &[u8]) -> Result<(), SchemaError> {
+        self.verify_depth()?;
+        let mut deserializer = postcard::Deserializer::from_bytes(value);
+        let decoded = SchemaSeed(self).deserialize(&mut deserializer)?;
+
+        // Re-encoding detects trailing bytes and non-canonical encodings.
+        if postcard::to_stdvec(&decoded)? == value {
+            Ok(())
+        } else {
+            Err(SchemaError::NotCanonical)
+        }
+    }
+
+    /// Verify that the schema is not nested deeper than `MAX_SCHEMA_DEPTH`.
+    pub fn verify_depth(&self) -> Result<(), SchemaError> {
+        if self.depth() > MAX_SCHEMA_DEPTH {
+            Err(SchemaError::TooDeep)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn depth(&self) -> usize {
+        let inner = match self {
+            Self::Option(schema) | Self::Seq(schema) => schema.depth(),
+            Self::Tuple(schemas) => schemas.iter().map(Self::depth).max().unwrap_or(0),
+            Self::Struct(fields) => fields
+                .iter()
+                .map(|(_, schema)| schema.depth())
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        };
+        inner + 1
+    }
+}
+
+/// A value decoded with a `Schema`.
+///
+/// It is encoded exactly the same way as the original value.
+enum Value {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Value>),
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Bool(v) => serializer.serialize_bool(*v),
+            Self::U8(v) => serializer.serialize_u8(*v),
+            Self::U16(v) => serializer.serialize_u16(*v),
+            Self::U32(v) => serializer.serialize_u32(*v),
+            Self::U64(v) => serializer.serialize_u64(*v),
+            Self::I8(v) => serializer.serialize_i8(*v),
+            Self::I16(v) => serializer.serialize_i16(*v),
+            Self::I32(v) => serializer.serialize_i32(*v),
+            Self::I64(v) => serializer.serialize_i64(*v),
+            Self::F32(v) => serializer.serialize_f32(*v),
+            Self::F64(v) => serializer.serialize_f64(*v),
+            Self::String(v) => serializer.serialize_str(v),
+            Self::Bytes(v) => serializer.serialize_bytes(v),
+            Self::None => serializer.serialize_none(),
+            Self::Some(v) => serializer.serialize_some(v),
+            Self::Seq(values) => serializer.collect_seq(values),
+            Self::Tuple(values) => {
+                let mut tuple = serializer.serialize_tuple(values.len())?;
+                for value in values {
+                    tuple.serialize_element(value)?;
+                }
+                tuple.end()
+            }
+        }
+    }
+}
+
+/// Decodes a `Value` as described by a `Schema`.
+#[derive(Clone, Copy)]
+struct SchemaSeed<'a>(&'a Schema);
+
+impl<'de, 'a> DeserializeSeed<'de> for SchemaSeed<'a> {
+    type Value = Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = SchemaVisitor(self.0);
+        match self.0 {
+            Schema::Bool => deserializer.deserialize_bool(visitor),
+            Schema::U8 => deserializer.deserialize_u8(visitor),
+            Schema::U16 => deserializer.deserialize_u16(visitor),
+            Schema::U32 => deserializer.deserialize_u32(visitor),
+            Schema::U64 => deserializer.deserialize_u64(visitor),
+            Schema::I8 => deserializer.deserialize_i8(visitor),
+            Schema::I16 => deserializer.deserialize_i16(visitor),
+            Schema::I32 => deserializer.deserialize_i32(visitor),
+            Schema::I64 => deserializer.deserialize_i64(visitor),
+            Schema::F32 => deserializer.deserialize_f32(visitor),
+            Schema::F64 => deserializer.deserialize_f64(visitor),
+            Schema::String => deserializer.deserialize_string(visitor),
+            Schema::Bytes => deserializer.deserialize_byte_buf(visitor),
+            Schema::Option(_) => deserializer.deserialize_option(visitor),
+            Schema::Seq(_) => deserializer.deserialize_seq(visitor),
+            Schema::Tuple(schemas) => deserializer.deserialize_tuple(schemas.len(), visitor),
+            Schema::Struct(fields) => deserializer.deserialize_tuple(fields.len(), visitor),
+        }
+    }
+}
+
+struct SchemaVisitor<'a>(&'a Schema);
+
+impl<'a> SchemaVisitor<'a> {
+    fn visit_fields<'de, A>(
+        self,
+        mut seq: A,
+        schemas: impl ExactSizeIterator<Item = &'a Schema>,
+    ) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let len = schemas.len();
+        let mut values = Vec::with_capacity(len);
+        for schema in schemas {
+            match seq.next_element_seed(SchemaSeed(schema))? {
+                Some(value) => values.push(value),
+                None => return Err(de::Error::invalid_length(values.len(), &self)),
+            }
+        }
+        Ok(Value::Tuple(values))
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for SchemaVisitor<'a> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a value matching {:?}", self.0)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_u8<E>(self, v: u8) -> Result<Self::Value, E> {
+        Ok(Value::U8(v))
+    }
+
+    fn visit_u16<E>(self, v: u16) -> Result<Self::Value, E> {
+        Ok(Value::U16(v))
+    }
+
+    fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E> {
+        Ok(Value::U32(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Value::U64(v))
+    }
+
+    fn visit_i8<E>(self, v: i8) -> Result<Self::Value, E> {
+        Ok(Value::I8(v))
+    }
+
+    fn visit_i16<E>(self, v: i16) -> Result<Self::Value, E> {
+        Ok(Value::I16(v))
+    }
+
+    fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E> {
+        Ok(Value::I32(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::I64(v))
+    }
+
+    fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E> {
+        Ok(Value::F32(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::F64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self.0 {
+            Schema::Option(schema) => Ok(Value::Some(Box::new(
+                SchemaSeed(schema).deserialize(deserializer)?,
+            ))),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Option, &self)),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        match self.0 {
+            Schema::Seq(schema) => {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element_seed(SchemaSeed(schema))? {
+                    values.push(value);
+                }
+                Ok(Value::Seq(values))
+            }
+            Schema::Tuple(schemas) => self.visit_fields(seq, schemas.iter()),
+            Schema::Struct(fields) => {
+                self.visit_fields(seq, fields.iter().map(|(_, schema)| schema))
+            }
+            _ => Err(de::Error::invalid_type(de::Unexpected::Seq, &self)),
+        }
+    }
+}
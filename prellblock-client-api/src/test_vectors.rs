@@ -0,0 +1,252 @@
+//! Deterministic test vectors of the encoding, hashing and signing of transactions and blocks.
+//!
+//! Alternative client implementations (e.g. in Python or Go) can check their
+//! serialization against the golden file `test-vectors.json` of this crate,
+//! which is kept in sync with `test_vectors` by a snapshot test (`tests/test_vectors.rs`).
+//! All byte strings are hex encoded, values are encoded with `postcard`
+//! and hashes are blake2b hashes of the encoded values.
+//!
+//! # Example
+//!
+//! ```
+//! use prellblock_client_api::test_vectors::{test_vectors, verify_test_vectors};
+//!
+//! let vectors = test_vectors();
+//! assert!(verify_test_vectors(&vectors).is_ok());
+//! ```
+
+use crate::{
+    consensus::{self, BlockHash, BlockNumber, Body, LeaderTerm, TransactionHash},
+    parameter::Parameter,
+    transaction, Transaction,
+};
+use err_derive::Error;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The private key signing the test vectors (hex encoded). Never use it for anything else.
+pub const TEST_IDENTITY: &str = "03d738c972f37a6fd9b33278ac0c50236e45637bcd5aeee82d8323655257d256";
+
+/// An error found by `verify_test_vectors`.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A field of a test vector does not match the reference implementation.
+    #[error(
+        display = "The {} of the test vector {:?} does not match.",
+        field,
+        name
+    )]
+    Mismatch {
+        /// The name of the test vector.
+        name: String,
+        /// The mismatching field.
+        field: &'static str,
+    },
+
+    /// The signature of a test vector is invalid.
+    #[error(display = "The signature of the test vector {:?} is invalid: {}", 0, 1)]
+    InvalidSignature(String, #[error(source)] pinxit::Error),
+
+    /// A value could not be encoded.
+    #[error(display = "{}", 0)]
+    Encoding(#[error(from)] postcard::Error),
+}
+
+/// The test vectors of transactions and blocks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestVectors {
+    /// The private key (hex encoded) that signed all transactions.
+    pub identity: String,
+    /// The signed transactions.
+    pub transactions: Vec<TransactionVector>,
+    /// The block bodies.
+    pub blocks: Vec<BlockVector>,
+}
+
+/// A signed `Transaction` with its encodings and hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionVector {
+    /// The name of the test vector.
+    pub name: String,
+    /// The signed transaction.
+    pub transaction: Signed<Transaction>,
+    /// The data that is signed (the transaction encoded as `Execute` request).
+    pub signing_data: String,
+    /// The encoded signed transaction.
+    pub encoded: String,
+    /// The hash of the encoded signed transaction.
+    pub hash: TransactionHash,
+}
+
+/// A block `Body` with its encoding and hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockVector {
+    /// The name of the test vector.
+    pub name: String,
+    /// The body of the block.
+    pub body: Body,
+    /// The encoded body.
+    pub encoded: String,
+    /// The hash of the encoded body (the `BlockHash`).
+    pub hash: BlockHash,
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A fixed point in time `secs` seconds after the epoch.
+fn timestamp(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+impl TransactionVector {
+    fn new(name: &str, identity: &Identity, transaction: Transaction) -> Self {
+        let signing_data = hex(&transaction.signable_data().unwrap());
+        let transaction = transaction.sign(identity).unwrap();
+        Self {
+            name: name.to_string(),
+            signing_data,
+            encoded: hex(&postcard::to_stdvec(&transaction).unwrap()),
+            hash: consensus::transaction_hash(&transaction),
+            transaction,
+        }
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        let mismatch = |field| Error::Mismatch {
+            name: self.name.clone(),
+            field,
+        };
+        let transaction = self.transaction.unverified_ref();
+        if self.signing_data != hex(&transaction.signable_data()?) {
+            return Err(mismatch("signing data"));
+        }
+        self.transaction
+            .verify_ref()
+            .map_err(|err| Error::InvalidSignature(self.name.clone(), err))?;
+        if self.encoded != hex(&postcard::to_stdvec(&self.transaction)?) {
+            return Err(mismatch("encoding"));
+        }
+        if self.hash != consensus::transaction_hash(&self.transaction) {
+            return Err(mismatch("hash"));
+        }
+        Ok(())
+    }
+}
+
+impl BlockVector {
+    fn new(name: &str, body: Body) -> Self {
+        Self {
+            name: name.to_string(),
+            encoded: hex(&postcard::to_stdvec(&body).unwrap()),
+            hash: body.hash(),
+            body,
+        }
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        let mismatch = |field| Error::Mismatch {
+            name: self.name.clone(),
+            field,
+        };
+        if self.encoded != hex(&postcard::to_stdvec(&self.body)?) {
+            return Err(mismatch("encoding"));
+        }
+        if self.hash != self.body.hash() {
+            return Err(mismatch("hash"));
+        }
+        Ok(())
+    }
+}
+
+/// Generate the test vectors.
+///
+/// The result is the same on every call (signatures are deterministic).
+#[must_use]
+pub fn test_vectors() -> TestVectors {
+    let identity: Identity = TEST_IDENTITY.parse().unwrap();
+    let id = identity.id().clone();
+
+    let transactions = vec![
+        TransactionVector::new(
+            "key value",
+            &identity,
+            Transaction::from_variant(transaction::KeyValue {
+                key: "temperature".to_string(),
+                value: postcard::to_stdvec(&21.5_f64).unwrap(),
+                timestamp: timestamp(1_600_000_000),
+            }),
+        ),
+        TransactionVector::new(
+            "set parameter",
+            &identity,
+            Transaction::from_variant(transaction::SetParameter {
+                parameter: Parameter::MaxTransactionsPerBlock,
+                value: 1000,
+                activation_height: BlockNumber::new(42),
+                timestamp: timestamp(1_600_000_001),
+            }),
+        ),
+        TransactionVector::new(
+            "set leader weight",
+            &identity,
+            Transaction::from_variant(transaction::SetLeaderWeight {
+                id: id.clone(),
+                weight: 2,
+                timestamp: timestamp(1_600_000_002),
+            }),
+        ),
+        TransactionVector::new(
+            "delete account",
+            &identity,
+            Transaction::from_variant(transaction::DeleteAccount {
+                id,
+                timestamp: timestamp(1_600_000_003),
+            }),
+        ),
+    ];
+
+    let empty = Body {
+        leader_term: LeaderTerm::default(),
+        height: BlockNumber::default(),
+        prev_block_hash: BlockHash::default(),
+        timestamp: timestamp(1_600_000_000),
+        transactions: Vec::new(),
+    };
+    let full = Body {
+        leader_term: LeaderTerm::new(1),
+        height: BlockNumber::new(1),
+        prev_block_hash: empty.hash(),
+        timestamp: timestamp(1_600_000_010),
+        transactions: transactions
+            .iter()
+            .map(|vector| vector.transaction.clone())
+            .collect(),
+    };
+
+    TestVectors {
+        identity: TEST_IDENTITY.to_string(),
+        transactions,
+        blocks: vec![
+            BlockVector::new("empty block", empty),
+            BlockVector::new("block with transactions", full),
+        ],
+    }
+}
+
+/// Verify `vectors` (e.g. read from the golden file or produced by another implementation)
+/// against the reference implementation.
+///
+/// The encodings, hashes and signing data need to match and the signatures need to be valid.
+pub fn verify_test_vectors(vectors: &TestVectors) -> Result<(), Error> {
+    for vector in &vectors.transactions {
+        vector.verify()?;
+    }
+    for vector in &vectors.blocks {
+        vector.verify()?;
+    }
+    Ok(())
+}
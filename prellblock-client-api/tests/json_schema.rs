@@ -0,0 +1,44 @@
+//! Snapshot test of the JSON schema of the client API.
+//!
+//! Run with `UPDATE_SCHEMA=1` to update the snapshot after changing the API.
+//! A missing snapshot is created (and needs to be committed).
+
+use std::{env, fs, path::Path};
+
+const SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/client-api.schema.json");
+
+#[test]
+fn json_schema_is_up_to_date() {
+    let schema = prellblock_client_api::json_schema::client_api();
+    let schema = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+
+    if env::var_os("UPDATE_SCHEMA").is_some() || !Path::new(SNAPSHOT).exists() {
+        fs::write(SNAPSHOT, schema).unwrap();
+        return;
+    }
+
+    let snapshot = fs::read_to_string(SNAPSHOT).unwrap();
+    assert!(
+        snapshot == schema,
+        "The client API changed, update the JSON schema with \
+         `UPDATE_SCHEMA=1 cargo test -p prellblock-client-api --features json-schema`."
+    );
+}
+
+#[test]
+fn json_schema_describes_all_requests() {
+    let schema = prellblock_client_api::json_schema::client_api();
+    let requests = schema["oneOf"].as_array().unwrap();
+    let responses = schema["responses"].as_object().unwrap();
+    let names = prellblock_client_api::ClientMessage::request_names();
+    assert_eq!(requests.len(), names.len());
+    assert_eq!(responses.len(), names.len());
+    for name in names {
+        assert!(responses.contains_key(*name), "{} is missing", name);
+    }
+
+    let definitions = &schema["definitions"];
+    assert_eq!(definitions["PeerId"]["type"], "string");
+    assert_eq!(definitions["BlockHash"]["pattern"], "^[0-9a-f]{128}$");
+    assert!(definitions["Transaction"]["oneOf"].is_array());
+}
@@ -0,0 +1,56 @@
+use pinxit::{Identity, PeerId, Signature, Signer};
+use prellblock_client_api::consensus::{SignatureList, SignatureListError};
+
+fn signatures(count: usize) -> Vec<(PeerId, Signature)> {
+    (0..count)
+        .map(|_| {
+            let identity = Identity::generate();
+            (identity.id().clone(), identity.sign_bytes(b"block"))
+        })
+        .collect()
+}
+
+fn is_sorted(signatures: &SignatureList) -> bool {
+    let peer_ids: Vec<&[u8]> = signatures
+        .into_iter()
+        .map(|(peer_id, _)| peer_id.as_bytes())
+        .collect();
+    peer_ids.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+#[test]
+fn test_duplicate_signers_are_rejected() {
+    let mut items = signatures(2);
+    let mut signatures = SignatureList::default();
+    for (peer_id, signature) in items.clone() {
+        signatures.insert(peer_id, signature).unwrap();
+    }
+
+    let (peer_id, signature) = items.pop().unwrap();
+    let err = signatures.insert(peer_id, signature).unwrap_err();
+    assert!(matches!(err, SignatureListError::DuplicateSigner(_)));
+    assert_eq!(signatures.len(), 2);
+}
+
+#[test]
+fn test_deserialized_lists_are_sorted_and_unique() {
+    let mut items = signatures(8);
+    items.sort_by(|(a, _), (b, _)| b.as_bytes().cmp(a.as_bytes()));
+
+    let bytes = postcard::to_stdvec(&items).unwrap();
+    let signatures: SignatureList = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(signatures.len(), items.len());
+    assert!(is_sorted(&signatures));
+    // The list is serialized the same way regardless of the order the signatures arrived in.
+    let mut sorted = items.clone();
+    sorted.reverse();
+    assert_eq!(
+        postcard::to_stdvec(&signatures).unwrap(),
+        postcard::to_stdvec(&sorted).unwrap()
+    );
+
+    // A list containing two signatures of the same peer is rejected.
+    items.push(items[0].clone());
+    let bytes = postcard::to_stdvec(&items).unwrap();
+    assert!(postcard::from_bytes::<SignatureList>(&bytes).is_err());
+}
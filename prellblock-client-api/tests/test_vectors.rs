@@ -0,0 +1,64 @@
+//! Snapshot test of the test vectors (golden file) for alternative client implementations.
+//!
+//! Run with `UPDATE_TEST_VECTORS=1` to update the golden file after an intended change of the encoding.
+//! A missing golden file is created (and needs to be committed).
+
+use prellblock_client_api::test_vectors::{self, test_vectors, verify_test_vectors, TestVectors};
+use std::{env, fs, path::Path};
+
+const GOLDEN_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors.json");
+
+#[test]
+fn test_vectors_are_up_to_date() {
+    let vectors = serde_json::to_string_pretty(&test_vectors()).unwrap() + "\n";
+
+    if env::var_os("UPDATE_TEST_VECTORS").is_some() || !Path::new(GOLDEN_FILE).exists() {
+        fs::write(GOLDEN_FILE, vectors).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(GOLDEN_FILE).unwrap();
+    assert!(
+        golden == vectors,
+        "The encoding, hashing or signing changed. This breaks alternative client implementations! \
+         If intended, update the golden file with `UPDATE_TEST_VECTORS=1 cargo test -p prellblock-client-api`."
+    );
+}
+
+#[test]
+fn golden_file_is_valid() {
+    let golden = match fs::read_to_string(GOLDEN_FILE) {
+        Ok(golden) => golden,
+        // Created by `test_vectors_are_up_to_date`.
+        Err(_) => return,
+    };
+    let vectors: TestVectors = serde_json::from_str(&golden).unwrap();
+    verify_test_vectors(&vectors).unwrap();
+}
+
+#[test]
+fn tampered_test_vectors_are_detected() {
+    let mut vectors = test_vectors();
+    vectors.blocks[1].hash = vectors.blocks[0].hash;
+    assert!(matches!(
+        verify_test_vectors(&vectors),
+        Err(test_vectors::Error::Mismatch { field: "hash", .. })
+    ));
+
+    let mut vectors = test_vectors();
+    vectors.transactions[0].encoded.push_str("00");
+    assert!(matches!(
+        verify_test_vectors(&vectors),
+        Err(test_vectors::Error::Mismatch {
+            field: "encoding",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_vectors_are_deterministic() {
+    let a = serde_json::to_string(&test_vectors()).unwrap();
+    let b = serde_json::to_string(&test_vectors()).unwrap();
+    assert_eq!(a, b);
+}
@@ -0,0 +1,288 @@
+#![warn(missing_docs, clippy::all, clippy::pedantic, clippy::nursery)]
+#![allow(
+    clippy::future_not_send,
+    clippy::missing_errors_doc,
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+
+//! A soak test simulating devices that publish sensor readings.
+//!
+//! Each device sends key-value transactions at a fixed rate to its own key,
+//! verifies the receipt of each transaction and finally waits until all values can be queried.
+//! The devices are distributed over the given identities (the accounts need writing rights).
+
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable};
+use prellblock_client::{consensus::transaction_hash, Client};
+use prellblock_client_api::{transaction, Transaction};
+use rand::{rngs::OsRng, RngCore};
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+use tokio::time;
+
+/// The interval in which the values are queried until all of them are visible.
+const QUERY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Generate synthetic sensor load on an RPU.
+#[derive(StructOpt, Debug)]
+struct Opt {
+    /// The address of the Turi of the RPU.
+    turi_address: SocketAddr,
+    /// The private key files of the accounts used by the devices (assigned round robin).
+    #[structopt(required = true)]
+    identity_files: Vec<String>,
+    /// The number of simulated devices.
+    #[structopt(short, long, default_value = "10")]
+    devices: usize,
+    /// The number of readings each device sends per second.
+    #[structopt(short, long, default_value = "1")]
+    rate: f64,
+    /// The number of bytes of each reading's payload.
+    #[structopt(short = "s", long, default_value = "64")]
+    payload_size: usize,
+    /// How long the devices send readings (e.g. "10min").
+    #[structopt(short = "t", long, default_value = "1min")]
+    duration: humantime::Duration,
+    /// The maximum time to wait until all readings can be queried.
+    #[structopt(long, default_value = "1min")]
+    query_timeout: humantime::Duration,
+    /// The prefix of the keys of the devices (a unique run id is appended).
+    #[structopt(long, default_value = "loadgen")]
+    key_prefix: String,
+}
+
+/// The readings sent by a single device.
+struct DeviceReport {
+    identity: Arc<Identity>,
+    key: String,
+    /// The client timestamps of the accepted readings.
+    accepted: HashSet<SystemTime>,
+    /// The time from sending a reading until receiving its receipt.
+    latencies: Vec<Duration>,
+    failed: usize,
+    invalid_receipts: usize,
+}
+
+#[tokio::main]
+async fn main() {
+    pretty_env_logger::init();
+
+    let opt = Opt::from_args();
+    log::debug!("Command line arguments: {:#?}", opt);
+    assert!(opt.rate > 0.0, "The rate must be positive.");
+
+    let identities: Vec<Arc<Identity>> = opt
+        .identity_files
+        .iter()
+        .map(|path| Arc::new(Identity::from_file(path).expect("Could not load private key file.")))
+        .collect();
+
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let interval = Duration::from_secs_f64(1.0 / opt.rate);
+    let duration: Duration = opt.duration.into();
+    println!(
+        "Simulating {} devices with {} identities for {:?} ({} readings/s of {} bytes each).",
+        opt.devices,
+        identities.len(),
+        duration,
+        opt.rate * opt.devices as f64,
+        opt.payload_size
+    );
+
+    let start = Instant::now();
+    let devices: Vec<_> = (0..opt.devices)
+        .map(|n| {
+            let identity = identities[n % identities.len()].clone();
+            let key = format!("{}-{}/device-{:04}", opt.key_prefix, run_id, n);
+            tokio::spawn(run_device(
+                opt.turi_address,
+                identity,
+                key,
+                interval,
+                start + duration,
+                opt.payload_size,
+            ))
+        })
+        .collect();
+
+    let mut reports = Vec::new();
+    for device in devices {
+        reports.push(device.await.expect("A device panicked."));
+    }
+    let elapsed = start.elapsed();
+    print_send_summary(&reports, elapsed, opt.payload_size);
+
+    let query_timeout: Duration = opt.query_timeout.into();
+    match wait_until_queryable(opt.turi_address, &reports, query_timeout).await {
+        Ok(time) => println!("All accepted readings were queryable after {:?}.", time),
+        Err(missing) => println!(
+            "{} accepted readings were not queryable after {:?}.",
+            missing, query_timeout
+        ),
+    }
+}
+
+/// Send readings of a single device every `interval` until `end`.
+async fn run_device(
+    turi_address: SocketAddr,
+    identity: Arc<Identity>,
+    key: String,
+    interval: Duration,
+    end: Instant,
+    payload_size: usize,
+) -> DeviceReport {
+    let mut client = Client::with_signer(turi_address, identity.clone());
+    let mut report = DeviceReport {
+        identity: identity.clone(),
+        key,
+        accepted: HashSet::new(),
+        latencies: Vec::new(),
+        failed: 0,
+        invalid_receipts: 0,
+    };
+    let mut payload = vec![0; payload_size];
+    let mut ticks = time::interval(interval);
+
+    for sequence in 0_u64.. {
+        ticks.tick().await;
+        if Instant::now() >= end {
+            break;
+        }
+        OsRng.fill_bytes(&mut payload);
+        let timestamp = SystemTime::now();
+        let transaction = Transaction::from_variant(transaction::KeyValue {
+            key: report.key.clone(),
+            value: postcard::to_stdvec(&payload).unwrap(),
+            timestamp,
+        })
+        .sign(&*identity)
+        .unwrap();
+        let expected_hash = transaction_hash(&transaction);
+        let idempotency_key = format!("{}/{}", report.key, sequence);
+
+        let sent = Instant::now();
+        match client.submit_idempotent(idempotency_key, transaction).await {
+            Ok(receipt) if receipt.transaction_hash == expected_hash => {
+                report.latencies.push(sent.elapsed());
+                report.accepted.insert(timestamp);
+            }
+            Ok(receipt) => {
+                log::warn!(
+                    "Receipt for {} has the wrong transaction hash: {:?}",
+                    report.key,
+                    receipt.transaction_hash
+                );
+                report.invalid_receipts += 1;
+            }
+            Err(err) => {
+                log::warn!("Failed to send a reading of {}: {}", report.key, err);
+                report.failed += 1;
+            }
+        }
+    }
+    report
+}
+
+/// Query the values of all devices until all accepted readings are visible.
+///
+/// Each device reads its own values, so no reading rights are needed.
+/// Returns the time it took or the number of missing readings after the `timeout`.
+async fn wait_until_queryable(
+    turi_address: SocketAddr,
+    reports: &[DeviceReport],
+    timeout: Duration,
+) -> Result<Duration, usize> {
+    let start = Instant::now();
+    let mut pending: Vec<_> = reports
+        .iter()
+        .filter(|report| !report.accepted.is_empty())
+        .map(|report| (report, report.accepted.clone()))
+        .collect();
+
+    loop {
+        let mut still_pending = Vec::new();
+        for (report, mut missing) in pending {
+            let peer_id = report.identity.id().clone();
+            let mut client = Client::with_signer(turi_address, report.identity.clone());
+            let values = client
+                .query()
+                .peer(peer_id.clone())
+                .key(report.key.clone())
+                .run_raw()
+                .await;
+            match values {
+                Ok(values) => {
+                    let series = values
+                        .get(&peer_id)
+                        .and_then(|values_of_peer| values_of_peer.get(&report.key));
+                    for (_, client_timestamp, _) in
+                        series.into_iter().flat_map(|series| series.values())
+                    {
+                        missing.remove(client_timestamp);
+                    }
+                }
+                Err(err) => log::warn!("Failed to query the readings of {}: {}", report.key, err),
+            }
+            if !missing.is_empty() {
+                still_pending.push((report, missing));
+            }
+        }
+        pending = still_pending;
+
+        if pending.is_empty() {
+            return Ok(start.elapsed());
+        }
+        if start.elapsed() >= timeout {
+            return Err(pending.iter().map(|(_, missing)| missing.len()).sum());
+        }
+        time::delay_for(QUERY_INTERVAL).await;
+    }
+}
+
+fn print_send_summary(reports: &[DeviceReport], elapsed: Duration, payload_size: usize) {
+    let mut latencies: Vec<Duration> = reports
+        .iter()
+        .flat_map(|report| report.latencies.iter().copied())
+        .collect();
+    latencies.sort();
+    let accepted = latencies.len();
+    let failed: usize = reports.iter().map(|report| report.failed).sum();
+    let invalid_receipts: usize = reports.iter().map(|report| report.invalid_receipts).sum();
+
+    println!("--------------------------------------------------------------------------------");
+    println!("Duration:               {:?}", elapsed);
+    println!("Accepted readings:      {}", accepted);
+    println!("Failed readings:        {}", failed);
+    println!("Invalid receipts:       {}", invalid_receipts);
+    println!(
+        "Throughput:             {:.1} readings/s ({:.1} KiB/s payload)",
+        accepted as f64 / elapsed.as_secs_f64(),
+        (accepted * payload_size) as f64 / 1024.0 / elapsed.as_secs_f64()
+    );
+    if let (Some(min), Some(max)) = (latencies.first(), latencies.last()) {
+        let sum: Duration = latencies.iter().sum();
+        println!("Receipt latency (min):  {:?}", min);
+        println!("Receipt latency (mean): {:?}", sum / accepted as u32);
+        println!("Receipt latency (p50):  {:?}", percentile(&latencies, 0.5));
+        println!("Receipt latency (p90):  {:?}", percentile(&latencies, 0.9));
+        println!("Receipt latency (p99):  {:?}", percentile(&latencies, 0.99));
+        println!("Receipt latency (max):  {:?}", max);
+    }
+    println!("--------------------------------------------------------------------------------");
+}
+
+/// Get the `p` percentile of the sorted (non-empty) `values`.
+fn percentile(values: &[Duration], p: f64) -> Duration {
+    let index = ((values.len() - 1) as f64 * p).round() as usize;
+    values[index]
+}
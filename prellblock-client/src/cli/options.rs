@@ -1,4 +1,4 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, path::PathBuf};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -7,6 +7,9 @@ pub struct Opt {
     pub private_key_file: String,
     /// The address of the receiving RPU's address.
     pub turi_address: SocketAddr,
+    /// A file with namespace keys for encrypting and decrypting values.
+    #[structopt(long)]
+    pub keyring: Option<PathBuf>,
     #[structopt(subcommand)]
     pub cmd: Cmd,
 }
@@ -16,6 +19,17 @@ pub struct Opt {
 pub enum Cmd {
     /// Set a single key value pair.
     Set(cmd::Set),
+    /// Set a single key value pair that must be committed before a deadline and watch its receipt.
+    #[structopt(name = "set_with_deadline")]
+    SetWithDeadline(cmd::SetWithDeadline),
+    /// Set multiple keys at once (all or none are written).
+    #[structopt(name = "set_many")]
+    SetMany(cmd::SetMany),
+    /// Set a shared key if it has the expected value when committed.
+    #[structopt(name = "compare_and_swap")]
+    CompareAndSwap(cmd::CompareAndSwap),
+    /// Import historical readings from a CSV or JSON Lines file.
+    Import(cmd::Import),
     /// Run a benchmark.
     #[structopt(name = "bench")]
     Benchmark(cmd::Benchmark),
@@ -28,6 +42,36 @@ pub enum Cmd {
     /// Delete an account.
     #[structopt(name = "delete_account")]
     DeleteAccount(cmd::DeleteAccount),
+    /// Set the payload schema of a key namespace.
+    #[structopt(name = "set_schema")]
+    SetSchema(cmd::SetSchema),
+    /// Schedule the migration of the world state to a new format version.
+    #[structopt(name = "schedule_migration")]
+    ScheduleMigration(cmd::ScheduleMigration),
+    /// Set a governed parameter of the blockchain.
+    #[structopt(name = "set_parameter")]
+    SetParameter(cmd::SetParameter),
+    /// Set the leader weight of an RPU.
+    #[structopt(name = "set_leader_weight")]
+    SetLeaderWeight(cmd::SetLeaderWeight),
+    /// Set the queue weight of an account.
+    #[structopt(name = "set_queue_weight")]
+    SetQueueWeight(cmd::SetQueueWeight),
+    /// Set the write throughput quota of a key namespace.
+    #[structopt(name = "set_quota")]
+    SetQuota(cmd::SetQuota),
+    /// Split a key of the keyring into shares for the RPUs and escrow it.
+    #[structopt(name = "escrow_key")]
+    EscrowKey(cmd::EscrowKey),
+    /// Write an authorization of an auditor, which must be approved by the RPUs.
+    #[structopt(name = "request_decryption")]
+    RequestDecryption(cmd::RequestDecryption),
+    /// Authorize an auditor to collect the shares of an escrowed key (approved by the RPUs).
+    #[structopt(name = "authorize_decryption")]
+    AuthorizeDecryption(cmd::AuthorizeDecryption),
+    /// Collect the shares of an escrowed key from the RPUs and reconstruct the key.
+    #[structopt(name = "recover_key")]
+    RecoverKey(cmd::RecoverKey),
     /// Get values from the blockchain.
     ///
     /// Specifying only a filter returns the last recorded value.
@@ -39,15 +83,75 @@ pub enum Cmd {
     /// Get blocks from the blockchain.
     #[structopt(name = "get_block")]
     GetBlock(cmd::GetBlock),
+    /// Stream blocks from the blockchain (suited for exporting many blocks).
+    #[structopt(name = "stream_blocks")]
+    StreamBlocks(cmd::StreamBlocks),
+    /// Subscribe to the committed blocks (resubscribing after lost connections).
+    #[structopt(name = "subscribe_blocks")]
+    SubscribeBlocks(cmd::SubscribeBlocks),
+    /// Get and verify the proof which RPUs accepted a block.
+    #[structopt(name = "block_proof")]
+    BlockProof(cmd::BlockProof),
     /// Get the current block number (that is going to be committed).
     #[structopt(name = "current_block_number")]
     CurrentBlockNumber,
+    /// Get and verify the head of the chain certified by a supermajority of the RPUs.
+    #[structopt(name = "certified_head")]
+    CertifiedHead,
+    /// Get the description (supported requests, limits and chain id) of the RPU.
+    Describe,
+    /// Get the peers banned by the RPU.
+    #[structopt(name = "banned_peers")]
+    BannedPeers,
+    /// Unban a peer on the RPU.
+    Unban(cmd::Unban),
+    /// Get the transactions queued on the RPU.
+    Queue,
+    /// Get the estimated clock skews of the other RPUs.
+    #[structopt(name = "clock_skews")]
+    ClockSkews,
+    /// Get the latest consensus failures recorded by the RPU.
+    #[structopt(name = "consensus_failures")]
+    ConsensusFailures,
+    /// Get the history of the leader terms seen by the RPU.
+    #[structopt(name = "leader_terms")]
+    LeaderTerms,
+    /// Get the results of the block storage scrubber of the RPU.
+    #[structopt(name = "scrub_statistics")]
+    ScrubStatistics,
+    /// Compare the chain of the RPU with the chains of all other RPUs.
+    #[structopt(name = "compare_chains")]
+    CompareChains,
+    /// Get the metrics of the connections of the RPU to other servers.
+    #[structopt(name = "network_metrics")]
+    NetworkMetrics,
+    /// Get the metrics of the requests handled by the RPU.
+    #[structopt(name = "request_metrics")]
+    RequestMetrics,
+    /// Get the status of the RPU (e.g. whether it was halted).
+    Status,
+    /// Evict queued transactions on all RPUs.
+    Evict(cmd::Evict),
+    /// Get where a transaction is on the RPU right now.
+    Trace(cmd::Trace),
+    /// Watch the receipt of a transaction submitted with a deadline.
+    #[structopt(name = "watch_receipt")]
+    WatchReceipt(cmd::WatchReceipt),
+    /// Get whether a committed transaction was applied.
+    #[structopt(name = "execution_receipt")]
+    ExecutionReceipt(cmd::ExecutionReceipt),
 }
 
 pub mod cmd {
+    use crate::import::{Format, ValueType};
     use pinxit::PeerId;
-    use prellblock_client::{consensus::BlockNumber, Filter, Span};
-    use std::str::FromStr;
+    use prellblock_client::{
+        consensus::{BlockNumber, TransactionHash},
+        metadata::{Quality, TimePrecision},
+        parameter::Parameter,
+        Filter, Span,
+    };
+    use std::{net::SocketAddr, path::PathBuf, str::FromStr};
     use structopt::StructOpt;
 
     /// Transaction to set a key to a value.
@@ -57,6 +161,87 @@ pub mod cmd {
         pub key: String,
         /// The value of the corresponding key.
         pub value: String,
+        /// The unit of the value (e.g. `km/h`).
+        #[structopt(long)]
+        pub unit: Option<String>,
+        /// The time the value was recorded at its source (e.g. `2020-06-01T12:00:00.123Z`).
+        #[structopt(long)]
+        pub source_time: Option<humantime::Timestamp>,
+        /// The offset (in minutes) of the source's time zone to UTC.
+        #[structopt(long, default_value = "0", allow_hyphen_values = true)]
+        pub utc_offset: i16,
+        /// The precision of the source time (`s`, `ms`, `us` or `ns`).
+        #[structopt(long, default_value = "ms")]
+        pub precision: TimePrecision,
+        /// The quality of the value (`good`, `uncertain` or `bad`).
+        #[structopt(long)]
+        pub quality: Option<Quality>,
+        /// Encrypt the value with the namespace key of the keyring.
+        #[structopt(long)]
+        pub encrypt: bool,
+    }
+
+    /// Transaction to set multiple keys at once.
+    #[derive(StructOpt, Debug)]
+    pub struct SetMany {
+        /// The keys and their values (e.g. `line3/temperature=21.5`).
+        #[structopt(required = true)]
+        pub values: Vec<ParseKeyValue>,
+    }
+
+    /// Transaction to set a shared key if it has the expected value.
+    #[derive(StructOpt, Debug)]
+    pub struct CompareAndSwap {
+        /// The shared key.
+        pub key: String,
+        /// The expected value (the key must not be set if omitted).
+        #[structopt(long)]
+        pub expected: Option<String>,
+        /// The new value (the key is removed if omitted).
+        #[structopt(long)]
+        pub new: Option<String>,
+    }
+
+    /// Set a key to a value that must be committed before a deadline.
+    #[derive(StructOpt, Debug)]
+    pub struct SetWithDeadline {
+        /// The key of this transaction.
+        pub key: String,
+        /// The value of the corresponding key.
+        pub value: String,
+        /// The time until the deadline (e.g. `30s`).
+        pub timeout: humantime::Duration,
+        /// The idempotency key of the submission (defaults to the transaction hash).
+        #[structopt(long)]
+        pub idempotency_key: Option<String>,
+    }
+
+    /// Import readings with the columns (or fields) `key`, `time`, `value`, `unit` and `quality`.
+    #[derive(StructOpt, Debug)]
+    pub struct Import {
+        /// The file to import.
+        pub file: PathBuf,
+        /// The format of the file (`csv` or `jsonl`, defaults to the file extension).
+        #[structopt(long)]
+        pub format: Option<Format>,
+        /// The type of the values (`f64`, `f32`, `i64`, `u64`, `bool` or `string`).
+        #[structopt(long, default_value = "f64")]
+        pub value_type: ValueType,
+        /// The offset (in minutes) of the sources' time zone to UTC.
+        #[structopt(long, default_value = "0", allow_hyphen_values = true)]
+        pub utc_offset: i16,
+        /// The precision of the times (`s`, `ms`, `us` or `ns`).
+        #[structopt(long, default_value = "ms")]
+        pub precision: TimePrecision,
+        /// The number of readings to submit before the progress is saved.
+        #[structopt(short, long, default_value = "1000")]
+        pub batch_size: usize,
+        /// The number of workers (clients) to use simultaneously.
+        #[structopt(short, long, default_value = "4")]
+        pub workers: usize,
+        /// The file the progress is saved to (defaults to `<file>.progress`).
+        #[structopt(long)]
+        pub progress_file: Option<PathBuf>,
     }
 
     /// Benchmark the blockchain.
@@ -101,6 +286,162 @@ pub mod cmd {
         pub peer_id: String,
     }
 
+    /// Set (or remove) the payload schema of a key namespace.
+    #[derive(StructOpt, Debug)]
+    pub struct SetSchema {
+        /// The key prefix the schema applies to.
+        pub namespace: String,
+        /// The filepath to a yaml-file containing the schema.
+        ///
+        /// The schema of the namespace is removed if no file is given.
+        pub schema_file: Option<String>,
+    }
+
+    /// Schedule the migration of the world state to a new format version.
+    #[derive(StructOpt, Debug)]
+    pub struct ScheduleMigration {
+        /// The format version to migrate to.
+        pub version: u32,
+        /// The number of the first block applied to the migrated world state.
+        pub activation_height: u64,
+    }
+
+    /// Set a governed parameter of the blockchain.
+    #[derive(StructOpt, Debug)]
+    pub struct SetParameter {
+        /// The parameter to set (e.g. `max_transactions_per_block`).
+        pub parameter: Parameter,
+        /// The new value (durations are given in milliseconds).
+        pub value: u64,
+        /// The number of the first block the value applies to.
+        pub activation_height: u64,
+    }
+
+    /// Set the leader weight of an RPU.
+    #[derive(StructOpt, Debug)]
+    pub struct SetLeaderWeight {
+        /// The public key of the RPU.
+        pub peer_id: PeerId,
+        /// The weight (`1` is the default, higher weights lead more often).
+        pub weight: u32,
+    }
+
+    /// Set the queue weight of an account.
+    #[derive(StructOpt, Debug)]
+    pub struct SetQueueWeight {
+        /// The public key of the account.
+        pub peer_id: PeerId,
+        /// The weight (`1` is the default, higher weights get more space in each block).
+        pub weight: u32,
+    }
+
+    /// Set (or remove) the write throughput quota of a key namespace.
+    #[derive(StructOpt, Debug)]
+    pub struct SetQuota {
+        /// The key prefix the quota applies to.
+        pub namespace: String,
+        /// The maximum number of transactions per window.
+        ///
+        /// The quota of the namespace is removed if no limits are given.
+        #[structopt(long)]
+        pub max_transactions: Option<u64>,
+        /// The maximum size of the transactions per window (in bytes).
+        #[structopt(long)]
+        pub max_bytes: Option<u64>,
+        /// The length of a window (in ms).
+        #[structopt(long, default_value = "60000")]
+        pub window_ms: u64,
+    }
+
+    /// Split a key of the keyring into shares for the RPUs and escrow it.
+    #[derive(StructOpt, Debug)]
+    pub struct EscrowKey {
+        /// The namespace of the key.
+        pub namespace: String,
+        /// The version of the key.
+        pub version: u32,
+        /// The number of shares needed to reconstruct the key (at least a supermajority of the RPUs).
+        pub threshold: usize,
+        /// The directory to write the share files of the RPUs to (`<peer_id>.yaml`).
+        pub output: PathBuf,
+        /// The public keys of the RPUs to hold a share.
+        #[structopt(required = true)]
+        pub rpus: Vec<PeerId>,
+    }
+
+    /// Write an authorization of an auditor, which must be approved by the RPUs.
+    #[derive(StructOpt, Debug)]
+    pub struct RequestDecryption {
+        /// The namespace of the key.
+        pub namespace: String,
+        /// The version of the key.
+        pub version: u32,
+        /// The public key of the auditor.
+        pub auditor: PeerId,
+        /// The reason of the audit (recorded on-chain).
+        pub reason: String,
+        /// The path of the authorization file to write.
+        pub output: PathBuf,
+        /// How long the authorization is valid after it was requested (e.g. `14days`).
+        #[structopt(long, default_value = "7days")]
+        pub valid_for: humantime::Duration,
+    }
+
+    /// Authorize an auditor to collect the shares of an escrowed key (approved by the RPUs).
+    #[derive(StructOpt, Debug)]
+    pub struct AuthorizeDecryption {
+        /// The path of the authorization file (approved with `prellblock approve-decryption`).
+        pub authorization: PathBuf,
+    }
+
+    /// Collect the shares of an escrowed key from the RPUs and reconstruct the key.
+    #[derive(StructOpt, Debug)]
+    pub struct RecoverKey {
+        /// The namespace of the key.
+        pub namespace: String,
+        /// The version of the key.
+        pub version: u32,
+        /// The RPUs holding the shares (`<peer-id>@<turi-address>`).
+        #[structopt(required = true)]
+        pub rpus: Vec<ParseRpu>,
+    }
+
+    /// Unban a peer.
+    #[derive(StructOpt, Debug)]
+    pub struct Unban {
+        /// The public key of the peer to unban.
+        pub peer_id: PeerId,
+    }
+
+    /// Evict queued transactions.
+    #[derive(StructOpt, Debug)]
+    pub struct Evict {
+        /// The hashes of the transactions to evict (see the `queue` subcommand).
+        #[structopt(required = true)]
+        pub hashes: Vec<TransactionHash>,
+    }
+
+    /// Trace a transaction.
+    #[derive(StructOpt, Debug)]
+    pub struct Trace {
+        /// The hash of the transaction (returned in the receipt of an idempotent submission).
+        pub hash: TransactionHash,
+    }
+
+    /// Watch the receipt of a transaction submitted with a deadline.
+    #[derive(StructOpt, Debug)]
+    pub struct WatchReceipt {
+        /// The hash of the transaction (returned in the receipt).
+        pub hash: TransactionHash,
+    }
+
+    /// Get the execution receipt of a committed transaction.
+    #[derive(StructOpt, Debug)]
+    pub struct ExecutionReceipt {
+        /// The hash of the transaction (returned in the receipt).
+        pub hash: TransactionHash,
+    }
+
     /// Update the permissions for a given account.
     #[derive(StructOpt, Debug)]
     pub struct GetValue {
@@ -144,6 +485,30 @@ pub mod cmd {
         pub filter: ParseFilter<BlockNumber>,
     }
 
+    /// Stream blocks from the blockchain.
+    #[derive(StructOpt, Debug)]
+    pub struct StreamBlocks {
+        /// The number of the first block.
+        pub from: u64,
+        /// The number of the block after the last block (stream up to the newest block if omitted).
+        pub to: Option<u64>,
+    }
+
+    /// Subscribe to the committed blocks.
+    #[derive(StructOpt, Debug)]
+    pub struct SubscribeBlocks {
+        /// Resume after the block with this number (start with the next committed block if omitted).
+        #[structopt(long)]
+        pub after: Option<u64>,
+    }
+
+    /// Get and verify the proof which RPUs accepted a block.
+    #[derive(StructOpt, Debug)]
+    pub struct BlockProof {
+        /// The number of the block.
+        pub block_number: u64,
+    }
+
     #[derive(Debug)]
     pub struct ParseFilter<T>(pub Filter<T>);
 
@@ -194,6 +559,30 @@ pub mod cmd {
     #[derive(Debug)]
     pub struct ParseSpan(pub Span);
 
+    #[derive(Debug)]
+    pub struct ParseKeyValue(pub String, pub String);
+
+    #[derive(Debug)]
+    pub struct ParseRpu(pub PeerId, pub SocketAddr);
+
+    impl FromStr for ParseRpu {
+        type Err = &'static str;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let pos = s.find('@').ok_or("Expected `<peer-id>@<turi-address>`")?;
+            let peer_id = s[..pos].parse().map_err(|_| "Invalid peer id")?;
+            let turi_address = s[pos + 1..].parse().map_err(|_| "Invalid Turi address")?;
+            Ok(Self(peer_id, turi_address))
+        }
+    }
+
+    impl FromStr for ParseKeyValue {
+        type Err = &'static str;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let pos = s.find('=').ok_or("Expected `<key>=<value>`")?;
+            Ok(Self(s[..pos].to_string(), s[pos + 1..].to_string()))
+        }
+    }
+
     impl FromStr for ParseSpan {
         type Err = Box<dyn std::error::Error>;
         fn from_str(s: &str) -> Result<Self, Self::Err> {
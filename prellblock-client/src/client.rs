@@ -2,17 +2,43 @@
 
 #![allow(clippy::future_not_send)]
 
+use crate::query::QueryBuilder;
 use balise::{client, Error};
 use newtype_enum::{Enum, Variant};
-use pinxit::{Identity, PeerId, Signable, Signed};
+use pinxit::{Identity, PeerId, Signable, Signed, Signer};
 use prellblock_client_api::{
     account::{Account, Permissions},
-    consensus::{Block, BlockNumber},
-    message, transaction, ClientMessage, Filter, GetAccount, GetBlock, GetCurrentBlockNumber,
-    GetValue, Query, ReadValues, Transaction,
+    consensus::{Block, BlockHash, BlockNumber, BlockProof, CertifiedHead, TransactionHash},
+    encryption::{EncryptedValue, KeyReference, Keyring},
+    escrow::{RecipientKey, ShareHash},
+    message,
+    metadata::Metadata,
+    parameter::Parameter,
+    quota::Quota,
+    schema::Schema,
+    transaction, ChainComparison, ClientMessage, ClockSkew, CompareChains, ConnectionMetrics,
+    ConsensusFailure, Description, EscrowShare, EvictTransactions, ExecuteIdempotent,
+    ExecuteWithDeadline, ExecutionReceipt, Filter, GetAccount, GetBannedPeers, GetBlock,
+    GetBlockProof, GetCertifiedHead, GetClockSkews, GetConsensusFailures, GetCurrentBlockNumber,
+    GetEscrowShare, GetExecutionReceipt, GetLeaderTerms, GetNetworkMetrics, GetQueue,
+    GetRequestMetrics, GetScrubStatistics, GetStatus, GetTransactionTrace, GetValue, InSession,
+    LeaderTermRecord, OpenSession, Query, QueuedTransaction, ReadValues, Receipt, ReceiptStatus,
+    RequestMetrics, ResumeToken, ScrubStatistics, SessionToken, SignedBatch, Status, StreamBlocks,
+    SubscribeBlocks, Transaction, TransactionTrace, UnbanPeer, WatchReceipt,
 };
 use serde::Serialize;
-use std::{net::SocketAddr, time::SystemTime};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::time;
+
+/// How often a transaction of a batch is sent before `Client::submit` gives up.
+const SUBMIT_ATTEMPTS: u32 = 3;
+/// The delay before sending a transaction again (multiplied by the number of failed attempts).
+const SUBMIT_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 /// A Client Instance.
 ///
@@ -32,7 +58,9 @@ use std::{net::SocketAddr, time::SystemTime};
 /// ```
 pub struct Client {
     rpu_client: client::Client<ClientMessage>,
-    identity: Identity,
+    signer: Arc<dyn Signer>,
+    keyring: Option<Keyring>,
+    session: Option<SessionToken>,
 }
 
 impl Client {
@@ -40,20 +68,80 @@ impl Client {
     ///
     /// The `turi_address` is the Turi's port to connect to.
     #[must_use]
-    #[allow(clippy::missing_const_for_fn)]
     pub fn new(turi_address: SocketAddr, identity: Identity) -> Self {
+        Self::with_signer(turi_address, Arc::new(identity))
+    }
+
+    /// Create a new client that signs with a shared `signer`.
+    ///
+    /// Multiple clients can share a signer without copying its private key.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn with_signer(turi_address: SocketAddr, signer: Arc<dyn Signer>) -> Self {
         Self {
             rpu_client: client::Client::new(turi_address),
-            identity,
+            signer,
+            keyring: None,
+            session: None,
         }
     }
 
+    /// Use the namespace keys of a `keyring` to encrypt and decrypt values.
+    ///
+    /// Queried values of keys in a namespace of the `keyring` are decrypted
+    /// (see [`encryption`](../prellblock_client_api/encryption/index.html)).
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn with_keyring(mut self, keyring: Keyring) -> Self {
+        self.keyring = Some(keyring);
+        self
+    }
+
+    /// Open a session with the RPU and return when it expires.
+    ///
+    /// While the session is valid, `submit_idempotent` and `query_values` authenticate
+    /// their requests with the session's token instead of a signature,
+    /// which saves the RPU from verifying a signature per request.
+    /// Submitted transactions stay individually signed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::Client;
+    /// # async fn test(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let expires_at = client.open_session().await?;
+    /// println!("The session expires at {:?}.", expires_at);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn open_session(&mut self) -> Result<SystemTime, Error> {
+        let challenge = self
+            .rpu_client
+            .send_request(message::GetSessionChallenge)
+            .await?;
+        let request = self.sign(OpenSession { challenge })?;
+        let token = self
+            .rpu_client
+            .send_request(message::OpenSession(request))
+            .await?;
+        let expires_at = token.expires_at;
+        self.session = Some(token);
+        Ok(expires_at)
+    }
+
+    /// The token of the current session (if it is not expired).
+    fn session_token(&self) -> Option<SessionToken> {
+        self.session
+            .as_ref()
+            .filter(|token| !token.is_expired())
+            .cloned()
+    }
+
     fn sign<T>(&self, value: T) -> Result<Signed<T>, Error>
     where
         T: Signable,
     {
         value
-            .sign(&self.identity)
+            .sign(&self.signer)
             .map_err(|err| Error::BoxError(err.into()))
     }
 
@@ -68,6 +156,126 @@ impl Client {
             .await
     }
 
+    /// Submit a batch of transactions that was signed offline (see `TransactionBuilder`).
+    ///
+    /// Expired batches are rejected before anything is sent.
+    /// Transactions failing with a retryable error (see `balise::Error::is_retryable`) are sent again.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::Client;
+    /// use prellblock_client::TransactionBuilder;
+    ///
+    /// # async fn test(client: &mut Client)  -> Result<(), Box<dyn std::error::Error>>{
+    /// // On the signing host:
+    /// let identity = "03d738c972f37a6fd9b33278ac0c50236e45637bcd5aeee82d8323655257d256".parse()?;
+    /// let mut builder = TransactionBuilder::new(identity);
+    /// builder.key_value("speed", &42)?;
+    /// let batch = builder.build();
+    ///
+    /// // Later, on the gateway:
+    /// client.submit(batch).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn submit(&mut self, batch: SignedBatch) -> Result<(), Error> {
+        batch
+            .verify_not_expired()
+            .map_err(|err| Error::BoxError(err.into()))?;
+        for transaction in batch.transactions {
+            let mut attempt = 1;
+            loop {
+                match self
+                    .rpu_client
+                    .send_request(message::Execute(transaction.clone()))
+                    .await
+                {
+                    // Resending is safe: The RPU ignores transactions it already knows.
+                    Err(err) if err.is_retryable() && attempt < SUBMIT_ATTEMPTS => {
+                        log::debug!("Submitting failed (attempt {}): {}", attempt, err);
+                        time::delay_for(SUBMIT_RETRY_DELAY * attempt).await;
+                        attempt += 1;
+                    }
+                    result => break result?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Submit a signed `transaction` at most once per `idempotency_key`.
+    ///
+    /// A retry with the same key and transaction (e.g. after a timeout) returns the original `Receipt`
+    /// instead of queueing the transaction again. Retries need to be sent to the same RPU.
+    /// The `transaction` needs to be signed by the identity of the client.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::Client;
+    /// use newtype_enum::Enum;
+    /// use pinxit::{Identity, Signable};
+    /// use prellblock_client_api::{transaction, Transaction};
+    /// use std::time::SystemTime;
+    ///
+    /// # async fn test(client: &mut Client, identity: &Identity) -> Result<(), Box<dyn std::error::Error>> {
+    /// let transaction = Transaction::from_variant(transaction::KeyValue {
+    ///     key: "speed".to_string(),
+    ///     value: postcard::to_stdvec(&42)?,
+    ///     timestamp: SystemTime::now(),
+    /// })
+    /// .sign(identity)?;
+    ///
+    /// let receipt = client.submit_idempotent("speed-0001".to_string(), transaction.clone()).await?;
+    /// // Retrying returns the same receipt.
+    /// assert_eq!(client.submit_idempotent("speed-0001".to_string(), transaction).await?, receipt);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn submit_idempotent(
+        &mut self,
+        idempotency_key: String,
+        transaction: Signed<Transaction>,
+    ) -> Result<Receipt, Error> {
+        let message = ExecuteIdempotent {
+            idempotency_key,
+            transaction,
+        };
+        if let Some(token) = self.session_token() {
+            let request = InSession {
+                token,
+                request: message,
+            };
+            return self
+                .rpu_client
+                .send_request(message::ExecuteIdempotentInSession(request))
+                .await;
+        }
+        self.rpu_client
+            .send_request(message::ExecuteIdempotent(self.sign(message)?))
+            .await
+    }
+
+    /// Submit a signed `transaction` that must be committed before the `deadline`.
+    ///
+    /// Like `submit_idempotent`, but the RPU removes the transaction from the queues of all RPUs
+    /// if it is not committed by the `deadline`. The outcome can be watched with `watch_receipt`
+    /// on the same RPU.
+    pub async fn submit_with_deadline(
+        &mut self,
+        idempotency_key: String,
+        transaction: Signed<Transaction>,
+        deadline: SystemTime,
+    ) -> Result<Receipt, Error> {
+        let message = ExecuteWithDeadline {
+            idempotency_key,
+            transaction,
+            deadline,
+        };
+        self.rpu_client
+            .send_request(message::ExecuteWithDeadline(self.sign(message)?))
+            .await
+    }
+
     /// Send a key-value transaction.
     pub async fn send_key_value<V>(&mut self, key: String, value: V) -> Result<(), Error>
     where
@@ -82,6 +290,97 @@ impl Client {
         .await
     }
 
+    /// Send a transaction setting all keys of `values` at once.
+    ///
+    /// The RPUs write all values or none of them (e.g. a consistent snapshot of related measurements).
+    pub async fn send_key_values<V>(&mut self, values: Vec<(String, V)>) -> Result<(), Error>
+    where
+        V: Serialize + Send,
+    {
+        let values = values
+            .into_iter()
+            .map(|(key, value)| postcard::to_stdvec(&value).map(|value| (key, value)))
+            .collect::<Result<_, _>>()?;
+        self.execute(transaction::MultiKeyValue {
+            values,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Send a transaction setting the shared `key` to `new` if its value is `expected` when committed.
+    ///
+    /// Whether the value was swapped is recorded in the execution receipt of the transaction
+    /// (see `execution_receipt`).
+    pub async fn send_compare_and_swap<V>(
+        &mut self,
+        key: String,
+        expected: Option<V>,
+        new: Option<V>,
+    ) -> Result<(), Error>
+    where
+        V: Serialize + Send,
+    {
+        let expected = expected
+            .map(|value| postcard::to_stdvec(&value))
+            .transpose()?;
+        let new = new.map(|value| postcard::to_stdvec(&value)).transpose()?;
+        self.execute(transaction::CompareAndSwap {
+            key,
+            expected,
+            new,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Send a key-value transaction with `metadata` describing the value.
+    ///
+    /// The `metadata` is validated before sending.
+    pub async fn send_key_value_with_metadata<V>(
+        &mut self,
+        key: String,
+        value: V,
+        metadata: Metadata,
+    ) -> Result<(), Error>
+    where
+        V: Serialize + Send,
+    {
+        metadata
+            .validate()
+            .map_err(|err| Error::BoxError(err.into()))?;
+        let value = postcard::to_stdvec(&value)?;
+        self.execute(transaction::KeyValueWithMetadata {
+            key,
+            value,
+            metadata,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Send a key-value transaction with the value encrypted by the client's keyring.
+    ///
+    /// The key, timestamp and signature remain readable by the RPUs.
+    pub async fn send_encrypted_key_value<V>(&mut self, key: String, value: V) -> Result<(), Error>
+    where
+        V: Serialize + Send,
+    {
+        let keyring = self
+            .keyring
+            .as_ref()
+            .ok_or_else(|| Error::BoxError("The client has no keyring.".into()))?;
+        let value = keyring
+            .encrypt(&key, &postcard::to_stdvec(&value)?)
+            .map_err(|err| Error::BoxError(err.into()))?;
+        self.execute(transaction::EncryptedKeyValue {
+            key,
+            value,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
     /// Update a `target` account's `permissions`.
     pub async fn update_account(
         &mut self,
@@ -121,6 +420,115 @@ impl Client {
         .await
     }
 
+    /// Set (or remove) the `schema` of all values in a key `namespace`.
+    pub async fn set_schema(
+        &mut self,
+        namespace: String,
+        schema: Option<Schema>,
+    ) -> Result<(), Error> {
+        self.execute(transaction::SetSchema {
+            namespace,
+            schema,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Schedule the migration of the `WorldState` to a format `version` at `activation_height`.
+    pub async fn schedule_migration(
+        &mut self,
+        version: u32,
+        activation_height: BlockNumber,
+    ) -> Result<(), Error> {
+        self.execute(transaction::ScheduleMigration {
+            version,
+            activation_height,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Set the governed `parameter` to `value` from the block at `activation_height` on.
+    pub async fn set_parameter(
+        &mut self,
+        parameter: Parameter,
+        value: u64,
+        activation_height: BlockNumber,
+    ) -> Result<(), Error> {
+        self.execute(transaction::SetParameter {
+            parameter,
+            value,
+            activation_height,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Set the leader `weight` of the RPU `id`.
+    pub async fn set_leader_weight(&mut self, id: PeerId, weight: u32) -> Result<(), Error> {
+        self.execute(transaction::SetLeaderWeight {
+            id,
+            weight,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Set the queue `weight` of the account `id`.
+    pub async fn set_queue_weight(&mut self, id: PeerId, weight: u32) -> Result<(), Error> {
+        self.execute(transaction::SetQueueWeight {
+            id,
+            weight,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Set (or remove) the write throughput `quota` of a key `namespace`.
+    pub async fn set_quota(
+        &mut self,
+        namespace: String,
+        quota: Option<Quota>,
+    ) -> Result<(), Error> {
+        self.execute(transaction::SetQuota {
+            namespace,
+            quota,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Escrow a namespace key by the hashes of its shares held by the RPUs.
+    ///
+    /// See [`escrow`](../prellblock_client_api/escrow/index.html).
+    pub async fn escrow_key(
+        &mut self,
+        key_reference: KeyReference,
+        key_hash: BlockHash,
+        threshold: usize,
+        share_hashes: Vec<(PeerId, ShareHash)>,
+    ) -> Result<(), Error> {
+        self.execute(transaction::EscrowKey {
+            key_reference,
+            key_hash,
+            threshold,
+            share_hashes,
+            timestamp: SystemTime::now(),
+        })
+        .await
+    }
+
+    /// Authorize an auditor to collect the shares of an escrowed key.
+    ///
+    /// The `authorization` must be approved by a supermajority of the RPUs
+    /// (see `transaction::AuthorizeDecryption::approve`).
+    pub async fn authorize_decryption(
+        &mut self,
+        authorization: transaction::AuthorizeDecryption,
+    ) -> Result<(), Error> {
+        self.execute(authorization).await
+    }
+
     /// Query one or multiple accounts.
     ///
     /// All accounts `Accounts` matching the `peer_ids` will be returned.
@@ -166,9 +574,53 @@ impl Client {
             filter: filter.into(),
             query,
         };
-        self.rpu_client
-            .send_request(message::GetValue(self.sign(message)?))
-            .await
+        let mut values = match self.session_token() {
+            Some(token) => {
+                let request = InSession {
+                    token,
+                    request: message,
+                };
+                self.rpu_client
+                    .send_request(message::GetValueInSession(request))
+                    .await?
+            }
+            None => {
+                self.rpu_client
+                    .send_request(message::GetValue(self.sign(message)?))
+                    .await?
+            }
+        };
+        self.decrypt_values(&mut values)?;
+        Ok(values)
+    }
+
+    /// Build a query of values, which handles pagination and deserializes the values.
+    ///
+    /// See [`QueryBuilder`](query/struct.QueryBuilder.html).
+    pub fn query(&mut self) -> QueryBuilder<'_> {
+        QueryBuilder::new(self)
+    }
+
+    /// Decrypt the `values` of keys in a namespace of the keyring.
+    fn decrypt_values(&self, values: &mut ReadValues) -> Result<(), Error> {
+        let keyring = match &self.keyring {
+            Some(keyring) => keyring,
+            None => return Ok(()),
+        };
+        for values_of_peer in values.values_mut() {
+            for (key, values_of_series) in values_of_peer {
+                if keyring.key_reference(key).is_none() {
+                    continue;
+                }
+                for (value, _, _) in values_of_series.values_mut() {
+                    let encrypted_value = EncryptedValue::decode(value)?;
+                    *value = keyring
+                        .decrypt(key, &encrypted_value)
+                        .map_err(|err| Error::BoxError(err.into()))?;
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Query the current value of specific key-value pairs.
@@ -220,6 +672,76 @@ impl Client {
             .await
     }
 
+    /// Stream the blocks from `from` up to (excluding) `to` in order.
+    ///
+    /// If `to` is `None`, all blocks up to the newest one are streamed.
+    /// The blocks are received one by one, so large ranges (e.g. exporting the whole blockchain)
+    /// do not need to fit into memory.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::{Client, consensus::BlockNumber};
+    /// # async fn test(client: &mut Client)  -> Result<(), Box<dyn std::error::Error>>{
+    /// let mut blocks = client.stream_blocks(BlockNumber::new(0), None).await?;
+    /// while let Some(block) = blocks.next().await {
+    ///     println!("{:?}", block?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stream_blocks(
+        &mut self,
+        from: BlockNumber,
+        to: Option<BlockNumber>,
+    ) -> Result<client::ResponseStream<'_, Block>, Error> {
+        let message = StreamBlocks { from, to };
+        let message = self.sign(message)?;
+        self.rpu_client
+            .send_stream_request(message::StreamBlocks(message))
+            .await
+    }
+
+    /// Subscribe to the committed blocks, resuming after the `resume_token`.
+    ///
+    /// Without a `resume_token`, the blocks committed from now on are received.
+    /// After a lost connection, subscribe again with the token of the last received block
+    /// to continue where the subscription left off.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::{Client, ResumeToken};
+    /// # async fn test(client: &mut Client)  -> Result<(), Box<dyn std::error::Error>>{
+    /// let mut resume_token = None;
+    /// let mut blocks = client.subscribe_blocks(resume_token).await?;
+    /// while let Some(block) = blocks.next().await {
+    ///     let block = block?;
+    ///     resume_token = Some(ResumeToken::new(block.block_number()));
+    ///     println!("{:?}", block);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_blocks(
+        &mut self,
+        resume_token: Option<ResumeToken>,
+    ) -> Result<client::ResponseStream<'_, Block>, Error> {
+        let message = SubscribeBlocks { resume_token };
+        let message = self.sign(message)?;
+        self.rpu_client
+            .send_stream_request(message::SubscribeBlocks(message))
+            .await
+    }
+
+    /// Retrieve the proof which RPUs accepted the block with the given `block_number`.
+    ///
+    /// The proof does not need to be trusted, it can be checked with `BlockProof::verify`.
+    pub async fn block_proof(&mut self, block_number: BlockNumber) -> Result<BlockProof, Error> {
+        let message = GetBlockProof { block_number };
+        self.rpu_client
+            .send_request(message::GetBlockProof(self.sign(message)?))
+            .await
+    }
+
     /// Retrieve the current block number.
     ///
     /// # Example
@@ -238,4 +760,201 @@ impl Client {
             ))
             .await
     }
+
+    /// Retrieve the head of the chain certified by a supermajority of the RPUs.
+    ///
+    /// Verify the certificate with [`CertifiedHead::verify`](consensus/struct.CertifiedHead.html#method.verify).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::Client;
+    /// # async fn test(client: &mut Client)  -> Result<(), Box<dyn std::error::Error>>{
+    /// let head = client.certified_head().await?;
+    /// let peer_ids = head.verify()?;
+    /// println!("Block #{} was confirmed by {} RPUs.", head.block_number, peer_ids.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn certified_head(&mut self) -> Result<CertifiedHead, Error> {
+        self.rpu_client
+            .send_request(message::GetCertifiedHead(self.sign(GetCertifiedHead)?))
+            .await
+    }
+
+    /// Retrieve the description (supported requests, limits and chain id) of the RPU.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::Client;
+    /// # async fn test(client: &mut Client)  -> Result<(), Box<dyn std::error::Error>>{
+    /// let description = client.describe().await?;
+    /// println!("The RPU supports: {:?}", description.requests);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn describe(&mut self) -> Result<Description, Error> {
+        self.rpu_client.send_request(message::Describe).await
+    }
+
+    /// Retrieve the peers banned by the RPU (only allowed for admins).
+    pub async fn banned_peers(&mut self) -> Result<Vec<PeerId>, Error> {
+        self.rpu_client
+            .send_request(message::GetBannedPeers(self.sign(GetBannedPeers)?))
+            .await
+    }
+
+    /// Unban a peer on the RPU (only allowed for admins).
+    pub async fn unban_peer(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        let message = UnbanPeer { peer_id };
+        self.rpu_client
+            .send_request(message::UnbanPeer(self.sign(message)?))
+            .await
+    }
+
+    /// Retrieve the estimated clock skews of the other RPUs (only allowed for admins).
+    pub async fn clock_skews(&mut self) -> Result<HashMap<PeerId, ClockSkew>, Error> {
+        self.rpu_client
+            .send_request(message::GetClockSkews(self.sign(GetClockSkews)?))
+            .await
+    }
+
+    /// Retrieve the latest consensus failures recorded by the RPU (only allowed for admins).
+    pub async fn consensus_failures(&mut self) -> Result<Vec<ConsensusFailure>, Error> {
+        self.rpu_client
+            .send_request(message::GetConsensusFailures(
+                self.sign(GetConsensusFailures)?,
+            ))
+            .await
+    }
+
+    /// Retrieve the history of the leader terms seen by the RPU (only allowed for admins).
+    pub async fn leader_terms(&mut self) -> Result<Vec<LeaderTermRecord>, Error> {
+        self.rpu_client
+            .send_request(message::GetLeaderTerms(self.sign(GetLeaderTerms)?))
+            .await
+    }
+
+    /// Retrieve the results of the block storage scrubber of the RPU (only allowed for admins).
+    pub async fn scrub_statistics(&mut self) -> Result<ScrubStatistics, Error> {
+        self.rpu_client
+            .send_request(message::GetScrubStatistics(self.sign(GetScrubStatistics)?))
+            .await
+    }
+
+    /// Retrieve the metrics of the connections of the RPU to other servers (only allowed for admins).
+    pub async fn network_metrics(&mut self) -> Result<Vec<ConnectionMetrics>, Error> {
+        self.rpu_client
+            .send_request(message::GetNetworkMetrics(self.sign(GetNetworkMetrics)?))
+            .await
+    }
+
+    /// Retrieve the metrics of the requests handled by the RPU (only allowed for admins).
+    pub async fn request_metrics(&mut self) -> Result<Vec<RequestMetrics>, Error> {
+        self.rpu_client
+            .send_request(message::GetRequestMetrics(self.sign(GetRequestMetrics)?))
+            .await
+    }
+
+    /// Retrieve the status of the RPU, e.g. whether it was halted (only allowed for admins).
+    pub async fn status(&mut self) -> Result<Status, Error> {
+        self.rpu_client
+            .send_request(message::GetStatus(self.sign(GetStatus)?))
+            .await
+    }
+
+    /// Compare the chain of the RPU with the chains of all other RPUs (only allowed for admins).
+    pub async fn compare_chains(&mut self) -> Result<ChainComparison, Error> {
+        self.rpu_client
+            .send_request(message::CompareChains(self.sign(CompareChains)?))
+            .await
+    }
+
+    /// Retrieve the share of an escrowed key held by the RPU `rpu` (only allowed for authorized auditors).
+    ///
+    /// The share is sealed to the `recipient_key` (open it with `escrow::RecipientKey::open`).
+    pub async fn escrow_share(
+        &mut self,
+        rpu: PeerId,
+        key_reference: KeyReference,
+        recipient_key: &RecipientKey,
+    ) -> Result<EscrowShare, Error> {
+        let message = GetEscrowShare {
+            key_reference,
+            rpu,
+            recipient_key: *recipient_key.public_key(),
+            timestamp: SystemTime::now(),
+        };
+        self.rpu_client
+            .send_request(message::GetEscrowShare(self.sign(message)?))
+            .await
+    }
+
+    /// Retrieve the transactions queued on the RPU (only allowed for admins).
+    pub async fn queued_transactions(&mut self) -> Result<Vec<QueuedTransaction>, Error> {
+        self.rpu_client
+            .send_request(message::GetQueue(self.sign(GetQueue)?))
+            .await
+    }
+
+    /// Evict queued transactions on all RPUs (only allowed for admins).
+    pub async fn evict_transactions(&mut self, hashes: Vec<TransactionHash>) -> Result<(), Error> {
+        let message = EvictTransactions {
+            hashes,
+            timestamp: SystemTime::now(),
+        };
+        self.rpu_client
+            .send_request(message::EvictTransactions(self.sign(message)?))
+            .await
+    }
+
+    /// Watch the status of the receipt of a transaction submitted with `submit_with_deadline`.
+    ///
+    /// The current status is received first, followed by every change until the status is final.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use prellblock_client::Client;
+    /// # use prellblock_client::consensus::TransactionHash;
+    /// # async fn test(client: &mut Client, transaction_hash: TransactionHash) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut statuses = client.watch_receipt(transaction_hash).await?;
+    /// while let Some(status) = statuses.next().await {
+    ///     println!("{:?}", status?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn watch_receipt(
+        &mut self,
+        transaction_hash: TransactionHash,
+    ) -> Result<client::ResponseStream<'_, ReceiptStatus>, Error> {
+        let message = WatchReceipt { transaction_hash };
+        let message = self.sign(message)?;
+        self.rpu_client
+            .send_stream_request(message::WatchReceipt(message))
+            .await
+    }
+
+    /// Retrieve whether the committed transaction `transaction_hash` was applied (only allowed for its signer and admins).
+    ///
+    /// Returns `None` if the transaction is not committed (yet).
+    pub async fn execution_receipt(
+        &mut self,
+        transaction_hash: TransactionHash,
+    ) -> Result<Option<ExecutionReceipt>, Error> {
+        let message = GetExecutionReceipt { transaction_hash };
+        self.rpu_client
+            .send_request(message::GetExecutionReceipt(self.sign(message)?))
+            .await
+    }
+
+    /// Retrieve where the transaction with the given `hash` is on the RPU right now (only allowed for admins).
+    pub async fn transaction_trace(
+        &mut self,
+        hash: TransactionHash,
+    ) -> Result<TransactionTrace, Error> {
+        let message = GetTransactionTrace { hash };
+        self.rpu_client
+            .send_request(message::GetTransactionTrace(self.sign(message)?))
+            .await
+    }
 }
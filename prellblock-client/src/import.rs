@@ -0,0 +1,336 @@
+//! Bulk import of historical readings (e.g. from a legacy historian).
+//!
+//! The readings are read from a CSV or JSON Lines file, signed in batches and submitted to an RPU.
+//! The number of imported readings is stored in a progress file after each batch,
+//! so an interrupted import continues with the first batch that was not completed.
+
+use crate::cli::prelude::*;
+use pinxit::Signer;
+use prellblock_client::{
+    metadata::{Metadata, Quality, SourceTimestamp, TimePrecision},
+    Client, SignedBatch, TransactionBuilder,
+};
+use serde::Deserialize;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The format of an import file.
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// Comma separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(format!("Unknown format {:?}.", s)),
+        }
+    }
+}
+
+/// The type the values are encoded as.
+#[derive(Debug, Clone, Copy)]
+pub enum ValueType {
+    /// A 64 bit floating point number.
+    F64,
+    /// A 32 bit floating point number.
+    F32,
+    /// A signed 64 bit integer.
+    I64,
+    /// An unsigned 64 bit integer.
+    U64,
+    /// A boolean.
+    Bool,
+    /// A string.
+    String,
+}
+
+impl FromStr for ValueType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f64" => Ok(Self::F64),
+            "f32" => Ok(Self::F32),
+            "i64" => Ok(Self::I64),
+            "u64" => Ok(Self::U64),
+            "bool" => Ok(Self::Bool),
+            "string" => Ok(Self::String),
+            _ => Err(format!("Unknown value type {:?}.", s)),
+        }
+    }
+}
+
+/// A single reading of an import file.
+#[derive(Debug, Deserialize)]
+struct Reading<V> {
+    key: String,
+    /// The time of the reading (RFC 3339).
+    time: String,
+    value: V,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    quality: Option<String>,
+}
+
+/// A reading that is ready to be signed.
+struct Record {
+    key: String,
+    value: RecordValue,
+    metadata: Metadata,
+}
+
+enum RecordValue {
+    F64(f64),
+    F32(f32),
+    I64(i64),
+    U64(u64),
+    Bool(bool),
+    String(String),
+}
+
+/// Settings for converting readings into records.
+struct Converter {
+    value_type: ValueType,
+    utc_offset_minutes: i16,
+    precision: TimePrecision,
+}
+
+impl Converter {
+    fn convert(&self, reading: Reading<String>) -> Result<Record, BoxError> {
+        let time: SystemTime = humantime::parse_rfc3339_weak(&reading.time)?;
+        let value = match self.value_type {
+            ValueType::F64 => RecordValue::F64(reading.value.trim().parse()?),
+            ValueType::F32 => RecordValue::F32(reading.value.trim().parse()?),
+            ValueType::I64 => RecordValue::I64(reading.value.trim().parse()?),
+            ValueType::U64 => RecordValue::U64(reading.value.trim().parse()?),
+            ValueType::Bool => RecordValue::Bool(reading.value.trim().parse()?),
+            ValueType::String => RecordValue::String(reading.value),
+        };
+        let quality = match reading.quality.as_deref() {
+            None | Some("") => None,
+            Some(quality) => Some(quality.parse::<Quality>()?),
+        };
+        let metadata = Metadata {
+            unit: reading.unit.filter(|unit| !unit.is_empty()),
+            source_timestamp: Some(SourceTimestamp {
+                time,
+                utc_offset_minutes: self.utc_offset_minutes,
+                precision: self.precision,
+            }),
+            quality,
+        };
+        metadata.validate()?;
+        Ok(Record {
+            key: reading.key,
+            value,
+            metadata,
+        })
+    }
+}
+
+/// Read all readings of the import file at `path`.
+fn read_readings(
+    path: &Path,
+    format: Format,
+) -> Result<Box<dyn Iterator<Item = Result<Reading<String>, BoxError>>>, BoxError> {
+    match format {
+        Format::Csv => {
+            let reader = csv::Reader::from_path(path)?;
+            Ok(Box::new(
+                reader
+                    .into_deserialize()
+                    .map(|result| result.map_err(BoxError::from)),
+            ))
+        }
+        Format::Jsonl => {
+            let reader = BufReader::new(fs::File::open(path)?);
+            Ok(Box::new(
+                reader
+                    .lines()
+                    .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+                    .map(|line| {
+                        let reading: Reading<serde_json::Value> = serde_json::from_str(&line?)?;
+                        // Numbers and booleans are converted with the configured value type.
+                        let value = match reading.value {
+                            serde_json::Value::String(value) => value,
+                            value => value.to_string(),
+                        };
+                        Ok(Reading {
+                            key: reading.key,
+                            time: reading.time,
+                            value,
+                            unit: reading.unit,
+                            quality: reading.quality,
+                        })
+                    }),
+            ))
+        }
+    }
+}
+
+/// Read the number of readings imported so far.
+fn read_progress(path: &Path) -> Result<usize, BoxError> {
+    match fs::read_to_string(path) {
+        Ok(progress) => Ok(progress.trim().parse()?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Store the number of readings imported so far.
+///
+/// The file is replaced atomically, so it is never left half written.
+fn write_progress(path: &Path, imported: usize) -> Result<(), BoxError> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, imported.to_string())?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Sign the `records` and submit them with `workers` parallel clients.
+///
+/// Records of the same key are submitted by the same worker, so their order is kept.
+async fn submit_batch(
+    signer: &Arc<dyn Signer>,
+    turi_address: SocketAddr,
+    records: Vec<Record>,
+    workers: usize,
+) -> Result<(), BoxError> {
+    let mut builders: Vec<_> = (0..workers)
+        .map(|_| TransactionBuilder::with_signer(signer.clone()))
+        .collect();
+    for record in records {
+        let mut hasher = DefaultHasher::new();
+        record.key.hash(&mut hasher);
+        #[allow(clippy::cast_possible_truncation)]
+        let builder = &mut builders[hasher.finish() as usize % workers];
+        let Record {
+            key,
+            value,
+            metadata,
+        } = record;
+        match &value {
+            RecordValue::F64(value) => builder.key_value_with_metadata(key, value, metadata),
+            RecordValue::F32(value) => builder.key_value_with_metadata(key, value, metadata),
+            RecordValue::I64(value) => builder.key_value_with_metadata(key, value, metadata),
+            RecordValue::U64(value) => builder.key_value_with_metadata(key, value, metadata),
+            RecordValue::Bool(value) => builder.key_value_with_metadata(key, value, metadata),
+            RecordValue::String(value) => builder.key_value_with_metadata(key, value, metadata),
+        }?;
+    }
+
+    let tasks: Vec<_> = builders
+        .into_iter()
+        .map(TransactionBuilder::build)
+        .filter(|batch: &SignedBatch| !batch.transactions.is_empty())
+        .map(|batch| {
+            let signer = signer.clone();
+            tokio::spawn(async move {
+                let mut client = Client::with_signer(turi_address, signer);
+                client.submit(batch).await
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await??;
+    }
+    Ok(())
+}
+
+/// Import the readings of a file.
+pub async fn main_import(signer: Arc<dyn Signer>, turi_address: SocketAddr, cmd: cmd::Import) {
+    if let Err(err) = import(signer, turi_address, cmd).await {
+        log::error!("Import failed: {}", err);
+    }
+}
+
+async fn import(
+    signer: Arc<dyn Signer>,
+    turi_address: SocketAddr,
+    cmd: cmd::Import,
+) -> Result<(), BoxError> {
+    let cmd::Import {
+        file,
+        format,
+        value_type,
+        utc_offset,
+        precision,
+        batch_size,
+        workers,
+        progress_file,
+    } = cmd;
+
+    let format = match format {
+        Some(format) => format,
+        None => match file.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") => Format::Jsonl,
+            _ => Format::Csv,
+        },
+    };
+    let progress_file = progress_file.unwrap_or_else(|| {
+        let mut progress_file = file.clone().into_os_string();
+        progress_file.push(".progress");
+        PathBuf::from(progress_file)
+    });
+    let converter = Converter {
+        value_type,
+        utc_offset_minutes: utc_offset,
+        precision,
+    };
+
+    let skip = read_progress(&progress_file)?;
+    if skip > 0 {
+        log::info!("Resuming after {} imported readings.", skip);
+    }
+
+    let start = Instant::now();
+    let mut imported = skip;
+    let mut readings = read_readings(&file, format)?.skip(skip).peekable();
+    while readings.peek().is_some() {
+        let records = readings
+            .by_ref()
+            .take(batch_size.max(1))
+            .enumerate()
+            .map(|(index, reading)| {
+                reading
+                    .and_then(|reading| converter.convert(reading))
+                    .map_err(|err| format!("Reading #{}: {}", imported + index + 1, err).into())
+            })
+            .collect::<Result<Vec<_>, BoxError>>()?;
+        let count = records.len();
+
+        submit_batch(&signer, turi_address, records, workers.max(1)).await?;
+        imported += count;
+        write_progress(&progress_file, imported)?;
+
+        #[allow(clippy::cast_precision_loss)]
+        let rate = (imported - skip) as f64 / start.elapsed().as_secs_f64();
+        log::info!("Imported {} readings ({:.0} readings/s).", imported, rate);
+    }
+
+    log::info!(
+        "Import of {} finished: {} readings imported in {:?}.",
+        file.display(),
+        imported - skip,
+        start.elapsed()
+    );
+    Ok(())
+}
@@ -4,6 +4,14 @@
 //! A Library Crate for external Clients - Malte (TM)
 
 mod client;
+pub mod query;
 
 pub use client::Client;
-pub use prellblock_client_api::{account, consensus, Filter, Query, Span};
+pub use prellblock_client_api::{
+    account, consensus, encryption, escrow, metadata, parameter, quota, schema, BuilderError,
+    ChainComparison, ClockSkew, ConnectionMetrics, ConsensusFailure, ConsensusFailureKind,
+    Description, EscrowShare, ExecutionReceipt, ExecutionStatus, Filter, Histogram,
+    LeaderTermRecord, PeerChainComparison, Query, QueuedTransaction, Receipt, ReceiptStatus,
+    RequestMetrics, ResumeToken, ScrubReport, ScrubStatistics, SignedBatch, Span, Status,
+    TransactionBuilder, ViewChangeReason, PROTOCOL_VERSION,
+};
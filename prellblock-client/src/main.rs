@@ -8,15 +8,40 @@
 //! An example client used to simulate clients.
 
 mod cli;
+mod import;
 
 use cli::prelude::*;
-use prellblock_client::{account::Permissions, Client, Query};
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signer};
+use prellblock_client::{
+    account::Permissions,
+    consensus::{transaction_hash, BlockNumber, TransactionHash},
+    encryption::{KeyReference, Keyring, KEY_LEN},
+    escrow::{self, RecipientKey},
+    metadata::{Metadata, SourceTimestamp},
+    quota::Quota,
+    schema::Schema,
+    Client, PeerChainComparison, Query, ResumeToken, PROTOCOL_VERSION,
+};
+use prellblock_client_api::{transaction, Transaction};
 use rand::{
     rngs::{OsRng, StdRng},
     RngCore, SeedableRng,
 };
-use std::{fs, net::SocketAddr, str, time::Instant};
+use serde::Deserialize;
+use std::{
+    fs,
+    net::SocketAddr,
+    path::Path,
+    str,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 use structopt::StructOpt;
+use tokio::time;
+
+/// The time to wait before resubscribing to the blocks after an error.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
 
 #[tokio::main]
 async fn main() {
@@ -26,41 +51,242 @@ async fn main() {
     let opt = Opt::from_args();
     log::debug!("Command line arguments: {:#?}", opt);
 
-    let identity_bytes =
-        fs::read_to_string(opt.private_key_file).expect("Could not open private key file.");
-    let client = create_client(opt.turi_address, &identity_bytes);
+    let signer: Arc<dyn Signer> = Arc::new(
+        Identity::from_file(&opt.private_key_file).expect("Could not load private key file."),
+    );
+    let mut client = Client::with_signer(opt.turi_address, signer.clone());
+    if let Some(keyring_file) = &opt.keyring {
+        client = client.with_keyring(read_keyring(keyring_file));
+    }
 
     match opt.cmd {
         Cmd::Set(cmd) => main_set(client, cmd).await,
-        Cmd::Benchmark(cmd) => main_benchmark(identity_bytes, opt.turi_address, cmd).await,
+        Cmd::SetMany(cmd) => main_set_many(client, cmd).await,
+        Cmd::CompareAndSwap(cmd) => main_compare_and_swap(client, signer, cmd).await,
+        Cmd::SetWithDeadline(cmd) => main_set_with_deadline(client, signer, cmd).await,
+        Cmd::Import(cmd) => import::main_import(signer, opt.turi_address, cmd).await,
+        Cmd::Benchmark(cmd) => main_benchmark(signer, opt.turi_address, cmd).await,
         Cmd::UpdateAccount(cmd) => main_update_account(client, cmd).await,
         Cmd::CreateAccount(cmd) => main_create_account(client, cmd).await,
         Cmd::DeleteAccount(cmd) => main_delete_account(client, cmd).await,
+        Cmd::SetSchema(cmd) => main_set_schema(client, cmd).await,
+        Cmd::ScheduleMigration(cmd) => main_schedule_migration(client, cmd).await,
+        Cmd::SetParameter(cmd) => main_set_parameter(client, cmd).await,
+        Cmd::SetLeaderWeight(cmd) => main_set_leader_weight(client, cmd).await,
+        Cmd::SetQueueWeight(cmd) => main_set_queue_weight(client, cmd).await,
+        Cmd::SetQuota(cmd) => main_set_quota(client, cmd).await,
+        Cmd::EscrowKey(cmd) => main_escrow_key(client, opt.keyring.as_deref(), cmd).await,
+        Cmd::RequestDecryption(cmd) => main_request_decryption(cmd),
+        Cmd::AuthorizeDecryption(cmd) => main_authorize_decryption(client, cmd).await,
+        Cmd::RecoverKey(cmd) => main_recover_key(signer, cmd).await,
         Cmd::GetValue(cmd) => main_get_value(client, cmd).await,
         Cmd::GetAccount(cmd) => main_get_account(client, cmd).await,
         Cmd::GetBlock(cmd) => main_get_block(client, cmd).await,
+        Cmd::StreamBlocks(cmd) => main_stream_blocks(client, cmd).await,
+        Cmd::SubscribeBlocks(cmd) => main_subscribe_blocks(client, cmd).await,
+        Cmd::BlockProof(cmd) => main_block_proof(client, cmd).await,
         Cmd::CurrentBlockNumber => main_current_block_number(client).await,
+        Cmd::CertifiedHead => main_certified_head(client).await,
+        Cmd::Describe => main_describe(client).await,
+        Cmd::BannedPeers => main_banned_peers(client).await,
+        Cmd::Unban(cmd) => main_unban(client, cmd).await,
+        Cmd::Queue => main_queue(client).await,
+        Cmd::ClockSkews => main_clock_skews(client).await,
+        Cmd::ConsensusFailures => main_consensus_failures(client).await,
+        Cmd::LeaderTerms => main_leader_terms(client).await,
+        Cmd::ScrubStatistics => main_scrub_statistics(client).await,
+        Cmd::CompareChains => main_compare_chains(client).await,
+        Cmd::NetworkMetrics => main_network_metrics(client).await,
+        Cmd::RequestMetrics => main_request_metrics(client).await,
+        Cmd::Status => main_status(client).await,
+        Cmd::Evict(cmd) => main_evict(client, cmd).await,
+        Cmd::Trace(cmd) => main_trace(client, cmd).await,
+        Cmd::WatchReceipt(cmd) => main_watch_receipt(client, cmd).await,
+        Cmd::ExecutionReceipt(cmd) => main_execution_receipt(client, cmd).await,
     }
 }
 
-fn create_client(turi_address: SocketAddr, identity: &str) -> Client {
-    let identity = identity
-        .parse()
-        .expect("Cannot read identity. Wrong format?");
-    Client::new(turi_address, identity)
-}
-
 async fn main_set(mut client: Client, cmd: cmd::Set) {
-    let cmd::Set { key, value } = cmd;
+    let cmd::Set {
+        key,
+        value,
+        unit,
+        source_time,
+        utc_offset,
+        precision,
+        quality,
+        encrypt,
+    } = cmd;
 
+    let source_timestamp = source_time.map(|time| SourceTimestamp {
+        time: time.into(),
+        utc_offset_minutes: utc_offset,
+        precision,
+    });
     // execute the test client
-    match client.send_key_value(key, value).await {
+    let has_metadata = unit.is_some() || source_timestamp.is_some() || quality.is_some();
+    let result = if encrypt {
+        if has_metadata {
+            log::error!("Metadata cannot be set for encrypted values.");
+            return;
+        }
+        client.send_encrypted_key_value(key, value).await
+    } else if !has_metadata {
+        client.send_key_value(key, value).await
+    } else {
+        let metadata = Metadata {
+            unit,
+            source_timestamp,
+            quality,
+        };
+        client
+            .send_key_value_with_metadata(key, value, metadata)
+            .await
+    };
+
+    match result {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_set_many(mut client: Client, cmd: cmd::SetMany) {
+    let values = cmd
+        .values
+        .into_iter()
+        .map(|cmd::ParseKeyValue(key, value)| (key, value))
+        .collect();
+
+    match client.send_key_values(values).await {
         Err(err) => log::error!("Failed to send transaction: {}", err),
         Ok(()) => log::debug!("Transaction ok!"),
     }
 }
 
-async fn main_benchmark(identity: String, turi_address: SocketAddr, cmd: cmd::Benchmark) {
+async fn main_compare_and_swap(
+    mut client: Client,
+    signer: Arc<dyn Signer>,
+    cmd: cmd::CompareAndSwap,
+) {
+    let cmd::CompareAndSwap { key, expected, new } = cmd;
+
+    let encode = |value: String| postcard::to_stdvec(&value).unwrap();
+    let transaction = Transaction::from_variant(transaction::CompareAndSwap {
+        key,
+        expected: expected.map(encode),
+        new: new.map(encode),
+        timestamp: SystemTime::now(),
+    })
+    .sign(&*signer)
+    .unwrap();
+    let idempotency_key = transaction_hash(&transaction).to_string();
+
+    match client.submit_idempotent(idempotency_key, transaction).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(receipt) => log::info!(
+            "Transaction {} was accepted, its outcome is recorded in its execution receipt.",
+            receipt.transaction_hash
+        ),
+    }
+}
+
+async fn main_set_with_deadline(
+    mut client: Client,
+    signer: Arc<dyn Signer>,
+    cmd: cmd::SetWithDeadline,
+) {
+    let cmd::SetWithDeadline {
+        key,
+        value,
+        timeout,
+        idempotency_key,
+    } = cmd;
+
+    let transaction = Transaction::from_variant(transaction::KeyValue {
+        key,
+        value: postcard::to_stdvec(&value).unwrap(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(&*signer)
+    .unwrap();
+    let idempotency_key =
+        idempotency_key.unwrap_or_else(|| transaction_hash(&transaction).to_string());
+    let deadline = SystemTime::now() + *timeout;
+
+    match client
+        .submit_with_deadline(idempotency_key, transaction, deadline)
+        .await
+    {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(receipt) => {
+            log::info!("Transaction {} was accepted.", receipt.transaction_hash);
+            watch_receipt(&mut client, receipt.transaction_hash).await;
+        }
+    }
+}
+
+async fn main_watch_receipt(mut client: Client, cmd: cmd::WatchReceipt) {
+    let cmd::WatchReceipt { hash } = cmd;
+    watch_receipt(&mut client, hash).await;
+}
+
+/// Log every status of the receipt of the transaction with the given `hash` until it is final.
+async fn watch_receipt(client: &mut Client, hash: TransactionHash) {
+    let mut statuses = match client.watch_receipt(hash).await {
+        Ok(statuses) => statuses,
+        Err(err) => {
+            log::error!("Failed to watch the receipt: {}", err);
+            return;
+        }
+    };
+    while let Some(status) = statuses.next().await {
+        match status {
+            Ok(status) => log::info!("Transaction {}: {:?}", hash, status),
+            Err(err) => {
+                log::error!("Failed to watch the receipt: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+async fn main_execution_receipt(mut client: Client, cmd: cmd::ExecutionReceipt) {
+    let cmd::ExecutionReceipt { hash } = cmd;
+    match client.execution_receipt(hash).await {
+        Err(err) => log::error!("Failed to retrieve the execution receipt: {}", err),
+        Ok(None) => log::info!("Transaction {} is not committed.", hash),
+        Ok(Some(receipt)) => log::info!("Transaction {}: {:#?}", hash, receipt),
+    }
+}
+
+/// A namespace key of a keyring file.
+#[derive(Deserialize)]
+struct KeyringEntry {
+    namespace: String,
+    version: u32,
+    /// The hex encoded key.
+    key: String,
+}
+
+/// Read a keyring file (a YAML list of namespace keys).
+fn read_keyring(path: &Path) -> Keyring {
+    let keyring_file_content = fs::read_to_string(path).expect("Could not read keyring file.");
+    let entries: Vec<KeyringEntry> =
+        serde_yaml::from_str(&keyring_file_content).expect("Invalid keyring file content.");
+    let mut keyring = Keyring::default();
+    for entry in entries {
+        let mut key = [0; KEY_LEN];
+        hex::decode_to_slice(&entry.key, &mut key).expect("Invalid key in keyring file.");
+        let key_reference = KeyReference {
+            namespace: entry.namespace,
+            version: entry.version,
+        };
+        keyring.insert(key_reference, key);
+    }
+    keyring
+}
+
+async fn main_benchmark(signer: Arc<dyn Signer>, turi_address: SocketAddr, cmd: cmd::Benchmark) {
     let cmd::Benchmark {
         key,
         transactions,
@@ -71,10 +297,9 @@ async fn main_benchmark(identity: String, turi_address: SocketAddr, cmd: cmd::Be
     let mut worker_handles = Vec::new();
     for _ in 0..workers {
         let key = key.clone();
-        let identity = identity.clone();
+        let signer = signer.clone();
         worker_handles.push(tokio::spawn(async move {
-            let mut client = create_client(turi_address, &identity);
-            drop(identity);
+            let mut client = Client::with_signer(turi_address, signer);
             let mut rng = StdRng::from_rng(OsRng {}).unwrap();
             let start = Instant::now();
             let half_size = (size + 1) / 2;
@@ -168,6 +393,246 @@ async fn main_delete_account(mut client: Client, cmd: cmd::DeleteAccount) {
     }
 }
 
+async fn main_set_schema(mut client: Client, cmd: cmd::SetSchema) {
+    let cmd::SetSchema {
+        namespace,
+        schema_file,
+    } = cmd;
+
+    // Read the `Schema` from the given file.
+    let schema: Option<Schema> = schema_file.map(|schema_file| {
+        let schema_file_content =
+            fs::read_to_string(schema_file).expect("Could not read schema file.");
+        serde_yaml::from_str(&schema_file_content).expect("Invalid schema file content.")
+    });
+
+    match client.set_schema(namespace, schema).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_schedule_migration(mut client: Client, cmd: cmd::ScheduleMigration) {
+    let cmd::ScheduleMigration {
+        version,
+        activation_height,
+    } = cmd;
+
+    let activation_height = BlockNumber::new(activation_height);
+    match client.schedule_migration(version, activation_height).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_set_parameter(mut client: Client, cmd: cmd::SetParameter) {
+    let cmd::SetParameter {
+        parameter,
+        value,
+        activation_height,
+    } = cmd;
+
+    let activation_height = BlockNumber::new(activation_height);
+    match client
+        .set_parameter(parameter, value, activation_height)
+        .await
+    {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_set_leader_weight(mut client: Client, cmd: cmd::SetLeaderWeight) {
+    let cmd::SetLeaderWeight { peer_id, weight } = cmd;
+    match client.set_leader_weight(peer_id, weight).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_set_queue_weight(mut client: Client, cmd: cmd::SetQueueWeight) {
+    let cmd::SetQueueWeight { peer_id, weight } = cmd;
+    match client.set_queue_weight(peer_id, weight).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_set_quota(mut client: Client, cmd: cmd::SetQuota) {
+    let cmd::SetQuota {
+        namespace,
+        max_transactions,
+        max_bytes,
+        window_ms,
+    } = cmd;
+
+    // Without limits, the quota is removed.
+    let quota = if max_transactions.is_none() && max_bytes.is_none() {
+        None
+    } else {
+        Some(Quota {
+            max_transactions: max_transactions.unwrap_or(u64::max_value()),
+            max_bytes: max_bytes.unwrap_or(u64::max_value()),
+            window_ms,
+        })
+    };
+    match client.set_quota(namespace, quota).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_escrow_key(mut client: Client, keyring_file: Option<&Path>, cmd: cmd::EscrowKey) {
+    let cmd::EscrowKey {
+        namespace,
+        version,
+        threshold,
+        output,
+        rpus,
+    } = cmd;
+
+    let keyring = match keyring_file {
+        Some(keyring_file) => read_keyring(keyring_file),
+        None => {
+            log::error!("A keyring is needed to escrow a key.");
+            return;
+        }
+    };
+    let key_reference = KeyReference { namespace, version };
+    let key = match keyring.key(&key_reference) {
+        Some(key) => key,
+        None => {
+            log::error!("The keyring has no key {}.", key_reference);
+            return;
+        }
+    };
+    let shares = match escrow::split(key, threshold, rpus.len()) {
+        Ok(shares) => shares,
+        Err(err) => {
+            log::error!("Failed to split the key: {}", err);
+            return;
+        }
+    };
+
+    // Each RPU has a single share file, so shares of other keys are kept.
+    fs::create_dir_all(&output).expect("Could not create the output directory.");
+    let mut share_hashes = Vec::new();
+    for (peer_id, share) in rpus.into_iter().zip(shares) {
+        let share_file = output.join(format!("{}.yaml", peer_id));
+        let mut rpu_shares: Vec<KeyShare> = match fs::read_to_string(&share_file) {
+            Ok(share_file_content) => {
+                serde_yaml::from_str(&share_file_content).expect("Invalid share file content.")
+            }
+            Err(_) => Vec::new(),
+        };
+        share_hashes.push((peer_id, share.hash()));
+        rpu_shares.push(share);
+        fs::write(&share_file, serde_yaml::to_string(&rpu_shares).unwrap())
+            .expect("Could not write share file.");
+    }
+
+    let key_hash = escrow::key_hash(key);
+    match client
+        .escrow_key(key_reference, key_hash, threshold, share_hashes)
+        .await
+    {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::info!(
+            "Escrowed the key. Install the share files of {:?} on the RPUs.",
+            output
+        ),
+    }
+}
+
+fn main_request_decryption(cmd: cmd::RequestDecryption) {
+    let cmd::RequestDecryption {
+        namespace,
+        version,
+        auditor,
+        reason,
+        output,
+        valid_for,
+    } = cmd;
+
+    let key_reference = KeyReference { namespace, version };
+    let expires_at = SystemTime::now() + *valid_for;
+    let authorization =
+        transaction::AuthorizeDecryption::new(key_reference, auditor, reason, expires_at);
+    fs::write(&output, serde_yaml::to_string(&authorization).unwrap())
+        .expect("Could not write authorization file.");
+    log::info!(
+        "Wrote the authorization to {:?}. It must be approved by a supermajority of the RPUs.",
+        output
+    );
+}
+
+async fn main_authorize_decryption(mut client: Client, cmd: cmd::AuthorizeDecryption) {
+    let authorization_file_content =
+        fs::read_to_string(cmd.authorization).expect("Could not read authorization file.");
+    let authorization = serde_yaml::from_str(&authorization_file_content)
+        .expect("Invalid authorization file content.");
+    match client.authorize_decryption(authorization).await {
+        Err(err) => log::error!("Failed to send transaction: {}", err),
+        Ok(()) => log::debug!("Transaction ok!"),
+    }
+}
+
+async fn main_recover_key(signer: Arc<dyn Signer>, cmd: cmd::RecoverKey) {
+    let cmd::RecoverKey {
+        namespace,
+        version,
+        rpus,
+    } = cmd;
+
+    let key_reference = KeyReference { namespace, version };
+    // A new recipient key for each recovery, so the shares cannot be retrieved with an old request.
+    let recipient_key = RecipientKey::generate();
+    let mut escrow_shares = Vec::new();
+    let mut shares = Vec::new();
+    for cmd::ParseRpu(rpu, turi_address) in rpus {
+        let mut client = Client::with_signer(turi_address, signer.clone());
+        let result = client
+            .escrow_share(rpu.clone(), key_reference.clone(), &recipient_key)
+            .await;
+        match result {
+            Ok(escrow_share) => {
+                match recipient_key.open(&escrow_share.sealed_share, &rpu, &key_reference) {
+                    Ok(share) => {
+                        shares.push(share);
+                        escrow_shares.push(escrow_share);
+                    }
+                    Err(err) => log::warn!("Failed to open the share of {}: {}", rpu, err),
+                }
+            }
+            Err(err) => log::warn!("Failed to retrieve the share of {}: {}", turi_address, err),
+        }
+    }
+
+    let (key_hash, threshold) = match escrow_shares.first() {
+        Some(escrow_share) => (escrow_share.key_hash, escrow_share.threshold),
+        None => {
+            log::error!("No shares of the key {} were retrieved.", key_reference);
+            return;
+        }
+    };
+    if escrow_shares.len() < threshold {
+        log::error!(
+            "Only {} of the {} shares needed to recover the key were retrieved.",
+            escrow_shares.len(),
+            threshold
+        );
+        return;
+    }
+
+    match escrow::combine(&shares) {
+        Err(err) => log::error!("Failed to recover the key: {}", err),
+        Ok(key) if escrow::key_hash(&key) != key_hash => {
+            log::error!("The recovered key does not match the escrowed key (invalid shares).");
+        }
+        Ok(key) => log::info!("Recovered the key {}: {}", key_reference, hex::encode(key)),
+    }
+}
+
 async fn main_get_value(mut client: Client, cmd: cmd::GetValue) {
     let cmd::GetValue {
         peer_id,
@@ -252,6 +717,272 @@ async fn main_get_block(mut client: Client, cmd: cmd::GetBlock) {
     }
 }
 
+async fn main_describe(mut client: Client) {
+    match client.describe().await {
+        Err(err) => log::error!("Failed to retrieve description: {}", err),
+        Ok(description) => {
+            if description.protocol_version != PROTOCOL_VERSION {
+                log::warn!(
+                    "The RPU uses protocol version {}, but this client uses version {}.",
+                    description.protocol_version,
+                    PROTOCOL_VERSION
+                );
+            }
+            log::info!("The RPU's description: {:#?}", description);
+        }
+    }
+}
+
+async fn main_banned_peers(mut client: Client) {
+    match client.banned_peers().await {
+        Err(err) => log::error!("Failed to retrieve banned peers: {}", err),
+        Ok(peer_ids) => log::info!("The banned peers are: {:#?}", peer_ids),
+    }
+}
+
+async fn main_unban(mut client: Client, cmd: cmd::Unban) {
+    let cmd::Unban { peer_id } = cmd;
+    match client.unban_peer(peer_id).await {
+        Err(err) => log::error!("Failed to unban peer: {}", err),
+        Ok(()) => log::debug!("Unban ok!"),
+    }
+}
+
+async fn main_queue(mut client: Client) {
+    match client.queued_transactions().await {
+        Err(err) => log::error!("Failed to retrieve queued transactions: {}", err),
+        Ok(transactions) => log::info!("The queued transactions are: {:#?}", transactions),
+    }
+}
+
+async fn main_stream_blocks(mut client: Client, cmd: cmd::StreamBlocks) {
+    let cmd::StreamBlocks { from, to } = cmd;
+
+    let from = BlockNumber::new(from);
+    let to = to.map(BlockNumber::new);
+    let mut blocks = match client.stream_blocks(from, to).await {
+        Ok(blocks) => blocks,
+        Err(err) => {
+            log::error!("Failed to stream blocks: {}", err);
+            return;
+        }
+    };
+
+    let mut count = 0_usize;
+    while let Some(block) = blocks.next().await {
+        match block {
+            Ok(block) => {
+                log::info!("{:#?}", block);
+                count += 1;
+            }
+            Err(err) => {
+                log::error!("Failed to stream blocks after {} blocks: {}", count, err);
+                return;
+            }
+        }
+    }
+    log::info!("Streamed {} blocks.", count);
+}
+
+async fn main_subscribe_blocks(mut client: Client, cmd: cmd::SubscribeBlocks) {
+    let cmd::SubscribeBlocks { after } = cmd;
+
+    let mut resume_token = after.map(|after| ResumeToken::new(BlockNumber::new(after)));
+    loop {
+        let err = match client.subscribe_blocks(resume_token).await {
+            Ok(mut blocks) => loop {
+                match blocks.next().await {
+                    Some(Ok(block)) => {
+                        log::info!("{:#?}", block);
+                        resume_token = Some(ResumeToken::new(block.block_number()));
+                    }
+                    Some(Err(err)) => break err,
+                    None => return,
+                }
+            },
+            Err(err) => err,
+        };
+        log::warn!(
+            "Block subscription failed, resubscribing after {:?}: {}",
+            resume_token,
+            err
+        );
+        time::delay_for(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn main_block_proof(mut client: Client, cmd: cmd::BlockProof) {
+    let cmd::BlockProof { block_number } = cmd;
+    let block_number = BlockNumber::new(block_number);
+
+    let block = match client.query_block(block_number.into()).await {
+        Ok(mut blocks) if !blocks.is_empty() => blocks.remove(0),
+        Ok(_) => {
+            log::error!("Block #{} does not exist.", block_number);
+            return;
+        }
+        Err(err) => {
+            log::error!("Failed to retrieve block: {}", err);
+            return;
+        }
+    };
+    let proof = match client.block_proof(block_number).await {
+        Ok(proof) => proof,
+        Err(err) => {
+            log::error!("Failed to retrieve block proof: {}", err);
+            return;
+        }
+    };
+
+    match proof.verify(&block) {
+        Err(err) => log::error!("The block proof is invalid: {}", err),
+        Ok(peer_ids) => log::info!(
+            "Block #{} ({:?}) was accepted by {} RPUs: {:#?}",
+            block_number,
+            proof.block_hash,
+            peer_ids.len(),
+            peer_ids
+        ),
+    }
+}
+
+async fn main_certified_head(mut client: Client) {
+    let head = match client.certified_head().await {
+        Ok(head) => head,
+        Err(err) => {
+            log::error!("Failed to retrieve certified head: {}", err);
+            return;
+        }
+    };
+
+    match head.verify() {
+        Err(err) => log::error!("The certified head is invalid: {}", err),
+        Ok(peer_ids) => log::info!(
+            "Block #{} ({:?}) was the head at {:?} according to {} RPUs: {:#?}",
+            head.block_number,
+            head.block_hash,
+            head.certified_at,
+            peer_ids.len(),
+            peer_ids
+        ),
+    }
+}
+
+async fn main_consensus_failures(mut client: Client) {
+    match client.consensus_failures().await {
+        Err(err) => log::error!("Failed to retrieve consensus failures: {}", err),
+        Ok(failures) if failures.is_empty() => log::info!("No consensus failures recorded."),
+        Ok(failures) => log::info!("The latest consensus failures are: {:#?}", failures),
+    }
+}
+
+async fn main_leader_terms(mut client: Client) {
+    match client.leader_terms().await {
+        Err(err) => log::error!("Failed to retrieve the leader terms: {}", err),
+        Ok(leader_terms) if leader_terms.is_empty() => log::info!("No leader terms recorded."),
+        Ok(leader_terms) => log::info!("The latest leader terms are: {:#?}", leader_terms),
+    }
+}
+
+async fn main_scrub_statistics(mut client: Client) {
+    match client.scrub_statistics().await {
+        Err(err) => log::error!("Failed to retrieve scrub statistics: {}", err),
+        Ok(statistics) => log::info!("The scrub statistics are: {:#?}", statistics),
+    }
+}
+
+async fn main_network_metrics(mut client: Client) {
+    match client.network_metrics().await {
+        Err(err) => log::error!("Failed to retrieve network metrics: {}", err),
+        Ok(metrics) => log::info!("The network metrics are: {:#?}", metrics),
+    }
+}
+
+async fn main_request_metrics(mut client: Client) {
+    match client.request_metrics().await {
+        Err(err) => log::error!("Failed to retrieve request metrics: {}", err),
+        Ok(metrics) => log::info!("The request metrics are: {:#?}", metrics),
+    }
+}
+
+async fn main_status(mut client: Client) {
+    match client.status().await {
+        Err(err) => log::error!("Failed to retrieve the status: {}", err),
+        Ok(status) => match status.halt_reason {
+            None => log::info!("The RPU is running at block #{}.", status.block_number),
+            Some(reason) => log::warn!(
+                "The RPU was halted at block #{}: {}",
+                status.block_number,
+                reason
+            ),
+        },
+    }
+}
+
+async fn main_compare_chains(mut client: Client) {
+    let comparison = match client.compare_chains().await {
+        Err(err) => {
+            log::error!("Failed to compare the chains: {}", err);
+            return;
+        }
+        Ok(comparison) => comparison,
+    };
+    log::info!(
+        "The RPU has {} blocks (last block hash: {:?}).",
+        comparison.block_number,
+        comparison.last_block_hash
+    );
+    for (peer_id, peer) in comparison.peers {
+        match peer {
+            PeerChainComparison::Compared {
+                block_number,
+                first_mismatch: None,
+            } => log::info!("{}: {} blocks, consistent.", peer_id, block_number),
+            PeerChainComparison::Compared {
+                block_number,
+                first_mismatch: Some(first_mismatch),
+            } => log::warn!(
+                "{}: {} blocks, diverged at block #{}.",
+                peer_id,
+                block_number,
+                first_mismatch
+            ),
+            PeerChainComparison::Failed(err) => {
+                log::error!("{}: could not compare the chain: {}", peer_id, err);
+            }
+        }
+    }
+}
+
+async fn main_clock_skews(mut client: Client) {
+    match client.clock_skews().await {
+        Err(err) => log::error!("Failed to retrieve clock skews: {}", err),
+        Ok(clock_skews) => log::info!("The estimated clock skews are: {:#?}", clock_skews),
+    }
+}
+
+async fn main_evict(mut client: Client, cmd: cmd::Evict) {
+    let cmd::Evict { hashes } = cmd;
+    match client.evict_transactions(hashes).await {
+        Err(err) => log::error!("Failed to evict transactions: {}", err),
+        Ok(()) => log::debug!("Eviction ok!"),
+    }
+}
+
+async fn main_trace(mut client: Client, cmd: cmd::Trace) {
+    let cmd::Trace { hash } = cmd;
+    match client.transaction_trace(hash).await {
+        Err(err) => log::error!("Failed to retrieve transaction trace: {}", err),
+        Ok(trace) => log::info!(
+            "Transaction {:?} on RPU {}: {:?}\n{:#?}",
+            hash,
+            trace.rpu,
+            trace.status,
+            trace.events
+        ),
+    }
+}
+
 async fn main_current_block_number(mut client: Client) {
     match client.current_block_number().await {
         Err(err) => log::error!("Failed to retrieve current block number: {}", err),
@@ -0,0 +1,238 @@
+//! A builder for queries of the values of accounts.
+
+use crate::Client;
+use balise::Error;
+use pinxit::{PeerId, Signature};
+use prellblock_client_api::{Filter, Query, ReadValues, Span};
+use serde::de::DeserializeOwned;
+use std::time::SystemTime;
+
+/// The number of values per series requested at once.
+const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// A value returned by a `QueryBuilder`.
+#[derive(Debug, Clone)]
+pub struct QueriedValue<T> {
+    /// The account that set the value.
+    pub peer_id: PeerId,
+    /// The key of the value.
+    pub key: String,
+    /// The time the value was stored on the RPU.
+    pub timestamp: SystemTime,
+    /// The timestamp of the transaction given by the client.
+    pub client_timestamp: SystemTime,
+    /// The signature of the transaction.
+    pub signature: Signature,
+    /// The deserialized value.
+    pub value: T,
+}
+
+/// A builder for queries of the values of accounts (see `Client::query`).
+///
+/// Large results are requested in pages transparently.
+///
+/// # Example
+/// ```no_run
+/// # use prellblock_client::Client;
+/// use prellblock_client::query::QueriedValue;
+/// use std::time::{Duration, SystemTime};
+///
+/// # async fn test(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+/// let peer_id = "4242424242424242424242424242424242424242424242424242424242424242".parse()?;
+/// let now = SystemTime::now();
+/// let speeds: Vec<QueriedValue<f64>> = client
+///     .query()
+///     .peer(peer_id)
+///     .key_prefix("line3/")
+///     .last(1000)
+///     .between(now - Duration::from_secs(3600), now)
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryBuilder<'a> {
+    client: &'a mut Client,
+    peer_ids: Vec<PeerId>,
+    filter: Filter<String>,
+    last: Option<usize>,
+    between: Option<(SystemTime, SystemTime)>,
+    page_size: usize,
+}
+
+/// A page of a query.
+struct Page {
+    peer_ids: Vec<PeerId>,
+    filter: Filter<String>,
+    /// The end of the page (`Span::Time` is exclusive).
+    end: Span,
+    /// The number of values per series still to read.
+    remaining: Option<usize>,
+}
+
+impl<'a> QueryBuilder<'a> {
+    pub(crate) fn new(client: &'a mut Client) -> Self {
+        Self {
+            client,
+            peer_ids: Vec::new(),
+            filter: (..).into(),
+            last: None,
+            between: None,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Read the values of the account `peer_id` (can be called multiple times).
+    #[must_use]
+    pub fn peer(mut self, peer_id: PeerId) -> Self {
+        self.peer_ids.push(peer_id);
+        self
+    }
+
+    /// Read the values of all accounts in `peer_ids`.
+    #[must_use]
+    pub fn peers(mut self, peer_ids: impl IntoIterator<Item = PeerId>) -> Self {
+        self.peer_ids.extend(peer_ids);
+        self
+    }
+
+    /// Only read the keys selected by `filter` (all keys by default).
+    #[must_use]
+    pub fn filter(mut self, filter: impl Into<Filter<String>>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Only read the key `key`.
+    #[must_use]
+    pub fn key(self, key: impl Into<String>) -> Self {
+        self.filter(Filter::Exact(key.into()))
+    }
+
+    /// Only read the keys starting with `prefix`.
+    #[must_use]
+    pub fn key_prefix(self, prefix: impl Into<String>) -> Self {
+        self.filter(Filter::key_prefix(prefix))
+    }
+
+    /// Only read the latest `count` values of each key.
+    #[must_use]
+    pub const fn last(mut self, count: usize) -> Self {
+        self.last = Some(count);
+        self
+    }
+
+    /// Only read the values stored from `start` (inclusive) to `end` (exclusive).
+    #[must_use]
+    pub const fn between(mut self, start: SystemTime, end: SystemTime) -> Self {
+        self.between = Some((start, end));
+        self
+    }
+
+    /// Request at most `page_size` values of each key at once.
+    #[must_use]
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Run the query and deserialize the values (ordered by account, key and time).
+    pub async fn run<T>(self) -> Result<Vec<QueriedValue<T>>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let mut values = Vec::new();
+        for (peer_id, values_of_peer) in self.run_raw().await? {
+            for (key, values_of_series) in values_of_peer {
+                for (timestamp, (value, client_timestamp, signature)) in values_of_series {
+                    values.push(QueriedValue {
+                        peer_id: peer_id.clone(),
+                        key: key.clone(),
+                        timestamp,
+                        client_timestamp,
+                        signature,
+                        value: postcard::from_bytes(&value)?,
+                    });
+                }
+            }
+        }
+        values.sort_by(|a, b| {
+            (a.peer_id.as_bytes(), &a.key, a.timestamp).cmp(&(
+                b.peer_id.as_bytes(),
+                &b.key,
+                b.timestamp,
+            ))
+        });
+        Ok(values)
+    }
+
+    /// Run the query and return the encoded values.
+    pub async fn run_raw(self) -> Result<ReadValues, Error> {
+        let Self {
+            client,
+            peer_ids,
+            filter,
+            last,
+            between,
+            page_size,
+        } = self;
+        let start = between.map(|(start, _)| start);
+
+        let mut values = ReadValues::new();
+        let mut pages = vec![Page {
+            peer_ids,
+            filter,
+            end: between.map_or(Span::Count(0), |(_, end)| Span::Time(end)),
+            remaining: last,
+        }];
+        while let Some(page) = pages.pop() {
+            let count = page
+                .remaining
+                .map_or(page_size, |remaining| remaining.min(page_size));
+            if count == 0 {
+                continue;
+            }
+            let query = Query::Range {
+                span: Span::Count(count),
+                end: page.end,
+                skip: None,
+            };
+            let result = client
+                .query_values(page.peer_ids, page.filter, query)
+                .await?;
+
+            for (peer_id, values_of_peer) in result {
+                for (key, values_of_series) in values_of_peer {
+                    let received = values_of_series.len();
+                    let oldest = values_of_series.keys().min().copied();
+                    let series = values
+                        .entry(peer_id.clone())
+                        .or_default()
+                        .entry(key.clone())
+                        .or_default();
+                    series.extend(
+                        values_of_series.into_iter().filter(|(timestamp, _)| {
+                            start.map_or(true, |start| *timestamp >= start)
+                        }),
+                    );
+
+                    // A full page may be followed by older values of the series.
+                    let oldest = match oldest {
+                        Some(oldest) if received == count => oldest,
+                        _ => continue,
+                    };
+                    if start.map_or(false, |start| oldest <= start) {
+                        continue;
+                    }
+                    pages.push(Page {
+                        peer_ids: vec![peer_id.clone()],
+                        filter: Filter::Exact(key),
+                        end: Span::Time(oldest),
+                        remaining: page.remaining.map(|remaining| remaining - received),
+                    });
+                }
+            }
+        }
+        Ok(values)
+    }
+}
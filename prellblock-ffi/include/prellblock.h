@@ -0,0 +1,66 @@
+/*
+ * C bindings for creating identities, signing transactions and submitting them to an RPU.
+ *
+ * Functions returning an `int` return `0` on success and `-1` on failure,
+ * functions returning a pointer return `NULL` on failure.
+ * The message of the last failure of the calling thread can be read with `prellblock_last_error`.
+ */
+
+#ifndef PRELLBLOCK_H
+#define PRELLBLOCK_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An identity (private key) signing transactions. */
+typedef struct PrellblockIdentity PrellblockIdentity;
+
+/* A batch of signed transactions to submit. */
+typedef struct PrellblockBatch PrellblockBatch;
+
+/* Get the message of the last failed call of the current thread (or `NULL`).
+ * The message is valid until the next failing call of the thread. */
+const char *prellblock_last_error(void);
+
+/* Generate a new random identity. */
+PrellblockIdentity *prellblock_identity_generate(void);
+
+/* Load an identity from its hex encoded private key. */
+PrellblockIdentity *prellblock_identity_from_hex(const char *hex);
+
+/* Get the hex encoded private key of an identity (free with `prellblock_string_free`). */
+char *prellblock_identity_to_hex(const PrellblockIdentity *identity);
+
+/* Get the hex encoded peer id (public key) of an identity (free with `prellblock_string_free`). */
+char *prellblock_identity_peer_id(const PrellblockIdentity *identity);
+
+/* Free an identity. The private key is zeroized. */
+void prellblock_identity_free(PrellblockIdentity *identity);
+
+/* Create an empty batch of transactions signed by `identity`. */
+PrellblockBatch *prellblock_batch_new(const PrellblockIdentity *identity);
+
+/* Sign a key-value transaction and add it to the batch. */
+int prellblock_batch_key_value(PrellblockBatch *batch, const char *key, const uint8_t *value,
+                               size_t value_len);
+
+/* Free a batch without submitting it. */
+void prellblock_batch_free(PrellblockBatch *batch);
+
+/* Submit all transactions of a batch to the RPU at `turi_address` (`host:port` of its Turi).
+ * Blocks until the RPU accepted all transactions. The batch is freed (also on failure). */
+int prellblock_submit(const char *turi_address, const PrellblockIdentity *identity,
+                      PrellblockBatch *batch);
+
+/* Free a string returned by the library. */
+void prellblock_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PRELLBLOCK_H */
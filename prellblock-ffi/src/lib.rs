@@ -0,0 +1,293 @@
+#![warn(missing_docs, clippy::all, clippy::pedantic, clippy::nursery)]
+#![allow(clippy::missing_errors_doc, clippy::similar_names)]
+
+//! C bindings for creating identities, signing transactions and submitting them to an RPU.
+//!
+//! The C declarations are in `include/prellblock.h`.
+//! Functions returning an `int` return `0` on success and `-1` on failure,
+//! functions returning a pointer return `NULL` on failure.
+//! The message of the last failure of the calling thread can be read with `prellblock_last_error`.
+
+use err_derive::Error;
+use hexutil::ToHex;
+use pinxit::Identity;
+use prellblock_client::{BuilderError, Client, TransactionBuilder};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    io,
+    net::SocketAddr,
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+    sync::Arc,
+};
+use tokio::runtime;
+
+/// An error of a call of the C API.
+#[derive(Debug, Error)]
+enum Error {
+    /// A pointer argument is `NULL`.
+    #[error(display = "The argument {} must not be NULL.", 0)]
+    NullPointer(&'static str),
+
+    /// A string argument is not valid UTF-8.
+    #[error(display = "The argument {} is not valid UTF-8.", 0)]
+    InvalidUtf8(&'static str),
+
+    /// The private key of an identity could not be parsed.
+    #[error(display = "The identity is not a valid hex encoded private key.")]
+    InvalidIdentity,
+
+    /// The address of the RPU could not be parsed.
+    #[error(display = "The address {:?} is not a valid socket address.", 0)]
+    InvalidAddress(String),
+
+    /// A transaction could not be signed.
+    #[error(display = "{}", 0)]
+    Builder(#[error(from)] BuilderError),
+
+    /// The RPU could not be reached or rejected the transaction.
+    #[error(display = "{}", 0)]
+    Client(#[error(from)] balise::Error),
+
+    /// The runtime for the network connection could not be started.
+    #[error(display = "{}", 0)]
+    Runtime(#[error(from)] io::Error),
+
+    /// The call panicked.
+    #[error(display = "The call panicked.")]
+    Panic,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// An identity (private key) signing transactions.
+pub struct PrellblockIdentity(Arc<Identity>);
+
+/// A batch of signed transactions to submit.
+pub struct PrellblockBatch(TransactionBuilder);
+
+/// Run `f` and store its error (or panic) as the last error of the thread.
+fn catch<T>(f: impl FnOnce() -> Result<T, Error>) -> Option<T> {
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(Err(Error::Panic));
+    result
+        .map_err(|err| {
+            let message = CString::new(err.to_string()).ok();
+            LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+        })
+        .ok()
+}
+
+/// Run `f` and return the status code.
+fn call(f: impl FnOnce() -> Result<(), Error>) -> c_int {
+    catch(f).map_or(-1, |()| 0)
+}
+
+/// Run `f` and return the allocated value (or `NULL`).
+fn call_box<T>(f: impl FnOnce() -> Result<T, Error>) -> *mut T {
+    catch(f).map_or(ptr::null_mut(), |value| Box::into_raw(Box::new(value)))
+}
+
+/// Run `f` and return the allocated string (or `NULL`).
+fn call_string(f: impl FnOnce() -> Result<String, Error>) -> *mut c_char {
+    // Hex strings never contain a NUL byte.
+    catch(f).map_or(ptr::null_mut(), |value| {
+        CString::new(value).unwrap().into_raw()
+    })
+}
+
+/// Get a reference to the value behind a pointer argument.
+unsafe fn arg<'a, T>(value: *const T, name: &'static str) -> Result<&'a T, Error> {
+    value.as_ref().ok_or(Error::NullPointer(name))
+}
+
+/// Get a string argument.
+unsafe fn str_arg<'a>(value: *const c_char, name: &'static str) -> Result<&'a str, Error> {
+    if value.is_null() {
+        return Err(Error::NullPointer(name));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| Error::InvalidUtf8(name))
+}
+
+/// Get the message of the last failed call of the current thread (or `NULL`).
+///
+/// The message is valid until the next failing call of the thread.
+#[no_mangle]
+pub extern "C" fn prellblock_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Generate a new random identity.
+#[no_mangle]
+pub extern "C" fn prellblock_identity_generate() -> *mut PrellblockIdentity {
+    call_box(|| Ok(PrellblockIdentity(Arc::new(Identity::generate()))))
+}
+
+/// Load an identity from its hex encoded private key.
+///
+/// # Safety
+///
+/// `hex` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_identity_from_hex(
+    hex: *const c_char,
+) -> *mut PrellblockIdentity {
+    call_box(|| {
+        let identity = str_arg(hex, "hex")?
+            .trim()
+            .parse()
+            .map_err(|_| Error::InvalidIdentity)?;
+        Ok(PrellblockIdentity(Arc::new(identity)))
+    })
+}
+
+/// Get the hex encoded private key of an `identity` (to store it).
+///
+/// # Safety
+///
+/// `identity` must be a valid identity. The returned string must be freed with `prellblock_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_identity_to_hex(
+    identity: *const PrellblockIdentity,
+) -> *mut c_char {
+    call_string(|| Ok(arg(identity, "identity")?.0.to_hex()))
+}
+
+/// Get the hex encoded `PeerId` (public key) of an `identity`.
+///
+/// # Safety
+///
+/// `identity` must be a valid identity. The returned string must be freed with `prellblock_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_identity_peer_id(
+    identity: *const PrellblockIdentity,
+) -> *mut c_char {
+    call_string(|| Ok(arg(identity, "identity")?.0.id().to_hex()))
+}
+
+/// Free an `identity`. The private key is zeroized.
+///
+/// # Safety
+///
+/// `identity` must be a valid identity or `NULL`. It must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_identity_free(identity: *mut PrellblockIdentity) {
+    if !identity.is_null() {
+        drop(Box::from_raw(identity));
+    }
+}
+
+/// Create an empty batch of transactions signed by `identity`.
+///
+/// # Safety
+///
+/// `identity` must be a valid identity. It can be freed before the batch.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_batch_new(
+    identity: *const PrellblockIdentity,
+) -> *mut PrellblockBatch {
+    call_box(|| {
+        let signer = arg(identity, "identity")?.0.clone();
+        Ok(PrellblockBatch(TransactionBuilder::with_signer(signer)))
+    })
+}
+
+/// Sign a key-value transaction and add it to the `batch`.
+///
+/// The `value` is stored as a byte array.
+///
+/// # Safety
+///
+/// `batch` must be a valid batch, `key` a NUL terminated string
+/// and `value` must point to `value_len` bytes (or be `NULL` if `value_len` is `0`).
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_batch_key_value(
+    batch: *mut PrellblockBatch,
+    key: *const c_char,
+    value: *const u8,
+    value_len: usize,
+) -> c_int {
+    call(|| {
+        let batch = batch.as_mut().ok_or(Error::NullPointer("batch"))?;
+        let key = str_arg(key, "key")?;
+        let value: &[u8] = if value_len == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(arg(value, "value")?, value_len)
+        };
+        batch.0.key_value(key, &value)?;
+        Ok(())
+    })
+}
+
+/// Free a `batch` without submitting it.
+///
+/// # Safety
+///
+/// `batch` must be a valid batch or `NULL`. It must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_batch_free(batch: *mut PrellblockBatch) {
+    if !batch.is_null() {
+        drop(Box::from_raw(batch));
+    }
+}
+
+/// Submit all transactions of a `batch` to the RPU at `turi_address` (`host:port` of its Turi).
+///
+/// The call blocks until all transactions are accepted by the RPU (not until they are committed).
+/// Transactions failing with a retryable error are sent again.
+///
+/// # Safety
+///
+/// `turi_address` must be a NUL terminated string and `identity` a valid identity.
+/// `batch` must be a valid batch. It is freed by this call (also on failure).
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_submit(
+    turi_address: *const c_char,
+    identity: *const PrellblockIdentity,
+    batch: *mut PrellblockBatch,
+) -> c_int {
+    call(|| {
+        if batch.is_null() {
+            return Err(Error::NullPointer("batch"));
+        }
+        let batch = Box::from_raw(batch).0.build();
+        let turi_address = str_arg(turi_address, "turi_address")?;
+        let turi_address: SocketAddr = turi_address
+            .parse()
+            .map_err(|_| Error::InvalidAddress(turi_address.to_string()))?;
+        let signer = arg(identity, "identity")?.0.clone();
+
+        let mut runtime = runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async {
+            let mut client = Client::with_signer(turi_address, signer);
+            client.submit(batch).await
+        })?;
+        Ok(())
+    })
+}
+
+/// Free a string returned by the library.
+///
+/// # Safety
+///
+/// `string` must be a string returned by the library or `NULL`. It must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn prellblock_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
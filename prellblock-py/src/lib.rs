@@ -0,0 +1,373 @@
+#![warn(missing_docs, clippy::all, clippy::pedantic, clippy::nursery)]
+#![allow(clippy::missing_errors_doc, clippy::similar_names)]
+
+//! Python bindings of the Prellblock client.
+//!
+//! The module is built with [`maturin`](https://github.com/PyO3/maturin) (see the README).
+//! Queried values are returned as rows `(peer_id, key, timestamp, value)`,
+//! which can be passed to `pandas.DataFrame` directly.
+
+use hexutil::ToHex;
+use pinxit::{Identity, PeerId};
+use prellblock_client::{query::QueryBuilder, Client as RpuClient};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+    types::PyBytes,
+    PyIterProtocol,
+};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    fmt,
+    net::SocketAddr,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::runtime::{self, Runtime};
+
+create_exception!(prellblock, PrellblockError, PyException);
+
+/// A queried value: `(peer_id, key, timestamp, value)`.
+type Row = (String, String, f64, PyObject);
+
+/// Convert an error of the client into a `PrellblockError`.
+#[allow(clippy::needless_pass_by_value)]
+fn py_err(err: impl fmt::Display) -> PyErr {
+    PrellblockError::new_err(err.to_string())
+}
+
+/// Convert a Python timestamp (seconds since the epoch) into a `SystemTime`.
+fn system_time(seconds: f64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs_f64(seconds.max(0.0))
+}
+
+/// Convert a `SystemTime` into a Python timestamp (seconds since the epoch).
+fn seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+/// Parse a hex encoded `PeerId`.
+fn parse_peer_id(peer_id: &str) -> PyResult<PeerId> {
+    peer_id
+        .parse()
+        .map_err(|_| PyValueError::new_err(format!("Invalid peer id {:?}.", peer_id)))
+}
+
+/// A value to submit, encoded like the same value submitted by the Rust client.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl<'source> FromPyObject<'source> for Value {
+    fn extract(value: &'source PyAny) -> PyResult<Self> {
+        // `bool` needs to be checked first, because it is a subclass of `int`.
+        if let Ok(value) = value.extract() {
+            Ok(Self::Bool(value))
+        } else if let Ok(value) = value.extract() {
+            Ok(Self::Int(value))
+        } else if let Ok(value) = value.extract() {
+            Ok(Self::Float(value))
+        } else if let Ok(value) = value.extract() {
+            Ok(Self::Str(value))
+        } else if let Ok(value) = value.downcast::<PyBytes>() {
+            Ok(Self::Bytes(value.as_bytes().to_vec()))
+        } else {
+            Err(PyValueError::new_err(
+                "The value needs to be a bool, int, float, str or bytes.",
+            ))
+        }
+    }
+}
+
+/// The type queried values are decoded as.
+#[derive(Debug, Clone, Copy)]
+enum ValueType {
+    Bool,
+    Int,
+    Float,
+    Str,
+    Bytes,
+    /// The encoded value.
+    Raw,
+}
+
+impl ValueType {
+    fn parse(name: &str) -> PyResult<Self> {
+        Ok(match name {
+            "bool" => Self::Bool,
+            "int" => Self::Int,
+            "float" => Self::Float,
+            "str" => Self::Str,
+            "bytes" => Self::Bytes,
+            "raw" => Self::Raw,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown value type {:?} (expected bool, int, float, str, bytes or raw).",
+                    name
+                )))
+            }
+        })
+    }
+
+    fn decode(self, py: Python, value: &[u8]) -> PyResult<PyObject> {
+        Ok(match self {
+            Self::Bool => postcard::from_bytes::<bool>(value)
+                .map_err(py_err)?
+                .into_py(py),
+            Self::Int => postcard::from_bytes::<i64>(value)
+                .map_err(py_err)?
+                .into_py(py),
+            Self::Float => postcard::from_bytes::<f64>(value)
+                .map_err(py_err)?
+                .into_py(py),
+            Self::Str => postcard::from_bytes::<String>(value)
+                .map_err(py_err)?
+                .into_py(py),
+            Self::Bytes => {
+                let value: Vec<u8> = postcard::from_bytes(value).map_err(py_err)?;
+                PyBytes::new(py, &value).into()
+            }
+            Self::Raw => PyBytes::new(py, value).into(),
+        })
+    }
+}
+
+/// A client sending transactions to and reading values from an RPU.
+///
+/// `Client(turi_address, identity)` connects to the Turi at `turi_address` (`ip:port`)
+/// and signs with the hex encoded private key `identity`.
+#[pyclass(unsendable)]
+struct Client {
+    runtime: Runtime,
+    client: RpuClient,
+}
+
+impl Client {
+    /// Run a query built by `build` and decode the values (ordered by account, key and time).
+    fn run_query<'a>(
+        &'a mut self,
+        py: Python,
+        value_type: ValueType,
+        build: impl FnOnce(QueryBuilder<'a>) -> QueryBuilder<'a>,
+    ) -> PyResult<Vec<Row>> {
+        let Self { runtime, client } = self;
+        let values = runtime
+            .block_on(build(client.query()).run_raw())
+            .map_err(py_err)?;
+
+        let mut rows = Vec::new();
+        for (peer_id, values_of_peer) in values {
+            for (key, values_of_series) in values_of_peer {
+                for (timestamp, (value, _, _)) in values_of_series {
+                    rows.push((peer_id.clone(), key.clone(), timestamp, value));
+                }
+            }
+        }
+        rows.sort_by(|a, b| (a.0.as_bytes(), &a.1, a.2).cmp(&(b.0.as_bytes(), &b.1, b.2)));
+        rows.into_iter()
+            .map(|(peer_id, key, timestamp, value)| {
+                Ok((
+                    peer_id.to_hex(),
+                    key,
+                    seconds(timestamp),
+                    value_type.decode(py, &value)?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Client {
+    #[new]
+    fn new(turi_address: &str, identity: &str) -> PyResult<Self> {
+        let turi_address: SocketAddr = turi_address
+            .parse()
+            .map_err(|_| PyValueError::new_err(format!("Invalid address {:?}.", turi_address)))?;
+        let identity: Identity = identity
+            .trim()
+            .parse()
+            .map_err(|_| PyValueError::new_err("Invalid identity."))?;
+        let runtime = runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .map_err(py_err)?;
+        Ok(Self {
+            runtime,
+            client: RpuClient::new(turi_address, identity),
+        })
+    }
+
+    /// Submit a key-value transaction. The value can be a bool, int, float, str or bytes.
+    #[text_signature = "($self, key, value)"]
+    fn submit(&mut self, key: String, value: Value) -> PyResult<()> {
+        let Self { runtime, client } = self;
+        runtime
+            .block_on(client.send_key_value(key, value))
+            .map_err(py_err)
+    }
+
+    /// Query the values of `key` (or of all keys starting with `key_prefix`) of the account `peer_id`.
+    ///
+    /// Only the `last` values of each key and values stored between `start` (inclusive) and
+    /// `end` (exclusive, in seconds since the epoch) are returned. Large results are read in pages.
+    /// The values are decoded as `value_type` (`bool`, `int`, `float`, `str`, `bytes` or `raw`).
+    #[args(
+        key = "None",
+        key_prefix = "None",
+        last = "None",
+        start = "None",
+        end = "None",
+        value_type = "\"float\""
+    )]
+    #[text_signature = "($self, peer_id, key=None, key_prefix=None, last=None, start=None, end=None, value_type=\"float\")"]
+    #[allow(clippy::too_many_arguments)]
+    fn query(
+        &mut self,
+        py: Python,
+        peer_id: &str,
+        key: Option<String>,
+        key_prefix: Option<String>,
+        last: Option<usize>,
+        start: Option<f64>,
+        end: Option<f64>,
+        value_type: &str,
+    ) -> PyResult<Vec<Row>> {
+        let peer_id = parse_peer_id(peer_id)?;
+        let value_type = ValueType::parse(value_type)?;
+        self.run_query(py, value_type, |mut query| {
+            query = query.peer(peer_id);
+            if let Some(key) = key {
+                query = query.key(key);
+            }
+            if let Some(key_prefix) = key_prefix {
+                query = query.key_prefix(key_prefix);
+            }
+            if let Some(last) = last {
+                query = query.last(last);
+            }
+            if start.is_some() || end.is_some() {
+                let start = start.map_or(UNIX_EPOCH, system_time);
+                let end = end.map_or_else(SystemTime::now, system_time);
+                query = query.between(start, end);
+            }
+            query
+        })
+    }
+
+    /// Get the number of the next block of the blockchain.
+    #[text_signature = "($self)"]
+    fn current_block_number(&mut self) -> PyResult<u64> {
+        let Self { runtime, client } = self;
+        let block_number = runtime
+            .block_on(client.current_block_number())
+            .map_err(py_err)?;
+        Ok(block_number.into())
+    }
+
+    /// Subscribe to the new values of `key` of the account `peer_id`.
+    ///
+    /// Iterating the subscription blocks until the next value is stored.
+    /// The RPU is polled every `poll_interval` seconds.
+    #[args(value_type = "\"float\"", poll_interval = "1.0")]
+    #[text_signature = "($self, peer_id, key, value_type=\"float\", poll_interval=1.0)"]
+    fn subscribe(
+        slf: PyRef<Self>,
+        peer_id: &str,
+        key: String,
+        value_type: &str,
+        poll_interval: f64,
+    ) -> PyResult<Subscription> {
+        Ok(Subscription {
+            client: slf.into(),
+            peer_id: parse_peer_id(peer_id)?,
+            key,
+            value_type: ValueType::parse(value_type)?,
+            poll_interval: Duration::from_secs_f64(poll_interval.max(0.0)),
+            after: SystemTime::now(),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+/// An iterator over the new values of a key (see `Client.subscribe`).
+#[pyclass(unsendable)]
+struct Subscription {
+    client: Py<Client>,
+    peer_id: PeerId,
+    key: String,
+    value_type: ValueType,
+    poll_interval: Duration,
+    /// Values stored before this time were already returned.
+    after: SystemTime,
+    pending: VecDeque<Row>,
+}
+
+impl Subscription {
+    /// Read the values stored since the last poll.
+    fn poll(&mut self, py: Python) -> PyResult<()> {
+        let now = SystemTime::now();
+        if now <= self.after {
+            return Ok(());
+        }
+        let (peer_id, key, after) = (self.peer_id.clone(), self.key.clone(), self.after);
+        let mut client = self.client.borrow_mut(py);
+        let rows = client.run_query(py, self.value_type, |query| {
+            query.peer(peer_id).key(key).between(after, now)
+        })?;
+        self.after = now;
+        self.pending.extend(rows);
+        Ok(())
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for Subscription {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<Row>> {
+        let py = slf.py();
+        loop {
+            if let Some(row) = slf.pending.pop_front() {
+                return Ok(Some(row));
+            }
+            slf.poll(py)?;
+            if slf.pending.is_empty() {
+                let poll_interval = slf.poll_interval;
+                py.allow_threads(|| thread::sleep(poll_interval));
+                // Allow interrupting the subscription with Ctrl-C.
+                py.check_signals()?;
+            }
+        }
+    }
+}
+
+/// Generate a new identity and return its hex encoded private key and peer id.
+#[pyfunction]
+#[text_signature = "()"]
+fn generate_identity() -> (String, String) {
+    let identity = Identity::generate();
+    (identity.to_hex(), identity.id().to_hex())
+}
+
+/// Python bindings of the Prellblock client.
+#[pymodule]
+fn prellblock(py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Client>()?;
+    module.add_class::<Subscription>()?;
+    module.add_function(wrap_pyfunction!(generate_identity, module)?)?;
+    module.add("PrellblockError", py.get_type::<PrellblockError>())?;
+    Ok(())
+}
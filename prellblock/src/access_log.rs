@@ -0,0 +1,203 @@
+//! A structured log of the requests handled by the `Turi`.
+//!
+//! Each request is logged with the client identity, the request type, its size, the outcome and the latency,
+//! so security audits can tell who wrote what when.
+//! The identity is the claimed signer of the request (or the account of its session),
+//! together with whether its signature was verified (failed requests may carry invalid signatures).
+//!
+//! The log is written as one line per request (plain text or JSON) and rotated by size.
+
+use pinxit::PeerId;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The configuration of the access log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessLogConfig {
+    /// The path of the log file.
+    pub path: String,
+    /// The size (in bytes) at which the log file is rotated.
+    #[serde(default = "default_max_size_bytes")]
+    pub max_size_bytes: u64,
+    /// The number of rotated log files that are kept (`<path>.1` is the newest).
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Whether the entries are written as JSON (instead of plain text).
+    #[serde(default)]
+    pub json: bool,
+}
+
+const fn default_max_size_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+const fn default_max_files() -> usize {
+    10
+}
+
+/// A request handled by the `Turi`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    /// When the request was received (in ms since the epoch).
+    pub timestamp_ms: u64,
+    /// The address of the client.
+    pub remote_addr: SocketAddr,
+    /// The signer the request claims (`None` for unsigned requests and invalid session tokens).
+    pub claimed_signer: Option<PeerId>,
+    /// Whether the signature of the `claimed_signer` (or the session token) was verified.
+    pub verified: bool,
+    /// The type of the request.
+    pub request: &'static str,
+    /// The size of the encoded request (in bytes).
+    pub request_size: usize,
+    /// The error the request failed with (`None` if it succeeded).
+    pub error: Option<String>,
+    /// The time until the response was ready (in µs).
+    pub latency_us: u64,
+}
+
+impl AccessLogEntry {
+    /// Create an entry of an unsigned request received at `received`, whose response took `latency`.
+    #[must_use]
+    pub fn new(
+        received: SystemTime,
+        remote_addr: SocketAddr,
+        request: &'static str,
+        request_size: usize,
+        error: Option<String>,
+        latency: Duration,
+    ) -> Self {
+        Self {
+            timestamp_ms: millis(received.duration_since(UNIX_EPOCH).unwrap_or_default()),
+            remote_addr,
+            claimed_signer: None,
+            verified: false,
+            request,
+            request_size,
+            error,
+            latency_us: micros(latency),
+        }
+    }
+
+    /// Set the signer the request claims and whether its signature was `verified`.
+    #[must_use]
+    pub fn with_claimed_signer(mut self, claimed_signer: PeerId, verified: bool) -> Self {
+        self.claimed_signer = Some(claimed_signer);
+        self.verified = verified;
+        self
+    }
+}
+
+impl fmt::Display for AccessLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "timestamp_ms={} remote_addr={} claimed_signer={} verified={} request={} request_size={} latency_us={} ",
+            self.timestamp_ms,
+            self.remote_addr,
+            self.claimed_signer
+                .as_ref()
+                .map_or_else(|| "-".to_string(), ToString::to_string),
+            self.verified,
+            self.request,
+            self.request_size,
+            self.latency_us,
+        )?;
+        match &self.error {
+            Some(error) => write!(f, "outcome=error error={:?}", error),
+            None => write!(f, "outcome=ok"),
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn micros(duration: Duration) -> u64 {
+    duration.as_micros() as u64
+}
+
+/// Writes the `AccessLogEntry`s to a file.
+///
+/// The file is rotated when it exceeds the maximum size:
+/// `<path>.1` becomes `<path>.2` (and so on) and the full file becomes `<path>.1`.
+#[derive(Debug)]
+pub struct AccessLog {
+    config: AccessLogConfig,
+    file: Mutex<(File, u64)>,
+}
+
+impl AccessLog {
+    /// Open the access log (appending to an existing file).
+    pub fn open(config: AccessLogConfig) -> io::Result<Self> {
+        let file = Self::open_file(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            config,
+            file: Mutex::new((file, size)),
+        })
+    }
+
+    fn open_file(path: &str) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// Write an `entry` to the log.
+    ///
+    /// Failures are logged, so a broken access log does not fail the requests.
+    pub fn record(&self, entry: &AccessLogEntry) {
+        if let Err(err) = self.write(entry) {
+            log::error!(
+                "Failed to write to the access log {}: {}",
+                self.config.path,
+                err
+            );
+        }
+    }
+
+    fn write(&self, entry: &AccessLogEntry) -> io::Result<()> {
+        let mut line = if self.config.json {
+            serde_json::to_string(entry)?
+        } else {
+            entry.to_string()
+        };
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        let (file, size) = &mut *file;
+        if *size > 0 && *size + line.len() as u64 > self.config.max_size_bytes {
+            self.rotate()?;
+            *file = Self::open_file(&self.config.path)?;
+            *size = 0;
+        }
+        file.write_all(line.as_bytes())?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift the rotated files by one and rotate the current file (the oldest file is deleted).
+    fn rotate(&self) -> io::Result<()> {
+        let rotated = |index: usize| PathBuf::from(format!("{}.{}", self.config.path, index));
+        if self.config.max_files == 0 {
+            return fs::remove_file(&self.config.path);
+        }
+        for index in (1..self.config.max_files).rev() {
+            let path = rotated(index);
+            if path.exists() {
+                fs::rename(path, rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.config.path, rotated(1))
+    }
+}
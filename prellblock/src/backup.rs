@@ -0,0 +1,412 @@
+//! Disaster recovery bundles of an RPU.
+//!
+//! A bundle contains all databases of all chains of an RPU (the `BlockStorage` with the
+//! recorded consensus state, the persisted `WorldState`, the `DataStorage` and the `BanList`),
+//! the shares of escrowed keys and the configuration file.
+//! Each database is protected by an integrity hash, the whole bundle by another one.
+//! Private keys (identities and TLS identities) are not included and need to be backed up separately.
+//!
+//! The databases can only be read and written while the RPU is stopped.
+
+use crate::{
+    block_storage::BlockStorage,
+    storage::StorageConfig,
+    world_state::{PersistenceConfig, WorldStateService},
+    BoxError, ChainConfig, RpuPrivateConfig,
+};
+use prellblock_client_api::consensus::{BlockHash, BlockNumber, ChainId};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, iter, path::Path, time::SystemTime};
+
+/// The format version of the bundles written by this RPU.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Identifies files containing a bundle.
+const MAGIC: [u8; 8] = *b"PRELLBAK";
+
+/// The archive as written to disk.
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    magic: [u8; 8],
+    version: u32,
+    /// The hash of the encoded `Bundle`.
+    hash: BlockHash,
+    bundle: Vec<u8>,
+}
+
+/// The contents of an archive.
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    created_at: SystemTime,
+    /// The configuration file of the RPU.
+    config: String,
+    chains: Vec<ChainBundle>,
+}
+
+/// The state of a single chain.
+#[derive(Serialize, Deserialize)]
+struct ChainBundle {
+    name: String,
+    chain_id: ChainId,
+    block_number: BlockNumber,
+    databases: Vec<Database>,
+    escrow_shares: Option<Vec<u8>>,
+}
+
+/// The kind of a database of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum DatabaseKind {
+    BlockStorage,
+    WorldState,
+    DataStorage,
+    BanList,
+}
+
+impl DatabaseKind {
+    /// The configuration the RPU opens the database of this kind with.
+    fn sled_config(self, path: &str, storage_config: &StorageConfig) -> sled::Config {
+        match self {
+            Self::BlockStorage | Self::WorldState | Self::DataStorage => {
+                storage_config.sled_config(path)
+            }
+            Self::BanList => sled::Config::default().path(path),
+        }
+    }
+}
+
+/// A database with all its trees.
+#[derive(Serialize, Deserialize)]
+struct Database {
+    kind: DatabaseKind,
+    /// The hash of the encoded `trees`.
+    hash: BlockHash,
+    trees: Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>,
+}
+
+/// A summary of a chain in a bundle.
+#[derive(Debug, Clone)]
+pub struct ChainSummary {
+    /// The name of the chain.
+    pub name: String,
+    /// The id of the chain.
+    pub chain_id: ChainId,
+    /// The number of blocks in the `BlockStorage`.
+    pub block_number: BlockNumber,
+}
+
+impl fmt::Display for ChainSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "chain {} ({}) with {} blocks",
+            self.name, self.chain_id, self.block_number
+        )
+    }
+}
+
+/// All chains of the RPU (the main chain first).
+fn chains(config: &RpuPrivateConfig) -> Vec<ChainConfig> {
+    let main_chain = ChainConfig {
+        name: "main".to_string(),
+        identity: config.identity.clone(),
+        genesis_transactions: None,
+        block_path: config.block_path.clone(),
+        data_path: config.data_path.clone(),
+        ban_list_path: config.ban_list_path.clone(),
+        escrow_shares: config.escrow_shares.clone(),
+        world_state: config.world_state.clone(),
+        queue_spill: config.queue_spill.clone(),
+        genesis: config.genesis.clone(),
+    };
+    iter::once(main_chain)
+        .chain(config.chains.iter().cloned())
+        .collect()
+}
+
+/// The paths of the databases of a chain.
+fn database_paths(chain: &ChainConfig) -> Vec<(DatabaseKind, String)> {
+    let mut paths = vec![
+        (DatabaseKind::BlockStorage, chain.block_path.clone()),
+        (DatabaseKind::DataStorage, chain.data_path.clone()),
+        (
+            DatabaseKind::BanList,
+            chain
+                .ban_list_path
+                .clone()
+                .unwrap_or_else(|| format!("{}_ban_list", chain.data_path)),
+        ),
+    ];
+    if let Some(world_state) = &chain.world_state {
+        paths.push((DatabaseKind::WorldState, world_state.path.clone()));
+    }
+    paths
+}
+
+/// Write a bundle of the RPU configured in the file at `config_path` to `output`.
+///
+/// The RPU needs to be stopped.
+pub fn backup(config_path: &str, output: &str) -> Result<Vec<ChainSummary>, BoxError> {
+    if Path::new(output).exists() {
+        return Err(format!("{} already exists.", output).into());
+    }
+    let config_data = fs::read_to_string(config_path)?;
+    let config: RpuPrivateConfig = toml::from_str(&config_data)?;
+
+    let mut chains = Vec::new();
+    for chain in self::chains(&config) {
+        // Only existing chains can be backed up.
+        if !Path::new(&chain.block_path).exists() {
+            return Err(format!("The BlockStorage of chain {} does not exist.", chain.name).into());
+        }
+        let block_storage = BlockStorage::with_config(&chain.block_path, None, &config.storage)?;
+        let chain_id = block_storage.chain_id()?;
+        let block_number = block_storage
+            .read(..)
+            .next_back()
+            .transpose()?
+            .map_or_else(BlockNumber::default, |block| block.block_number() + 1);
+        drop(block_storage);
+
+        let mut databases = Vec::new();
+        for (kind, path) in database_paths(&chain) {
+            if Path::new(&path).exists() {
+                databases.push(export_database(kind, &path, &config.storage)?);
+            }
+        }
+        let escrow_shares = match &chain.escrow_shares {
+            Some(path) => Some(fs::read(path)?),
+            None => None,
+        };
+
+        chains.push(ChainBundle {
+            name: chain.name,
+            chain_id,
+            block_number,
+            databases,
+            escrow_shares,
+        });
+    }
+
+    let bundle = Bundle {
+        created_at: SystemTime::now(),
+        config: config_data,
+        chains,
+    };
+    let summaries = summaries(&bundle);
+    let bundle = postcard::to_stdvec(&bundle)?;
+    let archive = Archive {
+        magic: MAGIC,
+        version: FORMAT_VERSION,
+        hash: BlockHash::of(&bundle),
+        bundle,
+    };
+    fs::write(output, postcard::to_stdvec(&archive)?)?;
+    Ok(summaries)
+}
+
+/// Verify the integrity of the bundle at `archive_path` without restoring it.
+pub fn verify(archive_path: &str) -> Result<Vec<ChainSummary>, BoxError> {
+    let bundle = read_bundle(archive_path)?;
+    Ok(summaries(&bundle))
+}
+
+/// Restore the bundle at `archive_path` to the paths configured in the file at `config_path`.
+///
+/// If the configuration file does not exist, the configuration of the bundle is written to it.
+/// Existing databases are never overwritten.
+/// The restored chains are loaded once to verify that the `WorldState` matches the `BlockStorage`.
+pub fn restore(archive_path: &str, config_path: &str) -> Result<Vec<ChainSummary>, BoxError> {
+    let bundle = read_bundle(archive_path)?;
+
+    let config_data = if Path::new(config_path).exists() {
+        fs::read_to_string(config_path)?
+    } else {
+        log::info!(
+            "Writing the configuration of the bundle to {}.",
+            config_path
+        );
+        fs::write(config_path, &bundle.config)?;
+        bundle.config.clone()
+    };
+    let config: RpuPrivateConfig = toml::from_str(&config_data)?;
+    let chain_configs = chains(&config);
+
+    // Check all chains before writing anything.
+    for chain_bundle in &bundle.chains {
+        let chain = chain_configs
+            .iter()
+            .find(|chain| chain.name == chain_bundle.name)
+            .ok_or_else(|| format!("The chain {} is not configured.", chain_bundle.name))?;
+        for (_, path) in database_paths(chain) {
+            if Path::new(&path).exists() {
+                return Err(format!("{} already exists.", path).into());
+            }
+        }
+        if let Some(path) = &chain.escrow_shares {
+            if chain_bundle.escrow_shares.is_some() && Path::new(path).exists() {
+                return Err(format!("{} already exists.", path).into());
+            }
+        }
+    }
+
+    for chain_bundle in &bundle.chains {
+        let chain = chain_configs
+            .iter()
+            .find(|chain| chain.name == chain_bundle.name)
+            .unwrap();
+        restore_chain(chain, chain_bundle, &config.storage)?;
+    }
+    Ok(summaries(&bundle))
+}
+
+fn restore_chain(
+    chain: &ChainConfig,
+    chain_bundle: &ChainBundle,
+    storage_config: &StorageConfig,
+) -> Result<(), BoxError> {
+    for database in &chain_bundle.databases {
+        let path = database_paths(chain)
+            .into_iter()
+            .find(|(kind, _)| *kind == database.kind)
+            .map(|(_, path)| path);
+        match path {
+            Some(path) => import_database(database, &path, storage_config)?,
+            None => log::warn!(
+                "Skipping the {:?} of chain {}: it is not persisted in the configuration.",
+                database.kind,
+                chain.name
+            ),
+        }
+    }
+    if let (Some(path), Some(escrow_shares)) = (&chain.escrow_shares, &chain_bundle.escrow_shares) {
+        fs::write(path, escrow_shares)?;
+    }
+
+    let block_storage = BlockStorage::with_config(&chain.block_path, None, storage_config)?;
+    if block_storage.chain_id()? != chain_bundle.chain_id {
+        return Err(format!("The restored chain {} has another chain id.", chain.name).into());
+    }
+    if let Some(persistence_config) = chain.world_state.clone() {
+        verify_world_state(
+            &block_storage,
+            persistence_config,
+            storage_config,
+            chain_bundle.block_number,
+        )?;
+    }
+    Ok(())
+}
+
+/// Load the restored `WorldState` and check that it matches the `BlockStorage`.
+fn verify_world_state(
+    block_storage: &BlockStorage,
+    persistence_config: PersistenceConfig,
+    storage_config: &StorageConfig,
+    block_number: BlockNumber,
+) -> Result<(), BoxError> {
+    let world_state = WorldStateService::with_tuned_persistence(
+        block_storage,
+        persistence_config,
+        storage_config,
+    )?;
+    if world_state.get().block_number != block_number {
+        return Err(format!(
+            "The restored WorldState is at block #{}, but expected #{}.",
+            world_state.get().block_number,
+            block_number
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Read a bundle and verify its integrity.
+fn read_bundle(archive_path: &str) -> Result<Bundle, BoxError> {
+    let archive: Archive = postcard::from_bytes(&fs::read(archive_path)?)?;
+    if archive.magic != MAGIC {
+        return Err(format!("{} is not a bundle.", archive_path).into());
+    }
+    if archive.version != FORMAT_VERSION {
+        return Err(format!(
+            "The bundle has format version {}, but only version {} is supported.",
+            archive.version, FORMAT_VERSION
+        )
+        .into());
+    }
+    if BlockHash::of(&archive.bundle) != archive.hash {
+        return Err("The bundle is corrupted (hash mismatch).".into());
+    }
+
+    let bundle: Bundle = postcard::from_bytes(&archive.bundle)?;
+    for chain in &bundle.chains {
+        for database in &chain.databases {
+            if hash_trees(&database.trees)? != database.hash {
+                return Err(format!(
+                    "The {:?} of chain {} is corrupted (hash mismatch).",
+                    database.kind, chain.name
+                )
+                .into());
+            }
+        }
+    }
+    Ok(bundle)
+}
+
+fn summaries(bundle: &Bundle) -> Vec<ChainSummary> {
+    bundle
+        .chains
+        .iter()
+        .map(|chain| ChainSummary {
+            name: chain.name.clone(),
+            chain_id: chain.chain_id,
+            block_number: chain.block_number,
+        })
+        .collect()
+}
+
+/// Read all trees of the database at `path`.
+fn export_database(
+    kind: DatabaseKind,
+    path: &str,
+    storage_config: &StorageConfig,
+) -> Result<Database, BoxError> {
+    let database = kind.sled_config(path, storage_config).open()?;
+    let mut trees = Vec::new();
+    for name in database.tree_names() {
+        let tree = database.open_tree(&name)?;
+        let entries = tree
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((key.to_vec(), value.to_vec()))
+            })
+            .collect::<Result<_, sled::Error>>()?;
+        trees.push((name.to_vec(), entries));
+    }
+    Ok(Database {
+        kind,
+        hash: hash_trees(&trees)?,
+        trees,
+    })
+}
+
+/// Write all trees of `database` to a new database at `path`.
+fn import_database(
+    database: &Database,
+    path: &str,
+    storage_config: &StorageConfig,
+) -> Result<(), BoxError> {
+    let db = database.kind.sled_config(path, storage_config).open()?;
+    for (name, entries) in &database.trees {
+        let tree = db.open_tree(name)?;
+        for (key, value) in entries {
+            tree.insert(key.as_slice(), value.as_slice())?;
+        }
+    }
+    db.flush()?;
+    Ok(())
+}
+
+fn hash_trees(trees: &[(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)]) -> Result<BlockHash, BoxError> {
+    Ok(BlockHash::of(&postcard::to_stdvec(trees)?))
+}
@@ -0,0 +1,126 @@
+//! A persistent list of banned peers.
+
+use crate::BoxError;
+use pinxit::PeerId;
+use sled::{Config, Db};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// The number of strikes after which a peer gets banned.
+pub const MAX_STRIKES: usize = 5;
+
+/// The default time after which a strike expires.
+pub const DEFAULT_STRIKE_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// A `BanList` keeps track of misbehaving peers.
+///
+/// Each time a peer is caught misbehaving (i.e. sending invalid signatures), it gets a strike.
+/// After `MAX_STRIKES` strikes within the strike expiry, the peer is banned.
+/// Banned peers are persisted on disk.
+#[derive(Debug)]
+pub struct BanList {
+    database: Db,
+    banned: RwLock<HashSet<PeerId>>,
+    strikes: Mutex<HashMap<PeerId, VecDeque<Instant>>>,
+    strike_expiry: Duration,
+}
+
+impl BanList {
+    /// Open the `BanList` stored at `path`.
+    pub fn new(path: &str) -> Result<Self, BoxError> {
+        let database = Config::default().path(path).open()?;
+
+        let banned = database
+            .iter()
+            .keys()
+            .map(|key| Ok(postcard::from_bytes(&key?)?))
+            .collect::<Result<_, BoxError>>()?;
+
+        Ok(Self {
+            database,
+            banned: RwLock::new(banned),
+            strikes: Mutex::default(),
+            strike_expiry: DEFAULT_STRIKE_EXPIRY,
+        })
+    }
+
+    /// Forget strikes after `strike_expiry`, so rare errors of an honest peer never add up to a ban.
+    #[must_use]
+    pub const fn with_strike_expiry(mut self, strike_expiry: Duration) -> Self {
+        self.strike_expiry = strike_expiry;
+        self
+    }
+
+    /// Check whether a peer is banned.
+    #[must_use]
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.banned.read().unwrap().contains(peer_id)
+    }
+
+    /// Get all banned peers.
+    #[must_use]
+    pub fn banned_peers(&self) -> Vec<PeerId> {
+        self.banned.read().unwrap().iter().cloned().collect()
+    }
+
+    /// Give a strike to a misbehaving peer.
+    ///
+    /// The peer is banned after `MAX_STRIKES` strikes that did not expire yet.
+    pub fn strike(&self, peer_id: &PeerId, reason: impl fmt::Display) {
+        if self.is_banned(peer_id) {
+            return;
+        }
+
+        let strikes = {
+            let mut strikes = self.strikes.lock().unwrap();
+            let peer_strikes = strikes.entry(peer_id.clone()).or_default();
+            let now = Instant::now();
+            while let Some(strike) = peer_strikes.front() {
+                if now.duration_since(*strike) < self.strike_expiry {
+                    break;
+                }
+                peer_strikes.pop_front();
+            }
+            peer_strikes.push_back(now);
+            peer_strikes.len()
+        };
+        log::warn!(
+            "Peer {} misbehaved ({}/{} strikes): {}",
+            peer_id,
+            strikes,
+            MAX_STRIKES,
+            reason
+        );
+
+        if strikes >= MAX_STRIKES {
+            if let Err(err) = self.ban(peer_id) {
+                log::error!("Failed to persist ban of peer {}: {}", peer_id, err);
+            }
+        }
+    }
+
+    /// Ban a peer.
+    pub fn ban(&self, peer_id: &PeerId) -> Result<(), BoxError> {
+        log::warn!("Banning peer {}.", peer_id);
+        self.banned.write().unwrap().insert(peer_id.clone());
+        self.database.insert(postcard::to_stdvec(peer_id)?, &[])?;
+        self.database.flush()?;
+        Ok(())
+    }
+
+    /// Unban a peer and reset its strikes.
+    ///
+    /// Returns whether the peer was banned.
+    pub fn unban(&self, peer_id: &PeerId) -> Result<bool, BoxError> {
+        log::info!("Unbanning peer {}.", peer_id);
+        self.strikes.lock().unwrap().remove(peer_id);
+        let was_banned = self.banned.write().unwrap().remove(peer_id);
+        self.database.remove(postcard::to_stdvec(peer_id)?)?;
+        self.database.flush()?;
+        Ok(was_banned)
+    }
+}
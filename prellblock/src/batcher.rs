@@ -1,17 +1,21 @@
 //! Module used for batching messages for a `Broadcaster`.
 
-use crate::{data_broadcaster::Broadcaster, peer::message};
+use crate::{
+    consensus::TRACE_LOG_TARGET,
+    data_broadcaster::Broadcaster,
+    peer::{message, BatchId, BatchTransactionStatus, TransactionBatch},
+};
 use pinxit::Signed;
-use prellblock_client_api::Transaction;
-use std::{mem, sync::Arc, time::Duration};
+use prellblock_client_api::{
+    consensus::transaction_hash, limits::MAX_TRANSACTIONS_PER_BATCH, parameter::Parameter,
+    Transaction,
+};
+use std::{mem, sync::Arc};
 use tokio::{
     sync::{mpsc, Mutex},
     time::timeout,
 };
 
-const MAX_TRANSACTIONS_PER_BATCH: usize = 4000;
-const MAX_TIME_BETWEEN_BATCHES: Duration = Duration::from_millis(400);
-
 /// A Batcher for messages.
 pub struct Batcher {
     broadcaster: Arc<Broadcaster>,
@@ -37,11 +41,22 @@ impl Batcher {
         batcher
     }
 
+    /// Get the `Broadcaster` the batches are sent with.
+    #[must_use]
+    pub fn broadcaster(&self) -> &Broadcaster {
+        &self.broadcaster
+    }
+
     /// Add a received message to the batchers bucket.
     pub async fn add_to_batch(self: Arc<Self>, transaction: Signed<Transaction>) {
         let mut bucket = self.bucket.lock().await;
         bucket.push(transaction);
-        if bucket.len() >= MAX_TRANSACTIONS_PER_BATCH {
+        let max_transactions = self
+            .broadcaster
+            .world_state()
+            .get()
+            .parameter_count(Parameter::MaxTransactionsPerBatch);
+        if bucket.len() >= max_transactions {
             log::trace!("Filled bucket.");
             let result = self.notifier.clone().try_send(());
             if let Err(mpsc::error::TrySendError::Closed(_)) = result {
@@ -52,7 +67,12 @@ impl Batcher {
 
     async fn periodically_send_to_broadcaster(self: Arc<Self>, mut receiver: mpsc::Receiver<()>) {
         loop {
-            let timeout_result = timeout(MAX_TIME_BETWEEN_BATCHES, receiver.recv()).await;
+            let max_time_between_batches = self
+                .broadcaster
+                .world_state()
+                .get()
+                .parameter_duration(Parameter::MaxTimeBetweenBatches);
+            let timeout_result = timeout(max_time_between_batches, receiver.recv()).await;
             let mut was_timeout = false;
             if let Ok(None) = timeout_result {
                 // It was nice to know you. Goodbye.
@@ -61,7 +81,15 @@ impl Batcher {
                 was_timeout = true;
             }
 
-            let transactions = mem::take(&mut *self.bucket.lock().await);
+            let transactions = {
+                let mut bucket = self.bucket.lock().await;
+                // Larger batches are rejected by the receivers, the rest is sent right after.
+                let rest = bucket.split_off(bucket.len().min(MAX_TRANSACTIONS_PER_BATCH));
+                if !rest.is_empty() {
+                    let _ = self.notifier.clone().try_send(());
+                }
+                mem::replace(&mut *bucket, rest)
+            };
             if transactions.is_empty() {
                 continue;
             }
@@ -71,11 +99,55 @@ impl Batcher {
                 was_timeout
             );
 
-            let message = message::ExecuteBatch(transactions);
+            for transaction in &transactions {
+                log::debug!(
+                    target: TRACE_LOG_TARGET,
+                    "Transaction {}: Broadcasting in a batch of {} transactions",
+                    transaction_hash(transaction),
+                    transactions.len()
+                );
+            }
+
+            // Retries of the batch keep the id, so the receivers queue it only once.
+            let message = message::ExecuteBatch(
+                self.broadcaster.chain_id(),
+                BatchId::random(),
+                TransactionBatch(transactions),
+            );
             match self.broadcaster.broadcast(&message).await {
-                Ok(_) => log::debug!("Batch sent successfully"),
+                Ok(responses) => {
+                    log::debug!("Batch sent successfully");
+                    log_statuses(&(message.2).0, &responses);
+                }
                 Err(err) => log::error!("Error while sending Batch: {}", err),
             };
         }
     }
 }
+
+/// Log the transactions of a batch that were rejected by other RPUs.
+fn log_statuses(transactions: &[Signed<Transaction>], responses: &[Vec<BatchTransactionStatus>]) {
+    for (index, transaction) in transactions.iter().enumerate() {
+        let mut duplicates = 0;
+        for statuses in responses {
+            match statuses.get(index) {
+                Some(BatchTransactionStatus::Rejected(reason)) => log::warn!(
+                    target: TRACE_LOG_TARGET,
+                    "Transaction {}: Rejected by an RPU: {}",
+                    transaction_hash(transaction),
+                    reason
+                ),
+                Some(BatchTransactionStatus::Duplicate) => duplicates += 1,
+                Some(BatchTransactionStatus::Queued) | None => {}
+            }
+        }
+        if duplicates > 0 {
+            log::debug!(
+                target: TRACE_LOG_TARGET,
+                "Transaction {}: Already known to {} RPUs",
+                transaction_hash(transaction),
+                duplicates
+            );
+        }
+    }
+}
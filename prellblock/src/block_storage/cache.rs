@@ -0,0 +1,119 @@
+//! An in-memory cache of recently committed and read blocks.
+
+use crate::consensus::{Block, BlockNumber};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+/// The hits and misses of the block cache of a `BlockStorage`.
+///
+/// The counters are totals since the `BlockStorage` was opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockCacheStatistics {
+    /// The number of blocks read from the cache.
+    pub hits: u64,
+    /// The number of blocks read from disk.
+    pub misses: u64,
+    /// The number of cached blocks.
+    pub len: usize,
+    /// The maximum number of cached blocks.
+    pub capacity: usize,
+}
+
+/// A least recently used cache of blocks.
+#[derive(Debug)]
+pub(super) struct BlockCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// The cached blocks with the time of their last use.
+    blocks: HashMap<BlockNumber, (u64, Block)>,
+    /// The cached block numbers ordered by the time of their last use.
+    uses: BTreeMap<u64, BlockNumber>,
+    /// The (logical) time of the last use.
+    now: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Inner {
+    /// Mark the block `block_number` as used and return the time of its use.
+    fn touch(&mut self, block_number: BlockNumber, last_use: Option<u64>) -> u64 {
+        if let Some(last_use) = last_use {
+            self.uses.remove(&last_use);
+        }
+        self.now += 1;
+        self.uses.insert(self.now, block_number);
+        self.now
+    }
+}
+
+impl BlockCache {
+    /// Create a cache of up to `capacity` blocks (`0` disables the cache).
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Get a cached block (and count the hit or miss).
+    pub(super) fn get(&self, block_number: BlockNumber) -> Option<Block> {
+        let mut inner = self.inner.lock().unwrap();
+        let last_use = match inner.blocks.get(&block_number) {
+            Some((last_use, _)) => *last_use,
+            None => {
+                inner.misses += 1;
+                return None;
+            }
+        };
+        inner.hits += 1;
+        let now = inner.touch(block_number, Some(last_use));
+        let (last_use, block) = inner.blocks.get_mut(&block_number).unwrap();
+        *last_use = now;
+        Some(block.clone())
+    }
+
+    /// Cache a block, evicting the least recently used block if the cache is full.
+    pub(super) fn insert(&self, block: &Block) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let block_number = block.block_number();
+        let last_use = inner
+            .blocks
+            .get(&block_number)
+            .map(|(last_use, _)| *last_use);
+        let now = inner.touch(block_number, last_use);
+        inner.blocks.insert(block_number, (now, block.clone()));
+        while inner.blocks.len() > self.capacity {
+            let (&oldest, &evicted) = inner.uses.iter().next().unwrap();
+            inner.uses.remove(&oldest);
+            inner.blocks.remove(&evicted);
+        }
+    }
+
+    /// Remove a block from the cache.
+    pub(super) fn remove(&self, block_number: BlockNumber) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((last_use, _)) = inner.blocks.remove(&block_number) {
+            inner.uses.remove(&last_use);
+        }
+    }
+
+    /// Get the hits and misses of the cache.
+    pub(super) fn statistics(&self) -> BlockCacheStatistics {
+        let inner = self.inner.lock().unwrap();
+        BlockCacheStatistics {
+            hits: inner.hits,
+            misses: inner.misses,
+            len: inner.blocks.len(),
+            capacity: self.capacity,
+        }
+    }
+}
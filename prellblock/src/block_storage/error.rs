@@ -1,5 +1,6 @@
 //! Module to check permissions of transactions.
 
+use crate::consensus::{BlockNumber, LeaderTerm};
 use err_derive::Error;
 
 /// An error of the `block_storage` module.
@@ -18,6 +19,38 @@ pub enum Error {
     #[error(display = "Block height does not fit the previous block height.")]
     BlockHeightDoesNotFit,
 
+    /// The same `Block` is already stored at this height.
+    #[error(display = "Block #{} is already stored.", 0)]
+    DuplicateBlock(BlockNumber),
+
+    /// A different `Block` is already stored at this height.
+    #[error(display = "A different block is already stored at height #{}.", 0)]
+    ForkDetected(BlockNumber),
+
+    /// There is no `Block` stored at this height.
+    #[error(display = "There is no block stored at height #{}.", 0)]
+    BlockMissing(BlockNumber),
+
+    /// A proposal is older than the latest proposal in the proposal log.
+    #[error(
+        display = "The proposal of block #{} in leader term {} is older than the latest proposal.",
+        1,
+        0
+    )]
+    ProposalNotMonotonic(LeaderTerm, BlockNumber),
+
+    /// A different block was already proposed in the same leader term at this height.
+    #[error(
+        display = "A different block #{} was already proposed in leader term {}.",
+        1,
+        0
+    )]
+    ConflictingProposal(LeaderTerm, BlockNumber),
+
+    /// There is no genesis block.
+    #[error(display = "The genesis block is missing.")]
+    GenesisBlockMissing,
+
     /// The `Block` could not be encoded correctly.
     #[error(display = "{}", 0)]
     Encoding(#[error(from)] postcard::Error),
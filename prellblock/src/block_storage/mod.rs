@@ -1,39 +1,105 @@
 //! The `BlockStorage` is a permantent storage for validated Blocks persisted on disk.
 
+mod cache;
 mod error;
 
+pub use cache::BlockCacheStatistics;
 pub use error::Error;
 
 use crate::{
-    consensus::{Block, BlockHash, BlockNumber, Body},
+    consensus::{Block, BlockHash, BlockNumber, ChainId, ConsensusMessage},
+    peer::RoundTripTimeSummary,
+    storage::StorageConfig,
     transaction_checker::AccountChecker,
 };
-use pinxit::{PeerId, Signature};
+use balise::Streaming;
+use cache::BlockCache;
+use pinxit::{PeerId, Signature, Signed};
 use prellblock_client_api::{
-    consensus::{GenesisTransactions, LeaderTerm, SignatureList},
-    Filter, Query, ReadValuesOfPeer, ReadValuesOfSeries, Span, Transaction,
+    consensus::{transaction_hash, GenesisTransactions, LeaderTerm, TransactionHash},
+    ConsensusFailure, ExecutionReceipt, Filter, LeaderTermRecord, Query, ReadValuesOfPeer,
+    ReadValuesOfSeries, Span, Transaction,
 };
-use sled::{Config, Db, Tree};
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
 use std::{
     collections::HashMap,
     convert::TryInto,
     fmt::Debug,
+    net::SocketAddr,
     ops::{Bound, RangeBounds},
     str,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
 const BLOCKS_TREE_NAME: &[u8] = b"blocks";
 const ACCOUNTS_TREE_NAME: &[u8] = b"accounts";
+const CONSENSUS_FAILURES_TREE_NAME: &[u8] = b"consensus_failures";
+const ROUND_TRIP_TIMES_TREE_NAME: &[u8] = b"round_trip_times";
+const LEADER_TERMS_TREE_NAME: &[u8] = b"leader_terms";
+const PROPOSALS_TREE_NAME: &[u8] = b"proposals";
+const EXECUTION_RECEIPTS_TREE_NAME: &[u8] = b"execution_receipts";
+const BUFFERED_MESSAGES_TREE_NAME: &[u8] = b"buffered_messages";
+
+/// The key prefix of buffered `ViewChange` messages (replayed before the `Commit` messages).
+const BUFFERED_VIEW_CHANGE_PREFIX: u8 = 0;
+/// The key prefix of buffered `Commit` messages.
+const BUFFERED_COMMIT_PREFIX: u8 = 1;
+
+/// The number of consensus failures kept (older ones are dropped).
+const MAX_CONSENSUS_FAILURES: usize = 256;
+
+/// The number of leader terms kept in the history (older ones are dropped).
+const MAX_LEADER_TERMS: usize = 1024;
+
+/// The number of proposals kept in the proposal log (older ones are dropped).
+const MAX_PROPOSALS: usize = 1024;
+
+/// The number of blocks read at once (and buffered) while streaming blocks.
+const STREAM_CHUNK_SIZE: usize = 16;
+
+/// A block proposed by this RPU as the leader (see `BlockStorage::write_proposal`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposalRecord {
+    /// The leader term the block was proposed in.
+    pub leader_term: LeaderTerm,
+    /// The number of the proposed block.
+    pub block_number: BlockNumber,
+    /// The hash of the proposed block.
+    pub block_hash: BlockHash,
+    /// The hash of the previous block.
+    pub prev_block_hash: BlockHash,
+    /// The timestamp of the proposed block.
+    pub timestamp: SystemTime,
+    /// The hashes of the transactions of the proposed block (in order).
+    pub transaction_hashes: Vec<TransactionHash>,
+}
+
+impl ProposalRecord {
+    /// The key of the record (ordered by leader term and block number).
+    fn key(&self) -> [u8; 16] {
+        proposal_key(self.leader_term, self.block_number)
+    }
+}
 
 /// A `BlockStorage` provides persistent storage on disk.
 ///
 /// Data is written to disk every 400ms.
+/// Recently written and read blocks are kept in a cache shared by all clones.
 #[derive(Debug, Clone)]
 pub struct BlockStorage {
     database: Db,
     blocks: Tree,
     accounts: Tree,
+    consensus_failures: Tree,
+    round_trip_times: Tree,
+    leader_terms: Tree,
+    proposals: Tree,
+    execution_receipts: Tree,
+    /// The buffered consensus messages (only if they are persisted).
+    buffered_messages: Option<Tree>,
+    cache: Arc<BlockCache>,
 }
 
 impl BlockStorage {
@@ -42,38 +108,49 @@ impl BlockStorage {
         path: &str,
         genesis_transactions: Option<GenesisTransactions>,
     ) -> Result<Self, Error> {
-        let config = Config::default()
-            .path(path)
-            .cache_capacity(8_000_000)
-            .flush_every_ms(Some(400))
-            .snapshot_after_ops(100)
-            .use_compression(false) // TODO: set this to `true`.
-            .compression_factor(20);
-
-        let database = config.open()?;
+        Self::with_config(path, genesis_transactions, &StorageConfig::default())
+    }
+
+    /// Create a new `BlockStorage` at path tuned by `config`.
+    pub fn with_config(
+        path: &str,
+        genesis_transactions: Option<GenesisTransactions>,
+        config: &StorageConfig,
+    ) -> Result<Self, Error> {
+        let database = config.sled_config(path).open()?;
         let blocks = database.open_tree(BLOCKS_TREE_NAME)?;
         let accounts = database.open_tree(ACCOUNTS_TREE_NAME)?;
+        let consensus_failures = database.open_tree(CONSENSUS_FAILURES_TREE_NAME)?;
+        let round_trip_times = database.open_tree(ROUND_TRIP_TIMES_TREE_NAME)?;
+        let leader_terms = database.open_tree(LEADER_TERMS_TREE_NAME)?;
+        let proposals = database.open_tree(PROPOSALS_TREE_NAME)?;
+        let execution_receipts = database.open_tree(EXECUTION_RECEIPTS_TREE_NAME)?;
+        // Messages buffered in an earlier run are discarded if they are not persisted anymore.
+        let buffered_messages = if config.persist_buffered_messages {
+            Some(database.open_tree(BUFFERED_MESSAGES_TREE_NAME)?)
+        } else {
+            database.drop_tree(BUFFERED_MESSAGES_TREE_NAME)?;
+            None
+        };
 
         let block_storage = Self {
             database,
             blocks,
             accounts,
+            consensus_failures,
+            round_trip_times,
+            leader_terms,
+            proposals,
+            execution_receipts,
+            buffered_messages,
+            cache: Arc::new(BlockCache::new(config.block_cache_capacity)),
         };
 
         // Apply genesis block if `BlockStorage` is empty.
         if block_storage.blocks.is_empty() {
             let genesis_transactions = genesis_transactions
                 .expect("No genesis transactions were given, but BlockStorage is empty.");
-            let genesis_block = Block {
-                body: Body {
-                    leader_term: LeaderTerm::default(),
-                    height: BlockNumber::default(),
-                    prev_block_hash: BlockHash::default(),
-                    timestamp: genesis_transactions.timestamp,
-                    transactions: genesis_transactions.transactions,
-                },
-                signatures: SignatureList::default(),
-            };
+            let genesis_block = genesis_transactions.block();
             block_storage.write_block(&genesis_block)?;
         }
 
@@ -82,15 +159,30 @@ impl BlockStorage {
 
     /// Write a value to the store.
     ///
-    /// The data will be accessible by the block number?.
+    /// The block must extend the stored head (height + 1 and matching previous hash).
+    /// Writing an already stored block returns `Error::DuplicateBlock`,
+    /// a different block at an already stored height returns `Error::ForkDetected`.
+    /// In both cases the stored data is left untouched.
     pub fn write_block(&self, block: &Block) -> Result<(), Error> {
         let (last_block_hash, block_number) = if let Some(last_block) = self.read(..).next_back() {
             let last_block = last_block?;
             (last_block.hash(), last_block.body.height + 1)
         } else {
-            (BlockHash::default(), BlockNumber::default())
+            // The genesis block chooses the hash function of the chain.
+            let algorithm = block.body.prev_block_hash.algorithm();
+            (BlockHash::zero(algorithm), BlockNumber::default())
         };
 
+        if block.body.height < block_number {
+            let height = block.body.height;
+            return match self.read(height..=height).next().transpose()? {
+                Some(stored_block) if stored_block.hash() == block.hash() => {
+                    Err(Error::DuplicateBlock(height))
+                }
+                _ => Err(Error::ForkDetected(height)),
+            };
+        }
+
         if block.body.prev_block_hash != last_block_hash {
             return Err(Error::BlockHashDoesNotMatch);
         }
@@ -115,13 +207,55 @@ impl BlockStorage {
                         transaction.signature(),
                     )?;
                 }
-                // We don't need to do anything here. Account permissions are saved in the `WorldState`.
+                // The metadata is part of the transaction in the block.
+                Transaction::KeyValueWithMetadata(params) => {
+                    self.write_value(
+                        transaction.signer(),
+                        &params.key,
+                        &params.value,
+                        params.timestamp,
+                        transaction.signature(),
+                    )?;
+                }
+                // Encrypted values are stored encoded, so readers get the key reference.
+                Transaction::EncryptedKeyValue(params) => {
+                    self.write_value(
+                        transaction.signer(),
+                        &params.key,
+                        &params.value.encode()?,
+                        params.timestamp,
+                        transaction.signature(),
+                    )?;
+                }
+                // Each value is stored with the signature of the whole transaction.
+                Transaction::MultiKeyValue(params) => {
+                    for (key, value) in &params.values {
+                        self.write_value(
+                            transaction.signer(),
+                            key,
+                            value,
+                            params.timestamp,
+                            transaction.signature(),
+                        )?;
+                    }
+                }
+                // We don't need to do anything here. Account permissions, schemas, migrations, parameters, leader weights, escrowed keys, queue weights, quotas and shared values are saved in the `WorldState`.
                 Transaction::UpdateAccount(_)
                 | Transaction::CreateAccount(_)
-                | Transaction::DeleteAccount(_) => {}
+                | Transaction::DeleteAccount(_)
+                | Transaction::SetSchema(_)
+                | Transaction::ScheduleMigration(_)
+                | Transaction::SetParameter(_)
+                | Transaction::SetLeaderWeight(_)
+                | Transaction::EscrowKey(_)
+                | Transaction::AuthorizeDecryption(_)
+                | Transaction::SetQueueWeight(_)
+                | Transaction::SetQuota(_)
+                | Transaction::CompareAndSwap(_) => {}
             }
         }
 
+        self.cache.insert(block);
         Ok(())
     }
 
@@ -158,7 +292,58 @@ impl BlockStorage {
         Ok(())
     }
 
+    /// Replace the stored copy of an already stored block (e.g. a corrupted one).
+    ///
+    /// Only the block itself is replaced, the values written by its transactions are left untouched.
+    /// The caller needs to verify that `block` is the block committed at its height.
+    pub fn replace_block(&self, block: &Block) -> Result<(), Error> {
+        let key = block.block_number().to_be_bytes();
+        if !self.blocks.contains_key(key)? {
+            return Err(Error::BlockMissing(block.block_number()));
+        }
+        let value = postcard::to_stdvec(&block)?;
+        self.blocks.insert(key, value)?;
+        self.blocks.flush()?;
+        self.cache.insert(block);
+        log::trace!("Replaced block #{}.", block.block_number());
+        Ok(())
+    }
+
+    /// Get the id of the chain (the hash of the genesis block).
+    pub fn chain_id(&self) -> Result<ChainId, Error> {
+        let genesis_block_number = BlockNumber::default();
+        match self
+            .read(genesis_block_number..=genesis_block_number)
+            .next()
+        {
+            Some(genesis_block) => Ok(genesis_block?.hash()),
+            None => Err(Error::GenesisBlockMissing),
+        }
+    }
+
+    /// Read a single block (from the cache if possible).
+    ///
+    /// Returns `None` if there is no block at `block_number`.
+    pub fn read_block(&self, block_number: BlockNumber) -> Result<Option<Block>, Error> {
+        if let Some(block) = self.cache.get(block_number) {
+            return Ok(Some(block));
+        }
+        let block = self.read(block_number..=block_number).next().transpose()?;
+        if let Some(block) = &block {
+            self.cache.insert(block);
+        }
+        Ok(block)
+    }
+
+    /// Get the hits and misses of the block cache.
+    #[must_use]
+    pub fn cache_statistics(&self) -> BlockCacheStatistics {
+        self.cache.statistics()
+    }
+
     /// Read a range of blocks from the store.
+    ///
+    /// The blocks are always read from disk (e.g. for scrubbing), use `read_block` for hot reads.
     pub fn read<R>(&self, range: R) -> impl DoubleEndedIterator<Item = Result<Block, Error>>
     where
         R: RangeBounds<BlockNumber> + Debug + Clone,
@@ -179,6 +364,287 @@ impl BlockStorage {
             })
     }
 
+    /// Stream the blocks from `from` up to (excluding) `to` in order.
+    ///
+    /// If `to` is `None`, all blocks up to the newest one are sent.
+    /// The blocks are read in chunks while the stream is being sent,
+    /// so the range can be arbitrarily large.
+    #[must_use]
+    pub fn stream(&self, from: BlockNumber, to: Option<BlockNumber>) -> Streaming<Block> {
+        let (mut sender, streaming) = Streaming::channel(STREAM_CHUNK_SIZE);
+        let block_storage = self.clone();
+        tokio::spawn(async move {
+            let end = to.map_or(Bound::Unbounded, Bound::Excluded);
+            let mut next = from;
+            loop {
+                let chunk = block_storage
+                    .read((Bound::Included(next), end))
+                    .take(STREAM_CHUNK_SIZE)
+                    .collect::<Result<Vec<_>, _>>();
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(err) => {
+                        log::warn!("Could not read blocks from #{}: {}", next, err);
+                        sender.abort(err).await;
+                        return;
+                    }
+                };
+
+                let last_block_number = match chunk.last() {
+                    Some(last_block) => last_block.block_number(),
+                    None => return,
+                };
+                for block in &chunk {
+                    if sender.send(block).await.is_err() {
+                        log::trace!("Block stream closed at block #{}.", block.block_number());
+                        return;
+                    }
+                }
+                next = last_block_number + 1;
+            }
+        });
+        streaming
+    }
+
+    /// Record a consensus `failure`.
+    ///
+    /// Only the latest `MAX_CONSENSUS_FAILURES` failures are kept.
+    pub fn write_consensus_failure(&self, failure: &ConsensusFailure) -> Result<(), Error> {
+        let id = self.database.generate_id()?;
+        let value = postcard::to_stdvec(failure)?;
+        self.consensus_failures.insert(id.to_be_bytes(), value)?;
+
+        while self.consensus_failures.len() > MAX_CONSENSUS_FAILURES {
+            self.consensus_failures.pop_min()?;
+        }
+        Ok(())
+    }
+
+    /// Read the recorded consensus failures (oldest first).
+    pub fn read_consensus_failures(&self) -> Result<Vec<ConsensusFailure>, Error> {
+        self.consensus_failures
+            .iter()
+            .values()
+            .map(|value| Ok(postcard::from_bytes(&value?)?))
+            .collect()
+    }
+
+    /// Record the start of a leader term.
+    ///
+    /// The previous leader term ends at the first block of the new one.
+    /// Only the latest `MAX_LEADER_TERMS` leader terms are kept.
+    pub fn write_leader_term(&self, record: &LeaderTermRecord) -> Result<(), Error> {
+        let key = u64::from(record.leader_term).to_be_bytes();
+        if let Some((previous_key, value)) =
+            self.leader_terms.range(..key).next_back().transpose()?
+        {
+            let mut previous: LeaderTermRecord = postcard::from_bytes(&value)?;
+            if previous.end_block.is_none() {
+                previous.end_block = Some(record.start_block);
+                self.leader_terms
+                    .insert(previous_key, postcard::to_stdvec(&previous)?)?;
+            }
+        }
+        self.leader_terms
+            .insert(key, postcard::to_stdvec(record)?)?;
+
+        while self.leader_terms.len() > MAX_LEADER_TERMS {
+            self.leader_terms.pop_min()?;
+        }
+        Ok(())
+    }
+
+    /// Record a block `proposal` of this RPU before it is sent to other RPUs.
+    ///
+    /// The proposal log increases monotonically: a proposal for an earlier leader term or block
+    /// than the latest one and a different proposal for the same leader term and block are rejected,
+    /// so a leader never proposes two different blocks at the same height (e.g. after a crash).
+    /// The record is flushed to disk before returning.
+    /// Only the latest `MAX_PROPOSALS` proposals are kept.
+    pub fn write_proposal(&self, proposal: &ProposalRecord) -> Result<(), Error> {
+        let key = proposal.key();
+        if let Some((last_key, value)) = self.proposals.last()? {
+            if last_key.as_ref() > &key[..] {
+                return Err(Error::ProposalNotMonotonic(
+                    proposal.leader_term,
+                    proposal.block_number,
+                ));
+            }
+            if last_key.as_ref() == &key[..] {
+                let last: ProposalRecord = postcard::from_bytes(&value)?;
+                if last.block_hash != proposal.block_hash {
+                    return Err(Error::ConflictingProposal(
+                        proposal.leader_term,
+                        proposal.block_number,
+                    ));
+                }
+                return Ok(());
+            }
+        }
+
+        self.proposals.insert(key, postcard::to_stdvec(proposal)?)?;
+        while self.proposals.len() > MAX_PROPOSALS {
+            self.proposals.pop_min()?;
+        }
+        self.proposals.flush()?;
+        Ok(())
+    }
+
+    /// Read the proposal of this RPU for the block `block_number` in the `leader_term` (if any).
+    pub fn read_proposal(
+        &self,
+        leader_term: LeaderTerm,
+        block_number: BlockNumber,
+    ) -> Result<Option<ProposalRecord>, Error> {
+        match self
+            .proposals
+            .get(proposal_key(leader_term, block_number))?
+        {
+            Some(value) => Ok(Some(postcard::from_bytes(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record the execution `receipts` of the transactions of a committed block.
+    ///
+    /// The receipts are removed again if the block is rolled back (see `pop_block`).
+    pub fn write_execution_receipts(&self, receipts: &[ExecutionReceipt]) -> Result<(), Error> {
+        for receipt in receipts {
+            self.execution_receipts.insert(
+                receipt.transaction_hash.to_string(),
+                postcard::to_stdvec(receipt)?,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read the execution receipt of the committed transaction `transaction_hash` (if any).
+    pub fn read_execution_receipt(
+        &self,
+        transaction_hash: &TransactionHash,
+    ) -> Result<Option<ExecutionReceipt>, Error> {
+        match self.execution_receipts.get(transaction_hash.to_string())? {
+            Some(value) => Ok(Some(postcard::from_bytes(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the recorded leader terms (oldest first).
+    pub fn read_leader_terms(&self) -> Result<Vec<LeaderTermRecord>, Error> {
+        self.leader_terms
+            .iter()
+            .values()
+            .map(|value| Ok(postcard::from_bytes(&value?)?))
+            .collect()
+    }
+
+    /// Check whether buffered consensus messages are kept across restarts.
+    ///
+    /// See `StorageConfig::persist_buffered_messages`.
+    #[must_use]
+    pub fn persists_buffered_messages(&self) -> bool {
+        self.buffered_messages.is_some()
+    }
+
+    /// Persist a consensus `message` that is buffered for a future phase (if enabled).
+    ///
+    /// `Commit` messages are kept per block number, `ViewChange` messages per leader term and signer.
+    /// Other messages are not buffered and ignored.
+    pub fn write_buffered_message(&self, message: &Signed<ConsensusMessage>) -> Result<(), Error> {
+        let buffered_messages = match &self.buffered_messages {
+            Some(buffered_messages) => buffered_messages,
+            None => return Ok(()),
+        };
+        let key = match message.unverified_ref() {
+            ConsensusMessage::Commit(commit) => {
+                buffered_message_key(BUFFERED_COMMIT_PREFIX, commit.block_number.into()).to_vec()
+            }
+            ConsensusMessage::ViewChange(view_change) => [
+                &buffered_message_key(
+                    BUFFERED_VIEW_CHANGE_PREFIX,
+                    view_change.new_leader_term.into(),
+                )[..],
+                message.signer().as_bytes(),
+            ]
+            .concat(),
+            _ => return Ok(()),
+        };
+        buffered_messages.insert(key, postcard::to_stdvec(message)?)?;
+        Ok(())
+    }
+
+    /// Read the persisted buffered consensus messages.
+    ///
+    /// The `ViewChange` messages come first (by leader term), then the `Commit` messages (by block number).
+    pub fn read_buffered_messages(&self) -> Result<Vec<Signed<ConsensusMessage>>, Error> {
+        match &self.buffered_messages {
+            Some(buffered_messages) => buffered_messages
+                .iter()
+                .values()
+                .map(|value| Ok(postcard::from_bytes(&value?)?))
+                .collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Remove the persisted buffered consensus messages that are obsolete.
+    ///
+    /// These are the `Commit` messages of blocks before `block_number`
+    /// and the `ViewChange` messages up to the `leader_term`.
+    pub fn remove_buffered_messages(
+        &self,
+        block_number: BlockNumber,
+        leader_term: LeaderTerm,
+    ) -> Result<(), Error> {
+        let buffered_messages = match &self.buffered_messages {
+            Some(buffered_messages) => buffered_messages,
+            None => return Ok(()),
+        };
+        let view_changes = buffered_message_key(BUFFERED_VIEW_CHANGE_PREFIX, 0)
+            ..buffered_message_key(
+                BUFFERED_VIEW_CHANGE_PREFIX,
+                u64::from(leader_term).saturating_add(1),
+            );
+        let commits = buffered_message_key(BUFFERED_COMMIT_PREFIX, 0)
+            ..buffered_message_key(BUFFERED_COMMIT_PREFIX, block_number.into());
+        let obsolete = buffered_messages
+            .range(view_changes)
+            .keys()
+            .chain(buffered_messages.range(commits).keys())
+            .collect::<Result<Vec<_>, _>>()?;
+        for key in obsolete {
+            buffered_messages.remove(key)?;
+        }
+        Ok(())
+    }
+
+    /// Store the `summaries` of the round trip times to other RPUs (replacing the stored ones).
+    pub fn write_round_trip_times(
+        &self,
+        summaries: &HashMap<SocketAddr, RoundTripTimeSummary>,
+    ) -> Result<(), Error> {
+        self.round_trip_times.clear()?;
+        for (peer_address, summary) in summaries {
+            let key = postcard::to_stdvec(peer_address)?;
+            let value = postcard::to_stdvec(summary)?;
+            self.round_trip_times.insert(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Read the stored summaries of the round trip times to other RPUs.
+    pub fn read_round_trip_times(
+        &self,
+    ) -> Result<HashMap<SocketAddr, RoundTripTimeSummary>, Error> {
+        self.round_trip_times
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((postcard::from_bytes(&key)?, postcard::from_bytes(&value)?))
+            })
+            .collect()
+    }
+
     /// Read transactions filtered by a `Filter` and a `Query` from `Blockstorage`.
     pub fn read_transactions(
         &self,
@@ -333,19 +799,48 @@ impl BlockStorage {
     pub fn pop_block(&self) -> Result<Option<Block>, Error> {
         if let Some((_, value)) = self.blocks.pop_max()? {
             let block: Block = postcard::from_bytes(&value)?;
+            self.cache.remove(block.block_number());
 
             // update value tree
             for transaction in &block.body.transactions {
+                self.execution_receipts
+                    .remove(transaction_hash(transaction).to_string())?;
                 match transaction.unverified_ref() {
                     Transaction::KeyValue(params) => {
                         let peer_id = transaction.signer();
                         let time_series_name = [peer_id.as_bytes(), params.key.as_bytes()].join(&0);
                         self.database.open_tree(time_series_name)?.pop_max()?;
                     }
-                    // We don't need to do anything here. Account permissions are rolled back in the `WorldState`.
+                    Transaction::KeyValueWithMetadata(params) => {
+                        let peer_id = transaction.signer();
+                        let time_series_name = [peer_id.as_bytes(), params.key.as_bytes()].join(&0);
+                        self.database.open_tree(time_series_name)?.pop_max()?;
+                    }
+                    Transaction::EncryptedKeyValue(params) => {
+                        let peer_id = transaction.signer();
+                        let time_series_name = [peer_id.as_bytes(), params.key.as_bytes()].join(&0);
+                        self.database.open_tree(time_series_name)?.pop_max()?;
+                    }
+                    Transaction::MultiKeyValue(params) => {
+                        let peer_id = transaction.signer();
+                        for (key, _) in &params.values {
+                            let time_series_name = [peer_id.as_bytes(), key.as_bytes()].join(&0);
+                            self.database.open_tree(time_series_name)?.pop_max()?;
+                        }
+                    }
+                    // We don't need to do anything here. Account permissions, schemas, migrations, parameters, leader weights, escrowed keys, queue weights, quotas and shared values are rolled back in the `WorldState`.
                     Transaction::UpdateAccount(_)
                     | Transaction::DeleteAccount(_)
-                    | Transaction::CreateAccount(_) => {}
+                    | Transaction::CreateAccount(_)
+                    | Transaction::SetSchema(_)
+                    | Transaction::ScheduleMigration(_)
+                    | Transaction::SetParameter(_)
+                    | Transaction::SetLeaderWeight(_)
+                    | Transaction::EscrowKey(_)
+                    | Transaction::AuthorizeDecryption(_)
+                    | Transaction::SetQueueWeight(_)
+                    | Transaction::SetQuota(_)
+                    | Transaction::CompareAndSwap(_) => {}
                 }
             }
 
@@ -394,3 +889,18 @@ fn system_time_from_bytes(bytes: &[u8]) -> SystemTime {
         SystemTime::UNIX_EPOCH - duration
     }
 }
+
+/// The key of a proposal: the big-endian leader term followed by the big-endian block number.
+fn proposal_key(leader_term: LeaderTerm, block_number: BlockNumber) -> [u8; 16] {
+    let mut key = [0; 16];
+    key[..8].copy_from_slice(&u64::from(leader_term).to_be_bytes());
+    key[8..].copy_from_slice(&u64::from(block_number).to_be_bytes());
+    key
+}
+
+/// The key of a buffered consensus message: the `prefix` followed by the big-endian `number`.
+fn buffered_message_key(prefix: u8, number: u64) -> [u8; 9] {
+    let mut key = [prefix; 9];
+    key[1..].copy_from_slice(&number.to_be_bytes());
+    key
+}
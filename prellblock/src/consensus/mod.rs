@@ -4,9 +4,13 @@ mod praftbft;
 mod transaction_applier;
 
 pub use praftbft::{
-    ConsensusMessage, ConsensusResponse, Error, PRaftBFT as Consensus, Queue, RingBuffer,
+    block_proof, confirmed_snapshot_manifest, consensus_response, verify_block_signatures,
+    BaliseNetwork, BroadcastResponse, Clock, ConsensusMessage, ConsensusNetwork, ConsensusResponse,
+    Error, InMemoryNetwork, MockClock, PRaftBFT as Consensus, ProposalSchedule, ProposalTiming,
+    Queue, QueueSpill, QueueSpillConfig, RingBuffer, Round, RoundMessage, RoundStep, SystemClock,
+    TRACE_LOG_TARGET,
 };
 pub(crate) use prellblock_client_api::consensus::{
-    Block, BlockHash, BlockNumber, Body, LeaderTerm, SignatureList,
+    Block, BlockHash, BlockNumber, Body, ChainId, LeaderTerm, SignatureList,
 };
 pub use transaction_applier::TransactionApplier;
@@ -0,0 +1,106 @@
+//! Transfer of the transactions of large blocks in chunks.
+//!
+//! If the transactions of a block exceed `MAX_APPEND_SIZE`, the leader sends them
+//! in `AppendChunk` messages before the `Append` message, which only references the chunks by their hashes.
+//! The follower reassembles the transactions from its buffered chunks.
+//! The reassembled block is verified by its block hash as usual.
+
+use super::{message::Metadata, Error, MAX_TRANSACTIONS_PER_BLOCK};
+use crate::consensus::BlockHash;
+use pinxit::Signed;
+use prellblock_client_api::Transaction;
+use std::{collections::HashMap, mem};
+
+/// The maximum size of the transactions sent within a single message (in bytes).
+const MAX_APPEND_SIZE: usize = 1024 * 1024;
+
+/// Split the `transactions` into chunks of at most `MAX_APPEND_SIZE` bytes.
+///
+/// A single transaction exceeding the size is sent in its own chunk.
+/// Returns a single chunk if all transactions fit into one message.
+pub fn split(
+    transactions: Vec<Signed<Transaction>>,
+) -> Result<Vec<Vec<Signed<Transaction>>>, Error> {
+    let mut chunks = Vec::new();
+    let mut chunk = Vec::new();
+    let mut chunk_size = 0;
+    for transaction in transactions {
+        let size = postcard::to_stdvec(&transaction)?.len();
+        if !chunk.is_empty() && chunk_size + size > MAX_APPEND_SIZE {
+            chunks.push(mem::take(&mut chunk));
+            chunk_size = 0;
+        }
+        chunk.push(transaction);
+        chunk_size += size;
+    }
+    if !chunk.is_empty() || chunks.is_empty() {
+        chunks.push(chunk);
+    }
+    Ok(chunks)
+}
+
+/// Calculate the hash of a chunk of `transactions`.
+pub fn chunk_hash(transactions: &[Signed<Transaction>]) -> Result<BlockHash, Error> {
+    Ok(BlockHash::of(&postcard::to_stdvec(transactions)?))
+}
+
+/// The chunks received for the block that is currently appended.
+#[derive(Debug, Default)]
+pub struct AppendChunks {
+    metadata: Option<Metadata>,
+    chunks: HashMap<BlockHash, Vec<Signed<Transaction>>>,
+    /// The number of buffered transactions.
+    len: usize,
+}
+
+impl AppendChunks {
+    /// Buffer a chunk of `transactions` for the block described by the `metadata`.
+    ///
+    /// Chunks of any other block are discarded.
+    pub fn insert(
+        &mut self,
+        metadata: Metadata,
+        transactions: Vec<Signed<Transaction>>,
+    ) -> Result<(), Error> {
+        if self.metadata.as_ref() != Some(&metadata) {
+            *self = Self {
+                metadata: Some(metadata),
+                ..Self::default()
+            };
+        }
+
+        let len = self.len + transactions.len();
+        if len > MAX_TRANSACTIONS_PER_BLOCK {
+            return Err(Error::TooManyTransactions(len));
+        }
+        let hash = chunk_hash(&transactions)?;
+        if self.chunks.insert(hash, transactions).is_none() {
+            self.len = len;
+        }
+        Ok(())
+    }
+
+    /// Reassemble the transactions of the block described by the `metadata` from the chunks with the given `hashes`.
+    ///
+    /// The buffered chunks are discarded afterwards.
+    pub fn take(
+        &mut self,
+        metadata: &Metadata,
+        hashes: &[BlockHash],
+    ) -> Result<Vec<Signed<Transaction>>, Error> {
+        if self.metadata.as_ref() != Some(metadata) {
+            *self = Self::default();
+        }
+
+        let mut transactions = Vec::new();
+        for hash in hashes {
+            let chunk = self
+                .chunks
+                .get(hash)
+                .ok_or(Error::MissingAppendChunk(*hash))?;
+            transactions.extend(chunk.iter().cloned());
+        }
+        *self = Self::default();
+        Ok(transactions)
+    }
+}
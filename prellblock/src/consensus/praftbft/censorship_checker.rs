@@ -1,9 +1,6 @@
-use super::{Core, ViewChange};
-use std::{ops::Deref, sync::Arc, time::Duration};
-use tokio::time;
-
-// After this amount of time a transaction should be committed.
-const CENSORSHIP_TIMEOUT: Duration = Duration::from_secs(10);
+use super::{clock, Core, ViewChange};
+use prellblock_client_api::{parameter::Parameter, ViewChangeReason};
+use std::{ops::Deref, sync::Arc};
 
 pub struct CensorshipChecker {
     core: Arc<Core>,
@@ -28,28 +25,39 @@ impl CensorshipChecker {
     /// number of blocks commited.
     pub async fn execute(self) {
         loop {
-            let timeout_result = time::timeout(
-                CENSORSHIP_TIMEOUT,
+            // After this amount of time a transaction should be committed.
+            let censorship_timeout = self
+                .world_state
+                .get()
+                .parameter_duration(Parameter::CensorshipTimeout);
+            let timeout_result = clock::timeout(
+                &*self.clock,
+                censorship_timeout,
                 self.notify_censorship_checker.notified(),
             )
             .await;
 
             // If there was no timeout, a leader change happened.
             // Give the leader enough time by sleeping again.
-            if timeout_result.is_ok() {
+            if timeout_result.is_some() {
                 continue;
             }
 
             // Checking only the first transaction,
             // the queue is already sorted by insertion time.
             let has_old_transactions = self.queue.lock().await.peek().map_or(false, |entry| {
-                entry.inserted().elapsed() > CENSORSHIP_TIMEOUT
+                self.clock.elapsed(entry.inserted()) > censorship_timeout
             });
 
             if has_old_transactions {
                 // leader seems to be faulty / dead or censoring
                 log::warn!("Found censored transactions. Requesting View Change.",);
-                self.view_change.request_view_change().await;
+                self.view_change
+                    .request_view_change(
+                        ViewChangeReason::Censorship,
+                        "Found censored transactions.",
+                    )
+                    .await;
             } else {
                 log::trace!("No old transactions found while checking for censorship.");
             }
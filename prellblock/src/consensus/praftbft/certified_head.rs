@@ -0,0 +1,117 @@
+//! Certification of the head of the chain for clients.
+//!
+//! Each RPU confirms that a block is the head of its chain at the time of the request
+//! by signing a `HeadAck` response. With the signatures of a supermajority, clients can trust
+//! the latest block number returned by a single RPU without polling all of them.
+//! The signatures are only collected when a client asks for them and cached
+//! until the head changes or the certificate is older than `MAX_CERTIFIED_HEAD_AGE`.
+
+use super::{
+    message::{consensus_message as message, consensus_response as response},
+    ConsensusResponse, Core, Error,
+};
+use crate::consensus::{BlockHash, BlockNumber};
+use newtype_enum::Enum;
+use pinxit::{PeerId, Signable};
+use prellblock_client_api::{consensus::CertifiedHead, parameter::Parameter};
+use std::time::Duration;
+
+/// The maximum age of a cached certificate.
+const MAX_CERTIFIED_HEAD_AGE: Duration = Duration::from_secs(10);
+
+impl Core {
+    /// Handle a request to confirm the head of the chain.
+    pub fn handle_head_request(
+        &self,
+        peer_id: &PeerId,
+        message: &message::HeadRequest,
+    ) -> Result<response::HeadAck, Error> {
+        let (block_number, block_hash) = self.head();
+        if message.block_number != block_number || message.block_hash != block_hash {
+            return Err(Error::NotTheHead(message.block_number));
+        }
+
+        // The certificate must not claim a time the head was not confirmed at.
+        let tolerance = self
+            .world_state
+            .get()
+            .parameter_duration(Parameter::TimestampTolerance);
+        self.clock_skews.verify_timestamp(
+            peer_id,
+            message.timestamp,
+            self.clock.now(),
+            tolerance,
+        )?;
+
+        Ok(response::HeadAck {
+            block_number,
+            block_hash,
+            timestamp: message.timestamp,
+        })
+    }
+
+    /// Get the head of the chain certified by a supermajority of the RPUs.
+    ///
+    /// Concurrent callers wait for the same certificate.
+    pub async fn certified_head(&self) -> Result<CertifiedHead, Error> {
+        let mut cached = self.certified_head.lock().await;
+        let (block_number, block_hash) = self.head();
+        if let Some(head) = &*cached {
+            let age = self
+                .clock
+                .now()
+                .duration_since(head.certified_at)
+                .unwrap_or_default();
+            if head.block_number == block_number
+                && head.block_hash == block_hash
+                && age < MAX_CERTIFIED_HEAD_AGE
+            {
+                return Ok(head.clone());
+            }
+        }
+
+        let timestamp = self.clock.now();
+        let request = message::HeadRequest {
+            block_number,
+            block_hash,
+            timestamp,
+        };
+        let epoch = self.epochs.current();
+        let signatures = self
+            .broadcast_until_majority(&epoch, request, move |ack| {
+                if ack.block_number == block_number
+                    && ack.block_hash == block_hash
+                    && ack.timestamp == timestamp
+                {
+                    Ok(())
+                } else {
+                    Err(Error::AckDoesNotMatch)
+                }
+            })
+            .await?;
+
+        let ack = ConsensusResponse::from_variant(response::HeadAck {
+            block_number,
+            block_hash,
+            timestamp,
+        });
+        let head = CertifiedHead {
+            block_number,
+            block_hash,
+            certified_at: timestamp,
+            signed_data: ack.signable_data()?,
+            signatures,
+        };
+        *cached = Some(head.clone());
+        Ok(head)
+    }
+
+    /// Get the number and the hash of the head block.
+    fn head(&self) -> (BlockNumber, BlockHash) {
+        let world_state = self.world_state.get();
+        (
+            world_state.block_number.saturating_sub(1),
+            world_state.last_block_hash,
+        )
+    }
+}
@@ -0,0 +1,130 @@
+//! Comparison of the chains of all RPUs (e.g. to verify the consistency after an incident).
+//!
+//! As each block contains the hash of its predecessor, two chains are equal up to a matching block.
+//! The first mismatching block is therefore found by requesting the hashes of evenly spaced blocks
+//! and narrowing down the range between the last matching and the first mismatching sample.
+
+use super::{
+    message::{consensus_message as message, consensus_response as response},
+    Core, Error, MAX_CHAIN_SAMPLES,
+};
+use crate::consensus::{BlockHash, BlockNumber};
+use futures::future::join_all;
+use pinxit::PeerId;
+use prellblock_client_api::{ChainComparison, PeerChainComparison};
+use std::net::SocketAddr;
+
+impl Core {
+    /// Handle a request for the hashes of some blocks.
+    pub fn handle_chain_sample_request(
+        &self,
+        message: &message::ChainSampleRequest,
+    ) -> Result<response::ChainSample, Error> {
+        let block_number = self.world_state.get().block_number;
+        let hashes = message
+            .block_numbers
+            .iter()
+            .map(|&sample| self.block_hash(sample, block_number))
+            .collect::<Result<_, _>>()?;
+        Ok(response::ChainSample {
+            block_number,
+            hashes,
+        })
+    }
+
+    /// Compare the own chain with the chains of all other RPUs.
+    pub async fn compare_chains(&self) -> Result<ChainComparison, Error> {
+        let world_state = self.world_state.get();
+        let epoch = self.epochs.current();
+        let own_peer_id = self.identity.id();
+        let comparisons = epoch
+            .peers
+            .iter()
+            .filter(|(peer_id, _)| peer_id != own_peer_id)
+            .map(|(peer_id, peer_address)| async move {
+                let comparison = match self.compare_chain(*peer_address).await {
+                    Ok(comparison) => comparison,
+                    Err(err) => PeerChainComparison::Failed(err.to_string()),
+                };
+                (peer_id.clone(), comparison)
+            });
+        let peers = join_all(comparisons).await.into_iter().collect();
+
+        Ok(ChainComparison {
+            block_number: world_state.block_number,
+            last_block_hash: world_state.last_block_hash,
+            peers,
+        })
+    }
+
+    /// Compare the own chain with the chain of the RPU at `peer_address`.
+    async fn compare_chain(&self, peer_address: SocketAddr) -> Result<PeerChainComparison, Error> {
+        let own_block_number = self.world_state.get().block_number;
+        // All blocks below `low` match, all blocks at or above `high` differ (or are missing).
+        let mut low = BlockNumber::default();
+        let mut high = own_block_number;
+        let mut peer_block_number = BlockNumber::default();
+
+        while low < high {
+            let block_numbers = samples(low, high);
+            let response = self
+                .send_message(
+                    peer_address,
+                    message::ChainSampleRequest {
+                        block_numbers: block_numbers.clone(),
+                    },
+                )
+                .await?;
+            if response.hashes.len() != block_numbers.len() {
+                return Err(Error::BadChainSample);
+            }
+            peer_block_number = response.block_number;
+
+            for (sample, peer_hash) in block_numbers.into_iter().zip(&response.hashes) {
+                let own_hash = self.block_hash(sample, own_block_number)?;
+                if own_hash.is_some() && own_hash == *peer_hash {
+                    low = sample + 1;
+                } else {
+                    high = sample;
+                    break;
+                }
+            }
+        }
+
+        let common_block_number = own_block_number.min(peer_block_number);
+        Ok(PeerChainComparison::Compared {
+            block_number: peer_block_number,
+            first_mismatch: if low < common_block_number {
+                Some(low)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Get the hash of the committed block at `sample` (`None` if it is not below `block_number`).
+    fn block_hash(
+        &self,
+        sample: BlockNumber,
+        block_number: BlockNumber,
+    ) -> Result<Option<BlockHash>, Error> {
+        if sample >= block_number {
+            return Ok(None);
+        }
+        Ok(self
+            .block_storage
+            .read_block(sample)?
+            .map(|block| block.hash()))
+    }
+}
+
+/// Get up to `MAX_CHAIN_SAMPLES` evenly spaced block numbers in `low..high` (ascending).
+///
+/// The last block number (`high - 1`) is always included.
+fn samples(low: BlockNumber, high: BlockNumber) -> Vec<BlockNumber> {
+    let len = u64::from(high) - u64::from(low);
+    let count = len.min(MAX_CHAIN_SAMPLES as u64);
+    (1..=count)
+        .map(|index| low + (len * index / count - 1))
+        .collect()
+}
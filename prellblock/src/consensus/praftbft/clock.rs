@@ -0,0 +1,185 @@
+//! The source of time of the consensus.
+//!
+//! All timeouts, timestamps and waiting times of `praftbft` are measured by a `Clock`,
+//! so the consensus can be driven by a `MockClock` in tests instead of waiting in real time.
+
+use futures::{
+    channel::oneshot,
+    future::{self, BoxFuture, Either},
+    pin_mut, FutureExt,
+};
+use std::{
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// A source of the current time that can also be waited on.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Get the current wall-clock time (e.g. for block timestamps).
+    fn now(&self) -> SystemTime;
+
+    /// Get the current monotonic time (e.g. for timeouts).
+    fn instant(&self) -> Instant;
+
+    /// Wait until `duration` passed.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Get the time passed since the `earlier` instant (zero if it is in the future).
+    fn elapsed(&self, earlier: Instant) -> Duration {
+        self.instant().saturating_duration_since(earlier)
+    }
+}
+
+/// The real clock of the system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        tokio::time::delay_for(duration).boxed()
+    }
+}
+
+/// A clock that only moves when it is advanced.
+///
+/// ```
+/// # use prellblock::consensus::{Clock, MockClock};
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # futures::executor::block_on(async {
+/// let clock = MockClock::new(UNIX_EPOCH);
+/// let sleep = clock.sleep(Duration::from_secs(5));
+/// assert_eq!(clock.sleepers(), 1);
+///
+/// clock.advance(Duration::from_secs(5));
+/// sleep.await;
+/// assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(5));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    state: Mutex<MockState>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    now: SystemTime,
+    elapsed: Duration,
+    /// The pending sleeps and when they end (relative to `start`).
+    sleepers: Vec<(Duration, oneshot::Sender<()>)>,
+}
+
+impl MockClock {
+    /// Create a clock showing the wall-clock time `now`.
+    #[must_use]
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            start: Instant::now(),
+            state: Mutex::new(MockState {
+                now,
+                elapsed: Duration::default(),
+                sleepers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Move the clock forward by `duration` and wake up the sleeps that ended.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.now += duration;
+        state.elapsed += duration;
+        let elapsed = state.elapsed;
+        let (ended, pending) = state
+            .sleepers
+            .drain(..)
+            .partition::<Vec<_>, _>(|(end, _)| *end <= elapsed);
+        state.sleepers = pending;
+        drop(state);
+
+        for (_, sender) in ended {
+            let _ = sender.send(());
+        }
+    }
+
+    /// Get the number of pending sleeps (e.g. to wait until all tasks are idle).
+    #[must_use]
+    pub fn sleepers(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.sleepers.retain(|(_, sender)| !sender.is_canceled());
+        state.sleepers.len()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        self.state.lock().unwrap().now
+    }
+
+    fn instant(&self) -> Instant {
+        self.start + self.state.lock().unwrap().elapsed
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        if duration == Duration::default() {
+            return future::ready(()).boxed();
+        }
+        let (sender, receiver) = oneshot::channel();
+        let mut state = self.state.lock().unwrap();
+        let end = state.elapsed + duration;
+        state.sleepers.push((end, sender));
+        receiver.map(|_| ()).boxed()
+    }
+}
+
+/// Wait for the `future`, but at most `duration` (as measured by the `clock`).
+///
+/// Returns `None` if the time passed first.
+pub(super) async fn timeout<F: Future>(
+    clock: &dyn Clock,
+    duration: Duration,
+    future: F,
+) -> Option<F::Output> {
+    let sleep = clock.sleep(duration);
+    pin_mut!(future);
+    match future::select(future, sleep).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(((), _)) => None,
+    }
+}
+
+/// Ticks every `period` (as measured by the `clock`), the first tick completes immediately.
+pub(super) struct Interval {
+    clock: Arc<dyn Clock>,
+    period: Duration,
+    next: Option<Instant>,
+}
+
+impl Interval {
+    pub(super) fn new(clock: Arc<dyn Clock>, period: Duration) -> Self {
+        Self {
+            clock,
+            period,
+            next: None,
+        }
+    }
+
+    /// Wait for the next tick.
+    pub(super) async fn tick(&mut self) {
+        let now = self.clock.instant();
+        let tick = self.next.unwrap_or(now);
+        if tick > now {
+            self.clock.sleep(tick - now).await;
+        }
+        self.next = Some(tick + self.period);
+    }
+}
@@ -0,0 +1,173 @@
+//! Estimation of the clock skew between RPUs.
+//!
+//! Each RPU periodically asks all other RPUs for their current time (using signed consensus messages)
+//! and estimates the offset of their clocks similar to NTP.
+
+use super::{clock::Interval, message::consensus_message, Core, Error};
+use pinxit::PeerId;
+use prellblock_client_api::ClockSkew;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ops::Deref,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+/// The time between two clock skew estimations.
+const CLOCK_SKEW_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A warning is logged if the clock of a peer is off by more than this.
+const CLOCK_SKEW_WARNING_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// The tolerance is widened by the skew of the leader, but never beyond this.
+const MAX_TIMESTAMP_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// The latest clock skew estimations of all peers.
+#[derive(Debug, Default)]
+pub struct ClockSkews {
+    estimations: RwLock<HashMap<PeerId, ClockSkew>>,
+}
+
+impl ClockSkews {
+    /// Get the latest estimations of all peers.
+    pub fn get(&self) -> HashMap<PeerId, ClockSkew> {
+        self.estimations.read().unwrap().clone()
+    }
+
+    fn insert(&self, peer_id: PeerId, clock_skew: ClockSkew) {
+        self.estimations
+            .write()
+            .unwrap()
+            .insert(peer_id, clock_skew);
+    }
+
+    /// Get the tolerance for timestamps of blocks proposed by `peer_id`.
+    ///
+    /// The base `tolerance` (see `Parameter::TimestampTolerance`) is widened
+    /// by the estimated skew of the peer's clock.
+    pub fn timestamp_tolerance(&self, peer_id: &PeerId, tolerance: Duration) -> Duration {
+        let skew = self
+            .estimations
+            .read()
+            .unwrap()
+            .get(peer_id)
+            .map_or(Duration::default(), ClockSkew::offset);
+        (tolerance + skew).min(MAX_TIMESTAMP_TOLERANCE)
+    }
+
+    /// Verify that the `timestamp` of a block proposed by `peer_id` is close enough to the local time `now`.
+    pub fn verify_timestamp(
+        &self,
+        peer_id: &PeerId,
+        timestamp: SystemTime,
+        now: SystemTime,
+        tolerance: Duration,
+    ) -> Result<(), Error> {
+        let difference = match now.duration_since(timestamp) {
+            Ok(difference) => difference,
+            Err(err) => err.duration(),
+        };
+        if difference > self.timestamp_tolerance(peer_id, tolerance) {
+            Err(Error::TimestampOutOfTolerance(timestamp))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Periodically estimates the clock skew of all peers.
+pub struct ClockSkewEstimator {
+    core: Arc<Core>,
+}
+
+impl Deref for ClockSkewEstimator {
+    type Target = Core;
+    fn deref(&self) -> &Self::Target {
+        &self.core
+    }
+}
+
+impl ClockSkewEstimator {
+    pub fn new(core: Arc<Core>) -> Self {
+        Self { core }
+    }
+
+    /// Execute the clock skew estimator.
+    pub async fn execute(self) {
+        let mut interval = Interval::new(self.clock.clone(), CLOCK_SKEW_INTERVAL);
+        loop {
+            interval.tick().await;
+            if self.verify_not_halted().is_err() {
+                continue;
+            }
+
+            let own_peer_id = self.identity.id().clone();
+            for (peer_id, peer_address) in &self.epochs.current().peers {
+                if *peer_id == own_peer_id || self.ban_list.is_banned(peer_id) {
+                    continue;
+                }
+
+                let sent = self.clock.now();
+                let response = self
+                    .send_message(*peer_address, consensus_message::ClockRequest)
+                    .await;
+                let received = self.clock.now();
+
+                let response = match response {
+                    Ok(response) if response.signer() == peer_id => response,
+                    Ok(response) => {
+                        log::warn!(
+                            "Clock response from {} signed by {}.",
+                            peer_id,
+                            response.signer()
+                        );
+                        continue;
+                    }
+                    Err(err) => {
+                        log::debug!("Could not estimate the clock skew of {}: {}", peer_id, err);
+                        continue;
+                    }
+                };
+
+                // Assume the peer read its clock halfway through the round trip.
+                let round_trip = received.duration_since(sent).unwrap_or_default();
+                let midpoint = sent + round_trip / 2;
+                let offset_micros = signed_micros(response.time, midpoint);
+
+                let clock_skew = ClockSkew {
+                    offset_micros,
+                    round_trip,
+                    measured_at: received,
+                };
+
+                if clock_skew.offset() > CLOCK_SKEW_WARNING_THRESHOLD {
+                    log::warn!(
+                        "The clock of {} is off by {}ms (round trip: {:?}).",
+                        peer_id,
+                        offset_micros / 1000,
+                        round_trip
+                    );
+                } else {
+                    log::trace!(
+                        "The clock of {} is off by {}µs (round trip: {:?}).",
+                        peer_id,
+                        offset_micros,
+                        round_trip
+                    );
+                }
+
+                self.clock_skews.insert(peer_id.clone(), clock_skew);
+            }
+        }
+    }
+}
+
+/// Get `a - b` in microseconds.
+fn signed_micros(a: SystemTime, b: SystemTime) -> i64 {
+    let (difference, sign) = match a.duration_since(b) {
+        Ok(difference) => (difference, 1),
+        Err(err) => (err.duration(), -1),
+    };
+    sign * i64::try_from(difference.as_micros()).unwrap_or(i64::max_value())
+}
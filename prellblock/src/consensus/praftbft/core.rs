@@ -1,54 +1,221 @@
-use super::{message::Request, ConsensusMessage, Error, Queue};
+use super::{
+    clock_skew::ClockSkews,
+    epoch::{Epoch, EpochManager},
+    message::{consensus_message, consensus_response, Metadata, PreparedBlock, Request},
+    spill::QueueSpill,
+    trace::Traces,
+    Clock, ConsensusMessage, ConsensusNetwork, ConsensusResponse, Error, ErrorVerify, Queue,
+};
 use crate::{
+    ban_list::BanList,
     block_storage::BlockStorage,
-    consensus::{LeaderTerm, SignatureList, TransactionApplier},
-    peer::{message as peer_message, Sender},
+    consensus::{BlockNumber, ChainId, LeaderTerm, SignatureList, TransactionApplier},
+    event_bus::{Event, EventBus},
     transaction_checker::TransactionChecker,
     world_state::WorldStateService,
 };
-use futures::{stream::FuturesUnordered, StreamExt};
+use futures::StreamExt;
 use newtype_enum::Enum;
-use pinxit::{Identity, PeerId, Signable, Signed, Verified};
-use prellblock_client_api::Transaction;
-use std::net::SocketAddr;
+use pinxit::{PeerId, Signable, Signature, Signed, Signer, Verified};
+use prellblock_client_api::{
+    consensus::{transaction_hash, CertifiedHead},
+    ConsensusFailure, ConsensusFailureKind, LeaderTermRecord, Transaction, ViewChangeReason,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
 use tokio::sync::{Mutex, Notify};
 
 #[derive(Debug)]
 pub struct Core {
-    pub(super) identity: Identity,
+    pub(super) identity: Arc<dyn Signer>,
+    pub(super) network: Arc<dyn ConsensusNetwork>,
+    pub(super) chain_id: ChainId,
     pub(super) block_storage: BlockStorage,
     pub(super) world_state: WorldStateService,
     pub(super) transaction_applier: TransactionApplier,
     pub(super) transaction_checker: TransactionChecker,
+    pub(super) ban_list: Arc<BanList>,
     pub(super) queue: Mutex<Queue<Signed<Transaction>>>,
+    pub(super) queue_spill: Option<QueueSpill>,
+    pub(super) epochs: EpochManager,
+    pub(super) clock_skews: ClockSkews,
     pub(super) notify_censorship_checker: Notify,
     pub(super) notify_leader: Notify,
+    pub(super) events: EventBus,
+    pub(super) traces: Traces,
+    /// The latest certified head of the chain (see `certified_head`).
+    pub(super) certified_head: Mutex<Option<CertifiedHead>>,
+    /// The source of all timestamps and timeouts.
+    pub(super) clock: Arc<dyn Clock>,
+    halt_reason: RwLock<Option<String>>,
 }
 
 impl Core {
     pub fn new(
-        identity: Identity,
+        identity: Arc<dyn Signer>,
         block_storage: BlockStorage,
         world_state: WorldStateService,
         transaction_applier: TransactionApplier,
+        ban_list: Arc<BanList>,
+        queue_spill: Option<QueueSpill>,
+        network: Arc<dyn ConsensusNetwork>,
+        events: EventBus,
+        clock: Arc<dyn Clock>,
     ) -> Self {
+        let chain_id = block_storage
+            .chain_id()
+            .expect("The BlockStorage has no genesis block.");
         Self {
+            network,
             identity,
+            chain_id,
             block_storage,
             world_state: world_state.clone(),
             transaction_applier,
             transaction_checker: TransactionChecker::new(world_state),
-            queue: Mutex::default(),
+            ban_list,
+            queue: Mutex::new(Queue::default().with_clock(clock.clone())),
+            queue_spill,
+            epochs: EpochManager::new(&world_state.get()),
+            clock_skews: ClockSkews::default(),
             notify_censorship_checker: Notify::new(),
             notify_leader: Notify::new(),
+            events,
+            traces: Traces::new(clock.clone()),
+            certified_head: Mutex::default(),
+            clock,
+            halt_reason: RwLock::default(),
+        }
+    }
+
+    /// Start a new epoch if the RPUs changed with the `WorldState`.
+    ///
+    /// The leader is notified, because the leader of the current leader term may have changed.
+    pub fn update_epoch(&self) {
+        if self.epochs.update(&self.world_state.get()) {
+            self.notify_leader.notify();
+        }
+    }
+
+    /// Halt the RPU because of a fatal `error`.
+    ///
+    /// The first fatal error halts the RPU: it stops taking part
+    /// in the consensus and rejects new transactions (read-only mode).
+    /// The RPU is halted before this returns, so no further message is handled.
+    /// The returned `Error::Halted` can be passed on to the caller.
+    pub fn fatal_error(&self, error: Error) -> Error {
+        let reason = error.to_string();
+        let mut halt_reason = self.halt_reason.write().unwrap();
+        if halt_reason.is_none() {
+            log::error!("Halting RPU (read-only mode): {}", reason);
+            *halt_reason = Some(reason.clone());
+            drop(halt_reason);
+            self.events.publish(Event::Halted(reason.clone()));
+        } else {
+            log::warn!("Fatal error while already halted: {}", reason);
+        }
+        Error::Halted(reason)
+    }
+
+    /// Get the reason why the RPU was halted (`None` if it is running).
+    pub fn halt_reason(&self) -> Option<String> {
+        self.halt_reason.read().unwrap().clone()
+    }
+
+    /// Verify that the RPU is not halted.
+    pub fn verify_not_halted(&self) -> Result<(), Error> {
+        match self.halt_reason() {
+            Some(reason) => Err(Error::Halted(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Record a consensus failure, so operators can diagnose a stalled chain after the fact.
+    pub fn record_failure(
+        &self,
+        kind: ConsensusFailureKind,
+        leader_term: LeaderTerm,
+        reason: impl fmt::Display,
+        peers: Vec<PeerId>,
+    ) {
+        let failure = ConsensusFailure {
+            time: self.clock.now(),
+            kind,
+            leader_term,
+            block_number: self.world_state.get().block_number,
+            reason: reason.to_string(),
+            peers,
+        };
+        if let Err(err) = self.block_storage.write_consensus_failure(&failure) {
+            log::error!("Failed to record consensus failure {:?}: {}", failure, err);
         }
+        self.events.publish(Event::ConsensusFailure(failure));
     }
 
+    /// Record the start of a `leader_term` at `block_number` in the history of leader terms.
+    ///
+    /// The reason is taken from the view change this RPU requested for the leader term (if any).
+    pub fn record_leader_term(
+        &self,
+        leader_term: LeaderTerm,
+        block_number: BlockNumber,
+        view_change_reasons: BTreeMap<ViewChangeReason, u32>,
+    ) {
+        let reason = self
+            .block_storage
+            .read_consensus_failures()
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .find(|failure| {
+                failure.kind == ConsensusFailureKind::ViewChange
+                    && failure.leader_term == leader_term
+            })
+            .map_or_else(
+                || "The view change was requested by other RPUs.".to_string(),
+                |failure| failure.reason,
+            );
+        let record = LeaderTermRecord {
+            leader_term,
+            leader: self.leader(leader_term),
+            started: self.clock.now(),
+            start_block: block_number,
+            end_block: None,
+            reason,
+            view_change_reasons,
+        };
+        if let Err(err) = self.block_storage.write_leader_term(&record) {
+            log::error!("Failed to record leader term {:?}: {}", record, err);
+        }
+    }
+
+    /// Remove the `transactions` from the queue (including the spilled ones).
+    ///
+    /// Spilled transactions are moved into the queue afterwards if there is space.
+    pub async fn remove_from_queue<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a Signed<Transaction>>,
+    ) {
+        let transactions: Vec<_> = transactions.into_iter().collect();
+        let mut queue = self.queue.lock().await;
+        queue.remove_all(transactions.iter().copied());
+        if let Some(queue_spill) = &self.queue_spill {
+            let hashes: Vec<_> = transactions
+                .iter()
+                .map(|transaction| transaction_hash(transaction))
+                .collect();
+            queue_spill.remove(&hashes);
+            queue_spill.refill(&mut queue);
+        }
+    }
+
+    /// Get the leader of a `leader_term` in the current epoch.
     pub fn leader(&self, leader_term: LeaderTerm) -> PeerId {
-        let peers = self.world_state.get().peers;
-        let index = u64::from(leader_term) % (peers.len() as u64);
-        #[allow(clippy::cast_possible_truncation)]
-        peers[index as usize].0.clone()
+        self.epochs.current().leader(leader_term).clone()
     }
 
     pub fn verify_rpu_majority_signatures<E>(
@@ -59,11 +226,8 @@ impl Core {
     where
         E: newtype_enum::Enum + Signable,
     {
-        if !signatures.is_unique() {
-            return Err(Error::DuplicateSignatures);
-        }
-
-        if !self.supermajority_reached(signatures.len()) {
+        let epoch = self.epochs.current();
+        if !epoch.supermajority_reached(signatures.len())? {
             return Err(Error::NotEnoughSignatures);
         }
 
@@ -73,15 +237,31 @@ impl Core {
             // The leader would filter out any wrong signatures.
             peer_id.verify(&message, signature)?;
 
-            // Also check whether the signer is a known RPU
-            self.transaction_checker
-                .account_checker(peer_id.clone())?
-                .verify_is_rpu()?;
+            // Also check whether the signer is an RPU of the current epoch.
+            if !epoch.contains(peer_id) {
+                return Err(Error::InvalidPeer(peer_id.clone()));
+            }
         }
 
         Ok(())
     }
 
+    /// Verify that the `prepared_block` was prepared by a supermajority.
+    pub fn verify_prepared_block(&self, prepared_block: &PreparedBlock) -> Result<(), Error> {
+        let metadata = &prepared_block.metadata;
+        if prepared_block.body.hash() != metadata.block_hash {
+            return Err(Error::BlockNotMatchingHash);
+        }
+        prepared_block.body.height.verify(metadata.block_number)?;
+
+        self.verify_rpu_majority_signatures(
+            consensus_response::AckPrepare {
+                metadata: metadata.clone(),
+            },
+            &prepared_block.ackprepare_signatures,
+        )
+    }
+
     #[allow(clippy::future_not_send)]
     pub async fn send_message<M>(
         &self,
@@ -92,106 +272,170 @@ impl Core {
         M: Request,
     {
         let signed_message = self.sign_message(message)?;
-        send_signed_message::<M>(peer_address, signed_message).await
+        let response = self.network.send_to(peer_address, signed_message).await?;
+        into_response::<M>(response)
     }
 
+    /// Broadcast a `message` to the RPUs of the `epoch` until a supermajority responded.
     #[allow(clippy::future_not_send)]
     pub async fn broadcast_until_majority<M, F>(
         &self,
+        epoch: &Epoch,
         message: M,
         verify_response: F,
     ) -> Result<SignatureList, Error>
     where
         M: Request,
-        F: Fn(&M::Response) -> Result<(), Error> + Clone + Send + Sync + 'static,
+        F: Fn(&M::Response) -> Result<(), Error>,
     {
-        let signed_message = self.sign_message(message)?;
+        self.broadcast_chunks_until_majority(epoch, Vec::new(), message, verify_response)
+            .await
+    }
 
-        let mut futures = FuturesUnordered::new();
-
-        let peers = self.world_state.get().peers;
-        let peers_count = peers.len();
-        for (peer_id, peer_address) in peers {
-            let signed_message = signed_message.clone();
-            let verify_response = verify_response.clone();
-
-            futures.push(tokio::spawn(async move {
-                let send_message_and_verify_response = async {
-                    let verified_response =
-                        send_signed_message::<M>(peer_address, signed_message).await?;
-                    let signer = verified_response.signer().clone();
-                    if signer == peer_id {
-                        verify_response(&*verified_response)?;
-                        Ok((signer, verified_response.signature().clone()))
-                    } else {
-                        Err(Error::InvalidPeer(signer))
-                    }
-                };
+    /// Broadcast the `chunks` followed by a `message` to the RPUs of the `epoch`
+    /// until a supermajority responded to the `message`.
+    ///
+    /// The `message` is only sent to an RPU after it accepted all `chunks`.
+    #[allow(clippy::future_not_send)]
+    pub async fn broadcast_chunks_until_majority<M, F>(
+        &self,
+        epoch: &Epoch,
+        chunks: Vec<consensus_message::AppendChunk>,
+        message: M,
+        verify_response: F,
+    ) -> Result<SignatureList, Error>
+    where
+        M: Request,
+        F: Fn(&M::Response) -> Result<(), Error>,
+    {
+        let mut messages = chunks
+            .into_iter()
+            .map(|chunk| self.sign_message(chunk))
+            .collect::<Result<Vec<_>, _>>()?;
+        let metadata = message.metadata().cloned();
+        messages.push(self.sign_message(message)?);
 
-                match send_message_and_verify_response.await {
-                    Ok(response) => Some(response),
-                    Err(err) => {
-                        log::warn!("Consensus error from {}: {}", peer_address, err);
-                        None
-                    }
-                }
-            }));
-        }
+        // Banned peers are excluded from broadcasts.
+        let peers: HashMap<SocketAddr, PeerId> = epoch
+            .peers
+            .iter()
+            .filter(|(peer_id, _)| !self.ban_list.is_banned(peer_id))
+            .map(|(peer_id, peer_address)| (*peer_address, peer_id.clone()))
+            .collect();
+        let mut broadcast = self
+            .network
+            .clone()
+            .broadcast(peers.keys().copied().collect(), messages);
 
         let mut responses = SignatureList::default();
 
-        while let Some(result) = futures.next().await {
-            match result {
-                Ok(Some(response)) => {
-                    responses.push(response);
+        while let Some((peer_address, response)) = broadcast.next().await {
+            let peer_id = &peers[&peer_address];
+            match verify_peer_response::<M>(response, peer_id, &verify_response) {
+                Ok(signature) => {
+                    responses.insert(peer_id.clone(), signature)?;
+                }
+                Err(Error::AlreadyCommitted(proof)) => {
+                    self.handle_already_committed(metadata.as_ref(), peer_id, &proof)?;
+                }
+                Err(err) => {
+                    if err.is_retryable() {
+                        // E.g. the peer is unreachable or still catching up.
+                        log::debug!("Transient consensus error from {}: {}", peer_address, err);
+                    } else {
+                        log::warn!("Consensus error from {}: {}", peer_address, err);
+                    }
+                    if err.is_misbehaviour() {
+                        self.ban_list.strike(peer_id, &err);
+                    }
                 }
-                Ok(None) => {}
-                Err(err) => log::warn!("Failed to join task: {}", err),
             }
-            if supermajority_reached(responses.len(), peers_count) {
+            if epoch.supermajority_reached(responses.len())? {
                 return Ok(responses);
             }
         }
 
-        // All sender tasks have died **before reaching supermajority**.
+        // All peers have answered **before reaching supermajority**.
         Err(Error::CouldNotGetSupermajority)
     }
 
-    fn sign_message<M>(&self, message: M) -> Result<peer_message::Consensus, Error>
+    /// Handle the `proof` of `peer_id` that the block of a broadcasted message was already committed.
+    ///
+    /// Fails if a different block was committed, so the round can be aborted early.
+    fn handle_already_committed(
+        &self,
+        metadata: Option<&Metadata>,
+        peer_id: &PeerId,
+        proof: &consensus_response::AlreadyCommitted,
+    ) -> Result<(), Error> {
+        let ackappend = consensus_response::AckAppend {
+            metadata: proof.metadata.clone(),
+        };
+        if let Err(err) =
+            self.verify_rpu_majority_signatures(ackappend, &proof.ackappend_signatures)
+        {
+            log::warn!(
+                "Invalid proof of a committed block from {}: {}",
+                peer_id,
+                err
+            );
+            if err.is_misbehaviour() {
+                self.ban_list.strike(peer_id, &err);
+            }
+            return Ok(());
+        }
+
+        let block_number = proof.metadata.block_number;
+        if metadata == Some(&proof.metadata) {
+            log::debug!("{} already committed block #{}.", peer_id, block_number);
+            Ok(())
+        } else {
+            Err(Error::DifferentBlockCommitted(block_number))
+        }
+    }
+
+    fn sign_message<M>(&self, message: M) -> Result<Signed<ConsensusMessage>, Error>
     where
         M: Request,
     {
         let message = ConsensusMessage::from_variant(message);
-        let message = message.sign(&self.identity)?;
-        Ok(peer_message::Consensus(message))
+        Ok(message.sign(&self.identity)?)
     }
 
     /// Check whether a number represents a supermajority (>2/3) compared
-    /// to the total number of peers in the consenus.
-    pub fn supermajority_reached(&self, response_len: usize) -> bool {
-        supermajority_reached(response_len, self.world_state.get().peers.len())
+    /// to the total number of peers in the current epoch.
+    pub fn supermajority_reached(&self, response_len: usize) -> Result<bool, Error> {
+        self.epochs.current().supermajority_reached(response_len)
     }
 }
 
-async fn send_signed_message<M>(
-    peer_address: SocketAddr,
-    signed_message: peer_message::Consensus,
-) -> Result<Verified<M::Response>, Error>
+/// Verify the `response` of `peer_id` to a message of type `M` and get its signature.
+fn verify_peer_response<M>(
+    response: Result<Signed<ConsensusResponse>, Error>,
+    peer_id: &PeerId,
+    verify: impl Fn(&M::Response) -> Result<(), Error>,
+) -> Result<Signature, Error>
 where
     M: Request,
 {
-    let mut sender = Sender::new(peer_address);
-    let response = sender.send_request(signed_message).await?;
-    let response = response.verify()?;
-    response.try_map(|response| response.into_variant().ok_or(Error::UnexpectedResponse))
+    let verified_response = into_response::<M>(response?)?;
+    let signer = verified_response.signer();
+    if signer == peer_id {
+        verify(&*verified_response)?;
+        Ok(verified_response.signature().clone())
+    } else {
+        Err(Error::InvalidPeer(signer.clone()))
+    }
 }
 
-/// Check whether a number represents a supermajority (>2/3) compared
-/// to the total number of peers (`peer_count`) in the consenus.
-pub fn supermajority_reached(response_len: usize, peer_count: usize) -> bool {
-    if peer_count < 4 {
-        panic!("Cannot find consensus for less than four peers.");
+/// Verify a `response` to a message of type `M`.
+fn into_response<M>(response: Signed<ConsensusResponse>) -> Result<Verified<M::Response>, Error>
+where
+    M: Request,
+{
+    let response = response.verify()?;
+    if let ConsensusResponse::AlreadyCommitted(proof) = &*response {
+        return Err(Error::AlreadyCommitted(Box::new(proof.clone())));
     }
-    let supermajority = peer_count * 2 / 3 + 1;
-    response_len >= supermajority
+    response.try_map(|response| response.into_variant().ok_or(Error::UnexpectedResponse))
 }
@@ -0,0 +1,135 @@
+//! The set of RPUs taking part in the consensus.
+//!
+//! The RPUs are governed by transactions (creating, updating or deleting RPU accounts
+//! and setting leader weights). A committed change starts a new `Epoch` at the next block.
+//! The `EpochManager` switches all consensus decisions (quorum sizes, leader selection
+//! and broadcast targets) atomically, so a round never mixes the RPUs of two epochs.
+//! Changing the `Parameter::FaultTolerance` also starts a new epoch.
+
+use super::Error;
+use crate::{
+    consensus::{BlockNumber, LeaderTerm},
+    world_state::{leader_index, FaultTolerance, FaultToleranceError, WorldState},
+};
+use pinxit::PeerId;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+
+/// The RPUs deciding the blocks from the `activation_height` on.
+#[derive(Debug)]
+pub struct Epoch {
+    /// The number of the epoch (counted since the RPU was started).
+    pub number: u64,
+    /// The first block decided by the RPUs of this epoch.
+    pub activation_height: BlockNumber,
+    /// The RPUs and their addresses (in the order of the leader selection).
+    pub peers: Vec<(PeerId, SocketAddr)>,
+    /// The leader weights of the `peers`.
+    leader_weights: Vec<u32>,
+    /// The number of faulty `peers` that are tolerated.
+    fault_tolerance: Result<FaultTolerance, FaultToleranceError>,
+}
+
+impl Epoch {
+    fn new(number: u64, world_state: &WorldState) -> Self {
+        Self {
+            number,
+            activation_height: world_state.block_number,
+            peers: world_state.peers.iter().cloned().collect(),
+            leader_weights: world_state
+                .peers
+                .iter()
+                .map(|(peer_id, _)| world_state.leader_weight(peer_id))
+                .collect(),
+            fault_tolerance: world_state.fault_tolerance(),
+        }
+    }
+
+    /// Check whether the RPUs of the epoch are the same as in the `world_state`.
+    fn matches(&self, world_state: &WorldState) -> bool {
+        self.peers.len() == world_state.peers.len()
+            && self.fault_tolerance == world_state.fault_tolerance()
+            && self
+                .peers
+                .iter()
+                .zip(&world_state.peers)
+                .zip(&self.leader_weights)
+                .all(|((peer, other), weight)| {
+                    peer == other && *weight == world_state.leader_weight(&peer.0)
+                })
+    }
+
+    /// Get the leader of a `leader_term` (see `WorldState::leader`).
+    #[must_use]
+    pub fn leader(&self, leader_term: LeaderTerm) -> &PeerId {
+        &self.peers[leader_index(&self.leader_weights, leader_term)].0
+    }
+
+    /// Check whether `peer_id` is one of the RPUs of the epoch.
+    #[must_use]
+    pub fn contains(&self, peer_id: &PeerId) -> bool {
+        self.peers.iter().any(|(id, _)| id == peer_id)
+    }
+
+    /// Get the number of faulty RPUs the epoch tolerates.
+    ///
+    /// Fails if there are not enough RPUs (see `WorldState::fault_tolerance`).
+    pub fn fault_tolerance(&self) -> Result<FaultTolerance, Error> {
+        Ok(self.fault_tolerance?)
+    }
+
+    /// Check whether `count` RPUs of the epoch are a supermajority (a quorum of `n - f` RPUs).
+    pub fn supermajority_reached(&self, count: usize) -> Result<bool, Error> {
+        Ok(self.fault_tolerance()?.quorum_reached(count))
+    }
+
+    /// Get the number of RPUs that contains at least one non-faulty RPU (`f + 1`).
+    pub fn nonfaulty_count(&self) -> Result<usize, Error> {
+        Ok(self.fault_tolerance()?.nonfaulty_count())
+    }
+}
+
+/// Owns the current `Epoch` of the consensus.
+///
+/// The epoch is updated whenever the `WorldState` changes (commit, rollback or snapshot)
+/// and consulted per round instead of reading the RPUs from the `WorldState`.
+#[derive(Debug)]
+pub struct EpochManager {
+    current: RwLock<Arc<Epoch>>,
+}
+
+impl EpochManager {
+    /// Create a new `EpochManager` starting with the RPUs in the `world_state`.
+    pub fn new(world_state: &WorldState) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(Epoch::new(0, world_state))),
+        }
+    }
+
+    /// Get the current `Epoch`.
+    pub fn current(&self) -> Arc<Epoch> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Start a new epoch if the RPUs in the `world_state` changed.
+    ///
+    /// Returns whether a new epoch was started.
+    pub fn update(&self, world_state: &WorldState) -> bool {
+        let mut current = self.current.write().unwrap();
+        if current.matches(world_state) {
+            return false;
+        }
+        let epoch = Arc::new(Epoch::new(current.number + 1, world_state));
+        log::info!(
+            "Epoch {} starts at block #{} with {} RPUs: {:?}",
+            epoch.number,
+            epoch.activation_height,
+            epoch.peers.len(),
+            epoch.peers
+        );
+        *current = epoch;
+        true
+    }
+}
@@ -1,12 +1,19 @@
-use super::{follower, ring_buffer};
+use super::{follower, message::consensus_response, ring_buffer, round::RoundMessage};
 use crate::{
     block_storage,
-    consensus::{BlockHash, BlockNumber, LeaderTerm},
+    consensus::{BlockHash, BlockNumber, ChainId, LeaderTerm},
     transaction_checker::PermissionError,
+    world_state::FaultToleranceError,
+    BoxError,
 };
+use balise::ErrorCategory;
 use err_derive::Error;
 use pinxit::PeerId;
-use prellblock_client_api::Transaction;
+use prellblock_client_api::{
+    consensus::{SignatureListError, TransactionHash},
+    Transaction, ViewChangeReason,
+};
+use std::time::SystemTime;
 
 /// An error of the `praftbft` consensus.
 #[derive(Debug, Error)]
@@ -27,6 +34,14 @@ pub enum Error {
     #[error(display = "{}", 0)]
     BlockStorage(#[error(from)] block_storage::Error),
 
+    /// A message could not be (de)serialized.
+    #[error(display = "{}", 0)]
+    Serialization(#[error(from)] postcard::Error),
+
+    /// The spilled part of the queue could not be accessed.
+    #[error(display = "{}", 0)]
+    Database(#[error(from)] sled::Error),
+
     /// The Client does not have the correct permissions.
     #[error(display = "{}", 0)]
     Permission(#[error(from)] PermissionError),
@@ -38,9 +53,9 @@ pub enum Error {
     #[error(display = "Not enough signatures.")]
     NotEnoughSignatures,
 
-    /// There where duplicates of the same signature found.
-    #[error(display = "A Signature is duplicated.")]
-    DuplicateSignatures,
+    /// A list of signatures is malformed (e.g. a peer signed twice).
+    #[error(display = "{}", 0)]
+    SignatureList(#[error(from)] SignatureListError),
 
     /// The signature could not be verified.
     #[error(display = "{}", 0)]
@@ -57,6 +72,10 @@ pub enum Error {
     #[error(display = "The RPU {} is not the current leader.", 0)]
     WrongLeader(PeerId),
 
+    /// This peer is banned because of repeated misbehaviour.
+    #[error(display = "The RPU {} is banned.", 0)]
+    Banned(PeerId),
+
     /// This peer is not allowed to take part in the consensus.
     #[error(
         display = "The RPU {} is not allowed to take part in the consensus.",
@@ -85,6 +104,50 @@ pub enum Error {
     )]
     BadInvalidTransactionIndex(usize),
 
+    /// The message contains more transactions than allowed in a block.
+    #[error(display = "The message contains too many transactions: {}", 0)]
+    TooManyTransactions(usize),
+
+    /// A chunk of the transactions of a large block was not received.
+    #[error(display = "The chunk {} of the appended block is missing.", 0)]
+    MissingAppendChunk(BlockHash),
+
+    /// A transaction of a synchronized block was neither queued nor received.
+    #[error(display = "The transaction {} of a synchronized block is missing.", 0)]
+    MissingTransaction(TransactionHash),
+
+    /// A requested transaction of a committed block does not exist.
+    #[error(display = "Block #{} has no transaction at index {}.", 0, 1)]
+    UnknownTransaction(BlockNumber, usize),
+
+    /// An interrupted proposal of the leader could not be completed.
+    #[error(display = "The interrupted proposal of block #{} was abandoned.", 0)]
+    ProposalAbandoned(BlockNumber),
+
+    /// The task applying the committed blocks stopped (after a fatal error).
+    #[error(display = "The block applier stopped.")]
+    BlockApplierStopped,
+
+    /// The receiver of a message already committed the block (the proof is attached).
+    #[error(display = "The receiver already committed the block.")]
+    AlreadyCommitted(Box<consensus_response::AlreadyCommitted>),
+
+    /// A different block was already committed at the proposed block number.
+    #[error(display = "A different block #{} was already committed.", 0)]
+    DifferentBlockCommitted(BlockNumber),
+
+    /// The message requests more block hashes than allowed.
+    #[error(display = "The message requests too many block hashes: {}", 0)]
+    TooManyChainSamples(usize),
+
+    /// The response to a `ChainSampleRequest` does not match the request.
+    #[error(display = "The chain sample does not match the requested blocks.")]
+    BadChainSample,
+
+    /// The requested block is not the head of the chain (the chains are not in sync right now).
+    #[error(display = "Block #{} is not the head of the chain.", 0)]
+    NotTheHead(BlockNumber),
+
     /// The ack message does not match the request.
     #[error(display = "The ack message does not match the request.")]
     AckDoesNotMatch,
@@ -100,6 +163,30 @@ pub enum Error {
     #[error(display = "The sent BlockHash does not match the hash of the block.")]
     BlockNotMatchingHash,
 
+    /// The block contains the same transaction more than once.
+    #[error(
+        display = "The transaction {} is contained more than once in the block.",
+        0
+    )]
+    DuplicateTransaction(TransactionHash),
+
+    /// The leader proposed a new block instead of re-proposing the prepared block.
+    #[error(display = "The leader did not re-propose the prepared block {}.", 0)]
+    PreparedBlockNotReproposed(BlockHash),
+
+    /// The leader term of the block body differs from the leader term the block is signed in.
+    #[error(
+        display = "The block body is in leader term {} instead of leader term {}.",
+        body,
+        signed
+    )]
+    BodyLeaderTermMismatch {
+        /// The leader term of the block body.
+        body: LeaderTerm,
+        /// The leader term of the signed metadata.
+        signed: LeaderTerm,
+    },
+
     /// The `BlockHash` does not match the expected `BlockHash`.
     #[error(
         display = "The BlockHash {} does not match the expected previous BlockHash {}.",
@@ -108,6 +195,19 @@ pub enum Error {
     )]
     PrevBlockHashDoesNotMatch(BlockHash, BlockHash),
 
+    /// The leader term of a block is lower than the leader term of the previous block.
+    #[error(
+        display = "The leader term {} of the block is lower than the leader term {} of the previous block.",
+        received,
+        last
+    )]
+    LeaderTermDecreased {
+        /// The leader term of the block.
+        received: LeaderTerm,
+        /// The leader term of the previous block.
+        last: LeaderTerm,
+    },
+
     // ----------------------------------------------------------------
     // Errors with the block number.
     // ----------------------------------------------------------------
@@ -149,9 +249,144 @@ pub enum Error {
         expected: follower::Phase,
     },
 
+    /// A phase message was received twice in the same round.
+    #[error(display = "Received the {:?} message of the round twice.", 0)]
+    DuplicateRoundMessage(RoundMessage),
+
     /// Could not get supermajority.
     #[error(display = "Could not get supermajority.")]
     CouldNotGetSupermajority,
+
+    /// There are not enough peers to tolerate the declared number of faulty peers.
+    #[error(display = "Cannot find consensus: {}", 0)]
+    NotEnoughPeers(#[error(from)] FaultToleranceError),
+
+    /// There is no block that could be rolled back.
+    #[error(display = "There is no block to roll back.")]
+    NoBlockToRollback,
+
+    /// The requested snapshot is not (or no longer) available.
+    #[error(display = "The snapshot at block {} is not available.", 0)]
+    SnapshotNotAvailable(BlockHash),
+
+    /// A received snapshot chunk does not match its hash.
+    #[error(display = "The snapshot chunk {} does not match its hash.", 0)]
+    SnapshotChunkHashMismatch(usize),
+
+    /// A received snapshot does not fit onto the own blockchain.
+    #[error(display = "The snapshot does not match the blockchain.")]
+    SnapshotDoesNotMatch,
+
+    /// The snapshot could not be downloaded from any RPU.
+    #[error(display = "Could not download the snapshot.")]
+    SnapshotDownloadFailed,
+
+    /// Not enough RPUs sent the same snapshot manifest to trust it.
+    #[error(display = "The snapshot is not confirmed by enough RPUs.")]
+    SnapshotNotConfirmed,
+
+    /// The blocks before a committed block could not be fetched from the RPUs that signed it.
+    #[error(display = "Could not fetch the blocks before block #{}.", 0)]
+    MissingBlocks(BlockNumber),
+
+    /// A corrupted block could not be downloaded from any RPU.
+    #[error(display = "Could not repair block #{}.", 0)]
+    BlockRepairFailed(BlockNumber),
+
+    /// The timestamp of a block differs too much from the local time.
+    #[error(display = "The block timestamp {:?} is out of tolerance.", 0)]
+    TimestampOutOfTolerance(SystemTime),
+
+    /// The Block could not be applied to the WorldState.
+    #[error(display = "Could not apply block to the WorldState: {}", 0)]
+    WorldState(BoxError),
+
+    /// Another RPU does not serve the chain (it was started with another genesis).
+    #[error(display = "The RPU {} was started with another genesis.", 0)]
+    GenesisMismatch(PeerId),
+
+    /// Too many RPUs were started with another genesis to reach a quorum.
+    #[error(
+        display = "{} RPUs were started with another genesis, no quorum can be reached.",
+        0
+    )]
+    MixedGenesis(usize),
+
+    /// A message signed for another chain was received.
+    #[error(display = "The message belongs to the chain {}.", 0)]
+    WrongChain(ChainId),
+
+    // ----------------------------------------------------------------
+    // Fatal errors
+    // ----------------------------------------------------------------
+    /// The RPU was halted because of a fatal error and is read-only now.
+    #[error(display = "The RPU is halted (read-only mode): {}", 0)]
+    Halted(String),
+}
+
+impl Error {
+    /// Get the category of the error.
+    ///
+    /// Transient errors may not occur when the same message is sent again later
+    /// (e.g. after the receiver caught up), fatal errors halt the RPU.
+    #[must_use]
+    pub const fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Balise(err) => err.category(),
+            Self::WrongLeaderTerm
+            | Self::WrongLeader(_)
+            | Self::MissingAppendChunk(_)
+            | Self::MissingTransaction(_)
+            | Self::WrongBlockNumber { .. }
+            | Self::LeaderTermTooBig(_)
+            | Self::WrongPhase { .. }
+            | Self::DuplicateRoundMessage(_)
+            | Self::CouldNotGetSupermajority
+            | Self::SnapshotNotAvailable(_)
+            | Self::SnapshotDownloadFailed
+            | Self::SnapshotNotConfirmed
+            | Self::BlockRepairFailed(_)
+            | Self::MissingBlocks(_)
+            | Self::TimestampOutOfTolerance(_)
+            | Self::NotTheHead(_) => ErrorCategory::Transient,
+            Self::Database(_)
+            | Self::WorldState(_)
+            | Self::BlockApplierStopped
+            | Self::MixedGenesis(_)
+            | Self::Halted(_) => ErrorCategory::Fatal,
+            _ => ErrorCategory::Rejected,
+        }
+    }
+
+    /// Whether sending the same message again later may succeed.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self.category(), ErrorCategory::Transient)
+    }
+
+    /// Whether the error halts (or halted) the RPU.
+    #[must_use]
+    pub const fn is_fatal(&self) -> bool {
+        matches!(self.category(), ErrorCategory::Fatal)
+    }
+
+    /// Whether the error proves misbehaviour of the peer that sent a (signed) message.
+    ///
+    /// Only invalid signatures are a proof: Other errors (e.g. a mismatching block hash)
+    /// may also be caused by a diverged state of this RPU, so they never lead to a ban.
+    pub(super) const fn is_misbehaviour(&self) -> bool {
+        matches!(self, Self::InvalidSignature(_) | Self::SignatureList(_))
+    }
+
+    /// Get the reason of the view change requested because of the error (see `ViewChange::request_view_change_on_error`).
+    pub(super) const fn view_change_reason(&self) -> ViewChangeReason {
+        match self {
+            Self::NotEnoughSignatures | Self::SignatureList(_) => {
+                ViewChangeReason::NotEnoughSignatures
+            }
+            _ => ViewChangeReason::InvalidAppend,
+        }
+    }
 }
 
 pub(super) trait ErrorVerify {
@@ -188,14 +423,6 @@ impl follower::Phase {
             expected,
         }
     }
-
-    pub(super) fn verify(self, expected: Self) -> Result<(), Error> {
-        if self == expected {
-            Ok(())
-        } else {
-            Err(self.error(expected))
-        }
-    }
 }
 
 impl From<ring_buffer::Error<LeaderTerm>> for Error {
@@ -0,0 +1,139 @@
+//! Applying committed blocks decoupled from the consensus voting.
+//!
+//! The follower state only tracks the committed frontier. Committed blocks are sent
+//! to a separate task that writes them to the `BlockStorage` and the `WorldState` in order,
+//! so the follower state is not locked during disk I/O and the next round can be voted on meanwhile.
+//! Only the stateful validation of an `Append` message (and everything else reading the `WorldState`
+//! of a block) waits until the previous block is applied (see `Applier::wait_applied`).
+//! Votes on `Prepare` and `Commit` messages proceed meanwhile.
+//! The committed transactions are only removed from the queue once their block is written,
+//! so they are not lost if writing the block fails.
+//! The task also takes the `WorldState` snapshots at the snapshot heights (see `SNAPSHOT_INTERVAL`).
+
+use super::{
+    snapshot::{is_snapshot_height, Snapshot, Snapshots},
+    Core, Error,
+};
+use crate::consensus::{Block, BlockNumber};
+use prellblock_client_api::{ExecutionStatus, TraceEventKind};
+use std::sync::{self, Arc};
+use tokio::sync::{mpsc, watch};
+
+/// A handle to the task applying the committed blocks.
+#[derive(Debug, Clone)]
+pub struct Applier {
+    sender: mpsc::UnboundedSender<Block>,
+    /// The number of the next block to apply (the block number of the `WorldState`).
+    applied: watch::Receiver<BlockNumber>,
+    /// The snapshots taken after applying the blocks.
+    snapshots: Arc<sync::Mutex<Snapshots>>,
+}
+
+impl Applier {
+    /// Start the task applying the committed blocks.
+    pub fn spawn(core: Arc<Core>) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (applied_sender, applied) = watch::channel(core.world_state.get().block_number);
+        let snapshots = Arc::default();
+        tokio::spawn(execute(
+            core,
+            receiver,
+            applied_sender,
+            Arc::clone(&snapshots),
+        ));
+        Self {
+            sender,
+            applied,
+            snapshots,
+        }
+    }
+
+    /// Get the snapshots taken so far.
+    pub fn snapshots(&self) -> sync::MutexGuard<'_, Snapshots> {
+        self.snapshots.lock().unwrap()
+    }
+
+    /// Apply a committed `block` after all previously committed blocks.
+    pub fn apply(&self, block: Block) -> Result<(), Error> {
+        self.sender
+            .send(block)
+            .map_err(|_| Error::BlockApplierStopped)
+    }
+
+    /// Wait until the `WorldState` reached the given `block_number`.
+    pub async fn wait_applied(&self, block_number: BlockNumber) -> Result<(), Error> {
+        let mut applied = self.applied.clone();
+        while let Some(applied_block_number) = applied.recv().await {
+            if applied_block_number >= block_number {
+                return Ok(());
+            }
+        }
+        Err(Error::BlockApplierStopped)
+    }
+}
+
+/// Apply the received blocks in order.
+///
+/// Failing to apply a committed block is fatal and stops the task.
+async fn execute(
+    core: Arc<Core>,
+    mut receiver: mpsc::UnboundedReceiver<Block>,
+    applied: watch::Sender<BlockNumber>,
+    snapshots: Arc<sync::Mutex<Snapshots>>,
+) {
+    while let Some(block) = receiver.recv().await {
+        let block_number = block.block_number();
+        let anchor = if is_snapshot_height(block_number + 1) {
+            Some(block.clone())
+        } else {
+            None
+        };
+        let transactions = block.body.transactions.clone();
+        let receipts = match core.transaction_applier.apply_block(block).await {
+            Ok(receipts) => receipts,
+            Err(err) => {
+                core.fatal_error(err);
+                return;
+            }
+        };
+        core.remove_from_queue(&transactions).await;
+        for receipt in receipts {
+            let kind = match receipt.status {
+                ExecutionStatus::Applied => TraceEventKind::Committed { block_number },
+                ExecutionStatus::Failed { reason } => TraceEventKind::Failed {
+                    block_number,
+                    reason,
+                },
+            };
+            core.traces.record(Some(receipt.transaction_hash), &kind);
+        }
+        core.update_epoch();
+        if let Some(anchor) = anchor {
+            take_snapshot(&core, anchor, &snapshots);
+        }
+
+        if applied.broadcast(block_number + 1).is_err() {
+            return;
+        }
+    }
+}
+
+/// Take a snapshot of the `WorldState` right after the `anchor` block was applied.
+fn take_snapshot(core: &Core, anchor: Block, snapshots: &sync::Mutex<Snapshots>) {
+    let world_state = core.world_state.get();
+    if world_state.last_block_hash != anchor.hash() {
+        return;
+    }
+    let block_number = anchor.block_number();
+    match Snapshot::new(anchor, &world_state) {
+        Ok(snapshot) => {
+            log::debug!("Took snapshot at block #{}.", block_number);
+            snapshots.lock().unwrap().push(snapshot);
+        }
+        Err(err) => log::warn!(
+            "Could not take snapshot at block #{}: {}",
+            block_number,
+            err
+        ),
+    }
+}
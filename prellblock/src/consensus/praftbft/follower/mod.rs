@@ -1,17 +1,32 @@
+mod applier;
+mod scrubber;
+mod snapshot;
 mod state;
 mod stateful_validation;
 mod synchronizer;
 
+pub use snapshot::confirmed_snapshot_manifest;
 pub use state::Phase;
 
 use super::{
-    message::{consensus_message as message, consensus_response as response},
-    Core, Error, ErrorVerify, InvalidTransaction, NotifyMap, ViewChange,
+    append_chunks::AppendChunks,
+    clock::Interval,
+    message::{
+        consensus_message as message, consensus_response as response, Metadata, PreparedBlock,
+    },
+    verify_unique_transactions, ConsensusMessage, Core, Error, ErrorVerify, InvalidTransaction,
+    NotifyMap, Round, RoundMessage, RoundStep, ViewChange,
 };
-use crate::consensus::{BlockNumber, LeaderTerm};
+use crate::consensus::{BlockNumber, Body, LeaderTerm};
+use applier::Applier;
 use pinxit::PeerId;
+use prellblock_client_api::{parameter::Parameter, ScrubStatistics, ViewChangeReason};
 use state::State;
-use std::{cmp::Ordering, ops::Deref, sync::Arc};
+use std::{
+    cmp::Ordering,
+    ops::Deref,
+    sync::{self, Arc},
+};
 use tokio::sync::{Mutex, MutexGuard, Semaphore};
 
 #[derive(Debug)]
@@ -19,7 +34,12 @@ pub struct Follower {
     core: Arc<Core>,
     view_change: Arc<ViewChange>,
     state: Mutex<State>,
+    applier: Applier,
     synchronizer_semaphore: Semaphore,
+    /// The received chunks of the block that is currently appended.
+    append_chunks: sync::Mutex<AppendChunks>,
+    /// The results of the block storage scrubber.
+    scrub_statistics: sync::Mutex<ScrubStatistics>,
 }
 
 impl Deref for Follower {
@@ -31,11 +51,15 @@ impl Deref for Follower {
 
 impl Follower {
     pub fn new(core: Arc<Core>, view_change: Arc<ViewChange>) -> Self {
+        let applier = Applier::spawn(core.clone());
         Self {
             core: core.clone(),
             view_change,
-            state: Mutex::new(State::new(core)),
+            state: Mutex::new(State::new(core, applier.clone())),
+            applier,
             synchronizer_semaphore: Semaphore::new(1),
+            append_chunks: sync::Mutex::default(),
+            scrub_statistics: sync::Mutex::default(),
         }
     }
 
@@ -43,7 +67,46 @@ impl Follower {
         self.state.lock().await
     }
 
+    /// Get the proof of the committed block if the `message` belongs to an already committed block.
+    ///
+    /// Late `Prepare`, `Append` and `Commit` messages (e.g. retries of the leader) are answered
+    /// with the proof instead of an error, so the sender can reconcile quickly.
+    pub fn already_committed(
+        &self,
+        message: &ConsensusMessage,
+    ) -> Result<Option<response::AlreadyCommitted>, Error> {
+        let block_number = match message {
+            ConsensusMessage::Prepare(message) => message.metadata.block_number,
+            ConsensusMessage::Append(message) => message.metadata.block_number,
+            ConsensusMessage::Commit(message) => message.metadata.block_number,
+            _ => return Ok(None),
+        };
+        if block_number >= self.world_state.get().block_number {
+            return Ok(None);
+        }
+
+        let block = match self.block_storage.read_block(block_number)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        Ok(Some(response::AlreadyCommitted {
+            metadata: Metadata {
+                leader_term: block.body.leader_term,
+                block_number,
+                block_hash: block.hash(),
+            },
+            ackappend_signatures: block.signatures,
+        }))
+    }
+
+    /// Wait until the `WorldState` reached the given `block_number`.
+    pub async fn wait_applied(&self, block_number: BlockNumber) -> Result<(), Error> {
+        self.applier.wait_applied(block_number).await
+    }
+
     /// Wait until we reached the block number the message is at.
+    ///
+    /// The previous block may still be applied meanwhile (see `applied_state_in_block`).
     async fn state_in_block(
         &self,
         leader_term: LeaderTerm,
@@ -51,11 +114,29 @@ impl Follower {
     ) -> Result<MutexGuard<'_, State>, Error> {
         self.synchronize_if_needed(leader_term, block_number)
             .await?;
+        Ok(self.state_at(block_number).await)
+    }
 
+    /// Wait until we reached the block number the message is at and the previous block is applied.
+    ///
+    /// Only the stateful validation of the transactions needs the `WorldState` of the block.
+    async fn applied_state_in_block(
+        &self,
+        leader_term: LeaderTerm,
+        block_number: BlockNumber,
+    ) -> Result<MutexGuard<'_, State>, Error> {
+        self.synchronize_if_needed(leader_term, block_number)
+            .await?;
+        self.wait_applied(block_number).await?;
+        Ok(self.state_at(block_number).await)
+    }
+
+    /// Lock the state as soon as it reached the `block_number`.
+    async fn state_at(&self, block_number: BlockNumber) -> MutexGuard<'_, State> {
         loop {
             let mut state = self.state.lock().await;
             if state.block_number >= block_number {
-                break Ok(state);
+                return state;
             }
             let wait = state.block_changed.wait(block_number);
             drop(state);
@@ -74,15 +155,26 @@ impl Follower {
 
         log::trace!("Handle Prepare message #{}.", message.block_number);
 
-        // Check whether the state for the block is Waiting.
+        // The Append or Commit message may have arrived first.
         // We only allow to receive messages once.
-        state.phase().verify(Phase::Waiting)?;
+        state.round.receive(RoundMessage::Prepare)?;
         message.leader_term.verify(state.leader_term)?;
         state.verify_leader(&peer_id)?;
         message.block_number.verify(state.block_number)?;
+        if let Some(expected_block_hash) = state.block_hash {
+            if message.block_hash != expected_block_hash {
+                return Err(Error::ChangedBlockHash);
+            }
+        }
+
+        // A block prepared in an earlier leader term must be re-proposed first.
+        self.view_change
+            .request_view_change_on_error(async { state.verify_reproposal(message.block_hash) })
+            .await?;
 
         // All checks passed, update our state.
         state.prepare(message.block_hash);
+        state.round.handled(RoundMessage::Prepare);
 
         // Send AckPrepare to the leader.
         // *Note*: Technically, we only need to send a signature of
@@ -92,19 +184,18 @@ impl Follower {
         })
     }
 
-    pub async fn handle_append_message(
+    pub async fn handle_append_chunk_message(
         &self,
         peer_id: PeerId,
-        message: message::Append,
-    ) -> Result<response::AckAppend, Error> {
-        let mut state = self
+        message: message::AppendChunk,
+    ) -> Result<response::Ok, Error> {
+        let state = self
             .state_in_block(message.leader_term, message.block_number)
             .await?;
 
-        log::trace!("Handle Append message #{}.", message.block_number);
+        log::trace!("Handle AppendChunk message #{}.", message.block_number);
 
-        // Check whether the state for the block is Prepare.
-        // We only allow to receive messages once.
+        // Chunks are only accepted before the Append message.
         match state.phase() {
             Phase::Waiting | Phase::Prepare => {}
             phase => return Err(phase.error(Phase::Prepare)),
@@ -113,18 +204,81 @@ impl Follower {
         message.leader_term.verify(state.leader_term)?;
         state.verify_leader(&peer_id)?;
         message.block_number.verify(state.block_number)?;
+        if let Some(expected_block_hash) = state.block_hash {
+            if message.block_hash != expected_block_hash {
+                return Err(Error::ChangedBlockHash);
+            }
+        }
+        drop(state);
+
+        self.append_chunks
+            .lock()
+            .unwrap()
+            .insert(message.metadata, message.transactions)?;
+
+        Ok(response::Ok)
+    }
+
+    pub async fn handle_append_message(
+        &self,
+        peer_id: PeerId,
+        mut message: message::Append,
+    ) -> Result<response::AckAppend, Error> {
+        let mut state = self
+            .applied_state_in_block(message.leader_term, message.block_number)
+            .await?;
+
+        log::trace!("Handle Append message #{}.", message.block_number);
+
+        // The Prepare message may arrive later.
+        // We only allow to receive messages once.
+        state.round.receive(RoundMessage::Append)?;
+
+        message.leader_term.verify(state.leader_term)?;
+        state.verify_leader(&peer_id)?;
+        message.block_number.verify(state.block_number)?;
+
+        // The transactions of a large block were sent in chunks before.
+        if !message.chunk_hashes.is_empty() {
+            message.valid_transactions = self
+                .append_chunks
+                .lock()
+                .unwrap()
+                .take(&message.metadata, &message.chunk_hashes)?;
+            message.chunk_hashes.clear();
+            message.verify_invariants()?;
+        }
 
         let metadata = message.metadata.clone();
-        let (body, invalid_transactions) = self
+        let (body, invalid_transactions, ackprepare_signatures) = self
             .view_change
             .request_view_change_on_error(async {
-                // Validate the Block Hash.
+                // A block prepared in an earlier leader term must be re-proposed first.
                 let block_hash = message.block_hash;
+                state.verify_reproposal(block_hash)?;
+
+                // Validate the Block Hash.
                 let body = state.body_with(message.valid_transactions, message.timestamp);
+                verify_body_leader_term(&body, &message.metadata)?;
                 if body.hash() != block_hash {
                     return Err(Error::BlockNotMatchingHash);
                 }
 
+                // The leader's clock must be reasonably in sync.
+                // (A re-proposed block keeps the timestamp of the earlier leader term.)
+                if state.prepared_block.is_none() {
+                    let tolerance = self
+                        .world_state
+                        .get()
+                        .parameter_duration(Parameter::TimestampTolerance);
+                    self.clock_skews.verify_timestamp(
+                        &peer_id,
+                        body.timestamp,
+                        self.clock.now(),
+                        tolerance,
+                    )?;
+                }
+
                 if let Some(expected_block_hash) = state.block_hash {
                     if block_hash != expected_block_hash {
                         return Err(Error::ChangedBlockHash);
@@ -146,19 +300,49 @@ impl Follower {
                     return Err(Error::EmptyBlock);
                 }
 
+                // Each transaction must only be applied once.
+                verify_unique_transactions(&body.transactions)?;
+
+                // The block must respect the governed limit (the hard limit is checked earlier).
+                let number_of_transactions =
+                    body.transactions.len() + message.invalid_transactions.len();
+                let max_transactions = self
+                    .world_state
+                    .get()
+                    .parameter_count(Parameter::MaxTransactionsPerBlock);
+                if number_of_transactions > max_transactions {
+                    return Err(Error::TooManyTransactions(number_of_transactions));
+                }
+
                 // Check for transaction validity.
-                self.stateful_validate(&body.transactions, &message.invalid_transactions)?;
+                self.stateful_validate(
+                    &body.transactions,
+                    &message.invalid_transactions,
+                    body.timestamp,
+                )?;
 
-                Ok((body, message.invalid_transactions))
+                Ok((
+                    body,
+                    message.invalid_transactions,
+                    message.ackprepare_signatures,
+                ))
             })
             .await?;
 
         // All checks passed, update our state.
+        self.view_change.prepared(PreparedBlock {
+            metadata: metadata.clone(),
+            ackprepare_signatures,
+            body: body.clone(),
+        });
         state.append(body, invalid_transactions);
 
         // There could be a commit message for this block number that arrived first.
         // We then need to apply the commit (or at least check).
-        if let Some(commit_message) = state.buffered_commit_message.take() {
+        let buffered = state.round.handled(RoundMessage::Append);
+        if let (Some(RoundMessage::Commit), Some(commit_message)) =
+            (buffered, state.buffered_commit_message.take())
+        {
             drop(state);
             match self.handle_commit_message(peer_id, commit_message).await {
                 Ok(_) => log::debug!("Used out-of-order commit."),
@@ -174,9 +358,7 @@ impl Follower {
         peer_id: PeerId,
         message: message::Commit,
     ) -> Result<response::Ok, Error> {
-        let mut state = self
-            .state_in_block(message.leader_term, message.block_number)
-            .await?;
+        let mut state = self.state_in_block_of_commit(&message).await?;
 
         log::trace!("Handle Commit message #{}.", message.block_number);
 
@@ -190,10 +372,10 @@ impl Follower {
             }
         }
 
-        // Check whether the state for the block is Append.
+        // The commit needs the block body, so it is buffered until the Append message arrived.
         // We only allow to receive messages once.
-        match state.phase() {
-            Phase::Waiting | Phase::Prepare if state.buffered_commit_message.is_none() => {
+        match state.round.receive(RoundMessage::Commit)? {
+            RoundStep::Buffer => {
                 log::trace!(
                     "Received out-of-order commit message #{}.",
                     message.block_number
@@ -203,14 +385,18 @@ impl Follower {
                 state.buffered_commit_message = Some(message);
                 return Ok(response::Ok);
             }
-            Phase::Append => {
+            RoundStep::Handle => {
                 state.block_hash = Some(message.block_hash);
             }
-            phase => return Err(phase.error(Phase::Append)),
         }
 
         self.view_change
             .request_view_change_on_error(async {
+                // The block is stored with the leader term its signatures are over.
+                if let Some((body, _)) = &state.block_content {
+                    verify_body_leader_term(body, &message.metadata)?;
+                }
+
                 // Check validity of ACKAPPEND Signatures.
                 self.verify_rpu_majority_signatures(
                     response::AckAppend {
@@ -224,11 +410,20 @@ impl Follower {
             .await?;
 
         // Write Block to WorldState
-        state.commit(message.ackappend_signatures).await;
+        state.commit(message.ackappend_signatures).await?;
 
         Ok(response::Ok)
     }
 
+    /// Check whether a `Commit` message for `block_number` waits for its `Append` message.
+    pub async fn is_commit_buffered(&self, block_number: BlockNumber) -> bool {
+        let state = self.state.lock().await;
+        state
+            .buffered_commit_message
+            .as_ref()
+            .map_or(false, |message| message.block_number == block_number)
+    }
+
     pub async fn handle_new_view_message(
         &self,
         peer_id: PeerId,
@@ -265,16 +460,27 @@ impl Follower {
         self.verify_rpu_majority_signatures(
             message::ViewChange {
                 new_leader_term: message.leader_term,
+                chain_id: self.chain_id,
+                prepared_block: None,
+                reason: None,
             },
             &message.view_change_signatures,
         )?;
 
+        if let Some(prepared_block) = &message.prepared_block {
+            self.verify_prepared_block(prepared_block)?;
+        }
+
         if ordering == Ordering::Less {
             drop(state);
 
             // If the leader is out of date, trigger a ViewChange Message.
             self.view_change
-                .request_view_change_in_leader_term(message.leader_term)
+                .request_view_change_in_leader_term(
+                    message.leader_term,
+                    ViewChangeReason::OutdatedLeader,
+                    "The new leader is out of date.",
+                )
                 .await;
         } else {
             // We are fine
@@ -301,10 +507,27 @@ impl Follower {
                 message.leader_term
             );
 
-            state.new_leader_term(message.leader_term, message.view_change_signatures);
+            state.new_leader_term(
+                message.leader_term,
+                message.view_change_signatures,
+                message.prepared_block,
+                self.view_change.reasons(message.leader_term),
+            );
 
             // The leader can start it's work.
             self.notify_leader.notify();
         }
     }
 }
+
+/// Verify that the `body` is in the leader term of the signed `metadata`.
+fn verify_body_leader_term(body: &Body, metadata: &Metadata) -> Result<(), Error> {
+    if body.leader_term == metadata.leader_term {
+        Ok(())
+    } else {
+        Err(Error::BodyLeaderTermMismatch {
+            body: body.leader_term,
+            signed: metadata.leader_term,
+        })
+    }
+}
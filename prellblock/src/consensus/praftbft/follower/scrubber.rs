@@ -0,0 +1,206 @@
+//! Periodic integrity checks of the `BlockStorage`.
+//!
+//! The scrubber re-reads all committed blocks from the newest to the oldest one.
+//! The newest block is verified by the hash in the `WorldState`, every other block
+//! by the previous block hash of its (already verified) successor.
+//! Corrupted blocks are downloaded from other RPUs (see `download_blocks`)
+//! and replace the bad copies on disk.
+
+use super::{Error, Follower, Interval};
+use crate::{
+    block_storage,
+    consensus::{Block, BlockHash, BlockNumber},
+};
+use prellblock_client_api::{ScrubReport, ScrubStatistics};
+use rand::seq::SliceRandom;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+/// The time between two scrubs.
+const SCRUB_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The number of blocks verified before the scrubber pauses for `SCRUB_PAUSE`.
+const SCRUB_CHUNK_SIZE: u64 = 256;
+
+/// The pause between two chunks, so the scrubber does not starve the consensus of disk reads.
+const SCRUB_PAUSE: Duration = Duration::from_millis(10);
+
+impl Follower {
+    /// Periodically scrub the `BlockStorage`.
+    pub async fn execute_scrubber(self: Arc<Self>) {
+        let mut interval = Interval::new(self.clock.clone(), SCRUB_INTERVAL);
+        loop {
+            interval.tick().await;
+            if self.verify_not_halted().is_err() {
+                continue;
+            }
+
+            match self.scrub().await {
+                Ok(report) => {
+                    if report.repaired.is_empty() && report.unrepaired.is_empty() {
+                        log::debug!(
+                            "Scrubbed {} blocks in {:?}.",
+                            report.verified_blocks,
+                            report.duration
+                        );
+                    } else {
+                        log::warn!(
+                            "Scrubbed {} blocks in {:?}: repaired blocks {:?}, unrepaired blocks {:?}.",
+                            report.verified_blocks,
+                            report.duration,
+                            report.repaired,
+                            report.unrepaired
+                        );
+                    }
+                    self.record_scrub(report);
+                }
+                Err(err) => log::error!("Scrubbing the block storage failed: {}", err),
+            }
+        }
+    }
+
+    /// Get the results of the scrubber.
+    pub fn scrub_statistics(&self) -> ScrubStatistics {
+        self.scrub_statistics.lock().unwrap().clone()
+    }
+
+    fn record_scrub(&self, report: ScrubReport) {
+        let mut statistics = self.scrub_statistics.lock().unwrap();
+        statistics.scrubs += 1;
+        statistics.verified_blocks += report.verified_blocks;
+        statistics.corrupted_blocks += (report.repaired.len() + report.unrepaired.len()) as u64;
+        statistics.repaired_blocks += report.repaired.len() as u64;
+        statistics.last_scrub = Some(report);
+    }
+
+    /// Verify all committed blocks and repair the corrupted ones.
+    async fn scrub(&self) -> Result<ScrubReport, Error> {
+        let started_at = self.clock.now();
+        let start = self.clock.instant();
+        let mut repaired = Vec::new();
+        let mut unrepaired = Vec::new();
+
+        let world_state = self.world_state.get();
+        let mut block_number = world_state.block_number;
+        // The expected hash of the block at `block_number - 1`.
+        // It is unknown below a block that could not be repaired.
+        let mut expected_hash = Some(world_state.last_block_hash);
+        drop(world_state);
+
+        let mut verified_blocks = 0;
+        while block_number > BlockNumber::default() {
+            block_number -= 1;
+            verified_blocks += 1;
+            if verified_blocks % SCRUB_CHUNK_SIZE == 0 {
+                self.clock.sleep(SCRUB_PAUSE).await;
+            }
+
+            let block = match self.block_storage.read(block_number..=block_number).next() {
+                Some(Ok(block)) if block.block_number() == block_number => Some(block),
+                Some(Ok(_)) | Some(Err(block_storage::Error::Encoding(_))) | None => None,
+                Some(Err(err)) => return Err(err.into()),
+            };
+
+            match (block, expected_hash) {
+                (Some(block), Some(hash)) if block.hash() == hash => {
+                    expected_hash = Some(block.body.prev_block_hash);
+                }
+                (Some(block), None) => {
+                    expected_hash = Some(block.body.prev_block_hash);
+                }
+                (_, Some(hash)) => {
+                    log::warn!("Block #{} is corrupted.", block_number);
+                    match self.repair_block(block_number, hash).await {
+                        Ok(block) => {
+                            log::info!("Repaired block #{}.", block_number);
+                            expected_hash = Some(block.body.prev_block_hash);
+                            repaired.push(block_number);
+                        }
+                        Err(err) => {
+                            log::error!("Could not repair block #{}: {}", block_number, err);
+                            expected_hash = None;
+                            unrepaired.push(block_number);
+                        }
+                    }
+                }
+                (None, None) => {
+                    log::error!("Block #{} is corrupted.", block_number);
+                    unrepaired.push(block_number);
+                }
+            }
+        }
+
+        Ok(ScrubReport {
+            started_at,
+            duration: self.clock.elapsed(start),
+            verified_blocks,
+            repaired,
+            unrepaired,
+        })
+    }
+
+    /// Download the block at `block_number` with the hash `expected_hash` from another RPU
+    /// and replace the stored copy.
+    async fn repair_block(
+        &self,
+        block_number: BlockNumber,
+        expected_hash: BlockHash,
+    ) -> Result<Block, Error> {
+        let own_peer_id = self.identity.id();
+        let mut peer_addresses: Vec<SocketAddr> = self
+            .epochs
+            .current()
+            .peers
+            .iter()
+            .filter(|(peer_id, _)| peer_id != own_peer_id && !self.ban_list.is_banned(peer_id))
+            .map(|(_, peer_address)| *peer_address)
+            .collect();
+        peer_addresses.shuffle(&mut rand::thread_rng());
+
+        for peer_address in peer_addresses {
+            let block = match self
+                .download_blocks(peer_address, block_number, block_number + 1)
+                .await
+            {
+                Ok(blocks) => blocks.into_iter().next(),
+                Err(err) => {
+                    log::debug!(
+                        "Could not download block #{} from {}: {}",
+                        block_number,
+                        peer_address,
+                        err
+                    );
+                    continue;
+                }
+            };
+            let block = match block {
+                Some(block)
+                    if block.block_number() == block_number && block.hash() == expected_hash =>
+                {
+                    block
+                }
+                _ => {
+                    log::debug!(
+                        "Block #{} downloaded from {} does not match.",
+                        block_number,
+                        peer_address
+                    );
+                    continue;
+                }
+            };
+
+            // The newest block could have been rolled back in the meantime.
+            let state = self.state.lock().await;
+            if block_number >= state.block_number
+                || (block_number + 1 == state.block_number
+                    && state.last_block_hash != expected_hash)
+            {
+                return Err(Error::BlockRepairFailed(block_number));
+            }
+            self.block_storage.replace_block(&block)?;
+            drop(state);
+            return Ok(block);
+        }
+
+        Err(Error::BlockRepairFailed(block_number))
+    }
+}
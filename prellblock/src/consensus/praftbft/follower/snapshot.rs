@@ -0,0 +1,386 @@
+//! Transfer of `WorldState` snapshots between RPUs.
+//!
+//! An RPU that is too far behind downloads the `WorldState` of another RPU
+//! (split into hashed chunks) instead of replaying all missing blocks.
+//! The missing blocks are still downloaded (as a stream) and stored, but not applied one by one.
+//!
+//! Only the anchor block of a snapshot is signed by a supermajority, the chunk hashes are not.
+//! A snapshot is therefore only downloaded if at least `f + 1` RPUs send the same manifest,
+//! so at least one non-faulty RPU vouches for it. To make the manifests comparable, all RPUs take
+//! their snapshots at the same block numbers (every `SNAPSHOT_INTERVAL` blocks, see `Applier`).
+
+use super::{super::message::Metadata, message, response, Error, Follower};
+use crate::{
+    consensus::{Block, BlockHash, BlockNumber},
+    world_state::WorldState,
+};
+use futures::future::join_all;
+use pinxit::PeerId;
+use rand::seq::SliceRandom;
+use std::{net::SocketAddr, sync::Arc};
+
+/// The number of blocks an RPU must be behind to synchronize with a snapshot.
+pub const SNAPSHOT_BLOCK_THRESHOLD: u64 = 1000;
+
+/// The size of a snapshot chunk in bytes.
+const SNAPSHOT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// A snapshot is taken whenever the block number of the `WorldState` is a multiple of this.
+///
+/// Until the next but one snapshot, interrupted downloads can be resumed.
+pub const SNAPSHOT_INTERVAL: u64 = 100;
+
+/// The number of attempts (one per RPU) to download a snapshot.
+const MAX_SNAPSHOT_ATTEMPTS: usize = 10;
+
+/// Whether a snapshot is taken of the `WorldState` at `block_number`.
+pub(super) fn is_snapshot_height(block_number: BlockNumber) -> bool {
+    block_number != BlockNumber::default() && u64::from(block_number) % SNAPSHOT_INTERVAL == 0
+}
+
+/// Select the newest snapshot manifest that was sent by at least `nonfaulty_count` RPUs.
+///
+/// Returns the manifest together with the addresses of the RPUs that sent it.
+/// Manifests sent by fewer RPUs (e.g. a tampered set of chunk hashes) are ignored.
+#[must_use]
+pub fn confirmed_snapshot_manifest(
+    manifests: Vec<(SocketAddr, response::SnapshotManifest)>,
+    nonfaulty_count: usize,
+) -> Option<(response::SnapshotManifest, Vec<SocketAddr>)> {
+    let mut groups: Vec<(BlockHash, response::SnapshotManifest, Vec<SocketAddr>)> = Vec::new();
+    for (peer_address, manifest) in manifests {
+        let anchor_hash = manifest.anchor.hash();
+        let group = groups.iter_mut().find(|(other_hash, other, _)| {
+            *other_hash == anchor_hash && other.chunk_hashes == manifest.chunk_hashes
+        });
+        match group {
+            Some((_, _, peer_addresses)) => {
+                if !peer_addresses.contains(&peer_address) {
+                    peer_addresses.push(peer_address);
+                }
+            }
+            None => groups.push((anchor_hash, manifest, vec![peer_address])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, _, peer_addresses)| peer_addresses.len() >= nonfaulty_count)
+        .max_by_key(|(_, manifest, _)| manifest.anchor.block_number())
+        .map(|(_, manifest, peer_addresses)| (manifest, peer_addresses))
+}
+
+/// A serialized `WorldState` split into chunks.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// The last block applied to the `WorldState`.
+    anchor: Block,
+    data: Vec<u8>,
+    chunk_hashes: Vec<BlockHash>,
+}
+
+impl Snapshot {
+    pub(super) fn new(anchor: Block, world_state: &WorldState) -> Result<Self, Error> {
+        let data = postcard::to_stdvec(world_state)?;
+        let chunk_hashes = data
+            .chunks(SNAPSHOT_CHUNK_SIZE)
+            .map(BlockHash::of)
+            .collect();
+        Ok(Self {
+            anchor,
+            data,
+            chunk_hashes,
+        })
+    }
+
+    fn manifest(&self) -> response::SnapshotManifest {
+        response::SnapshotManifest {
+            anchor: self.anchor.clone(),
+            chunk_hashes: self.chunk_hashes.clone(),
+        }
+    }
+
+    fn chunk(&self, index: usize) -> Option<&[u8]> {
+        self.data.chunks(SNAPSHOT_CHUNK_SIZE).nth(index)
+    }
+}
+
+/// The snapshots served to RPUs that are far behind.
+///
+/// The previous snapshot is kept, so downloads started before the newest snapshot was taken can finish.
+#[derive(Debug, Default)]
+pub struct Snapshots {
+    newest: Option<Arc<Snapshot>>,
+    previous: Option<Arc<Snapshot>>,
+}
+
+impl Snapshots {
+    /// Get the newest snapshot.
+    pub fn newest(&self) -> Option<Arc<Snapshot>> {
+        self.newest.clone()
+    }
+
+    /// Find the snapshot taken at the block with the `anchor_hash`.
+    pub fn find(&self, anchor_hash: &BlockHash) -> Option<Arc<Snapshot>> {
+        self.newest
+            .iter()
+            .chain(&self.previous)
+            .find(|snapshot| snapshot.anchor.hash() == *anchor_hash)
+            .cloned()
+    }
+
+    /// Add a newer `snapshot`, replacing the previous one.
+    pub fn push(&mut self, snapshot: Snapshot) -> Arc<Snapshot> {
+        let snapshot = Arc::new(snapshot);
+        self.previous = self.newest.replace(snapshot.clone());
+        snapshot
+    }
+}
+
+/// The progress of downloading a snapshot.
+///
+/// Downloaded parts are kept, so the download can be resumed from another RPU after a connection loss.
+struct SnapshotDownload {
+    manifest: response::SnapshotManifest,
+    chunks: Vec<Option<Vec<u8>>>,
+    /// The blocks leading to the snapshot in descending order (starting with the anchor).
+    ///
+    /// Each block is verified by its (already verified) successor.
+    blocks: Vec<Block>,
+}
+
+impl SnapshotDownload {
+    fn new(manifest: response::SnapshotManifest) -> Self {
+        Self {
+            chunks: vec![None; manifest.chunk_hashes.len()],
+            blocks: vec![manifest.anchor.clone()],
+            manifest,
+        }
+    }
+
+    /// The lowest block downloaded so far.
+    fn lowest_block(&self) -> &Block {
+        self.blocks.last().unwrap()
+    }
+}
+
+impl Follower {
+    /// Synchronize by downloading a snapshot of the `WorldState` from other RPUs.
+    pub(super) async fn synchronize_with_snapshot(&self) -> Result<(), Error> {
+        let own_peer_id = self.identity.id();
+        let epoch = self.epochs.current();
+        let block_number = self.state.lock().await.block_number;
+
+        let requests = epoch
+            .peers
+            .iter()
+            .filter(|(peer_id, _)| peer_id != own_peer_id && !self.ban_list.is_banned(peer_id))
+            .map(|(_, peer_address)| async move {
+                let manifest = self
+                    .send_message(*peer_address, message::SnapshotRequest)
+                    .await?
+                    .into_inner();
+                self.verify_snapshot_manifest(&manifest, block_number)?;
+                Ok::<_, Error>((*peer_address, manifest))
+            });
+        let manifests = join_all(requests)
+            .await
+            .into_iter()
+            .filter_map(|result| match result {
+                Ok(manifest) => Some(manifest),
+                Err(err) => {
+                    log::debug!("Could not get a snapshot manifest: {}", err);
+                    None
+                }
+            })
+            .collect();
+
+        let (manifest, mut peer_addresses) =
+            confirmed_snapshot_manifest(manifests, epoch.nonfaulty_count()?)
+                .ok_or(Error::SnapshotNotConfirmed)?;
+        peer_addresses.shuffle(&mut rand::thread_rng());
+        log::debug!(
+            "Downloading snapshot at block #{} (confirmed by {} RPUs).",
+            manifest.anchor.block_number(),
+            peer_addresses.len()
+        );
+
+        let mut download = SnapshotDownload::new(manifest);
+        for peer_address in peer_addresses.iter().cycle().take(MAX_SNAPSHOT_ATTEMPTS) {
+            match self.download_snapshot(&mut download, *peer_address).await {
+                Ok(()) => return self.install_snapshot(download).await,
+                Err(err) => log::warn!(
+                    "Snapshot download from {} interrupted: {}",
+                    peer_address,
+                    err
+                ),
+            }
+        }
+
+        Err(Error::SnapshotDownloadFailed)
+    }
+
+    /// Download all missing parts of a snapshot from the RPU at `peer_address`.
+    async fn download_snapshot(
+        &self,
+        download: &mut SnapshotDownload,
+        peer_address: SocketAddr,
+    ) -> Result<(), Error> {
+        let block_number = self.state.lock().await.block_number;
+
+        let anchor_hash = download.manifest.anchor.hash();
+        for index in 0..download.chunks.len() {
+            if download.chunks[index].is_some() {
+                continue;
+            }
+
+            let request = message::SnapshotChunkRequest { anchor_hash, index };
+            let chunk = self.send_message(peer_address, request).await?.into_inner();
+            if BlockHash::of(&chunk.data) != download.manifest.chunk_hashes[index] {
+                return Err(Error::SnapshotChunkHashMismatch(index));
+            }
+            download.chunks[index] = Some(chunk.data);
+        }
+
+        let to = download.lowest_block().block_number();
+        if to > block_number {
+            let blocks = self.download_blocks(peer_address, block_number, to).await?;
+            for block in blocks.into_iter().rev() {
+                let successor = download.lowest_block();
+                // The blocks are sent by another RPU, so their numbers must not overflow.
+                if block.block_number().checked_add(1) != Some(successor.block_number())
+                    || block.hash() != successor.body.prev_block_hash
+                {
+                    return Err(Error::SnapshotDoesNotMatch);
+                }
+                download.blocks.push(block);
+            }
+
+            if download.lowest_block().block_number() != block_number {
+                return Err(Error::SnapshotDoesNotMatch);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Download the blocks from `from` up to (excluding) `to` as a stream from the RPU at `peer_address`.
+    pub(super) async fn download_blocks(
+        &self,
+        peer_address: SocketAddr,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<Block>, Error> {
+        let blocks = self.network.download_blocks(peer_address, from, to).await?;
+        log::trace!("Downloaded {} blocks from {}.", blocks.len(), peer_address);
+        Ok(blocks)
+    }
+
+    /// Verify that the `manifest` is anchored in a committed block after `block_number`.
+    fn verify_snapshot_manifest(
+        &self,
+        manifest: &response::SnapshotManifest,
+        block_number: BlockNumber,
+    ) -> Result<(), Error> {
+        let anchor = &manifest.anchor;
+        if anchor.block_number() < block_number {
+            return Err(Error::SnapshotDoesNotMatch);
+        }
+
+        self.verify_rpu_majority_signatures(
+            response::AckAppend {
+                metadata: Metadata {
+                    leader_term: anchor.body.leader_term,
+                    block_number: anchor.body.height,
+                    block_hash: anchor.hash(),
+                },
+            },
+            &anchor.signatures,
+        )
+    }
+
+    /// Install a completely downloaded snapshot.
+    async fn install_snapshot(&self, download: SnapshotDownload) -> Result<(), Error> {
+        let anchor = download.manifest.anchor;
+        let data: Vec<u8> = download.chunks.into_iter().flatten().flatten().collect();
+        let world_state: WorldState = postcard::from_bytes(&data)?;
+        if anchor.block_number().checked_add(1) != Some(world_state.block_number)
+            || world_state.last_block_hash != anchor.hash()
+            || world_state.last_leader_term != anchor.body.leader_term
+        {
+            return Err(Error::SnapshotDoesNotMatch);
+        }
+
+        let mut state = self.state.lock().await;
+
+        // Blocks could have been committed while downloading.
+        let mut blocks = download.blocks;
+        blocks.retain(|block| block.block_number() >= state.block_number);
+        blocks.reverse();
+        match blocks.first() {
+            Some(first_block) if first_block.body.prev_block_hash == state.last_block_hash => {}
+            Some(_) => return Err(Error::SnapshotDoesNotMatch),
+            None => return Ok(()),
+        }
+
+        log::info!(
+            "Installing snapshot at block #{} ({} blocks skipped).",
+            anchor.block_number(),
+            blocks.len()
+        );
+        state.install_snapshot(blocks, world_state).await
+    }
+
+    /// Handle a request for the manifest of the current snapshot.
+    pub fn handle_snapshot_request(
+        &self,
+        peer_id: &PeerId,
+    ) -> Result<response::SnapshotManifest, Error> {
+        log::debug!("Request by {} for a snapshot.", peer_id);
+        Ok(self.current_snapshot()?.manifest())
+    }
+
+    /// Handle a request for a chunk of the current snapshot.
+    pub fn handle_snapshot_chunk_request(
+        &self,
+        message: &message::SnapshotChunkRequest,
+    ) -> Result<response::SnapshotChunk, Error> {
+        let snapshot = self.applier.snapshots().find(&message.anchor_hash);
+        let chunk = snapshot
+            .as_ref()
+            .and_then(|snapshot| snapshot.chunk(message.index))
+            .ok_or(Error::SnapshotNotAvailable(message.anchor_hash))?;
+        Ok(response::SnapshotChunk {
+            data: chunk.to_vec(),
+        })
+    }
+
+    /// Get the newest snapshot.
+    ///
+    /// Snapshots are taken by the `Applier`. After a restart, there is none until the next one
+    /// is taken, unless the `WorldState` is at a snapshot height.
+    fn current_snapshot(&self) -> Result<Arc<Snapshot>, Error> {
+        let mut snapshots = self.applier.snapshots();
+        if let Some(snapshot) = snapshots.newest() {
+            return Ok(snapshot);
+        }
+
+        let world_state = self.world_state.get();
+        if !is_snapshot_height(world_state.block_number) {
+            return Err(Error::SnapshotNotAvailable(world_state.last_block_hash));
+        }
+        let anchor_number = world_state.block_number - 1;
+        let anchor = self
+            .block_storage
+            .read(anchor_number..=anchor_number)
+            .next()
+            .transpose()?;
+        let anchor = match anchor {
+            Some(anchor) if anchor.hash() == world_state.last_block_hash => anchor,
+            _ => return Err(Error::SnapshotNotAvailable(world_state.last_block_hash)),
+        };
+
+        log::debug!("Creating snapshot at block #{}.", anchor_number);
+        Ok(snapshots.push(Snapshot::new(anchor, &world_state)?))
+    }
+}
@@ -1,17 +1,29 @@
-use super::{message, Core, Error, InvalidTransaction, NotifyMap};
-use crate::consensus::{Block, BlockHash, BlockNumber, Body, LeaderTerm, SignatureList};
+use super::{message, Applier, Core, Error, InvalidTransaction, NotifyMap, PreparedBlock, Round};
+use crate::{
+    consensus::{Block, BlockHash, BlockNumber, Body, LeaderTerm, SignatureList},
+    event_bus::Event,
+    world_state::WorldState,
+};
 use pinxit::{PeerId, Signed};
-use prellblock_client_api::Transaction;
-use std::{ops::Deref, sync::Arc, time::SystemTime};
+use prellblock_client_api::{
+    consensus::transaction_hash, TraceEventKind, Transaction, ViewChangeReason,
+};
+use std::{collections::BTreeMap, ops::Deref, sync::Arc, time::SystemTime};
 
 #[derive(Debug)]
 pub struct State {
     pub core: Arc<Core>,
+    /// Applies the committed blocks to the `BlockStorage` and the `WorldState`.
+    pub applier: Applier,
 
     /// The current leader term.
     pub leader_term: LeaderTerm,
     /// The signatures from the `NewView` message.
     pub new_view_signatures: SignatureList,
+    /// The prepared block from the `NewView` message.
+    ///
+    /// The leader has to re-propose it in the current round.
+    pub prepared_block: Option<PreparedBlock>,
 
     /// A notifier to notify taks once we reached a given block number.
     pub block_changed: NotifyMap<BlockNumber>,
@@ -26,6 +38,8 @@ pub struct State {
     /// Wheter an rollback is currently allowed (only once after a leader change)
     pub rollback_possible: bool,
 
+    /// The phase messages handled in the current round.
+    pub round: Round,
     /// An out-of-order commit message. (Set in prepare phase during handle commit)
     pub buffered_commit_message: Option<message::Commit>,
 }
@@ -45,18 +59,21 @@ pub enum Phase {
 }
 
 impl State {
-    pub fn new(core: Arc<Core>) -> Self {
+    pub fn new(core: Arc<Core>, applier: Applier) -> Self {
         let world_state = core.world_state.get();
         Self {
             core,
+            applier,
             leader_term: LeaderTerm::default(),
             new_view_signatures: SignatureList::default(),
+            prepared_block: None,
             block_changed: NotifyMap::default(),
             block_number: world_state.block_number,
             last_block_hash: world_state.last_block_hash,
             block_hash: None,
             block_content: None,
             rollback_possible: world_state.block_number > BlockNumber::default(),
+            round: Round::default(),
             buffered_commit_message: None,
         }
     }
@@ -80,7 +97,22 @@ impl State {
         }
     }
 
-    /// Create a body with the given `transactions`.
+    /// Verify that the leader re-proposes the prepared block (if there is one).
+    ///
+    /// The re-proposed block is moved to the current leader term (like the signatures for it).
+    pub fn verify_reproposal(&self, block_hash: BlockHash) -> Result<(), Error> {
+        if let Some(prepared_block) = &self.prepared_block {
+            let mut body = prepared_block.body.clone();
+            body.leader_term = self.leader_term;
+            let expected_block_hash = body.hash();
+            if block_hash != expected_block_hash {
+                return Err(Error::PreparedBlockNotReproposed(expected_block_hash));
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a body with the given `transactions` in the current leader term.
     pub fn body_with(&self, transactions: Vec<Signed<Transaction>>, timestamp: SystemTime) -> Body {
         Body {
             leader_term: self.leader_term,
@@ -99,11 +131,13 @@ impl State {
         }
     }
 
-    /// Move to the prepare phase.
+    /// Record the `block_hash` of a `Prepare` message.
+    ///
+    /// The `Append` or a buffered `Commit` message may have arrived first.
     ///
-    /// Panics if not in waiting phase.
+    /// Panics if a different block hash is known.
     pub fn prepare(&mut self, block_hash: BlockHash) {
-        assert_eq!(self.phase(), Phase::Waiting);
+        assert!(self.block_hash.map_or(true, |hash| hash == block_hash));
         self.block_hash = Some(block_hash);
     }
 
@@ -112,20 +146,27 @@ impl State {
     /// Panics if not in prepare phase.
     pub fn append(&mut self, body: Body, invalid_transactions: Vec<InvalidTransaction>) {
         assert_eq!(self.phase(), Phase::Prepare);
+        self.traces.record(
+            body.transactions.iter().map(transaction_hash),
+            &TraceEventKind::Proposed {
+                leader_term: body.leader_term,
+                block_number: body.height,
+            },
+        );
         self.block_content = Some((body, invalid_transactions))
     }
 
     /// Commit a block using a list of ackappend `signatures`.
     ///
     /// Panics if not in append phase.
-    pub async fn commit(&mut self, ackappend_signatures: SignatureList) {
+    pub async fn commit(&mut self, ackappend_signatures: SignatureList) -> Result<(), Error> {
         // Unwrap of `block_hash` and `block_content` should be safe
         // because we assert being in the Append phase.
         assert_eq!(self.phase(), Phase::Append);
         assert!(self.buffered_commit_message.is_none());
 
         let block = self.block_with(ackappend_signatures);
-        let block_hash = self.block_hash.take().unwrap();
+        let block_hash = self.block_hash.unwrap();
 
         // We are sure that these transactions are really invalid and therefore
         // they can be removed from the queue without losing good transactions.
@@ -135,55 +176,140 @@ impl State {
                 "Removing invalid transactions from queue: {:#?}",
                 invalid_transactions
             );
-            self.queue
-                .lock()
-                .await
-                .remove_all(invalid_transactions.iter().map(|(_, tx)| tx));
+            self.remove_from_queue(invalid_transactions.iter().map(|(_, tx)| tx))
+                .await;
+            self.traces.record(
+                invalid_transactions
+                    .iter()
+                    .map(|(_, tx)| transaction_hash(tx)),
+                &TraceEventKind::Rejected {
+                    block_number: self.block_number,
+                },
+            );
         }
 
         // Must be called at last because it resets the state.
-        self.apply_block(block_hash, block).await;
+        self.apply_block(block_hash, block).await
     }
 
     /// Applies a given block to the state.
     ///
+    /// Only the committed frontier is advanced here, the block is written to the
+    /// `BlockStorage` and the `WorldState` by the `Applier` in the background
+    /// (which also removes the committed transactions from the queue).
+    /// Failing to store a committed block is fatal and halts the RPU.
+    ///
     /// Panics if the block does not match the current block number.
-    pub async fn apply_block(&mut self, block_hash: BlockHash, block: Block) {
+    pub async fn apply_block(&mut self, block_hash: BlockHash, block: Block) -> Result<(), Error> {
         assert_eq!(block.block_number(), self.block_number);
 
-        // Remove committed transactions from our queue.
-        self.queue
-            .lock()
-            .await
-            .remove_all(block.body.transactions.iter());
-
         // Applies block.
-        self.transaction_applier.apply_block(block).await;
+        self.applier.apply(block)?;
 
         // Setup next round.
         self.block_number += 1;
         self.last_block_hash = block_hash;
+        self.block_hash = None;
         self.block_content = None;
+        self.prepared_block = None;
         // No rollback possible after one commit.
         self.rollback_possible = false;
 
+        self.round = Round::default();
+        self.buffered_commit_message = None;
+
+        // Notify waiting tasks
+        self.block_changed.notify_all(&self.block_number);
+
+        Ok(())
+    }
+
+    /// Install a `WorldState` snapshot together with the `blocks` leading to it.
+    ///
+    /// The blocks are only stored, they are not applied to the `WorldState`.
+    /// Failing to install the snapshot is fatal and halts the RPU.
+    pub async fn install_snapshot(
+        &mut self,
+        blocks: Vec<Block>,
+        world_state: WorldState,
+    ) -> Result<(), Error> {
+        // The snapshot replaces the `WorldState`, so all committed blocks must be applied first.
+        self.applier.wait_applied(self.block_number).await?;
+
+        let block_number = world_state.block_number;
+        let last_block_hash = world_state.last_block_hash;
+        if let Err(err) = self
+            .transaction_applier
+            .install_snapshot(&blocks, world_state)
+            .await
+        {
+            return Err(self.fatal_error(err.into()));
+        }
+        // The transactions are only removed once their blocks are written.
+        self.remove_from_queue(
+            blocks
+                .iter()
+                .flat_map(|block| block.body.transactions.iter()),
+        )
+        .await;
+        for block in &blocks {
+            self.traces.record(
+                block.body.transactions.iter().map(transaction_hash),
+                &TraceEventKind::Committed {
+                    block_number: block.block_number(),
+                },
+            );
+        }
+        self.core.update_epoch();
+
+        // Setup next round.
+        self.block_number = block_number;
+        self.last_block_hash = last_block_hash;
+        self.block_hash = None;
+        self.block_content = None;
+        self.prepared_block = None;
+        // There is no previous `WorldState` to roll back to.
+        self.rollback_possible = false;
+
+        self.round = Round::default();
         self.buffered_commit_message = None;
 
         // Notify waiting tasks
         self.block_changed.notify_all(&self.block_number);
+
+        Ok(())
     }
 
     /// Set a new `leader_term`.
-    pub fn new_leader_term(&mut self, leader_term: LeaderTerm, new_view_signatures: SignatureList) {
+    ///
+    /// The leader has to re-propose the `prepared_block` if it is not committed yet.
+    /// The `view_change_reasons` are recorded in the history of leader terms.
+    pub fn new_leader_term(
+        &mut self,
+        leader_term: LeaderTerm,
+        new_view_signatures: SignatureList,
+        prepared_block: Option<PreparedBlock>,
+        view_change_reasons: BTreeMap<ViewChangeReason, u32>,
+    ) {
         self.leader_term = leader_term;
         self.new_view_signatures = new_view_signatures;
 
         self.block_hash = None;
         self.block_content = None;
+        let block_number = self.block_number;
+        self.prepared_block =
+            prepared_block.filter(|prepared_block| prepared_block.body.height == block_number);
         self.rollback_possible = true;
 
+        self.round = Round::default();
         self.buffered_commit_message = None;
 
+        self.record_leader_term(leader_term, block_number, view_change_reasons);
+        self.events.publish(Event::ViewChanged {
+            leader_term,
+            leader: self.leader(leader_term),
+        });
+
         // On view change, we need to drop all messages from the
         // old leader to allow the new one to send new messages.
         assert_eq!(self.phase(), Phase::Waiting);
@@ -191,17 +317,43 @@ impl State {
 
     /// Rollback the last commited block.
     ///
+    /// Failing to rollback is fatal and halts the RPU.
+    ///
     /// Panics if no rollback is possible
     /// or the rollback has an unexpected `block_number`.
-    pub async fn rollback(&mut self) {
+    pub async fn rollback(&mut self) -> Result<(), Error> {
         assert!(self.rollback_possible);
 
-        // BlockStorage remove topmost block.
-        // Double Unwrap should be fine because there needs to be some block.
-        let last_block = self.block_storage.pop_block().unwrap().unwrap();
+        // The last block must be applied before it can be rolled back.
+        self.applier.wait_applied(self.block_number).await?;
+
+        // Remove the topmost block from the BlockStorage and the WorldState.
+        let last_block = match self.transaction_applier.rollback() {
+            Ok(last_block) => last_block,
+            Err(err) => return Err(self.fatal_error(err)),
+        };
+        self.core.update_epoch();
         assert_eq!(last_block.block_number() + 1, self.block_number);
 
+        // Reset State
+        self.block_number -= 1;
+        self.last_block_hash = last_block.body.prev_block_hash;
+        self.block_hash = None;
+        self.block_content = None;
+        self.prepared_block = None;
+        // better save than sorry
+        self.rollback_possible = false;
+
+        self.round = Round::default();
+        self.buffered_commit_message = None;
+
         // The transactions may not be lost.
+        self.traces.record(
+            last_block.body.transactions.iter().map(transaction_hash),
+            &TraceEventKind::RolledBack {
+                block_number: self.block_number,
+            },
+        );
         self.queue.lock().await.extend(last_block.body.transactions);
 
         // We ignore all invalid transactions during rolllback. They will be lost.
@@ -209,19 +361,6 @@ impl State {
         // If there are two peers left having these transactions in their queue, they
         // should be able to be elected as leader and propose the "lost" transactions.
 
-        // Rollback WorldState by one block.
-        self.world_state.rollback().unwrap();
-        let world_state = self.world_state.get();
-        self.block_number -= 1;
-        assert_eq!(world_state.block_number, self.block_number);
-
-        // Reset State
-        self.last_block_hash = world_state.last_block_hash;
-        self.block_hash = None;
-        self.block_content = None;
-        // better save than sorry
-        self.rollback_possible = false;
-
-        self.buffered_commit_message = None;
+        Ok(())
     }
 }
@@ -1,6 +1,7 @@
 use super::{Error, Follower, InvalidTransaction};
 use pinxit::{verify_signed_batch_iter, Signed};
 use prellblock_client_api::Transaction;
+use std::time::SystemTime;
 
 impl Follower {
     /// Stateful validate transactions sent by the leader.
@@ -8,6 +9,7 @@ impl Follower {
         &self,
         valid_transactions: &[Signed<Transaction>],
         invalid_transactions: &[InvalidTransaction],
+        timestamp: SystemTime,
     ) -> Result<(), Error> {
         let number_of_valid_transactions = valid_transactions.len();
         let mut valid_transactions = verify_signed_batch_iter(valid_transactions.iter())?;
@@ -23,7 +25,9 @@ impl Follower {
             .map(|(index, _)| index)
             .zip(verify_signed_batch_iter(invalid_transactions_iter)?);
 
+        // The quotas are counted at the timestamp of the block.
         let mut check = self.transaction_checker.check();
+        check.set_block_timestamp(timestamp);
 
         let mut index = 0;
         loop {
@@ -1,12 +1,33 @@
-use super::{super::message::Metadata, message, response, Error, ErrorVerify, Follower, State};
-use crate::consensus::{Block, BlockNumber, LeaderTerm};
-use pinxit::PeerId;
+use super::{
+    super::{
+        clock,
+        message::{BlockOutline, Metadata},
+        verify_leader_term, MAX_TRANSACTIONS_PER_BLOCK,
+    },
+    message, response,
+    snapshot::SNAPSHOT_BLOCK_THRESHOLD,
+    Error, ErrorVerify, Follower, State,
+};
+use crate::consensus::{Block, BlockNumber, LeaderTerm, SignatureList};
+use pinxit::{PeerId, Signed};
+use prellblock_client_api::{
+    consensus::{transaction_hash, TransactionHash},
+    Transaction,
+};
 use rand::Rng;
-use std::net::SocketAddr;
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    time::Duration,
+};
 use tokio::sync::{MutexGuard, SemaphorePermit};
 
 const SYNCHRONIZATION_BLOCK_THRESHOLD: u64 = 3;
 
+/// The time a `Commit` message waits for the blocks before its block
+/// until they are fetched from the RPUs that signed the commit.
+const GAP_TIMEOUT: Duration = Duration::from_secs(2);
+
 impl Follower {
     /// Synchronize if there is only one instance of synchronisation running.
     pub async fn synchronize_if_needed(
@@ -15,12 +36,22 @@ impl Follower {
         block_number: BlockNumber,
     ) -> Result<(), Error> {
         let synchronizer_permit = self.synchronizer_semaphore.acquire().await;
-        let state = self.state.lock().await;
+        let mut state = self.state.lock().await;
+
+        // Replaying all blocks would take too long, so we use a snapshot of another RPU.
+        if block_number >= state.block_number + SNAPSHOT_BLOCK_THRESHOLD {
+            drop(state);
+            if let Err(err) = self.synchronize_with_snapshot().await {
+                log::warn!("Synchronization with snapshot failed: {}", err);
+            }
+            state = self.state.lock().await;
+        }
 
         if self.is_synchronization_needed(&state, leader_term, block_number) {
             // choose peer to ask for synchronization randomly
             // but ensure, we're not sending the request to ourselves
-            let peers = self.world_state.get().peers;
+            let epoch = self.epochs.current();
+            let peers = &epoch.peers;
             assert_ne!(peers.len(), 1);
             let peer_address = loop {
                 let peer_index = rand::thread_rng().gen_range(0, peers.len());
@@ -41,6 +72,51 @@ impl Follower {
         Ok(())
     }
 
+    /// Wait until the block of a `Commit` message is the next block to commit.
+    ///
+    /// Gaps are too small to trigger a synchronization, so if the blocks before do not arrive
+    /// within the `GAP_TIMEOUT` (e.g. their messages were lost), they are fetched from the RPUs
+    /// that signed the commit: The signatures prove that a supermajority appended the block,
+    /// so these RPUs have all blocks before it. The blocks are applied in order.
+    pub(super) async fn state_in_block_of_commit(
+        &self,
+        message: &message::Commit,
+    ) -> Result<MutexGuard<'_, State>, Error> {
+        let wait = self.state_in_block(message.leader_term, message.block_number);
+        if let Some(state) = clock::timeout(&*self.clock, GAP_TIMEOUT, wait).await {
+            return state;
+        }
+
+        // Only a valid commit may trigger downloads.
+        self.verify_rpu_majority_signatures(
+            response::AckAppend {
+                metadata: message.metadata.clone(),
+            },
+            &message.ackappend_signatures,
+        )?;
+
+        for (peer_id, _) in &message.ackappend_signatures {
+            if peer_id == self.identity.id() {
+                continue;
+            }
+            log::warn!(
+                "Commit #{} is waiting for missing blocks, fetching them from {}.",
+                message.block_number,
+                peer_id
+            );
+            // Errors are logged by the synchronization, the next signer is tried then.
+            if let Ok(state) = self.synchronize_from(peer_id).await {
+                drop(state);
+            }
+
+            let wait = self.state_in_block(message.leader_term, message.block_number);
+            if let Some(state) = clock::timeout(&*self.clock, GAP_TIMEOUT, wait).await {
+                return state;
+            }
+        }
+        Err(Error::MissingBlocks(message.block_number))
+    }
+
     /// Check whether we need to synchronize to handle
     /// a request in a given `leader_term` and `block_number`.
     fn is_synchronization_needed(
@@ -57,8 +133,8 @@ impl Follower {
     pub async fn synchronize_from(&self, peer_id: &PeerId) -> Result<MutexGuard<'_, State>, Error> {
         let synchronizer_permit = self.synchronizer_semaphore.acquire().await;
         if let Some((_, peer_address)) = self
-            .world_state
-            .get()
+            .epochs
+            .current()
             .peers
             .iter()
             .find(|(pid, _)| pid == peer_id)
@@ -94,37 +170,51 @@ impl Follower {
             request
         );
 
-        // send request to peer
-        let response = self.send_message(peer_address, request).await?.into_inner();
+        // Queued transactions do not need to be downloaded again.
+        let (new_view, blocks) = if self.queue.lock().await.is_empty() {
+            let response = self.send_message(peer_address, request).await?.into_inner();
+            (response.new_view, response.blocks)
+        } else {
+            self.download_blocks_differential(peer_address, request)
+                .await?
+        };
 
         let mut state = self.state.lock().await;
-        if let Some((new_leader_term, view_change_signatures)) = response.new_view {
+        if let Some((new_leader_term, view_change_signatures)) = new_view {
             self.verify_rpu_majority_signatures(
-                message::ViewChange { new_leader_term },
+                message::ViewChange {
+                    new_leader_term,
+                    chain_id: self.chain_id,
+                    prepared_block: None,
+                    reason: None,
+                },
                 &view_change_signatures,
             )?;
-            state.new_leader_term(new_leader_term, view_change_signatures);
+            state.new_leader_term(
+                new_leader_term,
+                view_change_signatures,
+                None,
+                self.view_change.reasons(new_leader_term),
+            );
         }
 
-        if let Some(first_block) = response.blocks.first() {
+        if let Some(first_block) = blocks.first() {
             if state.rollback_possible
-                && first_block.block_number() + 1 == state.block_number
+                && state.block_number > BlockNumber::default()
+                && first_block.block_number() == state.block_number - 1
                 && first_block.hash() != state.last_block_hash
             {
                 // We had a chain split.
                 log::trace!("Doing rollback.");
-                state.rollback().await;
+                state.rollback().await?;
                 log::trace!("Done rollback.");
             }
         }
 
         // verify received blocks, if not timeouted
         // append correct blocks to own blockstorage
-        log::trace!(
-            "Received {} blocks while synchronizing.",
-            response.blocks.len()
-        );
-        for block in response.blocks {
+        log::trace!("Received {} blocks while synchronizing.", blocks.len());
+        for block in blocks {
             log::trace!("Applying synchronized block: {:#?}", block);
             if block.body.height < state.block_number {
                 continue;
@@ -138,8 +228,106 @@ impl Follower {
         Ok(state)
     }
 
+    /// Download the blocks missing for a synchronization `request`
+    /// without the transactions that are already queued.
+    ///
+    /// The downloaded transactions are verified by the block hashes when the blocks are applied.
+    async fn download_blocks_differential(
+        &self,
+        peer_address: SocketAddr,
+        request: message::SynchronizationRequest,
+    ) -> Result<(Option<(LeaderTerm, SignatureList)>, Vec<Block>), Error> {
+        let request = message::DifferentialSynchronizationRequest {
+            leader_term: request.leader_term,
+            block_number: request.block_number,
+            block_hash: request.block_hash,
+        };
+        let response = self.send_message(peer_address, request).await?.into_inner();
+
+        let hashes: HashSet<TransactionHash> = response
+            .blocks
+            .iter()
+            .flat_map(|outline| outline.transaction_hashes.iter().copied())
+            .collect();
+        let mut transactions = self.queued_transactions_with(&hashes).await;
+
+        let missing: Vec<(BlockNumber, usize)> = response
+            .blocks
+            .iter()
+            .flat_map(|outline| {
+                outline
+                    .transaction_hashes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, hash)| !transactions.contains_key(hash))
+                    .map(move |(index, _)| (outline.height, index))
+            })
+            .collect();
+        log::debug!(
+            "Downloading {} of {} transactions while synchronizing.",
+            missing.len(),
+            hashes.len()
+        );
+
+        for chunk in missing.chunks(MAX_TRANSACTIONS_PER_BLOCK) {
+            let request = message::TransactionRequest {
+                transactions: chunk.to_vec(),
+            };
+            let response = self.send_message(peer_address, request).await?.into_inner();
+            for transaction in response.transactions {
+                transactions.insert(transaction_hash(&transaction), transaction);
+            }
+        }
+
+        let blocks = response
+            .blocks
+            .into_iter()
+            .map(|outline| outline.into_block(&transactions))
+            .collect::<Result<_, _>>()?;
+        Ok((response.new_view, blocks))
+    }
+
+    /// Get the queued (or spilled) transactions with the given `hashes`.
+    async fn queued_transactions_with(
+        &self,
+        hashes: &HashSet<TransactionHash>,
+    ) -> HashMap<TransactionHash, Signed<Transaction>> {
+        let mut transactions: HashMap<_, _> = self
+            .queue
+            .lock()
+            .await
+            .iter()
+            .filter_map(|entry| {
+                let hash = transaction_hash(entry);
+                if hashes.contains(&hash) {
+                    Some((hash, (**entry).clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if let Some(queue_spill) = &self.queue_spill {
+            for hash in hashes {
+                if transactions.contains_key(hash) {
+                    continue;
+                }
+                match queue_spill.get(hash) {
+                    Ok(Some(transaction)) => {
+                        transactions.insert(*hash, transaction);
+                    }
+                    Ok(None) => {}
+                    Err(err) => log::warn!("Could not read spilled transaction {}: {}", hash, err),
+                }
+            }
+        }
+        transactions
+    }
+
     async fn apply_synchronized_block(&self, state: &mut State, block: Block) -> Result<(), Error> {
         block.body.height.verify(state.block_number)?;
+        // The block is verified against the epoch of the previous block.
+        self.wait_applied(state.block_number).await?;
 
         if block.body.prev_block_hash != state.last_block_hash {
             return Err(Error::PrevBlockHashDoesNotMatch(
@@ -147,6 +335,7 @@ impl Follower {
                 state.last_block_hash,
             ));
         }
+        verify_leader_term(&block, &self.world_state.get())?;
 
         // Verify block signatures
         let block_hash = block.hash();
@@ -170,9 +359,7 @@ impl Follower {
         self.transaction_checker.verify(data)?;
 
         // Persist the blocks after all checks have passed.
-        state.apply_block(block_hash, block).await;
-
-        Ok(())
+        state.apply_block(block_hash, block).await
     }
 
     pub async fn handle_synchronization_request(
@@ -202,27 +389,66 @@ impl Follower {
 
         // Only send the block `message.block_number`
         // if the first requested block's hash does not match the sent one.
-        let mut start_block_number = message.block_number;
-        if start_block_number > BlockNumber::default() {
-            start_block_number -= 1;
-        }
-        let mut blocks_iter = self
-            .block_storage
-            .read(start_block_number..=current_block_number);
-
-        let first_block = match blocks_iter.next() {
-            Some(Ok(first_block)) if message.block_hash != first_block.hash() => {
-                Some(Ok(first_block))
+        // Followers catching up mostly request recent blocks, so they are read through the cache.
+        let start_block_number = message.block_number.saturating_sub(1);
+        let mut blocks = Vec::new();
+        let mut block_number = start_block_number;
+        while block_number <= current_block_number {
+            let block = match self.block_storage.read_block(block_number)? {
+                Some(block) => block,
+                None => break,
+            };
+            if block_number != start_block_number || message.block_hash != block.hash() {
+                blocks.push(block);
             }
-            Some(Err(err)) => Some(Err(err)),
-            _ => None,
-        };
-        log::trace!("First block being sent to follower: {:#?}", first_block);
-        let blocks = first_block
-            .into_iter()
-            .chain(blocks_iter)
-            .collect::<Result<Vec<_>, _>>()?;
+            block_number += 1;
+        }
+        log::trace!("First block being sent to follower: {:#?}", blocks.first());
         log::trace!("Sending {} blocks to {}.", blocks.len(), peer_id);
         Ok(response::SynchronizationResponse { new_view, blocks })
     }
+
+    pub async fn handle_differential_synchronization_request(
+        &self,
+        peer_id: PeerId,
+        message: message::DifferentialSynchronizationRequest,
+    ) -> Result<response::DifferentialSynchronizationResponse, Error> {
+        let response = self
+            .handle_synchronization_request(
+                peer_id,
+                message::SynchronizationRequest {
+                    leader_term: message.leader_term,
+                    block_number: message.block_number,
+                    block_hash: message.block_hash,
+                },
+            )
+            .await?;
+        Ok(response::DifferentialSynchronizationResponse {
+            new_view: response.new_view,
+            blocks: response.blocks.iter().map(BlockOutline::new).collect(),
+        })
+    }
+
+    pub fn handle_transaction_request(
+        &self,
+        message: &message::TransactionRequest,
+    ) -> Result<response::TransactionResponse, Error> {
+        let mut block: Option<Block> = None;
+        let mut transactions = Vec::with_capacity(message.transactions.len());
+        for &(block_number, index) in &message.transactions {
+            // The transactions are requested in the order of the blocks.
+            if block.as_ref().map(Block::block_number) != Some(block_number) {
+                block = match self.block_storage.read_block(block_number)? {
+                    Some(block) => Some(block),
+                    None => return Err(Error::UnknownTransaction(block_number, index)),
+                };
+            }
+            let transaction = block
+                .as_ref()
+                .and_then(|block| block.body.transactions.get(index))
+                .ok_or(Error::UnknownTransaction(block_number, index))?;
+            transactions.push(transaction.clone());
+        }
+        Ok(response::TransactionResponse { transactions })
+    }
 }
@@ -0,0 +1,89 @@
+//! Detection of RPUs that were started with another genesis.
+//!
+//! After the start, each RPU asks all other RPUs of the current epoch for the chains they serve.
+//! An RPU that does not serve the chain was started with other genesis transactions
+//! (e.g. an outdated genesis file), so it can never take part in the consensus.
+//! Each such RPU is recorded as a consensus failure. If the remaining RPUs cannot reach a quorum,
+//! the RPU halts instead of waiting for a consensus that is impossible.
+
+use super::{clock::Interval, Core, Error};
+use pinxit::PeerId;
+use prellblock_client_api::ConsensusFailureKind;
+use std::{net::SocketAddr, ops::Deref, sync::Arc, time::Duration};
+
+/// The time between two attempts to reach the RPUs that did not answer yet.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Compares the chains of all other RPUs with the own chain once.
+pub struct GenesisCheck {
+    core: Arc<Core>,
+}
+
+impl Deref for GenesisCheck {
+    type Target = Core;
+    fn deref(&self) -> &Self::Target {
+        &self.core
+    }
+}
+
+impl GenesisCheck {
+    pub fn new(core: Arc<Core>) -> Self {
+        Self { core }
+    }
+
+    /// Execute the genesis check until every other RPU answered.
+    pub async fn execute(self) {
+        let own_peer_id = self.identity.id().clone();
+        let mut pending: Vec<(PeerId, SocketAddr)> = self
+            .epochs
+            .current()
+            .peers
+            .iter()
+            .filter(|(peer_id, _)| *peer_id != own_peer_id)
+            .cloned()
+            .collect();
+        let mut mismatched = Vec::new();
+
+        let mut interval = Interval::new(self.clock.clone(), RETRY_INTERVAL);
+        while !pending.is_empty() {
+            interval.tick().await;
+
+            let mut unanswered = Vec::new();
+            for (peer_id, peer_address) in pending {
+                match self.network.chain_ids(peer_address).await {
+                    Ok(chain_ids) if chain_ids.contains(&self.chain_id) => {
+                        log::trace!("{} serves the chain {}.", peer_id, self.chain_id);
+                    }
+                    Ok(_) => {
+                        let err = Error::GenesisMismatch(peer_id.clone());
+                        log::error!("{}", err);
+                        self.record_failure(
+                            ConsensusFailureKind::GenesisMismatch,
+                            self.world_state.get().last_leader_term,
+                            err,
+                            vec![peer_id.clone()],
+                        );
+                        mismatched.push(peer_id);
+                    }
+                    Err(err) => {
+                        log::debug!("Could not get the chains of {}: {}", peer_id, err);
+                        unanswered.push((peer_id, peer_address));
+                    }
+                }
+            }
+            pending = unanswered;
+
+            if mismatched.is_empty() {
+                continue;
+            }
+            let epoch = self.epochs.current();
+            let matching = epoch.peers.len().saturating_sub(mismatched.len());
+            if let Ok(fault_tolerance) = epoch.fault_tolerance() {
+                if !fault_tolerance.quorum_reached(matching) {
+                    self.fatal_error(Error::MixedGenesis(mismatched.len()));
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -1,21 +1,25 @@
 use super::{
-    message::{consensus_message as message, Metadata},
-    Core, Error, Follower, InvalidTransaction, ViewChange, MAX_TRANSACTIONS_PER_BLOCK,
+    append_chunks, clock,
+    epoch::Epoch,
+    message::{consensus_message as message, Metadata, PreparedBlock},
+    proposal_schedule::{ProposalSchedule, ProposalTiming},
+    Core, Error, Follower, InvalidTransaction, ViewChange,
 };
 use crate::{
+    block_storage::ProposalRecord,
     consensus::{BlockHash, BlockNumber, Body, LeaderTerm, SignatureList},
     transaction_checker::TransactionCheck,
 };
 use pinxit::{verify_signed_batch, Signed};
-use prellblock_client_api::Transaction;
+use prellblock_client_api::{
+    consensus::transaction_hash, parameter::Parameter, ConsensusFailureKind, Transaction,
+};
 use std::{
+    collections::{HashMap, HashSet},
     ops::Deref,
     sync::Arc,
-    time::{Duration, SystemTime},
+    time::SystemTime,
 };
-use tokio::time;
-
-const BLOCK_GENERATION_TIMEOUT: Duration = Duration::from_millis(400);
 
 #[derive(Debug)]
 pub struct Leader {
@@ -25,9 +29,15 @@ pub struct Leader {
     leader_term: LeaderTerm,
     block_number: BlockNumber,
     last_block_hash: BlockHash,
+    /// The epoch of the current round.
+    epoch: Arc<Epoch>,
     phase: Phase,
     /// Represents the leader's internal `WorldState`.
     transaction_check: TransactionCheck,
+    /// The prepared block from the last `NewView` message (to re-propose first).
+    prepared_block: Option<PreparedBlock>,
+    /// When the next block is proposed.
+    schedule: ProposalSchedule,
 }
 
 impl Deref for Leader {
@@ -48,6 +58,7 @@ enum Phase {
 impl Leader {
     pub fn new(core: Arc<Core>, follower: Arc<Follower>, view_change: Arc<ViewChange>) -> Self {
         let transaction_check = core.transaction_checker.check();
+        let epoch = core.epochs.current();
         Self {
             core,
             follower,
@@ -55,8 +66,11 @@ impl Leader {
             leader_term: LeaderTerm::default(),
             block_number: BlockNumber::default(),
             last_block_hash: BlockHash::default(),
+            epoch,
             phase: Phase::Waiting,
             transaction_check,
+            prepared_block: None,
+            schedule: ProposalSchedule::default(),
         }
     }
 
@@ -90,13 +104,31 @@ impl Leader {
                     self.leader_term,
                     self.block_number
                 ),
-                Err(err) => log::error!(
-                    "Error during leader term {} (block: #{}, phase: {:?}): {}",
-                    self.leader_term,
-                    self.block_number,
-                    self.phase,
-                    err
-                ),
+                Err(err) => {
+                    if err.is_retryable() {
+                        log::warn!(
+                            "Leader term {} ended (block: #{}, phase: {:?}): {}",
+                            self.leader_term,
+                            self.block_number,
+                            self.phase,
+                            err
+                        );
+                    } else {
+                        log::error!(
+                            "Error during leader term {} (block: #{}, phase: {:?}): {}",
+                            self.leader_term,
+                            self.block_number,
+                            self.phase,
+                            err
+                        );
+                    }
+                    self.record_failure(
+                        ConsensusFailureKind::Leader,
+                        self.leader_term,
+                        format!("{} (phase: {:?})", err, self.phase),
+                        Vec::new(),
+                    );
+                }
             }
 
             // After we are done with one leader term,
@@ -117,6 +149,15 @@ impl Leader {
             self.block_number = state.block_number;
             self.last_block_hash = state.last_block_hash;
         }
+        drop(state);
+
+        // The leader's world state starts from the last committed block.
+        if let Err(err) = self.follower.wait_applied(self.block_number).await {
+            log::warn!(
+                "Could not wait for the committed blocks to be applied: {}",
+                err
+            );
+        }
 
         // Update the leader's world state.
         self.transaction_check = self.transaction_checker.check();
@@ -127,7 +168,12 @@ impl Leader {
     async fn handle_new_view(&mut self) {
         if let Some(message) = self.view_change.get_new_view_message(self.block_number) {
             let new_leader_term = message.leader_term;
-            match self.broadcast_until_majority(message, |_| Ok(())).await {
+            self.prepared_block = message.prepared_block.clone();
+            let epoch = self.epochs.current();
+            match self
+                .broadcast_until_majority(&epoch, message, |_| Ok(()))
+                .await
+            {
                 Ok(_) => log::trace!(
                     "Succesfully broadcasted NewView Message {}.",
                     new_leader_term,
@@ -147,49 +193,209 @@ impl Leader {
     ///
     /// This function waits until it is notified to process transactions.
     async fn execute_leader_term(&mut self) -> Result<(), Error> {
-        let mut timeout_result = Ok(());
+        // A block prepared in an earlier leader term must be re-proposed before any new transactions.
+        if let Some(prepared_block) = self.prepared_block.take() {
+            if prepared_block.body.height == self.block_number {
+                self.repropose(prepared_block).await?;
+            }
+        }
+
+        // A proposal of this leader term may have been interrupted (e.g. by a crash).
+        self.recover_proposal().await?;
+
         loop {
             self.phase = Phase::Waiting;
 
-            let min_block_size = match timeout_result {
-                // No timeout, send only full blocks
-                Ok(()) => MAX_TRANSACTIONS_PER_BLOCK,
-                // Timeout, send all pending transactions
-                Err(_) => 1,
+            let timing = ProposalTiming::of(&self.world_state.get());
+            let queued = self.queue.lock().await.len();
+            let now = self.clock.instant();
+            let wait = match self.schedule.next_proposal(now, queued, &timing) {
+                Some(due) if due <= now => {
+                    self.execute_round().await?;
+                    self.schedule.proposed(self.clock.instant(), &timing);
+
+                    // The leader of the term may have changed with the epoch.
+                    if !self.is_current_leader() {
+                        log::info!(
+                            "Not the leader of leader term {} in epoch {} anymore.",
+                            self.leader_term,
+                            self.epochs.current().number
+                        );
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Some(due) => due - now,
+                None => timing.max_wait,
             };
-            while self.queue.lock().await.len() >= min_block_size {
-                self.execute_round().await?;
-            }
-            timeout_result =
-                time::timeout(BLOCK_GENERATION_TIMEOUT, self.notify_leader.notified()).await;
+            // New transactions may fill the block before it is due.
+            let _ = clock::timeout(&*self.clock, wait, self.notify_leader.notified()).await;
         }
     }
 
     /// Execute the leader during a single round (block number).
     async fn execute_round(&mut self) -> Result<(), Error> {
+        // A halted RPU must not propose blocks.
+        self.verify_not_halted()?;
+
+        self.epoch = self.epochs.current();
+        let world_state = self.world_state.get();
+        let max_transactions = world_state.parameter_count(Parameter::MaxTransactionsPerBlock);
         let mut transactions = Vec::new();
+        let mut hashes = HashSet::new();
 
         // TODO: Check size of transactions cumulated.
-        while let Some(transaction) = self.queue.lock().await.next() {
-            transactions.push(transaction);
-
-            if transactions.len() >= MAX_TRANSACTIONS_PER_BLOCK {
+        // The accounts share the block space by their queue weights.
+        while transactions.len() < max_transactions {
+            let queued = {
+                let mut queue = self.queue.lock().await;
+                let queued = queue.drain_fair(
+                    max_transactions - transactions.len(),
+                    |transaction| transaction.signer().clone(),
+                    |signer| world_state.queue_weight(signer),
+                );
+                if let Some(queue_spill) = &self.queue_spill {
+                    queue_spill.refill(&mut queue);
+                }
+                queued
+            };
+            if queued.is_empty() {
                 break;
             }
+            for transaction in queued {
+                // Followers reject blocks containing a transaction twice.
+                if !hashes.insert(transaction_hash(&transaction)) {
+                    log::debug!("Skipping duplicate transaction: {:?}", transaction);
+                    continue;
+                }
+                transactions.push(transaction);
+            }
         }
 
         // Also applies valid transactions onto the leader's virutal world state.
-        let (valid_transactions, invalid_transactions) = self.stateful_validate(transactions)?;
+        let timestamp = self.clock.now();
+        let (valid_transactions, invalid_transactions) =
+            self.stateful_validate(transactions, timestamp)?;
+
+        let body = Body {
+            leader_term: self.leader_term,
+            height: self.block_number,
+            prev_block_hash: self.last_block_hash,
+            timestamp,
+            transactions: valid_transactions,
+        };
 
+        self.propose(body, invalid_transactions).await
+    }
+
+    /// Re-propose a block that was prepared (but maybe not committed) in an earlier leader term.
+    async fn repropose(&mut self, prepared_block: PreparedBlock) -> Result<(), Error> {
+        self.verify_not_halted()?;
+        self.epoch = self.epochs.current();
+
+        let mut body = prepared_block.body;
+        if body.prev_block_hash != self.last_block_hash {
+            return Err(Error::PrevBlockHashDoesNotMatch(
+                body.prev_block_hash,
+                self.last_block_hash,
+            ));
+        }
+
+        log::info!(
+            "Re-proposing block #{} prepared in leader term {}.",
+            body.height,
+            body.leader_term
+        );
+        // The block is signed in the current leader term, so it must be stored with it.
+        body.leader_term = self.leader_term;
+
+        // The transactions were already validated by a supermajority.
+        // This only applies them onto the leader's virtual world state.
+        self.stateful_validate(body.transactions.clone(), body.timestamp)?;
+
+        self.propose(body, Vec::new()).await
+    }
+
+    /// Complete or abandon a proposal of the current leader term and round found in the proposal log.
+    ///
+    /// The proposal is completed if all of its transactions are queued (again),
+    /// otherwise the leader term is abandoned, so no different block is proposed at the same height.
+    async fn recover_proposal(&mut self) -> Result<(), Error> {
+        let proposal = match self
+            .block_storage
+            .read_proposal(self.leader_term, self.block_number)?
+        {
+            Some(proposal) if proposal.prev_block_hash == self.last_block_hash => proposal,
+            _ => return Ok(()),
+        };
+
+        let transactions = {
+            let mut queue = self.queue.lock().await;
+            let queued: HashMap<_, _> = queue
+                .iter()
+                .map(|entry| (transaction_hash(entry), &**entry))
+                .collect();
+            let transactions: Option<Vec<_>> = proposal
+                .transaction_hashes
+                .iter()
+                .map(|hash| queued.get(hash).map(|&transaction| transaction.clone()))
+                .collect();
+            if let Some(transactions) = &transactions {
+                queue.remove_all(transactions.iter());
+            }
+            transactions
+        };
+        let transactions = match transactions {
+            Some(transactions) => transactions,
+            None => {
+                log::warn!(
+                    "Abandoning the interrupted proposal of block #{} in leader term {}: transactions are missing.",
+                    proposal.block_number,
+                    proposal.leader_term
+                );
+                return Err(Error::ProposalAbandoned(proposal.block_number));
+            }
+        };
+
+        self.epoch = self.epochs.current();
+        log::info!(
+            "Completing the interrupted proposal of block #{} in leader term {}.",
+            proposal.block_number,
+            proposal.leader_term
+        );
+        let (valid_transactions, invalid_transactions) =
+            self.stateful_validate(transactions, proposal.timestamp)?;
         let body = Body {
             leader_term: self.leader_term,
             height: self.block_number,
             prev_block_hash: self.last_block_hash,
-            timestamp: SystemTime::now(),
+            timestamp: proposal.timestamp,
             transactions: valid_transactions,
         };
+        // The block must not differ from the recorded proposal.
+        if body.hash() != proposal.block_hash {
+            return Err(Error::ProposalAbandoned(proposal.block_number));
+        }
+        self.propose(body, invalid_transactions).await
+    }
 
+    /// Find a consensus on the `body` of the block in the current round.
+    ///
+    /// The proposal is recorded in the proposal log before it is sent.
+    async fn propose(
+        &mut self,
+        body: Body,
+        invalid_transactions: Vec<InvalidTransaction>,
+    ) -> Result<(), Error> {
         let block_hash = body.hash();
+        self.block_storage.write_proposal(&ProposalRecord {
+            leader_term: self.leader_term,
+            block_number: self.block_number,
+            block_hash,
+            prev_block_hash: body.prev_block_hash,
+            timestamp: body.timestamp,
+            transaction_hashes: body.transactions.iter().map(transaction_hash).collect(),
+        })?;
 
         let ackprepare_signatures = self.prepare(block_hash).await?;
         log::trace!(
@@ -230,8 +436,10 @@ impl Leader {
             metadata: metadata.clone(),
         };
 
-        self.broadcast_until_majority(message, move |ack| ack.metadata.verify(&metadata))
-            .await
+        self.broadcast_until_majority(&self.epoch, message, move |ack| {
+            ack.metadata.verify(&metadata)
+        })
+        .await
     }
 
     async fn append(
@@ -245,16 +453,39 @@ impl Leader {
         self.phase = Phase::Append;
 
         let metadata = self.metadata_with(block_hash);
+
+        // The transactions of large blocks are sent in chunks.
+        let mut chunks = append_chunks::split(valid_transactions)?;
+        let (valid_transactions, chunk_hashes, chunks) = if chunks.len() > 1 {
+            let chunk_hashes = chunks
+                .iter()
+                .map(|chunk| append_chunks::chunk_hash(chunk))
+                .collect::<Result<_, _>>()?;
+            let chunks = chunks
+                .into_iter()
+                .map(|transactions| message::AppendChunk {
+                    metadata: metadata.clone(),
+                    transactions,
+                })
+                .collect();
+            (Vec::new(), chunk_hashes, chunks)
+        } else {
+            (chunks.pop().unwrap_or_default(), Vec::new(), Vec::new())
+        };
+
         let message = message::Append {
             metadata: metadata.clone(),
             valid_transactions,
             invalid_transactions,
             ackprepare_signatures,
             timestamp,
+            chunk_hashes,
         };
 
-        self.broadcast_until_majority(message, move |ack| ack.metadata.verify(&metadata))
-            .await
+        self.broadcast_chunks_until_majority(&self.epoch, chunks, message, move |ack| {
+            ack.metadata.verify(&metadata)
+        })
+        .await
     }
 
     async fn commit(
@@ -270,7 +501,7 @@ impl Leader {
             ackappend_signatures,
         };
 
-        self.broadcast_until_majority(message, move |_| Ok(()))
+        self.broadcast_until_majority(&self.epoch, message, move |_| Ok(()))
             .await
     }
 
@@ -286,11 +517,14 @@ impl Leader {
         }
     }
 
+    /// Validate the `transactions` of a block with the `timestamp`.
     fn stateful_validate(
         &mut self,
         transactions: Vec<Signed<Transaction>>,
+        timestamp: SystemTime,
     ) -> Result<(Vec<Signed<Transaction>>, Vec<InvalidTransaction>), Error> {
         let verified_transactions = verify_signed_batch(transactions)?;
+        self.transaction_check.set_block_timestamp(timestamp);
 
         let mut valid_transactions = Vec::new();
         let mut invalid_transactions = Vec::new();
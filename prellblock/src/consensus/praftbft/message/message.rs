@@ -1,9 +1,12 @@
-use super::{InvalidTransaction, Metadata};
-use crate::consensus::{BlockHash, BlockNumber, LeaderTerm, SignatureList};
+use super::{
+    super::{MAX_CHAIN_SAMPLES, MAX_TRANSACTIONS_PER_BLOCK},
+    Error, InvalidTransaction, Metadata, PreparedBlock,
+};
+use crate::consensus::{BlockHash, BlockNumber, ChainId, LeaderTerm, SignatureList};
 use newtype_enum::newtype_enum;
 use pinxit::Signed;
-use prellblock_client_api::Transaction;
-use serde::{Deserialize, Serialize};
+use prellblock_client_api::{limits, Transaction, ViewChangeReason};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{ops::Deref, time::SystemTime};
 
 /// Messages used for finding a consensus.
@@ -25,12 +28,19 @@ pub enum ConsensusMessage {
         /// The transactions of the current `Block`.
         ///
         /// This should match the current `block_hash`.
+        #[serde(deserialize_with = "limits::transactions_per_block")]
         valid_transactions: Vec<Signed<Transaction>>,
         /// Invalid transactions to remove from the follower's queue.
         /// The indices point to the position at which they whould be applied.
+        #[serde(deserialize_with = "limits::transactions_per_block")]
         invalid_transactions: Vec<InvalidTransaction>,
         /// The timestamp of when the proposed Block was created by the leader.
         timestamp: SystemTime,
+        /// The hashes of the `AppendChunk`s containing the transactions of a large block (in order).
+        ///
+        /// If not empty, the `valid_transactions` are sent in the chunks instead.
+        #[serde(deserialize_with = "limits::transactions_per_block")]
+        chunk_hashes: Vec<BlockHash>,
     },
 
     /// A `ConsensusMessage` signalizing the Followers to Store the Block in the `BlockStorage` together with the `ACKAPPEND`-Signatures.
@@ -45,6 +55,20 @@ pub enum ConsensusMessage {
     ViewChange {
         /// The Leader Term we want to swap to.
         new_leader_term: LeaderTerm,
+        /// The chain of the leader term.
+        ///
+        /// The signatures are collected as proof of the leader term,
+        /// so they must not be valid for another chain with the same RPUs.
+        chain_id: ChainId,
+        /// The newest block the sender has prepared.
+        ///
+        /// It is not signed, because it is proven by its `AckPrepare` signatures.
+        /// This allows collecting the signatures of all `ViewChange` messages for a leader term.
+        prepared_block: Option<PreparedBlock>,
+        /// Why the sender requested the view change (`None` if it only joined the other RPUs).
+        ///
+        /// It is not signed (like the `prepared_block`), it is only used for observability.
+        reason: Option<ViewChangeReason>,
     },
 
     /// A `ConsensusMessage` signalizing that the new leader has accepted their term.
@@ -55,6 +79,8 @@ pub enum ConsensusMessage {
         view_change_signatures: SignatureList,
         /// The current block number of the leader.
         current_block_number: BlockNumber,
+        /// The prepared block the leader re-proposes before any new transactions.
+        prepared_block: Option<PreparedBlock>,
     },
 
     /// A Request issued during synchronization.
@@ -66,6 +92,130 @@ pub enum ConsensusMessage {
         /// The block hash of the topmost block we have.
         block_hash: BlockHash,
     },
+
+    /// A request for the manifest of a `WorldState` snapshot.
+    ///
+    /// Used by RPUs that are too far behind to replay all missing blocks.
+    SnapshotRequest,
+
+    /// A request for a chunk of a `WorldState` snapshot.
+    SnapshotChunkRequest {
+        /// The hash of the block the snapshot was taken at.
+        anchor_hash: BlockHash,
+        /// The index of the requested chunk.
+        index: usize,
+    },
+
+    /// A request for the current time of an RPU (used to estimate clock skew).
+    ClockRequest,
+    /// A request for the hashes of some blocks of an RPU (used to compare chains).
+    ChainSampleRequest {
+        /// The numbers of the requested blocks (at most `MAX_CHAIN_SAMPLES`).
+        #[serde(deserialize_with = "chain_samples")]
+        block_numbers: Vec<BlockNumber>,
+    },
+
+    /// A chunk of the transactions of a large block, sent before the `Append` message.
+    AppendChunk {
+        /// The message metadata.
+        metadata: Metadata,
+        /// The transactions of the chunk.
+        #[serde(deserialize_with = "limits::transactions_per_block")]
+        transactions: Vec<Signed<Transaction>>,
+    },
+
+    /// A request issued during differential synchronization.
+    ///
+    /// The response contains the missing blocks without their transactions.
+    DifferentialSynchronizationRequest {
+        /// The current leader term of the sender.
+        leader_term: LeaderTerm,
+        /// The current block number of the sender.
+        block_number: BlockNumber,
+        /// The block hash of the topmost block we have.
+        block_hash: BlockHash,
+    },
+
+    /// A request for some transactions of committed blocks (at most `MAX_TRANSACTIONS_PER_BLOCK`).
+    TransactionRequest {
+        /// The block number and the index of each requested transaction.
+        #[serde(deserialize_with = "limits::transactions_per_block")]
+        transactions: Vec<(BlockNumber, usize)>,
+    },
+
+    /// A request to confirm the head of the chain (used to certify the head for clients).
+    HeadRequest {
+        /// The number of the head block of the sender.
+        block_number: BlockNumber,
+        /// The hash of the head block of the sender.
+        block_hash: BlockHash,
+        /// The time of the request.
+        timestamp: SystemTime,
+    },
+}
+
+/// Deserialize the block numbers of a `ChainSampleRequest` (or the hashes of a `ChainSample`).
+pub(super) fn chain_samples<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    limits::deserialize_bounded(deserializer, MAX_CHAIN_SAMPLES)
+}
+
+impl ConsensusMessage {
+    /// Verify the invariants of the message that can be checked without any state.
+    ///
+    /// This rejects malformed messages before they reach the `Follower`.
+    pub fn verify_invariants(&self) -> Result<(), Error> {
+        if let Self::Append(message) = self {
+            message.verify_invariants()?;
+        }
+        if let Self::AppendChunk(message) = self {
+            if message.transactions.len() > MAX_TRANSACTIONS_PER_BLOCK {
+                return Err(Error::TooManyTransactions(message.transactions.len()));
+            }
+        }
+        if let Self::TransactionRequest(message) = self {
+            if message.transactions.len() > MAX_TRANSACTIONS_PER_BLOCK {
+                return Err(Error::TooManyTransactions(message.transactions.len()));
+            }
+        }
+        if let Self::ChainSampleRequest(message) = self {
+            if message.block_numbers.len() > MAX_CHAIN_SAMPLES {
+                return Err(Error::TooManyChainSamples(message.block_numbers.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl consensus_message::Append {
+    /// Verify the invariants of the message that can be checked without any state.
+    ///
+    /// The indices of invalid transactions can only be checked once the chunks are reassembled.
+    pub fn verify_invariants(&self) -> Result<(), Error> {
+        let number_of_transactions = self.valid_transactions.len()
+            + self.invalid_transactions.len()
+            + self.chunk_hashes.len();
+        if number_of_transactions > MAX_TRANSACTIONS_PER_BLOCK {
+            return Err(Error::TooManyTransactions(number_of_transactions));
+        }
+        if !self.chunk_hashes.is_empty() {
+            return Ok(());
+        }
+
+        // The indices of invalid transactions must be ascending
+        // and point into the valid transactions.
+        let mut last_index = 0;
+        for (index, _) in &self.invalid_transactions {
+            if *index < last_index || *index > self.valid_transactions.len() {
+                return Err(Error::BadInvalidTransactionIndex(*index));
+            }
+            last_index = *index;
+        }
+        Ok(())
+    }
 }
 
 impl Deref for consensus_message::Prepare {
@@ -82,6 +232,13 @@ impl Deref for consensus_message::Append {
     }
 }
 
+impl Deref for consensus_message::AppendChunk {
+    type Target = Metadata;
+    fn deref(&self) -> &Self::Target {
+        &self.metadata
+    }
+}
+
 impl Deref for consensus_message::Commit {
     type Target = Metadata;
     fn deref(&self) -> &Self::Target {
@@ -14,8 +14,14 @@ pub use request::Request;
 pub use response::{consensus_response, ConsensusResponse};
 
 use super::{Error, InvalidTransaction};
-use crate::consensus::{BlockHash, BlockNumber, LeaderTerm};
+use crate::consensus::{Block, BlockHash, BlockNumber, Body, LeaderTerm, SignatureList};
+use pinxit::Signed;
+use prellblock_client_api::{
+    consensus::{transaction_hash, TransactionHash},
+    Transaction,
+};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::SystemTime};
 
 /// Metadata about a block specific message.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -37,3 +43,88 @@ impl Metadata {
         }
     }
 }
+
+/// A block that was prepared by a supermajority, but may not be committed yet.
+///
+/// The `AckPrepare` signatures prove that it was prepared.
+/// A new leader must re-propose it before any new transactions,
+/// so a block committed by some RPUs is never replaced by another one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedBlock {
+    /// The metadata of the round the block was prepared in.
+    pub metadata: Metadata,
+    /// The signatures of all (2f+1) `AckPrepare` signatures.
+    pub ackprepare_signatures: SignatureList,
+    /// The body of the block.
+    pub body: Body,
+}
+
+impl PreparedBlock {
+    /// Whether the block was prepared after `other` (in a higher round or leader term).
+    pub fn is_newer_than(&self, other: &Self) -> bool {
+        (self.metadata.block_number, self.metadata.leader_term)
+            > (other.metadata.block_number, other.metadata.leader_term)
+    }
+}
+
+/// A committed block with the hashes of its transactions instead of the transactions.
+///
+/// Used during differential synchronization, where only the transactions
+/// that are not queued anyway are downloaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockOutline {
+    /// The `LeaderTerm` of the block.
+    pub leader_term: LeaderTerm,
+    /// The `BlockNumber` of the block.
+    pub height: BlockNumber,
+    /// The `BlockHash` of the previous block.
+    pub prev_block_hash: BlockHash,
+    /// The time the leader proposed the block.
+    pub timestamp: SystemTime,
+    /// The hashes of the transactions of the block (in order).
+    pub transaction_hashes: Vec<TransactionHash>,
+    /// The `AckAppend` signatures of the block.
+    pub signatures: SignatureList,
+}
+
+impl BlockOutline {
+    /// Create the outline of a `block`.
+    pub fn new(block: &Block) -> Self {
+        let body = &block.body;
+        Self {
+            leader_term: body.leader_term,
+            height: body.height,
+            prev_block_hash: body.prev_block_hash,
+            timestamp: body.timestamp,
+            transaction_hashes: body.transactions.iter().map(transaction_hash).collect(),
+            signatures: block.signatures.clone(),
+        }
+    }
+
+    /// Rebuild the block with the `transactions` (by their hashes).
+    pub fn into_block(
+        self,
+        transactions: &HashMap<TransactionHash, Signed<Transaction>>,
+    ) -> Result<Block, Error> {
+        let transactions = self
+            .transaction_hashes
+            .iter()
+            .map(|hash| {
+                transactions
+                    .get(hash)
+                    .cloned()
+                    .ok_or(Error::MissingTransaction(*hash))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Block {
+            body: Body {
+                leader_term: self.leader_term,
+                height: self.height,
+                prev_block_hash: self.prev_block_hash,
+                timestamp: self.timestamp,
+                transactions,
+            },
+            signatures: self.signatures,
+        })
+    }
+}
@@ -1,20 +1,37 @@
-use super::{consensus_message, consensus_response, ConsensusMessage, ConsensusResponse};
+use super::{consensus_message, consensus_response, ConsensusMessage, ConsensusResponse, Metadata};
 use newtype_enum::Variant;
 
 pub trait Request: Variant<ConsensusMessage> {
     type Response: Variant<ConsensusResponse>;
+
+    /// The metadata of the block the message belongs to (if any).
+    fn metadata(&self) -> Option<&Metadata> {
+        None
+    }
 }
 
 impl Request for consensus_message::Prepare {
     type Response = consensus_response::AckPrepare;
+
+    fn metadata(&self) -> Option<&Metadata> {
+        Some(&self.metadata)
+    }
 }
 
 impl Request for consensus_message::Append {
     type Response = consensus_response::AckAppend;
+
+    fn metadata(&self) -> Option<&Metadata> {
+        Some(&self.metadata)
+    }
 }
 
 impl Request for consensus_message::Commit {
     type Response = consensus_response::Ok;
+
+    fn metadata(&self) -> Option<&Metadata> {
+        Some(&self.metadata)
+    }
 }
 
 impl Request for consensus_message::ViewChange {
@@ -28,3 +45,35 @@ impl Request for consensus_message::NewView {
 impl Request for consensus_message::SynchronizationRequest {
     type Response = consensus_response::SynchronizationResponse;
 }
+
+impl Request for consensus_message::SnapshotRequest {
+    type Response = consensus_response::SnapshotManifest;
+}
+
+impl Request for consensus_message::SnapshotChunkRequest {
+    type Response = consensus_response::SnapshotChunk;
+}
+
+impl Request for consensus_message::ClockRequest {
+    type Response = consensus_response::ClockResponse;
+}
+
+impl Request for consensus_message::ChainSampleRequest {
+    type Response = consensus_response::ChainSample;
+}
+
+impl Request for consensus_message::AppendChunk {
+    type Response = consensus_response::Ok;
+}
+
+impl Request for consensus_message::DifferentialSynchronizationRequest {
+    type Response = consensus_response::DifferentialSynchronizationResponse;
+}
+
+impl Request for consensus_message::TransactionRequest {
+    type Response = consensus_response::TransactionResponse;
+}
+
+impl Request for consensus_message::HeadRequest {
+    type Response = consensus_response::HeadAck;
+}
@@ -1,7 +1,10 @@
-use super::Metadata;
-use crate::consensus::{Block, LeaderTerm, SignatureList};
+use super::{message::chain_samples, BlockOutline, Metadata};
+use crate::consensus::{Block, BlockHash, BlockNumber, LeaderTerm, SignatureList};
 use newtype_enum::newtype_enum;
+use pinxit::Signed;
+use prellblock_client_api::{limits, Transaction};
 use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
 
 /// Responses used for finding a consensus.
 #[newtype_enum(variants = "consensus_response")]
@@ -28,6 +31,71 @@ pub enum ConsensusResponse {
         blocks: Vec<Block>,
     },
 
+    /// A Response to a `SnapshotRequest`.
+    SnapshotManifest {
+        /// The last block contained in the snapshot.
+        anchor: Block,
+        /// The hashes of all chunks of the serialized `WorldState`.
+        chunk_hashes: Vec<BlockHash>,
+    },
+
+    /// A Response to a `SnapshotChunkRequest`.
+    SnapshotChunk {
+        /// The data of the chunk.
+        data: Vec<u8>,
+    },
+
+    /// A Response to a `ClockRequest`.
+    ClockResponse {
+        /// The time of the responding RPU.
+        time: SystemTime,
+    },
+
+    /// A Response to a `ChainSampleRequest`.
+    ChainSample {
+        /// The number of blocks of the responding RPU.
+        block_number: BlockNumber,
+        /// The hashes of the requested blocks (`None` if a block is not committed).
+        #[serde(deserialize_with = "chain_samples")]
+        hashes: Vec<Option<BlockHash>>,
+    },
     /// An empty response.
     Ok,
+
+    /// A Response to a `DifferentialSynchronizationRequest`.
+    DifferentialSynchronizationResponse {
+        /// The `NewView` message the sender is missing.
+        new_view: Option<(LeaderTerm, SignatureList)>,
+        /// The outlines of the `Block`s the sender has skipped.
+        blocks: Vec<BlockOutline>,
+    },
+
+    /// A Response to a `TransactionRequest`.
+    TransactionResponse {
+        /// The requested transactions (in the requested order).
+        #[serde(deserialize_with = "limits::transactions_per_block")]
+        transactions: Vec<Signed<Transaction>>,
+    },
+
+    /// A Response to a `Prepare`, `Append` or `Commit` message for an already committed block.
+    ///
+    /// It contains the proof of the committed block, so the sender can reconcile quickly.
+    AlreadyCommitted {
+        /// The metadata of the committed block.
+        metadata: Metadata,
+        /// The `AckAppend` signatures of the committed block.
+        ackappend_signatures: SignatureList,
+    },
+
+    /// A Response to a `HeadRequest` confirming that the block is the head of the chain.
+    ///
+    /// The encoding of the signed data is documented for clients (see `CertifiedHead`).
+    HeadAck {
+        /// The number of the head block.
+        block_number: BlockNumber,
+        /// The hash of the head block.
+        block_hash: BlockHash,
+        /// The time of the request.
+        timestamp: SystemTime,
+    },
 }
@@ -1,5 +1,5 @@
 use super::{ConsensusMessage, ConsensusResponse, Metadata};
-use crate::consensus::SignatureList;
+use crate::consensus::{ChainId, LeaderTerm, SignatureList};
 
 use pinxit::Signable;
 use serde::Serialize;
@@ -12,6 +12,10 @@ pub enum SignableData<'a> {
         metadata: &'a Metadata,
         ackprepare_signatures: &'a SignatureList,
     },
+    ViewChangeMessage {
+        new_leader_term: &'a LeaderTerm,
+        chain_id: &'a ChainId,
+    },
 }
 
 impl<'a> Signable for SignableData<'a> {
@@ -32,6 +36,12 @@ impl Signable for ConsensusMessage {
                 metadata: &message.metadata,
                 ackprepare_signatures: &message.ackprepare_signatures,
             },
+            // Skip `prepared_block` field of view change message. (It is proven by its signatures)
+            // Skip `reason` field of view change message. (It is only informational)
+            Self::ViewChange(message) => SignableData::ViewChangeMessage {
+                new_leader_term: &message.new_leader_term,
+                chain_id: &message.chain_id,
+            },
             _ => SignableData::ConsensusMessage(self),
         }
         .signable_data()
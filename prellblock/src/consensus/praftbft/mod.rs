@@ -1,35 +1,81 @@
+mod append_chunks;
 mod censorship_checker;
+mod certified_head;
+mod chain_comparison;
+mod clock;
+mod clock_skew;
 mod core;
+mod epoch;
 mod error;
 mod follower;
+mod genesis_check;
 mod leader;
 mod message;
+mod network;
 mod notify;
+mod proposal_schedule;
 mod queue;
 mod ring_buffer;
+mod round;
+mod spill;
+mod timeouts;
+mod trace;
 mod view_change;
 
+pub use clock::{Clock, MockClock, SystemClock};
 pub use error::Error;
-pub use message::{ConsensusMessage, ConsensusResponse};
+pub use follower::confirmed_snapshot_manifest;
+pub use message::{consensus_response, ConsensusMessage, ConsensusResponse};
+pub use network::{BaliseNetwork, BroadcastResponse, ConsensusNetwork, InMemoryNetwork};
+pub use proposal_schedule::{ProposalSchedule, ProposalTiming};
 pub use queue::Queue;
 pub use ring_buffer::RingBuffer;
+pub use round::{Round, RoundMessage, RoundStep};
+pub use spill::{QueueSpill, QueueSpillConfig};
+pub use trace::TRACE_LOG_TARGET;
 
 use self::core::Core;
-use super::TransactionApplier;
-use crate::{block_storage::BlockStorage, world_state::WorldStateService};
+use super::{Block, BlockNumber, ChainId, TransactionApplier};
+use crate::{
+    ban_list::BanList,
+    block_storage::BlockStorage,
+    event_bus::EventBus,
+    peer::{Outbox, Resolver},
+    world_state::{WorldState, WorldStateService},
+};
+use balise::{client::ConnectTimeouts, Streaming};
 use censorship_checker::CensorshipChecker;
+use clock_skew::ClockSkewEstimator;
 use error::ErrorVerify;
 use follower::Follower;
+use genesis_check::GenesisCheck;
 use leader::Leader;
-use message::Request;
+use message::{consensus_response, Metadata, Request};
 use newtype_enum::Enum;
 use notify::NotifyMap;
-use pinxit::{Identity, Signable, Signed};
-use prellblock_client_api::Transaction;
-use std::sync::Arc;
+use pinxit::{PeerId, Signable, Signed, Signer};
+use prellblock_client_api::{
+    consensus::{transaction_hash, BlockProof, CertifiedHead, TransactionHash},
+    limits,
+    parameter::Parameter,
+    ChainComparison, ClockSkew, ConsensusFailure, ConsensusFailureKind, ExecutionStatus,
+    LeaderTermRecord, QueuedTransaction, ScrubStatistics, Status, TraceEventKind, Transaction,
+    TransactionStatus, TransactionTrace,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 use view_change::ViewChange;
 
-const MAX_TRANSACTIONS_PER_BLOCK: usize = 4000;
+/// The hard limit of transactions per block, independent of the governed parameter.
+///
+/// It is enforced while deserializing the consensus messages (see `limits`).
+const MAX_TRANSACTIONS_PER_BLOCK: usize = limits::MAX_TRANSACTIONS_PER_BLOCK;
+
+/// The maximum number of block hashes requested at once while comparing chains.
+const MAX_CHAIN_SAMPLES: usize = 64;
 
 type InvalidTransaction = (usize, Signed<Transaction>);
 
@@ -45,17 +91,86 @@ pub struct PRaftBFT {
 impl PRaftBFT {
     /// Create new `PRaftBFT` Instance.
     ///
-    /// The instance is identified `identity` and in a group with other `peers`.
+    /// The instance is identified by the `identity` (shared, so the private key is not copied)
+    /// and in a group with other `peers`.
+    /// Misbehaving peers are added to the `ban_list`.
+    /// Connections to the other RPUs are established within the `connect_timeouts`.
+    /// The overflow of the transaction queue is spilled to the `queue_spill` (if given).
+    /// The addresses of the other RPUs are resolved with the `resolver`.
+    /// All timestamps and timeouts are taken from the `clock` (see `SystemClock` and `MockClock`).
     /// **Warning:** This starts a new thread for processing transactions in the background.
     pub async fn new(
-        identity: Identity,
+        identity: Arc<dyn Signer>,
+        block_storage: BlockStorage,
+        world_state: WorldStateService,
+        ban_list: Arc<BanList>,
+        connect_timeouts: ConnectTimeouts,
+        queue_spill: Option<QueueSpill>,
+        resolver: Arc<Resolver>,
+        clock: Arc<dyn Clock>,
+    ) -> Arc<Self> {
+        let events = EventBus::default();
+        let chain_id = block_storage
+            .chain_id()
+            .expect("The BlockStorage has no genesis block.");
+        let outbox = Outbox::new(identity.clone())
+            .with_connect_timeouts(connect_timeouts)
+            .with_resolver(resolver)
+            .with_events(events.clone());
+        let network = Arc::new(BaliseNetwork::new(outbox, chain_id));
+        Self::start(
+            identity,
+            block_storage,
+            world_state,
+            ban_list,
+            queue_spill,
+            network,
+            clock,
+            events,
+        )
+    }
+
+    /// Create a new `PRaftBFT` instance talking to the other RPUs over the `network`
+    /// (e.g. an `InMemoryNetwork` in tests).
+    ///
+    /// See `new` for the other arguments.
+    /// **Warning:** This starts a new thread for processing transactions in the background.
+    pub async fn with_network(
+        identity: Arc<dyn Signer>,
+        block_storage: BlockStorage,
+        world_state: WorldStateService,
+        ban_list: Arc<BanList>,
+        queue_spill: Option<QueueSpill>,
+        network: Arc<dyn ConsensusNetwork>,
+        clock: Arc<dyn Clock>,
+    ) -> Arc<Self> {
+        Self::start(
+            identity,
+            block_storage,
+            world_state,
+            ban_list,
+            queue_spill,
+            network,
+            clock,
+            EventBus::default(),
+        )
+    }
+
+    fn start(
+        identity: Arc<dyn Signer>,
         block_storage: BlockStorage,
         world_state: WorldStateService,
+        ban_list: Arc<BanList>,
+        queue_spill: Option<QueueSpill>,
+        network: Arc<dyn ConsensusNetwork>,
+        clock: Arc<dyn Clock>,
+        events: EventBus,
     ) -> Arc<Self> {
         log::debug!("Started consensus.");
 
         let transaction_applier =
-            TransactionApplier::new(block_storage.clone(), world_state.clone());
+            TransactionApplier::new(block_storage.clone(), world_state.clone())
+                .with_events(events.clone());
 
         // Setup core
         let core = Arc::new(Core::new(
@@ -63,8 +178,17 @@ impl PRaftBFT {
             block_storage,
             world_state,
             transaction_applier,
+            ban_list,
+            queue_spill,
+            network,
+            events,
+            clock,
         ));
 
+        // Setup adaptive timeouts
+        core.restore_round_trip_times();
+        tokio::spawn(core.clone().execute_round_trip_time_persister());
+
         // Setup view_change
         let view_change = Arc::new(ViewChange::new(core.clone()));
         tokio::spawn(view_change.clone().new_view_timeout_checker());
@@ -72,49 +196,354 @@ impl PRaftBFT {
         // Setup follower
         let follower = Arc::new(Follower::new(core.clone(), view_change.clone()));
 
+        // Setup block storage scrubber
+        tokio::spawn(follower.clone().execute_scrubber());
+
         // Setup censorship_checker
         let censorship_checker = CensorshipChecker::new(core.clone(), view_change.clone());
         tokio::spawn(censorship_checker.execute());
 
+        // Setup clock_skew_estimator
+        let clock_skew_estimator = ClockSkewEstimator::new(core.clone());
+        tokio::spawn(clock_skew_estimator.execute());
+
+        // Setup genesis_check
+        let genesis_check = GenesisCheck::new(core.clone());
+        tokio::spawn(genesis_check.execute());
+
         // Setup leader
         let leader = Leader::new(core.clone(), follower.clone(), view_change.clone());
         tokio::spawn(leader.execute());
 
         // Setup consensus
-        Arc::new(Self {
+        let consensus = Arc::new(Self {
             core,
             follower,
             view_change,
-        })
+        });
+
+        // Continue the rounds that were interrupted by a restart.
+        tokio::spawn(consensus.clone().replay_buffered_messages());
+
+        consensus
+    }
+
+    /// Get the reason why this RPU was halted (`None` if it is running).
+    ///
+    /// A halted RPU does not take part in the consensus and rejects new transactions.
+    #[must_use]
+    pub fn halt_reason(&self) -> Option<String> {
+        self.core.halt_reason()
+    }
+
+    /// Verify that this RPU is not halted.
+    pub fn verify_not_halted(&self) -> Result<(), Error> {
+        self.core.verify_not_halted()
+    }
+
+    /// Get the status of this RPU (e.g. to check whether it was halted).
+    #[must_use]
+    pub fn status(&self) -> Status {
+        Status {
+            block_number: self.core.world_state.get().block_number,
+            halt_reason: self.halt_reason(),
+        }
+    }
+
+    /// Get the bus on which the consensus publishes its `Event`s (e.g. to subscribe to commits).
+    #[must_use]
+    pub fn events(&self) -> &EventBus {
+        &self.core.events
+    }
+
+    /// Get the id of the chain the consensus is running for.
+    #[must_use]
+    pub fn chain_id(&self) -> ChainId {
+        self.core.chain_id
+    }
+
+    /// Check whether `peer_id` is one of the RPUs taking part in the consensus (in the current epoch).
+    #[must_use]
+    pub fn is_peer(&self, peer_id: &PeerId) -> bool {
+        self.core.epochs.current().contains(peer_id)
+    }
+
+    /// Get the list of banned peers.
+    #[must_use]
+    pub fn ban_list(&self) -> &BanList {
+        &self.core.ban_list
+    }
+
+    /// Stream the blocks from `from` up to (excluding) `to` to another RPU.
+    #[must_use]
+    pub fn stream_blocks(&self, from: BlockNumber, to: Option<BlockNumber>) -> Streaming<Block> {
+        self.core.block_storage.stream(from, to)
+    }
+
+    /// Get the latest clock skew estimations of the other RPUs.
+    #[must_use]
+    pub fn clock_skews(&self) -> HashMap<PeerId, ClockSkew> {
+        self.core.clock_skews.get()
+    }
+
+    /// Get the latest recorded consensus failures (oldest first).
+    pub fn consensus_failures(&self) -> Result<Vec<ConsensusFailure>, Error> {
+        Ok(self.core.block_storage.read_consensus_failures()?)
+    }
+
+    /// Get the history of the leader terms seen by this RPU (oldest first).
+    pub fn leader_terms(&self) -> Result<Vec<LeaderTermRecord>, Error> {
+        Ok(self.core.block_storage.read_leader_terms()?)
+    }
+
+    /// Get the results of the block storage scrubber.
+    #[must_use]
+    pub fn scrub_statistics(&self) -> ScrubStatistics {
+        self.follower.scrub_statistics()
+    }
+
+    /// Compare the own chain with the chains of all other RPUs.
+    pub async fn compare_chains(&self) -> Result<ChainComparison, Error> {
+        self.core.compare_chains().await
+    }
+
+    /// Get the head of the chain certified by a supermajority of the RPUs.
+    pub async fn certified_head(&self) -> Result<CertifiedHead, Error> {
+        self.core.certified_head().await
     }
 
     /// Stores incoming `Transaction`s in the Consensus' `queue`.
+    ///
+    /// Transactions are dropped if the RPU is halted.
     pub async fn take_transactions(&self, transactions: Vec<Signed<Transaction>>) {
+        if let Err(err) = self.verify_not_halted() {
+            log::warn!("Dropping {} transactions: {}", transactions.len(), err);
+            return;
+        }
+
+        self.core.traces.record(
+            transactions.iter().map(transaction_hash),
+            &TraceEventKind::Queued,
+        );
         let queue_len = {
             let mut queue = self.core.queue.lock().await;
-            queue.extend(transactions);
+            match &self.core.queue_spill {
+                Some(queue_spill) => queue_spill.enqueue(&mut queue, transactions),
+                None => queue.extend(transactions),
+            }
             queue.len()
         };
 
-        if queue_len > MAX_TRANSACTIONS_PER_BLOCK {
+        let max_transactions = self
+            .core
+            .world_state
+            .get()
+            .parameter_count(Parameter::MaxTransactionsPerBlock);
+        if queue_len > max_transactions {
             self.core.notify_leader.notify();
         }
     }
 
+    /// Get the number of transactions waiting in the `queue` (including the spilled ones).
+    pub async fn queue_len(&self) -> usize {
+        let queue = self.core.queue.lock().await;
+        queue.len() + self.core.queue_spill.as_ref().map_or(0, QueueSpill::len)
+    }
+
+    /// Get how long the oldest transaction in the `queue` has been waiting to be committed.
+    pub async fn commit_lag(&self) -> Duration {
+        self.core
+            .queue
+            .lock()
+            .await
+            .peek()
+            .map_or(Duration::default(), |entry| {
+                self.core.clock.elapsed(entry.inserted())
+            })
+    }
+
+    /// Get all transactions waiting in the `queue` (including the spilled ones).
+    pub async fn queued_transactions(&self) -> Vec<QueuedTransaction> {
+        let queue = self.core.queue.lock().await;
+        let mut queued: Vec<_> = queue
+            .iter()
+            .map(|entry| QueuedTransaction {
+                hash: transaction_hash(entry),
+                signer: entry.signer().clone(),
+                timestamp: entry.unverified_ref().timestamp(),
+                queued_for: self.core.clock.elapsed(entry.inserted()),
+            })
+            .collect();
+
+        // The spilled transactions are queued after the ones in memory.
+        if let Some(queue_spill) = &self.core.queue_spill {
+            match queue_spill.transactions() {
+                Ok(spilled) => {
+                    queued.extend(spilled.into_iter().map(|(queued_at, transaction)| {
+                        QueuedTransaction {
+                            hash: transaction_hash(&transaction),
+                            signer: transaction.signer().clone(),
+                            timestamp: transaction.unverified_ref().timestamp(),
+                            queued_for: self
+                                .core
+                                .clock
+                                .now()
+                                .duration_since(queued_at)
+                                .unwrap_or_default(),
+                        }
+                    }))
+                }
+                Err(err) => log::error!("Could not read the spilled transactions: {}", err),
+            }
+        }
+        queued
+    }
+
+    /// Remove the transactions with the given `hashes` from the `queue` and return them.
+    pub async fn evict_transactions(&self, hashes: &[TransactionHash]) -> Vec<Signed<Transaction>> {
+        self.remove_queued(hashes, &TraceEventKind::Evicted).await
+    }
+
+    /// Remove the transactions with the given `hashes` from the `queue` because their deadline passed.
+    pub async fn expire_transactions(
+        &self,
+        hashes: &[TransactionHash],
+    ) -> Vec<Signed<Transaction>> {
+        self.remove_queued(hashes, &TraceEventKind::Expired).await
+    }
+
+    /// Remove the transactions with the given `hashes` from the `queue` and record `kind` in their traces.
+    async fn remove_queued(
+        &self,
+        hashes: &[TransactionHash],
+        kind: &TraceEventKind,
+    ) -> Vec<Signed<Transaction>> {
+        let mut queue = self.core.queue.lock().await;
+        let mut removed =
+            queue.remove_where(|transaction| hashes.contains(&transaction_hash(transaction)));
+        if let Some(queue_spill) = &self.core.queue_spill {
+            removed.extend(queue_spill.remove(hashes));
+            queue_spill.refill(&mut queue);
+        }
+        drop(queue);
+        self.core
+            .traces
+            .record(removed.iter().map(transaction_hash), kind);
+        removed
+    }
+
+    /// Record an event of `kind` in the traces of the `transactions` (e.g. when received from a client).
+    pub fn trace_transactions<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a Signed<Transaction>>,
+        kind: &TraceEventKind,
+    ) {
+        self.core
+            .traces
+            .record(transactions.into_iter().map(transaction_hash), kind);
+    }
+
+    /// Get where the transaction with the given `hash` is on this RPU right now.
+    ///
+    /// Only the traces of the most recent transactions are available.
+    #[must_use]
+    pub fn transaction_trace(&self, hash: &TransactionHash) -> TransactionTrace {
+        let events = self.core.traces.get(hash);
+        let status = events
+            .last()
+            .map_or(TransactionStatus::Unknown, |event| match event.kind {
+                TraceEventKind::Submitted | TraceEventKind::ReceivedFrom(_) => {
+                    TransactionStatus::Received
+                }
+                TraceEventKind::Queued | TraceEventKind::RolledBack { .. } => {
+                    TransactionStatus::Queued {
+                        queued_for: self
+                            .core
+                            .clock
+                            .now()
+                            .duration_since(event.time)
+                            .unwrap_or_default(),
+                    }
+                }
+                TraceEventKind::Proposed {
+                    leader_term,
+                    block_number,
+                } => TransactionStatus::Proposed {
+                    leader_term,
+                    block_number,
+                },
+                TraceEventKind::Committed { block_number } => {
+                    TransactionStatus::Committed { block_number }
+                }
+                TraceEventKind::Rejected { block_number } => {
+                    TransactionStatus::Rejected { block_number }
+                }
+                TraceEventKind::Evicted => TransactionStatus::Evicted,
+                TraceEventKind::Expired => TransactionStatus::Expired,
+                TraceEventKind::Failed {
+                    block_number,
+                    ref reason,
+                } => TransactionStatus::Failed {
+                    block_number,
+                    reason: reason.clone(),
+                },
+            });
+        // The traces are not persisted, but the execution receipts are.
+        let status = match status {
+            TransactionStatus::Unknown | TransactionStatus::Committed { .. } => {
+                match self.core.block_storage.read_execution_receipt(hash) {
+                    Ok(Some(receipt)) => match receipt.status {
+                        ExecutionStatus::Applied => TransactionStatus::Committed {
+                            block_number: receipt.block_number,
+                        },
+                        ExecutionStatus::Failed { reason } => TransactionStatus::Failed {
+                            block_number: receipt.block_number,
+                            reason,
+                        },
+                    },
+                    Ok(None) => status,
+                    Err(err) => {
+                        log::warn!("Could not read the execution receipt: {}", err);
+                        status
+                    }
+                }
+            }
+            status => status,
+        };
+        TransactionTrace {
+            rpu: self.core.identity.id().clone(),
+            status,
+            events,
+        }
+    }
+
     /// Process the incoming `ConsensusMessages`.
     pub async fn handle_message(
         self: &Arc<Self>,
         message: Signed<ConsensusMessage>,
     ) -> Result<Signed<ConsensusResponse>, Error> {
+        // A halted RPU does not take part in the consensus.
+        self.verify_not_halted()?;
+
         let peer_id = message.signer().clone();
 
-        // Only RPUs are allowed.
-        self.core
-            .transaction_checker
-            .account_checker(peer_id.clone())?
-            .verify_is_rpu()?;
+        // Only RPUs of the current epoch are allowed.
+        if !self.is_peer(&peer_id) {
+            return Err(Error::InvalidPeer(peer_id));
+        }
 
         let signature = message.signature().clone();
+
+        // Messages that may be buffered for a future phase are kept across restarts (if enabled).
+        let persisted = match message.unverified_ref() {
+            ConsensusMessage::Commit(_) | ConsensusMessage::ViewChange(_)
+                if self.core.block_storage.persists_buffered_messages() =>
+            {
+                Some(message.clone())
+            }
+            _ => None,
+        };
+
         let message = message.verify()?;
 
         macro_rules! dispatch {
@@ -128,17 +557,210 @@ impl PRaftBFT {
         };
         }
 
-        let response: ConsensusResponse = dispatch! {
-            Prepare(message) => self.follower.handle_prepare_message(peer_id, message).await?,
-            Append(message) => self.follower.handle_append_message(peer_id, message).await?,
-            Commit(message) => self.follower.handle_commit_message(peer_id, message).await?,
-            ViewChange(message) => self.view_change.handle_view_change(peer_id, signature, message.new_leader_term)?,
-            NewView(message) => self.follower.handle_new_view_message(peer_id, message).await?,
-            SynchronizationRequest(message) => self.follower.handle_synchronization_request(peer_id, message).await?,
+        // From here on, the message is signed by `peer_id`.
+        // Any misbehaviour can be accounted to the peer.
+        let response: Result<ConsensusResponse, Error> = async {
+            message.verify_invariants()?;
+            if let Some(proof) = self.follower.already_committed(&message)? {
+                return Ok(ConsensusResponse::from_variant(proof));
+            }
+            let peer_id = peer_id.clone();
+            Ok(dispatch! {
+                Prepare(message) => self.follower.handle_prepare_message(peer_id, message).await?,
+                Append(message) => self.follower.handle_append_message(peer_id, message).await?,
+                Commit(message) => self.follower.handle_commit_message(peer_id, message).await?,
+                ViewChange(message) => self.view_change.handle_view_change(peer_id, signature, message)?,
+                NewView(message) => self.follower.handle_new_view_message(peer_id, message).await?,
+                SynchronizationRequest(message) => self.follower.handle_synchronization_request(peer_id, message).await?,
+                SnapshotRequest(_) => self.follower.handle_snapshot_request(&peer_id)?,
+                SnapshotChunkRequest(message) => self.follower.handle_snapshot_chunk_request(&message)?,
+                ClockRequest(_) => consensus_response::ClockResponse { time: self.core.clock.now() },
+                ChainSampleRequest(message) => self.core.handle_chain_sample_request(&message)?,
+                AppendChunk(message) => self.follower.handle_append_chunk_message(peer_id, message).await?,
+                DifferentialSynchronizationRequest(message) => self.follower.handle_differential_synchronization_request(peer_id, message).await?,
+                TransactionRequest(message) => self.follower.handle_transaction_request(&message)?,
+                HeadRequest(message) => self.core.handle_head_request(&peer_id, &message)?,
+            })
+        }
+        .await;
+
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                if err.is_misbehaviour() {
+                    self.core.ban_list.strike(&peer_id, &err);
+                }
+                self.core.record_failure(
+                    ConsensusFailureKind::RejectedMessage,
+                    self.view_change.leader_term(),
+                    &err,
+                    vec![peer_id],
+                );
+                return Err(err);
+            }
         };
 
+        if let Some(message) = persisted {
+            self.persist_buffered_message(&message).await;
+        }
+
         Ok(response.sign(&self.core.identity)?)
     }
+
+    /// Persist a handled `message` if it is still buffered for a future phase.
+    ///
+    /// Obsolete messages are removed at the same time, so only the messages
+    /// of the current block and of the leader terms in the view change window are kept.
+    async fn persist_buffered_message(&self, message: &Signed<ConsensusMessage>) {
+        let leader_term = self.view_change.leader_term();
+        let buffered = match message.unverified_ref() {
+            ConsensusMessage::Commit(commit) => {
+                self.follower.is_commit_buffered(commit.block_number).await
+            }
+            ConsensusMessage::ViewChange(view_change) => view_change.new_leader_term > leader_term,
+            _ => false,
+        };
+
+        let block_storage = &self.core.block_storage;
+        let block_number = self.core.world_state.get().block_number;
+        let result = block_storage
+            .remove_buffered_messages(block_number, leader_term)
+            .and_then(|()| {
+                if buffered {
+                    block_storage.write_buffered_message(message)
+                } else {
+                    Ok(())
+                }
+            });
+        if let Err(err) = result {
+            log::warn!("Could not persist a buffered message: {}", err);
+        }
+    }
+
+    /// Handle the buffered messages persisted before the last restart again.
+    ///
+    /// Messages that became obsolete in the meantime are dropped.
+    async fn replay_buffered_messages(self: Arc<Self>) {
+        let block_storage = &self.core.block_storage;
+        let block_number = self.core.world_state.get().block_number;
+        let messages = block_storage
+            .remove_buffered_messages(block_number, self.view_change.leader_term())
+            .and_then(|()| block_storage.read_buffered_messages());
+        let messages = match messages {
+            Ok(messages) => messages,
+            Err(err) => {
+                log::warn!("Could not read the persisted buffered messages: {}", err);
+                return;
+            }
+        };
+        if !messages.is_empty() {
+            log::info!("Replaying {} persisted buffered messages.", messages.len());
+        }
+
+        for message in messages {
+            let peer_id = message.signer().clone();
+            if !self.is_peer(&peer_id) {
+                continue;
+            }
+            let signature = message.signature().clone();
+            let result = match message.verify() {
+                Ok(message) => match message.into_inner() {
+                    ConsensusMessage::Commit(message) => self
+                        .follower
+                        .handle_commit_message(peer_id, message)
+                        .await
+                        .map(drop),
+                    ConsensusMessage::ViewChange(message) => self
+                        .view_change
+                        .handle_view_change(peer_id, signature, message)
+                        .map(drop),
+                    _ => Ok(()),
+                },
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = result {
+                log::debug!("Could not replay a buffered message: {}", err);
+            }
+        }
+    }
+}
+
+/// Verify that a committed `block` is signed by a supermajority of the RPUs in the `world_state`.
+///
+/// The `world_state` needs to be the state before applying the `block`.
+/// This can be used by nodes that do not take part in the consensus (e.g. observers).
+/// The leader term of the `block` must not be lower than the one of the previous block,
+/// because the signatures are only valid for the leader term stored in the `block`.
+pub fn verify_block_signatures(block: &Block, world_state: &WorldState) -> Result<(), Error> {
+    verify_leader_term(block, world_state)?;
+
+    let signatures = &block.signatures;
+    if !world_state
+        .fault_tolerance()?
+        .quorum_reached(signatures.len())
+    {
+        return Err(Error::NotEnoughSignatures);
+    }
+
+    let message = ackappend_message(block);
+    for (peer_id, signature) in signatures {
+        // Only RPUs are allowed to sign blocks.
+        if !world_state.peers.iter().any(|(id, _)| id == peer_id) {
+            return Err(Error::InvalidPeer(peer_id.clone()));
+        }
+        peer_id.verify(&message, signature)?;
+    }
+
+    Ok(())
+}
+
+/// Verify that no transaction is contained more than once in `transactions`.
+fn verify_unique_transactions(transactions: &[Signed<Transaction>]) -> Result<(), Error> {
+    let mut hashes = HashSet::new();
+    for transaction in transactions {
+        let hash = transaction_hash(transaction);
+        if !hashes.insert(hash) {
+            return Err(Error::DuplicateTransaction(hash));
+        }
+    }
+    Ok(())
+}
+
+/// Verify that the leader term of `block` does not decrease compared to the last block in the `world_state`.
+fn verify_leader_term(block: &Block, world_state: &WorldState) -> Result<(), Error> {
+    if block.body.leader_term < world_state.last_leader_term {
+        return Err(Error::LeaderTermDecreased {
+            received: block.body.leader_term,
+            last: world_state.last_leader_term,
+        });
+    }
+    Ok(())
+}
+
+/// Create the proof which RPUs accepted the `block`.
+///
+/// The proof contains the `AckAppend` message signed by the RPUs, so it can be verified
+/// by anyone without knowing the consensus messages.
+pub fn block_proof(block: &Block) -> Result<BlockProof, Error> {
+    let signed_data = ackappend_message(block).signable_data()?;
+    Ok(BlockProof {
+        leader_term: block.body.leader_term,
+        block_number: block.block_number(),
+        block_hash: block.hash(),
+        signed_data,
+        signatures: block.signatures.clone(),
+    })
+}
+
+/// The message each RPU signs to accept the `block`.
+fn ackappend_message(block: &Block) -> ConsensusResponse {
+    ConsensusResponse::from_variant(consensus_response::AckAppend {
+        metadata: Metadata {
+            leader_term: block.body.leader_term,
+            block_number: block.body.height,
+            block_hash: block.hash(),
+        },
+    })
 }
 
 fn get_response_converter<T>(_: &T) -> fn(T::Response) -> ConsensusResponse
@@ -0,0 +1,286 @@
+//! The transport of the consensus messages between RPUs.
+//!
+//! The consensus only talks to other RPUs through a `ConsensusNetwork`.
+//! RPUs use the `BaliseNetwork`, tests can connect several RPUs in one process
+//! with an `InMemoryNetwork` (and cut them off to simulate partitions).
+
+use super::{ConsensusMessage, ConsensusResponse, Error, PRaftBFT};
+use crate::{
+    consensus::{Block, BlockNumber, ChainId},
+    peer::{message as peer_message, Outbox, RoundTripTimes, Sender},
+};
+use futures::{
+    channel::mpsc,
+    future::BoxFuture,
+    stream::{BoxStream, StreamExt},
+    FutureExt,
+};
+use pinxit::{Signable, Signed};
+use prellblock_client_api::StreamBlocks;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    net::SocketAddr,
+    sync::{Arc, RwLock, Weak},
+};
+
+/// A response of an RPU to a broadcasted message.
+pub type BroadcastResponse = (SocketAddr, Result<Signed<ConsensusResponse>, Error>);
+
+/// A transport of consensus messages between RPUs.
+pub trait ConsensusNetwork: fmt::Debug + Send + Sync + 'static {
+    /// Send a `message` to the RPU at `peer_address` and wait for its response.
+    fn send_to(
+        &self,
+        peer_address: SocketAddr,
+        message: Signed<ConsensusMessage>,
+    ) -> BoxFuture<'_, Result<Signed<ConsensusResponse>, Error>>;
+
+    /// Send the `messages` (in order) to each of the `peers`.
+    ///
+    /// The stream yields the response of each peer to the last message as it arrives
+    /// (or the first error, the remaining messages are not sent to that peer then).
+    /// Messages are still delivered after the stream is dropped.
+    fn broadcast(
+        self: Arc<Self>,
+        peers: Vec<SocketAddr>,
+        messages: Vec<Signed<ConsensusMessage>>,
+    ) -> BoxStream<'static, BroadcastResponse> {
+        let (sender, receiver) = mpsc::unbounded();
+        for peer_address in peers {
+            let network = self.clone();
+            let messages = messages.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let mut response = None;
+                for message in messages {
+                    let result = network.send_to(peer_address, message).await;
+                    let failed = result.is_err();
+                    response = Some(result);
+                    if failed {
+                        break;
+                    }
+                }
+                if let Some(response) = response {
+                    let _ = sender.unbounded_send((peer_address, response));
+                }
+            });
+        }
+        receiver.boxed()
+    }
+
+    /// Download the blocks from `from` up to (excluding) `to` from the RPU at `peer_address`.
+    fn download_blocks(
+        &self,
+        peer_address: SocketAddr,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> BoxFuture<'_, Result<Vec<Block>, Error>>;
+
+    /// Get the ids of the chains served by the RPU at `peer_address` (see `genesis_check`).
+    fn chain_ids(&self, peer_address: SocketAddr) -> BoxFuture<'_, Result<Vec<ChainId>, Error>>;
+
+    /// Get the round trip times of the messages sent so far (see `timeouts`).
+    fn round_trip_times(&self) -> &RoundTripTimes;
+}
+
+/// Sends the consensus messages over the network using `balise`.
+#[derive(Debug)]
+pub struct BaliseNetwork {
+    outbox: Outbox,
+    chain_id: ChainId,
+}
+
+impl BaliseNetwork {
+    /// Send the messages of the chain `chain_id` with the `outbox`.
+    #[must_use]
+    pub const fn new(outbox: Outbox, chain_id: ChainId) -> Self {
+        Self { outbox, chain_id }
+    }
+}
+
+impl ConsensusNetwork for BaliseNetwork {
+    fn send_to(
+        &self,
+        peer_address: SocketAddr,
+        message: Signed<ConsensusMessage>,
+    ) -> BoxFuture<'_, Result<Signed<ConsensusResponse>, Error>> {
+        let message = peer_message::Consensus(self.chain_id, message);
+        async move { Ok(self.outbox.send(peer_address, message).await?) }.boxed()
+    }
+
+    fn download_blocks(
+        &self,
+        peer_address: SocketAddr,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> BoxFuture<'_, Result<Vec<Block>, Error>> {
+        async move {
+            let signer = self.outbox.signer();
+            let request = StreamBlocks { from, to: Some(to) }.sign(&signer)?;
+            let mut sender = Sender::new(peer_address)
+                .with_signer(signer)
+                .with_connect_timeouts(self.outbox.connect_timeouts());
+            let mut stream = sender
+                .send_stream_request(peer_message::StreamBlocks(self.chain_id, request))
+                .await?;
+
+            let mut blocks = Vec::new();
+            while let Some(block) = stream.next().await {
+                blocks.push(block?);
+            }
+            Ok(blocks)
+        }
+        .boxed()
+    }
+
+    fn chain_ids(&self, peer_address: SocketAddr) -> BoxFuture<'_, Result<Vec<ChainId>, Error>> {
+        async move {
+            Ok(self
+                .outbox
+                .send(peer_address, peer_message::GetChainIds)
+                .await?)
+        }
+        .boxed()
+    }
+
+    fn round_trip_times(&self) -> &RoundTripTimes {
+        self.outbox.round_trip_times()
+    }
+}
+
+/// Connects the RPUs of one process without any sockets (e.g. for tests).
+///
+/// Each RPU is reachable at the address it was connected with.
+#[derive(Default)]
+pub struct InMemoryNetwork {
+    rpus: RwLock<HashMap<SocketAddr, Weak<PRaftBFT>>>,
+    disconnected: RwLock<HashSet<SocketAddr>>,
+    dropped_messages: RwLock<Option<Box<MessageFilter>>>,
+    round_trip_times: RoundTripTimes,
+}
+
+/// Decides whether a message sent to an address is dropped.
+type MessageFilter = dyn Fn(SocketAddr, &Signed<ConsensusMessage>) -> bool + Send + Sync;
+
+impl fmt::Debug for InMemoryNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryNetwork")
+            .field("rpus", &self.rpus)
+            .field("disconnected", &self.disconnected)
+            .field(
+                "drops_messages",
+                &self.dropped_messages.read().unwrap().is_some(),
+            )
+            .field("round_trip_times", &self.round_trip_times)
+            .finish()
+    }
+}
+
+impl InMemoryNetwork {
+    /// Make the `consensus` reachable at `peer_address`.
+    pub fn connect(&self, peer_address: SocketAddr, consensus: &Arc<PRaftBFT>) {
+        self.rpus
+            .write()
+            .unwrap()
+            .insert(peer_address, Arc::downgrade(consensus));
+        self.disconnected.write().unwrap().remove(&peer_address);
+    }
+
+    /// Make the RPU at `peer_address` unreachable until it is connected again.
+    pub fn disconnect(&self, peer_address: SocketAddr) {
+        self.disconnected.write().unwrap().insert(peer_address);
+    }
+
+    /// Drop all messages for which `filter` returns `true` (replaces the previous filter).
+    ///
+    /// The filter gets the destination address and the message (e.g. to lose all `Commit` messages).
+    pub fn drop_messages(
+        &self,
+        filter: impl Fn(SocketAddr, &Signed<ConsensusMessage>) -> bool + Send + Sync + 'static,
+    ) {
+        *self.dropped_messages.write().unwrap() = Some(Box::new(filter));
+    }
+
+    /// Deliver all messages again (see `drop_messages`).
+    pub fn deliver_messages(&self) {
+        *self.dropped_messages.write().unwrap() = None;
+    }
+
+    fn is_dropped(&self, peer_address: SocketAddr, message: &Signed<ConsensusMessage>) -> bool {
+        self.dropped_messages
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(false, |filter| filter(peer_address, message))
+    }
+
+    fn rpu(&self, peer_address: SocketAddr) -> Result<Arc<PRaftBFT>, Error> {
+        let unreachable = || {
+            Error::Balise(
+                io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    format!("The RPU {} is not connected.", peer_address),
+                )
+                .into(),
+            )
+        };
+        if self.disconnected.read().unwrap().contains(&peer_address) {
+            return Err(unreachable());
+        }
+        self.rpus
+            .read()
+            .unwrap()
+            .get(&peer_address)
+            .and_then(Weak::upgrade)
+            .ok_or_else(unreachable)
+    }
+}
+
+impl ConsensusNetwork for InMemoryNetwork {
+    fn send_to(
+        &self,
+        peer_address: SocketAddr,
+        message: Signed<ConsensusMessage>,
+    ) -> BoxFuture<'_, Result<Signed<ConsensusResponse>, Error>> {
+        async move {
+            if self.is_dropped(peer_address, &message) {
+                return Err(Error::Balise(
+                    io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("The message to {} was dropped.", peer_address),
+                    )
+                    .into(),
+                ));
+            }
+            self.rpu(peer_address)?.handle_message(message).await
+        }
+        .boxed()
+    }
+
+    fn download_blocks(
+        &self,
+        peer_address: SocketAddr,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> BoxFuture<'_, Result<Vec<Block>, Error>> {
+        async move {
+            let rpu = self.rpu(peer_address)?;
+            let blocks = rpu
+                .core
+                .block_storage
+                .read(from..to)
+                .collect::<Result<_, _>>()?;
+            Ok(blocks)
+        }
+        .boxed()
+    }
+
+    fn chain_ids(&self, peer_address: SocketAddr) -> BoxFuture<'_, Result<Vec<ChainId>, Error>> {
+        async move { Ok(vec![self.rpu(peer_address)?.chain_id()]) }.boxed()
+    }
+
+    fn round_trip_times(&self) -> &RoundTripTimes {
+        &self.round_trip_times
+    }
+}
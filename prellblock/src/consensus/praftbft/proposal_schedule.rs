@@ -0,0 +1,97 @@
+//! The cadence at which the leader proposes blocks.
+//!
+//! Without a schedule, a leader under bursty ingest proposes many tiny blocks,
+//! each costing a full consensus round (three broadcasts, signatures and a disk write).
+//! The schedule keeps at least `Parameter::MinBlockInterval` (plus a random jitter of up to
+//! `Parameter::BlockIntervalJitter`) between two proposals and proposes partial blocks only after
+//! the pending transactions waited `Parameter::BlockGenerationTimeout`.
+
+use crate::world_state::WorldState;
+use prellblock_client_api::parameter::Parameter;
+use rand::Rng;
+use std::{
+    cmp,
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
+
+/// The governed parameters of the `ProposalSchedule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProposalTiming {
+    /// The number of transactions of a full block.
+    pub max_transactions: usize,
+    /// The minimum time between two proposals.
+    pub min_interval: Duration,
+    /// The maximum time pending transactions wait for a full block.
+    pub max_wait: Duration,
+    /// The maximum random delay added to the `min_interval`.
+    pub jitter: Duration,
+}
+
+impl ProposalTiming {
+    /// Get the timing for the next block from the `world_state`.
+    #[must_use]
+    pub fn of(world_state: &WorldState) -> Self {
+        Self {
+            max_transactions: world_state.parameter_count(Parameter::MaxTransactionsPerBlock),
+            min_interval: world_state.parameter_duration(Parameter::MinBlockInterval),
+            max_wait: world_state.parameter_duration(Parameter::BlockGenerationTimeout),
+            jitter: world_state.parameter_duration(Parameter::BlockIntervalJitter),
+        }
+    }
+}
+
+/// Decides when the leader proposes the next block.
+///
+/// Full blocks are proposed as soon as the minimum interval since the last proposal elapsed,
+/// partial blocks when the pending transactions additionally waited the maximum time.
+#[derive(Debug, Default)]
+pub struct ProposalSchedule {
+    /// When the last block was proposed.
+    last_proposal: Option<Instant>,
+    /// The random delay before the next proposal.
+    jitter: Duration,
+    /// When the leader first saw the pending transactions.
+    pending_since: Option<Instant>,
+}
+
+impl ProposalSchedule {
+    /// Get the time the next block is due with `queued` pending transactions
+    /// (`None` if there are no pending transactions).
+    pub fn next_proposal(
+        &mut self,
+        now: Instant,
+        queued: usize,
+        timing: &ProposalTiming,
+    ) -> Option<Instant> {
+        if queued == 0 {
+            self.pending_since = None;
+            return None;
+        }
+        let pending_since = *self.pending_since.get_or_insert(now);
+
+        let earliest = match self.last_proposal {
+            Some(last_proposal) => last_proposal + timing.min_interval + self.jitter,
+            None => now,
+        };
+        if queued >= timing.max_transactions {
+            Some(earliest)
+        } else {
+            Some(cmp::max(earliest, pending_since + timing.max_wait))
+        }
+    }
+
+    /// Record that a block was proposed at `now`.
+    ///
+    /// The transactions that did not fit into the block start waiting again.
+    pub fn proposed(&mut self, now: Instant, timing: &ProposalTiming) {
+        self.last_proposal = Some(now);
+        self.pending_since = None;
+        let jitter = u64::try_from(timing.jitter.as_millis()).unwrap_or(u64::max_value());
+        self.jitter = if jitter == 0 {
+            Duration::default()
+        } else {
+            Duration::from_millis(rand::thread_rng().gen_range(0, jitter.saturating_add(1)))
+        };
+    }
+}
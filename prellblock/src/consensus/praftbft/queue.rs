@@ -1,4 +1,11 @@
-use std::{collections::VecDeque, ops::Deref, time::Instant};
+use super::clock::{Clock, SystemClock};
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    ops::Deref,
+    sync::Arc,
+    time::Instant,
+};
 
 /// A queue of elements that have an associated insertion time (`inserted`).
 ///
@@ -23,20 +30,41 @@ use std::{collections::VecDeque, ops::Deref, time::Instant};
 #[derive(Debug)]
 pub struct Queue<T> {
     entries: VecDeque<Entry<T>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<T> Default for Queue<T> {
     fn default() -> Self {
         Self {
             entries: VecDeque::new(),
+            clock: Arc::new(SystemClock),
         }
     }
 }
 
 impl<T> Queue<T> {
+    /// Take the insertion times from the `clock` (instead of the system clock).
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the clock the insertion times are taken from.
+    #[must_use]
+    pub fn clock(&self) -> &dyn Clock {
+        &*self.clock
+    }
+
     /// Insert an `item` into the queue.
     pub fn insert(&mut self, item: T) {
-        self.entries.push_back(Entry::new(item))
+        let inserted = self.clock.instant();
+        self.entries.push_back(Entry { inserted, item })
+    }
+
+    /// Insert an `item` into the queue that was `inserted` earlier (e.g. restored from disk).
+    pub fn insert_at(&mut self, item: T, inserted: Instant) {
+        self.entries.push_back(Entry { inserted, item })
     }
 
     /// Get the number of items in the queue.
@@ -61,6 +89,97 @@ impl<T> Queue<T> {
         self.entries.front()
     }
 
+    /// Iterate over all entries of the queue (in insertion order).
+    pub fn iter(&self) -> impl Iterator<Item = &Entry<T>> {
+        self.entries.iter()
+    }
+
+    /// Remove all items matching the `predicate` from the queue and return them.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<T> {
+        let mut removed = Vec::new();
+        let mut kept = VecDeque::with_capacity(self.entries.len());
+        for entry in self.entries.drain(..) {
+            if predicate(&entry.item) {
+                removed.push(entry.item);
+            } else {
+                kept.push_back(entry);
+            }
+        }
+        self.entries = kept;
+        removed
+    }
+
+    /// Remove up to `max` items in a weighted round robin over their keys and return them.
+    ///
+    /// In each round, every key (in the order of its first item) gets up to `weight(key)` items
+    /// (at least one), which are taken in insertion order.
+    /// So each key gets a share proportional to its weight instead of the oldest items only.
+    /// The remaining items keep their order.
+    ///
+    /// ```
+    /// # use prellblock::consensus::Queue;
+    ///
+    /// let mut queue = Queue::default();
+    /// queue.extend(vec![('a', 1), ('a', 2), ('a', 3), ('a', 4), ('b', 1), ('c', 1), ('c', 2)]);
+    ///
+    /// let weight = |key: &char| if *key == 'c' { 2 } else { 1 };
+    /// let data = queue.drain_fair(5, |(key, _)| *key, weight);
+    /// assert_eq!(data, [('a', 1), ('b', 1), ('c', 1), ('c', 2), ('a', 2)]);
+    ///
+    /// let data: Vec<_> = queue.collect();
+    /// assert_eq!(data, [('a', 3), ('a', 4)]);
+    /// ```
+    pub fn drain_fair<K>(
+        &mut self,
+        max: usize,
+        key: impl Fn(&T) -> K,
+        weight: impl Fn(&K) -> u32,
+    ) -> Vec<T>
+    where
+        K: Eq + Hash,
+    {
+        // The indices of the entries of each key (in the order of the first entry).
+        let mut groups: Vec<(u32, VecDeque<usize>)> = Vec::new();
+        let mut group_indices = HashMap::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let key = key(&entry.item);
+            let group_index = match group_indices.get(&key) {
+                Some(&group_index) => group_index,
+                None => {
+                    groups.push((weight(&key).max(1), VecDeque::new()));
+                    group_indices.insert(key, groups.len() - 1);
+                    groups.len() - 1
+                }
+            };
+            groups[group_index].1.push_back(index);
+        }
+
+        let mut selected = Vec::new();
+        while selected.len() < max && !groups.is_empty() {
+            for (weight, indices) in &mut groups {
+                for _ in 0..*weight {
+                    if selected.len() >= max {
+                        break;
+                    }
+                    match indices.pop_front() {
+                        Some(index) => selected.push(index),
+                        None => break,
+                    }
+                }
+            }
+            groups.retain(|(_, indices)| !indices.is_empty());
+        }
+
+        let mut entries: Vec<_> = self.entries.drain(..).map(Some).collect();
+        let items = selected
+            .into_iter()
+            .filter_map(|index| entries[index].take())
+            .map(|entry| entry.item)
+            .collect();
+        self.entries = entries.into_iter().flatten().collect();
+        items
+    }
+
     /// Remove an `item` from the queue.
     ///
     /// **Note:** This needs to scan the whole queue
@@ -102,7 +221,9 @@ impl<T> Extend<T> for Queue<T> {
     where
         I: IntoIterator<Item = T>,
     {
-        self.entries.extend(iter.into_iter().map(Entry::new));
+        let inserted = self.clock.instant();
+        self.entries
+            .extend(iter.into_iter().map(|item| Entry { inserted, item }));
     }
 }
 
@@ -113,13 +234,6 @@ pub struct Entry<T> {
 }
 
 impl<T> Entry<T> {
-    fn new(item: T) -> Self {
-        Self {
-            inserted: Instant::now(),
-            item,
-        }
-    }
-
     pub const fn inserted(&self) -> Instant {
         self.inserted
     }
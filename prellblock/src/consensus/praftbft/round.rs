@@ -0,0 +1,74 @@
+//! The order of the phase messages a follower receives in a round.
+//!
+//! The leader pipelines its messages, so `Prepare`, `Append` and `Commit` of a round can arrive
+//! in any order. `Prepare` and `Append` are verified on their own (against the block hash of
+//! whichever message came first), only the `Commit` needs the block body of the `Append` and is
+//! buffered until then. Messages of future rounds wait until the round is reached.
+
+use super::Error;
+
+/// A phase message of a round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundMessage {
+    /// The leader announces the block hash.
+    Prepare,
+    /// The leader sends the block body.
+    Append,
+    /// The leader sends the signatures of the block.
+    Commit,
+}
+
+/// What to do with a received phase message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundStep {
+    /// Verify and apply the message now.
+    Handle,
+    /// Keep the message until the messages it depends on were handled.
+    Buffer,
+}
+
+/// The phase messages a follower handled (or buffered) in the current round.
+///
+/// A new `Round` is started after each block and each leader change.
+#[derive(Debug, Default, Clone)]
+pub struct Round {
+    prepared: bool,
+    appended: bool,
+    commit_buffered: bool,
+}
+
+impl Round {
+    /// Decide whether a received `message` is handled now or buffered.
+    ///
+    /// Each message is only accepted once per round.
+    pub fn receive(&mut self, message: RoundMessage) -> Result<RoundStep, Error> {
+        match message {
+            RoundMessage::Prepare if !self.prepared => Ok(RoundStep::Handle),
+            RoundMessage::Append if !self.appended => Ok(RoundStep::Handle),
+            RoundMessage::Commit if self.appended => Ok(RoundStep::Handle),
+            RoundMessage::Commit if !self.commit_buffered => {
+                self.commit_buffered = true;
+                Ok(RoundStep::Buffer)
+            }
+            message => Err(Error::DuplicateRoundMessage(message)),
+        }
+    }
+
+    /// Record that a `message` was handled successfully.
+    ///
+    /// Returns the buffered message that can be handled now.
+    pub fn handled(&mut self, message: RoundMessage) -> Option<RoundMessage> {
+        match message {
+            RoundMessage::Prepare => self.prepared = true,
+            RoundMessage::Append => {
+                self.appended = true;
+                if self.commit_buffered {
+                    self.commit_buffered = false;
+                    return Some(RoundMessage::Commit);
+                }
+            }
+            RoundMessage::Commit => {}
+        }
+        None
+    }
+}
@@ -0,0 +1,253 @@
+//! Spilling the overflow of the transaction queue to disk.
+
+use super::{clock::Clock, Error, Queue};
+use pinxit::Signed;
+use prellblock_client_api::{
+    consensus::{transaction_hash, TransactionHash},
+    Transaction,
+};
+use serde::{Deserialize, Serialize};
+use sled::{Config, Db, Tree};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Instant, SystemTime},
+};
+
+const ENTRIES_TREE_NAME: &[u8] = b"entries";
+const INDEX_TREE_NAME: &[u8] = b"index";
+
+/// The configuration of the `QueueSpill`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueSpillConfig {
+    /// The path to the directory for the spilled transactions.
+    pub path: String,
+    /// The maximum number of transactions kept in memory before spilling to disk.
+    #[serde(default = "default_max_memory_len")]
+    pub max_memory_len: usize,
+    /// The maximum number of transactions spilled to disk.
+    #[serde(default = "default_max_spilled")]
+    pub max_spilled: usize,
+}
+
+const fn default_max_memory_len() -> usize {
+    100_000
+}
+
+const fn default_max_spilled() -> usize {
+    1_000_000
+}
+
+/// A spilled transaction with the time it was queued.
+#[derive(Serialize, Deserialize)]
+struct SpilledTransaction {
+    queued_at: SystemTime,
+    transaction: Signed<Transaction>,
+}
+
+/// A disk-backed extension of the transaction `Queue`.
+///
+/// Transactions that do not fit into the in-memory queue are spilled to disk (in order)
+/// and moved back into the queue as soon as there is space again, keeping the time they were queued.
+/// While transactions are spilled, new transactions are spilled as well, so the queue keeps its order.
+/// If the spill is full, transactions are kept in memory instead.
+///
+/// The spilled transactions are discarded when the RPU is restarted (like the in-memory queue).
+#[derive(Debug)]
+pub struct QueueSpill {
+    config: QueueSpillConfig,
+    database: Db,
+    /// The spilled transactions by their (increasing) id.
+    entries: Tree,
+    /// The ids of the spilled transactions by their hash.
+    index: Tree,
+    len: AtomicUsize,
+}
+
+impl QueueSpill {
+    /// Open the `QueueSpill` at the path of the `config`.
+    ///
+    /// Transactions spilled before a restart are discarded.
+    pub fn open(config: QueueSpillConfig) -> Result<Self, Error> {
+        let database = Config::default().path(&config.path).open()?;
+        let entries = database.open_tree(ENTRIES_TREE_NAME)?;
+        let index = database.open_tree(INDEX_TREE_NAME)?;
+        entries.clear()?;
+        index.clear()?;
+
+        Ok(Self {
+            config,
+            database,
+            entries,
+            index,
+            len: AtomicUsize::new(0),
+        })
+    }
+
+    /// Get the number of spilled transactions.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    /// Check whether no transactions are spilled.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add the `transactions` to the `queue`, spilling those that do not fit into memory.
+    pub fn enqueue(
+        &self,
+        queue: &mut Queue<Signed<Transaction>>,
+        transactions: Vec<Signed<Transaction>>,
+    ) {
+        // Spilled transactions are older than the new ones.
+        self.refill(queue);
+
+        let mut kept_in_memory = 0;
+        for transaction in transactions {
+            if self.is_empty() && queue.len() < self.config.max_memory_len {
+                queue.insert(transaction);
+                continue;
+            }
+            if self.len() >= self.config.max_spilled {
+                kept_in_memory += 1;
+                queue.insert(transaction);
+                continue;
+            }
+            if let Err((err, transaction)) = self.spill(transaction, queue.clock().now()) {
+                log::warn!("Could not spill a transaction to disk: {}", err);
+                kept_in_memory += 1;
+                queue.insert(transaction);
+            }
+        }
+        if kept_in_memory > 0 {
+            log::warn!(
+                "Kept {} transactions in memory that could not be spilled.",
+                kept_in_memory
+            );
+        }
+    }
+
+    /// Move the oldest spilled transactions into the `queue` until it is full.
+    pub fn refill(&self, queue: &mut Queue<Signed<Transaction>>) {
+        while queue.len() < self.config.max_memory_len {
+            match self.pop() {
+                Ok(Some((queued_at, transaction))) => {
+                    let inserted = queued_instant(queue.clock(), queued_at);
+                    queue.insert_at(transaction, inserted);
+                }
+                Ok(None) => return,
+                Err(err) => {
+                    log::error!("Could not read a spilled transaction: {}", err);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Remove the spilled transactions with the given `hashes` and return them.
+    pub fn remove(&self, hashes: &[TransactionHash]) -> Vec<Signed<Transaction>> {
+        let mut removed = Vec::new();
+        if self.is_empty() {
+            return removed;
+        }
+        for hash in hashes {
+            match self.remove_one(hash) {
+                Ok(Some(transaction)) => removed.push(transaction),
+                Ok(None) => {}
+                Err(err) => log::error!("Could not remove spilled transaction {}: {}", hash, err),
+            }
+        }
+        removed
+    }
+
+    /// Get the spilled transaction with the given `hash`.
+    pub fn get(&self, hash: &TransactionHash) -> Result<Option<Signed<Transaction>>, Error> {
+        let id = match self.index.get(postcard::to_stdvec(hash)?)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        match self.entries.get(id)? {
+            Some(value) => {
+                let spilled: SpilledTransaction = postcard::from_bytes(&value)?;
+                Ok(Some(spilled.transaction))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the spilled transactions with the time they were queued (oldest first).
+    pub fn transactions(&self) -> Result<Vec<(SystemTime, Signed<Transaction>)>, Error> {
+        self.entries
+            .iter()
+            .values()
+            .map(|value| {
+                let spilled: SpilledTransaction = postcard::from_bytes(&value?)?;
+                Ok((spilled.queued_at, spilled.transaction))
+            })
+            .collect()
+    }
+
+    /// Write a `transaction` queued at `queued_at` to disk (returns it on failure).
+    fn spill(
+        &self,
+        transaction: Signed<Transaction>,
+        queued_at: SystemTime,
+    ) -> Result<(), (Error, Signed<Transaction>)> {
+        let hash = transaction_hash(&transaction);
+        let spilled = SpilledTransaction {
+            queued_at,
+            transaction,
+        };
+        let result = (|| -> Result<(), Error> {
+            let value = postcard::to_stdvec(&spilled)?;
+            let id = self.database.generate_id()?.to_be_bytes();
+            self.entries.insert(&id[..], value)?;
+            self.index.insert(postcard::to_stdvec(&hash)?, &id[..])?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => {
+                self.len.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(err) => Err((err, spilled.transaction)),
+        }
+    }
+
+    /// Remove the oldest spilled transaction.
+    fn pop(&self) -> Result<Option<(SystemTime, Signed<Transaction>)>, Error> {
+        let (_, value) = match self.entries.pop_min()? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let spilled: SpilledTransaction = postcard::from_bytes(&value)?;
+        let hash = transaction_hash(&spilled.transaction);
+        self.index.remove(postcard::to_stdvec(&hash)?)?;
+        self.len.fetch_sub(1, Ordering::SeqCst);
+        Ok(Some((spilled.queued_at, spilled.transaction)))
+    }
+
+    /// Remove the spilled transaction with the given `hash`.
+    fn remove_one(&self, hash: &TransactionHash) -> Result<Option<Signed<Transaction>>, Error> {
+        let id = match self.index.remove(postcard::to_stdvec(hash)?)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let value = match self.entries.remove(id)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        self.len.fetch_sub(1, Ordering::SeqCst);
+        let spilled: SpilledTransaction = postcard::from_bytes(&value)?;
+        Ok(Some(spilled.transaction))
+    }
+}
+
+/// Convert the time a transaction was queued into an `Instant` (as used by the `Queue`).
+fn queued_instant(clock: &dyn Clock, queued_at: SystemTime) -> Instant {
+    let now = clock.instant();
+    let elapsed = clock.now().duration_since(queued_at).unwrap_or_default();
+    now.checked_sub(elapsed).unwrap_or(now)
+}
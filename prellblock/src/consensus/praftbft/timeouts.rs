@@ -0,0 +1,82 @@
+//! Consensus timeouts adapted to the round trip times between RPUs.
+//!
+//! Instead of fixed constants, the timeouts are a multiple of the 99th percentile
+//! of the round trip times to the RPUs (see `Outbox`), so they are short in a LAN
+//! and long enough in a WAN. The round trip times are persisted periodically.
+
+use super::{clock::Interval, Core};
+use std::{sync::Arc, time::Duration};
+
+/// The time between two persistences of the round trip times.
+const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The `NewView` timeout if no round trip times are known yet.
+const DEFAULT_NEW_VIEW_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The `NewView` timeout is (at least) this multiple of the round trip time.
+const NEW_VIEW_ROUND_TRIPS: u32 = 8;
+
+/// The lower bound of the `NewView` timeout.
+const MIN_NEW_VIEW_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The upper bound of the `NewView` timeout.
+const MAX_NEW_VIEW_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl Core {
+    /// Get the round trip time within which a supermajority of the RPUs answers
+    /// (based on the 99th percentile of each RPU).
+    ///
+    /// Returns `None` if not enough round trip times are known.
+    pub fn supermajority_round_trip_time(&self) -> Option<Duration> {
+        let round_trip_times = self.network.round_trip_times();
+        let epoch = self.epochs.current();
+        let mut p99s: Vec<Duration> = epoch
+            .peers
+            .iter()
+            .filter(|(peer_id, _)| !self.ban_list.is_banned(peer_id))
+            .filter_map(|(_, peer_address)| Some(round_trip_times.summary(peer_address)?.p99))
+            .collect();
+        p99s.sort_unstable();
+        let quorum = epoch.fault_tolerance().ok()?.quorum();
+        p99s.get(quorum.checked_sub(1)?).copied()
+    }
+
+    /// Get the time to wait for a `NewView` message after a view change.
+    pub fn new_view_timeout(&self) -> Duration {
+        self.supermajority_round_trip_time()
+            .map_or(DEFAULT_NEW_VIEW_TIMEOUT, |round_trip_time| {
+                (round_trip_time * NEW_VIEW_ROUND_TRIPS)
+                    .max(MIN_NEW_VIEW_TIMEOUT)
+                    .min(MAX_NEW_VIEW_TIMEOUT)
+            })
+    }
+
+    /// Restore the round trip times persisted in the `BlockStorage`.
+    pub fn restore_round_trip_times(&self) {
+        match self.block_storage.read_round_trip_times() {
+            Ok(summaries) => self.network.round_trip_times().restore(summaries),
+            Err(err) => log::warn!("Could not restore the round trip times: {}", err),
+        }
+    }
+
+    /// Periodically persist the round trip times in the `BlockStorage`.
+    pub async fn execute_round_trip_time_persister(self: Arc<Self>) {
+        let mut interval = Interval::new(self.clock.clone(), PERSIST_INTERVAL);
+        loop {
+            interval.tick().await;
+            let summaries = self.network.round_trip_times().summaries();
+            for (peer_address, summary) in &summaries {
+                log::trace!(
+                    "Round trip time to {}: median {:?}, p99 {:?} ({} samples).",
+                    peer_address,
+                    summary.median,
+                    summary.p99,
+                    summary.samples
+                );
+            }
+            if let Err(err) = self.block_storage.write_round_trip_times(&summaries) {
+                log::warn!("Could not persist the round trip times: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! The traces of the most recent transactions on this RPU.
+
+use super::Clock;
+use prellblock_client_api::{consensus::TransactionHash, TraceEvent, TraceEventKind};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+/// The number of transactions whose traces are remembered.
+const MAX_TRACES: usize = 100_000;
+
+/// The log target of the trace events (e.g. `RUST_LOG=prellblock::trace=debug`).
+pub const TRACE_LOG_TARGET: &str = "prellblock::trace";
+
+/// The traces of the most recent transactions, identified by their `TransactionHash`.
+///
+/// Only the newest `MAX_TRACES` transactions are remembered. The traces are not persisted.
+#[derive(Debug)]
+pub struct Traces {
+    clock: Arc<dyn Clock>,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    traces: HashMap<TransactionHash, Vec<TraceEvent>>,
+    /// The transactions in the order of their first event.
+    order: VecDeque<TransactionHash>,
+}
+
+impl Traces {
+    /// Create empty traces, whose events are timed by the `clock`.
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Record an event of `kind` for all transactions in `hashes`.
+    pub fn record(&self, hashes: impl IntoIterator<Item = TransactionHash>, kind: &TraceEventKind) {
+        let time = self.clock.now();
+        let mut inner = self.inner.lock().unwrap();
+        for hash in hashes {
+            log::debug!(target: TRACE_LOG_TARGET, "Transaction {}: {:?}", hash, kind);
+            let event = TraceEvent {
+                time,
+                kind: kind.clone(),
+            };
+            if let Some(events) = inner.traces.get_mut(&hash) {
+                events.push(event);
+                continue;
+            }
+
+            if inner.order.len() >= MAX_TRACES {
+                if let Some(oldest) = inner.order.pop_front() {
+                    inner.traces.remove(&oldest);
+                }
+            }
+            inner.order.push_back(hash);
+            inner.traces.insert(hash, vec![event]);
+        }
+    }
+
+    /// Get the recorded events of the transaction with the given `hash` (oldest first).
+    pub fn get(&self, hash: &TransactionHash) -> Vec<TraceEvent> {
+        self.inner
+            .lock()
+            .unwrap()
+            .traces
+            .get(hash)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
@@ -1,21 +1,24 @@
 mod state;
 
 use super::{
-    message::{consensus_message as message, consensus_response as response},
+    clock,
+    message::{consensus_message as message, consensus_response as response, PreparedBlock},
     Core, Error, RingBuffer,
 };
 use crate::consensus::{BlockNumber, LeaderTerm};
 use pinxit::{PeerId, Signature};
+use prellblock_client_api::{ConsensusFailureKind, ViewChangeReason};
 use state::State;
 use std::{
+    collections::BTreeMap,
+    fmt,
     future::Future,
     ops::Deref,
     sync::{Arc, Mutex},
-    time::{Duration, Instant},
+    time::Duration,
 };
-use tokio::{sync::Notify, time};
+use tokio::sync::Notify;
 
-const NEW_VIEW_TIMEOUT: Duration = Duration::from_millis(1000);
 const RING_BUFFER_SIZE: usize = 64;
 
 #[derive(Debug)]
@@ -23,6 +26,8 @@ pub struct ViewChange {
     core: Arc<Core>,
     notify_new_view: Notify,
     state: Mutex<State>,
+    /// The newest block this RPU has prepared (sent with `ViewChange` messages).
+    prepared_block: Mutex<Option<PreparedBlock>>,
 }
 
 impl Deref for ViewChange {
@@ -38,10 +43,14 @@ impl ViewChange {
             core,
             notify_new_view: Notify::new(),
             state: Mutex::new(State::new(RING_BUFFER_SIZE)),
+            prepared_block: Mutex::default(),
         }
     }
 
     /// Get the `NewView` message if one is available for the leader.
+    ///
+    /// It contains the newest prepared block of the `ViewChange` messages
+    /// if the block is not committed yet (it is at `current_block_number`).
     pub fn get_new_view_message(
         &self,
         current_block_number: BlockNumber,
@@ -50,14 +59,17 @@ impl ViewChange {
 
         let leader_term = state.leader_term;
         if self.leader(leader_term) == *self.identity.id() {
-            state
-                .current_signatures
+            let view_change_signatures = state.current_signatures.take()?;
+            let prepared_block = state
+                .current_prepared_block
                 .take()
-                .map(|view_change_signatures| message::NewView {
-                    leader_term,
-                    current_block_number,
-                    view_change_signatures,
-                })
+                .filter(|prepared_block| prepared_block.body.height == current_block_number);
+            Some(message::NewView {
+                leader_term,
+                view_change_signatures,
+                current_block_number,
+                prepared_block,
+            })
         } else {
             None
         }
@@ -73,35 +85,93 @@ impl ViewChange {
         match future.await {
             Ok(value) => Ok(value),
             Err(err) => {
-                self.request_view_change().await;
+                self.request_view_change(err.view_change_reason(), &err)
+                    .await;
                 Err(err)
             }
         }
     }
 
+    /// Remember the `prepared_block` as the newest block this RPU has prepared.
+    pub fn prepared(&self, prepared_block: PreparedBlock) {
+        *self.prepared_block.lock().unwrap() = Some(prepared_block);
+    }
+
+    /// Get the current leader term.
+    pub fn leader_term(&self) -> LeaderTerm {
+        self.state.lock().unwrap().leader_term
+    }
+
+    /// Get the number of `ViewChange` messages per reason for the `leader_term`
+    /// (empty if the `leader_term` is not the current one).
+    pub fn reasons(&self, leader_term: LeaderTerm) -> BTreeMap<ViewChangeReason, u32> {
+        let state = self.state.lock().unwrap();
+        if state.leader_term == leader_term {
+            state.current_reasons.clone()
+        } else {
+            BTreeMap::new()
+        }
+    }
+
     /// Send a `ConsensusMessage::ViewChange` message because the leader
-    /// seems to be faulty.
-    pub async fn request_view_change(&self) {
-        let new_leader_term = self.state.lock().unwrap().leader_term + 1;
-        self.request_view_change_in_leader_term(new_leader_term)
+    /// seems to be faulty (for the given `reason`, described by `details`).
+    pub async fn request_view_change(&self, reason: ViewChangeReason, details: impl fmt::Display) {
+        let new_leader_term = self.leader_term() + 1;
+        self.request_view_change_in_leader_term(new_leader_term, reason, details)
             .await;
     }
 
     /// Send a `ConsensusMessage::ViewChange` for a given `leader_term`
-    /// because the leader seems to be faulty.
-    pub async fn request_view_change_in_leader_term(&self, new_leader_term: LeaderTerm) {
+    /// because the leader seems to be faulty (for the given `reason`, described by `details`).
+    pub async fn request_view_change_in_leader_term(
+        &self,
+        new_leader_term: LeaderTerm,
+        reason: ViewChangeReason,
+        details: impl fmt::Display,
+    ) {
         // No need to update the state,
         // we broadcast the message also to ourselves.
 
-        self.broadcast_view_change(new_leader_term).await;
+        let leader = self.leader(self.leader_term());
+        self.record_failure(
+            ConsensusFailureKind::ViewChange,
+            new_leader_term,
+            details,
+            vec![leader],
+        );
+
+        self.broadcast_view_change(new_leader_term, Some(reason))
+            .await;
     }
 
-    /// Broadcast a `ViewChange` message for a `new_leader_term`.
-    async fn broadcast_view_change(&self, new_leader_term: LeaderTerm) {
+    /// Broadcast a `ViewChange` message for a `new_leader_term`
+    /// (with the `reason` if this RPU requests the view change itself).
+    async fn broadcast_view_change(
+        &self,
+        new_leader_term: LeaderTerm,
+        reason: Option<ViewChangeReason>,
+    ) {
         log::trace!("Broadcasting ViewChange Message: {}", new_leader_term);
 
-        let message = message::ViewChange { new_leader_term };
-        match self.broadcast_until_majority(message, |_| Ok(())).await {
+        // Blocks we already committed do not need to be re-proposed.
+        let block_number = self.world_state.get().block_number;
+        let prepared_block = self
+            .prepared_block
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|prepared_block| prepared_block.body.height == block_number);
+        let message = message::ViewChange {
+            new_leader_term,
+            chain_id: self.chain_id,
+            prepared_block,
+            reason,
+        };
+        let epoch = self.epochs.current();
+        match self
+            .broadcast_until_majority(&epoch, message, |_| Ok(()))
+            .await
+        {
             Ok(_) => log::info!(
                 "ViewChange Message Broadcast {} did reach supermajority.",
                 new_leader_term
@@ -119,8 +189,17 @@ impl ViewChange {
         self: &Arc<Self>,
         peer_id: PeerId,
         signature: Signature,
-        new_leader_term: LeaderTerm,
+        message: message::ViewChange,
     ) -> Result<response::Ok, Error> {
+        let new_leader_term = message.new_leader_term;
+        // The signature is collected as proof of the leader term of this chain.
+        if message.chain_id != self.chain_id {
+            return Err(Error::WrongChain(message.chain_id));
+        }
+        if let Some(prepared_block) = &message.prepared_block {
+            self.verify_prepared_block(prepared_block)?;
+        }
+
         let mut state = self.state.lock().unwrap();
 
         let signatures = state.future_signatures.get_mut(new_leader_term)?;
@@ -129,18 +208,40 @@ impl ViewChange {
             // Ignore duplicate signature
             return Ok(response::Ok);
         }
+        let signature_count = signatures.len();
 
-        if signatures.len() == self.nonfaulty_count() {
+        if let Some(reason) = message.reason {
+            *state
+                .future_reasons
+                .get_mut(new_leader_term)?
+                .entry(reason)
+                .or_default() += 1;
+        }
+
+        // The new leader needs to re-propose the newest prepared block.
+        if let Some(prepared_block) = message.prepared_block {
+            let newest = state.future_prepared_blocks.get_mut(new_leader_term)?;
+            if newest
+                .as_ref()
+                .map_or(true, |newest| prepared_block.is_newer_than(newest))
+            {
+                *newest = Some(prepared_block);
+            }
+        }
+
+        if signature_count == self.epochs.current().nonfaulty_count()? {
             // if enough collected, broadcast message and update state accordingly
 
             let cloned_self = self.clone();
             tokio::spawn(async move {
-                cloned_self.broadcast_view_change(new_leader_term).await;
+                cloned_self
+                    .broadcast_view_change(new_leader_term, None)
+                    .await;
             });
         }
 
-        if self.supermajority_reached(signatures.len()) {
-            state.did_reach_supermajority(new_leader_term);
+        if self.supermajority_reached(signature_count)? {
+            state.did_reach_supermajority(new_leader_term, self.clock.instant());
 
             // Notify leader task to begin to work.
             self.notify_leader.notify();
@@ -180,12 +281,16 @@ impl ViewChange {
     /// Check if the `NewView` message arrives in time
     /// after `new_view_duration` has already passed.
     async fn check_new_view_timeout(&self, new_view_duration: Duration) {
-        let new_view_time_left = NEW_VIEW_TIMEOUT.checked_sub(new_view_duration);
+        let new_view_time_left = self.new_view_timeout().checked_sub(new_view_duration);
 
         let new_view_arrived_in_time = if let Some(remaining_time) = new_view_time_left {
-            time::timeout(remaining_time, self.notify_new_view.notified())
-                .await
-                .is_ok()
+            clock::timeout(
+                &*self.clock,
+                remaining_time,
+                self.notify_new_view.notified(),
+            )
+            .await
+            .is_some()
         } else {
             // timeout already reached
             false
@@ -195,7 +300,11 @@ impl ViewChange {
             log::trace!("NewView arrived in time.");
         } else {
             log::debug!("NewView has not arrived in time.");
-            self.request_view_change().await;
+            self.request_view_change(
+                ViewChangeReason::HeartbeatTimeout,
+                "The NewView message has not arrived in time.",
+            )
+            .await;
         }
     }
 
@@ -206,12 +315,6 @@ impl ViewChange {
             .lock()
             .unwrap()
             .new_view_time
-            .as_ref()
-            .map(Instant::elapsed)
-    }
-
-    /// Calculates the number that represents f + 1 nodes.
-    fn nonfaulty_count(&self) -> usize {
-        (self.world_state.get().peers.len() - 1) / 3 + 1
+            .map(|new_view_time| self.clock.elapsed(new_view_time))
     }
 }
@@ -1,7 +1,11 @@
-use super::RingBuffer;
+use super::{PreparedBlock, RingBuffer};
 use crate::consensus::{LeaderTerm, SignatureList};
 use pinxit::{PeerId, Signature};
-use std::{collections::HashMap, time::Instant};
+use prellblock_client_api::ViewChangeReason;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Instant,
+};
 
 #[derive(Debug)]
 pub struct State {
@@ -9,6 +13,13 @@ pub struct State {
     pub new_view_time: Option<Instant>,
     pub current_signatures: Option<SignatureList>,
     pub future_signatures: RingBuffer<LeaderTerm, HashMap<PeerId, Signature>>,
+    pub current_prepared_block: Option<PreparedBlock>,
+    /// The newest prepared block of all `ViewChange` messages per leader term.
+    pub future_prepared_blocks: RingBuffer<LeaderTerm, Option<PreparedBlock>>,
+    /// The reasons of the `ViewChange` messages of the current leader term.
+    pub current_reasons: BTreeMap<ViewChangeReason, u32>,
+    /// The number of `ViewChange` messages per reason and leader term.
+    pub future_reasons: RingBuffer<LeaderTerm, BTreeMap<ViewChangeReason, u32>>,
 }
 
 impl State {
@@ -18,22 +29,28 @@ impl State {
             new_view_time: None,
             current_signatures: None,
             future_signatures: RingBuffer::new(HashMap::new(), size, LeaderTerm::default()),
+            current_prepared_block: None,
+            future_prepared_blocks: RingBuffer::new(None, size, LeaderTerm::default()),
+            current_reasons: BTreeMap::new(),
+            future_reasons: RingBuffer::new(BTreeMap::new(), size, LeaderTerm::default()),
         }
     }
 
-    pub fn did_reach_supermajority(&mut self, new_leader_term: LeaderTerm) {
+    /// Move to the `new_leader_term`, whose `NewView` timeout starts `now`.
+    pub fn did_reach_supermajority(&mut self, new_leader_term: LeaderTerm, now: Instant) {
         assert!(new_leader_term > self.leader_term);
 
         self.future_signatures
             .increment_to(new_leader_term, HashMap::new());
+        self.future_prepared_blocks
+            .increment_to(new_leader_term, None);
+        self.future_reasons
+            .increment_to(new_leader_term, BTreeMap::new());
 
         self.leader_term = new_leader_term;
-        self.new_view_time = Some(Instant::now());
-        self.current_signatures = Some(
-            self.future_signatures
-                .increment(HashMap::new())
-                .into_iter()
-                .collect(),
-        );
+        self.new_view_time = Some(now);
+        self.current_signatures = Some(self.future_signatures.increment(HashMap::new()).into());
+        self.current_prepared_block = self.future_prepared_blocks.increment(None);
+        self.current_reasons = self.future_reasons.increment(BTreeMap::new());
     }
 }
@@ -1,44 +1,121 @@
 //! Can be used by any consensus algorithm to apply blocks.
 
-use super::Block;
-use crate::{block_storage::BlockStorage, world_state::WorldStateService};
+use super::{Block, Error};
+use crate::{
+    block_storage::{self, BlockStorage},
+    event_bus::{Event, EventBus},
+    world_state::{WorldState, WorldStateService},
+    BoxError,
+};
+use prellblock_client_api::ExecutionReceipt;
 
 /// Helps to apply transactions onto the `BlockStorage` and `WorldState`.
+///
+/// All changes of the chain go through the `TransactionApplier`, which publishes them as `Event`s.
 #[derive(Debug)]
 pub struct TransactionApplier {
     block_storage: BlockStorage,
     world_state: WorldStateService,
+    events: EventBus,
 }
 
 impl TransactionApplier {
     /// Create a new `TransactionApplier` instance.
     #[must_use]
-    pub const fn new(block_storage: BlockStorage, world_state: WorldStateService) -> Self {
+    pub fn new(block_storage: BlockStorage, world_state: WorldStateService) -> Self {
         Self {
             block_storage,
             world_state,
+            events: EventBus::default(),
         }
     }
 
+    /// Publish the changes of the chain on the `events` bus.
+    #[must_use]
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
     /// Applies a given to both the `world_state` and the `block_storage`.
-    pub async fn apply_block(&self, block: Block) {
+    ///
+    /// The `WorldState` is only updated if the block could be written to the `BlockStorage`.
+    /// Returns the `ExecutionReceipt`s of the transactions (they are recorded in the `BlockStorage`).
+    pub async fn apply_block(&self, block: Block) -> Result<Vec<ExecutionReceipt>, Error> {
+        let event = Event::BlockCommitted {
+            block_number: block.block_number(),
+            block_hash: block.hash(),
+            transactions: block.body.transactions.len(),
+        };
         // Write Block to BlockStorage
-        self.apply_to_block_storage(&block);
+        self.apply_to_block_storage(&block)?;
         // Write Block to WorldState
-        self.apply_to_worldstate(block).await;
+        let receipts = self
+            .apply_to_worldstate(block)
+            .await
+            .map_err(Error::WorldState)?;
+        self.block_storage.write_execution_receipts(&receipts)?;
+        self.events.publish(event);
+        Ok(receipts)
     }
 
     /// Applies a given block to the `BlockStorage`.
-    pub fn apply_to_block_storage(&self, block: &Block) {
+    pub fn apply_to_block_storage(&self, block: &Block) -> Result<(), block_storage::Error> {
         // Write Block to BlockStorage
-        self.block_storage.write_block(block).unwrap();
+        self.block_storage.write_block(block)
     }
 
     /// Applies a given block to the `WorldState`.
-    pub async fn apply_to_worldstate(&self, block: Block) {
+    ///
+    /// Returns the `ExecutionReceipt`s of the transactions.
+    pub async fn apply_to_worldstate(
+        &self,
+        block: Block,
+    ) -> Result<Vec<ExecutionReceipt>, BoxError> {
         // Write Block to WorldState
         let mut world_state = self.world_state.get_writable().await;
-        world_state.apply_block(block).unwrap();
+        let receipts = world_state.apply_block(block)?;
         world_state.save();
+        Ok(receipts)
+    }
+
+    /// Install a `WorldState` snapshot together with the `blocks` leading to it.
+    ///
+    /// The blocks are only stored, they are not applied to the `WorldState`.
+    pub async fn install_snapshot(
+        &self,
+        blocks: &[Block],
+        world_state: WorldState,
+    ) -> Result<(), block_storage::Error> {
+        for block in blocks {
+            self.apply_to_block_storage(block)?;
+        }
+        let block_number = world_state.block_number;
+        self.world_state.install_snapshot(world_state).await;
+        self.events
+            .publish(Event::SnapshotInstalled { block_number });
+        Ok(())
+    }
+
+    /// Roll back the last block of both the `block_storage` and the `world_state`.
+    ///
+    /// Returns the removed block.
+    pub fn rollback(&self) -> Result<Block, Error> {
+        let last_block = self
+            .block_storage
+            .pop_block()?
+            .ok_or(Error::NoBlockToRollback)?;
+        if self.world_state.rollback().is_none() {
+            return Err(Error::NoBlockToRollback);
+        }
+        assert_eq!(
+            self.world_state.get().block_number,
+            last_block.block_number()
+        );
+
+        self.events.publish(Event::BlockRolledBack {
+            block_number: last_block.block_number(),
+        });
+        Ok(last_block)
     }
 }
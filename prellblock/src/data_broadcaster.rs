@@ -1,31 +1,81 @@
 //! Module used for Broadcasting Messages between all RPUs.
 
 use crate::{
-    peer::{PeerMessage, Sender},
+    consensus::ChainId,
+    peer::{Outbox, PeerMessage, Resolver},
     world_state::WorldStateService,
 };
-use balise::Request;
+use balise::{client::ConnectTimeouts, Request};
 use futures::future::join_all;
+use pinxit::Signer;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
 
 /// A broadcaster for peer messages.
 pub struct Broadcaster {
+    chain_id: ChainId,
     world_state: WorldStateService,
+    outbox: Arc<Outbox>,
 }
 
 impl Broadcaster {
     /// Create a new Broadcaster
     ///
-    /// `world_state` should be a `WorldState` containing all other RPUs peer addresses.
+    /// `world_state` should be a `WorldState` containing all other RPUs peer addresses
+    /// of the chain with the given `chain_id`.
+    /// The requests are authenticated with the `signer` (the identity of the RPU).
     #[must_use]
-    pub const fn new(world_state: WorldStateService) -> Self {
-        Self { world_state }
+    pub fn new(chain_id: ChainId, world_state: WorldStateService, signer: Arc<dyn Signer>) -> Self {
+        Self {
+            chain_id,
+            world_state,
+            outbox: Arc::new(Outbox::new(signer)),
+        }
+    }
+
+    /// Use the `connect_timeouts` when connecting to other RPUs.
+    #[must_use]
+    pub fn with_connect_timeouts(self, connect_timeouts: ConnectTimeouts) -> Self {
+        let outbox = Outbox::new(self.outbox.signer())
+            .with_connect_timeouts(connect_timeouts)
+            .with_resolver(self.outbox.resolver());
+        Self {
+            outbox: Arc::new(outbox),
+            ..self
+        }
+    }
+
+    /// Resolve the addresses of other RPUs with the `resolver`.
+    #[must_use]
+    pub fn with_resolver(self, resolver: Arc<Resolver>) -> Self {
+        let outbox = Outbox::new(self.outbox.signer())
+            .with_connect_timeouts(self.outbox.connect_timeouts())
+            .with_resolver(resolver);
+        Self {
+            outbox: Arc::new(outbox),
+            ..self
+        }
+    }
+
+    /// Get the id of the chain the broadcaster sends to.
+    #[must_use]
+    pub const fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    /// Get the `WorldStateService` the peers are taken from.
+    pub(crate) const fn world_state(&self) -> &WorldStateService {
+        &self.world_state
     }
 
     /// Broadcast a batch to all known peers (stored in `peer_addresses`).
+    ///
+    /// Returns the responses of the peers.
     #[allow(clippy::future_not_send)]
-    pub async fn broadcast<T>(&self, message: &T) -> Result<(), balise::Error>
+    pub async fn broadcast<T>(&self, message: &T) -> Result<Vec<T::Response>, balise::Error>
     where
         T: Request<PeerMessage>,
+        T::Response: DeserializeOwned,
     {
         // Broadcast transaction to all RPUs.
         let results = join_all(
@@ -36,10 +86,10 @@ impl Broadcaster {
                 .map(|(_, peer_address)| {
                     let message = message.clone();
                     let peer_address = *peer_address;
+                    let outbox = self.outbox.clone();
                     tokio::spawn(async move {
                         log::trace!("Sending batch to {}.", peer_address);
-                        let mut sender = Sender::new(peer_address);
-                        let result = sender.send_request(message).await;
+                        let result = outbox.send(peer_address, message).await;
                         log::trace!("Sent batch to {}.", peer_address);
                         result
                     })
@@ -47,17 +97,17 @@ impl Broadcaster {
         )
         .await;
 
+        let mut responses = Vec::new();
         for result in results {
             match result {
                 Err(err) => {
                     log::error!("Error while broadcasting data: {}", err);
                 }
                 Ok(result) => {
-                    // Ignore result
-                    result?;
+                    responses.push(result?);
                 }
             }
         }
-        Ok(())
+        Ok(responses)
     }
 }
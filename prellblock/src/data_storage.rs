@@ -1,45 +1,58 @@
 //! The `DataStorage` is a temporary storage for incoming transactions persisted on disk.
 
 use hexutil::ToHex;
-use pinxit::PeerId;
-use prellblock_client_api::AccountTransaction;
-use serde::Serialize;
-use sled::{Config, Db, IVec, Tree};
+use pinxit::{PeerId, Signed};
+use prellblock_client_api::{consensus::TransactionHash, AccountTransaction, EvictTransactions};
+use serde::{Deserialize, Serialize};
+use sled::{Db, IVec, Tree};
 use std::time::SystemTime;
 
-use crate::BoxError;
+use crate::{storage::StorageConfig, BoxError};
 
 const KEY_VALUE_ROOT_TREE_NAME: &[u8] = b"root";
 const ACCOUNTS_TREE_NAME: &[u8] = b"accounts";
+const AUDIT_LOG_TREE_NAME: &[u8] = b"audit_log";
+
+/// An administrative action recorded in the audit log of the `DataStorage`.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum AuditEntry {
+    /// Queued transactions were evicted.
+    EvictTransactions {
+        /// The admin's signed request.
+        request: Signed<EvictTransactions>,
+        /// The hashes of the transactions that were actually evicted.
+        evicted: Vec<TransactionHash>,
+    },
+}
 
 /// A `DataStorage` provides persistent storage on disk.
 ///
-/// Data is written to disk every 400ms.
+/// Data is written to disk periodically (every 400ms by default, see `StorageConfig`).
 pub struct DataStorage {
     database: Db,
     key_value_root: Tree,
     accounts: Tree,
+    audit_log: Tree,
 }
 
 impl DataStorage {
     /// Create a new `Store` at path.
     pub fn new(path: &str) -> Result<Self, BoxError> {
-        let config = Config::default()
-            .path(path)
-            .cache_capacity(8_000_000)
-            .flush_every_ms(Some(400))
-            .snapshot_after_ops(100)
-            .use_compression(false) // TODO: set this to `true`.
-            .compression_factor(20);
-
-        let database = config.open()?;
+        Self::with_config(path, &StorageConfig::default())
+    }
+
+    /// Create a new `Store` at path tuned by `config`.
+    pub fn with_config(path: &str, config: &StorageConfig) -> Result<Self, BoxError> {
+        let database = config.sled_config(path).open()?;
         let key_value_root = database.open_tree(KEY_VALUE_ROOT_TREE_NAME)?;
         let accounts = database.open_tree(ACCOUNTS_TREE_NAME)?;
+        let audit_log = database.open_tree(AUDIT_LOG_TREE_NAME)?;
 
         Ok(Self {
             database,
             key_value_root,
             accounts,
+            audit_log,
         })
     }
 
@@ -115,6 +128,16 @@ impl DataStorage {
 
         Ok(())
     }
+
+    /// Record an administrative action that is not part of the blockchain (e.g. evicting transactions).
+    ///
+    /// Entries are stored by the time they were recorded.
+    pub fn write_audit_entry(&self, entry: &AuditEntry) -> Result<(), BoxError> {
+        let time = timestamp_nanos().to_be_bytes();
+        self.audit_log.insert(time, postcard::to_stdvec(entry)?)?;
+        self.audit_log.flush()?;
+        Ok(())
+    }
 }
 
 // We do not expect a system time that far off:
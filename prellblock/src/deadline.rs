@@ -0,0 +1,223 @@
+//! Deadlines of transactions submitted with `ExecuteWithDeadline`.
+
+use crate::{batcher::Batcher, consensus::Consensus, peer::message as peer_message};
+use balise::Streaming;
+use err_derive::Error;
+use pinxit::{PeerId, Signed};
+use prellblock_client_api::{
+    consensus::{transaction_hash, TransactionHash},
+    ExecuteWithDeadline, ReceiptStatus, TransactionStatus,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::time;
+
+/// The interval in which the deadlines are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Final receipts are forgotten after this amount of time.
+const RECEIPT_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum number of remembered receipts.
+const MAX_RECEIPTS: usize = 100_000;
+
+/// An error of the `deadline` module.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The deadline already passed when the transaction was submitted.
+    #[error(display = "The deadline of the transaction already passed.")]
+    DeadlinePassed,
+
+    /// The transaction is not signed by the sender of the request.
+    #[error(display = "The transaction is not signed by the sender of the request.")]
+    NotSigner,
+
+    /// Too many transactions with a deadline are pending.
+    #[error(display = "Too many transactions with a deadline are pending.")]
+    TooManyReceipts,
+
+    /// The RPU has no receipt of the transaction (or already forgot it).
+    #[error(display = "Unknown receipt of transaction {}.", 0)]
+    UnknownReceipt(TransactionHash),
+}
+
+#[derive(Debug)]
+struct Entry {
+    request: Signed<ExecuteWithDeadline>,
+    status: ReceiptStatus,
+    /// When the status became final.
+    finished: Option<Instant>,
+}
+
+/// The receipts of the transactions with a deadline submitted to this RPU.
+///
+/// A transaction that is not committed by its deadline is removed from the queues of all RPUs
+/// and its receipt is marked as expired. A transaction that was already proposed is not removed,
+/// its receipt waits for the outcome of the proposal instead.
+/// Final receipts are remembered for `RECEIPT_LIFETIME`. The receipts are not persisted.
+pub struct Deadlines {
+    batcher: Arc<Batcher>,
+    consensus: Arc<Consensus>,
+    receipts: Mutex<HashMap<TransactionHash, Entry>>,
+}
+
+impl Deadlines {
+    /// Create a new `Deadlines` instance and start checking the deadlines.
+    #[must_use]
+    pub fn new(batcher: Arc<Batcher>, consensus: Arc<Consensus>) -> Arc<Self> {
+        let deadlines = Arc::new(Self {
+            batcher,
+            consensus,
+            receipts: Mutex::default(),
+        });
+        let checker = deadlines.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                checker.check().await;
+            }
+        });
+        deadlines
+    }
+
+    /// Remember the deadline of a transaction submitted with the `request`.
+    ///
+    /// Returns `false` if the transaction is already known (e.g. the submission was retried).
+    pub fn insert(&self, request: Signed<ExecuteWithDeadline>) -> Result<bool, Error> {
+        let deadline = request.unverified_ref().deadline;
+        if deadline <= SystemTime::now() {
+            return Err(Error::DeadlinePassed);
+        }
+
+        let hash = transaction_hash(&request.unverified_ref().transaction);
+        let mut receipts = self.receipts.lock().unwrap();
+        if receipts.contains_key(&hash) {
+            return Ok(false);
+        }
+        if receipts.len() >= MAX_RECEIPTS {
+            return Err(Error::TooManyReceipts);
+        }
+        receipts.insert(
+            hash,
+            Entry {
+                request,
+                status: ReceiptStatus::Pending { deadline },
+                finished: None,
+            },
+        );
+        Ok(true)
+    }
+
+    /// Forget the receipt of the transaction with the given `hash` (e.g. because the submission failed).
+    pub fn remove(&self, hash: &TransactionHash) {
+        self.receipts.lock().unwrap().remove(hash);
+    }
+
+    /// Get the status of the receipt of the transaction with the given `hash` signed by `signer`.
+    pub fn status(&self, hash: &TransactionHash, signer: &PeerId) -> Result<ReceiptStatus, Error> {
+        let receipts = self.receipts.lock().unwrap();
+        let entry = receipts.get(hash).ok_or(Error::UnknownReceipt(*hash))?;
+        if entry.request.signer() != signer {
+            return Err(Error::NotSigner);
+        }
+        Ok(entry.status.clone())
+    }
+
+    /// Stream the status of the receipt of the transaction with the given `hash` signed by `signer`.
+    ///
+    /// The current status is sent first, followed by every change until the status is final.
+    pub fn watch(
+        self: &Arc<Self>,
+        hash: TransactionHash,
+        signer: PeerId,
+    ) -> Result<Streaming<ReceiptStatus>, Error> {
+        let mut status = self.status(&hash, &signer)?;
+        let (mut sender, streaming) = Streaming::channel(1);
+        let deadlines = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if sender.send(&status).await.is_err() || status.is_final() {
+                    return;
+                }
+                loop {
+                    time::delay_for(CHECK_INTERVAL).await;
+                    match deadlines.status(&hash, &signer) {
+                        Ok(new_status) if new_status == status => {}
+                        Ok(new_status) => {
+                            status = new_status;
+                            break;
+                        }
+                        Err(err) => {
+                            sender.abort(err).await;
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(streaming)
+    }
+
+    /// Update the pending receipts and expire the transactions whose deadline passed.
+    async fn check(&self) {
+        let now = SystemTime::now();
+        let mut expired = Vec::new();
+        {
+            let mut receipts = self.receipts.lock().unwrap();
+            receipts.retain(|_, entry| {
+                entry
+                    .finished
+                    .map_or(true, |finished| finished.elapsed() < RECEIPT_LIFETIME)
+            });
+            for (hash, entry) in receipts.iter_mut() {
+                let deadline = match entry.status {
+                    ReceiptStatus::Pending { deadline } => deadline,
+                    _ => continue,
+                };
+                entry.status = match self.consensus.transaction_trace(hash).status {
+                    TransactionStatus::Committed { block_number } => {
+                        ReceiptStatus::Committed { block_number }
+                    }
+                    TransactionStatus::Rejected { block_number } => {
+                        ReceiptStatus::Rejected { block_number }
+                    }
+                    TransactionStatus::Evicted => ReceiptStatus::Evicted,
+                    TransactionStatus::Failed {
+                        block_number,
+                        reason,
+                    } => ReceiptStatus::Failed {
+                        block_number,
+                        reason,
+                    },
+                    // The outcome of a proposal is awaited even if the deadline passes.
+                    TransactionStatus::Proposed { .. } => continue,
+                    _ if deadline <= now => {
+                        expired.push(entry.request.clone());
+                        ReceiptStatus::Expired { deadline }
+                    }
+                    _ => continue,
+                };
+                entry.finished = Some(Instant::now());
+            }
+        }
+
+        // Every RPU (including this one) removes the transactions from its own queue.
+        let broadcaster = self.batcher.broadcaster();
+        for request in expired {
+            let hash = transaction_hash(&request.unverified_ref().transaction);
+            log::info!(
+                "Transaction {} of {} was not committed by its deadline.",
+                hash,
+                request.signer()
+            );
+            let message = peer_message::ExpireTransaction(broadcaster.chain_id(), request);
+            if let Err(err) = broadcaster.broadcast(&message).await {
+                log::warn!("Could not expire transaction {} on all RPUs: {}", hash, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Protecting the databases of an RPU from a full disk.
+//!
+//! A watchdog periodically checks the free space on the disks of the databases of a chain.
+//! Below the threshold, the RPU is write-protected: the `Turi` rejects all transactions,
+//! so the databases do not run out of space (which can corrupt them).
+//! Transactions that are already queued and the consensus keep working,
+//! so the blocks that are already proposed are still committed.
+
+use crate::event_bus::{Event, EventBus};
+use err_derive::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::time;
+
+/// The configuration of the disk space watchdog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskSpaceConfig {
+    /// The minimum free space (in MiB) on the disks of the databases.
+    ///
+    /// Transactions are accepted again when 10% more space is free.
+    pub min_free_mb: u64,
+    /// The time (in ms) between two checks of the free space.
+    pub check_interval_ms: u64,
+}
+
+impl Default for DiskSpaceConfig {
+    fn default() -> Self {
+        Self {
+            min_free_mb: 1024,
+            check_interval_ms: 5000,
+        }
+    }
+}
+
+/// An error of the `disk_space` module.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The request was rejected because the disk space is low.
+    #[error(
+        display = "The RPU is write-protected: only {} MiB are free on the disk of {}.",
+        free_mb,
+        path
+    )]
+    WriteProtected {
+        /// The database with the least free space.
+        path: String,
+        /// The free space in MiB.
+        free_mb: u64,
+    },
+}
+
+/// A `DiskSpaceGuard` decides whether transactions are accepted.
+#[derive(Debug)]
+pub struct DiskSpaceGuard {
+    config: DiskSpaceConfig,
+    paths: Vec<String>,
+    events: EventBus,
+    /// The database with the least free space and its free space (while write-protected).
+    low: RwLock<Option<(String, u64)>>,
+}
+
+impl DiskSpaceGuard {
+    /// Create a new `DiskSpaceGuard` watching the disks of the databases at `paths`.
+    ///
+    /// All transactions are accepted until the first check.
+    #[must_use]
+    pub fn new(config: DiskSpaceConfig, paths: Vec<String>) -> Self {
+        Self {
+            config,
+            paths,
+            events: EventBus::default(),
+            low: RwLock::new(None),
+        }
+    }
+
+    /// Publish `DiskSpaceLow` and `DiskSpaceRecovered` events to `events`.
+    #[must_use]
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Verify that transactions are accepted.
+    pub fn verify_writable(&self) -> Result<(), Error> {
+        match &*self.low.read().unwrap() {
+            Some((path, free_mb)) => Err(Error::WriteProtected {
+                path: path.clone(),
+                free_mb: *free_mb,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Execute the watchdog.
+    pub async fn watch(self: Arc<Self>) {
+        loop {
+            self.check();
+            time::delay_for(Duration::from_millis(self.config.check_interval_ms)).await;
+        }
+    }
+
+    /// Check the free space once and update the write protection.
+    pub fn check(&self) {
+        let (path, free_mb) = match self.lowest_free_space() {
+            Some(lowest) => lowest,
+            None => return,
+        };
+
+        let min_free_mb = self.config.min_free_mb;
+        let mut low = self.low.write().unwrap();
+        if low.is_none() && free_mb < min_free_mb {
+            log::error!(
+                "Only {} MiB are free on the disk of {}, rejecting transactions.",
+                free_mb,
+                path
+            );
+            self.events.publish(Event::DiskSpaceLow {
+                path: path.clone(),
+                free_mb,
+            });
+            *low = Some((path, free_mb));
+        } else if low.is_some() {
+            if free_mb < min_free_mb.saturating_add(min_free_mb / 10) {
+                *low = Some((path, free_mb));
+            } else {
+                log::info!(
+                    "{} MiB are free on the disk of {}, accepting transactions again.",
+                    free_mb,
+                    path
+                );
+                self.events.publish(Event::DiskSpaceRecovered { free_mb });
+                *low = None;
+            }
+        }
+    }
+
+    /// Get the database with the least free space on its disk and the free space in MiB.
+    fn lowest_free_space(&self) -> Option<(String, u64)> {
+        self.paths
+            .iter()
+            .filter_map(|path| match fs2::available_space(path) {
+                Ok(free) => Some((path.clone(), free / 1024 / 1024)),
+                Err(err) => {
+                    log::warn!(
+                        "Could not get the free space on the disk of {}: {}",
+                        path,
+                        err
+                    );
+                    None
+                }
+            })
+            .min_by_key(|(_, free_mb)| *free_mb)
+    }
+}
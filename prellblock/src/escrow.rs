@@ -0,0 +1,97 @@
+//! The shares of escrowed keys held by an RPU (see `prellblock_client_api::escrow`).
+//!
+//! An RPU releases its share of an escrowed key only to auditors authorized on-chain
+//! with the approval of a supermajority of the RPUs (see `Transaction::AuthorizeDecryption`),
+//! until their authorization expires.
+//! A request is only accepted by the RPU it names and within the timestamp tolerance,
+//! and the share is sealed to the recipient key of the request. Every release is logged.
+
+use crate::{world_state::WorldStateService, BoxError};
+use pinxit::PeerId;
+use prellblock_client_api::{
+    escrow::{KeyShare, SealedShare, ShareHash},
+    parameter::Parameter,
+    EscrowShare, GetEscrowShare,
+};
+use std::{collections::HashMap, fs, time::SystemTime};
+
+/// The shares of escrowed keys held by an RPU.
+pub struct EscrowShares {
+    peer_id: PeerId,
+    world_state: WorldStateService,
+    shares: HashMap<ShareHash, KeyShare>,
+}
+
+impl EscrowShares {
+    /// Load the shares of the RPU `peer_id` from a YAML file (a list of hex encoded shares).
+    ///
+    /// The file is only read once: Shares installed later are only released after a restart of the RPU.
+    pub fn load(
+        peer_id: PeerId,
+        world_state: WorldStateService,
+        path: &str,
+    ) -> Result<Self, BoxError> {
+        let shares: Vec<KeyShare> = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        let shares = shares
+            .into_iter()
+            .map(|share| (share.hash(), share))
+            .collect();
+        Ok(Self {
+            peer_id,
+            world_state,
+            shares,
+        })
+    }
+
+    /// Release the share of the escrowed key requested by an `auditor`.
+    pub fn release(
+        &self,
+        auditor: &PeerId,
+        request: &GetEscrowShare,
+    ) -> Result<EscrowShare, BoxError> {
+        let key_reference = &request.key_reference;
+        if request.rpu != self.peer_id {
+            return Err(format!("The request is meant for the RPU {}.", request.rpu).into());
+        }
+
+        let world_state = self.world_state.get();
+        let now = SystemTime::now();
+        let tolerance = world_state.parameter_duration(Parameter::TimestampTolerance);
+        let age = match now.duration_since(request.timestamp) {
+            Ok(age) => age,
+            Err(err) => err.duration(),
+        };
+        if age > tolerance {
+            return Err("The request is stale (or its timestamp is in the future).".into());
+        }
+
+        let escrowed_key = world_state
+            .escrowed_keys
+            .get(key_reference)
+            .ok_or_else(|| format!("The key {} is not escrowed.", key_reference))?;
+        if !escrowed_key.is_auditor(auditor, now) {
+            return Err(format!(
+                "The account {} is not (or no longer) authorized to decrypt the key {}.",
+                auditor, key_reference
+            )
+            .into());
+        }
+        let share = escrowed_key
+            .share_hash(&self.peer_id)
+            .and_then(|share_hash| self.shares.get(share_hash))
+            .ok_or_else(|| format!("This RPU holds no share of the key {}.", key_reference))?;
+
+        log::warn!(
+            "Releasing the share of the key {} to the auditor {}.",
+            key_reference,
+            auditor
+        );
+        let sealed_share =
+            SealedShare::seal(share, &request.recipient_key, &self.peer_id, key_reference)?;
+        Ok(EscrowShare {
+            sealed_share,
+            key_hash: escrowed_key.key_hash,
+            threshold: escrowed_key.threshold,
+        })
+    }
+}
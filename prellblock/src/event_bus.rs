@@ -0,0 +1,104 @@
+//! A typed bus for internal events (e.g. committed blocks and view changes).
+//!
+//! Subsystems publish events without knowing who is interested in them.
+//! Other subsystems (e.g. mirrors, alerting or metrics) subscribe to the events
+//! instead of reaching into the consensus.
+//! A subscriber that falls more than `CAPACITY` events behind misses the oldest events
+//! (`RecvError::Lagged`), so slow subscribers never block the publishers.
+
+use pinxit::PeerId;
+use prellblock_client_api::{
+    consensus::{BlockHash, BlockNumber, LeaderTerm},
+    ConsensusFailure,
+};
+use std::net::SocketAddr;
+use tokio::sync::broadcast;
+
+pub use broadcast::{Receiver, RecvError};
+
+/// The number of events buffered for each subscriber.
+const CAPACITY: usize = 1024;
+
+/// An internal event.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A block was committed (written to the `BlockStorage` and applied to the `WorldState`).
+    BlockCommitted {
+        /// The number of the block.
+        block_number: BlockNumber,
+        /// The hash of the block.
+        block_hash: BlockHash,
+        /// The number of transactions in the block.
+        transactions: usize,
+    },
+    /// The last committed block was rolled back (after a view change).
+    BlockRolledBack {
+        /// The number of the block.
+        block_number: BlockNumber,
+    },
+    /// A `WorldState` snapshot (and the blocks leading to it) was installed.
+    SnapshotInstalled {
+        /// The number of the next block.
+        block_number: BlockNumber,
+    },
+    /// A new leader term started.
+    ViewChanged {
+        /// The new leader term.
+        leader_term: LeaderTerm,
+        /// The leader of the new leader term.
+        leader: PeerId,
+    },
+    /// Requests to an RPU started or stopped failing.
+    PeerHealthChanged {
+        /// The peer address of the RPU.
+        peer_address: SocketAddr,
+        /// Whether the last request to the RPU succeeded.
+        reachable: bool,
+    },
+    /// A consensus failure was recorded.
+    ConsensusFailure(ConsensusFailure),
+    /// The RPU was halted because of a fatal error (the reason).
+    Halted(String),
+    /// The free disk space fell below the threshold, the RPU is write-protected.
+    DiskSpaceLow {
+        /// The database with the least free space.
+        path: String,
+        /// The free space in MiB.
+        free_mb: u64,
+    },
+    /// Enough disk space is free again, the RPU accepts transactions again.
+    DiskSpaceRecovered {
+        /// The least free space in MiB.
+        free_mb: u64,
+    },
+}
+
+/// A bus to publish `Event`s to all subscribers.
+///
+/// Clones of a bus publish to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    /// Publish an `event` to all current subscribers.
+    pub fn publish(&self, event: Event) {
+        log::trace!("Publishing event: {:?}", event);
+        // Publishing without subscribers is fine.
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribe to all events published from now on.
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
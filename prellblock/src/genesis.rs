@@ -0,0 +1,37 @@
+//! The verification of the genesis transactions on the first start.
+//!
+//! An RPU only creates the genesis block of a chain from genesis transactions
+//! approved as configured (see `GenesisTransactions::approve`).
+//! Without a configured authority and with `founding_rpus` disabled, any genesis is accepted,
+//! but all given approvals must still be valid.
+
+use pinxit::PeerId;
+use prellblock_client_api::consensus::{GenesisError, GenesisTransactions};
+use serde::{Deserialize, Serialize};
+use std::iter;
+
+/// The approvals required for the genesis transactions of a chain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenesisConfig {
+    /// The bootstrap authority that must approve the genesis transactions.
+    pub authority: Option<PeerId>,
+    /// Accept the genesis transactions if all founding RPUs approved them.
+    ///
+    /// If an `authority` is configured as well, either approval is sufficient.
+    pub founding_rpus: bool,
+}
+
+impl GenesisConfig {
+    /// Verify that the `genesis` transactions are approved as configured.
+    pub fn verify(&self, genesis: &GenesisTransactions) -> Result<(), GenesisError> {
+        match (&self.authority, self.founding_rpus) {
+            (None, false) => genesis.verify_approvals(iter::empty()),
+            (Some(authority), false) => genesis.verify_approvals(iter::once(authority)),
+            (None, true) => genesis.verify_approvals(&genesis.founding_rpus()),
+            (Some(authority), true) => genesis
+                .verify_approvals(iter::once(authority))
+                .or_else(|_| genesis.verify_approvals(&genesis.founding_rpus())),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Recently used idempotency keys of client submissions.
+
+use err_derive::Error;
+use pinxit::PeerId;
+use prellblock_client_api::{Receipt, MAX_IDEMPOTENCY_KEY_LEN};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The number of keys remembered per account.
+const MAX_KEYS_PER_ACCOUNT: usize = 1024;
+
+/// Keys are forgotten after this amount of time.
+const KEY_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// An error of the `idempotency` module.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The idempotency key is too long.
+    #[error(display = "The idempotency key is too long ({} bytes).", 0)]
+    KeyTooLong(usize),
+
+    /// The idempotency key was already used for another transaction.
+    #[error(
+        display = "The idempotency key {:?} was already used for another transaction.",
+        0
+    )]
+    KeyReused(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    receipt: Receipt,
+    inserted: Instant,
+}
+
+/// The recently used idempotency keys of all accounts.
+///
+/// Only the newest `MAX_KEYS_PER_ACCOUNT` keys of each account are remembered
+/// for at most `KEY_LIFETIME`. The keys are not persisted.
+#[derive(Debug, Default)]
+pub struct IdempotencyKeys {
+    accounts: Mutex<HashMap<PeerId, VecDeque<Entry>>>,
+}
+
+impl IdempotencyKeys {
+    /// Remember the `receipt` for the `key` of the account `signer`.
+    ///
+    /// If the key was used before for the same transaction, the original `Receipt` is returned
+    /// and the `receipt` is discarded.
+    pub fn insert(
+        &self,
+        signer: &PeerId,
+        key: &str,
+        receipt: Receipt,
+    ) -> Result<Option<Receipt>, Error> {
+        if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+            return Err(Error::KeyTooLong(key.len()));
+        }
+
+        let mut accounts = self.accounts.lock().unwrap();
+        let entries = accounts.entry(signer.clone()).or_default();
+        while entries
+            .front()
+            .map_or(false, |entry| entry.inserted.elapsed() > KEY_LIFETIME)
+        {
+            entries.pop_front();
+        }
+
+        if let Some(entry) = entries.iter().find(|entry| entry.key == key) {
+            return if entry.receipt.transaction_hash == receipt.transaction_hash {
+                Ok(Some(entry.receipt.clone()))
+            } else {
+                Err(Error::KeyReused(key.to_string()))
+            };
+        }
+
+        if entries.len() >= MAX_KEYS_PER_ACCOUNT {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            key: key.to_string(),
+            receipt,
+            inserted: Instant::now(),
+        });
+        Ok(None)
+    }
+
+    /// Forget the `key` of the account `signer` (e.g. because the submission failed).
+    pub fn remove(&self, signer: &PeerId, key: &str) {
+        if let Some(entries) = self.accounts.lock().unwrap().get_mut(signer) {
+            entries.retain(|entry| entry.key != key);
+        }
+    }
+}
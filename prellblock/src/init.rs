@@ -0,0 +1,352 @@
+//! Generation of all files needed to run a (test) cluster of RPUs.
+//!
+//! In contrast to the genesis wizard, nothing is asked interactively:
+//! All RPUs get consecutive ports, the TLS certificates are signed by a new CA
+//! and all private keys are protected by the default password `prellblock`.
+
+use hexutil::ToHex;
+use newtype_enum::Enum;
+use openssl::{
+    asn1::{Asn1Integer, Asn1Time},
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    error::ErrorStack,
+    hash::MessageDigest,
+    nid::Nid,
+    pkcs12::Pkcs12,
+    pkey::{PKey, Private},
+    symm::Cipher,
+    x509::{
+        extension::{
+            BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+            SubjectKeyIdentifier,
+        },
+        X509Name, X509NameBuilder, X509,
+    },
+};
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{
+    genesis::GenesisConfig, peer::NetworkConfig, storage::StorageConfig, turi::TuriConfig,
+    world_state::FaultTolerance, RpuPrivateConfig,
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    consensus::{BlockNumber, GenesisTransactions, HashAlgorithm, SignatureList},
+    parameter::Parameter,
+    transaction, Transaction,
+};
+use std::{
+    error::Error,
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    time::SystemTime,
+};
+use structopt::StructOpt;
+use zeroize::Zeroizing;
+
+/// The password of the generated TLS identities and the CA private key.
+const PASSWORD: &str = "prellblock";
+
+/// The number of days the generated certificates are valid.
+const CERTIFICATE_VALIDITY_DAYS: u32 = 365;
+
+/// Generate identities, TLS certificates, genesis transactions and configurations for a cluster.
+#[derive(StructOpt, Debug)]
+pub struct InitOpt {
+    /// The number of RPUs.
+    #[structopt(short, long, default_value = "4")]
+    rpus: usize,
+    /// The number of faulty RPUs to tolerate (at least `3f + 1` RPUs are needed).
+    ///
+    /// If not set, as many faulty RPUs as the number of RPUs allows are tolerated.
+    #[structopt(short = "f", long)]
+    fault_tolerance: Option<usize>,
+    /// The hash function of the blocks (`blake2b` or `sha256`).
+    #[structopt(long, default_value = "blake2b")]
+    hash_algorithm: HashAlgorithm,
+    /// The directory to write all files to.
+    #[structopt(short, long, default_value = "config")]
+    output: String,
+    /// The IP address all RPUs listen on.
+    #[structopt(long, default_value = "127.0.0.1")]
+    ip: IpAddr,
+    /// The Turi port of the first RPU (incremented for each further RPU).
+    #[structopt(long, default_value = "3130")]
+    turi_port: u16,
+    /// The peer port of the first RPU (incremented for each further RPU).
+    #[structopt(long, default_value = "2480")]
+    peer_port: u16,
+}
+
+/// Write all files of a new cluster as configured in `opt`.
+pub fn init(opt: InitOpt) -> Result<(), Box<dyn Error>> {
+    let fault_tolerance = FaultTolerance::new(opt.rpus, opt.fault_tolerance.unwrap_or(0))?;
+    let genesis_path = format!("{}/genesis/genesis.yaml", opt.output);
+    if Path::new(&genesis_path).exists() {
+        return Err(format!("{} already exists.", genesis_path).into());
+    }
+
+    // The CA signs the TLS certificates, the signing identity signs the genesis transactions.
+    let (ca_cert, ca_key) = create_ca_cert()?;
+    let signing_identity = Identity::generate();
+    let ca_directory = format!("{}/ca", opt.output);
+    fs::create_dir_all(&ca_directory)?;
+    fs::write(
+        format!("{}/ca.key", ca_directory),
+        signing_identity.to_hex(),
+    )?;
+    fs::write(
+        format!("{}/ca-certificate.pem", ca_directory),
+        ca_cert.to_pem()?,
+    )?;
+    fs::write(
+        format!("{}/ca-private-key.pem", ca_directory),
+        ca_key.private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), PASSWORD.as_bytes())?,
+    )?;
+
+    let mut transactions = Vec::new();
+    for index in 0..opt.rpus {
+        let name = format!("rpu-{}", index + 1);
+        #[allow(clippy::cast_possible_truncation)]
+        let offset = index as u16;
+        let turi_address = SocketAddr::new(opt.ip, opt.turi_port + offset);
+        let peer_address = SocketAddr::new(opt.ip, opt.peer_port + offset);
+
+        let (peer_id, key_path) = write_identity(&opt.output, &name)?;
+
+        let (cert, key) = create_rpu_cert(&ca_cert, &ca_key, &name, opt.ip)?;
+        let pfx_path = format!("{}/{1}/{1}.pfx", opt.output, name);
+        let pkcs12 = Pkcs12::builder().build(PASSWORD, "cert", &key, &cert)?;
+        fs::write(&pfx_path, pkcs12.to_der()?)?;
+
+        let rpu_config = RpuPrivateConfig {
+            identity: key_path,
+            tls_id: pfx_path,
+            block_path: format!("blocks/{}", name),
+            data_path: format!("data/{}", name),
+            ban_list_path: Some(format!("ban_lists/{}", name)),
+            escrow_shares: None,
+            world_state: None,
+            queue_spill: None,
+            genesis: GenesisConfig {
+                authority: Some(signing_identity.id().clone()),
+                founding_rpus: false,
+            },
+            observer: None,
+            turi: TuriConfig::default(),
+            load_shedding: None,
+            disk_space: None,
+            chains: Vec::new(),
+            network: NetworkConfig::default(),
+            storage: StorageConfig::default(),
+        };
+        fs::write(
+            format!("{}/{1}/{1}.toml", opt.output, name),
+            toml::to_string(&rpu_config)?,
+        )?;
+
+        let account_type = AccountType::RPU {
+            turi_address,
+            peer_address,
+        };
+        transactions.push(create_account(
+            &signing_identity,
+            peer_id,
+            name,
+            account_type,
+        )?);
+    }
+
+    // An admin is needed to manage the accounts after the start.
+    let (admin_id, _) = write_identity(&opt.output, "admin")?;
+    transactions.push(create_account(
+        &signing_identity,
+        admin_id,
+        "admin".to_string(),
+        AccountType::Admin,
+    )?);
+
+    // The declared fault tolerance applies from the genesis block on.
+    if opt.fault_tolerance.is_some() {
+        transactions.push(
+            Transaction::from_variant(transaction::SetParameter {
+                parameter: Parameter::FaultTolerance,
+                value: fault_tolerance.faulty() as u64,
+                activation_height: BlockNumber::default(),
+                timestamp: SystemTime::now(),
+            })
+            .sign(&signing_identity)?,
+        );
+    }
+
+    // The RPUs only accept the genesis transactions approved by the signing identity.
+    let mut genesis = GenesisTransactions {
+        transactions,
+        timestamp: SystemTime::now(),
+        hash_algorithm: opt.hash_algorithm,
+        approvals: SignatureList::default(),
+    };
+    genesis.approve(&signing_identity)?;
+    fs::create_dir_all(format!("{}/genesis", opt.output))?;
+    fs::write(&genesis_path, serde_yaml::to_string(&genesis)?)?;
+
+    println!(
+        "Created a cluster of {} RPUs tolerating {} faulty RPUs in {}. Start each RPU with:",
+        opt.rpus,
+        fault_tolerance.faulty(),
+        opt.output
+    );
+    println!(
+        "prellblock {}/<rpu>/<rpu>.toml {}",
+        opt.output, genesis_path
+    );
+    Ok(())
+}
+
+/// Approve the genesis transactions at `genesis_path` with the identity at `identity_path`.
+///
+/// The approval is added to the file, so several identities (e.g. the founding RPUs) can approve it in turn.
+pub fn approve_genesis(genesis_path: &str, identity_path: &str) -> Result<PeerId, Box<dyn Error>> {
+    let mut genesis: GenesisTransactions =
+        serde_yaml::from_str(&fs::read_to_string(genesis_path)?)?;
+    let identity = Identity::from_file(identity_path)?;
+    genesis.approve(&identity)?;
+    fs::write(genesis_path, serde_yaml::to_string(&genesis)?)?;
+    Ok(identity.id().clone())
+}
+
+/// Approve the authorization of an audit at `authorization_path` with the identity of an RPU at `identity_path`.
+///
+/// The approval is added to the file, so a supermajority of the RPUs can approve it in turn.
+pub fn approve_decryption(
+    authorization_path: &str,
+    identity_path: &str,
+) -> Result<PeerId, Box<dyn Error>> {
+    let mut authorization: transaction::AuthorizeDecryption =
+        serde_yaml::from_str(&fs::read_to_string(authorization_path)?)?;
+    let identity = Identity::from_file(identity_path)?;
+    authorization.approve(&identity)?;
+    fs::write(authorization_path, serde_yaml::to_string(&authorization)?)?;
+    Ok(identity.id().clone())
+}
+
+/// Generate a new identity and write its keys to `<output>/<name>/<name>.{key,pub}`.
+///
+/// Returns the `PeerId` and the path of the private key.
+fn write_identity(output: &str, name: &str) -> Result<(PeerId, String), Box<dyn Error>> {
+    let identity = Identity::generate();
+    fs::create_dir_all(format!("{}/{}", output, name))?;
+    let key_path = format!("{}/{1}/{1}.key", output, name);
+    fs::write(&key_path, Zeroizing::new(identity.to_hex()).as_bytes())?;
+    fs::write(
+        format!("{}/{1}/{1}.pub", output, name),
+        identity.id().to_hex(),
+    )?;
+    Ok((identity.id().clone(), key_path))
+}
+
+fn create_account(
+    signing_identity: &Identity,
+    id: PeerId,
+    name: String,
+    account_type: AccountType,
+) -> Result<Signed<Transaction>, pinxit::Error> {
+    Transaction::from_variant(transaction::CreateAccount {
+        id,
+        name,
+        permissions: Permissions {
+            account_type: Some(account_type),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(false),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    })
+    .sign(signing_identity)
+}
+
+fn create_ca_cert() -> Result<(X509, PKey<Private>), ErrorStack> {
+    let pkey = generate_private_key()?;
+    let name = x509_name("prellblock-ca")?;
+
+    let mut x509 = X509::builder()?;
+    x509.set_not_after(&Asn1Time::days_from_now(CERTIFICATE_VALIDITY_DAYS)?)?;
+    x509.set_not_before(&Asn1Time::days_from_now(0)?)?;
+    x509.set_subject_name(&name)?;
+    x509.set_issuer_name(&name)?;
+    x509.set_version(2)?; // version 3
+    x509.set_pubkey(&pkey)?;
+    x509.set_serial_number(&generate_serial_number()?)?;
+
+    // Without this extension, it is no CA.
+    x509.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+    let key_identifier = SubjectKeyIdentifier::new().build(&x509.x509v3_context(None, None))?;
+    x509.append_extension(key_identifier)?;
+
+    x509.sign(&pkey, MessageDigest::sha512())?;
+    Ok((x509.build(), pkey))
+}
+
+fn create_rpu_cert(
+    ca_cert: &X509,
+    ca_key: &PKey<Private>,
+    name: &str,
+    ip: IpAddr,
+) -> Result<(X509, PKey<Private>), ErrorStack> {
+    let pkey = generate_private_key()?;
+
+    let mut x509 = X509::builder()?;
+    x509.set_not_after(&Asn1Time::days_from_now(CERTIFICATE_VALIDITY_DAYS)?)?;
+    x509.set_not_before(&Asn1Time::days_from_now(0)?)?;
+    x509.set_subject_name(&x509_name(name)?)?;
+    x509.set_issuer_name(ca_cert.subject_name())?;
+    x509.set_version(2)?; // version 3
+    x509.set_pubkey(&pkey)?;
+    x509.set_serial_number(&generate_serial_number()?)?;
+
+    let alternative_names = SubjectAlternativeName::new()
+        .dns(name)
+        .ip(&ip.to_string())
+        .build(&x509.x509v3_context(None, None))?;
+    x509.append_extension(alternative_names)?;
+
+    // Create extension for using as TLS.
+    let key_usage = KeyUsage::new()
+        .digital_signature()
+        .non_repudiation()
+        .key_encipherment()
+        .data_encipherment()
+        .build()?;
+    x509.append_extension(key_usage)?;
+    x509.append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+
+    // This is not a CA!
+    x509.append_extension(BasicConstraints::new().build()?)?;
+    let key_identifier = SubjectKeyIdentifier::new().build(&x509.x509v3_context(None, None))?;
+    x509.append_extension(key_identifier)?;
+
+    x509.sign(ca_key, MessageDigest::sha512())?;
+    Ok((x509.build(), pkey))
+}
+
+fn x509_name(common_name: &str) -> Result<X509Name, ErrorStack> {
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "Prellblock")?;
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+    Ok(name.build())
+}
+
+fn generate_private_key() -> Result<PKey<Private>, ErrorStack> {
+    let group = EcGroup::from_curve_name(Nid::SECP384R1)?;
+    PKey::from_ec_key(EcKey::generate(&group)?)
+}
+
+fn generate_serial_number() -> Result<Asn1Integer, ErrorStack> {
+    // Must be unique for each certificate of this authority.
+    // But selecting a random number should be sufficient in this case.
+    let mut serial = BigNum::new()?;
+    serial.rand(8 * 20, MsbOption::MAYBE_ZERO, true)?;
+    serial.set_negative(false);
+    serial.to_asn1_integer()
+}
@@ -15,13 +15,28 @@
 
 use serde::{Deserialize, Serialize};
 
+pub mod access_log;
+pub mod backup;
+pub mod ban_list;
 pub mod batcher;
 pub mod block_storage;
 pub mod consensus;
 pub mod data_broadcaster;
 pub mod data_storage;
+pub mod deadline;
+pub mod disk_space;
+pub mod escrow;
+pub mod event_bus;
+pub mod genesis;
+pub mod idempotency;
+pub mod load_shedding;
+pub mod observer;
 pub mod peer;
 pub mod reader;
+pub mod session;
+pub mod storage;
+pub mod subscription;
+pub mod systemd;
 pub mod transaction_checker;
 pub mod turi;
 pub mod world_state;
@@ -40,4 +55,74 @@ pub struct RpuPrivateConfig {
     pub block_path: String,
     /// The path to the directory for the `DataStorage`.
     pub data_path: String,
+    /// The path to the directory for the `BanList` (defaults to `<data_path>_ban_list`).
+    #[serde(default)]
+    pub ban_list_path: Option<String>,
+    /// The path to the shares of escrowed keys held by the RPU (see `escrow`).
+    #[serde(default)]
+    pub escrow_shares: Option<String>,
+    /// Persist the `WorldState` instead of rebuilding it from the `BlockStorage` on every start.
+    #[serde(default)]
+    pub world_state: Option<world_state::PersistenceConfig>,
+    /// Spill the overflow of the transaction queue to disk (disabled if not given).
+    #[serde(default)]
+    pub queue_spill: Option<consensus::QueueSpillConfig>,
+    /// The approvals required for the genesis transactions on the first start.
+    #[serde(default)]
+    pub genesis: genesis::GenesisConfig,
+    /// Run the RPU as a read-only observer instead of participating in the consensus.
+    #[serde(default)]
+    pub observer: Option<observer::ObserverConfig>,
+    /// The configuration of the client-facing `Turi` server.
+    #[serde(default)]
+    pub turi: turi::TuriConfig,
+    /// Shed load under resource pressure (disabled if not given).
+    #[serde(default)]
+    pub load_shedding: Option<load_shedding::LoadSheddingConfig>,
+    /// Reject transactions while the disk space is low (disabled if not given).
+    #[serde(default)]
+    pub disk_space: Option<disk_space::DiskSpaceConfig>,
+    /// Additional (independent) chains served by the RPU.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chains: Vec<ChainConfig>,
+    /// The configuration of the connections to other RPUs.
+    #[serde(default)]
+    pub network: peer::NetworkConfig,
+    /// The tuning of the databases of all chains.
+    #[serde(default)]
+    pub storage: storage::StorageConfig,
+}
+
+/// The Configuration of an additional chain served by an RPU.
+///
+/// Each chain has its own `BlockStorage`, `WorldState` and consensus.
+/// Signatures of consensus messages do not cover the chain, so each chain needs its own identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// The name of the chain (only used for logging).
+    pub name: String,
+    /// The `PeerId` of the RPU in this chain.
+    pub identity: String, // pinxit::Identity (hex -> .key)
+    /// The path to the genesis transactions file (only needed for the first start).
+    #[serde(default)]
+    pub genesis_transactions: Option<String>,
+    /// The path to the directory for the `BlockStorage`.
+    pub block_path: String,
+    /// The path to the directory for the `DataStorage`.
+    pub data_path: String,
+    /// The path to the directory for the `BanList` (defaults to `<data_path>_ban_list`).
+    #[serde(default)]
+    pub ban_list_path: Option<String>,
+    /// The path to the shares of escrowed keys held by the RPU in this chain (see `escrow`).
+    #[serde(default)]
+    pub escrow_shares: Option<String>,
+    /// Persist the `WorldState` instead of rebuilding it from the `BlockStorage` on every start.
+    #[serde(default)]
+    pub world_state: Option<world_state::PersistenceConfig>,
+    /// Spill the overflow of the transaction queue to disk (disabled if not given).
+    #[serde(default)]
+    pub queue_spill: Option<consensus::QueueSpillConfig>,
+    /// The approvals required for the genesis transactions on the first start.
+    #[serde(default)]
+    pub genesis: genesis::GenesisConfig,
 }
@@ -0,0 +1,130 @@
+//! Shedding load while the RPU is under resource pressure.
+//!
+//! A watchdog periodically checks the queue depth, the memory usage and the commit lag of the RPU.
+//! While a threshold is exceeded, the `Turi` rejects low-priority requests
+//! (key-value transactions and bulk block reads used for mirroring and exports)
+//! with a hint when to retry. Consensus messages and account transactions are never rejected,
+//! so the RPU keeps taking part in the consensus.
+
+use crate::consensus::Consensus;
+use err_derive::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::time;
+
+/// The time between two checks of the watchdog.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The configuration of the load shedding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadSheddingConfig {
+    /// The maximum number of transactions waiting in the queue.
+    pub max_queue_len: usize,
+    /// The maximum resident memory of the RPU process in MiB (only checked on Linux).
+    pub max_memory_mb: Option<u64>,
+    /// The maximum time (in ms) the oldest queued transaction may wait to be committed.
+    pub max_commit_lag_ms: u64,
+    /// The time (in ms) clients are asked to wait before retrying a rejected request.
+    pub retry_after_ms: u64,
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            max_queue_len: 100_000,
+            max_memory_mb: None,
+            max_commit_lag_ms: 30_000,
+            retry_after_ms: 5000,
+        }
+    }
+}
+
+/// An error of the `load_shedding` module.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The request was rejected because the RPU is overloaded.
+    #[error(display = "The RPU is overloaded ({}). Retry after {:?}.", 0, 1)]
+    Overloaded(String, Duration),
+}
+
+/// A `LoadShedder` decides whether low-priority requests are accepted.
+#[derive(Debug)]
+pub struct LoadShedder {
+    config: LoadSheddingConfig,
+    pressure: RwLock<Option<String>>,
+}
+
+impl LoadShedder {
+    /// Create a new `LoadShedder` that accepts all requests until its watchdog detects pressure.
+    #[must_use]
+    pub fn new(config: LoadSheddingConfig) -> Self {
+        Self {
+            config,
+            pressure: RwLock::new(None),
+        }
+    }
+
+    /// Verify that low-priority requests are accepted.
+    pub fn verify_accepting(&self) -> Result<(), Error> {
+        match &*self.pressure.read().unwrap() {
+            Some(reason) => Err(Error::Overloaded(
+                reason.clone(),
+                Duration::from_millis(self.config.retry_after_ms),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Execute the watchdog monitoring the `consensus`.
+    pub async fn watch(self: Arc<Self>, consensus: Arc<Consensus>) {
+        loop {
+            time::delay_for(WATCHDOG_INTERVAL).await;
+            let pressure = self.measure_pressure(&consensus).await;
+
+            let mut current = self.pressure.write().unwrap();
+            match (&*current, &pressure) {
+                (None, Some(reason)) => log::warn!("Shedding load: {}.", reason),
+                (Some(_), None) => log::info!("Stopped shedding load."),
+                _ => {}
+            }
+            *current = pressure;
+        }
+    }
+
+    /// Get the reason for shedding load (if any threshold is exceeded).
+    async fn measure_pressure(&self, consensus: &Consensus) -> Option<String> {
+        let queue_len = consensus.queue_len().await;
+        if queue_len > self.config.max_queue_len {
+            return Some(format!("{} transactions are queued", queue_len));
+        }
+
+        let commit_lag = consensus.commit_lag().await;
+        if commit_lag > Duration::from_millis(self.config.max_commit_lag_ms) {
+            return Some(format!(
+                "the oldest transaction is waiting for {:?}",
+                commit_lag
+            ));
+        }
+
+        if let (Some(max_memory_mb), Some(memory_mb)) = (self.config.max_memory_mb, memory_mb()) {
+            if memory_mb > max_memory_mb {
+                return Some(format!("{} MiB of memory are used", memory_mb));
+            }
+        }
+
+        None
+    }
+}
+
+/// Get the resident memory of the process in MiB (if available).
+fn memory_mb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024)
+}
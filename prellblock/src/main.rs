@@ -13,35 +13,99 @@
 //! By using an replicate-order-validate-execute procedure it is assured, that data will be saved, even in case of a total failure of all but one redundant processing unit.
 //! While working in full capactiy, data is stored and validated under byzantine fault tolerance. This project is carried out in cooperation with **Deutsche Bahn AG represented by DB Systel GmbH**.
 
-use balise::server::TlsIdentity;
-use futures::future;
-use pinxit::Identity;
+use balise::{
+    activation,
+    server::{Shutdown, TlsIdentity},
+};
+use futures::{future, FutureExt};
+use pinxit::{Identity, Signer};
 use prellblock::{
+    access_log::AccessLog,
+    backup::{self, ChainSummary},
+    ban_list::BanList,
     batcher::Batcher,
     block_storage::BlockStorage,
-    consensus::Consensus,
+    consensus::{Consensus, QueueSpill, SystemClock},
     data_broadcaster::Broadcaster,
     data_storage::DataStorage,
+    disk_space::DiskSpaceGuard,
+    escrow::EscrowShares,
+    event_bus::EventBus,
+    load_shedding::LoadShedder,
+    observer::{Observer, ObserverConfig},
     peer::{Calculator, PeerInbox, Receiver},
     reader::Reader,
+    storage::StorageConfig,
+    systemd,
     transaction_checker::TransactionChecker,
     turi::Turi,
     world_state::WorldStateService,
-    RpuPrivateConfig,
+    ChainConfig, RpuPrivateConfig,
 };
 use prellblock_client_api::{account::AccountType, consensus::GenesisTransactions};
-use std::{env, fs, io, sync::Arc};
-use structopt::StructOpt;
-use tokio::net::TcpListener;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs, io, iter,
+    net::SocketAddr,
+    process,
+    sync::Arc,
+    time::Duration,
+};
+use structopt::{clap::AppSettings, StructOpt};
+use tokio::{signal, time};
+
+mod init;
 
 // https://crates.io/crates/structopt
 
 #[derive(StructOpt, Debug)]
+#[structopt(setting = AppSettings::SubcommandsNegateReqs)]
 struct Opt {
     /// The path to the configuration file.
-    config: String,
+    #[structopt(required = true)]
+    config: Option<String>,
     /// The path to the genesis transactions file (only needed for the first start).
     genesis_transactions: Option<String>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Generate identities, TLS certificates, genesis transactions and configurations for a cluster.
+    Init(init::InitOpt),
+    /// Write a disaster recovery bundle of all chains of a stopped RPU.
+    Backup {
+        /// The path to the configuration file of the RPU.
+        config: String,
+        /// The path of the bundle to write.
+        output: String,
+    },
+    /// Restore a disaster recovery bundle to the paths of a configuration.
+    Restore {
+        /// The path of the bundle.
+        archive: String,
+        /// The path to the configuration file (written from the bundle if it does not exist).
+        #[structopt(required_unless = "check")]
+        config: Option<String>,
+        /// Only verify the integrity of the bundle.
+        #[structopt(long)]
+        check: bool,
+    },
+    /// Approve genesis transactions (e.g. as bootstrap authority or founding RPU).
+    ApproveGenesis {
+        /// The path to the genesis transactions file (the approval is added to it).
+        genesis_transactions: String,
+        /// The path to the private key of the approving identity.
+        identity: String,
+    },
+    /// Approve the authorization of an audit (see `prellblock-client request_decryption`).
+    ApproveDecryption {
+        /// The path to the authorization file (the approval is added to it).
+        authorization: String,
+        /// The path to the private key of the RPU.
+        identity: String,
+    },
 }
 
 #[tokio::main]
@@ -52,99 +116,516 @@ async fn main() {
     let opt = Opt::from_args();
     log::debug!("Command line arguments: {:#?}", opt);
 
+    match opt.command {
+        Some(Command::Init(init_opt)) => {
+            if let Err(err) = init::init(init_opt) {
+                log::error!("Could not initialize the cluster: {}", err);
+                process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Backup { config, output }) => {
+            match backup::backup(&config, &output) {
+                Ok(summaries) => log_chain_summaries("Backed up", &summaries),
+                Err(err) => {
+                    log::error!("Could not back up the RPU: {}", err);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Restore {
+            archive,
+            config,
+            check,
+        }) => {
+            let result = match config {
+                Some(config) if !check => {
+                    backup::restore(&archive, &config).map(|summaries| ("Restored", summaries))
+                }
+                _ => backup::verify(&archive).map(|summaries| ("Verified", summaries)),
+            };
+            match result {
+                Ok((action, summaries)) => log_chain_summaries(action, &summaries),
+                Err(err) => {
+                    log::error!("Could not restore the bundle: {}", err);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::ApproveGenesis {
+            genesis_transactions,
+            identity,
+        }) => {
+            match init::approve_genesis(&genesis_transactions, &identity) {
+                Ok(peer_id) => log::info!("Approved {} as {}.", genesis_transactions, peer_id),
+                Err(err) => {
+                    log::error!("Could not approve the genesis transactions: {}", err);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::ApproveDecryption {
+            authorization,
+            identity,
+        }) => {
+            match init::approve_decryption(&authorization, &identity) {
+                Ok(peer_id) => log::info!("Approved {} as {}.", authorization, peer_id),
+                Err(err) => {
+                    log::error!("Could not approve the authorization: {}", err);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        None => {}
+    }
+
     // load and parse config
-    let private_config_data = fs::read_to_string(opt.config).unwrap();
+    // (the config is required if no subcommand is given)
+    let private_config_data = fs::read_to_string(opt.config.unwrap()).unwrap();
     let private_config: RpuPrivateConfig = toml::from_str(&private_config_data).unwrap();
 
-    // load genesis block (if a path is given)
-    let genesis_transactions = if let Some(genesis_transactions) = opt.genesis_transactions {
-        let genesis_transactions_data = fs::read_to_string(genesis_transactions).unwrap();
-        let genesis_transactions: GenesisTransactions =
-            serde_yaml::from_str(&genesis_transactions_data).unwrap();
-        Some(genesis_transactions)
-    } else {
-        None
+    let main_chain = ChainConfig {
+        name: "main".to_string(),
+        identity: private_config.identity.clone(),
+        genesis_transactions: opt.genesis_transactions,
+        block_path: private_config.block_path.clone(),
+        data_path: private_config.data_path.clone(),
+        ban_list_path: private_config.ban_list_path.clone(),
+        escrow_shares: private_config.escrow_shares.clone(),
+        world_state: private_config.world_state.clone(),
+        queue_spill: private_config.queue_spill.clone(),
+        genesis: private_config.genesis.clone(),
     };
 
-    let hex_identity =
-        fs::read_to_string(&private_config.identity).expect("Could not load identity file.");
-    let identity: Identity = hex_identity.parse().expect("Identity could not be loaded.");
-    let peer_id = identity.id().clone();
+    if let Some(observer_config) = private_config.observer.clone() {
+        if !private_config.chains.is_empty() {
+            log::warn!("Observers only follow the main chain, additional chains are ignored.");
+        }
+        let (identity, block_storage, world_state) =
+            load_chain(&main_chain, &private_config.storage);
+        main_observer(
+            identity,
+            private_config,
+            observer_config,
+            block_storage,
+            world_state,
+        )
+        .await;
+        return;
+    }
 
-    let block_storage =
-        BlockStorage::new(&private_config.block_path, genesis_transactions).unwrap();
-    let world_state = WorldStateService::from_block_storage(&block_storage).unwrap();
+    let mut peer_ids = HashSet::new();
+    let mut account_ids = HashSet::new();
+    let mut turi_addresses = HashSet::new();
+    let mut peer_inboxes: HashMap<SocketAddr, Vec<Arc<PeerInbox>>> = HashMap::new();
+    let mut consensuses = Vec::new();
+    let mut tasks = Vec::new();
+    let resolver = Arc::new(private_config.network.resolver());
+    let shutdown = Shutdown::new();
+    // The Turis of all chains write to the same access log.
+    let access_log = open_access_log(&private_config);
 
-    let consensus = Consensus::new(identity, block_storage.clone(), world_state.clone()).await;
+    for chain_config in iter::once(main_chain).chain(private_config.chains.clone()) {
+        let (identity, block_storage, world_state) =
+            load_chain(&chain_config, &private_config.storage);
 
-    let broadcaster = Broadcaster::new(world_state.clone());
-    let broadcaster = Arc::new(broadcaster);
+        // Consensus messages can only be told apart by their signer.
+        let peer_id = identity.id().clone();
+        assert!(
+            peer_ids.insert(peer_id.clone()),
+            "The identity {} is used by multiple chains.",
+            peer_id
+        );
 
-    let batcher = Batcher::new(broadcaster);
+        // Transaction signatures are not bound to a chain, so they could be replayed on another chain.
+        for account_id in world_state.get().accounts.keys() {
+            assert!(
+                account_ids.insert(account_id.clone()),
+                "The account {} exists on multiple chains, each chain needs its own identities.",
+                account_id
+            );
+        }
 
-    let reader = Reader::new(block_storage, world_state.clone());
+        // Refuse to start a consensus that cannot be safe.
+        let fault_tolerance = world_state
+            .get()
+            .fault_tolerance()
+            .expect("The RPUs do not tolerate the declared number of faulty RPUs.");
+        log::info!(
+            "The {} RPUs tolerate {} faulty RPUs.",
+            fault_tolerance.peer_count(),
+            fault_tolerance.faulty()
+        );
 
-    // if configured correctly, the addresses for `Turi` and `PeerInbox` are in the `world_state`
-    let rpu_account = world_state
-        .get()
-        .accounts
-        .get(&peer_id)
-        .expect("RPU account not found")
-        .clone();
+        let ban_list_path = chain_config
+            .ban_list_path
+            .clone()
+            .unwrap_or_else(|| format!("{}_ban_list", chain_config.data_path));
+        let ban_list = Arc::new(BanList::new(&ban_list_path).unwrap());
 
-    let transaction_checker = TransactionChecker::new(world_state);
+        let queue_spill = chain_config
+            .queue_spill
+            .clone()
+            .map(|config| QueueSpill::open(config).expect("The queue spill could not be opened."));
 
-    let (turi_address, peer_address) = match rpu_account.account_type {
-        AccountType::RPU {
-            turi_address,
-            peer_address,
-        } => (turi_address, peer_address),
-        _ => panic!("Given account {} is no RPU.", peer_id),
-    };
+        let identity: Arc<dyn Signer> = Arc::new(identity);
+        let consensus = Consensus::new(
+            identity.clone(),
+            block_storage.clone(),
+            world_state.clone(),
+            ban_list,
+            private_config.network.connect_timeouts(),
+            queue_spill,
+            resolver.clone(),
+            Arc::new(SystemClock),
+        )
+        .await;
+        consensuses.push(consensus.clone());
 
-    // execute the turi in a new thread
-    let turi_task = {
-        let private_config = private_config.clone();
-        let transaction_checker = transaction_checker.clone();
-
-        tokio::spawn(async move {
-            let tls_identity = load_identity_from_env(private_config.tls_id).await?;
-            let mut listener = TcpListener::bind(turi_address).await?;
-            let turi = Turi::new(tls_identity, batcher, reader, transaction_checker);
-            turi.serve(&mut listener).await
-        })
+        let broadcaster = Broadcaster::new(consensus.chain_id(), world_state.clone(), identity)
+            .with_connect_timeouts(private_config.network.connect_timeouts())
+            .with_resolver(resolver.clone());
+        let broadcaster = Arc::new(broadcaster);
+
+        let batcher = Batcher::new(broadcaster);
+
+        let reader =
+            Reader::new(block_storage, world_state.clone()).with_events(consensus.events().clone());
+
+        // if configured correctly, the addresses for `Turi` and `PeerInbox` are in the `world_state`
+        let rpu_account = world_state
+            .get()
+            .accounts
+            .get(&peer_id)
+            .expect("RPU account not found")
+            .clone();
+
+        let escrow_shares = chain_config.escrow_shares.as_ref().map(|path| {
+            let escrow_shares = EscrowShares::load(peer_id.clone(), world_state.clone(), path)
+                .expect("Escrowed key shares could not be loaded.");
+            Arc::new(escrow_shares)
+        });
+
+        let transaction_checker = TransactionChecker::new(world_state);
+
+        let (turi_address, peer_address) = match rpu_account.account_type {
+            AccountType::RPU {
+                turi_address,
+                peer_address,
+            } => (turi_address, peer_address),
+            _ => panic!("Given account {} is no RPU.", peer_id),
+        };
+        turi_addresses.insert(turi_address);
+
+        // the watchdog runs in a new thread
+        let load_shedder = private_config.load_shedding.clone().map(|config| {
+            let load_shedder = Arc::new(LoadShedder::new(config));
+            tokio::spawn(load_shedder.clone().watch(consensus.clone()));
+            load_shedder
+        });
+        let disk_space_guard = private_config.disk_space.clone().map(|config| {
+            let disk_space_guard = DiskSpaceGuard::new(config, database_paths(&chain_config))
+                .with_events(consensus.events().clone());
+            let disk_space_guard = Arc::new(disk_space_guard);
+            tokio::spawn(disk_space_guard.clone().watch());
+            disk_space_guard
+        });
+
+        let mut listener = activation::bind(turi_address)
+            .await
+            .expect("The Turi address could not be bound.");
+
+        // execute the turi in a new thread
+        let turi_task = {
+            let turi_config = private_config.turi.clone();
+            let tls_id = turi_tls_id(&private_config);
+            let transaction_checker = transaction_checker.clone();
+            let consensus = consensus.clone();
+            let shutdown = shutdown.clone();
+            let access_log = access_log.clone();
+
+            tokio::spawn(async move {
+                let tls_identity = load_identity_from_env(tls_id).await?;
+                let mut turi = Turi::new(
+                    tls_identity,
+                    batcher,
+                    reader,
+                    transaction_checker,
+                    consensus,
+                )
+                .with_config(&turi_config)
+                .with_shutdown(shutdown);
+                if let Some(load_shedder) = load_shedder {
+                    turi = turi.with_load_shedder(load_shedder);
+                }
+                if let Some(disk_space_guard) = disk_space_guard {
+                    turi = turi.with_disk_space_guard(disk_space_guard);
+                }
+                if let Some(escrow_shares) = escrow_shares {
+                    turi = turi.with_escrow_shares(escrow_shares);
+                }
+                if let Some(access_log) = access_log {
+                    turi = turi.with_access_log(access_log);
+                }
+                turi.serve(&mut listener).await
+            })
+        };
+        let chain_name = chain_config.name.clone();
+        tasks.push(
+            async move {
+                log::error!("Turi of chain {} ended: {:?}", chain_name, turi_task.await);
+            }
+            .boxed(),
+        );
+
+        let data_storage =
+            DataStorage::with_config(&chain_config.data_path, &private_config.storage).unwrap();
+        let data_storage = Arc::new(data_storage);
+
+        let calculator = Calculator::new();
+        let calculator = Arc::new(calculator.into());
+
+        let peer_inbox = PeerInbox::new(calculator, data_storage, consensus, transaction_checker);
+        peer_inboxes
+            .entry(peer_address)
+            .or_default()
+            .push(Arc::new(peer_inbox));
+    }
+
+    // The consensus endpoints must never be reachable via a client port.
+    for peer_address in peer_inboxes.keys() {
+        assert!(
+            !turi_addresses.contains(peer_address),
+            "The address {} is used as Turi and peer address.",
+            peer_address
+        );
+    }
+
+    // Chains sharing a peer address share a receiver.
+    for (peer_address, peer_inboxes) in peer_inboxes {
+        let mut listener = activation::bind(peer_address)
+            .await
+            .expect("The peer address could not be bound.");
+
+        // execute the receiver in a new thread
+        let tls_id = private_config.tls_id.clone();
+        let peer_receiver_task = tokio::spawn(async move {
+            let tls_identity = load_identity_from_env(tls_id).await?;
+            let receiver = Receiver::new(tls_identity, peer_inboxes);
+            receiver.serve(&mut listener).await
+        });
+        tasks.push(
+            async move {
+                log::error!("Peer recceiver ended: {:?}", peer_receiver_task.await);
+            }
+            .boxed(),
+        );
+    }
+
+    // all servers are listening
+    notify_service_manager(consensuses);
+
+    // wait for all tasks (or until the RPU is stopped)
+    tokio::select! {
+        _ = future::join_all(tasks) => {}
+        () = stop_signal() => drain(shutdown, private_config.turi.shutdown_grace_period()).await,
+    }
+    log::info!("Going to hunt some mice. I meant *NICE*. Bye.");
+}
+
+/// Log the chains of a disaster recovery bundle.
+fn log_chain_summaries(action: &str, summaries: &[ChainSummary]) {
+    for summary in summaries {
+        log::info!("{} {}.", action, summary);
+    }
+}
+
+/// Load the identity, `BlockStorage` and `WorldState` of a chain.
+fn load_chain(
+    chain_config: &ChainConfig,
+    storage_config: &StorageConfig,
+) -> (Identity, BlockStorage, WorldStateService) {
+    // load genesis block (if a path is given)
+    let genesis_transactions =
+        if let Some(genesis_transactions) = &chain_config.genesis_transactions {
+            let genesis_transactions_data = fs::read_to_string(genesis_transactions).unwrap();
+            let genesis_transactions: GenesisTransactions =
+                serde_yaml::from_str(&genesis_transactions_data).unwrap();
+            if let Err(err) = chain_config.genesis.verify(&genesis_transactions) {
+                panic!(
+                    "The genesis transactions of the chain {} are not approved: {}",
+                    chain_config.name, err
+                );
+            }
+            Some(genesis_transactions)
+        } else {
+            None
+        };
+
+    let identity =
+        Identity::from_file(&chain_config.identity).expect("Identity could not be loaded.");
+
+    let block_storage = BlockStorage::with_config(
+        &chain_config.block_path,
+        genesis_transactions,
+        storage_config,
+    )
+    .unwrap();
+    let world_state = match chain_config.world_state.clone() {
+        Some(persistence_config) => WorldStateService::with_tuned_persistence(
+            &block_storage,
+            persistence_config,
+            storage_config,
+        )
+        .unwrap(),
+        None => WorldStateService::from_block_storage(&block_storage).unwrap(),
     };
 
-    let data_storage = DataStorage::new(&private_config.data_path).unwrap();
-    let data_storage = Arc::new(data_storage);
+    (identity, block_storage, world_state)
+}
+
+/// The paths of the databases of a chain written by the RPU.
+fn database_paths(chain_config: &ChainConfig) -> Vec<String> {
+    let mut paths = vec![
+        chain_config.block_path.clone(),
+        chain_config.data_path.clone(),
+    ];
+    if let Some(world_state) = &chain_config.world_state {
+        paths.push(world_state.path.clone());
+    }
+    if let Some(queue_spill) = &chain_config.queue_spill {
+        paths.push(queue_spill.path.clone());
+    }
+    paths
+}
+
+/// Open the access log of the Turi (if configured).
+fn open_access_log(private_config: &RpuPrivateConfig) -> Option<Arc<AccessLog>> {
+    private_config.turi.access_log.clone().map(|config| {
+        let access_log = AccessLog::open(config).expect("The access log could not be opened.");
+        Arc::new(access_log)
+    })
+}
 
-    let calculator = Calculator::new();
-    let calculator = Arc::new(calculator.into());
+/// Run the RPU as a read-only observer.
+async fn main_observer(
+    identity: Identity,
+    private_config: RpuPrivateConfig,
+    observer_config: ObserverConfig,
+    block_storage: BlockStorage,
+    world_state: WorldStateService,
+) {
+    log::info!("Running as observer.");
 
-    let peer_inbox = PeerInbox::new(calculator, data_storage, consensus, transaction_checker);
-    let peer_inbox = Arc::new(peer_inbox);
+    // The applied blocks are pushed to the observer's own block subscribers.
+    let events = EventBus::default();
+    let reader =
+        Reader::new(block_storage.clone(), world_state.clone()).with_events(events.clone());
+    let transaction_checker = TransactionChecker::new(world_state.clone());
 
-    // execute the receiver in a new thread
-    let peer_receiver_task = tokio::spawn(async move {
-        let tls_identity = load_identity_from_env(private_config.tls_id).await?;
-        let mut listener = TcpListener::bind(peer_address).await?;
-        let receiver = Receiver::new(tls_identity, peer_inbox);
-        receiver.serve(&mut listener).await
+    let mut listener = activation::bind(observer_config.turi_address)
+        .await
+        .expect("The Turi address could not be bound.");
+
+    // execute the turi in a new thread
+    let tls_id = turi_tls_id(&private_config);
+    let turi_config = private_config.turi.clone();
+    let access_log = open_access_log(&private_config);
+    let shutdown = Shutdown::new();
+    let turi_shutdown = shutdown.clone();
+    let turi_task = tokio::spawn(async move {
+        let tls_identity = load_identity_from_env(tls_id).await?;
+        let mut turi = Turi::read_only(tls_identity, reader, transaction_checker)
+            .with_config(&turi_config)
+            .with_shutdown(turi_shutdown);
+        if let Some(access_log) = access_log {
+            turi = turi.with_access_log(access_log);
+        }
+        turi.serve(&mut listener).await
     });
 
-    // wait for all tasks
-    future::join(
+    let mut observer = Observer::new(identity, block_storage, world_state)
+        .with_connect_timeouts(private_config.network.connect_timeouts())
+        .with_events(events);
+    if let Some(source) = observer_config.source {
+        observer = observer.with_source(source);
+    }
+    let observer_task = tokio::spawn(observer.execute());
+
+    // observers do not take part in the consensus
+    notify_service_manager(Vec::new());
+
+    // wait for all tasks (or until the RPU is stopped)
+    let tasks = future::join(
         async move {
             log::error!("Turi ended: {:?}", turi_task.await);
         },
         async move {
-            log::error!("Peer recceiver ended: {:?}", peer_receiver_task.await);
+            log::error!("Observer ended: {:?}", observer_task.await);
         },
-    )
-    .await;
+    );
+    tokio::select! {
+        _ = tasks => {}
+        () = stop_signal() => drain(shutdown, private_config.turi.shutdown_grace_period()).await,
+    }
     log::info!("Going to hunt some mice. I meant *NICE*. Bye.");
 }
 
+/// Report the RPU as ready and start the watchdog notifications (if enabled).
+fn notify_service_manager(consensuses: Vec<Arc<Consensus>>) {
+    systemd::notify("READY=1");
+    if let Some(timeout) = systemd::watchdog_timeout() {
+        log::info!(
+            "Notifying the watchdog while the consensus is live (timeout {:?}).",
+            timeout
+        );
+        tokio::spawn(systemd::watchdog(consensuses, timeout));
+    }
+}
+
+/// Wait until the RPU is asked to stop (`SIGINT` or `SIGTERM`).
+async fn stop_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Could not listen for SIGTERM.");
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = signal::ctrl_c().await;
+    }
+}
+
+/// Stop the `Turi`s gracefully: new requests are rejected and in-flight requests
+/// may take up to `grace_period` to finish.
+async fn drain(shutdown: Shutdown, grace_period: Duration) {
+    systemd::notify("STOPPING=1");
+    log::info!(
+        "Stopping the RPU, waiting up to {:?} for in-flight requests.",
+        grace_period
+    );
+    if time::timeout(grace_period, shutdown.drain()).await.is_err() {
+        log::warn!("The grace period elapsed, closing the remaining connections.");
+    }
+}
+
+/// The TLS identity of the `Turi` (defaults to the TLS identity of the RPU).
+fn turi_tls_id(private_config: &RpuPrivateConfig) -> String {
+    private_config
+        .turi
+        .tls_id
+        .clone()
+        .unwrap_or_else(|| private_config.tls_id.clone())
+}
+
 async fn load_identity_from_env(tls_identity_path: String) -> Result<TlsIdentity, io::Error> {
     let password = env::var("TLS_PASSWORD").unwrap_or_else(|_| "prellblock".to_string());
     balise::server::load_identity(tls_identity_path, &password).await
@@ -0,0 +1,228 @@
+//! An `Observer` follows the blockchain without taking part in the consensus.
+//!
+//! Observers can be used as query replicas: they stream the blocks from an RPU,
+//! maintain their own `WorldState` and serve the read API, so e.g. dashboards
+//! do not put load on the validating RPUs.
+
+use crate::{
+    block_storage::BlockStorage,
+    consensus::{verify_block_signatures, Block, TransactionApplier},
+    event_bus::EventBus,
+    transaction_checker::TransactionChecker,
+    world_state::WorldStateService,
+    BoxError,
+};
+use balise::client::{Client, ConnectTimeouts};
+use pinxit::{Identity, Signable};
+use prellblock_client_api::{
+    account::AccountType, message, ClientMessage, GetBlock, ResumeToken, SubscribeBlocks,
+};
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, time::Duration};
+use tokio::time;
+
+const SYNCHRONIZATION_INTERVAL: Duration = Duration::from_millis(400);
+const MAX_BLOCKS_PER_REQUEST: u64 = 16;
+
+/// The configuration of an RPU running as observer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObserverConfig {
+    /// The address on which the `Turi` listens for incoming (read-only) client requests.
+    pub turi_address: SocketAddr,
+    /// The `Turi` address of the RPU to stream the blocks from.
+    ///
+    /// If not set, all RPUs of the peer set are tried in turn.
+    #[serde(default)]
+    pub source: Option<SocketAddr>,
+}
+
+/// An `Observer` receives and verifies all blocks, maintains the `WorldState`
+/// and serves queries, but does not take part in the voting.
+///
+/// The missing blocks are fetched from the `Turi` of an RPU, afterwards
+/// new blocks are streamed as soon as they are committed.
+/// Therefore the observer's account must be allowed to read blocks.
+pub struct Observer {
+    identity: Identity,
+    source: Option<SocketAddr>,
+    world_state: WorldStateService,
+    transaction_applier: TransactionApplier,
+    transaction_checker: TransactionChecker,
+    connect_timeouts: ConnectTimeouts,
+}
+
+impl Observer {
+    /// Create a new `Observer` instance.
+    #[must_use]
+    pub fn new(
+        identity: Identity,
+        block_storage: BlockStorage,
+        world_state: WorldStateService,
+    ) -> Self {
+        Self {
+            identity,
+            source: None,
+            world_state: world_state.clone(),
+            transaction_applier: TransactionApplier::new(block_storage, world_state.clone()),
+            transaction_checker: TransactionChecker::new(world_state),
+            connect_timeouts: ConnectTimeouts::default(),
+        }
+    }
+
+    /// Use the `connect_timeouts` when connecting to the RPUs.
+    #[must_use]
+    pub const fn with_connect_timeouts(mut self, connect_timeouts: ConnectTimeouts) -> Self {
+        self.connect_timeouts = connect_timeouts;
+        self
+    }
+
+    /// Stream the blocks only from the RPU whose `Turi` listens on `source`.
+    #[must_use]
+    pub const fn with_source(mut self, source: SocketAddr) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Publish the applied blocks on the `events` bus.
+    ///
+    /// Pass the same bus to the `Reader`, so block subscriptions of the
+    /// observer's clients are served without polling.
+    #[must_use]
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.transaction_applier = self.transaction_applier.with_events(events);
+        self
+    }
+
+    /// Execute the observer.
+    ///
+    /// This follows one RPU at a time and switches to the next one
+    /// if the connection fails or the stream ends.
+    pub async fn execute(self) {
+        loop {
+            for turi_address in self.source_addresses() {
+                match self.follow(turi_address).await {
+                    Ok(()) => log::debug!("Observer stopped following {}.", turi_address),
+                    Err(err) => log::warn!("Observer could not follow {}: {}", turi_address, err),
+                }
+            }
+            time::delay_for(SYNCHRONIZATION_INTERVAL).await;
+        }
+    }
+
+    /// Get the `Turi` addresses of the RPUs to follow.
+    fn source_addresses(&self) -> Vec<SocketAddr> {
+        match self.source {
+            Some(source) => vec![source],
+            None => self.rpu_turi_addresses(),
+        }
+    }
+
+    /// Get the `Turi` addresses of all RPUs.
+    fn rpu_turi_addresses(&self) -> Vec<SocketAddr> {
+        let world_state = self.world_state.get();
+        world_state
+            .peers
+            .iter()
+            .filter_map(|(peer_id, _)| match world_state.accounts.get(peer_id) {
+                Some(account) => match account.account_type {
+                    AccountType::RPU { turi_address, .. } => Some(turi_address),
+                    _ => None,
+                },
+                None => None,
+            })
+            .collect()
+    }
+
+    /// Catch up with the RPU at `turi_address` and apply each new block as soon as it is streamed.
+    ///
+    /// Returns when the RPU ends the stream.
+    async fn follow(&self, turi_address: SocketAddr) -> Result<(), BoxError> {
+        let mut client =
+            Client::<ClientMessage>::new(turi_address).with_connect_timeouts(self.connect_timeouts);
+        self.synchronize(&mut client, turi_address).await?;
+
+        // Without a genesis block there is nothing to resume after yet.
+        let last_block_number = match self.world_state.get().block_number.checked_sub(1) {
+            Some(last_block_number) => last_block_number,
+            None => return Ok(()),
+        };
+        let message = SubscribeBlocks {
+            resume_token: Some(ResumeToken::new(last_block_number)),
+        };
+        let mut blocks = client
+            .send_stream_request(message::SubscribeBlocks(message.sign(&self.identity)?))
+            .await?;
+        log::info!(
+            "Observer follows {} after block #{}.",
+            turi_address,
+            last_block_number
+        );
+        while let Some(block) = blocks.next().await {
+            self.apply_block(block?).await?;
+        }
+        Ok(())
+    }
+
+    /// Fetch and apply all missing blocks from the RPU at `turi_address`.
+    async fn synchronize(
+        &self,
+        client: &mut Client<ClientMessage>,
+        turi_address: SocketAddr,
+    ) -> Result<(), BoxError> {
+        loop {
+            let block_number = self.world_state.get().block_number;
+            let message = GetBlock {
+                filter: (block_number..block_number + MAX_BLOCKS_PER_REQUEST).into(),
+            };
+            let blocks = client
+                .send_request(message::GetBlock(message.sign(&self.identity)?))
+                .await?;
+            if blocks.is_empty() {
+                return Ok(());
+            }
+
+            log::trace!(
+                "Observer received {} blocks from {}.",
+                blocks.len(),
+                turi_address
+            );
+            for block in blocks {
+                self.apply_block(block).await?;
+            }
+        }
+    }
+
+    /// Verify a `block` and apply it to the `BlockStorage` and `WorldState`.
+    async fn apply_block(&self, block: Block) -> Result<(), BoxError> {
+        let world_state = self.world_state.get();
+
+        if block.body.height != world_state.block_number {
+            return Err(format!(
+                "Received block #{}, but expected block #{}.",
+                block.body.height, world_state.block_number
+            )
+            .into());
+        }
+
+        if block.body.prev_block_hash != world_state.last_block_hash {
+            return Err(format!(
+                "The previous block hash {:?} of block #{} does not match {:?}.",
+                block.body.prev_block_hash, block.body.height, world_state.last_block_hash
+            )
+            .into());
+        }
+
+        verify_block_signatures(&block, &world_state)?;
+
+        if block.body.transactions.is_empty() {
+            return Err(format!("Block #{} is empty.", block.body.height).into());
+        }
+
+        // Validate Transactions
+        self.transaction_checker.verify(&block.body.transactions)?;
+
+        log::debug!("Observer applies block #{}.", block.body.height);
+        self.transaction_applier.apply_block(block).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+//! Prioritized dispatching of messages received from other RPUs.
+
+use super::{message, BatchTransactionStatus, PeerInbox};
+use crate::{
+    consensus::{Block, ConsensusResponse},
+    BoxError,
+};
+use balise::{server::ConnectionInfo, Streaming};
+use pinxit::{PeerId, Signed};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::{mpsc, oneshot, Notify};
+
+/// The maximum number of consensus messages waiting to be handled.
+const CONSENSUS_QUEUE_SIZE: usize = 256;
+/// The maximum number of transaction batches waiting to be handled.
+const BATCH_QUEUE_SIZE: usize = 16;
+
+type Job<P, R> = (P, oneshot::Sender<Result<R, BoxError>>);
+type ConsensusJob = Job<message::Consensus, Signed<ConsensusResponse>>;
+type BatchJob = Job<(message::ExecuteBatch, PeerId), Vec<BatchTransactionStatus>>;
+
+/// Dispatches messages to the `PeerInbox` by their category.
+///
+/// Each category has its own bounded queue. A full queue blocks the
+/// connection it was received on until there is room again.
+/// Consensus messages are always dispatched before transaction batches,
+/// so the consensus latency is not affected by a flood of client transactions.
+#[derive(Clone)]
+pub struct Dispatcher {
+    consensus_sender: mpsc::Sender<ConsensusJob>,
+    batch_sender: mpsc::Sender<BatchJob>,
+    pending_consensus: Arc<PendingMessages>,
+    peer_inbox: Arc<PeerInbox>,
+}
+
+impl Dispatcher {
+    /// Create a new `Dispatcher` and spawn its workers.
+    #[must_use]
+    pub fn spawn(peer_inbox: Arc<PeerInbox>) -> Self {
+        let (consensus_sender, consensus_receiver) = mpsc::channel(CONSENSUS_QUEUE_SIZE);
+        let (batch_sender, batch_receiver) = mpsc::channel(BATCH_QUEUE_SIZE);
+        let pending_consensus = Arc::new(PendingMessages::default());
+
+        tokio::spawn(consensus_worker(
+            peer_inbox.clone(),
+            consensus_receiver,
+            pending_consensus.clone(),
+        ));
+        tokio::spawn(batch_worker(
+            peer_inbox.clone(),
+            batch_receiver,
+            pending_consensus.clone(),
+        ));
+
+        Self {
+            consensus_sender,
+            batch_sender,
+            pending_consensus,
+            peer_inbox,
+        }
+    }
+
+    /// Verify that the request on the `connection` was sent by an RPU of the dispatcher's chain.
+    pub fn verify_peer(&self, connection: &ConnectionInfo) -> Result<(), BoxError> {
+        self.peer_inbox.verify_peer(connection)
+    }
+
+    /// Queue a consensus message and wait for its response.
+    pub async fn handle_consensus(
+        &self,
+        params: message::Consensus,
+    ) -> Result<Signed<ConsensusResponse>, BoxError> {
+        let (sender, receiver) = oneshot::channel();
+        self.pending_consensus.add();
+        if self
+            .consensus_sender
+            .clone()
+            .send((params, sender))
+            .await
+            .is_err()
+        {
+            self.pending_consensus.remove();
+            return Err("The consensus queue is closed.".into());
+        }
+        receiver.await?
+    }
+
+    /// Queue a batch of transactions received from the RPU `peer_id` and wait until it was handled.
+    pub async fn handle_execute_batch(
+        &self,
+        params: message::ExecuteBatch,
+        peer_id: PeerId,
+    ) -> Result<Vec<BatchTransactionStatus>, BoxError> {
+        let (sender, receiver) = oneshot::channel();
+        if self
+            .batch_sender
+            .clone()
+            .send(((params, peer_id), sender))
+            .await
+            .is_err()
+        {
+            return Err("The batch queue is closed.".into());
+        }
+        receiver.await?
+    }
+
+    /// Evict queued transactions.
+    ///
+    /// Evictions are rare admin requests, so they are not queued.
+    pub async fn handle_evict_transactions(
+        &self,
+        params: message::EvictTransactions,
+    ) -> Result<(), BoxError> {
+        self.peer_inbox.handle_evict_transactions(params).await
+    }
+
+    /// Remove a transaction whose deadline passed from the queue.
+    ///
+    /// Like evictions, expirations are not queued.
+    pub async fn handle_expire_transaction(
+        &self,
+        params: message::ExpireTransaction,
+    ) -> Result<(), BoxError> {
+        self.peer_inbox.handle_expire_transaction(params).await
+    }
+
+    /// Stream blocks to another RPU.
+    ///
+    /// The blocks are read while sending, so the request is not queued.
+    pub fn handle_stream_blocks(
+        &self,
+        params: message::StreamBlocks,
+    ) -> Result<Streaming<Block>, BoxError> {
+        self.peer_inbox.handle_stream_blocks(params)
+    }
+}
+
+/// Handle all consensus messages concurrently.
+///
+/// Consensus messages may wait for each other (e.g. a `Prepare` for the next block
+/// waits for the `Commit` of the current one), so they must not be handled one by one.
+async fn consensus_worker(
+    peer_inbox: Arc<PeerInbox>,
+    mut receiver: mpsc::Receiver<ConsensusJob>,
+    pending_consensus: Arc<PendingMessages>,
+) {
+    while let Some((params, sender)) = receiver.recv().await {
+        pending_consensus.remove();
+        let peer_inbox = peer_inbox.clone();
+        tokio::spawn(async move {
+            let _ = sender.send(peer_inbox.handle_consensus(params).await);
+        });
+    }
+}
+
+/// Handle all transaction batches one after another.
+///
+/// A batch is only handled if there are no consensus messages waiting.
+async fn batch_worker(
+    peer_inbox: Arc<PeerInbox>,
+    mut receiver: mpsc::Receiver<BatchJob>,
+    pending_consensus: Arc<PendingMessages>,
+) {
+    while let Some(((params, peer_id), sender)) = receiver.recv().await {
+        pending_consensus.wait_until_none().await;
+        let _ = sender.send(peer_inbox.handle_execute_batch(params, peer_id).await);
+    }
+}
+
+/// Counts the messages waiting in a queue.
+///
+/// A lower-priority worker waits for the count to drop to zero without polling.
+/// Only a single task may wait at a time.
+pub struct PendingMessages {
+    count: AtomicUsize,
+    none_pending: Notify,
+}
+
+impl Default for PendingMessages {
+    fn default() -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+            none_pending: Notify::new(),
+        }
+    }
+}
+
+impl PendingMessages {
+    /// Count a message that was added to the queue.
+    pub fn add(&self) {
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Count a message that was taken from the queue.
+    pub fn remove(&self) {
+        if self.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.none_pending.notify();
+        }
+    }
+
+    /// The number of messages in the queue.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Wait until no messages are left in the queue.
+    pub async fn wait_until_none(&self) {
+        // A notification sent before waiting is stored, so none is missed.
+        while self.count() > 0 {
+            self.none_pending.notified().await;
+        }
+    }
+}
@@ -1,22 +1,68 @@
 //! Message types that can be used to communicate between RPUs.
 
 mod calculator;
+mod dispatcher;
 mod peer_inbox;
+mod received_batches;
+mod received_evictions;
 mod receiver;
+mod resolver;
+mod round_trip_times;
 mod sender;
 
 pub use calculator::Calculator;
+pub use dispatcher::PendingMessages;
 pub use peer_inbox::PeerInbox;
+pub use received_batches::ReceivedBatches;
+pub use received_evictions::{ReceivedEvictions, EVICTION_WINDOW};
 pub use receiver::Receiver;
-pub use sender::Sender;
+pub use resolver::Resolver;
+pub use round_trip_times::{RoundTripTimeSummary, RoundTripTimes};
+pub use sender::{NetworkConfig, Outbox, Sender};
 
-use crate::consensus::{ConsensusMessage, ConsensusResponse};
-use balise::define_api;
+use crate::consensus::{Block, ChainId, ConsensusMessage, ConsensusResponse};
+use balise::{define_api, Streaming};
 use pinxit::Signed;
-use prellblock_client_api::Transaction;
+use prellblock_client_api::{
+    limits, EvictTransactions, ExecuteWithDeadline, StreamBlocks, Transaction,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+/// The id of an `ExecuteBatch` message.
+///
+/// Retries of a batch keep its id, so the receiving RPU queues its transactions only once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BatchId(pub u64);
+
+impl BatchId {
+    /// Create a random id for a new batch.
+    #[must_use]
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+}
+
+/// The status of a single transaction of an `ExecuteBatch` message on the receiving RPU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchTransactionStatus {
+    /// The transaction was added to the queue.
+    Queued,
+    /// The transaction is already queued, proposed or committed (or was sent twice in the batch).
+    Duplicate,
+    /// The transaction was rejected for the given reason (e.g. an invalid signature or missing permissions).
+    Rejected(String),
+}
+
+/// The transactions of an `ExecuteBatch` message.
+///
+/// Batches with more than `MAX_TRANSACTIONS_PER_BATCH` transactions are rejected while deserializing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TransactionBatch(
+    #[serde(deserialize_with = "limits::transactions_per_batch")] pub Vec<Signed<Transaction>>,
+);
+
 /// Play ping pong. See [`Ping`](message/struct.Ping.html).
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Pong;
@@ -35,10 +81,25 @@ define_api! {
         /// Ping Message. See [`Pong`](../struct.Pong.html).
         Ping => Pong,
 
-        /// Simple batch of transaction message. Will write a key:value pair.
-        ExecuteBatch(Vec<Signed<Transaction>>) => (),
+        /// Simple batch of transaction message for the chain with the given id. Will write a key:value pair.
+        ///
+        /// The response contains the status of each transaction (in the order of the batch).
+        /// A retried batch (with the same id) is answered with the statuses of the first attempt.
+        ExecuteBatch(ChainId, BatchId, TransactionBatch) => Vec<BatchTransactionStatus>,
+
+        /// Messages exchanged by the consensus of the chain with the given id.
+        Consensus(ChainId, Signed<ConsensusMessage>) => Signed<ConsensusResponse>,
+
+        /// An admin's request to evict queued transactions of the chain with the given id.
+        EvictTransactions(ChainId, Signed<EvictTransactions>) => (),
+
+        /// Stream the blocks of the chain with the given id to another RPU (used to catch up).
+        StreamBlocks(ChainId, Signed<StreamBlocks>) => Streaming<Block>,
+
+        /// Remove a transaction of the chain with the given id from the queue because its deadline passed.
+        ExpireTransaction(ChainId, Signed<ExecuteWithDeadline>) => (),
 
-        /// Messages exchanged by the consensus.
-        Consensus(Signed<ConsensusMessage>) => Signed<ConsensusResponse>,
+        /// Get the ids of the chains served by the RPU (used to detect a different genesis).
+        GetChainIds => Vec<ChainId>,
     }
 }
@@ -1,13 +1,23 @@
-use super::{message, Calculator, Pong};
+use super::{
+    message, BatchTransactionStatus, Calculator, Pong, ReceivedBatches, ReceivedEvictions,
+    TransactionBatch,
+};
 use crate::{
-    consensus::{Consensus, ConsensusResponse},
-    data_storage::DataStorage,
+    consensus::{Block, BlockHash, ChainId, Consensus, ConsensusResponse},
+    data_storage::{AuditEntry, DataStorage},
     transaction_checker::TransactionChecker,
     BoxError,
 };
-use pinxit::{verify_signed_batch_iter, Signed, VerifiedRef};
-use prellblock_client_api::Transaction;
-use std::sync::{Arc, Mutex};
+use balise::{server::ConnectionInfo, Streaming};
+use pinxit::{verify_signed_batch_iter, PeerId, Signed, VerifiedRef};
+use prellblock_client_api::{
+    consensus::transaction_hash, TraceEventKind, Transaction, TransactionStatus,
+};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
 
 type ArcMut<T> = Arc<Mutex<T>>;
 
@@ -17,6 +27,8 @@ pub struct PeerInbox {
     data_storage: Arc<DataStorage>,
     consensus: Arc<Consensus>,
     transaction_checker: TransactionChecker,
+    received_batches: ReceivedBatches,
+    received_evictions: ReceivedEvictions,
 }
 
 impl PeerInbox {
@@ -33,6 +45,45 @@ impl PeerInbox {
             data_storage,
             consensus,
             transaction_checker,
+            received_batches: ReceivedBatches::default(),
+            received_evictions: ReceivedEvictions::default(),
+        }
+    }
+
+    /// Get the id of the chain the inbox receives messages for.
+    #[must_use]
+    pub fn chain_id(&self) -> ChainId {
+        self.consensus.chain_id()
+    }
+
+    /// Check whether `peer_id` is one of the RPUs of the inbox's chain.
+    #[must_use]
+    pub fn is_peer(&self, peer_id: &PeerId) -> bool {
+        self.consensus.is_peer(peer_id)
+    }
+
+    /// Check whether a peer is banned on the inbox's chain.
+    #[must_use]
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.consensus.ban_list().is_banned(peer_id)
+    }
+
+    /// Verify that the request on the `connection` was sent by an RPU of the inbox's chain
+    /// that is not banned.
+    pub fn verify_peer(&self, connection: &ConnectionInfo) -> Result<(), BoxError> {
+        match &connection.peer_id {
+            Some(peer_id) if self.is_banned(peer_id) => {
+                Err(format!("The RPU {} is banned.", peer_id).into())
+            }
+            Some(peer_id) if self.is_peer(peer_id) => Ok(()),
+            Some(peer_id) => {
+                Err(format!("{} is no RPU of chain {}.", peer_id, self.chain_id()).into())
+            }
+            None => Err(format!(
+                "The request from {} is not authenticated.",
+                connection.remote_addr
+            )
+            .into()),
         }
     }
 
@@ -89,30 +140,328 @@ impl PeerInbox {
                 self.data_storage
                     .write_account_transaction(transaction.signer(), params)?;
             }
+            Transaction::SetSchema(params) => {
+                log::debug!(
+                    "Client {} sets schema of namespace {:?}: {:?}",
+                    &transaction.signer(),
+                    params.namespace,
+                    params.schema,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::ScheduleMigration(params) => {
+                log::debug!(
+                    "Client {} schedules the migration to WorldState version {} at block #{}.",
+                    &transaction.signer(),
+                    params.version,
+                    params.activation_height,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::SetParameter(params) => {
+                log::debug!(
+                    "Client {} sets parameter {} to {} from block #{} on.",
+                    &transaction.signer(),
+                    params.parameter,
+                    params.value,
+                    params.activation_height,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::KeyValueWithMetadata(params) => {
+                log::debug!(
+                    "Client {} set {} to {:?} ({:?}) (via another RPU)",
+                    &transaction.signer(),
+                    params.key,
+                    params.value,
+                    params.metadata,
+                );
+
+                self.data_storage.write_key_value(
+                    transaction.signer(),
+                    &params.key,
+                    &params.value,
+                    params.timestamp,
+                )?;
+            }
+            Transaction::SetLeaderWeight(params) => {
+                log::debug!(
+                    "Client {} sets the leader weight of {} to {}.",
+                    &transaction.signer(),
+                    params.id,
+                    params.weight,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::EncryptedKeyValue(params) => {
+                log::debug!(
+                    "Client {} set {} to an encrypted value (key {}) (via another RPU)",
+                    &transaction.signer(),
+                    params.key,
+                    params.value.key_reference,
+                );
+
+                self.data_storage.write_key_value(
+                    transaction.signer(),
+                    &params.key,
+                    &params.value.encode()?,
+                    params.timestamp,
+                )?;
+            }
+            Transaction::EscrowKey(params) => {
+                log::debug!(
+                    "Client {} escrows key {} (threshold {} of {} shares).",
+                    &transaction.signer(),
+                    params.key_reference,
+                    params.threshold,
+                    params.share_hashes.len(),
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::AuthorizeDecryption(params) => {
+                log::debug!(
+                    "Client {} authorizes {} to decrypt key {}: {:?}",
+                    &transaction.signer(),
+                    params.auditor,
+                    params.key_reference,
+                    params.reason,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::SetQueueWeight(params) => {
+                log::debug!(
+                    "Client {} sets the queue weight of {} to {}.",
+                    &transaction.signer(),
+                    params.id,
+                    params.weight,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::SetQuota(params) => {
+                log::debug!(
+                    "Client {} sets the quota of namespace {:?}: {:?}",
+                    &transaction.signer(),
+                    params.namespace,
+                    params.quota,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
+            Transaction::MultiKeyValue(params) => {
+                log::debug!(
+                    "Client {} set {} keys at once (via another RPU)",
+                    &transaction.signer(),
+                    params.values.len(),
+                );
+
+                for (key, value) in &params.values {
+                    self.data_storage.write_key_value(
+                        transaction.signer(),
+                        key,
+                        value,
+                        params.timestamp,
+                    )?;
+                }
+            }
+            Transaction::CompareAndSwap(params) => {
+                log::debug!(
+                    "Client {} swaps the shared key {} from {:?} to {:?} (via another RPU)",
+                    &transaction.signer(),
+                    params.key,
+                    params.expected,
+                    params.new,
+                );
+                self.data_storage
+                    .write_account_transaction(transaction.signer(), params)?;
+            }
         }
         Ok(())
     }
 
-    /// Handle a batch of `execute` `Signable` messages.
+    /// Handle a batch of `execute` `Signable` messages received from the RPU `peer_id`.
+    ///
+    /// Returns the status of each transaction. Only valid transactions that are not yet known are queued.
     pub async fn handle_execute_batch(
         &self,
         params: message::ExecuteBatch,
-    ) -> Result<(), BoxError> {
-        let message::ExecuteBatch(batch) = params;
+        peer_id: PeerId,
+    ) -> Result<Vec<BatchTransactionStatus>, BoxError> {
+        let message::ExecuteBatch(_, batch_id, TransactionBatch(batch)) = params;
+
+        // A halted RPU is read-only.
+        self.consensus.verify_not_halted()?;
+
+        // The sender retries a batch if the response got lost.
+        if let Some(statuses) = self.received_batches.get(&peer_id, batch_id) {
+            log::debug!("Received batch {:?} from {} again.", batch_id, peer_id);
+            return Ok(statuses);
+        }
+
+        let mut hashes = HashSet::new();
+        let mut execute = |transaction: VerifiedRef<Transaction>| {
+            let signed: &Signed<Transaction> = transaction.into();
+            if !hashes.insert(transaction_hash(signed)) || self.is_known(signed) {
+                return BatchTransactionStatus::Duplicate;
+            }
+            match self.handle_execute(transaction) {
+                Ok(()) => BatchTransactionStatus::Queued,
+                Err(err) => BatchTransactionStatus::Rejected(err.to_string()),
+            }
+        };
 
         // Batch verification makes it somewhat faster.
-        let verified = verify_signed_batch_iter(batch.iter())?;
-        for message in verified {
-            self.handle_execute(message)?;
+        // If it fails, the transactions are verified one by one to find the invalid ones.
+        let statuses: Vec<_> = match verify_signed_batch_iter(batch.iter()) {
+            Ok(verified) => verified.map(&mut execute).collect(),
+            Err(_) => batch
+                .iter()
+                .map(|transaction| match transaction.verify_ref() {
+                    Ok(transaction) => execute(transaction),
+                    Err(err) => BatchTransactionStatus::Rejected(err.to_string()),
+                })
+                .collect(),
+        };
+
+        // A concurrent retry of the batch may have been handled in the meantime.
+        if let Err(statuses) =
+            self.received_batches
+                .insert(peer_id.clone(), batch_id, statuses.clone())
+        {
+            return Ok(statuses);
+        }
+
+        let batch: Vec<_> = batch
+            .into_iter()
+            .zip(&statuses)
+            .filter(|(_, status)| **status == BatchTransactionStatus::Queued)
+            .map(|(transaction, _)| transaction)
+            .collect();
+        let rejected = statuses
+            .iter()
+            .filter(|status| matches!(status, BatchTransactionStatus::Rejected(_)))
+            .count();
+        if rejected > 0 {
+            log::warn!(
+                "Rejected {} transactions received from {}.",
+                rejected,
+                peer_id
+            );
         }
+        self.consensus
+            .trace_transactions(&batch, &TraceEventKind::ReceivedFrom(peer_id));
 
         let consensus = self.consensus.clone();
         // This would otherwise block the batcher on the sending side
         // because taking the transactions could take a while...
         tokio::spawn(async move { consensus.take_transactions(batch).await });
+        Ok(statuses)
+    }
+
+    /// Check whether the `transaction` is already queued, proposed or committed on this RPU.
+    fn is_known(&self, transaction: &Signed<Transaction>) -> bool {
+        let trace = self
+            .consensus
+            .transaction_trace(&transaction_hash(transaction));
+        matches!(
+            trace.status,
+            TransactionStatus::Queued { .. }
+                | TransactionStatus::Proposed { .. }
+                | TransactionStatus::Committed { .. }
+                | TransactionStatus::Failed { .. }
+        )
+    }
+
+    /// Handle an admin's request to evict queued transactions.
+    ///
+    /// The eviction is recorded in the audit log.
+    /// Requests that are not recent (see `EVICTION_WINDOW`) or were handled before are rejected.
+    pub async fn handle_evict_transactions(
+        &self,
+        params: message::EvictTransactions,
+    ) -> Result<(), BoxError> {
+        let message::EvictTransactions(_, request) = params;
+        let signer = request.verify_ref()?.signer().clone();
+        self.transaction_checker
+            .account_checker(signer.clone())?
+            .verify_is_admin()?;
+
+        // A recorded request must not evict transactions that were queued again later.
+        let request_hash = BlockHash::of(&postcard::to_stdvec(request.unverified_ref())?);
+        self.received_evictions
+            .insert(request_hash, request.unverified_ref().timestamp)?;
+
+        let evicted: Vec<_> = self
+            .consensus
+            .evict_transactions(&request.unverified_ref().hashes)
+            .await
+            .iter()
+            .map(transaction_hash)
+            .collect();
+        log::info!(
+            "Admin {} evicted {} queued transactions: {:?}",
+            signer,
+            evicted.len(),
+            evicted
+        );
+
+        self.data_storage
+            .write_audit_entry(&AuditEntry::EvictTransactions { request, evicted })
+    }
+
+    /// Handle an RPU's request to remove a transaction whose deadline passed.
+    ///
+    /// The client's request is verified, so the deadline cannot be forged by the sending RPU.
+    pub async fn handle_expire_transaction(
+        &self,
+        params: message::ExpireTransaction,
+    ) -> Result<(), BoxError> {
+        let message::ExpireTransaction(_, request) = params;
+        let request = request.verify()?;
+        if request.transaction.signer() != request.signer() {
+            return Err("The transaction is not signed by the sender of the request.".into());
+        }
+        if request.deadline > SystemTime::now() {
+            return Err("The deadline of the transaction has not passed yet.".into());
+        }
+
+        let hash = transaction_hash(&request.transaction);
+        if !self.consensus.expire_transactions(&[hash]).await.is_empty() {
+            log::info!(
+                "Removed transaction {} of {} from the queue (deadline passed).",
+                hash,
+                request.signer()
+            );
+        }
         Ok(())
     }
 
+    /// Handle an RPU's request to stream blocks (used to catch up).
+    pub fn handle_stream_blocks(
+        &self,
+        params: message::StreamBlocks,
+    ) -> Result<Streaming<Block>, BoxError> {
+        let message::StreamBlocks(_, request) = params;
+        let request = request.verify()?;
+        self.transaction_checker
+            .account_checker(request.signer().clone())?
+            .verify_is_rpu()?;
+
+        log::debug!(
+            "Streaming blocks from #{} to {:?} to {}.",
+            request.from,
+            request.to,
+            request.signer()
+        );
+        Ok(self.consensus.stream_blocks(request.from, request.to))
+    }
+
     /// Handle an add `Add` message, return a `usize` as a `Result`.
     pub fn handle_add(&self, params: &message::Add) -> Result<usize, BoxError> {
         Ok(self.calculator.lock().unwrap().add(params.0, params.1))
@@ -130,10 +479,17 @@ impl PeerInbox {
     }
 
     /// Forward messages to the consensus algorithm.
+    ///
+    /// Messages of banned peers are rejected.
     pub async fn handle_consensus(
         &self,
         params: message::Consensus,
     ) -> Result<Signed<ConsensusResponse>, BoxError> {
-        Ok(self.consensus.handle_message(params.0).await?)
+        let message::Consensus(_, message) = params;
+        let peer_id = message.signer();
+        if self.is_banned(peer_id) {
+            return Err(format!("The RPU {} is banned.", peer_id).into());
+        }
+        Ok(self.consensus.handle_message(message).await?)
     }
 }
@@ -0,0 +1,68 @@
+//! Deduplication of retried `ExecuteBatch` messages.
+
+use super::{BatchId, BatchTransactionStatus};
+use pinxit::PeerId;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// The number of batches remembered per `ReceivedBatches`.
+const MAX_BATCHES: usize = 4096;
+
+type Key = (PeerId, BatchId);
+
+/// The most recent `ExecuteBatch` messages handled by an RPU and their responses.
+///
+/// The sender retries a batch when the response is lost (e.g. after a timeout).
+/// A retried batch is answered with the response of the first attempt instead of being queued again.
+#[derive(Debug, Default)]
+pub struct ReceivedBatches {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    statuses: HashMap<Key, Vec<BatchTransactionStatus>>,
+    /// The batches in the order they were received.
+    order: VecDeque<Key>,
+}
+
+impl ReceivedBatches {
+    /// Get the response to the batch `batch_id` of `peer_id` if it was handled before.
+    #[must_use]
+    pub fn get(&self, peer_id: &PeerId, batch_id: BatchId) -> Option<Vec<BatchTransactionStatus>> {
+        self.inner
+            .lock()
+            .unwrap()
+            .statuses
+            .get(&(peer_id.clone(), batch_id))
+            .cloned()
+    }
+
+    /// Record the `statuses` of the batch `batch_id` of `peer_id`.
+    ///
+    /// Fails with the recorded statuses if the batch was handled concurrently,
+    /// its transactions must not be queued again then.
+    pub fn insert(
+        &self,
+        peer_id: PeerId,
+        batch_id: BatchId,
+        statuses: Vec<BatchTransactionStatus>,
+    ) -> Result<(), Vec<BatchTransactionStatus>> {
+        let mut inner = self.inner.lock().unwrap();
+        let key = (peer_id, batch_id);
+        if let Some(recorded) = inner.statuses.get(&key) {
+            return Err(recorded.clone());
+        }
+
+        if inner.order.len() >= MAX_BATCHES {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.statuses.remove(&oldest);
+            }
+        }
+        inner.order.push_back(key.clone());
+        inner.statuses.insert(key, statuses);
+        Ok(())
+    }
+}
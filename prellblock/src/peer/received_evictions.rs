@@ -0,0 +1,48 @@
+//! Protection against replayed `EvictTransactions` requests.
+
+use crate::{consensus::BlockHash, BoxError};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// The maximum difference between the timestamp of an eviction request and the local time.
+///
+/// This matches the replay window of the messages between RPUs.
+pub const EVICTION_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// The `EvictTransactions` requests handled by an RPU within the `EVICTION_WINDOW`.
+///
+/// Older requests are rejected by their timestamp, so only the recent ones need to be remembered.
+#[derive(Debug, Default)]
+pub struct ReceivedEvictions {
+    timestamps: Mutex<HashMap<BlockHash, SystemTime>>,
+}
+
+impl ReceivedEvictions {
+    /// Record the request with the hash `request_hash` and the `timestamp`.
+    ///
+    /// Fails if the timestamp is outside of the `EVICTION_WINDOW` or the request was handled before.
+    pub fn insert(&self, request_hash: BlockHash, timestamp: SystemTime) -> Result<(), BoxError> {
+        let now = SystemTime::now();
+        if !is_recent(now, timestamp) {
+            return Err("The timestamp of the eviction request is not recent.".into());
+        }
+
+        let mut timestamps = self.timestamps.lock().unwrap();
+        timestamps.retain(|_, timestamp| is_recent(now, *timestamp));
+        if timestamps.insert(request_hash, timestamp).is_some() {
+            return Err("The eviction request was replayed.".into());
+        }
+        Ok(())
+    }
+}
+
+fn is_recent(now: SystemTime, timestamp: SystemTime) -> bool {
+    let difference = match now.duration_since(timestamp) {
+        Ok(age) => age,
+        Err(err) => err.duration(),
+    };
+    difference <= EVICTION_WINDOW
+}
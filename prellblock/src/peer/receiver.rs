@@ -1,47 +1,106 @@
 //! A server for communicating between RPUs.
 
-use super::{PeerInbox, PeerMessage};
+use super::{dispatcher::Dispatcher, PeerInbox, PeerMessage};
+use crate::{consensus::ChainId, BoxError};
 use balise::{
+    envelope::Authenticator,
     handler,
-    server::{Server, TlsIdentity},
+    server::{ConnectionInfo, Server, TlsIdentity},
 };
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 
+/// The maximum difference between the timestamp of a request and the local time.
+///
+/// This matches the maximum tolerated clock skew of another RPU.
+const REPLAY_WINDOW: Duration = Duration::from_secs(5 * 60);
+
 /// A receiver (server) instance.
 ///
 /// The `Receiver` is used to receive messages being sent between RPUs.
+/// Messages are routed to the `PeerInbox` of the chain they belong to.
+/// Consensus messages are prioritized over batches of transactions.
+/// Only requests signed by an RPU of one of the chains reach the handlers.
+/// Messages of a chain are only accepted from the RPUs of that chain.
 #[derive(Clone)]
 pub struct Receiver {
     tls_identity: TlsIdentity,
-    peer_inbox: Arc<PeerInbox>,
+    peer_inboxes: Vec<Arc<PeerInbox>>,
 }
 
 impl Receiver {
-    /// Create a new receiver instance.
+    /// Create a new receiver instance for the chains of the `peer_inboxes`.
     #[must_use]
-    pub const fn new(tls_identity: TlsIdentity, peer_inbox: Arc<PeerInbox>) -> Self {
+    pub const fn new(tls_identity: TlsIdentity, peer_inboxes: Vec<Arc<PeerInbox>>) -> Self {
         Self {
             tls_identity,
-            peer_inbox,
+            peer_inboxes,
         }
     }
 
     /// The main server loop.
     pub async fn serve(self, listener: &mut TcpListener) -> Result<(), balise::Error> {
         let tls_identity = self.tls_identity.clone();
+        let peer_inbox = match self.peer_inboxes.first() {
+            Some(peer_inbox) => peer_inbox.clone(),
+            None => return Err(balise::Error::BoxError("No chain to receive for.".into())),
+        };
+        let dispatchers: Arc<HashMap<ChainId, Dispatcher>> = Arc::new(
+            self.peer_inboxes
+                .iter()
+                .map(|peer_inbox| (peer_inbox.chain_id(), Dispatcher::spawn(peer_inbox.clone())))
+                .collect(),
+        );
+        let peer_inboxes = self.peer_inboxes.clone();
+        // Banned peers are rejected before any request is handled.
+        let authenticator = Authenticator::new(REPLAY_WINDOW, move |peer_id| {
+            peer_inboxes
+                .iter()
+                .any(|peer_inbox| peer_inbox.is_peer(peer_id) && !peer_inbox.is_banned(peer_id))
+        });
         let server = Server::new(
-            handler!(PeerMessage, {
-                Add(params) =>  self.peer_inbox.handle_add(&params),
-                Sub(params) =>  self.peer_inbox.handle_sub(&params),
-                Ping(_) => self.peer_inbox.handle_ping(),
-                ExecuteBatch(params) => self.peer_inbox.handle_execute_batch(params).await,
-                Consensus(params) => self.peer_inbox.handle_consensus(params).await,
+            handler!(PeerMessage, connection, {
+                Add(params) =>  peer_inbox.handle_add(&params),
+                Sub(params) =>  peer_inbox.handle_sub(&params),
+                Ping(_) => peer_inbox.handle_ping(),
+                ExecuteBatch(params) => {
+                    let dispatcher = dispatcher(&dispatchers, &params.0, &connection)?;
+                    // The dispatcher only accepts authenticated peers.
+                    let sender = connection.peer_id.clone().ok_or("The request is not authenticated.")?;
+                    dispatcher.handle_execute_batch(params, sender).await
+                },
+                Consensus(params) => {
+                    let dispatcher = dispatcher(&dispatchers, &params.0, &connection)?;
+                    // Invalid signatures lead to strikes of the signer, so it must have sent the message itself.
+                    if connection.peer_id.as_ref() != Some(params.1.signer()) {
+                        return Err("The consensus message was not sent by its signer.".into());
+                    }
+                    dispatcher.handle_consensus(params).await
+                },
+                EvictTransactions(params) => dispatcher(&dispatchers, &params.0, &connection)?.handle_evict_transactions(params).await,
+                StreamBlocks(params) => dispatcher(&dispatchers, &params.0, &connection)?.handle_stream_blocks(params),
+                ExpireTransaction(params) => dispatcher(&dispatchers, &params.0, &connection)?.handle_expire_transaction(params).await,
+                GetChainIds(_) => Ok(dispatchers.keys().copied().collect()),
             }),
             tls_identity,
-        )?;
+        )?
+        .with_authenticator(authenticator);
         server.serve(listener).await?;
         Ok(())
     }
 }
+
+/// Get the `Dispatcher` of the chain with the given `chain_id`
+/// if the request on the `connection` was sent by an RPU of the chain.
+fn dispatcher<'a>(
+    dispatchers: &'a HashMap<ChainId, Dispatcher>,
+    chain_id: &ChainId,
+    connection: &ConnectionInfo,
+) -> Result<&'a Dispatcher, BoxError> {
+    let dispatcher = dispatchers
+        .get(chain_id)
+        .ok_or_else(|| format!("Unknown chain {}.", chain_id))?;
+    dispatcher.verify_peer(connection)?;
+    Ok(dispatcher)
+}
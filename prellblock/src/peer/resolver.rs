@@ -0,0 +1,84 @@
+//! Resolution of the DNS names of other RPUs.
+
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::net;
+
+/// Resolves the peer addresses of RPUs (as stored in the `WorldState`) to the addresses to connect to.
+///
+/// An RPU can be given a DNS name (`host:port`) in the configuration.
+/// The name is resolved again after the `resolve_interval` or after a connection failed,
+/// so an RPU changing its IP address (e.g. a restarted container) is reached without a restart.
+/// Addresses without a DNS name are used as they are.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    hosts: HashMap<SocketAddr, String>,
+    resolve_interval: Duration,
+    /// The resolved addresses and when they expire.
+    resolved: Mutex<HashMap<SocketAddr, (SocketAddr, Instant)>>,
+}
+
+impl Resolver {
+    /// Create a new `Resolver` with the DNS names of the RPUs by their `peer_address`.
+    #[must_use]
+    pub fn new(hosts: HashMap<SocketAddr, String>, resolve_interval: Duration) -> Self {
+        Self {
+            hosts,
+            resolve_interval,
+            resolved: Mutex::default(),
+        }
+    }
+
+    /// Get the address to connect to for the RPU at `peer_address`.
+    ///
+    /// If the DNS name cannot be resolved, the last resolved address is used (if there is one).
+    pub async fn resolve(&self, peer_address: SocketAddr) -> Result<SocketAddr, io::Error> {
+        let host = match self.hosts.get(&peer_address) {
+            Some(host) => host,
+            None => return Ok(peer_address),
+        };
+        let cached = self.resolved.lock().unwrap().get(&peer_address).copied();
+        if let Some((address, expires)) = cached {
+            if Instant::now() < expires {
+                return Ok(address);
+            }
+        }
+
+        let result = net::lookup_host(host.as_str())
+            .await
+            .and_then(|mut addresses| {
+                addresses.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} has no address.", host))
+                })
+            });
+        match (result, cached) {
+            (Ok(address), _) => {
+                if cached.map_or(false, |(cached, _)| cached != address) {
+                    log::info!("{} ({}) moved to {}.", host, peer_address, address);
+                }
+                self.resolved.lock().unwrap().insert(
+                    peer_address,
+                    (address, Instant::now() + self.resolve_interval),
+                );
+                Ok(address)
+            }
+            (Err(err), Some((address, _))) => {
+                log::warn!("Could not resolve {}, using {}: {}", host, address, err);
+                Ok(address)
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    /// Resolve the DNS name of the RPU at `peer_address` again on the next request (e.g. after a failure).
+    pub fn invalidate(&self, peer_address: SocketAddr) {
+        if let Some((_, expires)) = self.resolved.lock().unwrap().get_mut(&peer_address) {
+            *expires = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Round trip times of requests to other RPUs.
+//!
+//! The latest round trip times are kept per RPU and summarized by percentiles.
+//! The summaries are persisted, so a restarted RPU starts with the round trip times it measured before.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::Mutex,
+    time::Duration,
+};
+
+/// The number of round trip times kept per RPU (older ones are dropped).
+const MAX_SAMPLES: usize = 256;
+
+/// Below this number of samples, a restored summary is preferred over the new samples.
+const MIN_SAMPLES: usize = 16;
+
+/// A summary of the round trip times to an RPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundTripTimeSummary {
+    /// The number of round trip times the summary is based on.
+    pub samples: usize,
+    /// The median round trip time.
+    pub median: Duration,
+    /// The 99th percentile of the round trip times.
+    pub p99: Duration,
+}
+
+impl RoundTripTimeSummary {
+    /// Summarize the round trip times `samples`.
+    ///
+    /// Returns `None` if there are no samples.
+    fn new(samples: &VecDeque<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |percent: usize| sorted[(sorted.len() - 1) * percent / 100];
+        Some(Self {
+            samples: sorted.len(),
+            median: percentile(50),
+            p99: percentile(99),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    recent: VecDeque<Duration>,
+    /// The summary restored from the `BlockStorage`.
+    restored: Option<RoundTripTimeSummary>,
+}
+
+impl Samples {
+    fn summary(&self) -> Option<RoundTripTimeSummary> {
+        if self.recent.len() < MIN_SAMPLES && self.restored.is_some() {
+            self.restored
+        } else {
+            RoundTripTimeSummary::new(&self.recent)
+        }
+    }
+}
+
+/// The latest round trip times of requests to all RPUs.
+#[derive(Debug, Default)]
+pub struct RoundTripTimes {
+    peers: Mutex<HashMap<SocketAddr, Samples>>,
+}
+
+impl RoundTripTimes {
+    /// Record the `round_trip_time` of a successful request to the RPU at `peer_address`.
+    pub fn record(&self, peer_address: SocketAddr, round_trip_time: Duration) {
+        let mut peers = self.peers.lock().unwrap();
+        let recent = &mut peers.entry(peer_address).or_default().recent;
+        if recent.len() == MAX_SAMPLES {
+            recent.pop_front();
+        }
+        recent.push_back(round_trip_time);
+    }
+
+    /// Restore previously persisted `summaries`.
+    pub fn restore(&self, summaries: HashMap<SocketAddr, RoundTripTimeSummary>) {
+        let mut peers = self.peers.lock().unwrap();
+        for (peer_address, summary) in summaries {
+            peers.entry(peer_address).or_default().restored = Some(summary);
+        }
+    }
+
+    /// Get the summary of the round trip times to the RPU at `peer_address`.
+    #[must_use]
+    pub fn summary(&self, peer_address: &SocketAddr) -> Option<RoundTripTimeSummary> {
+        self.peers
+            .lock()
+            .unwrap()
+            .get(peer_address)
+            .and_then(Samples::summary)
+    }
+
+    /// Get the summaries of the round trip times to all RPUs.
+    #[must_use]
+    pub fn summaries(&self) -> HashMap<SocketAddr, RoundTripTimeSummary> {
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(peer_address, samples)| Some((*peer_address, samples.summary()?)))
+            .collect()
+    }
+}
@@ -1,10 +1,279 @@
 //! A client for communicating between RPUs.
 
-use super::PeerMessage;
-use balise::client::Client;
+use super::{PeerMessage, Resolver, RoundTripTimes};
+use crate::event_bus::{Event, EventBus};
+use balise::{
+    client::{Client, ConnectTimeouts},
+    Request,
+};
+use pinxit::Signer;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cmp,
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{sync::Semaphore, time};
+
+/// The maximum number of pending requests to a single RPU.
+const QUEUE_SIZE: usize = 256;
+
+/// A failed request is retried until this amount of time has passed since it was first sent.
+const RETRY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The delay before the first retry (doubled after each retry).
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum delay between two retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The configuration of the connections to other RPUs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// The maximum time to establish a TCP connection to another RPU (in milliseconds).
+    pub connect_timeout_ms: u64,
+    /// The maximum time for the TLS handshake with another RPU (in milliseconds).
+    pub handshake_timeout_ms: u64,
+    /// The DNS names (`host:port`) of RPUs by their peer address in the `WorldState`.
+    pub hosts: HashMap<SocketAddr, String>,
+    /// The time after which the DNS names are resolved again (in seconds).
+    pub resolve_interval_secs: u64,
+}
+
+impl Default for NetworkConfig {
+    #[allow(clippy::cast_possible_truncation)]
+    fn default() -> Self {
+        let connect_timeouts = ConnectTimeouts::default();
+        Self {
+            connect_timeout_ms: connect_timeouts.connect.as_millis() as u64,
+            handshake_timeout_ms: connect_timeouts.handshake.as_millis() as u64,
+            hosts: HashMap::new(),
+            resolve_interval_secs: 30,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// The timeouts for establishing connections to other RPUs.
+    #[must_use]
+    pub const fn connect_timeouts(&self) -> ConnectTimeouts {
+        ConnectTimeouts {
+            connect: Duration::from_millis(self.connect_timeout_ms),
+            handshake: Duration::from_millis(self.handshake_timeout_ms),
+        }
+    }
+
+    /// The `Resolver` of the DNS names of other RPUs.
+    #[must_use]
+    pub fn resolver(&self) -> Resolver {
+        Resolver::new(
+            self.hosts.clone(),
+            Duration::from_secs(self.resolve_interval_secs),
+        )
+    }
+}
 
 /// A sender instance.
 ///
 /// The sender keeps up a connection pool of open connections
 /// for improved efficiency.
 pub type Sender = Client<PeerMessage>;
+
+/// Outbound queues to the other RPUs.
+///
+/// A request that fails because the connection to an RPU dropped (or could not be established)
+/// is retried on a new connection until `RETRY_TIMEOUT` has passed,
+/// so short network outages do not fail a round of the consensus.
+/// At most `QUEUE_SIZE` requests per RPU are pending at once;
+/// further requests fail immediately, so requests to an unreachable RPU do not pile up.
+/// New connections fail if they exceed the `ConnectTimeouts`, so an unreachable RPU
+/// does not block a request for the (much longer) timeouts of the OS.
+/// The peer addresses are resolved with the `Resolver` before connecting (see `with_resolver`).
+/// The round trip times of successful requests are recorded per RPU.
+/// If requests to an RPU start or stop failing, an `Event::PeerHealthChanged` is published.
+#[derive(Debug)]
+pub struct Outbox {
+    signer: Arc<dyn Signer>,
+    connect_timeouts: ConnectTimeouts,
+    resolver: Arc<Resolver>,
+    queues: Mutex<HashMap<SocketAddr, Arc<Semaphore>>>,
+    round_trip_times: RoundTripTimes,
+    unreachable: Mutex<HashSet<SocketAddr>>,
+    events: EventBus,
+}
+
+impl Outbox {
+    /// Create a new `Outbox` authenticating all requests with the `signer`.
+    #[must_use]
+    pub fn new(signer: Arc<dyn Signer>) -> Self {
+        Self {
+            signer,
+            connect_timeouts: ConnectTimeouts::default(),
+            resolver: Arc::default(),
+            queues: Mutex::default(),
+            round_trip_times: RoundTripTimes::default(),
+            unreachable: Mutex::default(),
+            events: EventBus::default(),
+        }
+    }
+
+    /// Publish changes of the health of the RPUs on the `events` bus.
+    #[must_use]
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Use the `connect_timeouts` when connecting to other RPUs.
+    #[must_use]
+    pub const fn with_connect_timeouts(mut self, connect_timeouts: ConnectTimeouts) -> Self {
+        self.connect_timeouts = connect_timeouts;
+        self
+    }
+
+    /// Resolve the peer addresses with the `resolver` (e.g. to support DNS names).
+    #[must_use]
+    pub fn with_resolver(mut self, resolver: Arc<Resolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Get the `Resolver` of the peer addresses.
+    #[must_use]
+    pub fn resolver(&self) -> Arc<Resolver> {
+        self.resolver.clone()
+    }
+
+    /// Get the signer authenticating the requests.
+    #[must_use]
+    pub fn signer(&self) -> Arc<dyn Signer> {
+        self.signer.clone()
+    }
+
+    /// Get the timeouts used when connecting to other RPUs.
+    #[must_use]
+    pub const fn connect_timeouts(&self) -> ConnectTimeouts {
+        self.connect_timeouts
+    }
+
+    /// Get the round trip times of the requests sent so far.
+    #[must_use]
+    pub const fn round_trip_times(&self) -> &RoundTripTimes {
+        &self.round_trip_times
+    }
+
+    /// Send a `request` to the RPU at `peer_address`.
+    pub async fn send<Req>(
+        &self,
+        peer_address: SocketAddr,
+        request: Req,
+    ) -> Result<Req::Response, balise::Error>
+    where
+        Req: Request<PeerMessage>,
+        Req::Response: DeserializeOwned,
+    {
+        let queue = self
+            .queues
+            .lock()
+            .unwrap()
+            .entry(peer_address)
+            .or_insert_with(|| Arc::new(Semaphore::new(QUEUE_SIZE)))
+            .clone();
+        let _permit = queue.try_acquire().map_err(|_| {
+            balise::Error::BoxError(
+                format!("Too many requests queued for {}.", peer_address).into(),
+            )
+        })?;
+
+        let start = Instant::now();
+        let mut delay = RETRY_DELAY;
+        loop {
+            let address = self
+                .resolver
+                .resolve(peer_address)
+                .await
+                .map_err(balise::Error::IO);
+            let address = match address {
+                Ok(address) => address,
+                Err(err) if start.elapsed() + delay < RETRY_TIMEOUT => {
+                    log::debug!(
+                        "Resolving {} failed, retrying in {:?}: {}",
+                        peer_address,
+                        delay,
+                        err
+                    );
+                    time::delay_for(delay).await;
+                    delay = cmp::min(delay * 2, MAX_RETRY_DELAY);
+                    continue;
+                }
+                Err(err) => {
+                    self.set_reachable(peer_address, false);
+                    return Err(err);
+                }
+            };
+            let mut sender = Sender::new(address)
+                .with_signer(self.signer.clone())
+                .with_connect_timeouts(self.connect_timeouts);
+            let sent = Instant::now();
+            match sender.send_request(request.clone()).await {
+                Ok(response) => {
+                    self.round_trip_times.record(peer_address, sent.elapsed());
+                    self.set_reachable(peer_address, true);
+                    return Ok(response);
+                }
+                Err(err) if err.is_retryable() && start.elapsed() + delay < RETRY_TIMEOUT => {
+                    if is_connection_error(&err) {
+                        // The RPU may have moved to another IP address.
+                        self.resolver.invalidate(peer_address);
+                    }
+                    log::debug!(
+                        "Sending to {} failed, retrying in {:?}: {}",
+                        peer_address,
+                        delay,
+                        err
+                    );
+                    time::delay_for(delay).await;
+                    delay = cmp::min(delay * 2, MAX_RETRY_DELAY);
+                }
+                Err(err) => {
+                    if is_connection_error(&err) {
+                        self.resolver.invalidate(peer_address);
+                        self.set_reachable(peer_address, false);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Record whether the RPU at `peer_address` is `reachable` (publishing changes).
+    fn set_reachable(&self, peer_address: SocketAddr, reachable: bool) {
+        let mut unreachable = self.unreachable.lock().unwrap();
+        let changed = if reachable {
+            unreachable.remove(&peer_address)
+        } else {
+            unreachable.insert(peer_address)
+        };
+        drop(unreachable);
+        if changed {
+            self.events.publish(Event::PeerHealthChanged {
+                peer_address,
+                reachable,
+            });
+        }
+    }
+}
+
+/// Check whether an error is caused by the connection (and not by the request or a busy server).
+const fn is_connection_error(err: &balise::Error) -> bool {
+    matches!(
+        err,
+        balise::Error::IO(_)
+            | balise::Error::Timeout
+            | balise::Error::ConnectTimeout(_)
+            | balise::Error::HandshakeTimeout(_)
+    )
+}
@@ -1,10 +1,13 @@
 //! A server for communicating between RPUs.
 
 use crate::{
-    block_storage::BlockStorage, transaction_checker::TransactionChecker,
-    world_state::WorldStateService, BoxError,
+    block_storage::BlockStorage, consensus, event_bus::EventBus, subscription,
+    transaction_checker::TransactionChecker, world_state::WorldStateService, BoxError,
+};
+use pinxit::PeerId;
+use prellblock_client_api::{
+    consensus::ChainId, message, parameter::Parameter, ClientMessage, GetValue,
 };
-use prellblock_client_api::{message, ClientMessage};
 
 type Response<R> = Result<<R as balise::Request<ClientMessage>>::Response, BoxError>;
 
@@ -14,6 +17,7 @@ pub struct Reader {
     block_storage: BlockStorage,
     world_state: WorldStateService,
     transaction_checker: TransactionChecker,
+    events: Option<EventBus>,
 }
 
 impl Reader {
@@ -24,21 +28,36 @@ impl Reader {
             block_storage,
             world_state: world_state.clone(),
             transaction_checker: TransactionChecker::new(world_state),
+            events: None,
         }
     }
 
+    /// Deliver new blocks to subscribers as soon as they are published on `events`
+    /// (instead of polling the `BlockStorage`).
+    #[must_use]
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
     pub(crate) async fn handle_get_value(
         &self,
         params: message::GetValue,
     ) -> Response<message::GetValue> {
         let message::GetValue(message) = params;
         let message = message.verify()?;
+        let signer = message.signer().clone();
+        self.get_value(signer, message.into_inner())
+    }
 
-        let account_checker = self
-            .transaction_checker
-            .account_checker(message.signer().clone())?;
+    /// Get the values requested by `signer`.
+    pub(crate) fn get_value(
+        &self,
+        signer: PeerId,
+        message: GetValue,
+    ) -> Response<message::GetValue> {
+        let account_checker = self.transaction_checker.account_checker(signer)?;
 
-        let message = message.into_inner();
         let filter = message.filter;
         let query = message.query;
 
@@ -102,6 +121,116 @@ impl Reader {
         Ok(blocks?)
     }
 
+    pub(crate) async fn handle_stream_blocks(
+        &self,
+        params: message::StreamBlocks,
+    ) -> Response<message::StreamBlocks> {
+        let message::StreamBlocks(message) = params;
+        let message = message.verify()?;
+
+        self.transaction_checker
+            .account_checker(message.signer().clone())?
+            .verify_can_read_blocks()?;
+
+        log::debug!(
+            "Streaming blocks from #{} to {:?} to {}.",
+            message.from,
+            message.to,
+            message.signer()
+        );
+        Ok(self.block_storage.stream(message.from, message.to))
+    }
+
+    pub(crate) async fn handle_subscribe_blocks(
+        &self,
+        params: message::SubscribeBlocks,
+    ) -> Response<message::SubscribeBlocks> {
+        let message::SubscribeBlocks(message) = params;
+        let message = message.verify()?;
+
+        self.transaction_checker
+            .account_checker(message.signer().clone())?
+            .verify_can_read_blocks()?;
+
+        log::debug!(
+            "Subscribing {} to the blocks after {:?}.",
+            message.signer(),
+            message.resume_token,
+        );
+        Ok(subscription::subscribe_blocks(
+            self.block_storage.clone(),
+            self.events.as_ref(),
+            message.resume_token,
+        ))
+    }
+
+    /// Get the proof which RPUs accepted a block.
+    pub(crate) async fn handle_get_block_proof(
+        &self,
+        params: message::GetBlockProof,
+    ) -> Response<message::GetBlockProof> {
+        let message::GetBlockProof(message) = params;
+        let message = message.verify()?;
+
+        self.transaction_checker
+            .account_checker(message.signer().clone())?
+            .verify_can_read_blocks()?;
+
+        let block_number = message.block_number;
+        let block = self
+            .block_storage
+            .read_block(block_number)?
+            .ok_or_else(|| format!("Block #{} does not exist.", block_number))?;
+
+        Ok(consensus::block_proof(&block)?)
+    }
+
+    /// Get whether a committed transaction was applied.
+    ///
+    /// Only the signer of the transaction and admins may read its execution receipt.
+    pub(crate) async fn handle_get_execution_receipt(
+        &self,
+        params: message::GetExecutionReceipt,
+    ) -> Response<message::GetExecutionReceipt> {
+        let message::GetExecutionReceipt(message) = params;
+        let message = message.verify()?;
+
+        let account_checker = self
+            .transaction_checker
+            .account_checker(message.signer().clone())?;
+
+        let receipt = match self
+            .block_storage
+            .read_execution_receipt(&message.transaction_hash)?
+        {
+            Some(receipt) => receipt,
+            None => return Ok(None),
+        };
+
+        if account_checker.verify_is_admin().is_err() {
+            let block_number = receipt.block_number;
+            let block = self
+                .block_storage
+                .read_block(block_number)?
+                .ok_or_else(|| format!("Block #{} does not exist.", block_number))?;
+            let is_signer = block
+                .body
+                .transactions
+                .get(receipt.index)
+                .map_or(false, |transaction| {
+                    transaction.signer() == message.signer()
+                });
+            if !is_signer {
+                return Err(
+                    "Only the signer of the transaction and admins may read its execution receipt."
+                        .into(),
+                );
+            }
+        }
+
+        Ok(Some(receipt))
+    }
+
     /// The function will return the current blocknumber,
     /// as long as the issuer has a valid account.
     pub(crate) async fn handle_get_current_block_number(
@@ -120,4 +249,16 @@ impl Reader {
 
         Ok(block_number)
     }
+
+    /// The id of the chain is the hash of its genesis block.
+    pub(crate) fn chain_id(&self) -> Result<ChainId, BoxError> {
+        Ok(self.block_storage.chain_id()?)
+    }
+
+    /// The maximum number of transactions in a batch (see `Parameter::MaxTransactionsPerBatch`).
+    pub(crate) fn max_batch_size(&self) -> usize {
+        self.world_state
+            .get()
+            .parameter_count(Parameter::MaxTransactionsPerBatch)
+    }
 }
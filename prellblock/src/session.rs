@@ -0,0 +1,172 @@
+//! Sessions of clients that authenticate their requests with a token instead of a signature.
+//!
+//! A client signs a challenge of the RPU once and receives a short-lived `SessionToken`.
+//! Verifying the token only needs a MAC, which is much cheaper than verifying a signature.
+//! Challenges and tokens are authenticated by a random key of the RPU,
+//! so the RPU does not need to store any session.
+
+use err_derive::Error;
+use openssl::{error::ErrorStack, hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
+use pinxit::{PeerId, Signed};
+use prellblock_client_api::{OpenSession, SessionChallenge, SessionToken};
+use rand::RngCore;
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// A challenge needs to be signed within this amount of time.
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(30);
+
+/// The default lifetime of a session.
+pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// The number of random bytes of a challenge (and of the key).
+const NONCE_LEN: usize = 32;
+
+/// An error of the `session` module.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The challenge was not issued by this RPU.
+    #[error(display = "The session challenge was not issued by this RPU.")]
+    InvalidChallenge,
+
+    /// The challenge is too old.
+    #[error(display = "The session challenge expired.")]
+    ChallengeExpired,
+
+    /// The token was not issued by this RPU (e.g. before a restart).
+    #[error(display = "The session token was not issued by this RPU.")]
+    InvalidToken,
+
+    /// The session is expired.
+    #[error(display = "The session expired, open a new session.")]
+    SessionExpired,
+
+    /// The signature of the request to open the session is invalid.
+    #[error(display = "{}", 0)]
+    Signature(#[error(from)] pinxit::Error),
+
+    /// The authentication code could not be calculated.
+    #[error(display = "{}", 0)]
+    Mac(#[error(from)] ErrorStack),
+
+    /// The data could not be encoded.
+    #[error(display = "{}", 0)]
+    Encoding(#[error(from)] postcard::Error),
+}
+
+/// The data covered by the authentication codes.
+///
+/// The variants keep challenges from being used as tokens.
+#[derive(Serialize)]
+enum MacData<'a> {
+    Challenge {
+        issued_at: SystemTime,
+        nonce: &'a [u8],
+    },
+    Token {
+        peer_id: &'a PeerId,
+        expires_at: SystemTime,
+    },
+}
+
+/// Issues and checks the sessions of clients.
+///
+/// The key is not persisted, so all sessions end when the RPU is restarted.
+pub struct Sessions {
+    key: Vec<u8>,
+    lifetime: Duration,
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIFETIME)
+    }
+}
+
+impl Sessions {
+    /// Create a new instance issuing sessions that expire after `lifetime`.
+    #[must_use]
+    pub fn new(lifetime: Duration) -> Self {
+        let mut key = vec![0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self { key, lifetime }
+    }
+
+    /// Issue a new challenge.
+    pub fn challenge(&self) -> Result<SessionChallenge, Error> {
+        let issued_at = SystemTime::now();
+        let mut nonce = vec![0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mac = self.mac(&MacData::Challenge {
+            issued_at,
+            nonce: &nonce,
+        })?;
+        Ok(SessionChallenge {
+            issued_at,
+            nonce,
+            mac,
+        })
+    }
+
+    /// Open a session for the signer of a signed challenge.
+    ///
+    /// The caller needs to check whether the signer is allowed to open a session.
+    pub fn open(&self, request: Signed<OpenSession>) -> Result<SessionToken, Error> {
+        let request = request.verify()?;
+        let challenge = &request.challenge;
+        let data = MacData::Challenge {
+            issued_at: challenge.issued_at,
+            nonce: &challenge.nonce,
+        };
+        if !self.is_valid_mac(&data, &challenge.mac)? {
+            return Err(Error::InvalidChallenge);
+        }
+        let age = SystemTime::now()
+            .duration_since(challenge.issued_at)
+            .unwrap_or_default();
+        if age > CHALLENGE_LIFETIME {
+            return Err(Error::ChallengeExpired);
+        }
+
+        let peer_id = request.signer().clone();
+        let expires_at = SystemTime::now() + self.lifetime;
+        let mac = self.mac(&MacData::Token {
+            peer_id: &peer_id,
+            expires_at,
+        })?;
+        Ok(SessionToken {
+            peer_id,
+            expires_at,
+            mac,
+        })
+    }
+
+    /// Check a session `token` and get the account that opened the session.
+    pub fn authenticate<'a>(&self, token: &'a SessionToken) -> Result<&'a PeerId, Error> {
+        let data = MacData::Token {
+            peer_id: &token.peer_id,
+            expires_at: token.expires_at,
+        };
+        if !self.is_valid_mac(&data, &token.mac)? {
+            return Err(Error::InvalidToken);
+        }
+        if token.is_expired() {
+            return Err(Error::SessionExpired);
+        }
+        Ok(&token.peer_id)
+    }
+
+    /// Calculate the authentication code (HMAC-SHA256) of `data`.
+    fn mac(&self, data: &MacData) -> Result<Vec<u8>, Error> {
+        let key = PKey::hmac(&self.key)?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+        signer.update(&postcard::to_stdvec(data)?)?;
+        Ok(signer.sign_to_vec()?)
+    }
+
+    /// Check the authentication code of `data` (in constant time).
+    fn is_valid_mac(&self, data: &MacData, mac: &[u8]) -> Result<bool, Error> {
+        let expected = self.mac(data)?;
+        Ok(expected.len() == mac.len() && memcmp::eq(&expected, mac))
+    }
+}
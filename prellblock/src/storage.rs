@@ -0,0 +1,64 @@
+//! Tuning of the storage engine (`sled`) of the databases of an RPU.
+//!
+//! The defaults fit a small RPU. Edge devices with little memory can reduce the cache,
+//! servers can increase it and trade CPU time for disk space with compression.
+
+use serde::{Deserialize, Serialize};
+use sled::Config;
+
+/// The tuning of the `BlockStorage`, the `DataStorage` and the persisted `WorldState`.
+///
+/// Each database gets its own cache of `cache_capacity` bytes.
+/// Compression cannot be switched on or off for an existing database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// The maximum size of the page cache of each database in bytes.
+    pub cache_capacity: u64,
+    /// The time between two flushes to disk in ms (`0` leaves flushing to the operating system).
+    ///
+    /// The persisted `WorldState` is flushed as configured by its `fsync` policy.
+    pub flush_interval_ms: u64,
+    /// Compress the stored data with zstd.
+    pub compression: bool,
+    /// The zstd compression level (`1` to `22`, higher is smaller but slower).
+    pub compression_factor: i32,
+    /// The maximum number of recently committed and read blocks kept in memory (`0` disables the cache).
+    pub block_cache_capacity: usize,
+    /// Keep the consensus messages buffered for future phases across restarts of the RPU.
+    ///
+    /// On very slow links, a restart during a long round then does not lose
+    /// the partially collected quorum of a nearly committed block (or of a view change).
+    pub persist_buffered_messages: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            cache_capacity: 8_000_000,
+            flush_interval_ms: 400,
+            compression: false,
+            compression_factor: 20,
+            block_cache_capacity: 256,
+            persist_buffered_messages: false,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// The `sled` configuration of the database at `path`.
+    #[must_use]
+    pub fn sled_config(&self, path: &str) -> Config {
+        let flush_every_ms = match self.flush_interval_ms {
+            0 => None,
+            interval => Some(interval),
+        };
+        Config::default()
+            .path(path)
+            .cache_capacity(self.cache_capacity)
+            .flush_every_ms(flush_every_ms)
+            .snapshot_after_ops(100)
+            .use_compression(self.compression)
+            .compression_factor(self.compression_factor)
+    }
+}
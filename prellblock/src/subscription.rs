@@ -0,0 +1,105 @@
+//! Subscriptions to the committed blocks.
+//!
+//! A subscriber first receives the blocks after its `ResumeToken` from the `BlockStorage`
+//! and then each block as soon as it is committed. New blocks are noticed on the `EventBus`
+//! of the consensus (or of the observer), without one the `BlockStorage` is polled.
+//! Replay and live delivery read from the same storage, so no block is skipped in between.
+
+use crate::{
+    block_storage::{BlockStorage, Error},
+    consensus::{Block, BlockNumber},
+    event_bus::{Event, EventBus, Receiver, RecvError},
+};
+use balise::{StreamSender, Streaming};
+use prellblock_client_api::ResumeToken;
+use std::time::Duration;
+use tokio::time;
+
+/// The number of blocks read from the `BlockStorage` at once.
+const CHUNK_SIZE: usize = 16;
+
+/// How often the `BlockStorage` is polled for new blocks if there is no `EventBus`.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Subscribe to the blocks after `resume_token` (or the blocks committed from now on).
+///
+/// The stream never ends, a closed subscription is noticed when the next block is sent.
+/// If a delivered block is rolled back, its replacement is sent with the same block number.
+#[must_use]
+pub fn subscribe_blocks(
+    block_storage: BlockStorage,
+    events: Option<&EventBus>,
+    resume_token: Option<ResumeToken>,
+) -> Streaming<Block> {
+    // Subscribe before reading the storage, so no commit is missed.
+    let events = events.map(EventBus::subscribe);
+    let (sender, streaming) = Streaming::channel(CHUNK_SIZE);
+    tokio::spawn(async move {
+        let next = match resume_token {
+            Some(resume_token) => Ok(resume_token.next_block_number()),
+            None => next_block_number(&block_storage),
+        };
+        match next {
+            Ok(next) => deliver(block_storage, events, sender, next).await,
+            Err(err) => {
+                log::warn!("Could not start a block subscription: {}", err);
+                sender.abort(err).await;
+            }
+        }
+    });
+    streaming
+}
+
+/// Get the number of the block that will be committed next.
+fn next_block_number(block_storage: &BlockStorage) -> Result<BlockNumber, Error> {
+    Ok(match block_storage.read(..).next_back().transpose()? {
+        Some(block) => block.block_number() + 1,
+        None => BlockNumber::default(),
+    })
+}
+
+/// Send the stored blocks from `next` on and wait for new ones.
+async fn deliver(
+    block_storage: BlockStorage,
+    mut events: Option<Receiver<Event>>,
+    mut sender: StreamSender<Block>,
+    mut next: BlockNumber,
+) {
+    loop {
+        // Replay all blocks that are already stored.
+        loop {
+            let chunk = block_storage
+                .read(next..)
+                .take(CHUNK_SIZE)
+                .collect::<Result<Vec<_>, _>>();
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    log::warn!("Could not read blocks from #{}: {}", next, err);
+                    sender.abort(err).await;
+                    return;
+                }
+            };
+            if chunk.is_empty() {
+                break;
+            }
+            for block in &chunk {
+                if sender.send(block).await.is_err() {
+                    log::trace!("Block subscription closed at block #{}.", next);
+                    return;
+                }
+                next = block.block_number() + 1;
+            }
+        }
+
+        // Wait until new blocks are stored.
+        match &mut events {
+            Some(receiver) => match receiver.recv().await {
+                Ok(Event::BlockRolledBack { block_number }) => next = next.min(block_number),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => events = None,
+            },
+            None => time::delay_for(POLL_INTERVAL).await,
+        }
+    }
+}
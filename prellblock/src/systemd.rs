@@ -0,0 +1,95 @@
+//! Notifications of the service manager (systemd `sd_notify`).
+//!
+//! The RPU reports `READY=1` once all servers are listening.
+//! If the service has a watchdog (`WatchdogSec`), the RPU sends `WATCHDOG=1` while the consensus is live,
+//! so systemd restarts a stuck RPU (halted, or with transactions waiting longer than the watchdog timeout).
+
+use crate::consensus::Consensus;
+use std::{env, ffi::OsStr, io, process, sync::Arc, time::Duration};
+use tokio::time;
+
+/// Notify the service manager about a `state` change (e.g. `READY=1`).
+///
+/// Does nothing if the RPU was not started by a service manager.
+pub fn notify(state: &str) {
+    if let Some(socket) = env::var_os("NOTIFY_SOCKET") {
+        if let Err(err) = send(&socket, state) {
+            log::warn!("Could not notify the service manager ({}): {}", state, err);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::{ffi::OsStrExt, net::UnixDatagram};
+
+    if socket.as_bytes().starts_with(b"@") {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "abstract notification sockets are not supported",
+        ));
+    }
+    UnixDatagram::unbound()?.send_to(state.as_bytes(), socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &OsStr, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "notifications are only supported on unix",
+    ))
+}
+
+/// Get the timeout of the service manager's watchdog (`None` if it is disabled).
+#[must_use]
+pub fn watchdog_timeout() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(process::id()) {
+            return None;
+        }
+    }
+    let micros = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    Some(Duration::from_micros(micros))
+}
+
+/// Send `WATCHDOG=1` twice per `timeout` while all `consensuses` are live.
+pub async fn watchdog(consensuses: Vec<Arc<Consensus>>, timeout: Duration) {
+    let mut interval = time::interval(timeout / 2);
+    let mut live = true;
+    loop {
+        interval.tick().await;
+        match stuck_reason(&consensuses, timeout).await {
+            None => {
+                if !live {
+                    log::info!("The consensus is live again, notifying the watchdog.");
+                }
+                live = true;
+                notify("WATCHDOG=1");
+            }
+            Some(reason) => {
+                if live {
+                    log::error!("Stopped notifying the watchdog: {}.", reason);
+                }
+                live = false;
+            }
+        }
+    }
+}
+
+/// Get the reason why a consensus is stuck (`None` if all are live).
+async fn stuck_reason(consensuses: &[Arc<Consensus>], timeout: Duration) -> Option<String> {
+    for consensus in consensuses {
+        if let Some(reason) = consensus.halt_reason() {
+            return Some(format!("the RPU was halted ({})", reason));
+        }
+        let commit_lag = consensus.commit_lag().await;
+        if commit_lag > timeout {
+            return Some(format!(
+                "the oldest transaction is waiting for {:?}",
+                commit_lag
+            ));
+        }
+    }
+    None
+}
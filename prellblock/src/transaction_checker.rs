@@ -1,13 +1,23 @@
 //! Module to check permissions of transactions.
 
-use crate::world_state::{WorldState, WorldStateService};
+use crate::{
+    consensus::BlockNumber,
+    world_state::{
+        FaultTolerance, FaultToleranceError, QuotaExceeded, WorldState, WorldStateService,
+        LATEST_VERSION,
+    },
+};
 use err_derive::Error;
 use pinxit::{verify_signed_batch_iter, PeerId, Signed, VerifiedRef};
 use prellblock_client_api::{
     account::{Account, AccountType, ReadingPermission},
-    Transaction,
+    encryption::KeyReference,
+    metadata::MetadataError,
+    parameter::Parameter,
+    schema::SchemaError,
+    Transaction, MAX_LEADER_WEIGHT, MAX_QUEUE_WEIGHT,
 };
-use std::sync::Arc;
+use std::{collections::HashSet, convert::TryFrom, sync::Arc, time::SystemTime};
 
 /// An error of the `permission_checker` module.
 #[derive(Debug, Error)]
@@ -44,6 +54,119 @@ pub enum PermissionError {
     /// The account to be created already exists.
     #[error(display = "The account {} already exists.", 0)]
     AccountAlreadyExists(PeerId),
+
+    /// The value does not match the schema of its key.
+    #[error(display = "The value of key {:?} is invalid: {}", 0, 1)]
+    InvalidValue(String, SchemaError),
+
+    /// The metadata of the value is invalid.
+    #[error(display = "The metadata of key {:?} is invalid: {}", 0, 1)]
+    InvalidMetadata(String, MetadataError),
+
+    /// The schema to be set is invalid.
+    #[error(display = "{}", 0)]
+    InvalidSchema(#[error(from)] SchemaError),
+
+    /// The `WorldState` cannot be migrated to the version.
+    #[error(display = "Cannot migrate the WorldState to version {}.", 0)]
+    InvalidMigrationVersion(u32),
+
+    /// The activation height of a migration or parameter change is not in the future.
+    #[error(display = "The activation height #{} has already been reached.", 0)]
+    ActivationHeightReached(BlockNumber),
+
+    /// The value is not allowed for the parameter.
+    #[error(display = "The value {} is not allowed for parameter {}.", 1, 0)]
+    InvalidParameterValue(Parameter, u64),
+
+    /// The leader weight is out of range.
+    #[error(
+        display = "The leader weight {} is not between 1 and {}.",
+        0,
+        MAX_LEADER_WEIGHT
+    )]
+    InvalidLeaderWeight(u32),
+
+    /// The queue weight is out of range.
+    #[error(
+        display = "The queue weight {} is not between 1 and {}.",
+        0,
+        MAX_QUEUE_WEIGHT
+    )]
+    InvalidQueueWeight(u32),
+
+    /// The encrypted value references a key of another namespace.
+    #[error(
+        display = "The encrypted value of key {:?} references a key of another namespace.",
+        0
+    )]
+    InvalidKeyReference(String),
+
+    /// The key has a schema, which an encrypted value cannot be validated against.
+    #[error(
+        display = "The key {:?} has a schema and cannot be set to an encrypted value.",
+        0
+    )]
+    EncryptedValueWithSchema(String),
+
+    /// The threshold of an escrowed key is below a supermajority of the RPUs or above the number of shares.
+    #[error(
+        display = "The escrow threshold {} is below a supermajority of the RPUs or above the number of shares.",
+        0
+    )]
+    InvalidEscrowThreshold(usize),
+
+    /// An RPU holds multiple shares of an escrowed key.
+    #[error(display = "The RPU {} holds multiple shares of the key.", 0)]
+    DuplicateKeyShare(PeerId),
+
+    /// The key is not escrowed.
+    #[error(display = "The key {} is not escrowed.", 0)]
+    KeyNotEscrowed(KeyReference),
+
+    /// The payload of the transaction exceeds the governed maximum size.
+    #[error(
+        display = "The transaction has {} bytes, but at most {} bytes are allowed.",
+        0,
+        1
+    )]
+    TransactionTooLarge(usize, usize),
+
+    /// The quota of the namespace of the written key is exhausted.
+    #[error(display = "{}", 0)]
+    QuotaExceeded(#[error(from)] QuotaExceeded),
+
+    /// The window of a quota is empty.
+    #[error(display = "The quota of namespace {:?} has an empty window.", 0)]
+    InvalidQuota(String),
+
+    /// A transaction setting multiple keys contains no values.
+    #[error(display = "The transaction does not contain any values.")]
+    NoValues,
+
+    /// A transaction setting multiple keys contains a key more than once.
+    #[error(display = "The key {:?} is set more than once.", 0)]
+    DuplicateKey(String),
+
+    /// The RPUs would not tolerate the declared number of faulty RPUs (see `Parameter::FaultTolerance`).
+    #[error(display = "{}", 0)]
+    InsufficientFaultTolerance(#[error(from)] FaultToleranceError),
+
+    /// An approval of an audit is not a valid signature of an RPU.
+    #[error(display = "The approval of {} is invalid: {}", 0, 1)]
+    InvalidApproval(PeerId, pinxit::Error),
+
+    /// An audit is not approved by a supermajority of the RPUs.
+    #[error(
+        display = "The audit is approved by {} RPUs, but {} approvals are needed.",
+        0,
+        1
+    )]
+    InsufficientApprovals(usize, usize),
+
+    /// An audit is authorized with an expiry that is not after the transaction's timestamp.
+    #[error(display = "The authorization already expired at {:?}.", 0)]
+    AuthorizationExpired(SystemTime),
 }
 
 /// A `TransactionChecker` is used to check whether accounts are allowed to carry out transactions.
@@ -60,10 +183,13 @@ impl TransactionChecker {
     }
 
     /// Returns a `TransactionCheck` with the current world state as virtual clone.
+    ///
+    /// The quotas are checked for a block created now (see `TransactionCheck::set_block_timestamp`).
     #[must_use]
     pub fn check(&self) -> TransactionCheck {
         TransactionCheck {
             world_state: self.world_state.get(),
+            block_timestamp: SystemTime::now(),
         }
     }
 
@@ -187,9 +313,16 @@ impl AccountChecker {
 #[derive(Debug)]
 pub struct TransactionCheck {
     world_state: WorldState,
+    /// The timestamp of the block the transactions are checked for (to count the quotas).
+    block_timestamp: SystemTime,
 }
 
 impl TransactionCheck {
+    /// Check the following transactions for a block with the `timestamp`.
+    pub fn set_block_timestamp(&mut self, timestamp: SystemTime) {
+        self.block_timestamp = timestamp;
+    }
+
     /// Verify whether a given `transaction` issued by a `peer_id` is valid.
     ///
     /// This also applies the `transaction` to the `world_state`.
@@ -200,41 +333,295 @@ impl TransactionCheck {
     ) -> Result<(), PermissionError> {
         let account_checker = AccountChecker::new(&self.world_state, transaction.signer().clone())?;
 
+        // The size limit keeps blocks (and queues) small.
+        let size = transaction.payload_size();
+        let max_size = self
+            .world_state
+            .parameter_count(Parameter::MaxTransactionSize);
+        if size > max_size {
+            return Err(PermissionError::TransactionTooLarge(size, max_size));
+        }
+
         match &*transaction {
-            Transaction::KeyValue { .. } => {
-                if account_checker.account.writing_rights {
-                    Ok(())
-                } else {
-                    Err(PermissionError::WriteDenied(account_checker.peer_id))
+            Transaction::KeyValue(params) => {
+                if !account_checker.account.writing_rights {
+                    return Err(PermissionError::WriteDenied(account_checker.peer_id));
+                }
+                if let Some(schema) = self.world_state.schema(&params.key) {
+                    schema
+                        .validate(&params.value)
+                        .map_err(|err| PermissionError::InvalidValue(params.key.clone(), err))?;
                 }
+                self.world_state
+                    .use_quota(&transaction, self.block_timestamp)?;
+                Ok(())
             }
             Transaction::UpdateAccount(params) => {
                 account_checker.verify_is_admin()?;
                 if self.world_state.accounts.get(&params.id).is_none() {
                     return Err(PermissionError::AccountNotFound(params.id.clone()));
                 }
-                self.world_state
-                    .apply_transaction(transaction.to_owned().into());
-                Ok(())
+                self.apply_account_change(transaction)
             }
             Transaction::CreateAccount(params) => {
                 account_checker.verify_is_admin()?;
                 if self.world_state.accounts.get(&params.id).is_some() {
                     return Err(PermissionError::AccountAlreadyExists(params.id.clone()));
                 }
+                self.apply_account_change(transaction)
+            }
+            Transaction::DeleteAccount(params) => {
+                account_checker.verify_is_admin()?;
+                if self.world_state.accounts.get(&params.id).is_none() {
+                    return Err(PermissionError::AccountNotFound(params.id.clone()));
+                }
+                self.apply_account_change(transaction)
+            }
+            Transaction::SetSchema(params) => {
+                account_checker.verify_is_admin()?;
+                if let Some(schema) = &params.schema {
+                    schema.verify_depth()?;
+                }
                 self.world_state
                     .apply_transaction(transaction.to_owned().into());
                 Ok(())
             }
-            Transaction::DeleteAccount(params) => {
+            Transaction::ScheduleMigration(params) => {
                 account_checker.verify_is_admin()?;
-                if self.world_state.accounts.get(&params.id).is_none() {
+                if params.version <= self.world_state.version || params.version > LATEST_VERSION {
+                    return Err(PermissionError::InvalidMigrationVersion(params.version));
+                }
+                // The transaction is applied in the block at `block_number` at the earliest.
+                if params.activation_height <= self.world_state.block_number {
+                    return Err(PermissionError::ActivationHeightReached(
+                        params.activation_height,
+                    ));
+                }
+                self.world_state
+                    .apply_transaction(transaction.to_owned().into());
+                Ok(())
+            }
+            Transaction::SetParameter(params) => {
+                account_checker.verify_is_admin()?;
+                if !params.parameter.valid_values().contains(&params.value) {
+                    return Err(PermissionError::InvalidParameterValue(
+                        params.parameter,
+                        params.value,
+                    ));
+                }
+                if params.parameter == Parameter::FaultTolerance {
+                    FaultTolerance::new(self.world_state.peers.len(), count(params.value))?;
+                }
+                // The transaction is applied in the block at `block_number` at the earliest.
+                if params.activation_height <= self.world_state.block_number {
+                    return Err(PermissionError::ActivationHeightReached(
+                        params.activation_height,
+                    ));
+                }
+                self.world_state
+                    .apply_transaction(transaction.to_owned().into());
+                Ok(())
+            }
+            Transaction::KeyValueWithMetadata(params) => {
+                if !account_checker.account.writing_rights {
+                    return Err(PermissionError::WriteDenied(account_checker.peer_id));
+                }
+                if let Some(schema) = self.world_state.schema(&params.key) {
+                    schema
+                        .validate(&params.value)
+                        .map_err(|err| PermissionError::InvalidValue(params.key.clone(), err))?;
+                }
+                params
+                    .metadata
+                    .validate()
+                    .map_err(|err| PermissionError::InvalidMetadata(params.key.clone(), err))?;
+                self.world_state
+                    .use_quota(&transaction, self.block_timestamp)?;
+                Ok(())
+            }
+            Transaction::SetLeaderWeight(params) => {
+                account_checker.verify_is_admin()?;
+                if !self
+                    .world_state
+                    .peers
+                    .iter()
+                    .any(|(id, _)| *id == params.id)
+                {
+                    return Err(PermissionError::NotAnRPU(params.id.clone()));
+                }
+                if !(1..=MAX_LEADER_WEIGHT).contains(&params.weight) {
+                    return Err(PermissionError::InvalidLeaderWeight(params.weight));
+                }
+                self.world_state
+                    .apply_transaction(transaction.to_owned().into());
+                Ok(())
+            }
+            Transaction::EncryptedKeyValue(params) => {
+                if !account_checker.account.writing_rights {
+                    return Err(PermissionError::WriteDenied(account_checker.peer_id));
+                }
+                if !params
+                    .key
+                    .starts_with(params.value.key_reference.namespace.as_str())
+                {
+                    return Err(PermissionError::InvalidKeyReference(params.key.clone()));
+                }
+                if self.world_state.schema(&params.key).is_some() {
+                    return Err(PermissionError::EncryptedValueWithSchema(
+                        params.key.clone(),
+                    ));
+                }
+                self.world_state
+                    .use_quota(&transaction, self.block_timestamp)?;
+                Ok(())
+            }
+            Transaction::EscrowKey(params) => {
+                account_checker.verify_is_admin()?;
+                let mut holders = HashSet::new();
+                for (peer_id, _) in &params.share_hashes {
+                    if !self.world_state.peers.iter().any(|(id, _)| id == peer_id) {
+                        return Err(PermissionError::NotAnRPU(peer_id.clone()));
+                    }
+                    if !holders.insert(peer_id) {
+                        return Err(PermissionError::DuplicateKeyShare(peer_id.clone()));
+                    }
+                }
+                // No minority of the RPUs must be able to reconstruct the key.
+                let supermajority = self.world_state.fault_tolerance()?.quorum();
+                if params.threshold < supermajority || params.threshold > holders.len() {
+                    return Err(PermissionError::InvalidEscrowThreshold(params.threshold));
+                }
+                self.world_state
+                    .apply_transaction(transaction.to_owned().into());
+                Ok(())
+            }
+            Transaction::AuthorizeDecryption(params) => {
+                account_checker.verify_is_admin()?;
+                if !self
+                    .world_state
+                    .escrowed_keys
+                    .contains_key(&params.key_reference)
+                {
+                    return Err(PermissionError::KeyNotEscrowed(
+                        params.key_reference.clone(),
+                    ));
+                }
+                if !self.world_state.accounts.contains_key(&params.auditor) {
+                    return Err(PermissionError::AccountNotFound(params.auditor.clone()));
+                }
+                if params.expires_at <= params.timestamp {
+                    return Err(PermissionError::AuthorizationExpired(params.expires_at));
+                }
+                // A single admin must not be able to decrypt the values.
+                for (peer_id, _) in &params.approvals {
+                    if !self.world_state.peers.iter().any(|(id, _)| id == peer_id) {
+                        return Err(PermissionError::NotAnRPU(peer_id.clone()));
+                    }
+                }
+                params
+                    .verify_approvals()
+                    .map_err(|(peer_id, err)| PermissionError::InvalidApproval(peer_id, err))?;
+                let supermajority = self.world_state.fault_tolerance()?.quorum();
+                if params.approvals.len() < supermajority {
+                    return Err(PermissionError::InsufficientApprovals(
+                        params.approvals.len(),
+                        supermajority,
+                    ));
+                }
+                self.world_state
+                    .apply_transaction(transaction.to_owned().into());
+                Ok(())
+            }
+            Transaction::SetQueueWeight(params) => {
+                account_checker.verify_is_admin()?;
+                if !self.world_state.accounts.contains_key(&params.id) {
                     return Err(PermissionError::AccountNotFound(params.id.clone()));
                 }
+                if !(1..=MAX_QUEUE_WEIGHT).contains(&params.weight) {
+                    return Err(PermissionError::InvalidQueueWeight(params.weight));
+                }
+                self.world_state
+                    .apply_transaction(transaction.to_owned().into());
+                Ok(())
+            }
+            Transaction::SetQuota(params) => {
+                account_checker.verify_is_admin()?;
+                if let Some(quota) = &params.quota {
+                    if quota.window_ms == 0 {
+                        return Err(PermissionError::InvalidQuota(params.namespace.clone()));
+                    }
+                }
                 self.world_state
                     .apply_transaction(transaction.to_owned().into());
                 Ok(())
             }
+            Transaction::MultiKeyValue(params) => {
+                if !account_checker.account.writing_rights {
+                    return Err(PermissionError::WriteDenied(account_checker.peer_id));
+                }
+                if params.values.is_empty() {
+                    return Err(PermissionError::NoValues);
+                }
+                // All values are checked before any quota is used, so the transaction is valid as a whole or not at all.
+                let mut keys = HashSet::new();
+                for (key, value) in &params.values {
+                    if !keys.insert(key) {
+                        return Err(PermissionError::DuplicateKey(key.clone()));
+                    }
+                    if let Some(schema) = self.world_state.schema(key) {
+                        schema
+                            .validate(value)
+                            .map_err(|err| PermissionError::InvalidValue(key.clone(), err))?;
+                    }
+                }
+                self.world_state
+                    .use_quota(&transaction, self.block_timestamp)?;
+                Ok(())
+            }
+            // Whether the value matches is only decided when the transaction is committed.
+            Transaction::CompareAndSwap(params) => {
+                if !account_checker.account.writing_rights {
+                    return Err(PermissionError::WriteDenied(account_checker.peer_id));
+                }
+                if let (Some(schema), Some(value)) =
+                    (self.world_state.schema(&params.key), &params.new)
+                {
+                    schema
+                        .validate(value)
+                        .map_err(|err| PermissionError::InvalidValue(params.key.clone(), err))?;
+                }
+                self.world_state
+                    .use_quota(&transaction, self.block_timestamp)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Apply a `transaction` creating, updating or deleting an account.
+    ///
+    /// If the set of RPUs changes, the new RPUs must still tolerate the current (and scheduled)
+    /// number of faulty RPUs.
+    fn apply_account_change(
+        &mut self,
+        transaction: VerifiedRef<Transaction>,
+    ) -> Result<(), PermissionError> {
+        let mut world_state = self.world_state.clone();
+        world_state.apply_transaction(transaction.to_owned().into());
+        if world_state.peers.len() != self.world_state.peers.len() {
+            world_state.fault_tolerance()?;
+            if let Some(scheduled) = world_state
+                .scheduled_parameters
+                .get(&Parameter::FaultTolerance)
+            {
+                FaultTolerance::new(world_state.peers.len(), count(scheduled.value))?;
+            }
         }
+        self.world_state = world_state;
+        Ok(())
     }
 }
+
+/// Convert a parameter `value` that is a number of items.
+fn count(value: u64) -> usize {
+    usize::try_from(value).unwrap_or(usize::max_value())
+}
@@ -1,16 +1,114 @@
 //! A server for communicating between RPUs.
 
-use crate::{batcher::Batcher, reader::Reader, transaction_checker::TransactionChecker, BoxError};
+use crate::{
+    access_log::{AccessLog, AccessLogConfig, AccessLogEntry},
+    batcher::Batcher,
+    consensus::Consensus,
+    deadline::Deadlines,
+    disk_space::DiskSpaceGuard,
+    escrow::EscrowShares,
+    idempotency::IdempotencyKeys,
+    load_shedding::LoadShedder,
+    peer::message as peer_message,
+    reader::Reader,
+    session,
+    session::Sessions,
+    transaction_checker::TransactionChecker,
+    BoxError,
+};
 use balise::{
     handler,
-    server::{Server, TlsIdentity},
+    server::{ConnectionInfo, Limits, Server, Shutdown, TlsIdentity},
+};
+use pinxit::{PeerId, Signed};
+use prellblock_client_api::{
+    consensus::transaction_hash, message, ClientMessage, ConnectionMetrics, Description,
+    ExecuteIdempotent, ExecuteWithDeadline, Histogram, Pong, Receipt, RequestMetrics,
+    TraceEventKind, Transaction, PROTOCOL_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
-use prellblock_client_api::{message, ClientMessage, Pong, Transaction};
-use std::sync::Arc;
 use tokio::net::TcpListener;
 
 type Response<R> = Result<<R as balise::Request<ClientMessage>>::Response, BoxError>;
 
+/// The configuration of the client-facing `Turi` server.
+///
+/// The `Turi` is a server of its own (separate from the peer receiver),
+/// so its TLS identity and limits can be configured independently.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuriConfig {
+    /// The TLS identity file path (defaults to the `tls_id` of the RPU).
+    pub tls_id: Option<String>,
+    /// Whether admin requests (e.g. unbanning peers) are accepted.
+    pub admin_requests: bool,
+    /// The maximum number of open client connections.
+    pub max_connections: usize,
+    /// The time a client may take to complete the TLS handshake (in milliseconds).
+    pub handshake_timeout_ms: u64,
+    /// The time after which idle client connections are closed (in milliseconds).
+    pub idle_timeout_ms: u64,
+    /// The maximum number of in-flight requests over all clients.
+    pub max_in_flight: usize,
+    /// The maximum number of in-flight requests from the same host.
+    pub max_in_flight_per_host: usize,
+    /// The time in-flight requests may take to finish when the RPU is stopped (in milliseconds).
+    pub shutdown_grace_period_ms: u64,
+    /// The time after which client sessions expire (in milliseconds).
+    pub session_lifetime_ms: u64,
+    /// Log all requests to a file (disabled if not given).
+    pub access_log: Option<AccessLogConfig>,
+}
+
+impl Default for TuriConfig {
+    #[allow(clippy::cast_possible_truncation)]
+    fn default() -> Self {
+        let limits = Limits::default();
+        Self {
+            tls_id: None,
+            admin_requests: true,
+            max_connections: limits.max_connections,
+            handshake_timeout_ms: limits.handshake_timeout.as_millis() as u64,
+            idle_timeout_ms: limits.idle_timeout.as_millis() as u64,
+            max_in_flight: limits.max_in_flight,
+            max_in_flight_per_host: limits.max_in_flight_per_host,
+            shutdown_grace_period_ms: 10_000,
+            session_lifetime_ms: session::DEFAULT_LIFETIME.as_millis() as u64,
+            access_log: None,
+        }
+    }
+}
+
+impl TuriConfig {
+    /// The limits of the `Turi` server.
+    #[must_use]
+    pub const fn limits(&self) -> Limits {
+        Limits {
+            max_connections: self.max_connections,
+            handshake_timeout: Duration::from_millis(self.handshake_timeout_ms),
+            idle_timeout: Duration::from_millis(self.idle_timeout_ms),
+            max_in_flight: self.max_in_flight,
+            max_in_flight_per_host: self.max_in_flight_per_host,
+        }
+    }
+
+    /// The time in-flight requests may take to finish when the RPU is stopped.
+    #[must_use]
+    pub const fn shutdown_grace_period(&self) -> Duration {
+        Duration::from_millis(self.shutdown_grace_period_ms)
+    }
+
+    /// The time after which client sessions expire.
+    #[must_use]
+    pub const fn session_lifetime(&self) -> Duration {
+        Duration::from_millis(self.session_lifetime_ms)
+    }
+}
+
 /// A receiver (server) instance.
 ///
 /// The Turi (old German for "door") is the entrypoint for
@@ -18,51 +116,520 @@ type Response<R> = Result<<R as balise::Request<ClientMessage>>::Response, BoxEr
 #[derive(Clone)]
 pub struct Turi {
     tls_identity: TlsIdentity,
-    batcher: Arc<Batcher>,
+    limits: Limits,
+    admin_requests: bool,
+    load_shedder: Option<Arc<LoadShedder>>,
+    disk_space_guard: Option<Arc<DiskSpaceGuard>>,
+    escrow_shares: Option<Arc<EscrowShares>>,
+    access_log: Option<Arc<AccessLog>>,
+    shutdown: Shutdown,
+    sessions: Arc<Sessions>,
+    writer: Option<Writer>,
     reader: Reader,
     transaction_checker: TransactionChecker,
 }
 
+/// The parts of a `Turi` that are needed to execute transactions.
+#[derive(Clone)]
+struct Writer {
+    batcher: Arc<Batcher>,
+    consensus: Arc<Consensus>,
+    idempotency_keys: Arc<IdempotencyKeys>,
+    deadlines: Arc<Deadlines>,
+}
+
 impl Turi {
     /// Create a new receiver instance.
     ///
     /// The `identity` is a path to a `.pfx` file.
     #[must_use]
-    pub const fn new(
+    pub fn new(
         tls_identity: TlsIdentity,
         batcher: Arc<Batcher>,
         reader: Reader,
         transaction_checker: TransactionChecker,
+        consensus: Arc<Consensus>,
+    ) -> Self {
+        Self {
+            tls_identity,
+            limits: Limits::default(),
+            admin_requests: true,
+            load_shedder: None,
+            disk_space_guard: None,
+            escrow_shares: None,
+            access_log: None,
+            shutdown: Shutdown::default(),
+            sessions: Arc::default(),
+            writer: Some(Writer {
+                deadlines: Deadlines::new(batcher.clone(), consensus.clone()),
+                batcher,
+                consensus,
+                idempotency_keys: Arc::default(),
+            }),
+            reader,
+            transaction_checker,
+        }
+    }
+
+    /// Create a new read-only receiver instance (used by observers).
+    ///
+    /// All transactions sent to a read-only `Turi` are rejected.
+    #[must_use]
+    pub fn read_only(
+        tls_identity: TlsIdentity,
+        reader: Reader,
+        transaction_checker: TransactionChecker,
     ) -> Self {
         Self {
             tls_identity,
-            batcher,
+            limits: Limits::default(),
+            admin_requests: true,
+            load_shedder: None,
+            disk_space_guard: None,
+            escrow_shares: None,
+            access_log: None,
+            shutdown: Shutdown::default(),
+            sessions: Arc::default(),
+            writer: None,
             reader,
             transaction_checker,
         }
     }
 
+    /// Apply the limits and permissions of the `config`.
+    ///
+    /// The TLS identity of the `config` needs to be loaded by the caller.
+    #[must_use]
+    pub fn with_config(mut self, config: &TuriConfig) -> Self {
+        self.limits = config.limits();
+        self.admin_requests = config.admin_requests;
+        self.sessions = Arc::new(Sessions::new(config.session_lifetime()));
+        self
+    }
+
+    /// Shed low-priority requests while the `load_shedder` detects resource pressure.
+    #[must_use]
+    pub fn with_load_shedder(mut self, load_shedder: Arc<LoadShedder>) -> Self {
+        self.load_shedder = Some(load_shedder);
+        self
+    }
+
+    /// Reject all transactions while the `disk_space_guard` detects low disk space.
+    #[must_use]
+    pub fn with_disk_space_guard(mut self, disk_space_guard: Arc<DiskSpaceGuard>) -> Self {
+        self.disk_space_guard = Some(disk_space_guard);
+        self
+    }
+
+    /// Release the shares of escrowed keys held by the RPU to authorized auditors.
+    #[must_use]
+    pub fn with_escrow_shares(mut self, escrow_shares: Arc<EscrowShares>) -> Self {
+        self.escrow_shares = Some(escrow_shares);
+        self
+    }
+
+    /// Log all requests to the `access_log`.
+    #[must_use]
+    pub fn with_access_log(mut self, access_log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(access_log);
+        self
+    }
+
+    /// Stop the `Turi` gracefully when `shutdown` is drained.
+    #[must_use]
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
     /// The main server loop.
     pub async fn serve(self, listener: &mut TcpListener) -> Result<(), balise::Error> {
         let tls_identity = self.tls_identity.clone();
-        let server = Server::new(
-            handler!(ClientMessage, {
+        let limits = self.limits;
+        let shutdown = self.shutdown.clone();
+        let access_log = self.access_log.clone();
+        let sessions = self.sessions.clone();
+        let handler = handler!(ClientMessage, {
                 Ping(_) => Ok(Pong),
+                Describe(_) => self.handle_describe(),
                 Execute(params) => self.handle_execute(params).await,
                 GetValue(params) => self.reader.handle_get_value(params).await,
                 GetAccount(params) => self.reader.handle_get_account(params).await,
-                GetBlock(params) => self.reader.handle_get_block(params).await,
+                GetBlock(params) => self.handle_get_block(params).await,
+                StreamBlocks(params) => self.handle_stream_blocks(params).await,
                 GetCurrentBlockNumber(params) => self.reader.handle_get_current_block_number(params).await,
-            }),
-            tls_identity,
-        )?;
+                GetBannedPeers(params) => self.handle_get_banned_peers(params),
+                UnbanPeer(params) => self.handle_unban_peer(params),
+                GetQueue(params) => self.handle_get_queue(params).await,
+                EvictTransactions(params) => self.handle_evict_transactions(params).await,
+                GetClockSkews(params) => self.handle_get_clock_skews(params),
+                GetConsensusFailures(params) => self.handle_get_consensus_failures(params),
+                GetBlockProof(params) => self.reader.handle_get_block_proof(params).await,
+                ExecuteIdempotent(params) => self.handle_execute_idempotent(params).await,
+                GetScrubStatistics(params) => self.handle_get_scrub_statistics(params),
+                CompareChains(params) => self.handle_compare_chains(params).await,
+                GetEscrowShare(params) => self.handle_get_escrow_share(params),
+                GetSessionChallenge(_) => Ok(self.sessions.challenge()?),
+                OpenSession(params) => self.handle_open_session(params),
+                ExecuteIdempotentInSession(params) => self.handle_execute_idempotent_in_session(params).await,
+                GetValueInSession(params) => self.handle_get_value_in_session(params),
+                GetNetworkMetrics(params) => self.handle_get_network_metrics(params),
+                GetTransactionTrace(params) => self.handle_get_transaction_trace(params),
+                ExecuteWithDeadline(params) => self.handle_execute_with_deadline(params).await,
+                WatchReceipt(params) => self.handle_watch_receipt(params),
+                GetLeaderTerms(params) => self.handle_get_leader_terms(params),
+                GetExecutionReceipt(params) => self.reader.handle_get_execution_receipt(params).await,
+                GetRequestMetrics(params) => self.handle_get_request_metrics(params),
+                GetCertifiedHead(params) => self.handle_get_certified_head(params).await,
+                SubscribeBlocks(params) => self.handle_subscribe_blocks(params).await,
+                GetStatus(params) => self.handle_get_status(params),
+        });
+        let handler = move |request: ClientMessage, connection: ConnectionInfo| {
+            let access_log = access_log.map(|access_log| {
+                let (request_type, claimed_signer) = describe_request(&request, &sessions);
+                (access_log, request_type, claimed_signer, connection.clone())
+            });
+            let response = handler(request, connection);
+            async move {
+                let received = SystemTime::now();
+                let start = Instant::now();
+                let result = response.await;
+                if let Some((access_log, request_type, claimed_signer, connection)) = access_log {
+                    let mut entry = AccessLogEntry::new(
+                        received,
+                        connection.remote_addr,
+                        request_type,
+                        connection.request_size,
+                        result.as_ref().err().map(ToString::to_string),
+                        start.elapsed(),
+                    );
+                    if let Some((signer, verified)) = claimed_signer {
+                        entry = entry.with_claimed_signer(signer, verified);
+                    }
+                    access_log.record(&entry);
+                }
+                result
+            }
+        };
+        let server = Server::new(handler, tls_identity)?
+            .with_limits(limits)
+            .with_shutdown(shutdown);
         server.serve(listener).await?;
         Ok(())
     }
 
+    fn handle_describe(&self) -> Response<message::Describe> {
+        let requests = ClientMessage::request_names()
+            .iter()
+            .filter(|request| self.supports_request(request))
+            .map(|request| (*request).to_string())
+            .collect();
+
+        Ok(Description {
+            protocol_version: PROTOCOL_VERSION,
+            requests,
+            max_batch_size: self.reader.max_batch_size(),
+            max_payload_size: balise::frame::MAX_FRAME_LEN.into(),
+            chain_id: self.reader.chain_id()?,
+        })
+    }
+
+    /// Whether the request with the `name` is handled by this `Turi` (instead of being rejected).
+    fn supports_request(&self, name: &str) -> bool {
+        match name {
+            "Execute"
+            | "ExecuteIdempotent"
+            | "ExecuteIdempotentInSession"
+            | "ExecuteWithDeadline"
+            | "WatchReceipt"
+            | "GetCertifiedHead" => self.writer.is_some(),
+            "GetBannedPeers"
+            | "UnbanPeer"
+            | "GetQueue"
+            | "EvictTransactions"
+            | "GetClockSkews"
+            | "GetConsensusFailures"
+            | "GetLeaderTerms"
+            | "GetScrubStatistics"
+            | "CompareChains"
+            | "GetNetworkMetrics"
+            | "GetRequestMetrics"
+            | "GetTransactionTrace"
+            | "GetStatus" => self.writer.is_some() && self.admin_requests,
+            "GetEscrowShare" => self.escrow_shares.is_some(),
+            _ => true,
+        }
+    }
+
+    /// Verify that `signer` is an admin and admin requests are accepted by this `Turi`.
+    fn verify_admin_request(&self, signer: &PeerId) -> Result<(), BoxError> {
+        if !self.admin_requests {
+            return Err("Admin requests are disabled on this Turi.".into());
+        }
+        self.transaction_checker
+            .account_checker(signer.clone())?
+            .verify_is_admin()?;
+        Ok(())
+    }
+
+    /// Verify that low-priority requests are accepted (see `LoadShedder`).
+    fn verify_accepting(&self) -> Result<(), BoxError> {
+        if let Some(load_shedder) = &self.load_shedder {
+            load_shedder.verify_accepting()?;
+        }
+        Ok(())
+    }
+
+    /// Get the consensus of a `Turi` that is not read-only.
+    fn consensus(&self) -> Result<&Consensus, BoxError> {
+        match &self.writer {
+            Some(writer) => Ok(&writer.consensus),
+            None => Err("This RPU is a read-only observer.".into()),
+        }
+    }
+
+    fn handle_get_banned_peers(
+        &self,
+        params: message::GetBannedPeers,
+    ) -> Response<message::GetBannedPeers> {
+        let message::GetBannedPeers(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.ban_list().banned_peers())
+    }
+
+    fn handle_unban_peer(&self, params: message::UnbanPeer) -> Response<message::UnbanPeer> {
+        let message::UnbanPeer(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        self.consensus()?.ban_list().unban(&message.peer_id)?;
+        Ok(())
+    }
+
+    fn handle_get_clock_skews(
+        &self,
+        params: message::GetClockSkews,
+    ) -> Response<message::GetClockSkews> {
+        let message::GetClockSkews(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.clock_skews())
+    }
+
+    fn handle_get_consensus_failures(
+        &self,
+        params: message::GetConsensusFailures,
+    ) -> Response<message::GetConsensusFailures> {
+        let message::GetConsensusFailures(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.consensus_failures()?)
+    }
+
+    fn handle_get_leader_terms(
+        &self,
+        params: message::GetLeaderTerms,
+    ) -> Response<message::GetLeaderTerms> {
+        let message::GetLeaderTerms(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.leader_terms()?)
+    }
+
+    fn handle_get_status(&self, params: message::GetStatus) -> Response<message::GetStatus> {
+        let message::GetStatus(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.status())
+    }
+
+    fn handle_get_scrub_statistics(
+        &self,
+        params: message::GetScrubStatistics,
+    ) -> Response<message::GetScrubStatistics> {
+        let message::GetScrubStatistics(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.scrub_statistics())
+    }
+
+    fn handle_get_network_metrics(
+        &self,
+        params: message::GetNetworkMetrics,
+    ) -> Response<message::GetNetworkMetrics> {
+        let message::GetNetworkMetrics(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        let mut metrics: Vec<_> = balise::client::connection_metrics()
+            .into_iter()
+            .map(|(address, metrics)| ConnectionMetrics {
+                address,
+                open_connections: metrics.open_connections,
+                checkouts: metrics.checkouts,
+                checkout_wait: metrics.checkout_wait,
+                max_checkout_wait: metrics.max_checkout_wait,
+                connects: metrics.connects,
+                reconnects: metrics.reconnects,
+                connect_failures: metrics.connect_failures,
+                successes: metrics.successes,
+                timeouts: metrics.timeouts,
+                decode_errors: metrics.decode_errors,
+                io_errors: metrics.io_errors,
+                other_errors: metrics.other_errors,
+            })
+            .collect();
+        metrics.sort_by_key(|metrics| metrics.address);
+        Ok(metrics)
+    }
+
+    fn handle_get_request_metrics(
+        &self,
+        params: message::GetRequestMetrics,
+    ) -> Response<message::GetRequestMetrics> {
+        let message::GetRequestMetrics(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        let histogram = |histogram: balise::server::Histogram| Histogram {
+            buckets: histogram.buckets,
+            count: histogram.count,
+            sum: histogram.sum,
+        };
+        let mut metrics: Vec<_> = balise::server::request_metrics()
+            .into_iter()
+            .map(|((api, request), metrics)| RequestMetrics {
+                api: api.to_string(),
+                request: request.to_string(),
+                requests: metrics.requests,
+                errors: metrics.errors,
+                request_size: histogram(metrics.request_size),
+                response_size: histogram(metrics.response_size),
+                latency: histogram(metrics.latency),
+            })
+            .collect();
+        metrics.sort_by(|a, b| (&a.api, &a.request).cmp(&(&b.api, &b.request)));
+        Ok(metrics)
+    }
+
+    async fn handle_compare_chains(
+        &self,
+        params: message::CompareChains,
+    ) -> Response<message::CompareChains> {
+        let message::CompareChains(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.compare_chains().await?)
+    }
+
+    async fn handle_get_certified_head(
+        &self,
+        params: message::GetCertifiedHead,
+    ) -> Response<message::GetCertifiedHead> {
+        let message::GetCertifiedHead(message) = params;
+        let message = message.verify()?;
+
+        // The sender needs to have a valid account.
+        self.transaction_checker
+            .account_checker(message.signer().clone())?;
+
+        Ok(self.consensus()?.certified_head().await?)
+    }
+
+    fn handle_get_escrow_share(
+        &self,
+        params: message::GetEscrowShare,
+    ) -> Response<message::GetEscrowShare> {
+        let message::GetEscrowShare(message) = params;
+        let message = message.verify()?;
+
+        let escrow_shares = match &self.escrow_shares {
+            Some(escrow_shares) => escrow_shares,
+            None => return Err("This RPU holds no shares of escrowed keys.".into()),
+        };
+
+        // The account of the auditor must still be valid.
+        self.transaction_checker
+            .account_checker(message.signer().clone())?;
+
+        escrow_shares.release(message.signer(), &message)
+    }
+
+    fn handle_get_transaction_trace(
+        &self,
+        params: message::GetTransactionTrace,
+    ) -> Response<message::GetTransactionTrace> {
+        let message::GetTransactionTrace(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.transaction_trace(&message.hash))
+    }
+
+    async fn handle_get_queue(&self, params: message::GetQueue) -> Response<message::GetQueue> {
+        let message::GetQueue(message) = params;
+        let message = message.verify()?;
+
+        self.verify_admin_request(message.signer())?;
+
+        Ok(self.consensus()?.queued_transactions().await)
+    }
+
+    async fn handle_evict_transactions(
+        &self,
+        params: message::EvictTransactions,
+    ) -> Response<message::EvictTransactions> {
+        let message::EvictTransactions(request) = params;
+
+        let writer = match &self.writer {
+            Some(writer) => writer,
+            None => return Err("This RPU is a read-only observer.".into()),
+        };
+
+        self.verify_admin_request(request.verify_ref()?.signer())?;
+
+        // Every RPU (including this one) evicts the transactions from its own queue.
+        let broadcaster = writer.batcher.broadcaster();
+        let message = peer_message::EvictTransactions(broadcaster.chain_id(), request);
+        broadcaster.broadcast(&message).await?;
+        Ok(())
+    }
+
     async fn handle_execute(&self, params: message::Execute) -> Response<message::Execute> {
         let message::Execute(transaction) = params;
 
+        let writer = match &self.writer {
+            Some(writer) => writer,
+            None => return Err("This RPU is a read-only observer.".into()),
+        };
+
+        // A halted RPU is read-only.
+        writer.consensus.verify_not_halted()?;
+
+        // So is an RPU running out of disk space.
+        if let Some(disk_space_guard) = &self.disk_space_guard {
+            disk_space_guard.verify_writable()?;
+        }
+
         // Check validity of transaction signature.
         let transaction = transaction.verify()?;
 
@@ -70,6 +637,16 @@ impl Turi {
         self.transaction_checker
             .verify_permissions(transaction.borrow())?;
 
+        // Account transactions are accepted even under resource pressure.
+        if let Transaction::KeyValue(_)
+        | Transaction::KeyValueWithMetadata(_)
+        | Transaction::EncryptedKeyValue(_)
+        | Transaction::MultiKeyValue(_)
+        | Transaction::CompareAndSwap(_) = &*transaction
+        {
+            self.verify_accepting()?;
+        }
+
         let peer_id = transaction.signer();
         match &*transaction {
             Transaction::KeyValue(params) => {
@@ -108,13 +685,354 @@ impl Turi {
                     std::time::SystemTime::now().duration_since(params.timestamp),
                 );
             }
+            Transaction::SetSchema(params) => {
+                log::debug!(
+                    "Client {} sets schema of namespace {:?}: {:?} (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.namespace,
+                    params.schema,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::ScheduleMigration(params) => {
+                log::debug!(
+                    "Client {} schedules the migration to WorldState version {} at block #{} (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.version,
+                    params.activation_height,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::SetParameter(params) => {
+                log::debug!(
+                    "Client {} sets parameter {} to {} from block #{} on (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.parameter,
+                    params.value,
+                    params.activation_height,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::KeyValueWithMetadata(params) => {
+                log::debug!(
+                    "Client {} set {} to {:?} ({:?}) (time since transaction-creation on the client: {:?}).",
+                    peer_id,
+                    params.key,
+                    params.value,
+                    params.metadata,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::SetLeaderWeight(params) => {
+                log::debug!(
+                    "Client {} sets the leader weight of {} to {} (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.id,
+                    params.weight,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::EncryptedKeyValue(params) => {
+                log::debug!(
+                    "Client {} set {} to an encrypted value (key {}) (time since transaction-creation on the client: {:?}).",
+                    peer_id,
+                    params.key,
+                    params.value.key_reference,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::EscrowKey(params) => {
+                log::debug!(
+                    "Client {} escrows key {} (threshold {} of {} shares) (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.key_reference,
+                    params.threshold,
+                    params.share_hashes.len(),
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::AuthorizeDecryption(params) => {
+                log::debug!(
+                    "Client {} authorizes {} to decrypt key {}: {:?} (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.auditor,
+                    params.key_reference,
+                    params.reason,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::SetQueueWeight(params) => {
+                log::debug!(
+                    "Client {} sets the queue weight of {} to {} (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.id,
+                    params.weight,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::SetQuota(params) => {
+                log::debug!(
+                    "Client {} sets the quota of namespace {:?}: {:?} (time since transaction-creation on the client: {:?}).",
+                    &transaction.signer(),
+                    params.namespace,
+                    params.quota,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::MultiKeyValue(params) => {
+                log::debug!(
+                    "Client {} set {} keys at once (time since transaction-creation on the client: {:?}).",
+                    peer_id,
+                    params.values.len(),
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
+            Transaction::CompareAndSwap(params) => {
+                log::debug!(
+                    "Client {} swaps the shared key {} from {:?} to {:?} (time since transaction-creation on the client: {:?}).",
+                    peer_id,
+                    params.key,
+                    params.expected,
+                    params.new,
+                    std::time::SystemTime::now().duration_since(params.timestamp),
+                );
+            }
         }
 
-        let batcher = self.batcher.clone();
+        let transaction: Signed<Transaction> = transaction.into();
+        writer
+            .consensus
+            .trace_transactions(Some(&transaction), &TraceEventKind::Submitted);
+
+        let batcher = writer.batcher.clone();
         tokio::spawn(async move {
-            batcher.add_to_batch(transaction.into()).await;
+            batcher.add_to_batch(transaction).await;
         });
 
         Ok(())
     }
+
+    /// Bulk block reads (used for mirroring) are paused under resource pressure.
+    async fn handle_get_block(&self, params: message::GetBlock) -> Response<message::GetBlock> {
+        self.verify_accepting()?;
+        self.reader.handle_get_block(params).await
+    }
+
+    /// Block streams (used for exports) are paused under resource pressure.
+    async fn handle_stream_blocks(
+        &self,
+        params: message::StreamBlocks,
+    ) -> Response<message::StreamBlocks> {
+        self.verify_accepting()?;
+        self.reader.handle_stream_blocks(params).await
+    }
+
+    /// Subscriptions stay open, so only new ones are refused under resource pressure.
+    async fn handle_subscribe_blocks(
+        &self,
+        params: message::SubscribeBlocks,
+    ) -> Response<message::SubscribeBlocks> {
+        self.verify_accepting()?;
+        self.reader.handle_subscribe_blocks(params).await
+    }
+
+    async fn handle_execute_idempotent(
+        &self,
+        params: message::ExecuteIdempotent,
+    ) -> Response<message::ExecuteIdempotent> {
+        let message::ExecuteIdempotent(params) = params;
+        let params = params.verify()?;
+        let signer = params.signer().clone();
+        self.execute_idempotent(signer, params.into_inner()).await
+    }
+
+    /// Execute a transaction at most once per idempotency key and expire it after its deadline.
+    async fn handle_execute_with_deadline(
+        &self,
+        params: message::ExecuteWithDeadline,
+    ) -> Response<message::ExecuteWithDeadline> {
+        let message::ExecuteWithDeadline(request) = params;
+        let signer = request.verify_ref()?.signer().clone();
+
+        let writer = match &self.writer {
+            Some(writer) => writer,
+            None => return Err("This RPU is a read-only observer.".into()),
+        };
+
+        let hash = transaction_hash(&request.unverified_ref().transaction);
+        let inserted = writer.deadlines.insert(request.clone())?;
+        let ExecuteWithDeadline {
+            idempotency_key,
+            transaction,
+            ..
+        } = request.unverified_ref().clone();
+        let params = ExecuteIdempotent {
+            idempotency_key,
+            transaction,
+        };
+        match self.execute_idempotent(signer, params).await {
+            Ok(receipt) => Ok(receipt),
+            Err(err) => {
+                // A failed submission can be retried.
+                if inserted {
+                    writer.deadlines.remove(&hash);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn handle_watch_receipt(
+        &self,
+        params: message::WatchReceipt,
+    ) -> Response<message::WatchReceipt> {
+        let message::WatchReceipt(message) = params;
+        let message = message.verify()?;
+
+        let writer = match &self.writer {
+            Some(writer) => writer,
+            None => return Err("This RPU is a read-only observer.".into()),
+        };
+
+        Ok(writer
+            .deadlines
+            .watch(message.transaction_hash, message.signer().clone())?)
+    }
+
+    /// Open a session for an existing account.
+    fn handle_open_session(&self, params: message::OpenSession) -> Response<message::OpenSession> {
+        let message::OpenSession(request) = params;
+        let token = self.sessions.open(request)?;
+        self.transaction_checker
+            .account_checker(token.peer_id.clone())?;
+        log::debug!("Client {} opened a session.", token.peer_id);
+        Ok(token)
+    }
+
+    async fn handle_execute_idempotent_in_session(
+        &self,
+        params: message::ExecuteIdempotentInSession,
+    ) -> Response<message::ExecuteIdempotentInSession> {
+        let message::ExecuteIdempotentInSession(params) = params;
+        let signer = self.sessions.authenticate(&params.token)?.clone();
+        self.execute_idempotent(signer, params.request).await
+    }
+
+    fn handle_get_value_in_session(
+        &self,
+        params: message::GetValueInSession,
+    ) -> Response<message::GetValueInSession> {
+        let message::GetValueInSession(params) = params;
+        let signer = self.sessions.authenticate(&params.token)?.clone();
+        self.reader.get_value(signer, params.request)
+    }
+
+    /// Execute a transaction of `signer` at most once per idempotency key.
+    async fn execute_idempotent(
+        &self,
+        signer: PeerId,
+        params: ExecuteIdempotent,
+    ) -> Response<message::ExecuteIdempotent> {
+        let writer = match &self.writer {
+            Some(writer) => writer,
+            None => return Err("This RPU is a read-only observer.".into()),
+        };
+
+        // Only the account itself may use its keys.
+        if params.transaction.signer() != &signer {
+            return Err("The transaction is not signed by the sender of the request.".into());
+        }
+
+        let receipt = Receipt {
+            transaction_hash: transaction_hash(&params.transaction),
+            received_at: SystemTime::now(),
+        };
+        let key = params.idempotency_key;
+        if let Some(receipt) = writer
+            .idempotency_keys
+            .insert(&signer, &key, receipt.clone())?
+        {
+            log::debug!(
+                "Client {} retried the submission with idempotency key {:?}.",
+                signer,
+                key
+            );
+            return Ok(receipt);
+        }
+
+        match self
+            .handle_execute(message::Execute(params.transaction))
+            .await
+        {
+            Ok(()) => Ok(receipt),
+            Err(err) => {
+                // A failed submission can be retried with the same key.
+                writer.idempotency_keys.remove(&signer, &key);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Get the type of a `request` and the signer it claims (see `AccessLogEntry`).
+///
+/// The claimed signer is only marked as verified if its signature (or session token) is valid.
+fn describe_request(
+    request: &ClientMessage,
+    sessions: &Sessions,
+) -> (&'static str, Option<(PeerId, bool)>) {
+    macro_rules! describe {
+        (
+            unsigned: [$($unsigned:ident),*],
+            signed: [$($signed:ident),*],
+            in_session: [$($in_session:ident),*],
+        ) => {
+            match request {
+                $(ClientMessage::$unsigned(_) => (stringify!($unsigned), None),)*
+                $(ClientMessage::$signed(params) => {
+                    let verified = params.0.verify_ref().is_ok();
+                    (stringify!($signed), Some((params.0.signer().clone(), verified)))
+                })*
+                $(ClientMessage::$in_session(params) => {
+                    let client = sessions.authenticate(&params.0.token).ok().cloned();
+                    (stringify!($in_session), client.map(|client| (client, true)))
+                })*
+            }
+        };
+    }
+
+    describe!(
+        unsigned: [Ping, Describe, GetSessionChallenge],
+        signed: [
+            Execute,
+            GetValue,
+            GetAccount,
+            GetBlock,
+            GetCurrentBlockNumber,
+            GetBannedPeers,
+            UnbanPeer,
+            GetQueue,
+            EvictTransactions,
+            GetClockSkews,
+            StreamBlocks,
+            GetConsensusFailures,
+            GetBlockProof,
+            ExecuteIdempotent,
+            GetScrubStatistics,
+            CompareChains,
+            GetEscrowShare,
+            OpenSession,
+            GetNetworkMetrics,
+            GetTransactionTrace,
+            ExecuteWithDeadline,
+            WatchReceipt,
+            GetLeaderTerms,
+            GetExecutionReceipt,
+            GetRequestMetrics,
+            GetCertifiedHead,
+            SubscribeBlocks,
+            GetStatus
+        ],
+        in_session: [ExecuteIdempotentInSession, GetValueInSession],
+    )
 }
@@ -2,16 +2,45 @@
 
 #![allow(clippy::module_name_repetitions)]
 
+mod escrow;
+mod execution;
+mod fault_tolerance;
+mod keys;
+mod leader_weights;
+mod migration;
+mod parameters;
+mod queue_weights;
+mod quotas;
+mod storage;
+
+pub use escrow::EscrowedKey;
+pub use execution::ExecutionError;
+pub use fault_tolerance::{FaultTolerance, FaultToleranceError};
+pub(crate) use leader_weights::leader_index;
+pub use migration::{Migration, ScheduledMigration, LATEST_VERSION, MIGRATIONS};
+pub use parameters::ScheduledParameter;
 pub use prellblock_client_api::account::{Account, Permissions};
+pub use quotas::{QuotaCharge, QuotaExceeded};
+pub use storage::{FsyncPolicy, PersistenceConfig};
 
 use crate::{
     block_storage::BlockStorage,
-    consensus::{Block, BlockHash, BlockNumber},
+    consensus::{Block, BlockHash, BlockNumber, LeaderTerm},
+    storage::StorageConfig,
     BoxError,
 };
-use im::{HashMap, Vector};
+use im::{HashMap, OrdSet, Vector};
 use pinxit::{PeerId, Signed};
-use prellblock_client_api::{account::AccountType, Transaction};
+use prellblock_client_api::{
+    account::AccountType,
+    canonical,
+    consensus::transaction_hash,
+    encryption::KeyReference,
+    parameter::Parameter,
+    quota::{Quota, QuotaUsage},
+    schema::Schema,
+    ExecutionReceipt, ExecutionStatus, Transaction, DEFAULT_LEADER_WEIGHT, DEFAULT_QUEUE_WEIGHT,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt,
@@ -19,6 +48,7 @@ use std::{
     ops::{Deref, DerefMut},
     sync::{Arc, Mutex},
 };
+use storage::Storage;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 /// Struct holding a `Worldstate` and it's previous `Worldstate`, if any.
@@ -34,6 +64,7 @@ pub struct WorldStateReferences {
 pub struct WorldStateService {
     world_state_references: Arc<Mutex<WorldStateReferences>>,
     writer: Arc<Semaphore>,
+    storage: Option<Arc<Mutex<Storage>>>,
 }
 
 impl fmt::Display for WorldStateService {
@@ -54,27 +85,62 @@ impl WorldStateService {
         Self {
             world_state_references: Arc::new(world_state_references.into()),
             writer: Arc::new(Semaphore::new(1)),
+            storage: None,
         }
     }
 
     /// Create a new `WorldStateService` initalized with the blocks from a `block_storage`.
     pub fn from_block_storage(block_storage: &BlockStorage) -> Result<Self, BoxError> {
-        let mut world_state_references = WorldStateReferences::default();
+        let world_state_references = replay_block_storage(block_storage)?;
+        log::debug!("Current WorldState: {:#}", world_state_references.current);
+        Ok(Self::with_world_state_references(world_state_references))
+    }
 
-        let mut blocks = block_storage.read(..);
-        let last_block = blocks.next_back();
-        for block in blocks {
-            world_state_references.current.apply_block(block?)?;
-        }
+    /// Create a new `WorldStateService` that is persisted incrementally as configured in `config`.
+    ///
+    /// The persisted `WorldState` is only used if it matches a block in the `block_storage`.
+    /// If it is ahead of the `block_storage`, it is rolled back to the persisted snapshot.
+    /// Blocks that are missing in the persisted `WorldState` (e.g. after a crash) are applied.
+    /// Otherwise, the `WorldState` is rebuilt from all blocks.
+    pub fn with_persistence(
+        block_storage: &BlockStorage,
+        config: PersistenceConfig,
+    ) -> Result<Self, BoxError> {
+        Self::with_tuned_persistence(block_storage, config, &StorageConfig::default())
+    }
 
-        if let Some(last_block) = last_block {
-            world_state_references.prev = Some(world_state_references.current.clone());
-            world_state_references.current.apply_block(last_block?)?;
-        }
+    /// Like `with_persistence`, but the database is tuned by `storage_config`.
+    pub fn with_tuned_persistence(
+        block_storage: &BlockStorage,
+        config: PersistenceConfig,
+        storage_config: &StorageConfig,
+    ) -> Result<Self, BoxError> {
+        let mut storage = Storage::new(config, storage_config)?;
+
+        let world_state_references = match load_persisted(&mut storage, block_storage) {
+            Ok(Some(world_state_references)) => world_state_references,
+            result => {
+                if let Err(err) = result {
+                    log::warn!("Could not load persisted WorldState: {}", err);
+                }
+                log::info!("Rebuilding WorldState from BlockStorage.");
+                let world_state_references = replay_block_storage(block_storage)?;
+                match &world_state_references.prev {
+                    Some(prev) => {
+                        storage.reset(prev)?;
+                        storage.persist(prev, &world_state_references.current)?;
+                    }
+                    None => storage.reset(&world_state_references.current)?,
+                }
+                world_state_references
+            }
+        };
 
         log::debug!("Current WorldState: {:#}", world_state_references.current);
 
-        Ok(Self::with_world_state_references(world_state_references))
+        let mut world_state_service = Self::with_world_state_references(world_state_references);
+        world_state_service.storage = Some(Arc::new(Mutex::new(storage)));
+        Ok(world_state_service)
     }
 
     /// Create a new `WorldStateService`.
@@ -90,30 +156,161 @@ impl WorldStateService {
     }
 
     /// Rollback the `WorldState` to the previous state.
+    ///
+    /// The persisted `WorldState` (if any) is cleared and will be rewritten on the next save.
     #[allow(clippy::must_use_candidate)]
     pub fn rollback(&self) -> Option<WorldState> {
         let mut world_state_references = self.world_state_references.lock().unwrap();
         let previous = world_state_references.prev.take()?;
         let old_current = std::mem::replace(&mut world_state_references.current, previous);
+        if let Some(storage) = &self.storage {
+            if let Err(err) = storage.lock().unwrap().clear() {
+                log::error!("Could not clear persisted WorldState: {}", err);
+            }
+        }
         Some(old_current)
     }
 
+    /// Replace the `WorldState` by a snapshot received from another RPU.
+    ///
+    /// There is no previous state afterwards, so the snapshot cannot be rolled back.
+    pub async fn install_snapshot(&self, world_state: WorldState) {
+        let _permit = self.writer.acquire().await;
+        if let Some(storage) = &self.storage {
+            if let Err(err) = storage.lock().unwrap().reset(&world_state) {
+                log::error!("Could not persist WorldState snapshot: {}", err);
+            }
+        }
+        let mut world_state_references = self.world_state_references.lock().unwrap();
+        world_state_references.prev = None;
+        world_state_references.current = world_state;
+    }
+
     /// Return a copy of the entire `WorldState`.
     pub async fn get_writable(&self) -> WritableWorldState {
         let permit = self.writer.clone().acquire_owned().await;
         WritableWorldState {
             shared_world_state: self.world_state_references.clone(),
+            storage: self.storage.clone(),
             world_state: self.get(),
             permit,
         }
     }
 }
 
+/// Build the `WorldState` (and its previous state) by applying all blocks of the `block_storage`.
+fn replay_block_storage(block_storage: &BlockStorage) -> Result<WorldStateReferences, BoxError> {
+    let mut world_state_references = WorldStateReferences::default();
+
+    // The execution receipts are recorded again, in case the RPU stopped before recording them.
+    let mut blocks = block_storage.read(..);
+    let last_block = blocks.next_back();
+    for block in blocks {
+        let receipts = world_state_references.current.apply_block(block?)?;
+        block_storage.write_execution_receipts(&receipts)?;
+    }
+
+    if let Some(last_block) = last_block {
+        world_state_references.prev = Some(world_state_references.current.clone());
+        let receipts = world_state_references.current.apply_block(last_block?)?;
+        block_storage.write_execution_receipts(&receipts)?;
+    }
+
+    Ok(world_state_references)
+}
+
+/// Load the persisted `WorldState` and apply the blocks it is missing.
+///
+/// Returns `None` if the persisted `WorldState` does not match the `block_storage`.
+fn load_persisted(
+    storage: &mut Storage,
+    block_storage: &BlockStorage,
+) -> Result<Option<WorldStateReferences>, BoxError> {
+    let (mut current, mut prev) = match storage.load()? {
+        Some(world_states) => world_states,
+        None => return Ok(None),
+    };
+
+    // The persisted state must be based on the same chain as the `BlockStorage`.
+    // It can be ahead of the `BlockStorage` (e.g. if the last blocks were not flushed before a crash),
+    // then the older snapshot may still match.
+    if !matches_block_storage(&current, block_storage)? {
+        log::warn!(
+            "Persisted WorldState at block #{} does not match the BlockStorage.",
+            current.block_number
+        );
+        let snapshot = match storage.load_snapshot()? {
+            Some(snapshot) if matches_block_storage(&snapshot, block_storage)? => snapshot,
+            _ => return Ok(None),
+        };
+        log::warn!(
+            "Rolling back the persisted WorldState to the snapshot at block #{}.",
+            snapshot.block_number
+        );
+        storage.reset(&snapshot)?;
+        current = snapshot;
+        prev = None;
+    }
+
+    // Apply blocks that were stored but not yet persisted in the `WorldState` (e.g. after a crash).
+    for block in block_storage.read(current.block_number..) {
+        let previous = current.clone();
+        let receipts = current.apply_block(block?)?;
+        block_storage.write_execution_receipts(&receipts)?;
+        storage.persist(&previous, &current)?;
+        prev = Some(previous);
+    }
+
+    // The previous state is needed for rollbacks.
+    if prev.is_none() && current.block_number > BlockNumber::default() {
+        return Ok(None);
+    }
+
+    // Never start with a state that is inconsistent with the `BlockStorage`.
+    let block_number = block_storage
+        .read(..)
+        .next_back()
+        .transpose()?
+        .map_or_else(BlockNumber::default, |block| block.block_number() + 1);
+    if current.block_number != block_number || !matches_block_storage(&current, block_storage)? {
+        return Err(format!(
+            "The WorldState at block #{} is inconsistent with the BlockStorage at block #{}.",
+            current.block_number, block_number
+        )
+        .into());
+    }
+    log::info!(
+        "Loaded persisted WorldState at block #{} (state root {}).",
+        current.block_number,
+        current.state_root()?
+    );
+
+    Ok(Some(WorldStateReferences { current, prev }))
+}
+
+/// Check whether the last block applied to the `world_state` is stored in the `block_storage`.
+fn matches_block_storage(
+    world_state: &WorldState,
+    block_storage: &BlockStorage,
+) -> Result<bool, BoxError> {
+    if world_state.block_number == BlockNumber::default() {
+        return Ok(true);
+    }
+    let last_block = block_storage
+        .read(world_state.block_number - 1..world_state.block_number)
+        .next()
+        .transpose()?;
+    Ok(
+        matches!(last_block, Some(last_block) if last_block.body.hash() == world_state.last_block_hash),
+    )
+}
+
 /// A writable copy of the `WorldState`. Can be edited and later `save`d to the global `WorldState`
 #[derive(Debug)]
 #[must_use]
 pub struct WritableWorldState {
     shared_world_state: Arc<Mutex<WorldStateReferences>>,
+    storage: Option<Arc<Mutex<Storage>>>,
     world_state: WorldState,
     #[allow(dead_code)]
     permit: OwnedSemaphorePermit,
@@ -121,9 +318,21 @@ pub struct WritableWorldState {
 
 impl WritableWorldState {
     /// Save the cahnged `WorldState`.
+    ///
+    /// If persistence is enabled, the changes are persisted as well.
+    /// Failing to persist is not fatal, because the `WorldState` can be rebuilt from the `BlockStorage`.
     pub fn save(self) {
         log::trace!("Changed WorldState: {:#}", self.world_state);
         let mut world_state_references = self.shared_world_state.lock().unwrap();
+        if let Some(storage) = &self.storage {
+            if let Err(err) = storage
+                .lock()
+                .unwrap()
+                .persist(&world_state_references.current, &self.world_state)
+            {
+                log::error!("Could not persist WorldState: {}", err);
+            }
+        }
         world_state_references.prev = Some(world_state_references.current.clone());
         world_state_references.current = self.world_state;
     }
@@ -149,120 +358,327 @@ impl DerefMut for WritableWorldState {
 }
 
 /// A `WorldState` keeps track of the current state of the blockchain.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+///
+/// It is serialized canonically, so all RPUs create the same snapshot of the same state.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WorldState {
     /// Field storing the `Account` `Permissions`.
+    #[serde(serialize_with = "canonical::serialize_map")]
     pub accounts: HashMap<PeerId, Arc<Account>>,
     /// Field storing the `Peer`s.
     pub peers: Vector<(PeerId, SocketAddr)>,
+    /// The `Schema`s of the values by key namespace.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub schemas: HashMap<String, Schema>,
     /// The number of `Block`s applied to the `WorldState`.
     pub block_number: BlockNumber,
     /// Hash of the last `Block` in the `BlockStorage`.
     pub last_block_hash: BlockHash,
+    /// The format version of the `WorldState` (see `MIGRATIONS`).
+    pub version: u32,
+    /// The migration to execute at its activation height (if any).
+    pub scheduled_migration: Option<ScheduledMigration>,
+    /// The governed parameters that differ from their default value.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub parameters: HashMap<Parameter, u64>,
+    /// The parameter changes that are not yet active.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub scheduled_parameters: HashMap<Parameter, ScheduledParameter>,
+    /// The leader weights of the RPUs that differ from `DEFAULT_LEADER_WEIGHT`.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub leader_weights: HashMap<PeerId, u32>,
+    /// The namespace keys escrowed with the RPUs.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub escrowed_keys: HashMap<KeyReference, EscrowedKey>,
+    /// The leader term of the last `Block` (leader terms never decrease along the chain).
+    pub last_leader_term: LeaderTerm,
+    /// The queue weights of the accounts that differ from `DEFAULT_QUEUE_WEIGHT`.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub queue_weights: HashMap<PeerId, u32>,
+    /// The keys written by each account (see `scan_range`).
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub keys: HashMap<PeerId, OrdSet<String>>,
+    /// The write throughput `Quota`s by key namespace.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub quotas: HashMap<String, Quota>,
+    /// The usage of the `quotas` in their current window by key namespace.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub quota_usage: HashMap<String, QuotaUsage>,
+    /// The values of the shared keys set by `CompareAndSwap` transactions.
+    #[serde(serialize_with = "canonical::serialize_map")]
+    pub shared_values: HashMap<String, Vec<u8>>,
 }
 
 impl WorldState {
     /// Apply a block to the current world state.
-    pub fn apply_block(&mut self, block: Block) -> Result<(), BoxError> {
-        if block.body.prev_block_hash != self.last_block_hash {
+    ///
+    /// Transactions that cannot be applied are skipped (without changing the world state),
+    /// which is deterministic, so all RPUs reach the same state.
+    /// Returns the `ExecutionReceipt`s of all transactions of the block.
+    /// Only errors of the block itself (e.g. a wrong previous block hash) fail.
+    pub fn apply_block(&mut self, block: Block) -> Result<Vec<ExecutionReceipt>, BoxError> {
+        // The genesis block chooses the hash function of the chain.
+        let last_block_hash = if self.block_number == BlockNumber::default() {
+            BlockHash::zero(block.body.prev_block_hash.algorithm())
+        } else {
+            self.last_block_hash
+        };
+        if block.body.prev_block_hash != last_block_hash {
             return Err("Last block hash is not equal to hash of last block.".into());
         }
+        if block.body.leader_term < self.last_leader_term {
+            return Err(format!(
+                "The leader term {} of block #{} is lower than the leader term {} of the last block.",
+                block.body.leader_term, block.body.height, self.last_leader_term
+            )
+            .into());
+        }
+        // The block can be sent by another RPU, so its number must not overflow.
+        let next_block_number = block
+            .body
+            .height
+            .checked_add(1)
+            .ok_or_else(|| format!("The block #{} has no successor.", block.body.height))?;
         // TODO: validate block (peers, signatures, etc)
+        if let Some(scheduled_migration) = self.scheduled_migration {
+            if scheduled_migration.activation_height == block.body.height {
+                migration::migrate(self, scheduled_migration.version)?;
+                self.scheduled_migration = None;
+            }
+        }
         self.last_block_hash = block.body.hash();
-        self.block_number = block.body.height + 1;
-        for transaction in block.body.transactions {
-            self.apply_transaction(transaction);
+        self.last_leader_term = block.body.leader_term;
+        self.block_number = next_block_number;
+        let block_number = block.body.height;
+        let timestamp = block.body.timestamp;
+        let mut receipts = Vec::with_capacity(block.body.transactions.len());
+        for (index, transaction) in block.body.transactions.into_iter().enumerate() {
+            let transaction_hash = transaction_hash(&transaction);
+            // Only applied transactions count towards the quotas.
+            let result = self
+                .check_quota(transaction.unverified_ref(), timestamp)
+                .map_err(ExecutionError::from)
+                .and_then(|charge| {
+                    self.try_apply_transaction(transaction)?;
+                    self.charge_quota(charge);
+                    Ok(())
+                });
+            let status = match result {
+                Ok(()) => ExecutionStatus::Applied,
+                Err(err) => {
+                    log::error!(
+                        "Skipped transaction {} of block #{}: {}",
+                        transaction_hash,
+                        block_number,
+                        err
+                    );
+                    ExecutionStatus::Failed {
+                        reason: err.to_string(),
+                    }
+                }
+            };
+            receipts.push(ExecutionReceipt {
+                transaction_hash,
+                block_number,
+                index,
+                status,
+            });
         }
-        Ok(())
+        self.activate_parameters();
+        Ok(receipts)
+    }
+
+    /// Calculate the state root (the hash of the canonical serialization of the `WorldState`).
+    ///
+    /// Equal states have equal state roots on all RPUs.
+    pub fn state_root(&self) -> Result<BlockHash, postcard::Error> {
+        Ok(BlockHash::of(&postcard::to_stdvec(self)?))
+    }
+
+    /// Get the `Schema` of a `key` (the schema of the longest matching namespace).
+    #[must_use]
+    pub fn schema(&self, key: &str) -> Option<&Schema> {
+        self.schemas
+            .iter()
+            .filter(|(namespace, _)| key.starts_with(namespace.as_str()))
+            .max_by_key(|(namespace, _)| namespace.len())
+            .map(|(_, schema)| schema)
     }
 
     /// Apply a transaction to the current world state.
+    ///
+    /// A transaction that cannot be applied is skipped (see `try_apply_transaction`).
     pub fn apply_transaction(&mut self, transaction: Signed<Transaction>) {
+        if let Err(err) = self.try_apply_transaction(transaction) {
+            log::warn!("Skipped transaction: {}", err);
+        }
+    }
+
+    /// Apply a transaction to the current world state.
+    ///
+    /// The world state is left unchanged if the transaction cannot be applied.
+    pub fn try_apply_transaction(
+        &mut self,
+        transaction: Signed<Transaction>,
+    ) -> Result<(), ExecutionError> {
+        let signer = transaction.signer().clone();
         match transaction.unverified() {
-            Transaction::KeyValue(_) => {}
+            Transaction::KeyValue(params) => self.record_key(&signer, &params.key),
+            Transaction::KeyValueWithMetadata(params) => self.record_key(&signer, &params.key),
+            Transaction::EncryptedKeyValue(params) => self.record_key(&signer, &params.key),
             Transaction::UpdateAccount(params) => {
-                if let Some(account) = self.accounts.get_mut(&params.id).map(Arc::make_mut) {
+                // Should be checked in `TransactionChecker`.
+                let account = self
+                    .accounts
+                    .get_mut(&params.id)
+                    .map(Arc::make_mut)
+                    .ok_or_else(|| ExecutionError::UnknownAccount(params.id.clone()))?;
+                let peer_index = self.peers.iter().position(|(id, _)| *id == params.id);
+                match (&account.account_type, &params.permissions.account_type) {
+                    (AccountType::RPU { .. }, None)
+                    | (AccountType::RPU { .. }, Some(AccountType::RPU { .. })) => {}
                     // If was RPU and now it isn't, remove from peers list.
-                    // If it was, then add it to the peers list.
-                    match account.account_type {
-                        AccountType::RPU { .. } => {
-                            match params.permissions.account_type {
-                                None | Some(AccountType::RPU { .. }) => {}
-                                Some(_) => {
-                                    // Remove the account from peers.
-                                    if let Some(index) =
-                                        self.peers.iter().position(|(id, _)| *id == params.id)
-                                    {
-                                        self.peers.remove(index);
-                                    } else {
-                                        unreachable!(
-                                            "RPU to delete {} ({}) does not exist.",
-                                            params.id, account.name
-                                        )
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            if let Some(AccountType::RPU { peer_address, .. }) =
-                                params.permissions.account_type
-                            {
-                                // Add account because now it's an RPU.
-                                if self.peers.iter().any(|(id, _)| *id == params.id) {
-                                    unreachable!(
-                                        "RPU {} ({}) already exists.",
-                                        params.id, account.name
-                                    )
-                                }
-                                self.peers.push_back((params.id, peer_address));
-                            }
+                    (AccountType::RPU { .. }, Some(_)) => {
+                        let index = peer_index
+                            .ok_or_else(|| ExecutionError::UnknownPeer(params.id.clone()))?;
+                        self.leader_weights.remove(&params.id);
+                        self.peers.remove(index);
+                    }
+                    // Add account because now it's an RPU.
+                    (_, Some(AccountType::RPU { peer_address, .. })) => {
+                        if peer_index.is_some() {
+                            return Err(ExecutionError::PeerExists(params.id));
                         }
+                        self.peers.push_back((params.id.clone(), *peer_address));
                     }
-                    account.apply_permissions(params.permissions);
-                } else {
-                    // Should be checked in `TransactionChecker`.
-                    unreachable!("Account {} does not exist.", params.id);
+                    (_, _) => {}
                 }
+                account.apply_permissions(params.permissions);
             }
             Transaction::CreateAccount(params) => {
-                let mut account = Account::new(params.name);
                 let account_id = params.id;
-                account.apply_permissions(params.permissions);
-                let account = Arc::new(account);
-                if self
-                    .accounts
-                    .insert(account_id.clone(), account.clone())
-                    .is_some()
-                {
+                if self.accounts.contains_key(&account_id) {
                     // Should be checked in `TransactionChecker`.
-                    unreachable!("Account {} ({}) already exist.", account_id, account.name);
+                    return Err(ExecutionError::AccountExists(account_id));
                 }
+                let mut account = Account::new(params.name);
+                account.apply_permissions(params.permissions);
 
                 // Add the account as peer, if not exists.
                 if let AccountType::RPU { peer_address, .. } = account.account_type {
                     if self.peers.iter().any(|(id, _)| *id == account_id) {
-                        unreachable!("RPU {} ({}) already exists.", account_id, account.name)
+                        return Err(ExecutionError::PeerExists(account_id));
                     }
-                    self.peers.push_back((account_id, peer_address));
+                    self.peers.push_back((account_id.clone(), peer_address));
                 }
+                self.accounts.insert(account_id, Arc::new(account));
             }
             Transaction::DeleteAccount(params) => {
-                if let Some(account) = self.accounts.remove(&params.id) {
-                    // Remove the account from peers.
-                    if let Some(index) = self.peers.iter().position(|(id, _)| *id == params.id) {
-                        self.peers.remove(index);
-                    } else {
-                        unreachable!(
-                            "RPU to delete {} ({}) does not exist.",
-                            params.id, account.name
-                        )
-                    }
+                // Should be checked in `TransactionChecker`.
+                let account = self
+                    .accounts
+                    .get(&params.id)
+                    .ok_or_else(|| ExecutionError::UnknownAccount(params.id.clone()))?;
+                // Remove the account from peers.
+                if let AccountType::RPU { .. } = account.account_type {
+                    let index = self
+                        .peers
+                        .iter()
+                        .position(|(id, _)| *id == params.id)
+                        .ok_or_else(|| ExecutionError::UnknownPeer(params.id.clone()))?;
+                    self.peers.remove(index);
+                }
+                self.accounts.remove(&params.id);
+                self.leader_weights.remove(&params.id);
+                self.queue_weights.remove(&params.id);
+            }
+            Transaction::SetSchema(params) => match params.schema {
+                Some(schema) => {
+                    self.schemas.insert(params.namespace, schema);
+                }
+                None => {
+                    self.schemas.remove(&params.namespace);
+                }
+            },
+            Transaction::ScheduleMigration(params) => {
+                self.scheduled_migration = Some(ScheduledMigration {
+                    version: params.version,
+                    activation_height: params.activation_height,
+                });
+            }
+            Transaction::SetParameter(params) => {
+                self.scheduled_parameters.insert(
+                    params.parameter,
+                    ScheduledParameter {
+                        value: params.value,
+                        activation_height: params.activation_height,
+                    },
+                );
+            }
+            Transaction::SetLeaderWeight(params) => {
+                if params.weight == DEFAULT_LEADER_WEIGHT {
+                    self.leader_weights.remove(&params.id);
+                } else {
+                    self.leader_weights.insert(params.id, params.weight);
+                }
+            }
+            Transaction::EscrowKey(params) => {
+                self.escrowed_keys.insert(
+                    params.key_reference,
+                    EscrowedKey {
+                        key_hash: params.key_hash,
+                        threshold: params.threshold,
+                        share_hashes: params.share_hashes,
+                        auditors: Vec::new(),
+                    },
+                );
+            }
+            Transaction::AuthorizeDecryption(params) => {
+                if let Some(escrowed_key) = self.escrowed_keys.get_mut(&params.key_reference) {
+                    escrowed_key.authorize(params.auditor, params.expires_at);
                 } else {
                     // Should be checked in `TransactionChecker`.
-                    unreachable!("Account {} does not exist.", params.id);
+                    return Err(ExecutionError::KeyNotEscrowed(params.key_reference));
+                }
+            }
+            Transaction::SetQueueWeight(params) => {
+                if params.weight == DEFAULT_QUEUE_WEIGHT {
+                    self.queue_weights.remove(&params.id);
+                } else {
+                    self.queue_weights.insert(params.id, params.weight);
+                }
+            }
+            Transaction::SetQuota(params) => {
+                self.quota_usage.remove(&params.namespace);
+                match params.quota {
+                    Some(quota) => {
+                        self.quotas.insert(params.namespace, quota);
+                    }
+                    None => {
+                        self.quotas.remove(&params.namespace);
+                    }
+                }
+            }
+            Transaction::MultiKeyValue(params) => {
+                for (key, _) in &params.values {
+                    self.record_key(&signer, key);
+                }
+            }
+            Transaction::CompareAndSwap(params) => {
+                if self.shared_values.get(&params.key) != params.expected.as_ref() {
+                    return Err(ExecutionError::UnexpectedValue(params.key));
+                }
+                match params.new {
+                    Some(value) => {
+                        self.shared_values.insert(params.key, value);
+                    }
+                    None => {
+                        self.shared_values.remove(&params.key);
+                    }
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -0,0 +1,45 @@
+//! Namespace keys escrowed with the RPUs (see `prellblock_client_api::escrow`).
+
+use crate::consensus::BlockHash;
+use pinxit::PeerId;
+use prellblock_client_api::escrow::ShareHash;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// A namespace key whose shares are held by the RPUs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowedKey {
+    /// The hash of the key (to verify the reconstructed key).
+    pub key_hash: BlockHash,
+    /// The number of shares needed to reconstruct the key.
+    pub threshold: usize,
+    /// The hashes of the shares held by each RPU.
+    pub share_hashes: Vec<(PeerId, ShareHash)>,
+    /// The auditors authorized to collect the shares (with the expiry of their authorization).
+    pub auditors: Vec<(PeerId, SystemTime)>,
+}
+
+impl EscrowedKey {
+    /// Get the hash of the share held by the RPU `peer_id`.
+    #[must_use]
+    pub fn share_hash(&self, peer_id: &PeerId) -> Option<&ShareHash> {
+        self.share_hashes
+            .iter()
+            .find(|(id, _)| id == peer_id)
+            .map(|(_, share_hash)| share_hash)
+    }
+
+    /// Check whether `peer_id` is authorized to collect the shares at `now`.
+    #[must_use]
+    pub fn is_auditor(&self, peer_id: &PeerId, now: SystemTime) -> bool {
+        self.auditors
+            .iter()
+            .any(|(id, expires_at)| id == peer_id && now < *expires_at)
+    }
+
+    /// Authorize `auditor` to collect the shares until `expires_at` (replacing an earlier authorization).
+    pub fn authorize(&mut self, auditor: PeerId, expires_at: SystemTime) {
+        self.auditors.retain(|(id, _)| *id != auditor);
+        self.auditors.push((auditor, expires_at));
+    }
+}
@@ -0,0 +1,42 @@
+//! Errors of committed transactions that cannot be applied to the `WorldState`.
+
+use super::QuotaExceeded;
+use err_derive::Error;
+use pinxit::PeerId;
+use prellblock_client_api::encryption::KeyReference;
+
+/// A committed transaction could not be applied to the `WorldState`.
+///
+/// The `TransactionChecker` should prevent these errors (except `UnexpectedValue`, which is
+/// only known at commit time). If one occurs anyway, the transaction is skipped on all RPUs alike
+/// (see `WorldState::apply_block`).
+#[derive(Debug, Error)]
+pub enum ExecutionError {
+    /// The account does not exist.
+    #[error(display = "The account {} does not exist.", 0)]
+    UnknownAccount(PeerId),
+
+    /// The account already exists.
+    #[error(display = "The account {} already exists.", 0)]
+    AccountExists(PeerId),
+
+    /// The RPU is missing in the list of peers.
+    #[error(display = "The RPU {} is not a peer.", 0)]
+    UnknownPeer(PeerId),
+
+    /// The RPU is already in the list of peers.
+    #[error(display = "The RPU {} is already a peer.", 0)]
+    PeerExists(PeerId),
+
+    /// The key is not escrowed.
+    #[error(display = "The key {} is not escrowed.", 0)]
+    KeyNotEscrowed(KeyReference),
+
+    /// The quota of the namespace of the written key is exhausted.
+    #[error(display = "{}", 0)]
+    QuotaExceeded(#[error(from)] QuotaExceeded),
+
+    /// The shared key of a `CompareAndSwap` does not have the expected value.
+    #[error(display = "The key {:?} does not have the expected value.", 0)]
+    UnexpectedValue(String),
+}
@@ -0,0 +1,88 @@
+//! The number of faulty RPUs the consensus tolerates.
+//!
+//! The consensus is safe as long as at most `f` of `n >= 3f + 1` RPUs are faulty.
+//! Decisions need a quorum of `n - f` RPUs: two quorums always share at least `f + 1` RPUs
+//! (so at least one non-faulty RPU), and the `n - f` non-faulty RPUs can always form a quorum.
+
+use super::WorldState;
+use err_derive::Error;
+use prellblock_client_api::parameter::Parameter;
+
+/// The RPUs cannot tolerate the declared number of faulty RPUs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error(
+    display = "{} RPUs cannot tolerate {} faulty RPUs (at least 3f + 1 RPUs are needed).",
+    peer_count,
+    faulty
+)]
+pub struct FaultToleranceError {
+    /// The number of RPUs.
+    pub peer_count: usize,
+    /// The number of faulty RPUs to tolerate.
+    pub faulty: usize,
+}
+
+/// The number of faulty RPUs `f` tolerated by `n` RPUs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultTolerance {
+    peer_count: usize,
+    faulty: usize,
+}
+
+impl FaultTolerance {
+    /// Tolerate `faulty` of `peer_count` RPUs, or the most the RPUs allow if `faulty` is 0.
+    ///
+    /// At least one faulty RPU must be tolerated, so four RPUs are needed at least.
+    pub fn new(peer_count: usize, faulty: usize) -> Result<Self, FaultToleranceError> {
+        let faulty = if faulty == 0 {
+            (peer_count.saturating_sub(1) / 3).max(1)
+        } else {
+            faulty
+        };
+        // `n >= 3f + 1`
+        if peer_count.saturating_sub(1) / 3 < faulty {
+            return Err(FaultToleranceError { peer_count, faulty });
+        }
+        Ok(Self { peer_count, faulty })
+    }
+
+    /// Get the number of RPUs.
+    #[must_use]
+    pub const fn peer_count(self) -> usize {
+        self.peer_count
+    }
+
+    /// Get the number of faulty RPUs that are tolerated (`f`).
+    #[must_use]
+    pub const fn faulty(self) -> usize {
+        self.faulty
+    }
+
+    /// Get the number of RPUs needed for a decision (`n - f`).
+    #[must_use]
+    pub const fn quorum(self) -> usize {
+        self.peer_count - self.faulty
+    }
+
+    /// Get the number of RPUs that contains at least one non-faulty RPU (`f + 1`).
+    #[must_use]
+    pub const fn nonfaulty_count(self) -> usize {
+        self.faulty + 1
+    }
+
+    /// Check whether `count` RPUs are a quorum.
+    #[must_use]
+    pub const fn quorum_reached(self, count: usize) -> bool {
+        count >= self.quorum()
+    }
+}
+
+impl WorldState {
+    /// Get the `FaultTolerance` of the RPUs (see `Parameter::FaultTolerance`).
+    pub fn fault_tolerance(&self) -> Result<FaultTolerance, FaultToleranceError> {
+        FaultTolerance::new(
+            self.peers.len(),
+            self.parameter_count(Parameter::FaultTolerance),
+        )
+    }
+}
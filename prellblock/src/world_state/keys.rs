@@ -0,0 +1,64 @@
+//! The keys written by each account, to list and scan them without reading their values.
+
+use super::WorldState;
+use im::{ordset::DiffItem, OrdSet};
+use pinxit::PeerId;
+use prellblock_client_api::Filter;
+use std::ops::RangeBounds;
+
+impl WorldState {
+    /// Iterate over the keys written by the account `peer_id` in `range` (in lexicographical order).
+    ///
+    /// The `WorldState` is an immutable snapshot (`WorldStateService::get`), so blocks committed
+    /// while iterating do not change the result.
+    pub fn scan_range<'a, R>(
+        &'a self,
+        peer_id: &PeerId,
+        range: R,
+    ) -> impl Iterator<Item = &'a str> + 'a
+    where
+        R: RangeBounds<String> + 'a,
+    {
+        self.keys
+            .get(peer_id)
+            .map(|keys| keys.range(range))
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Iterate over the keys written by the account `peer_id` starting with `prefix` (in lexicographical order).
+    ///
+    /// See `scan_range` for the snapshot semantics.
+    pub fn keys_with_prefix<'a>(
+        &'a self,
+        peer_id: &PeerId,
+        prefix: &str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.scan_range(peer_id, Filter::key_prefix(prefix))
+    }
+
+    /// Record that the account `peer_id` wrote the `key`.
+    pub(super) fn record_key(&mut self, peer_id: &PeerId, key: &str) {
+        let keys = self.keys.entry(peer_id.clone()).or_insert_with(OrdSet::new);
+        if !keys.contains(key) {
+            keys.insert(key.to_string());
+        }
+    }
+}
+
+/// Get the keys in `current` that are not in `prev`.
+pub(super) fn added_keys(prev: Option<&OrdSet<String>>, current: &OrdSet<String>) -> Vec<String> {
+    match prev {
+        // Keys are never removed, so sets of the same size are equal.
+        Some(prev) if prev.len() == current.len() => Vec::new(),
+        Some(prev) => prev
+            .diff(current)
+            .filter_map(|item| match item {
+                DiffItem::Add(key) => Some(key.clone()),
+                _ => None,
+            })
+            .collect(),
+        None => current.iter().cloned().collect(),
+    }
+}
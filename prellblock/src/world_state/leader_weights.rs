@@ -0,0 +1,59 @@
+//! Selection of the leader of a leader term, preferring RPUs with a higher leader weight.
+
+use super::WorldState;
+use crate::consensus::LeaderTerm;
+use pinxit::PeerId;
+use prellblock_client_api::DEFAULT_LEADER_WEIGHT;
+
+impl WorldState {
+    /// Get the leader weight of the RPU `peer_id`.
+    #[must_use]
+    pub fn leader_weight(&self, peer_id: &PeerId) -> u32 {
+        self.leader_weights
+            .get(peer_id)
+            .copied()
+            .unwrap_or(DEFAULT_LEADER_WEIGHT)
+    }
+
+    /// Get the leader of a `leader_term`.
+    ///
+    /// The leaders are chosen by a smooth weighted round robin over the `peers`:
+    /// Within `W` consecutive leader terms (`W` is the sum of all weights), each RPU leads
+    /// as many terms as its weight, interleaved with the other RPUs.
+    /// If all weights are equal, the leaders rotate in the order of the `peers`.
+    #[must_use]
+    pub fn leader(&self, leader_term: LeaderTerm) -> &PeerId {
+        let weights: Vec<u32> = self
+            .peers
+            .iter()
+            .map(|(peer_id, _)| self.leader_weight(peer_id))
+            .collect();
+        &self.peers[leader_index(&weights, leader_term)].0
+    }
+}
+
+/// Get the index of the leader of a `leader_term` among RPUs with the given leader `weights`.
+///
+/// See `WorldState::leader`. Panics if `weights` is empty.
+#[must_use]
+pub fn leader_index(weights: &[u32], leader_term: LeaderTerm) -> usize {
+    let weights: Vec<i64> = weights.iter().copied().map(i64::from).collect();
+    let total: i64 = weights.iter().sum();
+
+    #[allow(clippy::cast_sign_loss)]
+    let rounds = (u64::from(leader_term) % (total as u64)) + 1;
+    let mut current = vec![0; weights.len()];
+    let mut leader = 0;
+    for _ in 0..rounds {
+        for (current, weight) in current.iter_mut().zip(&weights) {
+            *current += weight;
+        }
+        // The first RPU with the highest current weight leads.
+        leader = (0..current.len())
+            .rev()
+            .max_by_key(|&index| current[index])
+            .unwrap();
+        current[leader] -= total;
+    }
+    leader
+}
@@ -0,0 +1,65 @@
+//! Versioned `WorldState` formats and the migrations between them.
+//!
+//! Changing the layout of the `WorldState` would split the chain if RPUs changed it at different times.
+//! Therefore, the old (blue) and the new (green) format coexist in the code:
+//! The `WorldState` keeps its format `version` until an admin schedules the migration
+//! (see `Transaction::ScheduleMigration`). All RPUs (and every later replay of the chain)
+//! then migrate the `WorldState` right before applying the block at the activation height.
+//!
+//! To change the format, add a migration to `MIGRATIONS`. Migrations must be deterministic
+//! and must never be changed after they were released.
+
+use super::WorldState;
+use crate::{consensus::BlockNumber, BoxError};
+use serde::{Deserialize, Serialize};
+
+/// A migration of the `WorldState` to the next format version.
+pub struct Migration {
+    /// A short description of the changes.
+    pub description: &'static str,
+    /// Convert a `WorldState` of the previous version.
+    pub migrate: fn(&mut WorldState),
+}
+
+/// All migrations. The migration at index `i` migrates from version `i` to version `i + 1`.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    description: "Record the format version in the WorldState.",
+    migrate: |_| {},
+}];
+
+/// The newest `WorldState` format version supported by this RPU.
+#[allow(clippy::cast_possible_truncation)]
+pub const LATEST_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// A migration of the `WorldState` that is scheduled by an admin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMigration {
+    /// The format version to migrate to.
+    pub version: u32,
+    /// The number of the first block applied to the migrated `WorldState`.
+    pub activation_height: BlockNumber,
+}
+
+/// Migrate the `world_state` from its current version to `version`.
+pub(super) fn migrate(world_state: &mut WorldState, version: u32) -> Result<(), BoxError> {
+    if version > LATEST_VERSION {
+        return Err(format!(
+            "The WorldState version {} is not supported (the latest version is {}).",
+            version, LATEST_VERSION
+        )
+        .into());
+    }
+
+    while world_state.version < version {
+        let migration = &MIGRATIONS[world_state.version as usize];
+        log::info!(
+            "Migrating the WorldState to version {} at block #{}: {}",
+            world_state.version + 1,
+            world_state.block_number,
+            migration.description
+        );
+        (migration.migrate)(world_state);
+        world_state.version += 1;
+    }
+    Ok(())
+}
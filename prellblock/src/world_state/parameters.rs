@@ -0,0 +1,60 @@
+//! Parameters of the blockchain that are governed on-chain.
+
+use super::WorldState;
+use crate::consensus::BlockNumber;
+use prellblock_client_api::parameter::Parameter;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, time::Duration};
+
+/// A change of a `Parameter` that is not yet active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledParameter {
+    /// The new value.
+    pub value: u64,
+    /// The number of the first block the value applies to.
+    pub activation_height: BlockNumber,
+}
+
+impl WorldState {
+    /// Get the value of a governed `parameter` for the next block.
+    #[must_use]
+    pub fn parameter(&self, parameter: Parameter) -> u64 {
+        self.parameters
+            .get(&parameter)
+            .copied()
+            .unwrap_or_else(|| parameter.default_value())
+    }
+
+    /// Get the value of a governed `parameter` that is a number of items.
+    #[must_use]
+    pub fn parameter_count(&self, parameter: Parameter) -> usize {
+        usize::try_from(self.parameter(parameter)).unwrap_or(usize::max_value())
+    }
+
+    /// Get the value of a governed `parameter` that is a duration.
+    #[must_use]
+    pub fn parameter_duration(&self, parameter: Parameter) -> Duration {
+        Duration::from_millis(self.parameter(parameter))
+    }
+
+    /// Activate the scheduled parameters that apply to the next block.
+    pub(super) fn activate_parameters(&mut self) {
+        let block_number = self.block_number;
+        let activated: Vec<_> = self
+            .scheduled_parameters
+            .iter()
+            .filter(|(_, scheduled)| scheduled.activation_height <= block_number)
+            .map(|(parameter, scheduled)| (*parameter, scheduled.value))
+            .collect();
+        for (parameter, value) in activated {
+            log::info!(
+                "Parameter {} is set to {} from block #{} on.",
+                parameter,
+                value,
+                block_number
+            );
+            self.scheduled_parameters.remove(&parameter);
+            self.parameters.insert(parameter, value);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Weights of the accounts when the leader fills a block from the queued transactions.
+
+use super::WorldState;
+use pinxit::PeerId;
+use prellblock_client_api::DEFAULT_QUEUE_WEIGHT;
+
+impl WorldState {
+    /// Get the queue weight of the account `peer_id`.
+    ///
+    /// The leader takes up to this number of transactions of the account per round
+    /// when filling a block (see `Queue::drain_fair`).
+    #[must_use]
+    pub fn queue_weight(&self, peer_id: &PeerId) -> u32 {
+        self.queue_weights
+            .get(peer_id)
+            .copied()
+            .unwrap_or(DEFAULT_QUEUE_WEIGHT)
+    }
+}
@@ -0,0 +1,106 @@
+//! Write throughput quotas of key namespaces (see `prellblock_client_api::quota`).
+
+use super::WorldState;
+use err_derive::Error;
+use prellblock_client_api::{
+    quota::{Quota, QuotaUsage},
+    Transaction,
+};
+use std::{collections::BTreeMap, convert::TryFrom, time::SystemTime};
+
+/// A value cannot be written because the quota of its namespace is exhausted in the current window.
+#[derive(Debug, Error)]
+#[error(display = "The write quota of namespace {:?} is exhausted.", 0)]
+pub struct QuotaExceeded(pub String);
+
+/// The usage of the quotas after a checked transaction (see `WorldState::check_quota`).
+#[derive(Debug, Default)]
+#[must_use]
+pub struct QuotaCharge(Vec<(String, QuotaUsage)>);
+
+impl WorldState {
+    /// Get the namespace and the `Quota` of a `key` (the quota of the longest matching namespace).
+    #[must_use]
+    pub fn quota(&self, key: &str) -> Option<(&str, &Quota)> {
+        self.quotas
+            .iter()
+            .filter(|(namespace, _)| key.starts_with(namespace.as_str()))
+            .max_by_key(|(namespace, _)| namespace.len())
+            .map(|(namespace, quota)| (namespace.as_str(), quota))
+    }
+
+    /// Count the values written by the `transaction` in a block at `timestamp` against the quotas of their namespaces.
+    ///
+    /// Transactions not writing a value, and values without a quota, are not counted.
+    /// A `MultiKeyValue` counts once per namespace (with the size of its values in the namespace).
+    /// Nothing is counted if any quota is exhausted.
+    pub fn use_quota(
+        &mut self,
+        transaction: &Transaction,
+        timestamp: SystemTime,
+    ) -> Result<(), QuotaExceeded> {
+        let charge = self.check_quota(transaction, timestamp)?;
+        self.charge_quota(charge);
+        Ok(())
+    }
+
+    /// Check the quotas of the values written by the `transaction` in a block at `timestamp` without counting them.
+    ///
+    /// The returned `QuotaCharge` is counted with `charge_quota` (e.g. after the transaction was applied).
+    pub fn check_quota(
+        &self,
+        transaction: &Transaction,
+        timestamp: SystemTime,
+    ) -> Result<QuotaCharge, QuotaExceeded> {
+        let writes = match transaction {
+            Transaction::KeyValue(params) => vec![(&params.key, transaction.payload_size())],
+            Transaction::KeyValueWithMetadata(params) => {
+                vec![(&params.key, transaction.payload_size())]
+            }
+            Transaction::EncryptedKeyValue(params) => {
+                vec![(&params.key, transaction.payload_size())]
+            }
+            Transaction::CompareAndSwap(params) => {
+                vec![(&params.key, transaction.payload_size())]
+            }
+            Transaction::MultiKeyValue(params) => params
+                .values
+                .iter()
+                .map(|(key, value)| (key, key.len() + value.len()))
+                .collect(),
+            _ => return Ok(QuotaCharge::default()),
+        };
+
+        let mut sizes = BTreeMap::new();
+        for (key, size) in writes {
+            if let Some((namespace, quota)) = self.quota(key) {
+                let size = u64::try_from(size).unwrap_or(u64::max_value());
+                let (_, total) = sizes
+                    .entry(namespace.to_string())
+                    .or_insert((*quota, 0_u64));
+                *total = total.saturating_add(size);
+            }
+        }
+
+        let mut usages = Vec::with_capacity(sizes.len());
+        for (namespace, (quota, size)) in sizes {
+            let mut usage = self
+                .quota_usage
+                .get(&namespace)
+                .copied()
+                .unwrap_or_default();
+            if !usage.try_add(&quota, timestamp, size) {
+                return Err(QuotaExceeded(namespace));
+            }
+            usages.push((namespace, usage));
+        }
+        Ok(QuotaCharge(usages))
+    }
+
+    /// Count a `QuotaCharge` returned by `check_quota`.
+    pub fn charge_quota(&mut self, charge: QuotaCharge) {
+        for (namespace, usage) in charge.0 {
+            self.quota_usage.insert(namespace, usage);
+        }
+    }
+}
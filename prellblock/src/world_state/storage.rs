@@ -0,0 +1,468 @@
+//! Incremental persistence of the `WorldState`.
+//!
+//! The storage consists of a snapshot of the `WorldState` and a log of deltas
+//! (one per block) that are applied on top of the snapshot.
+//! The deltas are periodically compacted into the snapshot.
+
+use super::{keys, Account, EscrowedKey, ScheduledMigration, ScheduledParameter, WorldState};
+use crate::{
+    consensus::{BlockHash, BlockNumber, LeaderTerm},
+    storage::StorageConfig,
+    BoxError,
+};
+use im::{HashMap, OrdSet, Vector};
+use pinxit::PeerId;
+use prellblock_client_api::{
+    encryption::KeyReference,
+    parameter::Parameter,
+    quota::{Quota, QuotaUsage},
+    schema::Schema,
+};
+use serde::{Deserialize, Serialize};
+use sled::{Batch, Db, Tree};
+use std::{net::SocketAddr, sync::Arc};
+
+const SNAPSHOT_TREE_NAME: &[u8] = b"snapshot";
+const DELTAS_TREE_NAME: &[u8] = b"deltas";
+const SNAPSHOT_KEY: &[u8] = b"world_state";
+const SNAPSHOT_STATE_ROOT_KEY: &[u8] = b"state_root";
+
+/// The configuration of the `WorldState` persistence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistenceConfig {
+    /// The path to the directory for the persisted `WorldState`.
+    pub path: String,
+    /// The number of blocks after which the deltas are compacted into the snapshot.
+    #[serde(default = "default_compaction_interval")]
+    pub compaction_interval: u64,
+    /// When the persisted data is flushed to disk.
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+}
+
+const fn default_compaction_interval() -> u64 {
+    1000
+}
+
+/// When the persisted `WorldState` is flushed to disk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// Flush after every block.
+    Always,
+    /// Flush periodically in the background.
+    Periodic {
+        /// The time between two flushes in milliseconds.
+        interval_ms: u64,
+    },
+    /// Leave flushing to the operating system.
+    Never,
+}
+
+impl Default for FsyncPolicy {
+    fn default() -> Self {
+        Self::Periodic { interval_ms: 400 }
+    }
+}
+
+/// The changes of the `WorldState` caused by a single block.
+#[derive(Debug, Serialize, Deserialize)]
+struct Delta {
+    /// The block number of the `WorldState` the delta applies to.
+    from_block_number: BlockNumber,
+    /// The block number after applying the delta.
+    block_number: BlockNumber,
+    /// The last block hash after applying the delta.
+    last_block_hash: BlockHash,
+    /// Accounts that were created or changed.
+    changed_accounts: Vec<(PeerId, Arc<Account>)>,
+    /// Accounts that were deleted.
+    deleted_accounts: Vec<PeerId>,
+    /// The new list of peers (if it changed).
+    peers: Option<Vector<(PeerId, SocketAddr)>>,
+    /// The new schemas (if they changed).
+    schemas: Option<HashMap<String, Schema>>,
+    /// The format version after applying the delta.
+    version: u32,
+    /// The scheduled migration after applying the delta.
+    scheduled_migration: Option<ScheduledMigration>,
+    /// The new parameters and scheduled parameters (if they changed).
+    parameters: Option<(
+        HashMap<Parameter, u64>,
+        HashMap<Parameter, ScheduledParameter>,
+    )>,
+    /// The new leader weights (if they changed).
+    leader_weights: Option<HashMap<PeerId, u32>>,
+    /// The new escrowed keys (if they changed).
+    escrowed_keys: Option<HashMap<KeyReference, EscrowedKey>>,
+    /// The leader term of the last block after applying the delta.
+    last_leader_term: LeaderTerm,
+    /// The new queue weights (if they changed).
+    queue_weights: Option<HashMap<PeerId, u32>>,
+    /// The keys written by each account for the first time.
+    added_keys: Vec<(PeerId, Vec<String>)>,
+    /// The new quotas (if they changed).
+    quotas: Option<HashMap<String, Quota>>,
+    /// The new usage of the quotas (if it changed).
+    quota_usage: Option<HashMap<String, QuotaUsage>>,
+    /// The shared values that were set (`Some`) or removed (`None`).
+    changed_shared_values: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl Delta {
+    fn between(prev: &WorldState, current: &WorldState) -> Self {
+        let changed_accounts = current
+            .accounts
+            .iter()
+            .filter(|(peer_id, account)| match prev.accounts.get(peer_id) {
+                Some(prev_account) => !Arc::ptr_eq(prev_account, account),
+                None => true,
+            })
+            .map(|(peer_id, account)| (peer_id.clone(), account.clone()))
+            .collect();
+
+        let deleted_accounts = prev
+            .accounts
+            .keys()
+            .filter(|peer_id| !current.accounts.contains_key(peer_id))
+            .cloned()
+            .collect();
+
+        let peers = if prev.peers == current.peers {
+            None
+        } else {
+            Some(current.peers.clone())
+        };
+
+        let schemas = if prev.schemas == current.schemas {
+            None
+        } else {
+            Some(current.schemas.clone())
+        };
+
+        let parameters = if prev.parameters == current.parameters
+            && prev.scheduled_parameters == current.scheduled_parameters
+        {
+            None
+        } else {
+            Some((
+                current.parameters.clone(),
+                current.scheduled_parameters.clone(),
+            ))
+        };
+
+        let leader_weights = if prev.leader_weights == current.leader_weights {
+            None
+        } else {
+            Some(current.leader_weights.clone())
+        };
+
+        let escrowed_keys = if prev.escrowed_keys == current.escrowed_keys {
+            None
+        } else {
+            Some(current.escrowed_keys.clone())
+        };
+
+        let queue_weights = if prev.queue_weights == current.queue_weights {
+            None
+        } else {
+            Some(current.queue_weights.clone())
+        };
+
+        let added_keys = current
+            .keys
+            .iter()
+            .map(|(peer_id, keys)| {
+                (
+                    peer_id.clone(),
+                    keys::added_keys(prev.keys.get(peer_id), keys),
+                )
+            })
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect();
+
+        let quotas = if prev.quotas == current.quotas {
+            None
+        } else {
+            Some(current.quotas.clone())
+        };
+
+        let quota_usage = if prev.quota_usage == current.quota_usage {
+            None
+        } else {
+            Some(current.quota_usage.clone())
+        };
+
+        let changed_shared_values = current
+            .shared_values
+            .iter()
+            .filter(|(key, value)| prev.shared_values.get(key) != Some(value))
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .chain(
+                prev.shared_values
+                    .keys()
+                    .filter(|key| !current.shared_values.contains_key(key))
+                    .map(|key| (key.clone(), None)),
+            )
+            .collect();
+
+        Self {
+            from_block_number: prev.block_number,
+            block_number: current.block_number,
+            last_block_hash: current.last_block_hash,
+            changed_accounts,
+            deleted_accounts,
+            peers,
+            schemas,
+            version: current.version,
+            scheduled_migration: current.scheduled_migration,
+            parameters,
+            leader_weights,
+            escrowed_keys,
+            last_leader_term: current.last_leader_term,
+            queue_weights,
+            added_keys,
+            quotas,
+            quota_usage,
+            changed_shared_values,
+        }
+    }
+
+    fn apply_to(self, world_state: &mut WorldState) {
+        for (peer_id, account) in self.changed_accounts {
+            world_state.accounts.insert(peer_id, account);
+        }
+        for peer_id in self.deleted_accounts {
+            world_state.accounts.remove(&peer_id);
+        }
+        if let Some(peers) = self.peers {
+            world_state.peers = peers;
+        }
+        if let Some(schemas) = self.schemas {
+            world_state.schemas = schemas;
+        }
+        world_state.block_number = self.block_number;
+        world_state.last_block_hash = self.last_block_hash;
+        world_state.version = self.version;
+        world_state.scheduled_migration = self.scheduled_migration;
+        if let Some((parameters, scheduled_parameters)) = self.parameters {
+            world_state.parameters = parameters;
+            world_state.scheduled_parameters = scheduled_parameters;
+        }
+        if let Some(leader_weights) = self.leader_weights {
+            world_state.leader_weights = leader_weights;
+        }
+        if let Some(escrowed_keys) = self.escrowed_keys {
+            world_state.escrowed_keys = escrowed_keys;
+        }
+        world_state.last_leader_term = self.last_leader_term;
+        if let Some(queue_weights) = self.queue_weights {
+            world_state.queue_weights = queue_weights;
+        }
+        for (peer_id, keys) in self.added_keys {
+            world_state
+                .keys
+                .entry(peer_id)
+                .or_insert_with(OrdSet::new)
+                .extend(keys);
+        }
+        if let Some(quotas) = self.quotas {
+            world_state.quotas = quotas;
+        }
+        if let Some(quota_usage) = self.quota_usage {
+            world_state.quota_usage = quota_usage;
+        }
+        for (key, value) in self.changed_shared_values {
+            match value {
+                Some(value) => world_state.shared_values.insert(key, value),
+                None => world_state.shared_values.remove(&key),
+            };
+        }
+    }
+}
+
+/// Persists the `WorldState` in a sled database.
+///
+/// The block number and last block hash of the snapshot and each delta
+/// tie the persisted state to a block of the `BlockStorage`.
+#[derive(Debug)]
+pub struct Storage {
+    database: Db,
+    snapshot: Tree,
+    deltas: Tree,
+    config: PersistenceConfig,
+    /// The block number of the stored snapshot (if any).
+    snapshot_block_number: Option<BlockNumber>,
+}
+
+impl Storage {
+    /// Open the storage configured by `config` and tuned by `storage_config`.
+    pub fn new(
+        config: PersistenceConfig,
+        storage_config: &StorageConfig,
+    ) -> Result<Self, BoxError> {
+        let flush_every_ms = match config.fsync {
+            FsyncPolicy::Periodic { interval_ms } => Some(interval_ms),
+            FsyncPolicy::Always | FsyncPolicy::Never => None,
+        };
+        let database = storage_config
+            .sled_config(&config.path)
+            .flush_every_ms(flush_every_ms)
+            .open()?;
+        let snapshot = database.open_tree(SNAPSHOT_TREE_NAME)?;
+        let deltas = database.open_tree(DELTAS_TREE_NAME)?;
+
+        // A snapshot in an outdated format is replaced when the `WorldState` is rebuilt.
+        let snapshot_block_number = match snapshot.get(SNAPSHOT_KEY)? {
+            Some(data) => match postcard::from_bytes::<WorldState>(&data) {
+                Ok(world_state) => Some(world_state.block_number),
+                Err(err) => {
+                    log::warn!("Could not decode persisted WorldState snapshot: {}", err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            database,
+            snapshot,
+            deltas,
+            config,
+            snapshot_block_number,
+        })
+    }
+
+    /// Load the persisted `WorldState` and its previous `WorldState` (if known).
+    ///
+    /// Returns `None` if nothing was persisted.
+    pub fn load(&self) -> Result<Option<(WorldState, Option<WorldState>)>, BoxError> {
+        let mut current = match self.load_snapshot()? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+        let mut prev = None;
+
+        for entry in self.deltas.range(current.block_number.to_be_bytes()..) {
+            let (_, data) = entry?;
+            let delta: Delta = postcard::from_bytes(&data)?;
+            if delta.from_block_number != current.block_number {
+                return Err(format!(
+                    "WorldState delta for block #{} does not apply to block #{}.",
+                    delta.from_block_number, current.block_number
+                )
+                .into());
+            }
+            prev = Some(current.clone());
+            delta.apply_to(&mut current);
+        }
+
+        Ok(Some((current, prev)))
+    }
+
+    /// Load the persisted snapshot (without the deltas) and verify its state root.
+    ///
+    /// Returns `None` if nothing was persisted.
+    pub fn load_snapshot(&self) -> Result<Option<WorldState>, BoxError> {
+        let data = match self.snapshot.get(SNAPSHOT_KEY)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let snapshot: WorldState = postcard::from_bytes(&data)?;
+        // Snapshots written before state roots were recorded are trusted.
+        if let Some(state_root) = self.snapshot.get(SNAPSHOT_STATE_ROOT_KEY)? {
+            let state_root: BlockHash = postcard::from_bytes(&state_root)?;
+            if snapshot.state_root()? != state_root {
+                return Err(format!(
+                    "The state root of the persisted WorldState snapshot at block #{} does not match.",
+                    snapshot.block_number
+                )
+                .into());
+            }
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Persist the change from `prev` to `current`.
+    pub fn persist(&mut self, prev: &WorldState, current: &WorldState) -> Result<(), BoxError> {
+        if prev.block_number == current.block_number {
+            // The state changed without a block (e.g. while setting up the genesis state).
+            self.write_snapshot(current)?;
+        } else {
+            if self.snapshot_block_number.is_none() {
+                // There is no base for the delta yet.
+                self.write_snapshot(prev)?;
+            }
+
+            let delta = Delta::between(prev, current);
+            self.deltas.insert(
+                prev.block_number.to_be_bytes(),
+                postcard::to_stdvec(&delta)?,
+            )?;
+
+            // Keep the snapshot one block behind, so the previous state can be restored.
+            if let Some(snapshot_block_number) = self.snapshot_block_number {
+                if snapshot_block_number + self.config.compaction_interval < prev.block_number {
+                    self.compact(prev)?;
+                }
+            }
+        }
+
+        if let FsyncPolicy::Always = self.config.fsync {
+            self.database.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Remove all persisted data.
+    ///
+    /// This is needed when the `WorldState` is rolled back.
+    pub fn clear(&mut self) -> Result<(), BoxError> {
+        self.snapshot.clear()?;
+        self.deltas.clear()?;
+        self.snapshot_block_number = None;
+        self.database.flush()?;
+        Ok(())
+    }
+
+    /// Replace all persisted data by `world_state`.
+    pub fn reset(&mut self, world_state: &WorldState) -> Result<(), BoxError> {
+        self.clear()?;
+        self.write_snapshot(world_state)?;
+        self.database.flush()?;
+        Ok(())
+    }
+
+    /// Compact all deltas before `snapshot` into the snapshot.
+    fn compact(&mut self, snapshot: &WorldState) -> Result<(), BoxError> {
+        log::debug!(
+            "Compacting WorldState deltas up to block #{}.",
+            snapshot.block_number
+        );
+        // The snapshot is written first: Deltas before the snapshot are ignored while loading.
+        self.write_snapshot(snapshot)?;
+
+        let mut batch = Batch::default();
+        for key in self
+            .deltas
+            .range(..snapshot.block_number.to_be_bytes())
+            .keys()
+        {
+            batch.remove(key?);
+        }
+        self.deltas.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn write_snapshot(&mut self, world_state: &WorldState) -> Result<(), BoxError> {
+        let mut batch = Batch::default();
+        batch.insert(SNAPSHOT_KEY, postcard::to_stdvec(world_state)?);
+        batch.insert(
+            SNAPSHOT_STATE_ROOT_KEY,
+            postcard::to_stdvec(&world_state.state_root()?)?,
+        );
+        self.snapshot.apply_batch(batch)?;
+        self.snapshot_block_number = Some(world_state.block_number);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::TestDir;
+use pinxit::Identity;
+use prellblock::access_log::{AccessLog, AccessLogConfig, AccessLogEntry};
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+fn entry(error: Option<&str>) -> AccessLogEntry {
+    AccessLogEntry::new(
+        SystemTime::now(),
+        "127.0.0.1:3130".parse().unwrap(),
+        "Execute",
+        42,
+        error.map(String::from),
+        Duration::from_micros(1500),
+    )
+    .with_claimed_signer(Identity::generate().id().clone(), error.is_none())
+}
+
+#[test]
+fn test_entries_are_written_as_lines() {
+    let dir = TestDir::new("access-log-lines");
+    let path = dir.path("access.log");
+    let access_log = AccessLog::open(AccessLogConfig {
+        path: path.clone(),
+        max_size_bytes: 1024 * 1024,
+        max_files: 2,
+        json: false,
+    })
+    .unwrap();
+
+    access_log.record(&entry(None));
+    access_log.record(&entry(Some("Account not found.")));
+
+    let log = fs::read_to_string(&path).unwrap();
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0]
+        .contains("verified=true request=Execute request_size=42 latency_us=1500 outcome=ok"));
+    assert!(lines[1].contains("verified=false"));
+    assert!(lines[1].ends_with("outcome=error error=\"Account not found.\""));
+}
+
+#[test]
+fn test_json_entries_are_parseable() {
+    let dir = TestDir::new("access-log-json");
+    let path = dir.path("access.log");
+    let access_log = AccessLog::open(AccessLogConfig {
+        path: path.clone(),
+        max_size_bytes: 1024 * 1024,
+        max_files: 2,
+        json: true,
+    })
+    .unwrap();
+
+    access_log.record(&entry(None));
+
+    let log = fs::read_to_string(&path).unwrap();
+    let value: serde_json::Value = serde_json::from_str(log.trim_end()).unwrap();
+    assert_eq!(value["request"], "Execute");
+    assert_eq!(value["error"], serde_json::Value::Null);
+    assert_eq!(value["verified"], true);
+}
+
+#[test]
+fn test_log_is_rotated() {
+    let dir = TestDir::new("access-log-rotation");
+    let path = dir.path("access.log");
+    let line_len = entry(None).to_string().len() as u64 + 1;
+    let access_log = AccessLog::open(AccessLogConfig {
+        path: path.clone(),
+        // Two entries fit into a file.
+        max_size_bytes: 2 * line_len,
+        max_files: 2,
+        json: false,
+    })
+    .unwrap();
+
+    for _ in 0..7 {
+        access_log.record(&entry(None));
+    }
+
+    let count_lines = |path: &str| fs::read_to_string(path).unwrap().lines().count();
+    assert_eq!(count_lines(&path), 1);
+    assert_eq!(count_lines(&format!("{}.1", path)), 2);
+    assert_eq!(count_lines(&format!("{}.2", path)), 2);
+    // Older files are deleted.
+    assert!(fs::metadata(format!("{}.3", path)).is_err());
+}
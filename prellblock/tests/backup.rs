@@ -0,0 +1,122 @@
+mod common;
+
+use common::TestDir;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable};
+use prellblock::{
+    backup,
+    block_storage::BlockStorage,
+    world_state::{FsyncPolicy, PersistenceConfig, WorldStateService},
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    consensus::{Block, GenesisTransactions, HashAlgorithm, SignatureList},
+    transaction, Transaction,
+};
+use std::{fs, time::SystemTime};
+
+/// Write the configuration of an RPU storing its databases in the directory `dir`.
+fn write_config(dir: &str) -> String {
+    fs::create_dir_all(dir).unwrap();
+    let config_path = format!("{}/rpu.toml", dir);
+    let config = format!(
+        r#"identity = "rpu.key"
+tls_id = "rpu.pfx"
+block_path = "{dir}/blocks"
+data_path = "{dir}/data"
+
+[world_state]
+path = "{dir}/world_state"
+"#,
+        dir = dir
+    );
+    fs::write(&config_path, config).unwrap();
+    config_path
+}
+
+fn genesis_transactions() -> GenesisTransactions {
+    let admin = Identity::generate();
+    let create_admin = Transaction::from_variant(transaction::CreateAccount {
+        id: admin.id().clone(),
+        name: "admin".to_string(),
+        permissions: Permissions {
+            account_type: Some(AccountType::Admin),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(false),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    });
+    GenesisTransactions {
+        transactions: vec![create_admin.sign(&admin).unwrap()],
+        timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    }
+}
+
+/// Create the databases of an RPU and return its blocks.
+fn create_rpu(dir: &str) -> Vec<Block> {
+    let block_storage =
+        BlockStorage::new(&format!("{}/blocks", dir), Some(genesis_transactions())).unwrap();
+    let persistence_config = PersistenceConfig {
+        path: format!("{}/world_state", dir),
+        compaction_interval: 1,
+        fsync: FsyncPolicy::default(),
+    };
+    WorldStateService::with_persistence(&block_storage, persistence_config).unwrap();
+    block_storage.read(..).collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn test_backup_can_be_restored() {
+    let dir = TestDir::new("backup");
+    let source = dir.path("source");
+    let config = write_config(&source);
+    let blocks = create_rpu(&source);
+
+    let archive = dir.path("archive.prellbak");
+    let summaries = backup::backup(&config, &archive).unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].name, "main");
+    assert_eq!(summaries[0].block_number, blocks[0].block_number() + 1);
+
+    // A backup never overwrites an existing file.
+    assert!(backup::backup(&config, &archive).is_err());
+    assert_eq!(backup::verify(&archive).unwrap().len(), 1);
+
+    let target = dir.path("target");
+    let target_config = write_config(&target);
+    backup::restore(&archive, &target_config).unwrap();
+
+    let block_storage = BlockStorage::new(&format!("{}/blocks", target), None).unwrap();
+    let restored: Vec<Block> = block_storage.read(..).collect::<Result<_, _>>().unwrap();
+    assert_eq!(restored.len(), blocks.len());
+    assert_eq!(restored[0].hash(), blocks[0].hash());
+    drop(block_storage);
+
+    // Existing databases are never overwritten.
+    assert!(backup::restore(&archive, &target_config).is_err());
+}
+
+#[test]
+fn test_corrupted_backup_is_rejected() {
+    let dir = TestDir::new("backup-corrupted");
+    let source = dir.path("source");
+    let config = write_config(&source);
+    create_rpu(&source);
+
+    let archive = dir.path("archive.prellbak");
+    backup::backup(&config, &archive).unwrap();
+
+    let mut data = fs::read(&archive).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xff;
+    fs::write(&archive, data).unwrap();
+
+    assert!(backup::verify(&archive).is_err());
+    let target = dir.path("target");
+    let target_config = write_config(&target);
+    assert!(backup::restore(&archive, &target_config).is_err());
+    assert!(!std::path::Path::new(&format!("{}/blocks", target)).exists());
+}
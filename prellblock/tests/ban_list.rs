@@ -0,0 +1,63 @@
+mod common;
+
+use common::TestDir;
+use pinxit::Identity;
+use prellblock::ban_list::{BanList, MAX_STRIKES};
+use std::{thread, time::Duration};
+
+#[test]
+fn test_peers_are_banned_after_too_many_strikes() {
+    let dir = TestDir::new("ban-list-strikes");
+    let ban_list = BanList::new(&dir.path("ban_list")).unwrap();
+    let peer = Identity::generate().id().clone();
+    let other = Identity::generate().id().clone();
+
+    for _ in 1..MAX_STRIKES {
+        ban_list.strike(&peer, "invalid signature");
+    }
+    ban_list.strike(&other, "invalid signature");
+    assert!(!ban_list.is_banned(&peer));
+
+    ban_list.strike(&peer, "invalid signature");
+    assert!(ban_list.is_banned(&peer));
+    assert!(!ban_list.is_banned(&other));
+    assert_eq!(ban_list.banned_peers(), vec![peer]);
+}
+
+#[test]
+fn test_strikes_expire() {
+    let dir = TestDir::new("ban-list-expiry");
+    let ban_list = BanList::new(&dir.path("ban_list"))
+        .unwrap()
+        .with_strike_expiry(Duration::from_millis(100));
+    let peer = Identity::generate().id().clone();
+
+    for _ in 1..MAX_STRIKES {
+        ban_list.strike(&peer, "invalid signature");
+    }
+    thread::sleep(Duration::from_millis(200));
+
+    // The earlier strikes are forgotten, so a single one does not ban the peer.
+    ban_list.strike(&peer, "invalid signature");
+    assert!(!ban_list.is_banned(&peer));
+}
+
+#[test]
+fn test_bans_are_persisted() {
+    let dir = TestDir::new("ban-list-persisted");
+    let path = dir.path("ban_list");
+    let peer = Identity::generate().id().clone();
+
+    {
+        let ban_list = BanList::new(&path).unwrap();
+        ban_list.ban(&peer).unwrap();
+    }
+    let ban_list = BanList::new(&path).unwrap();
+    assert!(ban_list.is_banned(&peer));
+
+    assert!(ban_list.unban(&peer).unwrap());
+    assert!(!ban_list.unban(&peer).unwrap());
+    drop(ban_list);
+    let ban_list = BanList::new(&path).unwrap();
+    assert!(!ban_list.is_banned(&peer));
+}
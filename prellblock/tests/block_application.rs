@@ -0,0 +1,148 @@
+mod common;
+
+use common::{create_account, create_writer, TestDir};
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed, Signer};
+use prellblock::{
+    ban_list::BanList,
+    block_storage::BlockStorage,
+    consensus::{Consensus, InMemoryNetwork, SystemClock},
+    world_state::WorldStateService,
+};
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{transaction_hash, BlockNumber, GenesisTransactions, HashAlgorithm, SignatureList},
+    transaction, TraceEventKind, Transaction,
+};
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::time;
+
+fn peer_address(index: u16) -> SocketAddr {
+    ([127, 0, 0, 1], 5331 + index).into()
+}
+
+fn key_value(writer: &Identity, value: &str) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::KeyValue {
+        key: "temperature".to_string(),
+        value: value.as_bytes().to_vec(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+async fn start_rpu(
+    dir: &TestDir,
+    index: u16,
+    identity: Identity,
+    genesis: &[Signed<Transaction>],
+    network: Arc<InMemoryNetwork>,
+) -> (Arc<Consensus>, WorldStateService) {
+    let genesis = GenesisTransactions {
+        transactions: genesis.to_vec(),
+        timestamp: SystemTime::UNIX_EPOCH,
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    };
+    let block_storage =
+        BlockStorage::new(&dir.path(&format!("{}-blocks", index)), Some(genesis)).unwrap();
+    let world_state = WorldStateService::from_block_storage(&block_storage).unwrap();
+    let ban_list = Arc::new(BanList::new(&dir.path(&format!("{}-ban-list", index))).unwrap());
+
+    let identity: Arc<dyn Signer> = Arc::new(identity);
+    let rpu = Consensus::with_network(
+        identity,
+        block_storage,
+        world_state.clone(),
+        ban_list,
+        None,
+        network.clone(),
+        Arc::new(SystemClock),
+    )
+    .await;
+    network.connect(peer_address(index), &rpu);
+    (rpu, world_state)
+}
+
+/// Wait until the block with the `block_number` is applied to the `world_state`.
+async fn wait_applied(world_state: &WorldStateService, block_number: BlockNumber) {
+    time::timeout(Duration::from_secs(10), async {
+        while world_state.get().block_number <= block_number {
+            time::delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_votes_proceed_while_a_block_is_applied() {
+    let dir = TestDir::new("block-application");
+    let network = Arc::new(InMemoryNetwork::default());
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let identities: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+
+    let mut genesis = vec![
+        create_account(&admin, admin.id(), AccountType::Admin),
+        create_writer(&admin, writer.id(), AccountType::Normal),
+    ];
+    for (index, identity) in identities.iter().enumerate() {
+        let peer_address = peer_address(index as u16);
+        let rpu_type = AccountType::RPU {
+            turi_address: peer_address,
+            peer_address,
+        };
+        genesis.push(create_account(&admin, identity.id(), rpu_type));
+    }
+
+    // The first RPU leads the first leader term.
+    let mut rpus = Vec::new();
+    for (index, identity) in identities.into_iter().enumerate() {
+        let rpu = start_rpu(&dir, index as u16, identity, &genesis, network.clone()).await;
+        rpus.push(rpu);
+    }
+    let (leader, leader_world_state) = &rpus[0];
+    let (follower, _) = &rpus[1];
+
+    // Two followers cannot apply blocks (their `WorldState` is locked).
+    let mut blocked = Vec::new();
+    for (_, world_state) in &rpus[2..] {
+        blocked.push(world_state.get_writable().await);
+    }
+
+    let first_block = leader_world_state.get().block_number;
+    leader
+        .take_transactions(vec![key_value(&writer, "first")])
+        .await;
+    wait_applied(leader_world_state, first_block).await;
+
+    // The blocked followers still vote on the next block (the leader needs their `AckPrepare`),
+    // so it reaches the append phase.
+    let transaction = key_value(&writer, "second");
+    let hash = transaction_hash(&transaction);
+    leader.take_transactions(vec![transaction]).await;
+    time::timeout(Duration::from_secs(10), async {
+        loop {
+            let trace = follower.transaction_trace(&hash);
+            let proposed = trace
+                .events
+                .iter()
+                .any(|event| matches!(event.kind, TraceEventKind::Proposed { .. }));
+            if proposed {
+                return;
+            }
+            time::delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    // The block is committed as soon as the blocked followers applied the previous block.
+    drop(blocked);
+    wait_applied(leader_world_state, first_block + 1).await;
+}
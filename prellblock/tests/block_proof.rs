@@ -0,0 +1,147 @@
+use pinxit::{Identity, Signable};
+use prellblock::{
+    consensus::{block_proof, verify_block_signatures},
+    world_state::WorldState,
+};
+use prellblock_client_api::consensus::{
+    Block, BlockHash, BlockNumber, BlockProof, Body, CertifiedHead, LeaderTerm, SignatureList,
+};
+use std::{convert::Infallible, time::SystemTime};
+
+struct RawData(Vec<u8>);
+
+impl Signable for RawData {
+    type SignableData = Vec<u8>;
+    type Error = Infallible;
+    fn signable_data(&self) -> Result<Self::SignableData, Self::Error> {
+        Ok(self.0.clone())
+    }
+}
+
+#[test]
+fn test_block_proof_matches_consensus_signatures() {
+    let identities: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+    let mut world_state = WorldState::default();
+    for identity in &identities {
+        let address = "127.0.0.1:2480".parse().unwrap();
+        world_state
+            .peers
+            .push_back((identity.id().clone(), address));
+    }
+
+    let mut block = Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: BlockNumber::default(),
+            prev_block_hash: BlockHash::of(b"genesis"),
+            timestamp: SystemTime::now(),
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    };
+
+    // Sign the block like an external signer using only the documented encoding.
+    let data = RawData(BlockProof::ackappend_data(
+        block.body.leader_term,
+        block.block_number(),
+        block.hash(),
+    ));
+    for identity in &identities {
+        let signature = identity.sign(&data).unwrap();
+        block
+            .signatures
+            .insert(identity.id().clone(), signature)
+            .unwrap();
+    }
+
+    // The consensus accepts these signatures ...
+    verify_block_signatures(&block, &world_state).unwrap();
+
+    // ... and the proof created by an RPU verifies.
+    let proof = block_proof(&block).unwrap();
+    let signers = proof.verify(&block).unwrap();
+    assert_eq!(signers.len(), identities.len());
+
+    // A proof does not verify for another block.
+    let mut other_block = block.clone();
+    other_block.body.prev_block_hash = BlockHash::of(b"other");
+    assert!(proof.verify(&other_block).is_err());
+}
+
+#[test]
+fn test_block_with_decreasing_leader_term_is_rejected() {
+    let identity = Identity::generate();
+    let mut world_state = WorldState::default();
+    world_state
+        .peers
+        .push_back((identity.id().clone(), "127.0.0.1:2480".parse().unwrap()));
+    world_state.last_leader_term = LeaderTerm::default() + 2;
+
+    let mut block = Block {
+        body: Body {
+            leader_term: LeaderTerm::default() + 1,
+            height: BlockNumber::default(),
+            prev_block_hash: world_state.last_block_hash,
+            timestamp: SystemTime::now(),
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    };
+    let data = RawData(BlockProof::ackappend_data(
+        block.body.leader_term,
+        block.block_number(),
+        block.hash(),
+    ));
+    let signature = identity.sign(&data).unwrap();
+    block
+        .signatures
+        .insert(identity.id().clone(), signature)
+        .unwrap();
+
+    // Valid signatures do not allow rewriting the leader term history.
+    assert!(verify_block_signatures(&block, &world_state).is_err());
+    assert!(world_state.clone().apply_block(block.clone()).is_err());
+
+    // Blocks of the same leader term are accepted.
+    world_state.last_leader_term = block.body.leader_term;
+    verify_block_signatures(&block, &world_state).unwrap();
+    world_state.apply_block(block.clone()).unwrap();
+    assert_eq!(world_state.last_leader_term, block.body.leader_term);
+}
+
+#[test]
+fn test_certified_head_verifies_signatures() {
+    let identities: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+    let block_number = BlockNumber::new(41);
+    let block_hash = BlockHash::of(b"head");
+    let certified_at = SystemTime::now();
+
+    // Sign the head like an RPU using only the documented encoding.
+    let signed_data = CertifiedHead::head_ack_data(block_number, block_hash, certified_at);
+    let data = RawData(signed_data.clone());
+    let mut signatures = SignatureList::default();
+    for identity in &identities {
+        let signature = identity.sign(&data).unwrap();
+        signatures.insert(identity.id().clone(), signature).unwrap();
+    }
+
+    let head = CertifiedHead {
+        block_number,
+        block_hash,
+        certified_at,
+        signed_data,
+        signatures,
+    };
+    let signers = head.verify().unwrap();
+    assert_eq!(signers.len(), identities.len());
+
+    // The signatures do not certify another head.
+    let mut other_head = head.clone();
+    other_head.block_number = block_number + 1;
+    assert!(other_head.verify().is_err());
+    let mut other_head = head;
+    other_head.signed_data =
+        CertifiedHead::head_ack_data(block_number + 1, block_hash, certified_at);
+    other_head.block_number = block_number + 1;
+    assert!(other_head.verify().is_err());
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{create_account, rpu_type};
+use pinxit::{Identity, Signer};
+use prellblock::{
+    consensus::{block_proof, verify_block_signatures, Error},
+    world_state::WorldState,
+};
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{Block, Body, SignatureList},
+};
+use std::time::SystemTime;
+
+/// Create a `WorldState` with the `rpus` (as seen by an observer before applying the next block).
+fn world_state(rpus: &[Identity]) -> WorldState {
+    let admin = Identity::generate();
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_account(&admin, admin.id(), AccountType::Admin));
+    for (index, rpu) in rpus.iter().enumerate() {
+        world_state.apply_transaction(create_account(&admin, rpu.id(), rpu_type(index as u16)));
+    }
+    world_state
+}
+
+fn new_block(world_state: &WorldState) -> Block {
+    Block {
+        body: Body {
+            leader_term: world_state.last_leader_term,
+            height: world_state.block_number,
+            prev_block_hash: world_state.last_block_hash,
+            timestamp: SystemTime::now(),
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    }
+}
+
+/// Sign the `block` with the `signers` (as the RPUs do with their `AckAppend` response).
+fn sign(block: &mut Block, signers: &[Identity]) {
+    let signed_data = block_proof(block).unwrap().signed_data;
+    for signer in signers {
+        block
+            .signatures
+            .insert(signer.id().clone(), signer.sign_bytes(&signed_data))
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_blocks_need_a_quorum_of_signatures() {
+    // Four RPUs tolerate one faulty RPU, so three signatures are needed.
+    let rpus: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+    let world_state = world_state(&rpus);
+
+    let mut block = new_block(&world_state);
+    sign(&mut block, &rpus[..2]);
+    let err = verify_block_signatures(&block, &world_state).unwrap_err();
+    assert!(matches!(err, Error::NotEnoughSignatures));
+
+    sign(&mut block, &rpus[2..3]);
+    verify_block_signatures(&block, &world_state).unwrap();
+}
+
+#[test]
+fn test_blocks_signed_by_others_than_rpus_are_rejected() {
+    let rpus: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+    let world_state = world_state(&rpus);
+
+    // A signature of an unknown peer does not count towards the quorum.
+    let mut block = new_block(&world_state);
+    sign(&mut block, &rpus[..2]);
+    sign(&mut block, &[Identity::generate()]);
+    let err = verify_block_signatures(&block, &world_state).unwrap_err();
+    assert!(matches!(err, Error::InvalidPeer(_)));
+
+    // A signature of another block does not count either.
+    let mut other_block = new_block(&world_state);
+    other_block.body.timestamp = SystemTime::UNIX_EPOCH;
+    sign(&mut other_block, &rpus[2..3]);
+    let mut block = new_block(&world_state);
+    sign(&mut block, &rpus[..2]);
+    for (peer_id, signature) in &other_block.signatures {
+        block
+            .signatures
+            .insert(peer_id.clone(), signature.clone())
+            .unwrap();
+    }
+    let err = verify_block_signatures(&block, &world_state).unwrap_err();
+    assert!(matches!(err, Error::InvalidSignature(_)));
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::TestDir;
+use prellblock::block_storage::{BlockStorage, Error};
+use prellblock_client_api::consensus::{
+    Block, BlockNumber, Body, GenesisTransactions, HashAlgorithm, LeaderTerm, SignatureList,
+};
+use std::time::{Duration, SystemTime};
+
+fn block_storage(dir: &TestDir) -> BlockStorage {
+    let genesis = GenesisTransactions {
+        transactions: vec![],
+        timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    };
+    BlockStorage::new(&dir.path("blocks"), Some(genesis)).unwrap()
+}
+
+fn next_block(prev_block: &Block, timestamp: SystemTime) -> Block {
+    Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: prev_block.block_number() + 1,
+            prev_block_hash: prev_block.hash(),
+            timestamp,
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    }
+}
+
+#[test]
+fn test_writing_a_stored_block_again_is_rejected() {
+    let dir = TestDir::new("block-storage-duplicate");
+    let block_storage = block_storage(&dir);
+    let genesis = block_storage
+        .read_block(BlockNumber::default())
+        .unwrap()
+        .unwrap();
+    let block = next_block(&genesis, SystemTime::now());
+    block_storage.write_block(&block).unwrap();
+
+    let err = block_storage.write_block(&block).unwrap_err();
+    assert!(matches!(err, Error::DuplicateBlock(height) if height == block.block_number()));
+    let err = block_storage.write_block(&genesis).unwrap_err();
+    assert!(matches!(err, Error::DuplicateBlock(height) if height == BlockNumber::default()));
+}
+
+#[test]
+fn test_conflicting_blocks_at_a_stored_height_are_detected() {
+    let dir = TestDir::new("block-storage-fork");
+    let block_storage = block_storage(&dir);
+    let genesis = block_storage
+        .read_block(BlockNumber::default())
+        .unwrap()
+        .unwrap();
+    let timestamp = SystemTime::now();
+    let block = next_block(&genesis, timestamp);
+    block_storage.write_block(&block).unwrap();
+
+    // Another block with the same predecessor (e.g. proposed in another leader term).
+    let conflicting = next_block(&genesis, timestamp + Duration::from_secs(1));
+    let err = block_storage.write_block(&conflicting).unwrap_err();
+    assert!(matches!(err, Error::ForkDetected(height) if height == block.block_number()));
+
+    // The stored block is left untouched.
+    let stored = block_storage
+        .read_block(block.block_number())
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.hash(), block.hash());
+    let next = next_block(&block, timestamp);
+    block_storage.write_block(&next).unwrap();
+}
@@ -0,0 +1,64 @@
+use futures::FutureExt;
+use prellblock::consensus::{Clock, MockClock, Queue};
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[tokio::test]
+async fn test_sleeps_end_when_the_clock_is_advanced() {
+    let clock = MockClock::new(UNIX_EPOCH);
+    let mut short = clock.sleep(Duration::from_secs(1));
+    let mut long = clock.sleep(Duration::from_secs(60));
+    assert_eq!(clock.sleepers(), 2);
+    assert!((&mut short).now_or_never().is_none());
+
+    clock.advance(Duration::from_millis(999));
+    assert!((&mut short).now_or_never().is_none());
+
+    clock.advance(Duration::from_millis(1));
+    assert!((&mut short).now_or_never().is_some());
+    assert!((&mut long).now_or_never().is_none());
+    assert_eq!(clock.sleepers(), 1);
+
+    // A long timeout passes without waiting.
+    clock.advance(Duration::from_secs(3600));
+    long.await;
+    assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(3601));
+}
+
+#[tokio::test]
+async fn test_sleeping_tasks_are_woken_up() {
+    let clock = Arc::new(MockClock::new(UNIX_EPOCH));
+    let sleep = clock.sleep(Duration::from_secs(10));
+    let task = tokio::spawn(sleep);
+
+    clock.advance(Duration::from_secs(10));
+    task.await.unwrap();
+    assert_eq!(clock.sleepers(), 0);
+}
+
+#[test]
+fn test_dropped_sleeps_are_not_counted() {
+    let clock = MockClock::new(UNIX_EPOCH);
+    drop(clock.sleep(Duration::from_secs(1)));
+    assert_eq!(clock.sleepers(), 0);
+    // Sleeping for nothing does not wait.
+    assert!(clock.sleep(Duration::default()).now_or_never().is_some());
+}
+
+#[test]
+fn test_queue_waiting_time_follows_the_clock() {
+    let clock = Arc::new(MockClock::new(UNIX_EPOCH));
+    let mut queue = Queue::default().with_clock(clock.clone());
+    queue.insert(1);
+    clock.advance(Duration::from_secs(30));
+    queue.insert(2);
+    clock.advance(Duration::from_secs(5));
+
+    let waited: Vec<_> = queue
+        .iter()
+        .map(|entry| clock.elapsed(entry.inserted()))
+        .collect();
+    assert_eq!(waited, [Duration::from_secs(35), Duration::from_secs(5)]);
+}
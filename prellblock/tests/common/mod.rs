@@ -0,0 +1,133 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate only uses some of the helpers.
+#![allow(dead_code)]
+
+use newtype_enum::Enum;
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{
+    transaction_checker::{PermissionError, TransactionCheck, TransactionChecker},
+    world_state::{WorldState, WorldStateService},
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    transaction, Transaction,
+};
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+/// A temporary directory that is unique to a test and removed when it is dropped.
+///
+/// Declare it before everything that uses its paths (e.g. databases), so it is dropped last.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// Create a new empty directory for the test `name`.
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "prellblock-test-{}-{}-{}",
+            name,
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        // The process id may be reused, so never work with stale data.
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    /// Get the path of the entry `name` in the directory.
+    pub fn path(&self, name: &str) -> String {
+        self.path.join(name).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn create_account_with_rights(
+    admin: &Identity,
+    id: &PeerId,
+    account_type: AccountType,
+    has_writing_rights: bool,
+) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::CreateAccount {
+        id: id.clone(),
+        name: id.to_string(),
+        permissions: Permissions {
+            account_type: Some(account_type),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(has_writing_rights),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+/// Create an account without writing rights, signed by the `admin`.
+pub fn create_account(
+    admin: &Identity,
+    id: &PeerId,
+    account_type: AccountType,
+) -> Signed<Transaction> {
+    create_account_with_rights(admin, id, account_type, false)
+}
+
+/// Create an account with writing rights, signed by the `admin`.
+pub fn create_writer(
+    admin: &Identity,
+    id: &PeerId,
+    account_type: AccountType,
+) -> Signed<Transaction> {
+    create_account_with_rights(admin, id, account_type, true)
+}
+
+/// The account type of the RPU with the `index` (listening on the ports from 2480 on).
+pub fn rpu_type(index: u16) -> AccountType {
+    let port = 2480 + index;
+    AccountType::RPU {
+        turi_address: ([127, 0, 0, 1], port + 1000).into(),
+        peer_address: ([127, 0, 0, 1], port).into(),
+    }
+}
+
+/// Create a `WorldState` with an admin and `count` RPUs.
+pub fn world_state_with_rpus(admin: &Identity, count: u16) -> (WorldState, Vec<PeerId>) {
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_account(admin, admin.id(), AccountType::Admin));
+    let rpus: Vec<PeerId> = (0..count)
+        .map(|_| Identity::generate().id().clone())
+        .collect();
+    for (index, rpu) in rpus.iter().enumerate() {
+        world_state.apply_transaction(create_account(admin, rpu, rpu_type(index as u16)));
+    }
+    (world_state, rpus)
+}
+
+/// Create a `TransactionChecker` on the `world_state`.
+pub async fn checker(world_state: WorldState) -> TransactionChecker {
+    let world_state_service = WorldStateService::new();
+    world_state_service.install_snapshot(world_state).await;
+    TransactionChecker::new(world_state_service)
+}
+
+/// Verify the `transaction` in the `check` and apply it (so later transactions see it).
+pub fn verify(
+    check: &mut TransactionCheck,
+    transaction: Signed<Transaction>,
+) -> Result<(), PermissionError> {
+    check.verify_permissions_and_apply(transaction.verify_ref().unwrap())
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::create_writer;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::world_state::WorldState;
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{Block, Body, LeaderTerm, SignatureList},
+    transaction, ExecutionStatus, Transaction,
+};
+use std::time::SystemTime;
+
+const LOCK: &str = "line3/maintenance";
+
+fn compare_and_swap(
+    writer: &Identity,
+    expected: Option<&[u8]>,
+    new: Option<&[u8]>,
+) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::CompareAndSwap {
+        key: LOCK.to_string(),
+        expected: expected.map(<[u8]>::to_vec),
+        new: new.map(<[u8]>::to_vec),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+fn apply(world_state: &mut WorldState, transactions: Vec<Signed<Transaction>>) -> Vec<bool> {
+    let body = Body {
+        leader_term: LeaderTerm::default(),
+        height: world_state.block_number,
+        prev_block_hash: world_state.last_block_hash,
+        timestamp: SystemTime::now(),
+        transactions,
+    };
+    let receipts = world_state
+        .apply_block(Block {
+            body,
+            signatures: SignatureList::default(),
+        })
+        .unwrap();
+    receipts
+        .into_iter()
+        .map(|receipt| receipt.status == ExecutionStatus::Applied)
+        .collect()
+}
+
+#[test]
+fn test_only_one_claim_of_a_lock_succeeds() {
+    let admin = Identity::generate();
+    let first = Identity::generate();
+    let second = Identity::generate();
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_writer(&admin, admin.id(), AccountType::Admin));
+    world_state.apply_transaction(create_writer(&admin, first.id(), AccountType::Normal));
+    world_state.apply_transaction(create_writer(&admin, second.id(), AccountType::Normal));
+
+    // Both try to claim the free lock in the same block, the first transaction wins.
+    let applied = apply(
+        &mut world_state,
+        vec![
+            compare_and_swap(&first, None, Some(b"first")),
+            compare_and_swap(&second, None, Some(b"second")),
+        ],
+    );
+    assert_eq!(applied, [true, false]);
+    assert_eq!(world_state.shared_values[LOCK], b"first");
+
+    // Only the holder can release the lock.
+    let applied = apply(
+        &mut world_state,
+        vec![
+            compare_and_swap(&second, Some(b"second"), None),
+            compare_and_swap(&first, Some(b"first"), None),
+        ],
+    );
+    assert_eq!(applied, [false, true]);
+    assert!(world_state.shared_values.is_empty());
+
+    let applied = apply(
+        &mut world_state,
+        vec![compare_and_swap(&second, None, Some(b"second"))],
+    );
+    assert_eq!(applied, [true]);
+}
@@ -0,0 +1,113 @@
+use prellblock::world_state::WorldState;
+use prellblock_client_api::consensus::{
+    Block, BlockHash, BlockNumber, Body, HashAlgorithm, LeaderTerm, SignatureList,
+};
+use std::time::SystemTime;
+
+#[test]
+fn block_numbers_are_serialized_as_integers() {
+    let block_number = BlockNumber::new(300);
+    assert_eq!(
+        postcard::to_stdvec(&block_number).unwrap(),
+        postcard::to_stdvec(&300_u64).unwrap()
+    );
+    let deserialized: BlockNumber =
+        postcard::from_bytes(&postcard::to_stdvec(&block_number).unwrap()).unwrap();
+    assert_eq!(deserialized, block_number);
+
+    let leader_term = LeaderTerm::new(42);
+    assert_eq!(
+        postcard::to_stdvec(&leader_term).unwrap(),
+        postcard::to_stdvec(&42_u64).unwrap()
+    );
+}
+
+#[test]
+fn block_number_bytes_keep_the_order() {
+    let numbers = [0, 1, 255, 256, 65_536, u64::MAX];
+    for pair in numbers.windows(2) {
+        let lower = BlockNumber::new(pair[0]);
+        let higher = BlockNumber::new(pair[1]);
+        assert!(lower.to_be_bytes().as_ref() < higher.to_be_bytes().as_ref());
+        assert!(
+            LeaderTerm::new(pair[0]).to_be_bytes().as_ref()
+                < LeaderTerm::new(pair[1]).to_be_bytes().as_ref()
+        );
+    }
+    for &number in &numbers {
+        let block_number = BlockNumber::new(number);
+        assert_eq!(
+            BlockNumber::from_be_bytes(block_number.to_be_bytes().as_ref()),
+            Some(block_number)
+        );
+    }
+    assert_eq!(BlockNumber::from_be_bytes(&[1, 2, 3]), None);
+}
+
+#[test]
+fn display_and_from_str_round_trip() {
+    let block_number = BlockNumber::new(1234);
+    assert_eq!(block_number.to_string().parse(), Ok(block_number));
+    let leader_term = LeaderTerm::new(u64::MAX);
+    assert_eq!(leader_term.to_string().parse(), Ok(leader_term));
+
+    assert!("-1".parse::<BlockNumber>().is_err());
+    assert!("18446744073709551616".parse::<LeaderTerm>().is_err());
+    assert!("#5".parse::<BlockNumber>().is_err());
+}
+
+#[test]
+fn checked_and_saturating_arithmetic() {
+    let max = BlockNumber::new(u64::MAX);
+    assert_eq!(max.checked_add(1), None);
+    assert_eq!(max.saturating_add(1), max);
+    assert_eq!(BlockNumber::default().checked_sub(1), None);
+    assert_eq!(
+        BlockNumber::default().saturating_sub(1),
+        BlockNumber::default()
+    );
+    assert_eq!(
+        BlockNumber::new(5).checked_sub(2),
+        Some(BlockNumber::new(3))
+    );
+
+    let max = LeaderTerm::new(u64::MAX);
+    assert_eq!(max.checked_add(1), None);
+    assert_eq!(max.saturating_add(1), max);
+    assert_eq!(LeaderTerm::default() + 2, LeaderTerm::new(2));
+}
+
+#[test]
+#[should_panic(expected = "block number underflow")]
+fn block_number_underflow_panics() {
+    let mut block_number = BlockNumber::default();
+    block_number -= 1;
+}
+
+#[test]
+#[should_panic(expected = "leader term overflow")]
+fn leader_term_overflow_panics() {
+    let _ = LeaderTerm::new(u64::MAX) + 1;
+}
+
+#[test]
+fn block_with_maximum_height_is_rejected() {
+    // A block sent by a peer must not make the RPU panic.
+    let block = Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: BlockNumber::new(u64::MAX),
+            prev_block_hash: BlockHash::zero(HashAlgorithm::default()),
+            timestamp: SystemTime::now(),
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    };
+    let mut world_state = WorldState::default();
+    assert!(world_state.apply_block(block).is_err());
+    assert_eq!(world_state.block_number, BlockNumber::default());
+    assert_eq!(
+        world_state.last_block_hash,
+        WorldState::default().last_block_hash
+    );
+}
@@ -0,0 +1,49 @@
+use prellblock::{
+    disk_space::{DiskSpaceConfig, DiskSpaceGuard},
+    event_bus::{Event, EventBus},
+};
+use std::env;
+
+fn temp_path() -> String {
+    env::temp_dir().to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_disk_space_low() {
+    let events = EventBus::default();
+    let mut receiver = events.subscribe();
+    let config = DiskSpaceConfig {
+        min_free_mb: u64::MAX,
+        ..DiskSpaceConfig::default()
+    };
+    let guard = DiskSpaceGuard::new(config, vec![temp_path()]).with_events(events);
+
+    // Transactions are accepted until the first check.
+    assert!(guard.verify_writable().is_ok());
+
+    guard.check();
+    assert!(guard.verify_writable().is_err());
+    match receiver.try_recv().unwrap() {
+        Event::DiskSpaceLow { path, .. } => assert_eq!(path, temp_path()),
+        event => panic!("Unexpected event {:?}", event),
+    }
+
+    // The event is only published once.
+    guard.check();
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn test_disk_space_sufficient() {
+    let events = EventBus::default();
+    let mut receiver = events.subscribe();
+    let config = DiskSpaceConfig {
+        min_free_mb: 0,
+        ..DiskSpaceConfig::default()
+    };
+    let guard = DiskSpaceGuard::new(config, vec![temp_path()]).with_events(events);
+
+    guard.check();
+    assert!(guard.verify_writable().is_ok());
+    assert!(receiver.try_recv().is_err());
+}
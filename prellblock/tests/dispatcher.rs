@@ -0,0 +1,46 @@
+use prellblock::peer::PendingMessages;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::time;
+
+#[tokio::test]
+async fn test_batches_wait_for_pending_consensus_messages() {
+    let pending = Arc::new(PendingMessages::default());
+    // Nothing is pending, so a batch is handled right away.
+    time::timeout(Duration::from_secs(1), pending.wait_until_none())
+        .await
+        .unwrap();
+
+    pending.add();
+    pending.add();
+    let handled = Arc::new(AtomicBool::new(false));
+    let batch = {
+        let pending = pending.clone();
+        let handled = handled.clone();
+        tokio::spawn(async move {
+            pending.wait_until_none().await;
+            handled.store(true, Ordering::SeqCst);
+        })
+    };
+
+    time::delay_for(Duration::from_millis(50)).await;
+    assert!(!handled.load(Ordering::SeqCst));
+
+    // The batch still waits for the second consensus message.
+    pending.remove();
+    time::delay_for(Duration::from_millis(50)).await;
+    assert!(!handled.load(Ordering::SeqCst));
+
+    pending.remove();
+    time::timeout(Duration::from_secs(1), batch)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(handled.load(Ordering::SeqCst));
+    assert_eq!(pending.count(), 0);
+}
@@ -0,0 +1,334 @@
+mod common;
+
+use common::{checker, create_account, rpu_type, TestDir};
+use newtype_enum::Enum;
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{
+    escrow::EscrowShares,
+    transaction_checker::PermissionError,
+    world_state::{WorldState, WorldStateService},
+};
+use prellblock_client_api::{
+    account::AccountType,
+    encryption::{KeyReference, Keyring},
+    escrow::{self, KeyShare, RecipientKey, SealedShare},
+    transaction, GetEscrowShare, Transaction,
+};
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn key_reference() -> KeyReference {
+    KeyReference {
+        namespace: "plant1.".to_string(),
+        version: 1,
+    }
+}
+
+fn escrow_key(
+    admin: &Identity,
+    threshold: usize,
+    holders: &[PeerId],
+    shares: &[KeyShare],
+) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::EscrowKey {
+        key_reference: key_reference(),
+        key_hash: escrow::key_hash(&[0; 32]),
+        threshold,
+        share_hashes: holders
+            .iter()
+            .cloned()
+            .zip(shares.iter().map(KeyShare::hash))
+            .collect(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+/// Authorize the `auditor` with the approvals of the `approvers` until `expires_at`.
+fn authorize_decryption_until<'a>(
+    admin: &Identity,
+    auditor: &PeerId,
+    approvers: impl IntoIterator<Item = &'a Identity>,
+    expires_at: SystemTime,
+) -> Signed<Transaction> {
+    let mut authorization = transaction::AuthorizeDecryption::new(
+        key_reference(),
+        auditor.clone(),
+        "Annual audit".to_string(),
+        expires_at,
+    );
+    for approver in approvers {
+        authorization.approve(approver).unwrap();
+    }
+    Transaction::from_variant(authorization)
+        .sign(admin)
+        .unwrap()
+}
+
+/// Authorize the `auditor` with the approvals of the `approvers` for an hour.
+fn authorize_decryption<'a>(
+    admin: &Identity,
+    auditor: &PeerId,
+    approvers: impl IntoIterator<Item = &'a Identity>,
+) -> Signed<Transaction> {
+    authorize_decryption_until(admin, auditor, approvers, SystemTime::now() + HOUR)
+}
+
+/// Create a `WorldState` with an admin, an auditor and `count` RPUs.
+fn world_state(admin: &Identity, auditor: &PeerId, count: u16) -> (WorldState, Vec<Identity>) {
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_account(admin, admin.id(), AccountType::Admin));
+    world_state.apply_transaction(create_account(admin, auditor, AccountType::Normal));
+    let rpus: Vec<Identity> = (0..count).map(|_| Identity::generate()).collect();
+    for (index, rpu) in rpus.iter().enumerate() {
+        world_state.apply_transaction(create_account(admin, rpu.id(), rpu_type(index as u16)));
+    }
+    (world_state, rpus)
+}
+
+fn peer_ids(identities: &[Identity]) -> Vec<PeerId> {
+    identities
+        .iter()
+        .map(|identity| identity.id().clone())
+        .collect()
+}
+
+#[test]
+fn test_shares_reconstruct_the_key() {
+    let key = Keyring::generate_key();
+    let shares = escrow::split(&key, 3, 4).unwrap();
+    assert_eq!(shares.len(), 4);
+
+    let recovered = escrow::combine(&[shares[3].clone(), shares[0].clone(), shares[2].clone()]);
+    assert_eq!(
+        escrow::key_hash(&recovered.unwrap()),
+        escrow::key_hash(&key)
+    );
+    assert_ne!(escrow::combine(&shares[..2]).unwrap(), key);
+    assert!(escrow::combine(&[shares[1].clone(), shares[1].clone()]).is_err());
+    assert!(escrow::split(&key, 5, 4).is_err());
+    assert!(escrow::split(&key, 0, 4).is_err());
+}
+
+#[test]
+fn test_authorized_auditors_are_recorded() {
+    let admin = Identity::generate();
+    let auditor = Identity::generate().id().clone();
+    let (mut world_state, rpu_identities) = world_state(&admin, &auditor, 4);
+    let rpus = peer_ids(&rpu_identities);
+    let shares = escrow::split(&[0; 32], 3, 4).unwrap();
+
+    world_state.apply_transaction(escrow_key(&admin, 3, &rpus, &shares));
+    let escrowed_key = &world_state.escrowed_keys[&key_reference()];
+    assert_eq!(escrowed_key.share_hash(&rpus[2]), Some(&shares[2].hash()));
+    let now = SystemTime::now();
+    assert!(!escrowed_key.is_auditor(&auditor, now));
+
+    // Authorizing the auditor again replaces the expiry.
+    let approvers = &rpu_identities[..3];
+    let expires_at = now + 2 * HOUR;
+    world_state.apply_transaction(authorize_decryption(&admin, &auditor, approvers));
+    world_state.apply_transaction(authorize_decryption_until(
+        &admin, &auditor, approvers, expires_at,
+    ));
+    let escrowed_key = &world_state.escrowed_keys[&key_reference()];
+    assert_eq!(escrowed_key.auditors, vec![(auditor.clone(), expires_at)]);
+    assert!(escrowed_key.is_auditor(&auditor, now));
+    assert!(!escrowed_key.is_auditor(&auditor, expires_at));
+
+    // Escrowing the key again revokes the authorizations.
+    world_state.apply_transaction(escrow_key(&admin, 3, &rpus, &shares));
+    assert!(!world_state.escrowed_keys[&key_reference()].is_auditor(&auditor, now));
+}
+
+#[tokio::test]
+async fn test_escrow_is_checked() {
+    let admin = Identity::generate();
+    let auditor = Identity::generate().id().clone();
+    let (world_state, rpu_identities) = world_state(&admin, &auditor, 4);
+    let rpus = peer_ids(&rpu_identities);
+    let checker = checker(world_state).await;
+    let shares = escrow::split(&[0; 32], 3, 4).unwrap();
+
+    let check = |transaction: Signed<Transaction>| {
+        checker.verify_permissions(transaction.verify_ref().unwrap())
+    };
+
+    // The key must be escrowed before an auditor can be authorized.
+    assert!(matches!(
+        check(authorize_decryption(&admin, &auditor, &rpu_identities)),
+        Err(PermissionError::KeyNotEscrowed(_))
+    ));
+
+    assert!(check(escrow_key(&admin, 3, &rpus, &shares)).is_ok());
+    // A minority of the RPUs must not be able to reconstruct the key.
+    assert!(matches!(
+        check(escrow_key(&admin, 2, &rpus, &shares)),
+        Err(PermissionError::InvalidEscrowThreshold(2))
+    ));
+    assert!(matches!(
+        check(escrow_key(&admin, 4, &rpus[..3], &shares)),
+        Err(PermissionError::InvalidEscrowThreshold(4))
+    ));
+    assert!(matches!(
+        check(escrow_key(&admin, 3, &[auditor.clone()], &shares)),
+        Err(PermissionError::NotAnRPU(_))
+    ));
+    let holders = [rpus[0].clone(), rpus[1].clone(), rpus[0].clone()];
+    assert!(matches!(
+        check(escrow_key(&admin, 3, &holders, &shares)),
+        Err(PermissionError::DuplicateKeyShare(_))
+    ));
+    let rpu = Identity::generate();
+    assert!(matches!(
+        check(escrow_key(&rpu, 3, &rpus, &shares)),
+        Err(PermissionError::AccountNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_audits_need_the_approval_of_a_supermajority() {
+    let admin = Identity::generate();
+    let auditor = Identity::generate().id().clone();
+    let (mut world_state, rpu_identities) = world_state(&admin, &auditor, 4);
+    let shares = escrow::split(&[0; 32], 3, 4).unwrap();
+    world_state.apply_transaction(escrow_key(&admin, 3, &peer_ids(&rpu_identities), &shares));
+    let checker = checker(world_state).await;
+
+    let check = |transaction: Signed<Transaction>| {
+        checker.verify_permissions(transaction.verify_ref().unwrap())
+    };
+
+    // A single admin cannot authorize an audit.
+    assert!(matches!(
+        check(authorize_decryption(&admin, &auditor, Vec::new())),
+        Err(PermissionError::InsufficientApprovals(0, 3))
+    ));
+    assert!(matches!(
+        check(authorize_decryption(&admin, &auditor, &rpu_identities[..2])),
+        Err(PermissionError::InsufficientApprovals(2, 3))
+    ));
+    // Only RPUs can approve.
+    let approvers = vec![&rpu_identities[0], &rpu_identities[1], &admin];
+    assert!(matches!(
+        check(authorize_decryption(&admin, &auditor, approvers)),
+        Err(PermissionError::NotAnRPU(_))
+    ));
+    assert!(check(authorize_decryption(&admin, &auditor, &rpu_identities[1..])).is_ok());
+    // An authorization must expire after it was created.
+    assert!(matches!(
+        check(authorize_decryption_until(
+            &admin,
+            &auditor,
+            &rpu_identities[1..],
+            SystemTime::UNIX_EPOCH
+        )),
+        Err(PermissionError::AuthorizationExpired(_))
+    ));
+
+    // An approval does not cover another audit.
+    let mut authorization = transaction::AuthorizeDecryption::new(
+        key_reference(),
+        auditor.clone(),
+        "Annual audit".to_string(),
+        SystemTime::now() + HOUR,
+    );
+    for approver in &rpu_identities[..3] {
+        authorization.approve(approver).unwrap();
+    }
+    authorization.reason = "Something else".to_string();
+    assert!(matches!(
+        check(
+            Transaction::from_variant(authorization)
+                .sign(&admin)
+                .unwrap()
+        ),
+        Err(PermissionError::InvalidApproval(_, _))
+    ));
+}
+
+#[test]
+fn test_sealed_shares_are_bound_to_the_recipient() {
+    let rpu = Identity::generate().id().clone();
+    let share = escrow::split(&[7; 32], 1, 1).unwrap().remove(0);
+    let recipient_key = RecipientKey::generate();
+
+    let sealed_share =
+        SealedShare::seal(&share, recipient_key.public_key(), &rpu, &key_reference()).unwrap();
+    let opened = recipient_key.open(&sealed_share, &rpu, &key_reference());
+    assert_eq!(opened.unwrap(), share);
+
+    // Only the recipient can open the share, and only as the share of the RPU that sealed it.
+    assert!(RecipientKey::generate()
+        .open(&sealed_share, &rpu, &key_reference())
+        .is_err());
+    let other_rpu = Identity::generate().id().clone();
+    assert!(recipient_key
+        .open(&sealed_share, &other_rpu, &key_reference())
+        .is_err());
+    // Keys of low order are rejected.
+    assert!(SealedShare::seal(&share, &[0; 32], &rpu, &key_reference()).is_err());
+}
+
+#[tokio::test]
+async fn test_shares_are_only_released_to_fresh_requests_of_authorized_auditors() {
+    let dir = TestDir::new("escrow-release");
+    let admin = Identity::generate();
+    let auditor = Identity::generate().id().clone();
+    let (mut world_state, rpu_identities) = world_state(&admin, &auditor, 4);
+    let rpus = peer_ids(&rpu_identities);
+    let shares = escrow::split(&[7; 32], 3, 4).unwrap();
+    world_state.apply_transaction(escrow_key(&admin, 3, &rpus, &shares));
+    world_state.apply_transaction(authorize_decryption(&admin, &auditor, &rpu_identities[..3]));
+
+    let world_state_service = WorldStateService::new();
+    world_state_service
+        .install_snapshot(world_state.clone())
+        .await;
+    let path = dir.path("escrow_shares.yaml");
+    fs::write(&path, serde_yaml::to_string(&shares[..1]).unwrap()).unwrap();
+    let escrow_shares =
+        EscrowShares::load(rpus[0].clone(), world_state_service.clone(), &path).unwrap();
+
+    let recipient_key = RecipientKey::generate();
+    let request = GetEscrowShare {
+        key_reference: key_reference(),
+        rpu: rpus[0].clone(),
+        recipient_key: *recipient_key.public_key(),
+        timestamp: SystemTime::now(),
+    };
+    let escrow_share = escrow_shares.release(&auditor, &request).unwrap();
+    let share = recipient_key
+        .open(&escrow_share.sealed_share, &rpus[0], &key_reference())
+        .unwrap();
+    assert_eq!(share, shares[0]);
+
+    // Other accounts, other RPUs and stale requests are rejected.
+    let admin_id = admin.id().clone();
+    assert!(escrow_shares.release(&admin_id, &request).is_err());
+    let for_other_rpu = GetEscrowShare {
+        rpu: rpus[1].clone(),
+        ..request.clone()
+    };
+    assert!(escrow_shares.release(&auditor, &for_other_rpu).is_err());
+    let stale = GetEscrowShare {
+        timestamp: SystemTime::now() - HOUR,
+        ..request.clone()
+    };
+    assert!(escrow_shares.release(&auditor, &stale).is_err());
+
+    // No shares are released after the authorization expired.
+    world_state.apply_transaction(authorize_decryption_until(
+        &admin,
+        &auditor,
+        &rpu_identities[..3],
+        SystemTime::now() - Duration::from_secs(1),
+    ));
+    world_state_service.install_snapshot(world_state).await;
+    assert!(escrow_shares.release(&auditor, &request).is_err());
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::TestDir;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::{
+    block_storage::BlockStorage,
+    consensus::TransactionApplier,
+    event_bus::{Event, EventBus},
+    world_state::WorldStateService,
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    consensus::{Block, Body, GenesisTransactions, HashAlgorithm, LeaderTerm, SignatureList},
+    transaction, Transaction,
+};
+use std::time::SystemTime;
+
+fn create_admin(admin: &Identity) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::CreateAccount {
+        id: admin.id().clone(),
+        name: "admin".to_string(),
+        permissions: Permissions {
+            account_type: Some(AccountType::Admin),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(true),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn key_value(admin: &Identity) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::KeyValue {
+        key: "temperature".to_string(),
+        value: b"42".to_vec(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_chain_changes_are_published() {
+    let dir = TestDir::new("event-bus");
+    let admin = Identity::generate();
+    let block_storage = BlockStorage::new(
+        &dir.path("blocks"),
+        Some(GenesisTransactions {
+            transactions: vec![create_admin(&admin)],
+            timestamp: SystemTime::now(),
+            hash_algorithm: HashAlgorithm::default(),
+            approvals: SignatureList::default(),
+        }),
+    )
+    .unwrap();
+    let world_state = WorldStateService::from_block_storage(&block_storage).unwrap();
+    let events = EventBus::default();
+    let transaction_applier =
+        TransactionApplier::new(block_storage, world_state.clone()).with_events(events.clone());
+    let mut receiver = events.subscribe();
+
+    let current = world_state.get();
+    let block = Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: current.block_number,
+            prev_block_hash: current.last_block_hash,
+            timestamp: SystemTime::now(),
+            transactions: vec![key_value(&admin), key_value(&admin)],
+        },
+        signatures: SignatureList::default(),
+    };
+    let block_hash = block.hash();
+    transaction_applier.apply_block(block).await.unwrap();
+
+    match receiver.try_recv().unwrap() {
+        Event::BlockCommitted {
+            block_number,
+            block_hash: committed_hash,
+            transactions,
+        } => {
+            assert_eq!(block_number, current.block_number);
+            assert_eq!(committed_hash, block_hash);
+            assert_eq!(transactions, 2);
+        }
+        event => panic!("Unexpected event {:?}", event),
+    }
+
+    let rolled_back = transaction_applier.rollback().unwrap();
+    assert_eq!(rolled_back.hash(), block_hash);
+    assert_eq!(world_state.get().block_number, current.block_number);
+    match receiver.try_recv().unwrap() {
+        Event::BlockRolledBack { block_number } => assert_eq!(block_number, current.block_number),
+        event => panic!("Unexpected event {:?}", event),
+    }
+    assert!(receiver.try_recv().is_err());
+}
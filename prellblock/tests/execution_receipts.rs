@@ -0,0 +1,137 @@
+mod common;
+
+use common::{create_account, TestDir};
+use newtype_enum::Enum;
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{
+    block_storage::BlockStorage,
+    world_state::{ExecutionError, WorldState},
+};
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{
+        transaction_hash, Block, BlockHash, BlockNumber, Body, GenesisTransactions, LeaderTerm,
+        SignatureList,
+    },
+    transaction, ExecutionStatus, Transaction,
+};
+use std::time::SystemTime;
+
+fn delete_account(admin: &Identity, id: &PeerId) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::DeleteAccount {
+        id: id.clone(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn chain(transactions: Vec<Vec<Signed<Transaction>>>) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for transactions in transactions {
+        let (height, prev_block_hash) = match blocks.last() {
+            Some(block) => (block.block_number() + 1, block.hash()),
+            None => (BlockNumber::default(), BlockHash::default()),
+        };
+        blocks.push(Block {
+            body: Body {
+                leader_term: LeaderTerm::default(),
+                height,
+                prev_block_hash,
+                timestamp: SystemTime::now(),
+                transactions,
+            },
+            signatures: SignatureList::default(),
+        });
+    }
+    blocks
+}
+
+#[test]
+fn test_deleting_a_normal_account() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_account(&admin, admin.id(), AccountType::Admin));
+    world_state.apply_transaction(create_account(&admin, writer.id(), AccountType::Normal));
+
+    world_state
+        .try_apply_transaction(delete_account(&admin, writer.id()))
+        .unwrap();
+    assert!(world_state.accounts.get(writer.id()).is_none());
+
+    let result = world_state.try_apply_transaction(delete_account(&admin, writer.id()));
+    assert!(matches!(result, Err(ExecutionError::UnknownAccount(_))));
+}
+
+#[test]
+fn test_failing_transaction_is_skipped() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let duplicate = create_account(&admin, admin.id(), AccountType::Normal);
+    let blocks = chain(vec![
+        vec![create_account(&admin, admin.id(), AccountType::Admin)],
+        vec![
+            duplicate.clone(),
+            create_account(&admin, writer.id(), AccountType::Normal),
+        ],
+    ]);
+
+    let mut world_state = WorldState::default();
+    world_state.apply_block(blocks[0].clone()).unwrap();
+    let receipts = world_state.apply_block(blocks[1].clone()).unwrap();
+
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(receipts[0].transaction_hash, transaction_hash(&duplicate));
+    assert_eq!(receipts[0].block_number, BlockNumber::new(1));
+    assert_eq!(receipts[0].index, 0);
+    assert!(matches!(receipts[0].status, ExecutionStatus::Failed { .. }));
+    assert_eq!(receipts[1].index, 1);
+    assert_eq!(receipts[1].status, ExecutionStatus::Applied);
+    // The failing transaction did not change the admin, the next one was applied.
+    assert_eq!(
+        world_state.accounts.get(admin.id()).unwrap().account_type,
+        AccountType::Admin
+    );
+    assert!(world_state.accounts.get(writer.id()).is_some());
+    assert_eq!(world_state.block_number, BlockNumber::new(2));
+}
+
+#[test]
+fn test_execution_receipts_are_recorded() {
+    let dir = TestDir::new("execution-receipts");
+    let admin = Identity::generate();
+    let duplicate = create_account(&admin, admin.id(), AccountType::Normal);
+    let blocks = chain(vec![
+        vec![create_account(&admin, admin.id(), AccountType::Admin)],
+        vec![duplicate.clone()],
+    ]);
+    let genesis = &blocks[0].body;
+    let block_storage = BlockStorage::new(
+        &dir.path("blocks"),
+        Some(GenesisTransactions {
+            transactions: genesis.transactions.clone(),
+            timestamp: genesis.timestamp,
+            hash_algorithm: genesis.prev_block_hash.algorithm(),
+            approvals: SignatureList::default(),
+        }),
+    )
+    .unwrap();
+    block_storage.write_block(&blocks[1]).unwrap();
+
+    let mut world_state = WorldState::default();
+    world_state.apply_block(blocks[0].clone()).unwrap();
+    let receipts = world_state.apply_block(blocks[1].clone()).unwrap();
+    block_storage.write_execution_receipts(&receipts).unwrap();
+
+    let hash = transaction_hash(&duplicate);
+    let receipt = block_storage.read_execution_receipt(&hash).unwrap();
+    assert_eq!(receipt.as_ref(), receipts.first());
+
+    // Rolling back the block removes its receipts.
+    block_storage.pop_block().unwrap();
+    assert!(block_storage
+        .read_execution_receipt(&hash)
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{checker, create_account, rpu_type, world_state_with_rpus};
+use newtype_enum::Enum;
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{transaction_checker::PermissionError, world_state::FaultTolerance};
+use prellblock_client_api::{
+    consensus::BlockNumber, parameter::Parameter, transaction, Transaction,
+};
+use std::time::SystemTime;
+
+fn delete_account(admin: &Identity, id: &PeerId) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::DeleteAccount {
+        id: id.clone(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn set_fault_tolerance(admin: &Identity, value: u64) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::SetParameter {
+        parameter: Parameter::FaultTolerance,
+        value,
+        activation_height: BlockNumber::new(1),
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+#[test]
+fn test_quorum_is_derived_from_the_fault_tolerance() {
+    // Without a declared fault tolerance, the quorum is a supermajority (more than 2/3).
+    for peer_count in 4..100 {
+        let fault_tolerance = FaultTolerance::new(peer_count, 0).unwrap();
+        assert_eq!(fault_tolerance.faulty(), (peer_count - 1) / 3);
+        assert_eq!(fault_tolerance.quorum(), peer_count * 2 / 3 + 1);
+    }
+    assert!(FaultTolerance::new(3, 0).is_err());
+
+    let fault_tolerance = FaultTolerance::new(7, 1).unwrap();
+    assert_eq!(fault_tolerance.quorum(), 6);
+    assert_eq!(fault_tolerance.nonfaulty_count(), 2);
+    assert!(FaultTolerance::new(7, 2).is_ok());
+    assert!(FaultTolerance::new(6, 2).is_err());
+}
+
+#[tokio::test]
+async fn test_rpus_must_tolerate_the_declared_faults() {
+    let admin = Identity::generate();
+    let (world_state, rpus) = world_state_with_rpus(&admin, 4);
+    let checker = checker(world_state).await;
+
+    let check = |transaction: Signed<Transaction>| {
+        checker.verify_permissions(transaction.verify_ref().unwrap())
+    };
+
+    assert!(check(set_fault_tolerance(&admin, 1)).is_ok());
+    assert!(matches!(
+        check(set_fault_tolerance(&admin, 2)),
+        Err(PermissionError::InsufficientFaultTolerance(_))
+    ));
+
+    // Four RPUs are the minimum for one faulty RPU.
+    assert!(matches!(
+        check(delete_account(&admin, &rpus[0])),
+        Err(PermissionError::InsufficientFaultTolerance(_))
+    ));
+    let rpu = Identity::generate();
+    assert!(check(create_account(&admin, rpu.id(), rpu_type(4))).is_ok());
+    assert!(check(delete_account(&admin, admin.id())).is_ok());
+}
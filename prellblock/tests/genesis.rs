@@ -0,0 +1,88 @@
+mod common;
+
+use common::{create_account, rpu_type};
+use pinxit::Identity;
+use prellblock::genesis::GenesisConfig;
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{GenesisError, GenesisTransactions, HashAlgorithm, SignatureList},
+};
+use std::time::SystemTime;
+
+/// Create genesis transactions with an admin and the `rpus`.
+fn genesis_transactions(authority: &Identity, rpus: &[Identity]) -> GenesisTransactions {
+    let admin = Identity::generate();
+    let mut transactions = vec![create_account(authority, admin.id(), AccountType::Admin)];
+    for (index, rpu) in rpus.iter().enumerate() {
+        transactions.push(create_account(authority, rpu.id(), rpu_type(index as u16)));
+    }
+    GenesisTransactions {
+        transactions,
+        timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    }
+}
+
+#[test]
+fn test_genesis_requires_the_authority() {
+    let authority = Identity::generate();
+    let mut genesis = genesis_transactions(&authority, &[]);
+    let config = GenesisConfig {
+        authority: Some(authority.id().clone()),
+        founding_rpus: false,
+    };
+
+    assert!(matches!(
+        config.verify(&genesis),
+        Err(GenesisError::MissingApproval(peer_id)) if peer_id == *authority.id()
+    ));
+    // Without a configuration, unapproved genesis transactions are accepted.
+    assert!(GenesisConfig::default().verify(&genesis).is_ok());
+
+    genesis.approve(&authority).unwrap();
+    assert!(config.verify(&genesis).is_ok());
+
+    // The approvals survive a round trip through the genesis file.
+    let genesis: GenesisTransactions =
+        serde_yaml::from_str(&serde_yaml::to_string(&genesis).unwrap()).unwrap();
+    assert!(config.verify(&genesis).is_ok());
+}
+
+#[test]
+fn test_changed_genesis_invalidates_approvals() {
+    let authority = Identity::generate();
+    let mut genesis = genesis_transactions(&authority, &[]);
+    genesis.approve(&authority).unwrap();
+
+    genesis.transactions.pop();
+    assert!(matches!(
+        GenesisConfig::default().verify(&genesis),
+        Err(GenesisError::InvalidApproval(..))
+    ));
+}
+
+#[test]
+fn test_genesis_requires_all_founding_rpus() {
+    let authority = Identity::generate();
+    let rpus: Vec<_> = (0..4).map(|_| Identity::generate()).collect();
+    let mut genesis = genesis_transactions(&authority, &rpus);
+    let rpu_ids: Vec<_> = rpus.iter().map(|rpu| rpu.id().clone()).collect();
+    assert_eq!(genesis.founding_rpus(), rpu_ids);
+
+    let config = GenesisConfig {
+        authority: Some(authority.id().clone()),
+        founding_rpus: true,
+    };
+    for rpu in &rpus[..3] {
+        genesis.approve(rpu).unwrap();
+    }
+    assert!(matches!(
+        config.verify(&genesis),
+        Err(GenesisError::MissingApproval(peer_id)) if peer_id == *rpus[3].id()
+    ));
+
+    // All founding RPUs are sufficient without the authority.
+    genesis.approve(&rpus[3]).unwrap();
+    assert!(config.verify(&genesis).is_ok());
+}
@@ -0,0 +1,205 @@
+mod common;
+
+use common::TestDir;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::{
+    block_storage::BlockStorage,
+    transaction_checker::{PermissionError, TransactionChecker},
+    world_state::{FsyncPolicy, PersistenceConfig, WorldState, WorldStateService},
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    consensus::{
+        Block, BlockHash, BlockNumber, Body, GenesisTransactions, LeaderTerm, SignatureList,
+    },
+    parameter::Parameter,
+    transaction, Transaction,
+};
+use std::time::SystemTime;
+
+fn create_admin(admin: &Identity) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::CreateAccount {
+        id: admin.id().clone(),
+        name: "admin".to_string(),
+        permissions: Permissions {
+            account_type: Some(AccountType::Admin),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(false),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn set_parameter(
+    admin: &Identity,
+    parameter: Parameter,
+    value: u64,
+    activation_height: u64,
+) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::SetParameter {
+        parameter,
+        value,
+        activation_height: BlockNumber::new(activation_height),
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn chain(transactions: Vec<Vec<Signed<Transaction>>>) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for transactions in transactions {
+        let (height, prev_block_hash) = match blocks.last() {
+            Some(block) => (block.block_number() + 1, block.hash()),
+            None => (BlockNumber::default(), BlockHash::default()),
+        };
+        blocks.push(Block {
+            body: Body {
+                leader_term: LeaderTerm::default(),
+                height,
+                prev_block_hash,
+                timestamp: SystemTime::now(),
+                transactions,
+            },
+            signatures: SignatureList::default(),
+        });
+    }
+    blocks
+}
+
+#[test]
+fn test_parameter_is_activated_at_activation_height() {
+    let dir = TestDir::new("parameters");
+    let admin = Identity::generate();
+    let blocks = chain(vec![
+        vec![create_admin(&admin)],
+        vec![set_parameter(
+            &admin,
+            Parameter::MaxTransactionsPerBlock,
+            100,
+            3,
+        )],
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+    ]);
+
+    // The value applies to the block at the activation height.
+    let mut world_state = WorldState::default();
+    for block in &blocks {
+        let expected = if world_state.block_number < BlockNumber::new(3) {
+            Parameter::MaxTransactionsPerBlock.default_value()
+        } else {
+            100
+        };
+        assert_eq!(
+            world_state.parameter(Parameter::MaxTransactionsPerBlock),
+            expected
+        );
+        world_state.apply_block(block.clone()).unwrap();
+    }
+    assert_eq!(
+        world_state.parameter(Parameter::MaxTransactionsPerBlock),
+        100
+    );
+    assert!(world_state.scheduled_parameters.is_empty());
+
+    // Replaying and reloading the persisted chain leads to the same state.
+    let genesis = &blocks[0].body;
+    let block_storage = BlockStorage::new(
+        &dir.path("blocks"),
+        Some(GenesisTransactions {
+            transactions: genesis.transactions.clone(),
+            timestamp: genesis.timestamp,
+            hash_algorithm: genesis.prev_block_hash.algorithm(),
+            approvals: SignatureList::default(),
+        }),
+    )
+    .unwrap();
+    for block in &blocks[1..] {
+        block_storage.write_block(block).unwrap();
+    }
+    let config = PersistenceConfig {
+        path: dir.path("world-state"),
+        compaction_interval: 1000,
+        fsync: FsyncPolicy::default(),
+    };
+    for _ in 0..2 {
+        let replayed = WorldStateService::with_persistence(&block_storage, config.clone())
+            .unwrap()
+            .get();
+        assert_eq!(
+            postcard::to_stdvec(&replayed).unwrap(),
+            postcard::to_stdvec(&world_state).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_transaction_larger_than_max_size_is_rejected() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let create_writer = Transaction::from_variant(transaction::CreateAccount {
+        id: writer.id().clone(),
+        name: "writer".to_string(),
+        permissions: Permissions {
+            account_type: Some(AccountType::Normal),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(true),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    })
+    .sign(&admin)
+    .unwrap();
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_admin(&admin));
+    world_state.apply_transaction(create_writer);
+
+    let key_value = |size: usize| {
+        Transaction::from_variant(transaction::KeyValue {
+            key: "key".to_string(),
+            value: vec![0; size],
+            timestamp: SystemTime::now(),
+        })
+        .sign(&writer)
+        .unwrap()
+    };
+    let max_size = Parameter::MaxTransactionSize.default_value() as usize;
+    let small = key_value(1000);
+    let large = key_value(max_size);
+    assert!(large.clone().unverified().payload_size() > max_size);
+
+    let check = |world_state: WorldState| {
+        let world_state_service = WorldStateService::new();
+        let small = small.clone();
+        let large = large.clone();
+        async move {
+            world_state_service.install_snapshot(world_state).await;
+            let checker = TransactionChecker::new(world_state_service);
+            (
+                checker.verify_permissions(small.verify_ref().unwrap()),
+                checker.verify_permissions(large.verify_ref().unwrap()),
+            )
+        }
+    };
+
+    let (small_result, large_result) = check(world_state.clone()).await;
+    assert!(small_result.is_ok());
+    assert!(matches!(
+        large_result,
+        Err(PermissionError::TransactionTooLarge(_, max)) if max == max_size
+    ));
+
+    // Raising the limit accepts the transaction.
+    world_state
+        .parameters
+        .insert(Parameter::MaxTransactionSize, 2 * max_size as u64);
+    let (small_result, large_result) = check(world_state).await;
+    assert!(small_result.is_ok());
+    assert!(large_result.is_ok());
+}
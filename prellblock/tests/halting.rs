@@ -0,0 +1,117 @@
+mod common;
+
+use common::TestDir;
+use im::Vector;
+use pinxit::{Identity, PeerId, Signer};
+use prellblock::{
+    ban_list::BanList,
+    block_storage::BlockStorage,
+    consensus::{Consensus, InMemoryNetwork, SystemClock},
+    event_bus::Event,
+    world_state::WorldStateService,
+};
+use prellblock_client_api::consensus::{GenesisTransactions, HashAlgorithm, SignatureList};
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time;
+
+async fn start_rpu(
+    dir: &TestDir,
+    name: &str,
+    identity: Identity,
+    peers: Vector<(PeerId, SocketAddr)>,
+    genesis_timestamp: SystemTime,
+    network: Arc<InMemoryNetwork>,
+) -> Arc<Consensus> {
+    let genesis = GenesisTransactions {
+        transactions: vec![],
+        timestamp: genesis_timestamp,
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    };
+    let block_storage =
+        BlockStorage::new(&dir.path(&format!("{}-blocks", name)), Some(genesis)).unwrap();
+    let world_state = WorldStateService::default();
+    {
+        let mut world_state = world_state.get_writable().await;
+        world_state.peers = peers;
+        world_state.save();
+    }
+    let ban_list = Arc::new(BanList::new(&dir.path(&format!("{}-ban-list", name))).unwrap());
+
+    let identity: Arc<dyn Signer> = Arc::new(identity);
+    Consensus::with_network(
+        identity,
+        block_storage,
+        world_state,
+        ban_list,
+        None,
+        network,
+        Arc::new(SystemClock),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_rpu_halts_on_mixed_genesis() {
+    let dir = TestDir::new("halting");
+    let network = Arc::new(InMemoryNetwork::default());
+    let identities: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+    let peers: Vector<(PeerId, SocketAddr)> = identities
+        .iter()
+        .enumerate()
+        .map(|(index, identity)| {
+            let address = ([127, 0, 0, 1], 5131 + index as u16).into();
+            (identity.id().clone(), address)
+        })
+        .collect();
+
+    // All other RPUs were started with another genesis, so no quorum can be reached.
+    let mut identities = identities.into_iter();
+    let own_identity = identities.next().unwrap();
+    for (index, identity) in identities.enumerate() {
+        let name = format!("other-{}", index);
+        let other_genesis = UNIX_EPOCH + Duration::from_secs(1);
+        let rpu = start_rpu(
+            &dir,
+            &name,
+            identity,
+            peers.clone(),
+            other_genesis,
+            network.clone(),
+        )
+        .await;
+        network.connect(peers[index + 1].1, &rpu);
+    }
+
+    let rpu = start_rpu(
+        &dir,
+        "own",
+        own_identity,
+        peers.clone(),
+        UNIX_EPOCH,
+        network.clone(),
+    )
+    .await;
+    network.connect(peers[0].1, &rpu);
+    assert_eq!(rpu.status().halt_reason, None);
+    let mut events = rpu.events().subscribe();
+
+    let reason = time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Event::Halted(reason) = events.recv().await.unwrap() {
+                return reason;
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    // The RPU is halted as soon as the event is published.
+    let status = rpu.status();
+    assert_eq!(status.halt_reason, Some(reason));
+    assert!(rpu.verify_not_halted().is_err());
+}
@@ -0,0 +1,99 @@
+use pinxit::{Identity, PeerId};
+use prellblock::world_state::{Account, WorldState};
+use prellblock_client_api::consensus::{
+    Block, BlockHash, BlockNumber, Body, HashAlgorithm, LeaderTerm, SignatureList,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+#[test]
+fn test_block_hash_function() {
+    // Changing the hash function breaks all existing chains.
+    let golden: BlockHash = "a3c1b2c8f6b425adff58d97a32f87ca08deb652e1a4748e0013a8512b5e12c0f\
+                             96ffb5993c8d42a5ad659952f52df41ac39452d0f753a38662f3d8eaa18eb58b"
+        .parse()
+        .unwrap();
+    assert_eq!(BlockHash::of(b"prellblock"), golden);
+}
+
+#[test]
+fn test_sha256_block_hash() {
+    let golden: BlockHash = "5f035833440cd20c8d6b49352838e4c8885c2e06deecb1144eca0f582e652679"
+        .parse()
+        .unwrap();
+    let hash = HashAlgorithm::Sha256.hash(b"prellblock");
+    assert_eq!(hash, golden);
+    assert_eq!(golden.algorithm(), HashAlgorithm::Sha256);
+    assert_eq!(hash.to_string().parse::<BlockHash>().unwrap(), hash);
+
+    let data = postcard::to_stdvec(&hash).unwrap();
+    assert_eq!(postcard::from_bytes::<BlockHash>(&data).unwrap(), hash);
+}
+
+#[test]
+fn test_genesis_chooses_the_hash_function() {
+    let genesis = Body {
+        leader_term: LeaderTerm::default(),
+        height: BlockNumber::default(),
+        prev_block_hash: BlockHash::zero(HashAlgorithm::Sha256),
+        timestamp: SystemTime::UNIX_EPOCH,
+        transactions: Vec::new(),
+    };
+    let chain_id = genesis.hash();
+    assert_eq!(chain_id.algorithm(), HashAlgorithm::Sha256);
+
+    // All following blocks use the hash function of the genesis block.
+    let next = Body {
+        height: BlockNumber::new(1),
+        prev_block_hash: chain_id,
+        ..genesis.clone()
+    };
+    assert_eq!(next.hash().algorithm(), HashAlgorithm::Sha256);
+    assert_ne!(
+        BlockHash::zero(HashAlgorithm::Blake2b),
+        genesis.prev_block_hash
+    );
+}
+
+#[test]
+fn test_block_hash_survives_serialization() {
+    let block = Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: BlockNumber::default(),
+            prev_block_hash: BlockHash::of(b"genesis"),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_589_000_000),
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    };
+
+    let data = postcard::to_stdvec(&block).unwrap();
+    let decoded: Block = postcard::from_bytes(&data).unwrap();
+    assert_eq!(decoded.hash(), block.hash());
+}
+
+#[test]
+fn test_world_state_is_serialized_canonically() {
+    let accounts: Vec<(PeerId, Arc<Account>)> = (0..32)
+        .map(|index| {
+            let id = Identity::generate().id().clone();
+            (id, Arc::new(Account::new(format!("account-{}", index))))
+        })
+        .collect();
+
+    let mut world_state = WorldState::default();
+    world_state.accounts = accounts.iter().cloned().collect();
+    // Same entries, but a different insertion order (and hasher state).
+    let mut other_world_state = WorldState::default();
+    other_world_state.accounts = accounts.iter().rev().cloned().collect();
+
+    let data = postcard::to_stdvec(&world_state).unwrap();
+    let other_data = postcard::to_stdvec(&other_world_state).unwrap();
+    assert_eq!(BlockHash::of(&data), BlockHash::of(&other_data));
+
+    let decoded: WorldState = postcard::from_bytes(&data).unwrap();
+    assert_eq!(postcard::to_stdvec(&decoded).unwrap(), data);
+}
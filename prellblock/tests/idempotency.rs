@@ -0,0 +1,42 @@
+use pinxit::Identity;
+use prellblock::idempotency::IdempotencyKeys;
+use prellblock_client_api::{consensus::BlockHash, Receipt};
+use std::time::SystemTime;
+
+fn receipt(transaction: &[u8]) -> Receipt {
+    Receipt {
+        transaction_hash: BlockHash::of(transaction),
+        received_at: SystemTime::now(),
+    }
+}
+
+#[test]
+fn test_retry_returns_original_receipt() {
+    let keys = IdempotencyKeys::default();
+    let signer = Identity::generate().id().clone();
+    let original = receipt(b"transaction");
+
+    assert_eq!(keys.insert(&signer, "key", original.clone()).unwrap(), None);
+    assert_eq!(
+        keys.insert(&signer, "key", receipt(b"transaction"))
+            .unwrap(),
+        Some(original)
+    );
+
+    // The key cannot be used for another transaction ...
+    assert!(keys.insert(&signer, "key", receipt(b"other")).is_err());
+    // ... but by another account.
+    let other_signer = Identity::generate().id().clone();
+    assert_eq!(
+        keys.insert(&other_signer, "key", receipt(b"other"))
+            .unwrap(),
+        None
+    );
+
+    // A removed key can be used again.
+    keys.remove(&signer, "key");
+    assert_eq!(
+        keys.insert(&signer, "key", receipt(b"other")).unwrap(),
+        None
+    );
+}
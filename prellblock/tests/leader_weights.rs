@@ -0,0 +1,93 @@
+mod common;
+
+use common::{checker, world_state_with_rpus};
+use newtype_enum::Enum;
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{transaction_checker::PermissionError, world_state::WorldState};
+use prellblock_client_api::{consensus::LeaderTerm, transaction, Transaction};
+use std::{collections::HashMap, time::SystemTime};
+
+fn set_leader_weight(admin: &Identity, id: &PeerId, weight: u32) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::SetLeaderWeight {
+        id: id.clone(),
+        weight,
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn leaders(world_state: &WorldState, terms: u64) -> Vec<PeerId> {
+    (0..terms)
+        .map(|term| world_state.leader(LeaderTerm::default() + term).clone())
+        .collect()
+}
+
+#[test]
+fn test_equal_weights_rotate_in_order() {
+    let admin = Identity::generate();
+    let (world_state, rpus) = world_state_with_rpus(&admin, 4);
+
+    let expected: Vec<PeerId> = rpus.iter().cycle().take(8).cloned().collect();
+    assert_eq!(leaders(&world_state, 8), expected);
+}
+
+#[test]
+fn test_preferred_rpus_lead_more_often() {
+    let admin = Identity::generate();
+    let (mut world_state, rpus) = world_state_with_rpus(&admin, 4);
+    world_state.apply_transaction(set_leader_weight(&admin, &rpus[1], 4));
+    world_state.apply_transaction(set_leader_weight(&admin, &rpus[2], 3));
+
+    // The preferred RPU leads the first leader term.
+    assert_eq!(world_state.leader(LeaderTerm::default()), &rpus[1]);
+
+    // Within a cycle (the sum of all weights), each RPU leads as many terms as its weight.
+    let cycle = leaders(&world_state, 9);
+    let mut counts: HashMap<&PeerId, u32> = HashMap::new();
+    for leader in &cycle {
+        *counts.entry(leader).or_default() += 1;
+    }
+    for rpu in &rpus {
+        assert_eq!(counts[rpu], world_state.leader_weight(rpu));
+    }
+
+    // The next cycle repeats the schedule.
+    assert_eq!(leaders(&world_state, 18)[9..], cycle[..]);
+
+    // Resetting the weights restores the plain rotation.
+    world_state.apply_transaction(set_leader_weight(&admin, &rpus[1], 1));
+    world_state.apply_transaction(set_leader_weight(&admin, &rpus[2], 1));
+    assert!(world_state.leader_weights.is_empty());
+    assert_eq!(leaders(&world_state, 4), rpus);
+}
+
+#[tokio::test]
+async fn test_leader_weight_is_checked() {
+    let admin = Identity::generate();
+    let (world_state, rpus) = world_state_with_rpus(&admin, 4);
+    let checker = checker(world_state).await;
+
+    let check = |transaction: Signed<Transaction>| {
+        checker.verify_permissions(transaction.verify_ref().unwrap())
+    };
+
+    assert!(check(set_leader_weight(&admin, &rpus[0], 16)).is_ok());
+    assert!(matches!(
+        check(set_leader_weight(&admin, &rpus[0], 0)),
+        Err(PermissionError::InvalidLeaderWeight(0))
+    ));
+    assert!(matches!(
+        check(set_leader_weight(&admin, &rpus[0], 17)),
+        Err(PermissionError::InvalidLeaderWeight(17))
+    ));
+    assert!(matches!(
+        check(set_leader_weight(&admin, admin.id(), 2)),
+        Err(PermissionError::NotAnRPU(_))
+    ));
+    let rpu = Identity::generate();
+    assert!(matches!(
+        check(set_leader_weight(&rpu, &rpus[0], 2)),
+        Err(PermissionError::AccountNotFound(_))
+    ));
+}
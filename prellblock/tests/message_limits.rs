@@ -0,0 +1,74 @@
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::peer::TransactionBatch;
+use prellblock_client_api::{
+    consensus::SignatureList,
+    limits::{MAX_PEERS, MAX_TRANSACTIONS_PER_BATCH, MAX_VALUES_PER_TRANSACTION},
+    schema::{Schema, MAX_SCHEMA_DEPTH},
+    transaction, Transaction,
+};
+use std::time::SystemTime;
+
+/// Encode only the length of a sequence (postcard encodes it like a `usize`).
+fn announced_length(len: usize) -> Vec<u8> {
+    postcard::to_stdvec(&len).unwrap()
+}
+
+fn multi_key_value(count: usize) -> Vec<u8> {
+    let transaction = Transaction::from_variant(transaction::MultiKeyValue {
+        values: (0..count)
+            .map(|index| (index.to_string(), Vec::new()))
+            .collect(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(&Identity::generate())
+    .unwrap();
+    postcard::to_stdvec(&transaction).unwrap()
+}
+
+#[test]
+fn test_announced_lengths_are_rejected() {
+    // Lengths beyond the limit are rejected before any item is read.
+    let bytes = announced_length(MAX_TRANSACTIONS_PER_BATCH + 1);
+    assert!(matches!(
+        postcard::from_bytes::<TransactionBatch>(&bytes),
+        Err(postcard::Error::SerdeDeCustom)
+    ));
+    let bytes = announced_length(MAX_PEERS + 1);
+    assert!(matches!(
+        postcard::from_bytes::<SignatureList>(&bytes),
+        Err(postcard::Error::SerdeDeCustom)
+    ));
+
+    // Lengths within the limit only fail because the items are missing.
+    let bytes = announced_length(MAX_PEERS);
+    assert!(matches!(
+        postcard::from_bytes::<SignatureList>(&bytes),
+        Err(postcard::Error::DeserializeUnexpectedEnd)
+    ));
+}
+
+#[test]
+fn test_values_per_transaction_are_limited() {
+    let bytes = multi_key_value(MAX_VALUES_PER_TRANSACTION);
+    assert!(postcard::from_bytes::<Signed<Transaction>>(&bytes).is_ok());
+
+    let bytes = multi_key_value(MAX_VALUES_PER_TRANSACTION + 1);
+    assert!(postcard::from_bytes::<Signed<Transaction>>(&bytes).is_err());
+}
+
+#[test]
+fn test_deeply_nested_schemas_are_rejected() {
+    let mut schema = Schema::Bool;
+    for _ in 1..MAX_SCHEMA_DEPTH {
+        schema = Schema::Option(Box::new(schema));
+    }
+    let bytes = postcard::to_stdvec(&schema).unwrap();
+    assert_eq!(postcard::from_bytes::<Schema>(&bytes).unwrap(), schema);
+
+    // A schema nested this deep would exhaust the stack without the limit.
+    let option_tag = postcard::to_stdvec(&Schema::Option(Box::new(Schema::Bool))).unwrap()[0];
+    let mut bytes = vec![option_tag; 1_000_000];
+    bytes.push(0);
+    assert!(postcard::from_bytes::<Schema>(&bytes).is_err());
+}
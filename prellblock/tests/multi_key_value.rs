@@ -0,0 +1,96 @@
+mod common;
+
+use common::{checker, create_account, create_writer, verify};
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::{transaction_checker::PermissionError, world_state::WorldState};
+use prellblock_client_api::{account::AccountType, quota::Quota, transaction, Transaction};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const QUOTA: Quota = Quota {
+    max_transactions: 1,
+    max_bytes: 1_000_000,
+    window_ms: 60_000,
+};
+
+fn key_values(writer: &Identity, keys: &[&str]) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::MultiKeyValue {
+        values: keys.iter().map(|key| (key.to_string(), vec![42])).collect(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+/// Create a `WorldState` with an admin, a writer and quotas on the namespaces `plant1.` and `plant2.`.
+fn world_state(admin: &Identity, writer: &Identity) -> WorldState {
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_account(admin, admin.id(), AccountType::Admin));
+    world_state.apply_transaction(create_writer(admin, writer.id(), AccountType::Normal));
+    for namespace in &["plant1.", "plant2."] {
+        world_state.apply_transaction(
+            Transaction::from_variant(transaction::SetQuota {
+                namespace: namespace.to_string(),
+                quota: Some(QUOTA),
+                timestamp: SystemTime::now(),
+            })
+            .sign(admin)
+            .unwrap(),
+        );
+    }
+    world_state
+}
+
+#[tokio::test]
+async fn test_invalid_key_values_are_rejected() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let checker = checker(world_state(&admin, &writer)).await;
+    let mut check = checker.check();
+    check.set_block_timestamp(UNIX_EPOCH);
+
+    assert!(matches!(
+        verify(&mut check, key_values(&writer, &[])),
+        Err(PermissionError::NoValues)
+    ));
+    assert!(matches!(
+        verify(&mut check, key_values(&writer, &["plant2.a", "plant2.b", "plant2.a"])),
+        Err(PermissionError::DuplicateKey(key)) if key == "plant2.a"
+    ));
+}
+
+#[tokio::test]
+async fn test_key_values_count_once_per_namespace() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let checker = checker(world_state(&admin, &writer)).await;
+    let mut check = checker.check();
+    check.set_block_timestamp(UNIX_EPOCH);
+
+    // Both values of the namespace count as one transaction.
+    let transaction = key_values(&writer, &["plant1.temperature", "plant1.pressure"]);
+    assert!(verify(&mut check, transaction).is_ok());
+
+    // The quota of `plant1.` is exhausted, so the quota of `plant2.` is not used either.
+    let transaction = key_values(&writer, &["plant2.temperature", "plant1.temperature"]);
+    assert!(matches!(
+        verify(&mut check, transaction),
+        Err(PermissionError::QuotaExceeded(_))
+    ));
+    assert!(verify(&mut check, key_values(&writer, &["plant2.temperature"])).is_ok());
+    assert!(verify(&mut check, key_values(&writer, &["plant2.temperature"])).is_err());
+}
+
+#[test]
+fn test_key_values_record_all_keys() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let mut world_state = world_state(&admin, &writer);
+
+    world_state
+        .try_apply_transaction(key_values(&writer, &["plant2.a", "plant2.b"]))
+        .unwrap();
+
+    let keys: Vec<_> = world_state.keys[writer.id()].iter().cloned().collect();
+    assert_eq!(keys, ["plant2.a", "plant2.b"]);
+}
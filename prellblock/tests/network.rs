@@ -0,0 +1,74 @@
+use im::Vector;
+use pinxit::{Identity, Signer};
+use prellblock::{
+    ban_list::BanList,
+    block_storage::BlockStorage,
+    consensus::{Consensus, ConsensusNetwork, InMemoryNetwork, SystemClock},
+    world_state::WorldStateService,
+};
+use prellblock_client_api::consensus::{
+    BlockNumber, GenesisTransactions, HashAlgorithm, SignatureList,
+};
+use std::{net::SocketAddr, sync::Arc, time::SystemTime};
+
+async fn start_rpu(network: Arc<InMemoryNetwork>, peer_address: SocketAddr) -> Arc<Consensus> {
+    let identity = Identity::generate();
+    let mut peers = Vector::new();
+    peers.push_back((identity.id().clone(), peer_address));
+
+    let genesis = GenesisTransactions {
+        transactions: vec![],
+        timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    };
+    let block_storage = BlockStorage::new("../blocks/test-network", Some(genesis)).unwrap();
+    let world_state = WorldStateService::default();
+    {
+        let mut world_state = world_state.get_writable().await;
+        world_state.peers = peers;
+        world_state.save();
+    }
+    let ban_list = Arc::new(BanList::new("../data/test-network-ban-list").unwrap());
+
+    let identity: Arc<dyn Signer> = Arc::new(identity);
+    Consensus::with_network(
+        identity,
+        block_storage,
+        world_state,
+        ban_list,
+        None,
+        network,
+        Arc::new(SystemClock),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_blocks_are_downloaded_from_connected_rpus() {
+    let network = Arc::new(InMemoryNetwork::default());
+    let peer_address: SocketAddr = "127.0.0.1:4131".parse().unwrap();
+    let genesis = BlockNumber::default();
+
+    // Nothing is connected yet.
+    assert!(network
+        .download_blocks(peer_address, genesis, genesis + 1)
+        .await
+        .is_err());
+
+    let consensus = start_rpu(network.clone(), peer_address).await;
+    network.connect(peer_address, &consensus);
+    let blocks = network
+        .download_blocks(peer_address, genesis, genesis + 1)
+        .await
+        .unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].block_number(), genesis);
+
+    // A disconnected RPU is unreachable (e.g. to simulate a partition).
+    network.disconnect(peer_address);
+    assert!(network
+        .download_blocks(peer_address, genesis, genesis + 1)
+        .await
+        .is_err());
+}
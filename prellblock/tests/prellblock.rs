@@ -1,11 +1,12 @@
-use balise::server::TlsIdentity;
+use balise::{client::ConnectTimeouts, server::TlsIdentity};
 use futures::{select, FutureExt};
 use im::Vector;
-use pinxit::Identity;
+use pinxit::{Identity, Signer};
 use prellblock::{
+    ban_list::BanList,
     batcher::Batcher,
     block_storage::BlockStorage,
-    consensus::Consensus,
+    consensus::{Consensus, SystemClock},
     data_broadcaster::Broadcaster,
     data_storage::DataStorage,
     peer::{Calculator, PeerInbox, Receiver},
@@ -14,7 +15,7 @@ use prellblock::{
     turi::Turi,
     world_state::WorldStateService,
 };
-use prellblock_client_api::consensus::GenesisTransactions;
+use prellblock_client_api::consensus::{GenesisTransactions, HashAlgorithm, SignatureList};
 use std::{
     net::SocketAddr,
     sync::Arc,
@@ -39,6 +40,8 @@ async fn test_prellblock() {
     let fake_genesis = GenesisTransactions {
         transactions: vec![],
         timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
     };
 
     let block_storage = BlockStorage::new("../blocks/test-prellblock", Some(fake_genesis)).unwrap();
@@ -49,9 +52,22 @@ async fn test_prellblock() {
         world_state.save();
     }
 
-    let consensus = Consensus::new(identity, block_storage.clone(), world_state.clone()).await;
-
-    let broadcaster = Broadcaster::new(world_state.clone());
+    let ban_list = Arc::new(BanList::new("../data/test-prellblock-ban-list").unwrap());
+
+    let identity: Arc<dyn Signer> = Arc::new(identity);
+    let consensus = Consensus::new(
+        identity.clone(),
+        block_storage.clone(),
+        world_state.clone(),
+        ban_list,
+        ConnectTimeouts::default(),
+        None,
+        Arc::default(),
+        Arc::new(SystemClock),
+    )
+    .await;
+
+    let broadcaster = Broadcaster::new(consensus.chain_id(), world_state.clone(), identity);
     let broadcaster = Arc::new(broadcaster);
 
     let batcher = Batcher::new(broadcaster);
@@ -66,10 +82,17 @@ async fn test_prellblock() {
     // execute the turi in a new thread
     let turi_task = {
         let transaction_checker = transaction_checker.clone();
+        let consensus = consensus.clone();
         let test_identity = test_identity.clone();
         tokio::spawn(async move {
             let mut listener = TcpListener::bind(turi_address).await?;
-            let turi = Turi::new(test_identity, batcher, reader, transaction_checker);
+            let turi = Turi::new(
+                test_identity,
+                batcher,
+                reader,
+                transaction_checker,
+                consensus,
+            );
             turi.serve(&mut listener).await
         })
     };
@@ -86,7 +109,7 @@ async fn test_prellblock() {
     // execute the receiver in a new thread
     let peer_receiver_task = tokio::spawn(async move {
         let mut listener = TcpListener::bind(peer_address).await?;
-        let receiver = Receiver::new(test_identity, peer_inbox);
+        let receiver = Receiver::new(test_identity, vec![peer_inbox]);
         receiver.serve(&mut listener).await
     });
 
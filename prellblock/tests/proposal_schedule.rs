@@ -0,0 +1,93 @@
+use prellblock::consensus::{ProposalSchedule, ProposalTiming};
+use std::time::{Duration, Instant};
+
+const TIMING: ProposalTiming = ProposalTiming {
+    max_transactions: 10,
+    min_interval: Duration::from_millis(300),
+    max_wait: Duration::from_millis(500),
+    jitter: Duration::from_millis(0),
+};
+
+#[test]
+fn test_nothing_is_proposed_without_transactions() {
+    let mut schedule = ProposalSchedule::default();
+    assert_eq!(schedule.next_proposal(Instant::now(), 0, &TIMING), None);
+}
+
+#[test]
+fn test_full_blocks_are_proposed_after_the_min_interval() {
+    let mut schedule = ProposalSchedule::default();
+    let start = Instant::now();
+
+    // The first full block is proposed immediately.
+    assert_eq!(schedule.next_proposal(start, 10, &TIMING), Some(start));
+    schedule.proposed(start, &TIMING);
+
+    // The next one only after the minimum interval.
+    let now = start + Duration::from_millis(10);
+    assert_eq!(
+        schedule.next_proposal(now, 25, &TIMING),
+        Some(start + TIMING.min_interval)
+    );
+}
+
+#[test]
+fn test_partial_blocks_wait_for_the_max_wait() {
+    let mut schedule = ProposalSchedule::default();
+    let start = Instant::now();
+
+    // Pending transactions wait from when they were first seen, even when more arrive.
+    assert_eq!(
+        schedule.next_proposal(start, 1, &TIMING),
+        Some(start + TIMING.max_wait)
+    );
+    let later = start + Duration::from_millis(200);
+    assert_eq!(
+        schedule.next_proposal(later, 5, &TIMING),
+        Some(start + TIMING.max_wait)
+    );
+
+    // The wait starts again for the transactions after a proposal.
+    let proposed_at = start + TIMING.max_wait;
+    schedule.proposed(proposed_at, &TIMING);
+    let now = proposed_at + Duration::from_millis(50);
+    assert_eq!(
+        schedule.next_proposal(now, 1, &TIMING),
+        Some(now + TIMING.max_wait)
+    );
+
+    // A queue that was drained resets the wait.
+    assert_eq!(schedule.next_proposal(now, 0, &TIMING), None);
+}
+
+#[test]
+fn test_partial_blocks_respect_the_min_interval() {
+    let timing = ProposalTiming {
+        min_interval: Duration::from_millis(1000),
+        ..TIMING
+    };
+    let mut schedule = ProposalSchedule::default();
+    let start = Instant::now();
+    schedule.proposed(start, &timing);
+
+    assert_eq!(
+        schedule.next_proposal(start, 1, &timing),
+        Some(start + timing.min_interval)
+    );
+}
+
+#[test]
+fn test_jitter_delays_the_next_proposal() {
+    let timing = ProposalTiming {
+        jitter: Duration::from_millis(100),
+        ..TIMING
+    };
+    let start = Instant::now();
+    for _ in 0..20 {
+        let mut schedule = ProposalSchedule::default();
+        schedule.proposed(start, &timing);
+        let due = schedule.next_proposal(start, 10, &timing).unwrap();
+        assert!(due >= start + timing.min_interval);
+        assert!(due <= start + timing.min_interval + timing.jitter);
+    }
+}
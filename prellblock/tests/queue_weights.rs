@@ -0,0 +1,103 @@
+mod common;
+
+use common::{checker, create_writer};
+use newtype_enum::Enum;
+use pinxit::{Identity, PeerId, Signable, Signed};
+use prellblock::{consensus::Queue, transaction_checker::PermissionError, world_state::WorldState};
+use prellblock_client_api::{account::AccountType, transaction, Transaction};
+use std::time::SystemTime;
+
+fn set_queue_weight(admin: &Identity, id: &PeerId, weight: u32) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::SetQueueWeight {
+        id: id.clone(),
+        weight,
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn key_value(writer: &Identity, key: &str) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::KeyValue {
+        key: key.to_string(),
+        value: Vec::new(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+/// Create a `WorldState` with an admin and `count` writing accounts.
+fn world_state(admin: &Identity, count: usize) -> (WorldState, Vec<Identity>) {
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_writer(admin, admin.id(), AccountType::Admin));
+    let writers: Vec<Identity> = (0..count).map(|_| Identity::generate()).collect();
+    for writer in &writers {
+        world_state.apply_transaction(create_writer(admin, writer.id(), AccountType::Normal));
+    }
+    (world_state, writers)
+}
+
+#[test]
+fn test_noisy_account_does_not_dominate_blocks() {
+    let admin = Identity::generate();
+    let (mut world_state, writers) = world_state(&admin, 3);
+    world_state.apply_transaction(set_queue_weight(&admin, writers[2].id(), 2));
+
+    // The first account queued lots of transactions before the others.
+    let mut queue = Queue::default();
+    for index in 0..100 {
+        queue.insert(key_value(&writers[0], &format!("noisy-{}", index)));
+    }
+    for index in 0..10 {
+        queue.insert(key_value(&writers[1], &format!("quiet-{}", index)));
+        queue.insert(key_value(&writers[2], &format!("important-{}", index)));
+    }
+
+    let block = queue.drain_fair(
+        20,
+        |transaction| transaction.signer().clone(),
+        |signer| world_state.queue_weight(signer),
+    );
+    let count = |writer: &Identity| {
+        block
+            .iter()
+            .filter(|transaction| transaction.signer() == writer.id())
+            .count()
+    };
+    assert_eq!(block.len(), 20);
+    assert_eq!(count(&writers[0]), 5);
+    assert_eq!(count(&writers[1]), 5);
+    assert_eq!(count(&writers[2]), 10);
+    assert_eq!(queue.len(), 90);
+
+    // Resetting the weight removes it from the `WorldState`.
+    world_state.apply_transaction(set_queue_weight(&admin, writers[2].id(), 1));
+    assert!(world_state.queue_weights.is_empty());
+}
+
+#[tokio::test]
+async fn test_queue_weight_is_checked() {
+    let admin = Identity::generate();
+    let (world_state, writers) = world_state(&admin, 1);
+    let checker = checker(world_state).await;
+
+    let check = |transaction: Signed<Transaction>| {
+        checker.verify_permissions(transaction.verify_ref().unwrap())
+    };
+
+    assert!(check(set_queue_weight(&admin, writers[0].id(), 64)).is_ok());
+    assert!(matches!(
+        check(set_queue_weight(&admin, writers[0].id(), 0)),
+        Err(PermissionError::InvalidQueueWeight(0))
+    ));
+    assert!(matches!(
+        check(set_queue_weight(&admin, writers[0].id(), 65)),
+        Err(PermissionError::InvalidQueueWeight(65))
+    ));
+    assert!(matches!(
+        check(set_queue_weight(&admin, Identity::generate().id(), 2)),
+        Err(PermissionError::AccountNotFound(_))
+    ));
+    assert!(check(set_queue_weight(&writers[0], writers[0].id(), 2)).is_err());
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use common::{checker, create_account, create_writer, verify};
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::{transaction_checker::PermissionError, world_state::WorldState};
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{Block, Body, LeaderTerm, SignatureList},
+    quota::Quota,
+    transaction, ExecutionStatus, Transaction,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const QUOTA: Quota = Quota {
+    max_transactions: 2,
+    max_bytes: 1_000_000,
+    window_ms: 60_000,
+};
+
+fn set_quota(admin: &Identity, namespace: &str, quota: Option<Quota>) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::SetQuota {
+        namespace: namespace.to_string(),
+        quota,
+        timestamp: SystemTime::now(),
+    })
+    .sign(admin)
+    .unwrap()
+}
+
+fn key_value(writer: &Identity, key: &str) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::KeyValue {
+        key: key.to_string(),
+        value: Vec::new(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+/// A point in time in the window `window` of `QUOTA`.
+fn in_window(window: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(window * QUOTA.window_ms + 1)
+}
+
+/// Create a `WorldState` with an admin, a writer and a quota on the namespace `plant1.`.
+fn world_state(admin: &Identity, writer: &Identity) -> WorldState {
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(create_account(admin, admin.id(), AccountType::Admin));
+    world_state.apply_transaction(create_writer(admin, writer.id(), AccountType::Normal));
+    world_state.apply_transaction(set_quota(admin, "plant1.", Some(QUOTA)));
+    world_state
+}
+
+#[tokio::test]
+async fn test_quota_is_enforced_per_window() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let checker = checker(world_state(&admin, &writer)).await;
+    let mut check = checker.check();
+
+    // Other namespaces are not limited.
+    check.set_block_timestamp(in_window(1));
+    for index in 0..5 {
+        let key = format!("plant2.{}", index);
+        assert!(verify(&mut check, key_value(&writer, &key)).is_ok());
+    }
+
+    assert!(verify(&mut check, key_value(&writer, "plant1.a")).is_ok());
+    assert!(verify(&mut check, key_value(&writer, "plant1.b")).is_ok());
+    assert!(matches!(
+        verify(&mut check, key_value(&writer, "plant1.c")),
+        Err(PermissionError::QuotaExceeded(_))
+    ));
+
+    // An earlier timestamp counts towards the current window.
+    check.set_block_timestamp(in_window(0));
+    assert!(verify(&mut check, key_value(&writer, "plant1.c")).is_err());
+
+    // The next window starts over.
+    check.set_block_timestamp(in_window(2));
+    assert!(verify(&mut check, key_value(&writer, "plant1.c")).is_ok());
+}
+
+#[test]
+fn test_block_exceeding_the_quota_is_not_applied() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let mut world_state = world_state(&admin, &writer);
+
+    let body = Body {
+        leader_term: LeaderTerm::default(),
+        height: world_state.block_number,
+        prev_block_hash: world_state.last_block_hash,
+        timestamp: in_window(1),
+        transactions: vec![
+            key_value(&writer, "plant1.a"),
+            key_value(&writer, "plant1.b"),
+            key_value(&writer, "plant1.c"),
+        ],
+    };
+    let receipts = world_state
+        .apply_block(Block {
+            body,
+            signatures: SignatureList::default(),
+        })
+        .unwrap();
+
+    assert_eq!(receipts[0].status, ExecutionStatus::Applied);
+    assert_eq!(receipts[1].status, ExecutionStatus::Applied);
+    assert!(matches!(receipts[2].status, ExecutionStatus::Failed { .. }));
+    assert_eq!(
+        world_state.quota_usage.get("plant1.").unwrap().transactions,
+        2
+    );
+
+    // Removing the quota also removes its usage.
+    world_state.apply_transaction(set_quota(&admin, "plant1.", None));
+    assert!(world_state.quotas.is_empty());
+    assert!(world_state.quota_usage.is_empty());
+}
+
+#[test]
+fn test_failed_transaction_does_not_use_the_quota() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let mut world_state = world_state(&admin, &writer);
+
+    // The shared key is not set, so the swap fails.
+    let failing = Transaction::from_variant(transaction::CompareAndSwap {
+        key: "plant1.lock".to_string(),
+        expected: Some(b"locked".to_vec()),
+        new: None,
+        timestamp: SystemTime::now(),
+    })
+    .sign(&writer)
+    .unwrap();
+    let body = Body {
+        leader_term: LeaderTerm::default(),
+        height: world_state.block_number,
+        prev_block_hash: world_state.last_block_hash,
+        timestamp: in_window(1),
+        transactions: vec![
+            key_value(&writer, "plant1.a"),
+            failing,
+            key_value(&writer, "plant1.b"),
+        ],
+    };
+    let receipts = world_state
+        .apply_block(Block {
+            body,
+            signatures: SignatureList::default(),
+        })
+        .unwrap();
+
+    assert_eq!(receipts[0].status, ExecutionStatus::Applied);
+    assert!(matches!(receipts[1].status, ExecutionStatus::Failed { .. }));
+    assert_eq!(receipts[2].status, ExecutionStatus::Applied);
+    assert_eq!(
+        world_state.quota_usage.get("plant1.").unwrap().transactions,
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_set_quota_is_checked() {
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let checker = checker(world_state(&admin, &writer)).await;
+
+    let check = |transaction: Signed<Transaction>| {
+        checker.verify_permissions(transaction.verify_ref().unwrap())
+    };
+
+    assert!(check(set_quota(&admin, "plant2.", Some(QUOTA))).is_ok());
+    assert!(matches!(
+        check(set_quota(
+            &admin,
+            "plant2.",
+            Some(Quota {
+                window_ms: 0,
+                ..QUOTA
+            })
+        )),
+        Err(PermissionError::InvalidQuota(_))
+    ));
+    assert!(check(set_quota(&writer, "plant2.", None)).is_err());
+}
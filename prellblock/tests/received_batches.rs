@@ -0,0 +1,52 @@
+use pinxit::Identity;
+use prellblock::peer::{BatchId, BatchTransactionStatus, ReceivedBatches};
+
+#[test]
+fn test_retried_batches_get_the_first_response() {
+    let received_batches = ReceivedBatches::default();
+    let peer = Identity::generate().id().clone();
+    let batch_id = BatchId::random();
+    let statuses = vec![
+        BatchTransactionStatus::Queued,
+        BatchTransactionStatus::Rejected("Missing permissions.".to_string()),
+    ];
+
+    assert_eq!(received_batches.get(&peer, batch_id), None);
+    received_batches
+        .insert(peer.clone(), batch_id, statuses.clone())
+        .unwrap();
+    assert_eq!(
+        received_batches.get(&peer, batch_id),
+        Some(statuses.clone())
+    );
+
+    // A concurrent retry must not be queued again.
+    assert_eq!(
+        received_batches.insert(
+            peer.clone(),
+            batch_id,
+            vec![BatchTransactionStatus::Duplicate; 2]
+        ),
+        Err(statuses)
+    );
+}
+
+#[test]
+fn test_batch_ids_are_per_peer() {
+    let received_batches = ReceivedBatches::default();
+    let first = Identity::generate().id().clone();
+    let second = Identity::generate().id().clone();
+    let batch_id = BatchId(42);
+
+    received_batches
+        .insert(
+            first.clone(),
+            batch_id,
+            vec![BatchTransactionStatus::Queued],
+        )
+        .unwrap();
+    assert_eq!(received_batches.get(&second, batch_id), None);
+    assert!(received_batches
+        .insert(second, batch_id, vec![BatchTransactionStatus::Queued])
+        .is_ok());
+}
@@ -0,0 +1,38 @@
+use prellblock::peer::{ReceivedEvictions, EVICTION_WINDOW};
+use prellblock_client_api::consensus::BlockHash;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_replayed_evictions_are_rejected() {
+    let received_evictions = ReceivedEvictions::default();
+    let timestamp = SystemTime::now();
+
+    received_evictions
+        .insert(BlockHash::of(b"first"), timestamp)
+        .unwrap();
+    assert!(received_evictions
+        .insert(BlockHash::of(b"first"), timestamp)
+        .is_err());
+
+    // Another request with the same timestamp is accepted.
+    received_evictions
+        .insert(BlockHash::of(b"second"), timestamp)
+        .unwrap();
+}
+
+#[test]
+fn test_stale_evictions_are_rejected() {
+    let received_evictions = ReceivedEvictions::default();
+    let now = SystemTime::now();
+    let margin = Duration::from_secs(60);
+
+    assert!(received_evictions
+        .insert(BlockHash::of(b"stale"), now - EVICTION_WINDOW - margin)
+        .is_err());
+    assert!(received_evictions
+        .insert(BlockHash::of(b"future"), now + EVICTION_WINDOW + margin)
+        .is_err());
+    received_evictions
+        .insert(BlockHash::of(b"recent"), now - EVICTION_WINDOW + margin)
+        .unwrap();
+}
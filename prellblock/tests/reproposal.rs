@@ -0,0 +1,201 @@
+mod common;
+
+use common::{create_account, create_writer, TestDir};
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed, Signer};
+use prellblock::{
+    ban_list::BanList,
+    block_storage::BlockStorage,
+    consensus::{
+        block_proof, verify_block_signatures, Consensus, ConsensusMessage, ConsensusNetwork,
+        InMemoryNetwork, SystemClock,
+    },
+    world_state::WorldStateService,
+};
+use prellblock_client_api::{
+    account::AccountType,
+    consensus::{
+        transaction_hash, BlockNumber, GenesisTransactions, HashAlgorithm, LeaderTerm,
+        SignatureList,
+    },
+    parameter::Parameter,
+    transaction, TraceEventKind, Transaction, TransactionHash,
+};
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::time;
+
+fn peer_address(index: u16) -> SocketAddr {
+    ([127, 0, 0, 1], 5431 + index).into()
+}
+
+fn key_value(writer: &Identity, value: &str) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::KeyValue {
+        key: "temperature".to_string(),
+        value: value.as_bytes().to_vec(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+fn genesis_transactions(genesis: &[Signed<Transaction>]) -> GenesisTransactions {
+    GenesisTransactions {
+        transactions: genesis.to_vec(),
+        timestamp: SystemTime::UNIX_EPOCH,
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    }
+}
+
+async fn start_rpu(
+    dir: &TestDir,
+    index: u16,
+    identity: Identity,
+    genesis: &[Signed<Transaction>],
+    network: Arc<InMemoryNetwork>,
+) -> (Arc<Consensus>, WorldStateService) {
+    let block_storage = BlockStorage::new(
+        &dir.path(&format!("{}-blocks", index)),
+        Some(genesis_transactions(genesis)),
+    )
+    .unwrap();
+    let world_state = WorldStateService::from_block_storage(&block_storage).unwrap();
+    let ban_list = Arc::new(BanList::new(&dir.path(&format!("{}-ban-list", index))).unwrap());
+
+    let identity: Arc<dyn Signer> = Arc::new(identity);
+    let rpu = Consensus::with_network(
+        identity,
+        block_storage,
+        world_state.clone(),
+        ban_list,
+        None,
+        network.clone(),
+        Arc::new(SystemClock),
+    )
+    .await;
+    network.connect(peer_address(index), &rpu);
+    (rpu, world_state)
+}
+
+/// Wait until the block with the `block_number` is applied to the `world_state`.
+async fn wait_applied(world_state: &WorldStateService, block_number: BlockNumber) {
+    time::timeout(Duration::from_secs(20), async {
+        while world_state.get().block_number <= block_number {
+            time::delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+/// Wait until the transaction with the `hash` is proposed to the `rpu`.
+async fn wait_proposed(rpu: &Consensus, hash: &TransactionHash) {
+    time::timeout(Duration::from_secs(10), async {
+        loop {
+            let proposed = rpu
+                .transaction_trace(hash)
+                .events
+                .iter()
+                .any(|event| matches!(event.kind, TraceEventKind::Proposed { .. }));
+            if proposed {
+                return;
+            }
+            time::delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_reproposed_block_is_in_the_new_leader_term() {
+    let dir = TestDir::new("reproposal");
+    let network = Arc::new(InMemoryNetwork::default());
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let identities: Vec<Identity> = (0..4).map(|_| Identity::generate()).collect();
+    let old_leader_id = identities[0].id().clone();
+
+    // A short censorship timeout triggers the view change quickly.
+    let mut genesis = vec![
+        create_account(&admin, admin.id(), AccountType::Admin),
+        create_writer(&admin, writer.id(), AccountType::Normal),
+        Transaction::from_variant(transaction::SetParameter {
+            parameter: Parameter::CensorshipTimeout,
+            value: 1000,
+            activation_height: BlockNumber::new(0),
+            timestamp: SystemTime::now(),
+        })
+        .sign(&admin)
+        .unwrap(),
+    ];
+    for (index, identity) in identities.iter().enumerate() {
+        let peer_address = peer_address(index as u16);
+        let rpu_type = AccountType::RPU {
+            turi_address: peer_address,
+            peer_address,
+        };
+        genesis.push(create_account(&admin, identity.id(), rpu_type));
+    }
+
+    // The first RPU leads the first leader term.
+    let mut rpus = Vec::new();
+    for (index, identity) in identities.into_iter().enumerate() {
+        let rpu = start_rpu(&dir, index as u16, identity, &genesis, network.clone()).await;
+        rpus.push(rpu);
+    }
+    let (_, new_leader_world_state) = &rpus[1];
+    let block_number = new_leader_world_state.get().block_number;
+
+    // The block reaches the append phase, but nobody receives the `Commit` message.
+    network.drop_messages(|_, message| {
+        matches!(message.unverified_ref(), ConsensusMessage::Commit(_))
+    });
+    let transaction = key_value(&writer, "prepared");
+    let hash = transaction_hash(&transaction);
+    for (rpu, _) in &rpus {
+        rpu.take_transactions(vec![transaction.clone()]).await;
+    }
+    for (rpu, _) in &rpus[1..] {
+        wait_proposed(rpu, &hash).await;
+    }
+
+    // The old leader fails, so the second RPU re-proposes the prepared block.
+    network.drop_messages(move |_, message| *message.signer() == old_leader_id);
+    network.disconnect(peer_address(0));
+    for (_, world_state) in &rpus[1..] {
+        wait_applied(world_state, block_number).await;
+    }
+
+    let mut blocks = network
+        .download_blocks(peer_address(1), block_number, block_number + 1)
+        .await
+        .unwrap();
+    let block = blocks.pop().unwrap();
+    assert_eq!(block.body.leader_term, LeaderTerm::new(1));
+    assert_eq!(block.body.transactions, vec![transaction]);
+
+    // The block proof and the signatures are valid for the stored block.
+    block_proof(&block).unwrap().verify(&block).unwrap();
+    let block_storage = BlockStorage::new(
+        &dir.path("fresh-blocks"),
+        Some(genesis_transactions(&genesis)),
+    )
+    .unwrap();
+    let world_state = WorldStateService::from_block_storage(&block_storage).unwrap();
+    verify_block_signatures(&block, &world_state.get()).unwrap();
+
+    // The old leader synchronizes the block when it receives the next block.
+    network.deliver_messages();
+    let (old_leader, old_leader_world_state) = &rpus[0];
+    network.connect(peer_address(0), old_leader);
+    let transaction = key_value(&writer, "next");
+    for (rpu, _) in &rpus {
+        rpu.take_transactions(vec![transaction.clone()]).await;
+    }
+    wait_applied(old_leader_world_state, block_number + 1).await;
+}
@@ -0,0 +1,117 @@
+use prellblock::consensus::{Error, Round, RoundMessage, RoundStep};
+
+const MESSAGES: [RoundMessage; 3] = [
+    RoundMessage::Prepare,
+    RoundMessage::Append,
+    RoundMessage::Commit,
+];
+
+/// Receive the `messages` in the given order and return the order they were handled in.
+fn handle_in_order(messages: &[RoundMessage]) -> Vec<RoundMessage> {
+    let mut round = Round::default();
+    let mut handled = Vec::new();
+    for &message in messages {
+        match round.receive(message).unwrap() {
+            RoundStep::Handle => {
+                handled.push(message);
+                if let Some(buffered) = round.handled(message) {
+                    handled.push(buffered);
+                }
+            }
+            RoundStep::Buffer => {}
+        }
+    }
+    handled
+}
+
+fn orderings() -> Vec<Vec<RoundMessage>> {
+    let mut orderings = Vec::new();
+    for first in 0..3 {
+        for second in 0..3 {
+            if second != first {
+                let third = 3 - first - second;
+                orderings.push(vec![MESSAGES[first], MESSAGES[second], MESSAGES[third]]);
+            }
+        }
+    }
+    orderings
+}
+
+#[test]
+fn test_all_orderings_are_handled() {
+    for ordering in orderings() {
+        let handled = handle_in_order(&ordering);
+        assert_eq!(handled.len(), 3, "{:?}", ordering);
+        for message in &MESSAGES {
+            assert!(handled.contains(message), "{:?}", ordering);
+        }
+        // The commit is always handled after the block body.
+        let position = |message| handled.iter().position(|&m| m == message).unwrap();
+        assert!(
+            position(RoundMessage::Append) < position(RoundMessage::Commit),
+            "{:?}",
+            ordering
+        );
+    }
+}
+
+#[test]
+fn test_commit_is_buffered_until_append() {
+    let mut round = Round::default();
+    assert_eq!(
+        round.receive(RoundMessage::Commit).unwrap(),
+        RoundStep::Buffer
+    );
+    assert_eq!(
+        round.receive(RoundMessage::Prepare).unwrap(),
+        RoundStep::Handle
+    );
+    assert_eq!(round.handled(RoundMessage::Prepare), None);
+    assert_eq!(
+        round.receive(RoundMessage::Append).unwrap(),
+        RoundStep::Handle
+    );
+    assert_eq!(
+        round.handled(RoundMessage::Append),
+        Some(RoundMessage::Commit)
+    );
+    // A retried commit is handled directly.
+    assert_eq!(
+        round.receive(RoundMessage::Commit).unwrap(),
+        RoundStep::Handle
+    );
+}
+
+#[test]
+fn test_messages_are_accepted_once() {
+    let mut round = Round::default();
+    for &message in &MESSAGES[..2] {
+        assert_eq!(round.receive(message).unwrap(), RoundStep::Handle);
+        round.handled(message);
+        assert!(matches!(
+            round.receive(message),
+            Err(Error::DuplicateRoundMessage(duplicate)) if duplicate == message
+        ));
+    }
+
+    let mut round = Round::default();
+    assert_eq!(
+        round.receive(RoundMessage::Commit).unwrap(),
+        RoundStep::Buffer
+    );
+    assert!(round.receive(RoundMessage::Commit).is_err());
+}
+
+#[test]
+fn test_failed_messages_can_be_received_again() {
+    let mut round = Round::default();
+    // The Append failed verification, so it was not recorded as handled.
+    assert_eq!(
+        round.receive(RoundMessage::Append).unwrap(),
+        RoundStep::Handle
+    );
+    assert_eq!(
+        round.receive(RoundMessage::Append).unwrap(),
+        RoundStep::Handle
+    );
+}
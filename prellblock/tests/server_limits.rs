@@ -0,0 +1,152 @@
+use balise::{
+    frame::ResponseFrame,
+    server::{ConnectionInfo, IntoResponse, Limits, Server, TlsIdentity},
+    ErrorCode,
+};
+use openssl::ssl::{SslConnector, SslMethod, SslStream, SslVerifyMode};
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, runtime::Runtime, time};
+
+/// The time the server takes to handle a request.
+const HANDLING_TIME: Duration = Duration::from_millis(500);
+
+/// Start a server with the `limits`.
+fn start_server(runtime: &mut Runtime, limits: Limits) -> SocketAddr {
+    let identity =
+        TlsIdentity::from_pkcs12(include_bytes!("test-identity.pfx"), "prellblock").unwrap();
+    let server = Server::new(
+        |(): (), _: ConnectionInfo| async {
+            time::delay_for(HANDLING_TIME).await;
+            ().into_response()
+        },
+        identity,
+    )
+    .unwrap()
+    .with_limits(limits);
+    let mut listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+    let address = listener.local_addr().unwrap();
+    runtime.spawn(async move { server.serve(&mut listener).await });
+    address
+}
+
+/// Whether the server closed the connection (instead of the read timing out).
+fn is_closed(result: io::Result<usize>) -> bool {
+    match result {
+        Ok(read) => read == 0,
+        Err(err) => !matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ),
+    }
+}
+
+fn connect(address: SocketAddr) -> TcpStream {
+    let stream = TcpStream::connect(address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    stream
+}
+
+fn connect_tls(address: SocketAddr) -> SslStream<TcpStream> {
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder
+        .build()
+        .connect("localhost", connect(address))
+        .unwrap()
+}
+
+/// Send a frame with the `payload` (the server decodes an empty payload as request).
+fn send_request(stream: &mut SslStream<TcpStream>, payload: &[u8]) {
+    stream
+        .write_all(&(payload.len() as u32).to_le_bytes())
+        .unwrap();
+    stream.write_all(payload).unwrap();
+}
+
+fn read_response(stream: &mut SslStream<TcpStream>) -> ResponseFrame<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len).unwrap();
+    let mut payload = vec![0; u32::from_le_bytes(len) as usize];
+    stream.read_exact(&mut payload).unwrap();
+    postcard::from_bytes(&payload).unwrap()
+}
+
+#[test]
+fn test_connections_without_handshake_release_their_slot() {
+    let mut runtime = Runtime::new().unwrap();
+    let address = start_server(
+        &mut runtime,
+        Limits {
+            max_connections: 1,
+            handshake_timeout: Duration::from_millis(200),
+            ..Limits::default()
+        },
+    );
+
+    // The first connection holds the only slot, the second waits in the backlog.
+    let start = Instant::now();
+    let mut idle = connect(address);
+    let mut waiting = connect(address);
+
+    assert!(is_closed(idle.read(&mut [0; 1])));
+    // The second connection is only handled (and closed) once the slot is released.
+    assert!(is_closed(waiting.read(&mut [0; 1])));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_idle_connections_release_their_slot() {
+    let mut runtime = Runtime::new().unwrap();
+    let address = start_server(
+        &mut runtime,
+        Limits {
+            max_connections: 1,
+            idle_timeout: Duration::from_millis(200),
+            ..Limits::default()
+        },
+    );
+
+    // The handshake is completed, but no request is sent.
+    let mut idle = connect_tls(address);
+    assert!(is_closed(idle.read(&mut [0; 1])));
+
+    // The slot is available for the next client again.
+    let mut next = connect_tls(address);
+    assert!(is_closed(next.read(&mut [0; 1])));
+}
+
+#[test]
+fn test_requests_of_a_busy_host_are_rejected_before_they_are_read() {
+    let mut runtime = Runtime::new().unwrap();
+    let address = start_server(
+        &mut runtime,
+        Limits {
+            max_in_flight_per_host: 1,
+            ..Limits::default()
+        },
+    );
+    let mut first = connect_tls(address);
+    let mut second = connect_tls(address);
+
+    send_request(&mut first, &[]);
+    thread::sleep(HANDLING_TIME / 5);
+
+    // The payload could not be decoded, but it is not even read while the host is busy.
+    send_request(&mut second, &[0xff; 64 * 1024]);
+    let err = read_response(&mut second).into_result().unwrap_err();
+    assert_eq!(err.code, ErrorCode::ServerBusy);
+    assert!(err.retryable);
+
+    assert_eq!(read_response(&mut first), ResponseFrame::Ok(Vec::new()));
+
+    // The skipped payload does not corrupt the next request on the same connection.
+    send_request(&mut second, &[]);
+    assert_eq!(read_response(&mut second), ResponseFrame::Ok(Vec::new()));
+}
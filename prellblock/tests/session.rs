@@ -0,0 +1,50 @@
+use pinxit::{Identity, Signable};
+use prellblock::session::Sessions;
+use prellblock_client_api::OpenSession;
+use std::time::Duration;
+
+#[test]
+fn test_session_token_authenticates_signer() {
+    let sessions = Sessions::default();
+    let identity = Identity::generate();
+
+    let challenge = sessions.challenge().unwrap();
+    let request = OpenSession { challenge }.sign(&identity).unwrap();
+    let token = sessions.open(request).unwrap();
+    assert_eq!(sessions.authenticate(&token).unwrap(), identity.id());
+
+    // The token cannot be transferred to another account ...
+    let mut forged_token = token.clone();
+    forged_token.peer_id = Identity::generate().id().clone();
+    assert!(sessions.authenticate(&forged_token).is_err());
+
+    // ... or extended.
+    let mut forged_token = token.clone();
+    forged_token.expires_at += Duration::from_secs(1);
+    assert!(sessions.authenticate(&forged_token).is_err());
+
+    // Tokens are only valid on the RPU that issued them.
+    assert!(Sessions::default().authenticate(&token).is_err());
+}
+
+#[test]
+fn test_session_requires_challenge_of_rpu() {
+    let sessions = Sessions::default();
+    let identity = Identity::generate();
+
+    let challenge = Sessions::default().challenge().unwrap();
+    let request = OpenSession { challenge }.sign(&identity).unwrap();
+    assert!(sessions.open(request).is_err());
+}
+
+#[test]
+fn test_expired_session_is_rejected() {
+    let sessions = Sessions::new(Duration::from_secs(0));
+    let identity = Identity::generate();
+
+    let challenge = sessions.challenge().unwrap();
+    let request = OpenSession { challenge }.sign(&identity).unwrap();
+    let token = sessions.open(request).unwrap();
+    assert!(token.is_expired());
+    assert!(sessions.authenticate(&token).is_err());
+}
@@ -0,0 +1,74 @@
+use prellblock::consensus::{confirmed_snapshot_manifest, consensus_response::SnapshotManifest};
+use prellblock_client_api::consensus::{
+    Block, BlockHash, BlockNumber, Body, LeaderTerm, SignatureList,
+};
+use std::{net::SocketAddr, time::SystemTime};
+
+fn anchor(height: u64) -> Block {
+    Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: BlockNumber::new(height),
+            prev_block_hash: BlockHash::of(b"genesis"),
+            timestamp: SystemTime::UNIX_EPOCH,
+            transactions: Vec::new(),
+        },
+        signatures: SignatureList::default(),
+    }
+}
+
+fn manifest(anchor: &Block, chunks: &[&[u8]]) -> SnapshotManifest {
+    SnapshotManifest {
+        anchor: anchor.clone(),
+        chunk_hashes: chunks.iter().copied().map(BlockHash::of).collect(),
+    }
+}
+
+fn address(index: u16) -> SocketAddr {
+    ([127, 0, 0, 1], 3131 + index).into()
+}
+
+#[test]
+fn test_tampered_snapshot_manifests_are_rejected() {
+    // Four RPUs tolerate one faulty RPU, so two matching manifests are needed.
+    let nonfaulty_count = 2;
+    let anchor = anchor(99);
+    let honest = manifest(&anchor, &[b"accounts", b"peers"]);
+    let tampered = manifest(&anchor, &[b"accounts", b"forged peers"]);
+
+    // A tampered chunk set of a valid anchor sent by a single RPU is not accepted.
+    let manifests = vec![(address(0), tampered.clone()), (address(1), honest.clone())];
+    assert!(confirmed_snapshot_manifest(manifests, nonfaulty_count).is_none());
+
+    // Sending it twice does not count as a second RPU.
+    let manifests = vec![
+        (address(0), tampered.clone()),
+        (address(0), tampered.clone()),
+    ];
+    assert!(confirmed_snapshot_manifest(manifests, nonfaulty_count).is_none());
+
+    // The honest manifest is chosen and only downloaded from the RPUs that sent it.
+    let manifests = vec![
+        (address(0), tampered),
+        (address(1), honest.clone()),
+        (address(2), honest.clone()),
+    ];
+    let (confirmed, peer_addresses) =
+        confirmed_snapshot_manifest(manifests, nonfaulty_count).unwrap();
+    assert_eq!(confirmed.chunk_hashes, honest.chunk_hashes);
+    assert_eq!(peer_addresses, vec![address(1), address(2)]);
+}
+
+#[test]
+fn test_newest_confirmed_snapshot_is_chosen() {
+    let old = manifest(&anchor(99), &[b"old"]);
+    let new = manifest(&anchor(199), &[b"new"]);
+    let manifests = vec![
+        (address(0), old.clone()),
+        (address(1), new.clone()),
+        (address(2), old),
+        (address(3), new),
+    ];
+    let (confirmed, _) = confirmed_snapshot_manifest(manifests, 2).unwrap();
+    assert_eq!(confirmed.anchor.block_number(), BlockNumber::new(199));
+}
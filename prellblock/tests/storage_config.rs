@@ -0,0 +1,144 @@
+mod common;
+
+use common::TestDir;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable};
+use prellblock::{
+    block_storage::BlockStorage, data_storage::DataStorage, storage::StorageConfig,
+    RpuPrivateConfig,
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    consensus::{Block, BlockNumber, GenesisTransactions, HashAlgorithm, SignatureList},
+    transaction, Transaction,
+};
+use std::time::SystemTime;
+
+fn genesis_transactions() -> GenesisTransactions {
+    let admin = Identity::generate();
+    let create_admin = Transaction::from_variant(transaction::CreateAccount {
+        id: admin.id().clone(),
+        name: "admin".to_string(),
+        permissions: Permissions {
+            account_type: Some(AccountType::Admin),
+            expire_at: Some(Expiry::Never),
+            has_writing_rights: Some(false),
+            reading_rights: Some(Vec::new()),
+        },
+        timestamp: SystemTime::now(),
+    });
+    GenesisTransactions {
+        transactions: vec![create_admin.sign(&admin).unwrap()],
+        timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    }
+}
+
+#[test]
+fn test_storage_config_defaults() {
+    let config: RpuPrivateConfig = toml::from_str(
+        r#"identity = "rpu.key"
+tls_id = "rpu.pfx"
+block_path = "blocks"
+data_path = "data"
+
+[storage]
+cache_capacity = 1_000_000
+compression = true
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.storage.cache_capacity, 1_000_000);
+    assert!(config.storage.compression);
+    // Not given values keep their defaults.
+    assert_eq!(
+        config.storage.flush_interval_ms,
+        StorageConfig::default().flush_interval_ms
+    );
+}
+
+#[test]
+fn test_compressed_block_storage_can_be_reopened() {
+    let dir = TestDir::new("storage-config-blocks");
+    let path = dir.path("blocks");
+    let config = StorageConfig {
+        cache_capacity: 1_000_000,
+        flush_interval_ms: 0,
+        compression: true,
+        compression_factor: 3,
+        block_cache_capacity: 16,
+        persist_buffered_messages: false,
+    };
+
+    let block_storage =
+        BlockStorage::with_config(&path, Some(genesis_transactions()), &config).unwrap();
+    let blocks: Vec<Block> = block_storage.read(..).collect::<Result<_, _>>().unwrap();
+    drop(block_storage);
+
+    let block_storage = BlockStorage::with_config(&path, None, &config).unwrap();
+    let reopened: Vec<Block> = block_storage.read(..).collect::<Result<_, _>>().unwrap();
+    assert_eq!(reopened.len(), blocks.len());
+    assert_eq!(reopened[0].hash(), blocks[0].hash());
+}
+
+#[test]
+fn test_data_storage_with_config() {
+    let dir = TestDir::new("storage-config-data");
+    let path = dir.path("data");
+    let config = StorageConfig {
+        cache_capacity: 1_000_000,
+        ..StorageConfig::default()
+    };
+    DataStorage::with_config(&path, &config).unwrap();
+}
+
+#[test]
+fn test_block_cache() {
+    let dir = TestDir::new("storage-config-block-cache");
+    let path = dir.path("blocks");
+    let config = StorageConfig {
+        block_cache_capacity: 16,
+        ..StorageConfig::default()
+    };
+    let block_storage =
+        BlockStorage::with_config(&path, Some(genesis_transactions()), &config).unwrap();
+    let genesis_block_number = BlockNumber::default();
+
+    // The genesis block was cached when it was written.
+    let genesis_block = block_storage.read_block(genesis_block_number).unwrap();
+    assert!(genesis_block.is_some());
+    assert!(block_storage
+        .read_block(genesis_block_number + 1)
+        .unwrap()
+        .is_none());
+
+    let statistics = block_storage.cache_statistics();
+    assert_eq!(statistics.hits, 1);
+    assert_eq!(statistics.misses, 1);
+    assert_eq!(statistics.len, 1);
+    assert_eq!(statistics.capacity, 16);
+}
+
+#[test]
+fn test_disabled_block_cache() {
+    let dir = TestDir::new("storage-config-no-block-cache");
+    let path = dir.path("blocks");
+    let config = StorageConfig {
+        block_cache_capacity: 0,
+        ..StorageConfig::default()
+    };
+    let block_storage =
+        BlockStorage::with_config(&path, Some(genesis_transactions()), &config).unwrap();
+
+    for _ in 0..2 {
+        assert!(block_storage
+            .read_block(BlockNumber::default())
+            .unwrap()
+            .is_some());
+    }
+    let statistics = block_storage.cache_statistics();
+    assert_eq!(statistics.hits, 0);
+    assert_eq!(statistics.misses, 2);
+    assert_eq!(statistics.len, 0);
+}
@@ -0,0 +1,63 @@
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::world_state::WorldState;
+use prellblock_client_api::{transaction, Transaction};
+use std::time::SystemTime;
+
+fn key_value(writer: &Identity, key: &str) -> Signed<Transaction> {
+    Transaction::from_variant(transaction::KeyValue {
+        key: key.to_string(),
+        value: Vec::new(),
+        timestamp: SystemTime::now(),
+    })
+    .sign(writer)
+    .unwrap()
+}
+
+#[test]
+fn test_keys_can_be_scanned() {
+    let writer = Identity::generate();
+    let other = Identity::generate();
+    let mut world_state = WorldState::default();
+    for key in &[
+        "line3/speed",
+        "line2/speed",
+        "line3/brake",
+        "line30/speed",
+        "line3/speed",
+    ] {
+        world_state.apply_transaction(key_value(&writer, key));
+    }
+    world_state.apply_transaction(key_value(&other, "line3/door"));
+
+    let keys: Vec<_> = world_state
+        .keys_with_prefix(writer.id(), "line3/")
+        .collect();
+    assert_eq!(keys, ["line3/brake", "line3/speed"]);
+
+    let keys: Vec<_> = world_state
+        .scan_range(writer.id(), "line3".to_string().."line4".to_string())
+        .collect();
+    assert_eq!(keys, ["line3/brake", "line3/speed", "line30/speed"]);
+
+    let keys: Vec<_> = world_state.scan_range(other.id(), ..).collect();
+    assert_eq!(keys, ["line3/door"]);
+
+    let unknown = Identity::generate();
+    assert_eq!(world_state.keys_with_prefix(unknown.id(), "").count(), 0);
+}
+
+#[test]
+fn test_scan_uses_a_consistent_snapshot() {
+    let writer = Identity::generate();
+    let mut world_state = WorldState::default();
+    world_state.apply_transaction(key_value(&writer, "a"));
+
+    let snapshot = world_state.clone();
+    let mut keys = snapshot.keys_with_prefix(writer.id(), "");
+    world_state.apply_transaction(key_value(&writer, "b"));
+
+    assert_eq!(keys.next(), Some("a"));
+    assert_eq!(keys.next(), None);
+    assert_eq!(world_state.keys_with_prefix(writer.id(), "").count(), 2);
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::TestDir;
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::{
+    block_storage::BlockStorage,
+    world_state::{FsyncPolicy, PersistenceConfig, WorldState, WorldStateService, LATEST_VERSION},
+};
+use prellblock_client_api::{
+    account::{AccountType, Expiry, Permissions},
+    consensus::{
+        Block, BlockHash, BlockNumber, Body, GenesisTransactions, LeaderTerm, SignatureList,
+    },
+    transaction, Transaction,
+};
+use std::time::SystemTime;
+
+/// A chain with an admin account in its genesis block.
+struct TestChain {
+    admin: Identity,
+    blocks: Vec<Block>,
+}
+
+impl TestChain {
+    fn new() -> Self {
+        let admin = Identity::generate();
+        let create_admin = Transaction::from_variant(transaction::CreateAccount {
+            id: admin.id().clone(),
+            name: "admin".to_string(),
+            permissions: Permissions {
+                account_type: Some(AccountType::Admin),
+                expire_at: Some(Expiry::Never),
+                has_writing_rights: Some(false),
+                reading_rights: Some(Vec::new()),
+            },
+            timestamp: SystemTime::now(),
+        });
+        let mut chain = Self {
+            blocks: Vec::new(),
+            admin,
+        };
+        let create_admin = chain.sign(create_admin);
+        chain.push_block(vec![create_admin]);
+        chain
+    }
+
+    fn sign(&self, transaction: Transaction) -> Signed<Transaction> {
+        transaction.sign(&self.admin).unwrap()
+    }
+
+    fn push_block(&mut self, transactions: Vec<Signed<Transaction>>) {
+        let (height, prev_block_hash) = match self.blocks.last() {
+            Some(block) => (block.block_number() + 1, block.hash()),
+            None => (BlockNumber::default(), BlockHash::default()),
+        };
+        self.blocks.push(Block {
+            body: Body {
+                leader_term: LeaderTerm::default(),
+                height,
+                prev_block_hash,
+                timestamp: SystemTime::now(),
+                transactions,
+            },
+            signatures: SignatureList::default(),
+        });
+    }
+
+    fn schedule_migration(&self, version: u32, activation_height: u64) -> Signed<Transaction> {
+        self.sign(Transaction::from_variant(transaction::ScheduleMigration {
+            version,
+            activation_height: BlockNumber::new(activation_height),
+            timestamp: SystemTime::now(),
+        }))
+    }
+
+    fn genesis_transactions(&self) -> GenesisTransactions {
+        let genesis = &self.blocks[0].body;
+        GenesisTransactions {
+            transactions: genesis.transactions.clone(),
+            timestamp: genesis.timestamp,
+            hash_algorithm: genesis.prev_block_hash.algorithm(),
+            approvals: SignatureList::default(),
+        }
+    }
+
+    /// Write the chain to a new `BlockStorage` at `path`.
+    fn block_storage(&self, path: &str) -> BlockStorage {
+        let block_storage = BlockStorage::new(path, Some(self.genesis_transactions())).unwrap();
+        for block in &self.blocks[1..] {
+            block_storage.write_block(block).unwrap();
+        }
+        block_storage
+    }
+}
+
+#[test]
+fn test_old_chain_keeps_its_version() {
+    let dir = TestDir::new("migration-old");
+    let mut chain = TestChain::new();
+    chain.push_block(Vec::new());
+    chain.push_block(Vec::new());
+
+    let world_state =
+        WorldStateService::from_block_storage(&chain.block_storage(&dir.path("blocks")))
+            .unwrap()
+            .get();
+    assert_eq!(world_state.version, 0);
+    assert_eq!(world_state.scheduled_migration, None);
+}
+
+#[test]
+fn test_migration_is_executed_at_activation_height() {
+    let dir = TestDir::new("migration");
+    let mut chain = TestChain::new();
+    let schedule = chain.schedule_migration(LATEST_VERSION, 3);
+    chain.push_block(vec![schedule]);
+    for _ in 2..5 {
+        chain.push_block(Vec::new());
+    }
+
+    // Apply the blocks one by one.
+    let mut world_state = WorldState::default();
+    for block in &chain.blocks {
+        let expected_version = if block.block_number() < BlockNumber::new(3) {
+            0
+        } else {
+            LATEST_VERSION
+        };
+        world_state.apply_block(block.clone()).unwrap();
+        assert_eq!(world_state.version, expected_version);
+    }
+    assert_eq!(world_state.scheduled_migration, None);
+
+    // Replaying the chain leads to the same state.
+    let block_storage = chain.block_storage(&dir.path("blocks"));
+    let replayed = WorldStateService::from_block_storage(&block_storage)
+        .unwrap()
+        .get();
+    assert_eq!(
+        postcard::to_stdvec(&replayed).unwrap(),
+        postcard::to_stdvec(&world_state).unwrap()
+    );
+
+    // So does loading the persisted state (including the migration in its deltas).
+    let config = PersistenceConfig {
+        path: dir.path("world-state"),
+        compaction_interval: 1000,
+        fsync: FsyncPolicy::default(),
+    };
+    let persisted = WorldStateService::with_persistence(&block_storage, config.clone())
+        .unwrap()
+        .get();
+    assert_eq!(persisted.version, LATEST_VERSION);
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    assert_eq!(
+        postcard::to_stdvec(&reloaded).unwrap(),
+        postcard::to_stdvec(&world_state).unwrap()
+    );
+}
+
+#[test]
+fn test_unsupported_version_stops_at_activation_height() {
+    let mut chain = TestChain::new();
+    let schedule = chain.schedule_migration(LATEST_VERSION + 1, 2);
+    chain.push_block(vec![schedule]);
+    chain.push_block(Vec::new());
+
+    let mut world_state = WorldState::default();
+    world_state.apply_block(chain.blocks[0].clone()).unwrap();
+    world_state.apply_block(chain.blocks[1].clone()).unwrap();
+    assert!(world_state.apply_block(chain.blocks[2].clone()).is_err());
+    assert_eq!(world_state.version, 0);
+}
@@ -0,0 +1,459 @@
+mod common;
+
+use common::{create_account, create_writer, TestDir};
+use newtype_enum::Enum;
+use pinxit::{Identity, Signable, Signed};
+use prellblock::{
+    block_storage::BlockStorage,
+    world_state::{
+        Account, EscrowedKey, FsyncPolicy, PersistenceConfig, ScheduledMigration,
+        ScheduledParameter, WorldState, WorldStateService, LATEST_VERSION,
+    },
+};
+use prellblock_client_api::{
+    account::{AccountType, Permissions},
+    consensus::{
+        Block, BlockHash, BlockNumber, Body, GenesisTransactions, HashAlgorithm, LeaderTerm,
+        SignatureList,
+    },
+    encryption::KeyReference,
+    parameter::Parameter,
+    quota::{Quota, QuotaUsage},
+    schema::Schema,
+    transaction, Transaction,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// Create a `BlockStorage` at `path` with a genesis block and `count` empty blocks.
+fn block_storage(path: &str, admin: &Identity, count: usize) -> BlockStorage {
+    let genesis_transactions = GenesisTransactions {
+        transactions: vec![create_account(admin, admin.id(), AccountType::Admin)],
+        timestamp: SystemTime::now(),
+        hash_algorithm: HashAlgorithm::default(),
+        approvals: SignatureList::default(),
+    };
+    let block_storage = BlockStorage::new(path, Some(genesis_transactions)).unwrap();
+
+    for _ in 0..count {
+        append_block(&block_storage, Vec::new());
+    }
+    block_storage
+}
+
+/// Write a block with the `transactions` on top of the last block.
+fn append_block(block_storage: &BlockStorage, transactions: Vec<Signed<Transaction>>) {
+    let last_block: Block = block_storage.read(..).next_back().unwrap().unwrap();
+    let block = Block {
+        body: Body {
+            leader_term: LeaderTerm::default(),
+            height: last_block.block_number() + 1,
+            prev_block_hash: last_block.hash(),
+            timestamp: SystemTime::now(),
+            transactions,
+        },
+        signatures: SignatureList::default(),
+    };
+    block_storage.write_block(&block).unwrap();
+}
+
+fn persistence_config(dir: &TestDir) -> PersistenceConfig {
+    PersistenceConfig {
+        path: dir.path("world-state"),
+        compaction_interval: 1000,
+        fsync: FsyncPolicy::default(),
+    }
+}
+
+fn block_number(block_storage: &BlockStorage) -> BlockNumber {
+    block_storage
+        .read(..)
+        .next_back()
+        .unwrap()
+        .unwrap()
+        .block_number()
+        + 1
+}
+
+/// Fill every field of the `world_state` with values depending on the `round`.
+///
+/// All fields are listed, so a new field of the `WorldState` has to be added here (and to the persisted deltas).
+fn populate(world_state: &WorldState, admin: &Identity, round: u8) -> WorldState {
+    let peer_id = admin.id().clone();
+    let mut account = Account::new(format!("account {}", round));
+    account.writing_rights = true;
+    let namespace = format!("plant{}.", round);
+    let scheduled = BlockNumber::new(100 + u64::from(round));
+    // Shared values are removed again in odd rounds.
+    let shared_values = if round % 2 == 0 {
+        vec![(namespace.clone(), vec![round])]
+    } else {
+        Vec::new()
+    };
+
+    WorldState {
+        accounts: world_state
+            .accounts
+            .update(peer_id.clone(), Arc::new(account)),
+        peers: vec![(
+            peer_id.clone(),
+            ([127, 0, 0, 1], 3000 + u16::from(round)).into(),
+        )]
+        .into_iter()
+        .collect(),
+        schemas: vec![(namespace.clone(), Schema::U8)].into_iter().collect(),
+        block_number: world_state.block_number,
+        last_block_hash: world_state.last_block_hash,
+        version: LATEST_VERSION,
+        scheduled_migration: Some(ScheduledMigration {
+            version: LATEST_VERSION + 1,
+            activation_height: scheduled,
+        }),
+        parameters: vec![(Parameter::MaxTransactionSize, u64::from(round))]
+            .into_iter()
+            .collect(),
+        scheduled_parameters: vec![(
+            Parameter::CensorshipTimeout,
+            ScheduledParameter {
+                value: u64::from(round),
+                activation_height: scheduled,
+            },
+        )]
+        .into_iter()
+        .collect(),
+        leader_weights: vec![(peer_id.clone(), u32::from(round))]
+            .into_iter()
+            .collect(),
+        escrowed_keys: vec![(
+            KeyReference {
+                namespace: namespace.clone(),
+                version: u32::from(round),
+            },
+            EscrowedKey {
+                key_hash: BlockHash::of(&[round]),
+                threshold: 1,
+                share_hashes: vec![(peer_id.clone(), BlockHash::of(&[round, 1]))],
+                auditors: vec![(
+                    peer_id.clone(),
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(u64::from(round)),
+                )],
+            },
+        )]
+        .into_iter()
+        .collect(),
+        last_leader_term: LeaderTerm::new(u64::from(round)),
+        queue_weights: vec![(peer_id.clone(), u32::from(round))]
+            .into_iter()
+            .collect(),
+        keys: world_state.keys.update(
+            peer_id,
+            world_state
+                .keys
+                .get(admin.id())
+                .cloned()
+                .unwrap_or_default()
+                .update(format!("{}a", namespace)),
+        ),
+        quotas: vec![(
+            namespace.clone(),
+            Quota {
+                max_transactions: u64::from(round),
+                max_bytes: 1_000_000,
+                window_ms: 60_000,
+            },
+        )]
+        .into_iter()
+        .collect(),
+        quota_usage: vec![(
+            namespace,
+            QuotaUsage {
+                window: u64::from(round),
+                transactions: 1,
+                bytes: 1,
+            },
+        )]
+        .into_iter()
+        .collect(),
+        shared_values: shared_values.into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_persisted_world_state_ahead_of_block_storage() {
+    let dir = TestDir::new("persistence-ahead");
+    let block_storage = block_storage(&dir.path("blocks"), &Identity::generate(), 5);
+    let config = PersistenceConfig {
+        path: dir.path("world-state"),
+        compaction_interval: 2,
+        fsync: FsyncPolicy::default(),
+    };
+    let persisted = WorldStateService::with_persistence(&block_storage, config.clone())
+        .unwrap()
+        .get();
+    assert_eq!(persisted.block_number, block_number(&block_storage));
+
+    // The last blocks were lost (e.g. not flushed before a crash).
+    block_storage.pop_block().unwrap().unwrap();
+    block_storage.pop_block().unwrap().unwrap();
+
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    let replayed = WorldStateService::from_block_storage(&block_storage)
+        .unwrap()
+        .get();
+    assert_eq!(reloaded.block_number, block_number(&block_storage));
+    assert_eq!(
+        reloaded.state_root().unwrap(),
+        replayed.state_root().unwrap()
+    );
+}
+
+#[test]
+fn test_persisted_world_state_is_reused() {
+    let dir = TestDir::new("persistence-reuse");
+    let block_storage = block_storage(&dir.path("blocks"), &Identity::generate(), 3);
+    let config = PersistenceConfig {
+        path: dir.path("world-state"),
+        compaction_interval: 2,
+        fsync: FsyncPolicy::default(),
+    };
+    let persisted = WorldStateService::with_persistence(&block_storage, config.clone())
+        .unwrap()
+        .get();
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    assert_eq!(reloaded.block_number, persisted.block_number);
+    assert_eq!(
+        reloaded.state_root().unwrap(),
+        persisted.state_root().unwrap()
+    );
+}
+
+#[test]
+fn test_persisted_quotas_are_restored() {
+    let dir = TestDir::new("persistence-quotas");
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let block_storage = block_storage(&dir.path("blocks"), &admin, 0);
+    let config = persistence_config(&dir);
+    WorldStateService::with_persistence(&block_storage, config.clone()).unwrap();
+
+    let set_quota = Transaction::from_variant(transaction::SetQuota {
+        namespace: "plant1.".to_string(),
+        quota: Some(Quota {
+            max_transactions: 10,
+            max_bytes: 1_000_000,
+            window_ms: 3_600_000,
+        }),
+        timestamp: SystemTime::now(),
+    });
+    let key_value = Transaction::from_variant(transaction::KeyValue {
+        key: "plant1.temperature".to_string(),
+        value: vec![42],
+        timestamp: SystemTime::now(),
+    });
+    append_block(
+        &block_storage,
+        vec![
+            create_writer(&admin, writer.id(), AccountType::Normal),
+            set_quota.sign(&admin).unwrap(),
+        ],
+    );
+    append_block(&block_storage, vec![key_value.sign(&writer).unwrap()]);
+
+    // The new blocks are applied and persisted as deltas.
+    let persisted = WorldStateService::with_persistence(&block_storage, config.clone())
+        .unwrap()
+        .get();
+    assert!(persisted.quotas.contains_key("plant1."));
+    assert_eq!(persisted.quota_usage["plant1."].transactions, 1);
+
+    // The deltas are loaded instead of replaying the blocks.
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    assert_eq!(reloaded.quotas, persisted.quotas);
+    assert_eq!(reloaded.quota_usage, persisted.quota_usage);
+    assert_eq!(
+        reloaded.state_root().unwrap(),
+        persisted.state_root().unwrap()
+    );
+}
+
+#[test]
+fn test_persisted_shared_values_are_restored() {
+    let dir = TestDir::new("persistence-shared-values");
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let block_storage = block_storage(&dir.path("blocks"), &admin, 0);
+    let config = persistence_config(&dir);
+    WorldStateService::with_persistence(&block_storage, config.clone()).unwrap();
+
+    let compare_and_swap = |key: &str, expected: Option<u8>, new: Option<u8>| {
+        Transaction::from_variant(transaction::CompareAndSwap {
+            key: key.to_string(),
+            expected: expected.map(|value| vec![value]),
+            new: new.map(|value| vec![value]),
+            timestamp: SystemTime::now(),
+        })
+        .sign(&writer)
+        .unwrap()
+    };
+    append_block(
+        &block_storage,
+        vec![
+            create_writer(&admin, writer.id(), AccountType::Normal),
+            compare_and_swap("lock", None, Some(1)),
+            compare_and_swap("leader", None, Some(1)),
+        ],
+    );
+    append_block(
+        &block_storage,
+        vec![
+            compare_and_swap("lock", Some(1), None),
+            compare_and_swap("leader", Some(1), Some(2)),
+        ],
+    );
+
+    let persisted = WorldStateService::with_persistence(&block_storage, config.clone())
+        .unwrap()
+        .get();
+    assert!(!persisted.shared_values.contains_key("lock"));
+    assert_eq!(persisted.shared_values["leader"], vec![2]);
+
+    // A restart restores the shared values from the deltas.
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    assert_eq!(reloaded.shared_values, persisted.shared_values);
+    assert_eq!(
+        reloaded.state_root().unwrap(),
+        persisted.state_root().unwrap()
+    );
+}
+
+#[test]
+fn test_world_state_round_trip_with_transactions() {
+    let dir = TestDir::new("persistence-round-trip");
+    let admin = Identity::generate();
+    let writer = Identity::generate();
+    let reader = Identity::generate();
+    let block_storage = block_storage(&dir.path("blocks"), &admin, 0);
+    // Compact after every second block, so a snapshot and deltas are loaded.
+    let config = PersistenceConfig {
+        compaction_interval: 2,
+        ..persistence_config(&dir)
+    };
+    WorldStateService::with_persistence(&block_storage, config.clone()).unwrap();
+
+    let key_value = |key: &str, value: u8| {
+        Transaction::from_variant(transaction::KeyValue {
+            key: key.to_string(),
+            value: vec![value],
+            timestamp: SystemTime::now(),
+        })
+        .sign(&writer)
+        .unwrap()
+    };
+    let update_reader = Transaction::from_variant(transaction::UpdateAccount {
+        id: reader.id().clone(),
+        permissions: Permissions {
+            account_type: None,
+            expire_at: None,
+            has_writing_rights: Some(true),
+            reading_rights: None,
+        },
+        timestamp: SystemTime::now(),
+    });
+    let set_parameter = Transaction::from_variant(transaction::SetParameter {
+        parameter: Parameter::MaxTransactionSize,
+        value: 4096,
+        activation_height: BlockNumber::new(100),
+        timestamp: SystemTime::now(),
+    });
+    let delete_reader = Transaction::from_variant(transaction::DeleteAccount {
+        id: reader.id().clone(),
+        timestamp: SystemTime::now(),
+    });
+    append_block(
+        &block_storage,
+        vec![
+            create_writer(&admin, writer.id(), AccountType::Normal),
+            create_account(&admin, reader.id(), AccountType::Normal),
+        ],
+    );
+    append_block(
+        &block_storage,
+        vec![key_value("plant1.a", 1), key_value("plant1.b", 2)],
+    );
+    append_block(
+        &block_storage,
+        vec![
+            update_reader.sign(&admin).unwrap(),
+            set_parameter.sign(&admin).unwrap(),
+            key_value("plant1.a", 3),
+        ],
+    );
+    append_block(&block_storage, vec![delete_reader.sign(&admin).unwrap()]);
+
+    let persisted = WorldStateService::with_persistence(&block_storage, config.clone())
+        .unwrap()
+        .get();
+    assert_eq!(persisted.block_number, block_number(&block_storage));
+    assert!(!persisted.accounts.contains_key(reader.id()));
+    assert_eq!(persisted.keys[writer.id()].len(), 2);
+    assert!(persisted
+        .scheduled_parameters
+        .contains_key(&Parameter::MaxTransactionSize));
+
+    // The reloaded state equals the state replayed from the blocks.
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    let replayed = WorldStateService::from_block_storage(&block_storage)
+        .unwrap()
+        .get();
+    assert_eq!(
+        reloaded.state_root().unwrap(),
+        persisted.state_root().unwrap()
+    );
+    assert_eq!(
+        reloaded.state_root().unwrap(),
+        replayed.state_root().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_persisted_world_state_equals_the_original() {
+    let dir = TestDir::new("persistence-equality");
+    let admin = Identity::generate();
+    let block_storage = block_storage(&dir.path("blocks"), &admin, 0);
+    // Compact after every second block, so a snapshot and deltas are loaded.
+    let config = PersistenceConfig {
+        compaction_interval: 2,
+        ..persistence_config(&dir)
+    };
+    let world_state = WorldStateService::with_persistence(&block_storage, config.clone()).unwrap();
+
+    for round in 1..=5 {
+        append_block(&block_storage, Vec::new());
+        // The block is not applied, the state only has to match it.
+        let block = block_storage.read(..).next_back().unwrap().unwrap();
+        let mut writable = world_state.get_writable().await;
+        writable.block_number = block.block_number() + 1;
+        writable.last_block_hash = block.body.hash();
+        *writable = populate(&writable, &admin, round);
+        writable.save();
+    }
+    let original = world_state.get();
+    drop(world_state);
+
+    let reloaded = WorldStateService::with_persistence(&block_storage, config)
+        .unwrap()
+        .get();
+    assert_eq!(reloaded, original);
+}